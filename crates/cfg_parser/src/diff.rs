@@ -0,0 +1,184 @@
+//! Structural differences between two versions of a text config.
+//!
+//! Entries are matched by key rather than by line, so moving, reindenting or reformatting
+//! entries isn't reported, and each change names the full path of the entry it affects.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::text::ConfigEntry;
+use crate::text::keyed;
+
+/// One difference between two versions of a text config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigChange {
+    /// An entry only found in the new version.
+    Added { path: Vec<String>, entry: String },
+    /// An entry only found in the old version.
+    Removed { path: Vec<String>, entry: String },
+    /// A property's values changed.
+    Changed { path: Vec<String>, old: String, new: String },
+    /// A block's ID or the parent it inherits from changed.
+    ClassChanged { path: Vec<String>, old: String, new: String },
+}
+
+impl ConfigChange {
+    /// The path to the changed entry, from the top level down.
+    pub fn path(&self) -> &[String] {
+        match self {
+            ConfigChange::Added { path, .. }
+            | ConfigChange::Removed { path, .. }
+            | ConfigChange::Changed { path, .. }
+            | ConfigChange::ClassChanged { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path().join(" > ");
+        match self {
+            ConfigChange::Added { entry, .. } => write!(f, "+ {path}: {entry}"),
+            ConfigChange::Removed { entry, .. } => write!(f, "- {path}: {entry}"),
+            ConfigChange::Changed { old, new, .. } => write!(f, "~ {path}: {old} -> {new}"),
+            ConfigChange::ClassChanged { old, new, .. } => {
+                write!(f, "~ {path} (class): {old} -> {new}")
+            }
+        }
+    }
+}
+
+/// The changes going from the entries in `old` to those in `new`. Removed and changed
+/// entries come first, in `old`'s order, followed by added ones in `new`'s order.
+pub fn diff_entries(old: &[ConfigEntry], new: &[ConfigEntry]) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    diff_into(&mut changes, &mut Vec::new(), old, new);
+    changes
+}
+
+fn diff_into(
+    changes: &mut Vec<ConfigChange>,
+    path: &mut Vec<String>,
+    old: &[ConfigEntry],
+    new: &[ConfigEntry],
+) {
+    let old = keyed(old);
+    let new = keyed(new);
+    let new_by_key: HashMap<&str, &ConfigEntry> =
+        new.iter().map(|(key, entry)| (key.as_str(), *entry)).collect();
+    let old_keys: HashSet<&str> = old.iter().map(|(key, _)| key.as_str()).collect();
+
+    for (key, old_entry) in &old {
+        path.push(key.clone());
+        match new_by_key.get(key.as_str()) {
+            None => changes
+                .push(ConfigChange::Removed { path: path.clone(), entry: summary(old_entry) }),
+            Some(new_entry) => diff_entry(changes, path, old_entry, new_entry),
+        }
+        path.pop();
+    }
+
+    for (key, new_entry) in &new {
+        if !old_keys.contains(key.as_str()) {
+            let mut path = path.clone();
+            path.push(key.clone());
+            changes.push(ConfigChange::Added { path, entry: summary(new_entry) });
+        }
+    }
+}
+
+fn diff_entry(
+    changes: &mut Vec<ConfigChange>,
+    path: &mut Vec<String>,
+    old: &ConfigEntry,
+    new: &ConfigEntry,
+) {
+    match (&old.children, &new.children) {
+        (Some(old_children), Some(new_children)) => {
+            if old.values != new.values {
+                changes.push(ConfigChange::ClassChanged {
+                    path: path.clone(),
+                    old: old.values.join(" "),
+                    new: new.values.join(" "),
+                });
+            }
+            diff_into(changes, path, old_children, new_children);
+        }
+        (None, None) => {
+            if old.values != new.values {
+                changes.push(ConfigChange::Changed {
+                    path: path.clone(),
+                    old: old.values.join(" "),
+                    new: new.values.join(" "),
+                });
+            }
+        }
+        // A property became a block or the other way around
+        _ => {
+            changes.push(ConfigChange::Removed { path: path.clone(), entry: summary(old) });
+            changes.push(ConfigChange::Added { path: path.clone(), entry: summary(new) });
+        }
+    }
+}
+
+/// How an added or removed entry is described.
+fn summary(entry: &ConfigEntry) -> String {
+    let values = entry.values.join(" ");
+    match &entry.children {
+        Some(children) => {
+            let plural = if children.len() == 1 { "entry" } else { "entries" };
+            format!("{values} {{ {} {plural} }}", children.len()).trim_start().to_string()
+        }
+        None => values,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::parse;
+
+    fn changes(old: &str, new: &str) -> Vec<String> {
+        let old = parse(old).unwrap();
+        let new = parse(new).unwrap();
+        diff_entries(&old, &new).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn reports_changed_keys() {
+        let old = r#"Weapon : "{1}Base.et" {
+ m_Damage 10
+ m_Range 100
+ m_Muzzles {
+  SCR_Muzzle "{A}" { m_Rate 600 }
+  SCR_Muzzle "{B}" { m_Rate 300 }
+ }
+}"#;
+        // Minified, with the muzzles reordered
+        let new = "Weapon : \"{2}Other.et\" { m_Damage 12 m_Muzzles { \
+                   SCR_Muzzle \"{B}\" { m_Rate 350 } SCR_Muzzle \"{A}\" { m_Rate 600 } } \
+                   m_Weight 4.5 }";
+
+        assert_eq!(
+            changes(old, new),
+            [
+                r#"~ Weapon (class): : "{1}Base.et" -> : "{2}Other.et""#,
+                "~ Weapon > m_Damage: 10 -> 12",
+                "- Weapon > m_Range: 100",
+                r#"~ Weapon > m_Muzzles > SCR_Muzzle "{B}" > m_Rate: 300 -> 350"#,
+                "+ Weapon > m_Weight: 4.5",
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_repeated_keys() {
+        let old = "Root { Item { m_A 1 } Item { m_A 2 } }";
+        let new = "Root { Item { m_A 1 } Item { m_A 3 } Item { } }";
+        assert_eq!(
+            changes(old, new),
+            ["~ Root > Item #2 > m_A: 2 -> 3", "+ Root > Item #3: { 0 entries }"]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Splitting text configs into tokens.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token<'a> {
+    Open,
+    Close,
+    Semicolon,
+    Comma,
+    Colon,
+    Equals,
+    /// A quoted string, including its quotes.
+    Str(&'a str),
+    /// An unquoted name or number.
+    Word(&'a str),
+    /// A `//` comment or preprocessor directive, which runs to the end of its line.
+    Line(&'a str),
+    BlockComment(&'a str),
+}
+
+/// Whether an unquoted word is a name (as opposed to a number).
+pub(crate) fn is_name(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+pub(crate) fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    // Whether `pos` is the first non-blank character of its line
+    let mut line_start = true;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let token = match bytes[pos] {
+            b'\n' => {
+                pos += 1;
+                line_start = true;
+                continue;
+            }
+            b if b.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'{' => Token::Open,
+            b'}' => Token::Close,
+            b';' => Token::Semicolon,
+            b',' => Token::Comma,
+            b':' => Token::Colon,
+            b'=' => Token::Equals,
+            b'"' => {
+                pos += 1;
+                loop {
+                    match text[pos..].find('"') {
+                        // `""` is an escaped quote in config.cpp strings
+                        Some(end) if bytes.get(pos + end + 1) == Some(&b'"') => pos += end + 2,
+                        Some(end) => {
+                            pos += end + 1;
+                            break;
+                        }
+                        None => {
+                            pos = bytes.len();
+                            break;
+                        }
+                    }
+                }
+                line_start = false;
+                tokens.push(Token::Str(&text[start..pos]));
+                continue;
+            }
+            b'#' if line_start => {
+                pos = text[pos..].find('\n').map_or(bytes.len(), |end| pos + end);
+                tokens.push(Token::Line(text[start..pos].trim_end()));
+                continue;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                pos = text[pos..].find('\n').map_or(bytes.len(), |end| pos + end);
+                tokens.push(Token::Line(text[start..pos].trim_end()));
+                continue;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                pos = text[pos + 2..].find("*/").map_or(bytes.len(), |end| pos + 2 + end + 2);
+                line_start = false;
+                tokens.push(Token::BlockComment(&text[start..pos]));
+                continue;
+            }
+            _ => {
+                while pos < bytes.len() && !ends_word(bytes, pos) {
+                    pos += 1;
+                }
+                line_start = false;
+                tokens.push(Token::Word(&text[start..pos]));
+                continue;
+            }
+        };
+
+        pos += 1;
+        line_start = false;
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// Whether the byte at `pos` can't be part of an unquoted word.
+fn ends_word(bytes: &[u8], pos: usize) -> bool {
+    match bytes[pos] {
+        b'{' | b'}' | b';' | b',' | b':' | b'=' | b'"' => true,
+        b'/' => matches!(bytes.get(pos + 1), Some(b'/' | b'*')),
+        b => b.is_ascii_whitespace(),
+    }
+}
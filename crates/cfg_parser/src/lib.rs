@@ -5,6 +5,13 @@ use byteorder::LittleEndian;
 use byteorder::ReadBytesExt;
 use thiserror::Error;
 
+pub mod diff;
+mod lexer;
+pub mod pretty;
+pub mod table;
+pub mod text;
+pub mod units;
+
 #[derive(Debug, Error)]
 pub enum RapError {
     #[error("invalid magic: expected \\0raP header")]
@@ -0,0 +1,191 @@
+//! Reformatting text configs that were written on a single line.
+//!
+//! Works on tokens instead of a syntax tree, so the same printer handles Enfusion text
+//! configs (`.conf`, `.et`, `.layout`, ...) and `config.cpp`-style classes, and anything it
+//! doesn't recognize is copied through rather than rejected.
+
+use crate::lexer::Token;
+use crate::lexer::is_name;
+use crate::lexer::tokenize;
+
+/// Lines at least this long which open a block are taken as a sign of minification.
+const MINIFIED_LINE_LEN: usize = 400;
+
+/// Whether `text` looks like a config squeezed onto very long lines.
+pub fn is_minified(text: &str) -> bool {
+    text.lines().any(|line| line.len() >= MINIFIED_LINE_LEN && line.contains('{'))
+}
+
+/// Reformats a text config with one entry per line and tab-indented blocks.
+///
+/// Entries are split where one ends and the next begins: after `;`, around blocks, and
+/// before a name that follows a value (`m_Count 3 m_Name "x"`). Array literals assigned with
+/// `=` stay on one line.
+pub fn pretty_print(text: &str) -> String {
+    let tokens = tokenize(text);
+    let mut printer = Printer::default();
+    let mut inline_depth = 0usize;
+    let mut prev = None;
+
+    for (idx, &token) in tokens.iter().enumerate() {
+        let after_inline_open = inline_depth > 0 && prev == Some(Token::Open);
+        match token {
+            Token::Open if inline_depth > 0 || prev == Some(Token::Equals) => {
+                printer.push("{", !after_inline_open);
+                inline_depth += 1;
+            }
+            Token::Open => {
+                printer.push("{", true);
+                printer.indent += 1;
+                printer.newline();
+            }
+            Token::Close if inline_depth > 0 => {
+                printer.push("}", false);
+                inline_depth -= 1;
+            }
+            Token::Close => {
+                printer.newline();
+                printer.indent = printer.indent.saturating_sub(1);
+                printer.push("}", true);
+                if tokens.get(idx + 1) != Some(&Token::Semicolon) {
+                    printer.newline();
+                }
+            }
+            Token::Semicolon => {
+                printer.push(";", false);
+                if inline_depth == 0 {
+                    printer.newline();
+                }
+            }
+            Token::Comma => printer.push(",", false),
+            Token::Colon => printer.push(":", true),
+            Token::Equals => printer.push("=", true),
+            Token::Str(s) => {
+                printer.push(s, !after_inline_open);
+                printer.line_has_value = true;
+            }
+            Token::Word(word) => {
+                let is_name = is_name(word);
+                let starts_entry = is_name
+                    && inline_depth == 0
+                    && !matches!(prev, Some(Token::Colon | Token::Equals))
+                    && (printer.line_has_value || printer.line_names >= 2);
+                if starts_entry {
+                    printer.newline();
+                }
+
+                printer.push(word, !after_inline_open);
+                if is_name {
+                    printer.line_names += 1;
+                } else {
+                    printer.line_has_value = true;
+                }
+            }
+            Token::Line(line) => {
+                if line.starts_with('#') {
+                    printer.newline();
+                }
+                printer.push(line, true);
+                printer.newline();
+            }
+            Token::BlockComment(comment) => printer.push(comment, !after_inline_open),
+        }
+        prev = Some(token);
+    }
+
+    printer.newline();
+    printer.out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    indent: usize,
+    /// Whether anything has been written to the current line.
+    line_started: bool,
+    line_has_value: bool,
+    line_names: usize,
+}
+
+impl Printer {
+    fn push(&mut self, text: &str, space_before: bool) {
+        if !self.line_started {
+            self.out.extend(std::iter::repeat_n('\t', self.indent));
+            self.line_started = true;
+        } else if space_before {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        if self.line_started {
+            self.out.push('\n');
+        }
+        self.line_started = false;
+        self.line_has_value = false;
+        self.line_names = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_enfusion_entries() {
+        let minified = r#"SCR_Config : "{1234}Configs/Base.conf" { m_Name "Test" m_Enabled 1 m_Position 1 2.5 -3 m_Items { SCR_Item "{ABCD}" { m_Count 2 } SCR_Other { } } }"#;
+        let expected = "\
+SCR_Config : \"{1234}Configs/Base.conf\" {
+\tm_Name \"Test\"
+\tm_Enabled 1
+\tm_Position 1 2.5 -3
+\tm_Items {
+\t\tSCR_Item \"{ABCD}\" {
+\t\t\tm_Count 2
+\t\t}
+\t\tSCR_Other {
+\t\t}
+\t}
+}
+";
+        assert_eq!(pretty_print(minified), expected);
+    }
+
+    #[test]
+    fn keeps_array_literals_inline() {
+        let minified = r#"#include "base.hpp"
+class CfgPatches{class Test:Base{units[]={};weapons[]={{"a","b""c"},{1,2}};};};"#;
+        let expected = "\
+#include \"base.hpp\"
+class CfgPatches {
+\tclass Test : Base {
+\t\tunits[] = {};
+\t\tweapons[] = {{\"a\", \"b\"\"c\"}, {1, 2}};
+\t};
+};
+";
+        assert_eq!(pretty_print(minified), expected);
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let minified = "Root { // the root\nm_Value 1 /* inline */ m_Other 2 }";
+        let expected = "\
+Root {
+\t// the root
+\tm_Value 1 /* inline */
+\tm_Other 2
+}
+";
+        assert_eq!(pretty_print(minified), expected);
+    }
+
+    #[test]
+    fn detects_minified_text() {
+        let entry = "m_Value 1 ";
+        let minified = format!("Root {{ {}}}", entry.repeat(50));
+        assert!(is_minified(&minified));
+        assert!(!is_minified(&pretty_print(&minified)));
+    }
+}
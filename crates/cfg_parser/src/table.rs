@@ -0,0 +1,244 @@
+//! Collecting repeated records in a text config, such as a list of weapon or vehicle
+//! definitions, into tables with a column per property.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::text::ConfigEntry;
+
+/// Blocks of the same class found side by side need at least this many members to be
+/// shown as a table.
+const MIN_RECORDS: usize = 2;
+
+/// Blocks of one class from the same parent, with a row per block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordTable {
+    /// Path to the blocks from the top level down, ending with their class.
+    pub path: Vec<String>,
+    /// `ID` followed by property names in the order they're first seen. Properties of
+    /// nested blocks are named `block.property`.
+    pub columns: Vec<String>,
+    /// A cell per column. Properties a record doesn't set are left empty.
+    pub rows: Vec<Vec<String>>,
+}
+
+impl RecordTable {
+    /// Sorts the rows by `column`, comparing cells as numbers where both are. Empty cells
+    /// always sort last.
+    pub fn sort_by_column(&mut self, column: usize, descending: bool) {
+        self.rows.sort_by(|a, b| {
+            let (a, b) = (&a[column], &b[column]);
+            match (a.is_empty(), b.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) if descending => compare_cells(a, b).reverse(),
+                (false, false) => compare_cells(a, b),
+            }
+        });
+    }
+
+    /// The table as CSV, with the column names as the first row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for row in std::iter::once(&self.columns).chain(&self.rows) {
+            let cells: Vec<String> = row.iter().map(|cell| csv_cell(cell)).collect();
+            csv.push_str(&cells.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Finds every group of at least two same-class blocks sharing a parent, at any depth.
+pub fn find_tables(entries: &[ConfigEntry]) -> Vec<RecordTable> {
+    let mut tables = Vec::new();
+    collect_tables(&mut tables, &mut Vec::new(), entries);
+    tables
+}
+
+fn collect_tables(tables: &mut Vec<RecordTable>, path: &mut Vec<String>, entries: &[ConfigEntry]) {
+    let mut classes: Vec<(&str, Vec<&ConfigEntry>)> = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.children.is_some()) {
+        match classes.iter_mut().find(|(class, _)| *class == entry.key) {
+            Some((_, records)) => records.push(entry),
+            None => classes.push((&entry.key, vec![entry])),
+        }
+    }
+
+    for (class, records) in classes {
+        if records.len() >= MIN_RECORDS {
+            path.push(if class.is_empty() { "{ }".to_string() } else { class.to_string() });
+            tables.push(record_table(path.clone(), &records));
+            path.pop();
+        }
+    }
+
+    // Records can hold repeated blocks of their own
+    for (idx, entry) in entries.iter().enumerate() {
+        let Some(children) = &entry.children else {
+            continue;
+        };
+        path.push(match (entry.key.as_str(), entry.id()) {
+            ("", _) => format!("[{idx}]"),
+            (key, Some(id)) => format!("{key} {id}"),
+            (key, None) => key.to_string(),
+        });
+        collect_tables(tables, path, children);
+        path.pop();
+    }
+}
+
+fn record_table(path: Vec<String>, records: &[&ConfigEntry]) -> RecordTable {
+    let mut columns = vec!["ID".to_string()];
+    let mut rows = Vec::with_capacity(records.len());
+    for (idx, record) in records.iter().enumerate() {
+        let id = match record.id() {
+            Some(id) => unquote(id).to_string(),
+            None => format!("#{}", idx + 1),
+        };
+
+        let mut cells = Vec::new();
+        if let Some(parent) = record.parent() {
+            cells.push(("Parent".to_string(), unquote(parent).to_string()));
+        }
+        flatten(&mut cells, "", record.children.as_deref().unwrap_or_default());
+
+        let mut row = vec![id];
+        row.resize(columns.len(), String::new());
+        for (column, value) in cells {
+            let idx = match columns.iter().position(|existing| *existing == column) {
+                Some(idx) => idx,
+                None => {
+                    columns.push(column);
+                    row.push(String::new());
+                    columns.len() - 1
+                }
+            };
+            row[idx] = value;
+        }
+        rows.push(row);
+    }
+
+    for row in &mut rows {
+        row.resize(columns.len(), String::new());
+    }
+    RecordTable { path, columns, rows }
+}
+
+/// Adds a `(column, value)` pair for every property in `entries`, prefixing nested blocks'
+/// properties with the block's name. Repeated names are numbered, as in
+/// [`keyed`](crate::text::keyed).
+fn flatten(cells: &mut Vec<(String, String)>, prefix: &str, entries: &[ConfigEntry]) {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        let count = seen.entry(&entry.key).or_default();
+        *count += 1;
+        let name = match *count {
+            1 => format!("{prefix}{}", entry.key),
+            count => format!("{prefix}{} #{count}", entry.key),
+        };
+
+        match &entry.children {
+            // A list of bare values, such as `m_Tags { "a" "b" }`
+            Some(children) if children.iter().all(is_bare_value) => {
+                let values: Vec<&str> = children.iter().map(|child| unquote(&child.key)).collect();
+                cells.push((name, values.join(" ")));
+            }
+            Some(children) => flatten(cells, &format!("{name}."), children),
+            None => {
+                let value = match &entry.values[..] {
+                    [value] => unquote(value).to_string(),
+                    values => values.join(" "),
+                };
+                cells.push((name, value));
+            }
+        }
+    }
+}
+
+fn is_bare_value(entry: &ConfigEntry) -> bool {
+    entry.children.is_none() && entry.values.is_empty()
+}
+
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value)
+}
+
+/// Numbers sort before text, and numerically among themselves.
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+fn csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::parse;
+
+    const WEAPONS: &str = r#"
+SCR_WeaponList {
+ m_Weapons {
+  SCR_Weapon "{A1}" : "{00}Base.et" {
+   m_Name "M16A2"
+   m_Damage 40
+   m_Sight { m_Zoom 1.5 }
+   m_Tags { "rifle" "nato" }
+  }
+  SCR_Weapon "{B2}" {
+   m_Name "AK-74, late"
+   m_Damage 9.5
+   m_Weight 3.3
+  }
+  SCR_Weapon "{C3}" {
+   m_Name "PM"
+  }
+  SCR_Ammo { m_Caliber 5.56 }
+ }
+}"#;
+
+    #[test]
+    fn collects_repeated_blocks() {
+        let tables = find_tables(&parse(WEAPONS).unwrap());
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert_eq!(table.path, ["SCR_WeaponList", "m_Weapons", "SCR_Weapon"]);
+        assert_eq!(
+            table.columns,
+            ["ID", "Parent", "m_Name", "m_Damage", "m_Sight.m_Zoom", "m_Tags", "m_Weight"]
+        );
+        assert_eq!(table.rows[0], ["{A1}", "{00}Base.et", "M16A2", "40", "1.5", "rifle nato", ""]);
+        assert_eq!(table.rows[2], ["{C3}", "", "PM", "", "", "", ""]);
+    }
+
+    #[test]
+    fn sorts_and_exports_rows() {
+        let mut table = find_tables(&parse(WEAPONS).unwrap()).remove(0);
+        table.sort_by_column(3, false);
+        let ids: Vec<&str> = table.rows.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(ids, ["{B2}", "{A1}", "{C3}"]);
+
+        table.sort_by_column(3, true);
+        table.columns.truncate(4);
+        table.rows.iter_mut().for_each(|row| row.truncate(4));
+        assert_eq!(
+            table.to_csv(),
+            "ID,Parent,m_Name,m_Damage\n\
+             {A1},{00}Base.et,M16A2,40\n\
+             {B2},,\"AK-74, late\",9.5\n\
+             {C3},,PM,\n"
+        );
+    }
+}
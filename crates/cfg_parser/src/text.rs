@@ -0,0 +1,234 @@
+//! Parsing Enfusion text configs (`.conf`, `.et`, `.layout`, ...) into a tree of entries.
+//!
+//! The format has no separators between entries: a property is a name followed by its
+//! values (`m_Position 1 2 3`), and a block is a header followed by braces
+//! (`SCR_Item "{ID}" : "{ID}Prefabs/Base.et" { ... }`). An entry ends where a name follows
+//! one of its values, the same rule [`pretty_print`](crate::pretty::pretty_print) splits
+//! lines on.
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+
+use thiserror::Error;
+
+use crate::lexer::Token;
+use crate::lexer::is_name;
+use crate::lexer::tokenize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TextConfigError {
+    #[error("`}}` without a matching `{{`")]
+    UnexpectedClose,
+    #[error("block {0:?} is never closed")]
+    UnclosedBlock(String),
+}
+
+/// A property, a block of nested entries, or a bare value in a list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    /// The property name, or a block's class. Bare values are stored here too, with their
+    /// quotes if they're strings.
+    pub key: String,
+    /// Tokens after the key: a property's values, or a block's ID and `: "parent"`.
+    pub values: Vec<String>,
+    /// The nested entries, if this is a block.
+    pub children: Option<Vec<ConfigEntry>>,
+}
+
+impl ConfigEntry {
+    /// The entry's quoted ID, if it's a block which has one.
+    pub fn id(&self) -> Option<&str> {
+        self.children.as_ref()?;
+        self.values.first().filter(|value| value.starts_with('"')).map(String::as_str)
+    }
+
+    /// The parent a block inherits from, after its `:`.
+    pub fn parent(&self) -> Option<&str> {
+        let colon = self.values.iter().position(|value| value == ":")?;
+        self.values.get(colon + 1).map(String::as_str)
+    }
+}
+
+/// Parses the entries of a text config. Comments are dropped.
+pub fn parse(text: &str) -> Result<Vec<ConfigEntry>, TextConfigError> {
+    let tokens = tokenize(text);
+    let mut tokens = tokens
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Line(_) | Token::BlockComment(_)))
+        .peekable();
+
+    parse_entries(&mut tokens, None)
+}
+
+/// Pairs entries with keys which tell them apart: blocks by their ID, and entries which
+/// still share a key are numbered in order (`Item`, `Item #2`, ...).
+pub fn keyed(entries: &[ConfigEntry]) -> Vec<(String, &ConfigEntry)> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    entries
+        .iter()
+        .map(|entry| {
+            let key = match entry.id() {
+                Some(id) => format!("{} {id}", entry.key),
+                None => entry.key.clone(),
+            };
+            let count = seen.entry(key.clone()).or_default();
+            *count += 1;
+            let key = if *count > 1 { format!("{key} #{count}") } else { key };
+            (key, entry)
+        })
+        .collect()
+}
+
+/// The entry at `path`, a list of keys from [`keyed`] from the top level down.
+pub fn find_entry<'a>(entries: &'a [ConfigEntry], path: &[String]) -> Option<&'a ConfigEntry> {
+    let (first, rest) = path.split_first()?;
+    let (_, entry) = keyed(entries).into_iter().find(|(key, _)| key == first)?;
+    if rest.is_empty() { Some(entry) } else { find_entry(entry.children.as_deref()?, rest) }
+}
+
+/// Parses entries until the end of `block`, or of the input for the top level.
+fn parse_entries<'a>(
+    tokens: &mut Peekable<impl Iterator<Item = Token<'a>>>,
+    block: Option<&str>,
+) -> Result<Vec<ConfigEntry>, TextConfigError> {
+    let mut entries = Vec::new();
+    loop {
+        let key = match tokens.next() {
+            None => match block {
+                Some(key) => return Err(TextConfigError::UnclosedBlock(key.to_string())),
+                None => return Ok(entries),
+            },
+            Some(Token::Close) if block.is_some() => return Ok(entries),
+            Some(Token::Close) => return Err(TextConfigError::UnexpectedClose),
+            // A block without a header, such as a vector in a list of vectors
+            Some(Token::Open) => {
+                let children = parse_entries(tokens, Some(""))?;
+                entries.push(ConfigEntry {
+                    key: String::new(),
+                    values: Vec::new(),
+                    children: Some(children),
+                });
+                continue;
+            }
+            Some(Token::Word(key)) => key,
+            Some(Token::Str(value)) => {
+                entries.push(ConfigEntry {
+                    key: value.to_string(),
+                    values: Vec::new(),
+                    children: None,
+                });
+                continue;
+            }
+            Some(_) => continue,
+        };
+
+        let mut entry = ConfigEntry { key: key.to_string(), values: Vec::new(), children: None };
+        if !is_name(key) {
+            entries.push(entry);
+            continue;
+        }
+
+        let mut names = 1;
+        let mut has_value = false;
+        let mut after_colon = false;
+        while let Some(&token) = tokens.peek() {
+            match token {
+                Token::Open => {
+                    tokens.next();
+                    entry.children = Some(parse_entries(tokens, Some(key))?);
+                    break;
+                }
+                Token::Word(word) if is_name(word) && !after_colon && (has_value || names >= 2) => {
+                    break;
+                }
+                Token::Word(value) | Token::Str(value) => {
+                    tokens.next();
+                    entry.values.push(value.to_string());
+                    if matches!(token, Token::Word(word) if is_name(word)) {
+                        names += 1;
+                    } else {
+                        has_value = true;
+                    }
+                    after_colon = false;
+                }
+                Token::Colon => {
+                    tokens.next();
+                    entry.values.push(":".to_string());
+                    after_colon = true;
+                }
+                Token::Semicolon => {
+                    tokens.next();
+                    break;
+                }
+                Token::Close => break,
+                _ => {
+                    tokens.next();
+                }
+            }
+        }
+        entries.push(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn property(key: &str, values: &[&str]) -> ConfigEntry {
+        let values = values.iter().map(|value| value.to_string()).collect();
+        ConfigEntry { key: key.to_string(), values, children: None }
+    }
+
+    #[test]
+    fn parses_properties_and_blocks() {
+        let text = r#"
+Vehicle : "{1234}Prefabs/Base.et" {
+ ID "ABCD"
+ // comment
+ m_Position 1 2.5 -3 m_Flag true
+ m_Tags { "a" "b" }
+ components {
+  SCR_Item "{EF01}" {
+   m_Count 2
+  }
+ }
+}"#;
+        let entries = parse(text).unwrap();
+        assert_eq!(entries.len(), 1);
+        let vehicle = &entries[0];
+        assert_eq!(vehicle.key, "Vehicle");
+        assert_eq!(vehicle.parent(), Some("\"{1234}Prefabs/Base.et\""));
+        assert_eq!(vehicle.id(), None);
+
+        let children = vehicle.children.as_ref().unwrap();
+        assert_eq!(children[0], property("ID", &["\"ABCD\""]));
+        assert_eq!(children[1], property("m_Position", &["1", "2.5", "-3"]));
+        assert_eq!(children[2], property("m_Flag", &["true"]));
+
+        let tags = children[3].children.as_ref().unwrap();
+        assert_eq!(tags, &[property("\"a\"", &[]), property("\"b\"", &[])]);
+
+        let item = &children[4].children.as_ref().unwrap()[0];
+        assert_eq!(item.id(), Some("\"{EF01}\""));
+        assert_eq!(item.children.as_deref(), Some(&[property("m_Count", &["2"])][..]));
+    }
+
+    #[test]
+    fn finds_entries_by_path() {
+        let entries =
+            parse(r#"Root { Item "{A}" { m_A 1 } Item { m_A 2 } Item { m_A 3 } }"#).unwrap();
+        let path = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        let find = |keys: &[&str]| find_entry(&entries, &path(keys)).map(|e| e.values.clone());
+
+        assert_eq!(find(&["Root", "Item \"{A}\"", "m_A"]), Some(vec!["1".to_string()]));
+        assert_eq!(find(&["Root", "Item #2", "m_A"]), Some(vec!["3".to_string()]));
+        assert_eq!(find(&["Root", "Item #3", "m_A"]), None);
+        assert_eq!(find(&["Root", "m_A"]), None);
+    }
+
+    #[test]
+    fn reports_unbalanced_braces() {
+        assert_eq!(parse("Root { m_Value 1"), Err(TextConfigError::UnclosedBlock("Root".into())));
+        assert_eq!(parse("m_Value 1 }"), Err(TextConfigError::UnexpectedClose));
+    }
+}
@@ -0,0 +1,239 @@
+//! Recognizing what text config values measure, so they can be shown with their units.
+//!
+//! Configs store bare numbers: an angle, a delay and a color are all written as floats.
+//! Which unit a property is in is guessed from its name using [`ValueRules`], read from a
+//! rules file in the format of [`DEFAULT_RULES`].
+
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// The rules used when no other rules file is given. The comments at the top describe the
+/// format.
+pub const DEFAULT_RULES: &str = include_str!("../value_rules.txt");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Degrees,
+    Radians,
+    Seconds,
+    Milliseconds,
+    /// Red, green, blue and optionally alpha, either from 0 to 1 or from 0 to 255.
+    Color,
+}
+
+impl Unit {
+    /// The unit values are converted to, and what to multiply them by.
+    fn counterpart(self) -> Option<(Unit, f64)> {
+        match self {
+            Unit::Degrees => Some((Unit::Radians, PI / 180.0)),
+            Unit::Radians => Some((Unit::Degrees, 180.0 / PI)),
+            Unit::Seconds => Some((Unit::Milliseconds, 1000.0)),
+            Unit::Milliseconds => Some((Unit::Seconds, 0.001)),
+            Unit::Color => None,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Unit::Degrees => "°",
+            Unit::Radians => " rad",
+            Unit::Seconds => " s",
+            Unit::Milliseconds => " ms",
+            Unit::Color => "",
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "degrees" => Ok(Unit::Degrees),
+            "radians" => Ok(Unit::Radians),
+            "seconds" => Ok(Unit::Seconds),
+            "milliseconds" => Ok(Unit::Milliseconds),
+            "color" => Ok(Unit::Color),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RulesError {
+    #[error("line {line}: expected a pattern followed by a unit")]
+    Malformed { line: usize },
+    #[error("line {line}: unknown unit {unit:?}")]
+    UnknownUnit { line: usize, unit: String },
+}
+
+/// Property name patterns and the units their values are in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueRules {
+    rules: Vec<(String, Unit)>,
+}
+
+impl ValueRules {
+    /// Reads a rules file: one `pattern unit` pair per line, with `#` starting a comment line.
+    pub fn parse(text: &str) -> Result<Self, RulesError> {
+        let mut rules = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [pattern, unit] = parts[..] else {
+                return Err(RulesError::Malformed { line: idx + 1 });
+            };
+            let unit = unit
+                .parse()
+                .map_err(|_| RulesError::UnknownUnit { line: idx + 1, unit: unit.to_string() })?;
+            rules.push((pattern.to_string(), unit));
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// The unit of the property called `name`, from the first rule matching it.
+    pub fn unit_for(&self, name: &str) -> Option<Unit> {
+        self.rules.iter().find(|(pattern, _)| wildcard_match(pattern, name)).map(|(_, unit)| *unit)
+    }
+}
+
+impl Default for ValueRules {
+    fn default() -> Self {
+        Self::parse(DEFAULT_RULES).expect("default value rules are valid")
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = name.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`, so the whole name has to match
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// A property's values, shown in their unit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderedValue {
+    /// Numbers followed by their unit, such as `90° 45°`.
+    Text(String),
+    /// Red, green, blue and alpha from 0 to 1.
+    Color([f32; 4]),
+}
+
+/// Shows `values` as measured in `unit`. With `convert`, angles and durations are shown in
+/// their other unit: degrees as radians, seconds as milliseconds, and the other way around.
+///
+/// Returns `None` if the values aren't all numbers, or aren't three or four color channels.
+pub fn render(unit: Unit, values: &[String], convert: bool) -> Option<RenderedValue> {
+    // Vectors are sometimes written as a single string, such as `"1 0.5 0"`
+    let numbers: Vec<&str> =
+        values.iter().flat_map(|value| value.trim_matches('"').split_whitespace()).collect();
+    let parsed: Vec<f64> = numbers.iter().map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    if parsed.is_empty() {
+        return None;
+    }
+
+    if unit == Unit::Color {
+        let scale = if parsed.iter().any(|&channel| channel > 1.0) { 255.0 } else { 1.0 };
+        let rgba = match parsed[..] {
+            [r, g, b] => [r, g, b, scale],
+            [r, g, b, a] => [r, g, b, a],
+            _ => return None,
+        };
+        return Some(RenderedValue::Color(
+            rgba.map(|channel| (channel / scale).clamp(0.0, 1.0) as f32),
+        ));
+    }
+
+    let shown: Vec<String> = match unit.counterpart() {
+        Some((other, factor)) if convert => parsed
+            .iter()
+            .map(|n| format!("{}{}", format_number(n * factor), other.suffix()))
+            .collect(),
+        _ => numbers.iter().map(|n| format!("{n}{}", unit.suffix())).collect(),
+    };
+    Some(RenderedValue::Text(shown.join(" ")))
+}
+
+/// Up to four decimal places, without trailing zeros.
+fn format_number(n: f64) -> String {
+    let formatted = format!("{n:.4}");
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    if trimmed == "-0" { "0".to_string() } else { trimmed.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn matches_property_names() {
+        let rules = ValueRules::default();
+        assert_eq!(rules.unit_for("m_iReloadDelayMs"), Some(Unit::Milliseconds));
+        assert_eq!(rules.unit_for("m_fReloadTime"), Some(Unit::Seconds));
+        assert_eq!(rules.unit_for("m_vAngles"), Some(Unit::Degrees));
+        assert_eq!(rules.unit_for("m_BackgroundColor"), Some(Unit::Color));
+        assert_eq!(rules.unit_for("m_aItems"), None);
+        assert_eq!(rules.unit_for("m_TimeScale"), None);
+
+        let rules = ValueRules::parse("# custom\nm_Exact seconds\n\nm_*Spin degrees").unwrap();
+        assert_eq!(rules.unit_for("m_Exact"), Some(Unit::Seconds));
+        assert_eq!(rules.unit_for("m_ExactMatch"), None);
+        assert_eq!(rules.unit_for("m_WheelSpin"), Some(Unit::Degrees));
+
+        assert_eq!(ValueRules::parse("m_A\n"), Err(RulesError::Malformed { line: 1 }));
+        assert_eq!(
+            ValueRules::parse("\nm_A furlongs"),
+            Err(RulesError::UnknownUnit { line: 2, unit: "furlongs".into() })
+        );
+    }
+
+    #[test]
+    fn renders_and_converts_values() {
+        let text = |unit, vals: &[&str], convert| match render(unit, &values(vals), convert) {
+            Some(RenderedValue::Text(text)) => text,
+            other => panic!("expected text, got {other:?}"),
+        };
+        assert_eq!(text(Unit::Degrees, &["0", "90", "-45.5"], false), "0° 90° -45.5°");
+        assert_eq!(text(Unit::Degrees, &["180"], true), "3.1416 rad");
+        assert_eq!(text(Unit::Milliseconds, &["1500"], true), "1.5 s");
+        assert_eq!(text(Unit::Seconds, &["\"0.25\""], true), "250 ms");
+
+        assert_eq!(
+            render(Unit::Color, &values(&["1", "0.5", "0"]), false),
+            Some(RenderedValue::Color([1.0, 0.5, 0.0, 1.0]))
+        );
+        assert_eq!(
+            render(Unit::Color, &values(&["\"255 0 51 0\""]), false),
+            Some(RenderedValue::Color([1.0, 0.0, 0.2, 0.0]))
+        );
+
+        assert_eq!(render(Unit::Color, &values(&["1", "0"]), false), None);
+        assert_eq!(render(Unit::Seconds, &values(&["\"{ABCD}Prefab.et\""]), false), None);
+        assert_eq!(render(Unit::Seconds, &[], false), None);
+    }
+}
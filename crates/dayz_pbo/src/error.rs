@@ -9,4 +9,7 @@ pub enum PboError {
 
     #[error("Parser error")]
     ParserError(ContextError<StrContext>),
+
+    #[error("failed to decompress entry: {0}")]
+    Decompression(String),
 }
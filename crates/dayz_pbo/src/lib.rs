@@ -3,6 +3,7 @@ pub use parser::*;
 #[cfg(feature = "async_vfs")]
 pub mod async_pbo_vfs;
 pub mod error;
+pub mod lzss;
 mod parser;
 #[cfg(feature = "vfs")]
 pub mod pbo_vfs;
@@ -0,0 +1,97 @@
+//! Decompression of `Cprs` entries, which use Bohemia's LZSS variant.
+//!
+//! Data is a series of blocks, each a flag byte followed by up to eight items. A set flag
+//! bit (least significant first) means the item is a literal byte; a clear bit means a
+//! two-byte back-reference: a 12-bit distance back from the end of the output and a 4-bit
+//! length (plus 3). References before the start of the output produce spaces. The
+//! compressed data is followed by a 4-byte checksum: the wrapping sum of the output bytes.
+
+use crate::error::PboError;
+
+/// Decompresses an entry's data into `original_size` bytes and verifies its checksum.
+pub fn decompress(data: &[u8], original_size: usize) -> Result<Vec<u8>, PboError> {
+    let truncated = || PboError::Decompression("compressed data is truncated".to_string());
+
+    let mut out = Vec::with_capacity(original_size);
+    let mut input = data.iter().copied();
+    while out.len() < original_size {
+        let mut flags = input.next().ok_or_else(truncated)?;
+        for _ in 0..8 {
+            if out.len() >= original_size {
+                break;
+            }
+
+            if flags & 1 != 0 {
+                out.push(input.next().ok_or_else(truncated)?);
+            } else {
+                let low = input.next().ok_or_else(truncated)? as usize;
+                let high = input.next().ok_or_else(truncated)? as usize;
+                let distance = low | ((high & 0xF0) << 4);
+                let len = (high & 0x0F) + 3;
+                if distance == 0 {
+                    return Err(PboError::Decompression(format!(
+                        "back-reference at output offset {} has a distance of 0",
+                        out.len()
+                    )));
+                }
+
+                let start = out.len() as isize - distance as isize;
+                for pos in start..start + len as isize {
+                    if out.len() >= original_size {
+                        break;
+                    }
+
+                    // Copy byte by byte since a reference may overlap its own output
+                    let byte = if pos < 0 { b' ' } else { out[pos as usize] };
+                    out.push(byte);
+                }
+            }
+            flags >>= 1;
+        }
+    }
+
+    let checksum_bytes: Vec<u8> = input.take(4).collect();
+    let expected = <[u8; 4]>::try_from(checksum_bytes).map_err(|_| truncated())?;
+    let expected = u32::from_le_bytes(expected);
+    let actual = out.iter().fold(0u32, |sum, byte| sum.wrapping_add(*byte as u32));
+    if actual != expected {
+        return Err(PboError::Decompression(format!(
+            "checksum mismatch: expected {expected:#010X}, got {actual:#010X}"
+        )));
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_literals_and_references() {
+        let expected = b"abcabcabcX";
+        let checksum = expected.iter().map(|b| *b as u32).sum::<u32>();
+
+        // Flags 0b1111_0111: three literals, a reference, then literals
+        let mut data = vec![0b1111_0111, b'a', b'b', b'c'];
+        // Distance 3, length 6 (encoded as 3)
+        data.extend([3, 0x03]);
+        data.push(b'X');
+        data.extend(checksum.to_le_bytes());
+
+        assert_eq!(decompress(&data, expected.len()).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_zero_distance_references() {
+        // A literal, then a reference to the current end of the output
+        let data = [0b0000_0001, b'a', 0, 0x00, 0, 0, 0, 0];
+        assert!(matches!(decompress(&data, 4), Err(PboError::Decompression(_))));
+    }
+
+    #[test]
+    fn rejects_bad_checksums() {
+        let data = [0xFF, b'a', 0, 0, 0, 0];
+        assert!(matches!(decompress(&data, 1), Err(PboError::Decompression(_))));
+    }
+}
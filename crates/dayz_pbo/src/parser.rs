@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::ops::Range;
 
@@ -18,7 +19,8 @@ use winnow::token::take_while;
 pub enum PackingMethod {
     /// Uncompressed file data.
     Uncompressed,
-    /// LZH-compressed file data (packing method "Cprs" / 0x43707273).
+    /// LZSS-compressed file data (packing method "Cprs" / 0x43707273), used by Arma
+    /// titles. See [`crate::lzss`].
     Compressed,
     /// Version/product header extension entry (packing method "Vers" / 0x56657273).
     /// The entry itself carries no file data; it is followed by key-value
@@ -57,6 +59,14 @@ pub struct HeaderEntry {
     pub data_size: u32,
 }
 
+impl HeaderEntry {
+    /// Whether the entry's data must be decompressed. Some tools mark entries as
+    /// compressed without actually compressing them, leaving both sizes equal.
+    pub fn is_compressed(&self) -> bool {
+        self.packing_method == PackingMethod::Compressed && self.original_size != self.data_size
+    }
+}
+
 /// Parsed representation of a PBO archive.
 #[derive(Debug, Clone)]
 pub struct PboFile {
@@ -325,6 +335,21 @@ impl PboFile {
         let range = self.entry_data_range_by_index(index);
         &data[range]
     }
+
+    /// Turns the stored data of the entry at `index` into the file's contents,
+    /// decompressing it if needed.
+    pub fn decode_entry<'a>(
+        &self,
+        index: usize,
+        stored: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, PboError> {
+        let entry = &self.entries[index];
+        if entry.is_compressed() {
+            crate::lzss::decompress(stored, entry.original_size as usize).map(Cow::Owned)
+        } else {
+            Ok(Cow::Borrowed(stored))
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -9,8 +9,10 @@ use fskit::Metadata;
 use fskit::ReadOnlyVfs;
 use fskit::VfsTree;
 use fskit::VfsTreeBuilder;
+use vfs::VfsError;
 use vfs::VfsMetadata;
 use vfs::VfsResult;
+use vfs::error::VfsErrorKind;
 
 use crate::PboFile;
 
@@ -121,6 +123,22 @@ impl<T> PboOpener<T> {
     fn make_reader(&self, meta: &PboFileMeta) -> ArcSliceReader<T> {
         ArcSliceReader { source: Arc::clone(&self.source), range: self.data_range(meta), pos: 0 }
     }
+
+    fn is_compressed(&self, meta: &PboFileMeta) -> bool {
+        self.pbo.entries[meta.entry_index].is_compressed()
+    }
+
+    /// Decompresses a compressed entry's data into memory.
+    fn decompress(&self, meta: &PboFileMeta) -> VfsResult<Vec<u8>>
+    where
+        T: AsRef<[u8]>,
+    {
+        let stored = &(*self.source).as_ref()[self.data_range(meta)];
+        self.pbo
+            .decode_entry(meta.entry_index, stored)
+            .map(|data| data.into_owned())
+            .map_err(|e| VfsError::from(VfsErrorKind::Other(e.to_string())))
+    }
 }
 
 impl<T> fskit::FileOpener<PboFileMeta> for PboOpener<T>
@@ -128,6 +146,10 @@ where
     T: AsRef<[u8]> + Debug + Send + Sync + 'static,
 {
     fn open(&self, meta: &PboFileMeta) -> vfs::VfsResult<Box<dyn vfs::SeekAndRead + Send>> {
+        if self.is_compressed(meta) {
+            return Ok(Box::new(std::io::Cursor::new(self.decompress(meta)?)));
+        }
+
         Ok(Box::new(self.make_reader(meta)))
     }
 }
@@ -167,6 +189,10 @@ const _: () = {
             &self,
             meta: &PboFileMeta,
         ) -> vfs::VfsResult<Box<dyn vfs::async_vfs::SeekAndRead + Send + Unpin>> {
+            if self.is_compressed(meta) {
+                return Ok(Box::new(futures::io::Cursor::new(self.decompress(meta)?)));
+            }
+
             Ok(Box::new(AsyncSliceReader(self.make_reader(meta))))
         }
     }
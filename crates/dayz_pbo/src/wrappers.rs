@@ -161,9 +161,13 @@ mod async_impl {
             let fskit::VfsEntry::File(meta) = entry else {
                 return Err(VfsError::from(VfsErrorKind::Other("not a file".into())));
             };
-            let range = self.inner.pbo_file.entry_data_range_by_index(meta.entry_index);
+            let pbo_file = &self.inner.pbo_file;
+            let range = pbo_file.entry_data_range_by_index(meta.entry_index);
             let data = self.inner.prime_file(range).await?;
-            Ok(Box::new(AsyncCursor::new(data.0.as_ref().to_vec())))
+            let contents = pbo_file
+                .decode_entry(meta.entry_index, &data.0)
+                .map_err(|e| VfsError::from(VfsErrorKind::Other(e.to_string())))?;
+            Ok(Box::new(AsyncCursor::new(contents.into_owned())))
         }
 
         async fn metadata(&self, path: &str) -> VfsResult<VfsMetadata> {
@@ -0,0 +1,272 @@
+//! Writing many files out of the workspace at once, and checking previously written files
+//! against it.
+//!
+//! Files are extracted by a fixed number of worker threads, each streaming one file at a
+//! time to disk. Archive readers decompress a whole file up front, so workers also reserve
+//! each file's size from a shared budget before opening it, which caps how much file data is
+//! in memory no matter how many workers there are.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+
+use sha2::Digest;
+use sha2::Sha256;
+use vfs::VfsPath;
+
+/// How closely a file already on disk has to match the workspace's copy to count as
+/// up to date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Check {
+    /// Same length.
+    Size,
+    /// Same length and SHA-256. Reads every file on both sides.
+    Hash,
+}
+
+pub struct Options {
+    /// Number of files extracted at once.
+    pub jobs: NonZeroUsize,
+    /// Most decompressed bytes held in memory across all workers. A file larger than this
+    /// is still extracted, but only once nothing else is in flight.
+    pub memory_budget: u64,
+    /// Skip files already on disk which pass this check.
+    pub resume: Option<Check>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            jobs: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            memory_budget: 1024 * 1024 * 1024,
+            resume: None,
+        }
+    }
+}
+
+/// A file to write.
+pub struct Job<'a> {
+    /// Path inside the workspace.
+    pub path: &'a str,
+    /// `path` relative to the output directory, for reporting.
+    pub relative: PathBuf,
+    pub dest: PathBuf,
+}
+
+pub enum Outcome {
+    Written,
+    /// The file on disk already passed the resume check.
+    Skipped,
+    Failed(String),
+}
+
+/// How a file on disk compares to the workspace's copy.
+pub enum Verified {
+    UpToDate,
+    Missing,
+    Changed,
+    Failed(String),
+}
+
+/// Extracts every job, calling `report` with each outcome in the same order as `jobs`
+/// regardless of which worker finishes first.
+pub fn extract_all(
+    root: &VfsPath,
+    jobs: &[Job<'_>],
+    options: &Options,
+    report: impl FnMut(&Job<'_>, Outcome),
+) {
+    let budget = Budget::new(options.memory_budget);
+    let extract = |job: &Job<'_>| {
+        extract_file(root, job, options.resume, &budget).unwrap_or_else(Outcome::Failed)
+    };
+    run_ordered(jobs, options.jobs, extract, report);
+}
+
+/// Compares each job's destination with the workspace, calling `report` in the same order
+/// as `jobs`. `options.resume` is ignored in favor of `check`.
+pub fn verify_all(
+    root: &VfsPath,
+    jobs: &[Job<'_>],
+    options: &Options,
+    check: Check,
+    report: impl FnMut(&Job<'_>, Verified),
+) {
+    let budget = Budget::new(options.memory_budget);
+    let verify =
+        |job: &Job<'_>| verify_file(root, job, check, &budget).unwrap_or_else(Verified::Failed);
+    run_ordered(jobs, options.jobs, verify, report);
+}
+
+/// Runs `work` on every job from up to `workers` threads, calling `report` with the results
+/// in the order of `jobs`.
+fn run_ordered<T: Send>(
+    jobs: &[Job<'_>],
+    workers: NonZeroUsize,
+    work: impl Fn(&Job<'_>) -> T + Sync,
+    mut report: impl FnMut(&Job<'_>, T),
+) {
+    let next_job = AtomicUsize::new(0);
+    let workers = workers.get().min(jobs.len());
+    let (tx, rx) = mpsc::sync_channel(workers);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (work, next_job) = (&work, &next_job);
+            scope.spawn(move || {
+                loop {
+                    let idx = next_job.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = jobs.get(idx) else {
+                        break;
+                    };
+                    if tx.send((idx, work(job))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut finished = BTreeMap::new();
+        let mut next_report = 0;
+        for (idx, result) in rx {
+            finished.insert(idx, result);
+            while let Some(result) = finished.remove(&next_report) {
+                report(&jobs[next_report], result);
+                next_report += 1;
+            }
+        }
+    });
+}
+
+fn extract_file(
+    root: &VfsPath,
+    job: &Job<'_>,
+    resume: Option<Check>,
+    budget: &Budget,
+) -> Result<Outcome, String> {
+    let file = root.join(job.path).map_err(|e| e.to_string())?;
+    let len = file.metadata().map_err(|e| e.to_string())?.len;
+    let _reservation = budget.reserve(len);
+
+    if let Some(check) = resume
+        && matches_on_disk(&file, len, &job.dest, check).map_err(|e| e.to_string())?
+    {
+        return Ok(Outcome::Skipped);
+    }
+
+    let mut reader = file.open_file().map_err(|e| e.to_string())?;
+    if let Some(parent) = job.dest.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    // Written under a temporary name so an interrupted run never leaves a truncated file
+    // behind that looks complete
+    let partial = partial_path(&job.dest);
+    let written = std::fs::File::create(&partial)
+        .and_then(|mut out| io::copy(&mut reader, &mut out))
+        .and_then(|_| std::fs::rename(&partial, &job.dest));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.to_string());
+    }
+
+    Ok(Outcome::Written)
+}
+
+fn verify_file(
+    root: &VfsPath,
+    job: &Job<'_>,
+    check: Check,
+    budget: &Budget,
+) -> Result<Verified, String> {
+    let file = root.join(job.path).map_err(|e| e.to_string())?;
+    let len = file.metadata().map_err(|e| e.to_string())?.len;
+    if !job.dest.exists() {
+        return Ok(Verified::Missing);
+    }
+
+    // Only hashing reads the workspace's copy
+    let _reservation = (check == Check::Hash).then(|| budget.reserve(len));
+    let up_to_date = matches_on_disk(&file, len, &job.dest, check).map_err(|e| e.to_string())?;
+    Ok(if up_to_date { Verified::UpToDate } else { Verified::Changed })
+}
+
+/// Whether `dest` exists and matches `file`, which is `len` bytes long, according to `check`.
+pub fn matches_on_disk(file: &VfsPath, len: u64, dest: &Path, check: Check) -> io::Result<bool> {
+    let on_disk = match std::fs::metadata(dest) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    if !on_disk.is_file() || on_disk.len() != len {
+        return Ok(false);
+    }
+
+    match check {
+        Check::Size => Ok(true),
+        Check::Hash => {
+            let expected = sha256(&mut file.open_file().map_err(io::Error::other)?)?;
+            Ok(sha256(&mut std::fs::File::open(dest)?)? == expected)
+        }
+    }
+}
+
+pub fn sha256(reader: &mut impl io::Read) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// Bytes of file data workers may hold at once.
+struct Budget {
+    capacity: u64,
+    available: Mutex<u64>,
+    released: Condvar,
+}
+
+impl Budget {
+    fn new(capacity: u64) -> Self {
+        Budget { capacity, available: Mutex::new(capacity), released: Condvar::new() }
+    }
+
+    /// Blocks until `len` bytes are free. Files larger than the whole budget wait for it to
+    /// be entirely free instead.
+    fn reserve(&self, len: u64) -> Reservation<'_> {
+        let len = len.min(self.capacity);
+        let available = self.available.lock().expect("budget lock poisoned");
+        let mut available = self
+            .released
+            .wait_while(available, |available| *available < len)
+            .expect("budget lock poisoned");
+        *available -= len;
+        Reservation { budget: self, len }
+    }
+}
+
+/// Returns its bytes to the budget when dropped.
+struct Reservation<'a> {
+    budget: &'a Budget,
+    len: u64,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        *self.budget.available.lock().expect("budget lock poisoned") += self.len;
+        self.budget.released.notify_all();
+    }
+}
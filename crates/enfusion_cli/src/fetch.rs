@@ -0,0 +1,87 @@
+//! Downloading archives from a URL and checking them against a published hash.
+
+use std::fmt;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use sha2::Digest;
+use sha2::Sha256;
+
+/// A verified download.
+pub struct Download {
+    pub path: PathBuf,
+    /// Size in bytes.
+    pub len: u64,
+}
+
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The file downloaded, but its contents aren't what the caller expected.
+    HashMismatch { url: String, expected: String, actual: String },
+    /// The file couldn't be downloaded or saved.
+    Failed(String),
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::HashMismatch { url, expected, actual } => {
+                write!(f, "SHA-256 mismatch for {url}: expected {expected}, got {actual}")
+            }
+            DownloadError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Downloads `url` into `dir`, keeping the file name from the URL so the archive type can
+/// be detected. The file is deleted again if its SHA-256 doesn't match `expected_sha256`.
+pub fn download(url: &str, expected_sha256: &str, dir: &Path) -> Result<Download, DownloadError> {
+    let failed = DownloadError::Failed;
+    let expected = expected_sha256.trim().to_ascii_lowercase();
+    if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(failed(format!("\"{expected_sha256}\" is not a SHA-256 hash")));
+    }
+
+    let name = file_name(url).ok_or_else(|| failed(format!("no file name in URL: {url}")))?;
+    std::fs::create_dir_all(dir)
+        .map_err(|e| failed(format!("cannot create {}: {e}", dir.display())))?;
+    let dest = dir.join(name);
+
+    let mut response =
+        ureq::get(url).call().map_err(|e| failed(format!("download failed: {e}")))?;
+    let mut body = response.body_mut().as_reader();
+    let mut file = std::fs::File::create(&dest)
+        .map_err(|e| failed(format!("cannot create {}: {e}", dest.display())))?;
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut downloaded = 0u64;
+    loop {
+        let read = body.read(&mut buf).map_err(|e| failed(format!("download failed: {e}")))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        file.write_all(&buf[..read])
+            .map_err(|e| failed(format!("cannot write {}: {e}", dest.display())))?;
+        downloaded += read as u64;
+    }
+    drop(file);
+
+    let actual: String = hasher.finalize().iter().map(|b| format!("{b:02x}")).collect();
+    if actual != expected {
+        let _ = std::fs::remove_file(&dest);
+        return Err(DownloadError::HashMismatch { url: url.to_string(), expected, actual });
+    }
+
+    Ok(Download { path: dest, len: downloaded })
+}
+
+/// The last path segment of `url`, without any query or fragment.
+fn file_name(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let (_, name) = path.rsplit_once('/')?;
+    (!name.is_empty()).then_some(name)
+}
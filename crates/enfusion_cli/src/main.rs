@@ -2,27 +2,108 @@ use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::ffi::OsStr;
 use std::io::Read;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::ops::ControlFlow;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::Parser;
 use clap::Subcommand;
+use enfusion_pak::diff::ChangeKind;
+use enfusion_pak::entry_type::EntryType;
+use enfusion_pak::entry_type::detect_type;
+use enfusion_pak::entry_type::text_extensions;
+use enfusion_pak::error::SignatureError;
+use enfusion_pak::extract::dest_path;
+use enfusion_pak::extract::long_path;
+use enfusion_pak::extract::relative_path;
+use enfusion_pak::ignore_vfs::IGNORE_FILE_NAME;
+use enfusion_pak::ignore_vfs::IgnoreRules;
+use enfusion_pak::signing;
+use enfusion_pak::writer::WriteOptions;
+use enfusion_search::Searcher;
 use globset::Glob;
 use globset::GlobMatcher;
 use vfs::MemoryFS;
 use vfs::OverlayFS;
 use vfs::VfsPath;
 
+mod extract;
+mod fetch;
+mod patch;
+mod serve;
+
+/// Exit codes and `--porcelain` formats, shown at the end of `--help`.
+const SCRIPTING_HELP: &str = "\
+Exit codes:
+  0  Success
+  1  Nothing matched (list, glob, grep, cat, extract), the download's hash didn't match,
+     verify-extracted found differences, patch found no added or changed files, or
+     verify-signature found a missing, unsigned or mismatched manifest
+  2  Error: invalid arguments, an unreadable download, or failed reads and writes.
+     Other archives which can't be loaded are skipped with a warning
+
+--porcelain output is tab-separated, one record per line:
+  list, glob  <path>, or <size>\t<path> with --long
+  grep        <path>\t<line>\t<text> for each matching line, or <path> with -l
+  extract     <written file>
+  info        <archive>\t<key>\t<value>
+  verify-extracted  <missing|changed|extra>\t<path relative to the directory>
+  patch       <added|changed|removed>\t<path>
+  verify-signature  <path> of each file which doesn't match the manifest";
+
 /// CLI for browsing and searching Enfusion PAK and DayZ PBO archives.
 ///
 /// Accepts `.pak` and `.pbo` files, or directories containing them.
 #[derive(Parser, Debug)]
-#[command(name = "enfusion", version, about)]
+#[command(name = "enfusion", version, about, after_help = SCRIPTING_HELP)]
 struct Cli {
+    /// Print nothing on stdout and only errors on stderr; check the exit code instead.
+    #[arg(long, short, global = true, conflicts_with = "porcelain")]
+    quiet: bool,
+
+    /// Print stable, tab-separated output for scripts (see below).
+    #[arg(long, global = true)]
+    porcelain: bool,
+
     #[command(subcommand)]
     command: Command,
 }
 
+/// Process exit status. Like grep and diff, 1 is a negative answer rather than a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success = 0,
+    /// Nothing matched, or a check found differences.
+    Negative = 1,
+    Error = 2,
+}
+
+impl Status {
+    /// [`Status::Success`] if anything was found, otherwise [`Status::Negative`].
+    fn found(found: bool) -> Self {
+        if found { Status::Success } else { Status::Negative }
+    }
+}
+
+/// Prints `message` to stderr and exits with [`Status::Error`].
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{message}");
+    std::process::exit(Status::Error as i32);
+}
+
+/// How much a command prints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputMode {
+    Normal,
+    /// Nothing on stdout, and only errors on stderr.
+    Quiet,
+    /// Stable, machine-readable stdout.
+    Porcelain,
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
     /// List files in the archive(s).
@@ -36,6 +117,10 @@ enum Command {
         #[arg(long, short)]
         flat: bool,
 
+        /// Print only the full paths, unsorted. The quickest way to list very large archives.
+        #[arg(long, conflicts_with = "long")]
+        paths_only: bool,
+
         /// Only show files matching this glob pattern (e.g. "**/*.xml", "DZ/AI/**").
         #[arg(long, short = 'g')]
         glob: Option<String>,
@@ -43,6 +128,10 @@ enum Command {
         /// Show file sizes.
         #[arg(long, short)]
         long: bool,
+
+        /// Only show files of these types (comma-separated, e.g. "script,config").
+        #[arg(long = "type", short = 't', value_delimiter = ',', value_parser = parse_entry_type)]
+        types: Vec<EntryType>,
     },
 
     /// Find files matching a glob pattern (flat output, one path per line).
@@ -58,6 +147,10 @@ enum Command {
         /// Show file sizes.
         #[arg(long, short)]
         long: bool,
+
+        /// Only show files of these types (comma-separated, e.g. "script,config").
+        #[arg(long = "type", short = 't', value_delimiter = ',', value_parser = parse_entry_type)]
+        types: Vec<EntryType>,
     },
 
     /// Search file contents with a regex pattern.
@@ -92,15 +185,20 @@ enum Command {
         context: usize,
     },
 
-    /// Print the raw contents of a file to stdout.
+    /// Print the contents of one or more files to stdout.
     Cat {
         /// Archive files or directories to load (.pak, .pbo).
         #[arg(required = true)]
         files: Vec<PathBuf>,
 
-        /// Path within the archive (e.g. "DZ/AI/config.bin").
+        /// Path within the archive (e.g. "DZ/AI/config.bin"), or a glob pattern matching
+        /// several files (e.g. "DZ/weapons/**/config.bin").
         #[arg(long, short)]
         path: String,
+
+        /// Print a "==> path <==" line before each file.
+        #[arg(long)]
+        header: bool,
     },
 
     /// Show archive metadata (extensions, entry count, prefix).
@@ -109,28 +207,210 @@ enum Command {
         #[arg(required = true)]
         files: Vec<PathBuf>,
     },
+
+    /// Write files out of the archive(s) to a directory.
+    Extract {
+        /// Archive files or directories to load (.pak, .pbo).
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Directory to write files to.
+        #[arg(long, short)]
+        output: PathBuf,
+
+        /// Only extract files matching this glob pattern.
+        #[arg(long, short = 'g')]
+        glob: Option<String>,
+
+        /// Number of files to extract at once. Defaults to the number of CPUs.
+        #[arg(long, short)]
+        jobs: Option<NonZeroUsize>,
+
+        /// Most decompressed file data, in MiB, to hold in memory at once.
+        #[arg(long, default_value = "1024")]
+        max_memory: u64,
+
+        /// Skip files already in the output directory which match the archive's copy, by
+        /// size or by size and SHA-256.
+        #[arg(long, value_enum)]
+        resume: Option<extract::Check>,
+    },
+
+    /// Compare a previously extracted directory with the archive(s).
+    ///
+    /// Reports files which are missing from the directory, differ from the archive's copy,
+    /// or aren't in the archives at all. Extra files matched by a `.enfignore` file at the
+    /// root of the directory aren't reported.
+    VerifyExtracted {
+        /// Directory the archives were extracted to.
+        dir: PathBuf,
+
+        /// Archive files or directories to load (.pak, .pbo).
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Only compare files matching this glob pattern.
+        #[arg(long, short = 'g')]
+        glob: Option<String>,
+
+        /// Compare by size, or by size and SHA-256.
+        #[arg(long, value_enum, default_value = "size")]
+        check: extract::Check,
+
+        /// Number of files to compare at once. Defaults to the number of CPUs.
+        #[arg(long, short)]
+        jobs: Option<NonZeroUsize>,
+    },
+
+    /// Write a pak holding only the files added or changed between two builds.
+    ///
+    /// Loading the patch after the base build gives the modified build's contents, so a
+    /// mod update can ship just what changed. Files removed in the modified build are
+    /// reported, but a pak can't delete them. Files are zlib-compressed where that makes them
+    /// smaller, unless --store is given.
+    Patch {
+        /// Archive files or directories of the old build.
+        #[arg(long, required = true, num_args = 1..)]
+        base: Vec<PathBuf>,
+
+        /// Archive files or directories of the new build.
+        #[arg(long, required = true, num_args = 1..)]
+        modified: Vec<PathBuf>,
+
+        /// Where to write the patch pak.
+        #[arg(long, short)]
+        output: PathBuf,
+
+        /// Only compare files matching this glob pattern.
+        #[arg(long, short = 'g')]
+        glob: Option<String>,
+
+        /// Embed a manifest of every file's SHA-256 in the patch.
+        #[arg(long)]
+        manifest: bool,
+
+        /// Sign the embedded manifest with the ed25519 key in this file, given as 64 hex
+        /// characters (e.g. from `openssl rand -hex 32`). Implies --manifest.
+        #[arg(long)]
+        signing_key: Option<PathBuf>,
+
+        /// Start each file at least this large on an offset which is a multiple of this
+        /// many bytes, e.g. 4096. Must be a power of two.
+        #[arg(long, default_value = "1")]
+        align: u32,
+
+        /// Store files uncompressed, e.g. so aligned files can be mapped straight out of the
+        /// pak.
+        #[arg(long)]
+        store: bool,
+    },
+
+    /// Check a pak's files against its embedded manifest, and the manifest's signature.
+    ///
+    /// Servers can run this before loading a mod to make sure it's what its author
+    /// published.
+    VerifySignature {
+        /// The .pak file to check.
+        file: PathBuf,
+
+        /// The author's ed25519 public key, as 64 hex characters. Without it, only the
+        /// files are checked against the manifest, which anyone could have rewritten.
+        #[arg(long)]
+        public_key: Option<String>,
+    },
+
+    /// Download an archive, verify its hash, then list or extract it.
+    ///
+    /// Intended for CI jobs validating published mod packages. The URL must end in the
+    /// archive's file name (e.g. ".../data.pak") so its type can be detected.
+    Fetch {
+        /// URL of the .pak or .pbo file.
+        url: String,
+
+        /// Expected SHA-256 of the archive, as hex. Nothing is read from the archive if
+        /// it doesn't match.
+        #[arg(long)]
+        sha256: String,
+
+        /// Keep the downloaded archive in this directory instead of deleting it afterwards.
+        #[arg(long)]
+        keep: Option<PathBuf>,
+
+        #[command(subcommand)]
+        action: FetchAction,
+    },
+
+    /// Answer JSON-RPC 2.0 requests to search and read builds, one per line on stdin.
+    ///
+    /// Methods are `builds`, `search` ({build, pattern, ignore_case, glob, extensions,
+    /// context, max_results}) and `read` ({build, path, offset, length}). Responses are
+    /// written to stdout, one per line.
+    Serve {
+        /// A build to load, as NAME=PATH where PATH is an archive or a directory of them.
+        /// Repeat a name to load several paths as one build.
+        #[arg(
+            long = "build",
+            value_name = "NAME=PATH",
+            required = true,
+            value_parser = parse_build
+        )]
+        builds: Vec<(String, PathBuf)>,
+    },
 }
 
-const DEFAULT_TEXT_EXTENSIONS: &[&str] = &[
-    "c", "et", "conf", "layout", "agr", "asi", "ast", "asy", "aw", "emat", "hpp", "json", "txt",
-    "xml",
-];
+/// What to do with a fetched archive.
+#[derive(Subcommand, Debug)]
+enum FetchAction {
+    /// List files in the archive.
+    #[command(alias = "ls")]
+    List {
+        /// Show full paths (one per line) instead of a tree.
+        #[arg(long, short)]
+        flat: bool,
+
+        /// Only show files matching this glob pattern.
+        #[arg(long, short = 'g')]
+        glob: Option<String>,
+
+        /// Show file sizes.
+        #[arg(long, short)]
+        long: bool,
+    },
+
+    /// Write files out of the archive to a directory.
+    Extract {
+        /// Directory to write files to.
+        #[arg(long, short)]
+        output: PathBuf,
+
+        /// Only extract files matching this glob pattern.
+        #[arg(long, short = 'g')]
+        glob: Option<String>,
+    },
+}
 
 fn main() {
     let cli = Cli::parse();
+    let mode = if cli.quiet {
+        OutputMode::Quiet
+    } else if cli.porcelain {
+        OutputMode::Porcelain
+    } else {
+        OutputMode::Normal
+    };
 
-    match cli.command {
-        Command::List { files, flat, glob, long } => {
+    let status = match cli.command {
+        Command::List { files, flat, paths_only, glob, long, types } => {
             let input_paths = require_inputs(&files);
             let (overlay, file_set) = mount_archives(&input_paths);
             let matcher = glob.as_deref().map(compile_glob);
-            cmd_list(&overlay, &file_set, flat, matcher.as_ref(), long);
+            cmd_list(&overlay, &file_set, flat, paths_only, matcher.as_ref(), long, &types, mode)
         }
-        Command::Glob { pattern, files, long } => {
+        Command::Glob { pattern, files, long, types } => {
             let input_paths = require_inputs(&files);
             let (overlay, file_set) = mount_archives(&input_paths);
             let matcher = compile_glob(&pattern);
-            cmd_list(&overlay, &file_set, true, Some(&matcher), long);
+            cmd_list(&overlay, &file_set, true, false, Some(&matcher), long, &types, mode)
         }
         Command::Grep { pattern, files, ignore_case, glob, extensions, files_only, context } => {
             let input_paths = require_inputs(&files);
@@ -145,34 +425,93 @@ fn main() {
                 extensions,
                 files_only,
                 context,
-            );
+                mode,
+            )
         }
-        Command::Cat { files, path } => {
+        Command::Cat { files, path, header } => {
             let input_paths = require_inputs(&files);
-            let (overlay, _) = mount_archives(&input_paths);
-            cmd_cat(&overlay, &path);
+            let (overlay, file_set) = mount_archives(&input_paths);
+            cmd_cat(&overlay, &file_set, &path, header, mode)
         }
         Command::Info { files } => {
             let input_paths = require_inputs(&files);
-            cmd_info(&input_paths);
+            cmd_info(&input_paths, mode)
         }
-    }
+        Command::Extract { files, output, glob, jobs, max_memory, resume } => {
+            let input_paths = require_inputs(&files);
+            let (overlay, file_set) = mount_archives(&input_paths);
+            let matcher = glob.as_deref().map(compile_glob);
+            let defaults = extract::Options::default();
+            let options = extract::Options {
+                jobs: jobs.unwrap_or(defaults.jobs),
+                memory_budget: max_memory.saturating_mul(1024 * 1024),
+                resume,
+            };
+            cmd_extract(&overlay, &file_set, &output, matcher.as_ref(), &options, mode)
+        }
+        Command::VerifyExtracted { dir, files, glob, check, jobs } => {
+            let input_paths = require_inputs(&files);
+            let (overlay, file_set) = mount_archives(&input_paths);
+            let matcher = glob.as_deref().map(compile_glob);
+            let defaults = extract::Options::default();
+            let options = extract::Options { jobs: jobs.unwrap_or(defaults.jobs), ..defaults };
+            cmd_verify_extracted(&overlay, &file_set, &dir, matcher.as_ref(), check, &options, mode)
+        }
+        Command::Patch { base, modified, output, glob, manifest, signing_key, align, store } => {
+            if !align.is_power_of_two() {
+                fail("--align must be a power of two");
+            }
+            let (base_overlay, base_files) = mount_archives(&require_inputs(&base));
+            let (modified_overlay, modified_files) = mount_archives(&require_inputs(&modified));
+            let matcher = glob.as_deref().map(compile_glob);
+            let options = patch::Options {
+                manifest,
+                signing_key: signing_key.as_deref().map(load_signing_key),
+                write: WriteOptions {
+                    alignment: align,
+                    min_aligned_len: align,
+                    padding: 0,
+                    compress: !store,
+                },
+            };
+            cmd_patch(
+                (&base_overlay, &base_files),
+                (&modified_overlay, &modified_files),
+                &output,
+                matcher.as_ref(),
+                &options,
+                mode,
+            )
+        }
+        Command::VerifySignature { file, public_key } => {
+            let public_key = public_key.as_deref().map(|hex| {
+                signing::from_hex(hex)
+                    .and_then(|key| signing::VerifyingKey::from_bytes(&key).ok())
+                    .unwrap_or_else(|| {
+                        fail("--public-key must be an ed25519 key as 64 hex characters")
+                    })
+            });
+            cmd_verify_signature(&file, public_key.as_ref(), mode)
+        }
+        Command::Fetch { url, sha256, keep, action } => {
+            cmd_fetch(&url, &sha256, keep, action, mode)
+        }
+        Command::Serve { builds } => cmd_serve(&builds),
+    };
+
+    std::process::exit(status as i32);
 }
 
 fn compile_glob(pattern: &str) -> GlobMatcher {
     Glob::new(pattern)
-        .unwrap_or_else(|e| {
-            eprintln!("Invalid glob pattern: {e}");
-            std::process::exit(1);
-        })
+        .unwrap_or_else(|e| fail(format!("Invalid glob pattern: {e}")))
         .compile_matcher()
 }
 
 fn require_inputs(files: &[PathBuf]) -> Vec<PathBuf> {
     let input_paths = expand_inputs(files);
     if input_paths.is_empty() {
-        eprintln!("No .pak or .pbo files found in the provided paths.");
-        std::process::exit(1);
+        fail("No .pak or .pbo files found in the provided paths.");
     }
     input_paths
 }
@@ -198,15 +537,34 @@ fn expand_inputs(paths: &[PathBuf]) -> Vec<PathBuf> {
     result
 }
 
-fn is_supported(path: &std::path::Path) -> bool {
+fn is_supported(path: &Path) -> bool {
     matches!(path.extension().and_then(OsStr::to_str), Some("pak" | "pbo"))
 }
 
 /// Parse and mount all archives into a single overlay VFS.
 /// Returns the overlay root and a set of file paths (for quick is-file checks).
+///
+/// Archives that can't be loaded are skipped with a warning on stderr.
 fn mount_archives(paths: &[PathBuf]) -> (VfsPath, HashSet<String>) {
+    let (mounted, errors) = load_archives(paths);
+    for error in errors {
+        eprintln!("{error}");
+    }
+    mounted
+}
+
+/// Like [`mount_archives`], but returns every archive's load error instead of skipping
+/// those archives.
+fn try_mount_archives(paths: &[PathBuf]) -> Result<(VfsPath, HashSet<String>), String> {
+    let (mounted, errors) = load_archives(paths);
+    if errors.is_empty() { Ok(mounted) } else { Err(errors.join("\n")) }
+}
+
+/// Mounts every archive which loads, alongside the errors of those which don't.
+fn load_archives(paths: &[PathBuf]) -> ((VfsPath, HashSet<String>), Vec<String>) {
     let mut vfs_layers: Vec<VfsPath> = vec![VfsPath::new(MemoryFS::new())];
     let mut file_set = HashSet::new();
+    let mut errors = Vec::new();
 
     for path in paths {
         let ext = path.extension().and_then(OsStr::to_str).unwrap_or("").to_ascii_lowercase();
@@ -219,11 +577,11 @@ fn mount_archives(paths: &[PathBuf]) -> (VfsPath, HashSet<String>) {
                             let vfs = dayz_pbo::pbo_vfs::PboVfs::new(mmap, pbo);
                             vfs_layers.push(VfsPath::new(vfs));
                         }
-                        Err(e) => eprintln!("Error parsing {}: {e}", path.display()),
+                        Err(e) => errors.push(format!("Error parsing {}: {e}", path.display())),
                     },
-                    Err(e) => eprintln!("Error mmapping {}: {e}", path.display()),
+                    Err(e) => errors.push(format!("Error mmapping {}: {e}", path.display())),
                 },
-                Err(e) => eprintln!("Error opening {}: {e}", path.display()),
+                Err(e) => errors.push(format!("Error opening {}: {e}", path.display())),
             },
             "pak" => match std::fs::File::open(path) {
                 Ok(file) => {
@@ -237,15 +595,18 @@ fn mount_archives(paths: &[PathBuf]) -> (VfsPath, HashSet<String>) {
                                         mmap,
                                         pak,
                                     );
-                                let vfs = enfusion_pak::pak_vfs::PakVfs::new(Arc::new(wrapper));
-                                vfs_layers.push(VfsPath::new(vfs));
+                                match enfusion_pak::pak_vfs::PakVfs::new(Arc::new(wrapper)) {
+                                    Ok(vfs) => vfs_layers.push(VfsPath::new(vfs)),
+                                    Err(e) => errors
+                                        .push(format!("Error mounting {}: {e}", path.display())),
+                                }
                             }
-                            Err(e) => eprintln!("Error parsing {}: {e}", path.display()),
+                            Err(e) => errors.push(format!("Error parsing {}: {e}", path.display())),
                         },
-                        Err(e) => eprintln!("Error mmapping {}: {e}", path.display()),
+                        Err(e) => errors.push(format!("Error mmapping {}: {e}", path.display())),
                     }
                 }
-                Err(e) => eprintln!("Error opening {}: {e}", path.display()),
+                Err(e) => errors.push(format!("Error opening {}: {e}", path.display())),
             },
             _ => {}
         }
@@ -269,7 +630,7 @@ fn mount_archives(paths: &[PathBuf]) -> (VfsPath, HashSet<String>) {
         }
     }
 
-    (overlay, file_set)
+    ((overlay, file_set), errors)
 }
 
 /// Match a VFS path against a glob. Paths in the VFS start with `/`, so we
@@ -279,76 +640,162 @@ fn glob_matches(matcher: &GlobMatcher, vfs_path: &str) -> bool {
     matcher.is_match(path)
 }
 
+fn parse_build(arg: &str) -> Result<(String, PathBuf), String> {
+    match arg.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+            Ok((name.to_string(), PathBuf::from(path)))
+        }
+        _ => Err(format!("expected NAME=PATH, got \"{arg}\"")),
+    }
+}
+
+fn parse_entry_type(name: &str) -> Result<EntryType, String> {
+    EntryType::from_name(name).ok_or_else(|| {
+        let known: Vec<&str> = EntryType::ALL.iter().map(|ty| ty.name()).collect();
+        format!("unknown type \"{name}\" (expected one of: {})", known.join(", "))
+    })
+}
+
+/// Writes `path` followed by a newline without lossy conversion, so scripts get back exactly
+/// the path they need to open even when it isn't valid UTF-8.
+fn print_path(path: &Path) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout
+        .write_all(path.as_os_str().as_encoded_bytes())
+        .and_then(|()| stdout.write_all(b"\n"));
+}
+
+/// Whether a file passes the `--type` filter. Types are detected by extension only so
+/// listing doesn't have to read every file.
+fn type_matches(types: &[EntryType], vfs_path: &str) -> bool {
+    types.is_empty() || types.contains(&detect_type(vfs_path, &[]))
+}
+
 // ---------------------------------------------------------------------------
 // Subcommands
 // ---------------------------------------------------------------------------
 
+#[allow(clippy::too_many_arguments)]
 fn cmd_list(
     root: &VfsPath,
     file_set: &HashSet<String>,
     flat: bool,
+    paths_only: bool,
     glob: Option<&GlobMatcher>,
     long: bool,
-) {
-    if flat {
-        let mut paths: Vec<&String> = file_set.iter().collect();
-        paths.sort();
-        for path in paths {
-            if let Some(g) = glob
-                && !glob_matches(g, path)
-            {
-                continue;
-            }
-            if long
-                && let Ok(vfs_path) = root.join(path)
-                && let Ok(meta) = vfs_path.metadata()
-            {
-                println!("{:>10}  {}", meta.len, path);
-                continue;
-            }
-            println!("{path}");
-        }
+    types: &[EntryType],
+    mode: OutputMode,
+) -> Status {
+    let matches =
+        |path: &str| glob.is_none_or(|g| glob_matches(g, path)) && type_matches(types, path);
+
+    if mode == OutputMode::Quiet {
+        return Status::found(file_set.iter().any(|path| matches(path)));
+    }
+
+    // Listings can run to hundreds of thousands of lines, so write through one buffer rather
+    // than locking and flushing stdout per line
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let result = if paths_only {
+        list_paths(&mut out, file_set, &matches)
+    } else if flat || mode == OutputMode::Porcelain {
+        list_flat(&mut out, root, file_set, &matches, long, mode)
     } else {
-        // Tree walk
-        let mut queue: VecDeque<(usize, VfsPath)> = VecDeque::new();
-        queue.push_back((0, root.clone()));
+        list_tree(&mut out, root, file_set, &matches, long)
+    }
+    .and_then(|found| out.flush().map(|()| found));
 
-        while let Some((depth, node)) = queue.pop_front() {
-            let name = if depth == 0 { ".".to_string() } else { node.filename() };
+    match result {
+        Ok(found) => Status::found(found),
+        // The reader went away (e.g. `| head`), so there's nobody left to write to
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Status::Success,
+        Err(e) => fail(format!("Cannot write the listing: {e}")),
+    }
+}
 
-            let is_file = file_set.contains(node.as_str());
+/// Writes every matching path in the set's own order, skipping the sort.
+fn list_paths(
+    out: &mut impl Write,
+    file_set: &HashSet<String>,
+    matches: &impl Fn(&str) -> bool,
+) -> std::io::Result<bool> {
+    let mut found = false;
+    for path in file_set.iter().filter(|path| matches(path)) {
+        found = true;
+        out.write_all(path.as_bytes())?;
+        out.write_all(b"\n")?;
+    }
 
-            if let Some(g) = glob
-                && is_file
-                && !glob_matches(g, node.as_str())
-            {
-                continue;
-            }
+    Ok(found)
+}
 
-            let indent = "  ".repeat(depth);
-            if is_file {
-                if long {
-                    let size = node.metadata().map(|m| m.len).unwrap_or(0);
-                    println!("{indent}{name}  ({size} bytes)");
-                } else {
-                    println!("{indent}{name}");
-                }
+fn list_flat(
+    out: &mut impl Write,
+    root: &VfsPath,
+    file_set: &HashSet<String>,
+    matches: &impl Fn(&str) -> bool,
+    long: bool,
+    mode: OutputMode,
+) -> std::io::Result<bool> {
+    let mut paths: Vec<&String> = file_set.iter().filter(|path| matches(path)).collect();
+    paths.sort_unstable();
+    for path in &paths {
+        let size =
+            long.then(|| root.join(path).ok()?.metadata().ok()).flatten().map(|meta| meta.len);
+        match (size, mode) {
+            (Some(size), OutputMode::Porcelain) => writeln!(out, "{size}\t{path}")?,
+            (Some(size), _) => writeln!(out, "{size:>10}  {path}")?,
+            (None, _) => writeln!(out, "{path}")?,
+        }
+    }
+
+    Ok(!paths.is_empty())
+}
+
+fn list_tree(
+    out: &mut impl Write,
+    root: &VfsPath,
+    file_set: &HashSet<String>,
+    matches: &impl Fn(&str) -> bool,
+    long: bool,
+) -> std::io::Result<bool> {
+    let mut found = false;
+    let mut queue: VecDeque<(usize, VfsPath)> = VecDeque::new();
+    queue.push_back((0, root.clone()));
+
+    while let Some((depth, node)) = queue.pop_front() {
+        let is_file = file_set.contains(node.as_str());
+        if is_file && !matches(node.as_str()) {
+            continue;
+        }
+
+        let indent = depth * 2;
+        let name = if depth == 0 { "." } else { node.as_str().rsplit('/').next().unwrap_or("") };
+        if is_file {
+            found = true;
+            if long {
+                let size = node.metadata().map(|m| m.len).unwrap_or(0);
+                writeln!(out, "{:indent$}{name}  ({size} bytes)", "")?;
             } else {
-                println!("{indent}{name}/");
-                if let Ok(children) = node.read_dir() {
-                    let mut children: Vec<_> = children.collect();
-                    children.sort_by(|a, b| {
-                        let a_is_file = file_set.contains(a.as_str());
-                        let b_is_file = file_set.contains(b.as_str());
-                        a_is_file.cmp(&b_is_file).then(a.filename().cmp(&b.filename()))
-                    });
-                    for child in children.into_iter().rev() {
-                        queue.push_front((depth + 1, child));
-                    }
+                writeln!(out, "{:indent$}{name}", "")?;
+            }
+        } else {
+            writeln!(out, "{:indent$}{name}/", "")?;
+            if let Ok(children) = node.read_dir() {
+                let mut children: Vec<_> = children.collect();
+                children.sort_by(|a, b| {
+                    let a_is_file = file_set.contains(a.as_str());
+                    let b_is_file = file_set.contains(b.as_str());
+                    a_is_file.cmp(&b_is_file).then(a.filename().cmp(&b.filename()))
+                });
+                for child in children.into_iter().rev() {
+                    queue.push_front((depth + 1, child));
                 }
             }
         }
     }
+
+    Ok(found)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -361,201 +808,684 @@ fn cmd_grep(
     extensions: Option<Vec<String>>,
     files_only: bool,
     context: usize,
-) {
-    let regex = regex::RegexBuilder::new(pattern).case_insensitive(ignore_case).build();
+    mode: OutputMode,
+) -> Status {
+    let regex = regex::bytes::RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .unwrap_or_else(|e| fail(format!("Invalid regex: {e}")));
+    let searcher = Searcher::new(regex).context(context);
 
-    let regex = match regex {
-        Ok(r) => r,
-        Err(e) => {
-            eprintln!("Invalid regex: {e}");
-            std::process::exit(1);
+    let files = grep_candidates(root, file_set, glob, extensions);
+
+    if mode == OutputMode::Quiet {
+        let mut found = false;
+        searcher.search_files(files, |_, _| {
+            found = true;
+            ControlFlow::Break(())
+        });
+        return Status::found(found);
+    }
+
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let mut found = false;
+    let mut written = Ok(());
+    searcher.search_files(files, |file, matches| {
+        found = true;
+        written = write_grep_matches(&mut out, file.as_str(), &matches, files_only, mode);
+        if written.is_ok() { ControlFlow::Continue(()) } else { ControlFlow::Break(()) }
+    });
+
+    match written.and_then(|()| out.flush()) {
+        Ok(()) => Status::found(found),
+        // The reader went away (e.g. `| head`), so there's nobody left to write to
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Status::Success,
+        Err(e) => fail(format!("Cannot write the matches: {e}")),
+    }
+}
+
+/// Writes one file's matches in the format `mode` and `files_only` ask for.
+fn write_grep_matches(
+    out: &mut impl Write,
+    file_path: &str,
+    matches: &[enfusion_search::LineMatch],
+    files_only: bool,
+    mode: OutputMode,
+) -> std::io::Result<()> {
+    if files_only {
+        return writeln!(out, "{file_path}");
+    }
+
+    if mode == OutputMode::Porcelain {
+        for line_match in matches {
+            writeln!(out, "{file_path}\t{}\t{}", line_match.line, line_match.text)?;
+        }
+        return Ok(());
+    }
+
+    writeln!(out, "{file_path}:")?;
+    let mut last_printed_line: Option<usize> = None;
+    for line_match in matches {
+        // Separator between non-contiguous match groups
+        if let Some(last) = last_printed_line
+            && line_match.first_line() > last + 1
+        {
+            writeln!(out, "--")?;
         }
-    };
 
-    // File filtering: --glob takes precedence, otherwise --extensions, otherwise defaults
+        for (line_number, line) in line_match.lines() {
+            if last_printed_line.is_some_and(|last| line_number <= last) {
+                continue;
+            }
+            let marker = if line_number == line_match.line { ">" } else { " " };
+            writeln!(out, "{marker}{line_number:>6}: {line}")?;
+            last_printed_line = Some(line_number);
+        }
+    }
+    writeln!(out)
+}
+
+/// The files `grep` searches, in path order: those matching `glob` if it's given, otherwise
+/// those with one of `extensions`, which defaults to common text formats.
+fn grep_candidates(
+    root: &VfsPath,
+    file_set: &HashSet<String>,
+    glob: Option<&GlobMatcher>,
+    extensions: Option<Vec<String>>,
+) -> Vec<VfsPath> {
+    // --glob takes precedence, otherwise --extensions, otherwise defaults
     let ext_filter: Option<Vec<String>> = if glob.is_none() {
-        Some(
-            extensions
-                .unwrap_or_else(|| DEFAULT_TEXT_EXTENSIONS.iter().map(|s| s.to_string()).collect()),
-        )
+        Some(extensions.unwrap_or_else(|| text_extensions().map(|s| s.to_string()).collect()))
     } else {
         None
     };
 
     let mut paths: Vec<&String> = file_set.iter().collect();
     paths.sort();
+    paths
+        .into_iter()
+        .filter(|file_path| match (glob, &ext_filter) {
+            (Some(g), _) => glob_matches(g, file_path),
+            (None, Some(exts)) => {
+                let ext = file_path
+                    .rsplit_once('.')
+                    .map(|(_, e)| e.to_ascii_lowercase())
+                    .unwrap_or_default();
+                exts.iter().any(|a| a.eq_ignore_ascii_case(&ext))
+            }
+            (None, None) => true,
+        })
+        .filter_map(|file_path| root.join(file_path).ok())
+        .collect()
+}
 
-    for file_path in paths {
-        // Apply glob or extension filter
-        if let Some(g) = glob {
-            if !glob_matches(g, file_path) {
-                continue;
+fn cmd_cat(
+    root: &VfsPath,
+    file_set: &HashSet<String>,
+    path: &str,
+    header: bool,
+    mode: OutputMode,
+) -> Status {
+    let is_glob = path.contains(['*', '?', '[', '{']);
+    let paths = if is_glob {
+        let matcher = compile_glob(path);
+        let mut matched: Vec<&str> = file_set
+            .iter()
+            .filter(|file| glob_matches(&matcher, file))
+            .map(String::as_str)
+            .collect();
+        if matched.is_empty() {
+            if mode != OutputMode::Quiet {
+                eprintln!("No files match {path}");
             }
-        } else if let Some(ref exts) = ext_filter {
-            let ext =
-                file_path.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
-            if !exts.iter().any(|a| a.eq_ignore_ascii_case(&ext)) {
-                continue;
+            return Status::Negative;
+        }
+        matched.sort();
+        matched
+    } else {
+        if !root.join(path).and_then(|file| file.is_file()).unwrap_or(false) {
+            if mode != OutputMode::Quiet {
+                eprintln!("File not found: {path}");
             }
+            return Status::Negative;
         }
+        vec![path]
+    };
 
-        let vfs_path = match root.join(file_path) {
-            Ok(p) => p,
-            Err(_) => continue,
+    let mut out: Box<dyn Write> = if mode == OutputMode::Quiet {
+        Box::new(std::io::sink())
+    } else {
+        Box::new(std::io::BufWriter::new(std::io::stdout().lock()))
+    };
+    let mut failed = false;
+    for (idx, file) in paths.iter().enumerate() {
+        let header_written = if header {
+            let separator = if idx > 0 { "\n" } else { "" };
+            writeln!(out, "{separator}==> {} <==", file.trim_start_matches('/'))
+        } else {
+            Ok(())
         };
+        let result = header_written.and_then(|()| cat_file(root, file, &mut out));
 
-        let mut reader = match vfs_path.open_file() {
-            Ok(r) => r,
-            Err(_) => continue,
-        };
+        match result {
+            Ok(()) => {}
+            // The reader went away (e.g. `| head`), so there's nobody left to write to
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Status::Success,
+            Err(e) => {
+                eprintln!("{e}");
+                failed = true;
+                // A single named file failing is fatal; with a glob, carry on with the rest
+                if !is_glob {
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Err(e) = out.flush()
+        && e.kind() != std::io::ErrorKind::BrokenPipe
+    {
+        eprintln!("Failed to write to stdout: {e}");
+        failed = true;
+    }
+    if failed { Status::Error } else { Status::Success }
+}
+
+/// Streams one file's decompressed contents to `out`. Rapified configs are decompiled,
+/// which needs the whole file in memory; everything else is copied in chunks.
+fn cat_file(root: &VfsPath, path: &str, out: &mut impl Write) -> std::io::Result<()> {
+    let vfs_path = root
+        .join(path)
+        .map_err(|e| std::io::Error::other(format!("Path not found: {path} ({e})")))?;
+    if !vfs_path.is_file().unwrap_or(false) {
+        return Err(std::io::Error::other(format!("File not found: {path}")));
+    }
+    let mut reader = vfs_path
+        .open_file()
+        .map_err(|e| std::io::Error::other(format!("Cannot open file: {path} ({e})")))?;
+
+    let mut magic = Vec::with_capacity(4);
+    (&mut reader).take(4).read_to_end(&mut magic)?;
+    if cfg_parser::is_rapified(&magic) {
+        let mut data = magic;
+        reader.read_to_end(&mut data)?;
+        match cfg_parser::RapFile::parse(&data) {
+            Ok(rap) => return out.write_all(cfg_parser::decompile(&rap).as_bytes()),
+            Err(e) => {
+                eprintln!("Warning: rapified config parse failed for {path}: {e}");
+                return out.write_all(&data);
+            }
+        }
+    }
+
+    out.write_all(&magic)?;
+    std::io::copy(&mut reader, out)?;
+    Ok(())
+}
+
+/// Pairs each file matching `glob` with where it's extracted to under `output_root`, in path
+/// order. Files whose paths would leave the output directory are reported and counted
+/// instead.
+fn extract_jobs<'a>(
+    file_set: &'a HashSet<String>,
+    output_root: &Path,
+    glob: Option<&GlobMatcher>,
+) -> (Vec<extract::Job<'a>>, usize) {
+    let mut paths: Vec<&String> = file_set.iter().collect();
+    paths.sort();
 
-        let mut contents = String::new();
-        if reader.read_to_string(&mut contents).is_err() {
+    let mut jobs = Vec::new();
+    let mut escaping = 0;
+    for path in paths {
+        if let Some(g) = glob
+            && !glob_matches(g, path)
+        {
             continue;
         }
 
-        if files_only {
-            if regex.is_match(&contents) {
-                println!("{file_path}");
+        let (Ok(relative), Ok(dest)) = (relative_path(path), dest_path(output_root, path)) else {
+            eprintln!("Skipping {path}: path escapes the output directory");
+            escaping += 1;
+            continue;
+        };
+
+        jobs.push(extract::Job { path, relative, dest });
+    }
+
+    (jobs, escaping)
+}
+
+fn cmd_extract(
+    root: &VfsPath,
+    file_set: &HashSet<String>,
+    output: &Path,
+    glob: Option<&GlobMatcher>,
+    options: &extract::Options,
+    mode: OutputMode,
+) -> Status {
+    let (jobs, mut failed) = extract_jobs(file_set, output, glob);
+    let matched = jobs.len() + failed;
+
+    let mut extracted = 0;
+    let mut skipped = 0;
+    extract::extract_all(root, &jobs, options, |job, outcome| match outcome {
+        extract::Outcome::Written => {
+            extracted += 1;
+            if mode == OutputMode::Porcelain {
+                print_path(&output.join(&job.relative));
             }
+        }
+        extract::Outcome::Skipped => skipped += 1,
+        extract::Outcome::Failed(e) => {
+            eprintln!("Error extracting {}: {e}", job.path);
+            failed += 1;
+        }
+    });
+
+    if mode == OutputMode::Normal {
+        eprint!("Extracted {extracted} file(s) to {}", output.display());
+        if skipped > 0 {
+            eprint!(", skipped {skipped} already up to date");
+        }
+        eprintln!();
+    }
+    if failed > 0 { Status::Error } else { Status::found(matched > 0) }
+}
+
+fn cmd_verify_extracted(
+    root: &VfsPath,
+    file_set: &HashSet<String>,
+    dir: &Path,
+    glob: Option<&GlobMatcher>,
+    check: extract::Check,
+    options: &extract::Options,
+    mode: OutputMode,
+) -> Status {
+    if !dir.is_dir() {
+        fail(format!("{} is not a directory", dir.display()));
+    }
+
+    let dir_root = long_path(dir);
+    let (jobs, mut failed) = extract_jobs(file_set, &dir_root, glob);
+
+    let report = |status: &str, relative: &Path| match mode {
+        OutputMode::Quiet => {}
+        OutputMode::Porcelain => {
+            print!("{status}\t");
+            print_path(relative);
+        }
+        OutputMode::Normal => println!("{status}: {}", relative.display()),
+    };
+
+    let mut up_to_date = 0;
+    let mut drifted = 0;
+    extract::verify_all(root, &jobs, options, check, |job, verified| match verified {
+        extract::Verified::UpToDate => up_to_date += 1,
+        extract::Verified::Missing => {
+            drifted += 1;
+            report("missing", &job.relative);
+        }
+        extract::Verified::Changed => {
+            drifted += 1;
+            report("changed", &job.relative);
+        }
+        extract::Verified::Failed(e) => {
+            eprintln!("Error comparing {}: {e}", job.path);
+            failed += 1;
+        }
+    });
+
+    // Anything else on disk under the glob isn't from the archives, unless the folder's
+    // ignore file says it isn't meant to be
+    let expected: HashSet<&Path> = jobs.iter().map(|job| job.dest.as_path()).collect();
+    let ignored = IgnoreRules::load(&dir_root).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {e}", dir.join(IGNORE_FILE_NAME).display());
+        failed += 1;
+        IgnoreRules::default()
+    });
+    let mut on_disk = Vec::new();
+    if let Err(e) = files_under(&dir_root, &mut on_disk) {
+        eprintln!("Error reading {}: {e}", dir.display());
+        failed += 1;
+    }
+    on_disk.sort();
+    for path in on_disk {
+        let Ok(relative) = path.strip_prefix(&dir_root) else {
+            continue;
+        };
+        let archive_path: Vec<_> =
+            relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        let archive_path = archive_path.join("/");
+        if expected.contains(path.as_path())
+            || glob.is_some_and(|g| !glob_matches(g, &archive_path))
+            || ignored.is_ignored(&archive_path, false)
+        {
             continue;
         }
+        drifted += 1;
+        report("extra", relative);
+    }
 
-        let lines: Vec<&str> = contents.lines().collect();
-        let mut printed_header = false;
-        let mut last_printed_line: Option<usize> = None;
+    if mode == OutputMode::Normal {
+        eprintln!("{up_to_date} file(s) up to date, {drifted} differ");
+    }
+    if failed > 0 {
+        Status::Error
+    } else if drifted > 0 {
+        Status::Negative
+    } else {
+        Status::Success
+    }
+}
 
-        for (line_idx, line) in lines.iter().enumerate() {
-            if regex.is_match(line) {
-                if !printed_header {
-                    println!("{}:", file_path);
-                    printed_header = true;
-                }
+fn cmd_patch(
+    base: (&VfsPath, &HashSet<String>),
+    modified: (&VfsPath, &HashSet<String>),
+    output: &Path,
+    glob: Option<&GlobMatcher>,
+    options: &patch::Options,
+    mode: OutputMode,
+) -> Status {
+    let now = jiff::Timestamp::now().to_zoned(jiff::tz::TimeZone::UTC).datetime();
+    let include = |path: &str| glob.is_none_or(|g| glob_matches(g, path));
+    let mut patch = patch::build(base.0, base.1, modified.0, modified.1, include, now)
+        .unwrap_or_else(|e| fail(e));
 
-                let ctx_start = line_idx.saturating_sub(context);
-                let ctx_end = (line_idx + context + 1).min(lines.len());
+    for change in &patch.changes {
+        let (kind, path) = (change.kind.name(), &change.path);
+        match mode {
+            OutputMode::Quiet => {}
+            OutputMode::Porcelain => println!("{kind}\t{path}"),
+            OutputMode::Normal => println!("{kind}: {path}"),
+        }
+    }
 
-                // Separator between non-contiguous match groups
-                if let Some(last) = last_printed_line
-                    && ctx_start > last + 1
-                {
-                    println!("--");
-                }
+    let count =
+        |kind: ChangeKind| patch.changes.iter().filter(|change| change.kind == kind).count();
+    let removed = count(ChangeKind::Removed);
+    if removed > 0 && mode == OutputMode::Normal {
+        eprintln!("Warning: {removed} file(s) removed in the modified build stay in the base");
+    }
+    if patch.is_empty() {
+        if mode == OutputMode::Normal {
+            eprintln!("No files were added or changed; not writing {}", output.display());
+        }
+        return Status::Negative;
+    }
+
+    if options.manifest || options.signing_key.is_some() {
+        if let Err(e) = patch.pak.embed_manifest(&[], options.signing_key.as_ref()) {
+            fail(format!("Error adding the manifest: {e}"));
+        }
+        if let (Some(key), OutputMode::Normal) = (&options.signing_key, mode) {
+            let public_key = signing::to_hex(key.verifying_key().as_bytes());
+            eprintln!("Signed the manifest; public key: {public_key}");
+        }
+    }
+
+    let written = std::fs::File::create(output).map_err(|e| e.to_string()).and_then(|file| {
+        let out = std::io::BufWriter::new(file);
+        patch.pak.write_with(&[], out, &options.write).map_err(|e| e.to_string())
+    });
+    if let Err(e) = written {
+        fail(format!("Error writing {}: {e}", output.display()));
+    }
+
+    if mode == OutputMode::Normal {
+        eprintln!(
+            "Wrote {} added and {} changed file(s) to {}",
+            count(ChangeKind::Added),
+            count(ChangeKind::Changed),
+            output.display()
+        );
+    }
+    Status::Success
+}
+
+/// Reads an ed25519 signing key stored as hex.
+fn load_signing_key(path: &Path) -> signing::SigningKey {
+    let hex = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| fail(format!("Error reading {}: {e}", path.display())));
+    match signing::from_hex(&hex) {
+        Some(seed) => signing::SigningKey::from_bytes(&seed),
+        None => fail(format!("{} must hold an ed25519 key as 64 hex characters", path.display())),
+    }
+}
+
+fn cmd_verify_signature(
+    path: &Path,
+    public_key: Option<&signing::VerifyingKey>,
+    mode: OutputMode,
+) -> Status {
+    let data = std::fs::File::open(path)
+        .and_then(|file| unsafe { memmap2::Mmap::map(&file) })
+        .unwrap_or_else(|e| fail(format!("Error opening {}: {e}", path.display())));
+    let pak = enfusion_pak::PakFile::parse(&data)
+        .unwrap_or_else(|e| fail(format!("Error parsing {}: {e}", path.display())));
 
-                #[allow(clippy::needless_range_loop)]
-                for i in ctx_start..ctx_end {
-                    if let Some(last) = last_printed_line
-                        && i <= last
-                    {
-                        continue;
+    match pak.verify_manifest(&data, public_key) {
+        Ok(manifest) => {
+            if mode == OutputMode::Normal {
+                let checked = if public_key.is_some() { "signed" } else { "unverified" };
+                eprintln!("{} file(s) match the {checked} manifest", manifest.files().len());
+            }
+            Status::Success
+        }
+        Err(SignatureError::Pak(e)) => fail(format!("Error reading {}: {e}", path.display())),
+        Err(e) => {
+            if let SignatureError::Mismatch(paths) = &e {
+                for mismatched in paths {
+                    match mode {
+                        OutputMode::Quiet => {}
+                        OutputMode::Porcelain => println!("{mismatched}"),
+                        OutputMode::Normal => println!("mismatch: {mismatched}"),
                     }
-                    let marker = if i == line_idx { ">" } else { " " };
-                    println!("{marker}{:>6}: {}", i + 1, lines[i]);
-                    last_printed_line = Some(i);
                 }
             }
+            if mode != OutputMode::Quiet {
+                eprintln!("{}: {e}", path.display());
+            }
+            Status::Negative
+        }
+    }
+}
+
+/// Collects every file under `dir`, recursively.
+fn files_under(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            files_under(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
         }
+    }
+    Ok(())
+}
 
-        if printed_header {
-            println!();
+/// The directory a fetched archive is downloaded to. It's removed when dropped unless the
+/// download is being kept.
+struct DownloadDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl Drop for DownloadDir {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = std::fs::remove_dir_all(&self.path);
         }
     }
 }
 
-fn cmd_cat(root: &VfsPath, path: &str) {
-    let vfs_path = match root.join(path) {
-        Ok(p) => p,
+fn cmd_fetch(
+    url: &str,
+    sha256: &str,
+    keep: Option<PathBuf>,
+    action: FetchAction,
+    mode: OutputMode,
+) -> Status {
+    // Checked up front, since an invalid glob exits without cleaning up the download
+    let matcher = match &action {
+        FetchAction::List { glob, .. } | FetchAction::Extract { glob, .. } => {
+            glob.as_deref().map(compile_glob)
+        }
+    };
+
+    let dir = DownloadDir {
+        path: keep.clone().unwrap_or_else(|| {
+            std::env::temp_dir().join(format!("enfusion-fetch-{}", std::process::id()))
+        }),
+        keep: keep.is_some(),
+    };
+
+    let archive = match fetch::download(url, sha256, &dir.path) {
+        Ok(download) => {
+            if mode == OutputMode::Normal {
+                eprintln!(
+                    "Downloaded {} bytes to {} (SHA-256 verified)",
+                    download.len,
+                    download.path.display()
+                );
+            }
+            download.path
+        }
+        Err(e @ fetch::DownloadError::HashMismatch { .. }) => {
+            eprintln!("{e}");
+            return Status::Negative;
+        }
         Err(e) => {
-            eprintln!("Path not found: {path} ({e})");
-            std::process::exit(1);
+            eprintln!("{e}");
+            return Status::Error;
         }
     };
 
-    if !vfs_path.exists().unwrap_or(false) {
-        eprintln!("File not found: {path}");
-        std::process::exit(1);
+    if !is_supported(&archive) {
+        eprintln!("{} is not a .pak or .pbo file", archive.display());
+        return Status::Error;
     }
 
-    let mut reader = match vfs_path.open_file() {
-        Ok(r) => r,
+    // Mounted after `dir`, so the archive is unmapped before it's deleted
+    let (overlay, file_set) = match try_mount_archives(std::slice::from_ref(&archive)) {
+        Ok(mounted) => mounted,
         Err(e) => {
-            eprintln!("Cannot open file: {path} ({e})");
-            std::process::exit(1);
+            eprintln!("{e}");
+            return Status::Error;
         }
     };
+    match action {
+        FetchAction::List { flat, long, .. } => {
+            cmd_list(&overlay, &file_set, flat, false, matcher.as_ref(), long, &[], mode)
+        }
+        FetchAction::Extract { output, .. } => {
+            let options = extract::Options::default();
+            cmd_extract(&overlay, &file_set, &output, matcher.as_ref(), &options, mode)
+        }
+    }
+}
 
-    let mut data = Vec::new();
-    reader.read_to_end(&mut data).expect("failed to read file");
+fn cmd_serve(builds: &[(String, PathBuf)]) -> Status {
+    let mut grouped: Vec<(&str, Vec<PathBuf>)> = Vec::new();
+    for (name, path) in builds {
+        match grouped.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, paths)) => paths.push(path.clone()),
+            None => grouped.push((name, vec![path.clone()])),
+        }
+    }
 
-    if cfg_parser::is_rapified(&data) {
-        match cfg_parser::RapFile::parse(&data) {
-            Ok(rap) => {
-                let decompiled = cfg_parser::decompile(&rap);
-                print!("{decompiled}");
-                return;
+    let builds = grouped
+        .into_iter()
+        .map(|(name, paths)| {
+            let (root, files) = mount_archives(&require_inputs(&paths));
+            serve::Build { name: name.to_string(), root, files }
+        })
+        .collect();
+
+    let server = serve::Server::new(builds);
+    match server.run(std::io::stdin().lock(), &mut std::io::stdout().lock()) {
+        Ok(()) => Status::Success,
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Status::Success,
+        Err(e) => {
+            eprintln!("{e}");
+            Status::Error
+        }
+    }
+}
+
+fn cmd_info(paths: &[PathBuf], mode: OutputMode) -> Status {
+    let mut status = Status::Success;
+    for path in paths {
+        let mut fields = Vec::new();
+        if let Err(e) = archive_info(path, &mut fields) {
+            eprintln!("{}: {e}", path.display());
+            status = Status::Error;
+            continue;
+        }
+
+        match mode {
+            OutputMode::Quiet => {}
+            OutputMode::Porcelain => {
+                for (key, value) in &fields {
+                    let mut stdout = std::io::stdout().lock();
+                    let _ = stdout.write_all(path.as_os_str().as_encoded_bytes());
+                    let _ = writeln!(stdout, "\t{key}\t{value}");
+                }
             }
-            Err(e) => {
-                eprintln!("Warning: rapified config parse failed: {e}");
+            OutputMode::Normal => {
+                println!("{}:", path.display());
+                for (key, value) in &fields {
+                    match key.split_once('.') {
+                        // Nested fields (e.g. PBO header extensions) are grouped under
+                        // their prefix, which is printed once
+                        Some((_, name)) => println!("    {name}: {value}"),
+                        None if value.is_empty() => println!("  {key}:"),
+                        None => println!("  {key}: {value}"),
+                    }
+                }
+                println!();
             }
         }
     }
 
-    let mut stdout = std::io::stdout().lock();
-    std::io::Write::write_all(&mut stdout, &data).expect("failed to write to stdout");
+    status
 }
 
-fn cmd_info(paths: &[PathBuf]) {
-    for path in paths {
-        let ext = path.extension().and_then(OsStr::to_str).unwrap_or("").to_ascii_lowercase();
-
-        println!("{}:", path.display());
+/// Collects an archive's metadata as `(key, value)` pairs. Keys of nested values are
+/// `prefix.name`, and follow a `prefix` key with an empty value.
+fn archive_info(path: &Path, fields: &mut Vec<(String, String)>) -> Result<(), String> {
+    let ext = path.extension().and_then(OsStr::to_str).unwrap_or("").to_ascii_lowercase();
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(|e| e.to_string())?;
+    let mut field = |key: &str, value: String| fields.push((key.to_string(), value));
 
-        match ext.as_str() {
-            "pbo" => match std::fs::File::open(path) {
-                Ok(file) => match unsafe { memmap2::Mmap::map(&file) } {
-                    Ok(mmap) => match dayz_pbo::PboFile::parse(&mmap) {
-                        Ok(pbo) => {
-                            println!("  type: PBO");
-                            println!("  entries: {}", pbo.entries.len());
-                            let total_size: u64 =
-                                pbo.entries.iter().map(|e| e.data_size as u64).sum();
-                            println!("  total_data_size: {total_size}");
-                            if !pbo.extensions.is_empty() {
-                                println!("  extensions:");
-                                for (k, v) in &pbo.extensions {
-                                    println!("    {k}: {v}");
-                                }
-                            }
-                            if let Some(checksum) = pbo.checksum {
-                                let hex: String =
-                                    checksum.iter().map(|b| format!("{b:02x}")).collect();
-                                println!("  sha1: {hex}");
-                            }
-                        }
-                        Err(e) => println!("  error: {e}"),
-                    },
-                    Err(e) => println!("  error: {e}"),
-                },
-                Err(e) => println!("  error: {e}"),
-            },
-            "pak" => match std::fs::File::open(path) {
-                Ok(file) => match unsafe { memmap2::Mmap::map(&file) } {
-                    Ok(mmap) => match enfusion_pak::PakFile::parse(&mmap) {
-                        Ok(pak) => {
-                            println!("  type: PAK");
-                            println!("  chunks: {}", pak.chunks().len());
-                            for chunk in pak.chunks() {
-                                println!("    {:?}", chunk.kind());
-                            }
-                        }
-                        Err(e) => println!("  error: {e}"),
-                    },
-                    Err(e) => println!("  error: {e}"),
-                },
-                Err(e) => println!("  error: {e}"),
-            },
-            _ => println!("  unsupported format"),
+    match ext.as_str() {
+        "pbo" => {
+            let pbo = dayz_pbo::PboFile::parse(&mmap).map_err(|e| e.to_string())?;
+            field("type", "PBO".to_string());
+            field("entries", pbo.entries.len().to_string());
+            let total_size: u64 = pbo.entries.iter().map(|e| e.data_size as u64).sum();
+            field("total_data_size", total_size.to_string());
+            if !pbo.extensions.is_empty() {
+                field("extensions", String::new());
+                for (k, v) in &pbo.extensions {
+                    field(&format!("extensions.{k}"), v.to_string());
+                }
+            }
+            if let Some(checksum) = pbo.checksum {
+                field("sha1", checksum.iter().map(|b| format!("{b:02x}")).collect());
+            }
         }
-        println!();
+        "pak" => {
+            let pak = enfusion_pak::PakFile::parse(&mmap).map_err(|e| e.to_string())?;
+            field("type", "PAK".to_string());
+            field("chunks", pak.chunks().len().to_string());
+            for (idx, chunk) in pak.chunks().iter().enumerate() {
+                field(&format!("chunks.{idx}"), format!("{:?}", chunk.kind()));
+            }
+        }
+        _ => return Err("unsupported format".to_string()),
     }
+
+    Ok(())
 }
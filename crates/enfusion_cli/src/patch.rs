@@ -0,0 +1,107 @@
+//! Building a pak which holds only the files that differ between two builds.
+//!
+//! Loaded after the base build, a patch pak's files take precedence, giving the modified
+//! build's contents without shipping the whole archive again. A pak has no way to mark a
+//! file as deleted, so files missing from the modified build are reported but can't be
+//! carried by the patch.
+
+use std::collections::HashSet;
+use std::io;
+use std::io::Read;
+
+use enfusion_pak::Chunk;
+use enfusion_pak::FileEntry;
+use enfusion_pak::PakFile;
+use enfusion_pak::RcFileEntry;
+use enfusion_pak::diff::ChangeKind;
+use enfusion_pak::diff::FileChange;
+use enfusion_pak::diff::FileSummary;
+use enfusion_pak::diff::diff_listings;
+use enfusion_pak::signing::SigningKey;
+use enfusion_pak::writer::WriteOptions;
+use jiff::civil::DateTime;
+use vfs::VfsPath;
+
+pub struct Options {
+    /// Embed a manifest of the patch's files.
+    pub manifest: bool,
+    /// Sign the manifest with this key. Embeds a manifest even if `manifest` is false.
+    pub signing_key: Option<SigningKey>,
+    pub write: WriteOptions,
+}
+
+pub struct Patch {
+    /// Holds every added and changed file. They're compressed when it's written if
+    /// [`Options::write`] says to.
+    pub pak: PakFile,
+    /// Every difference found, in path order. Removed files aren't in the patch.
+    pub changes: Vec<FileChange>,
+}
+
+impl Patch {
+    /// Whether the patch holds any files.
+    pub fn is_empty(&self) -> bool {
+        self.changes.iter().all(|change| change.kind == ChangeKind::Removed)
+    }
+}
+
+/// Compares the files in `modified` which `include` accepts against `base`. Added and
+/// changed files are stored in the patch with `modified_time` as their timestamp.
+pub fn build(
+    base: &VfsPath,
+    base_files: &HashSet<String>,
+    modified: &VfsPath,
+    modified_files: &HashSet<String>,
+    include: impl Fn(&str) -> bool,
+    modified_time: DateTime,
+) -> io::Result<Patch> {
+    let changes = diff_listings(
+        summarize(base, base_files, &include)?,
+        summarize(modified, modified_files, &include)?,
+    );
+
+    let mut pak = PakFile::new();
+    let Some(Chunk::File { fs }) = pak.file_chunk_mut() else {
+        unreachable!("new paks have a FILE chunk");
+    };
+    let root = RcFileEntry::make_mut(fs);
+
+    for FileChange { path, .. } in changes.iter().filter(|c| c.kind != ChangeKind::Removed) {
+        let data = read_file(modified, path)?;
+        let relative = path.trim_start_matches('/');
+        let (dir, name) = relative.rsplit_once('/').unwrap_or(("", relative));
+        root.insert(dir, FileEntry::new_file(name, data, modified_time))
+            .map_err(|e| io::Error::other(format!("Cannot add {path} to the patch: {e}")))?;
+    }
+
+    Ok(Patch { pak, changes })
+}
+
+/// Summarizes each of `files` which `include` accepts by its length and contents, so that
+/// files which changed without changing size are still found.
+fn summarize(
+    root: &VfsPath,
+    files: &HashSet<String>,
+    include: impl Fn(&str) -> bool,
+) -> io::Result<Vec<(String, FileSummary)>> {
+    files
+        .iter()
+        .filter(|path| include(path))
+        .map(|path| {
+            let file = root
+                .join(path)
+                .and_then(|file| file.open_file())
+                .map_err(|e| io::Error::other(format!("Cannot open file: {path} ({e})")))?;
+            Ok((path.clone(), FileSummary::read_from(file)?))
+        })
+        .collect()
+}
+
+fn read_file(root: &VfsPath, path: &str) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    root.join(path)
+        .and_then(|file| file.open_file())
+        .map_err(|e| io::Error::other(format!("Cannot open file: {path} ({e})")))?
+        .read_to_end(&mut data)?;
+    Ok(data)
+}
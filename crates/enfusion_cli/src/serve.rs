@@ -0,0 +1,279 @@
+//! `enfusion serve`: answering JSON-RPC 2.0 requests about loaded builds, so dashboards and
+//! bots can search them without scraping `grep` output.
+//!
+//! Requests are read from stdin and responses written to stdout, one JSON object per line.
+//! Notifications (requests without an `id`) are run but not answered. Methods:
+//!
+//! - `builds`: the names of the loaded builds.
+//! - `search` with `{build, pattern, ignore_case?, glob?, extensions?, context?,
+//!   max_results?}`: regex matches as `{matches: [{path, line, before, text, after}],
+//!   files_scanned, files_matched, truncated}`. Files are chosen as `grep` chooses them.
+//! - `read` with `{build, path, offset?, length?}`: the file's contents as `{path, size,
+//!   offset, text}`, or with `hex` in place of `text` if the requested bytes aren't UTF-8.
+//!   Rapified configs are decompiled first, so line numbers agree with `search`.
+
+use std::collections::HashSet;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::ops::ControlFlow;
+
+use enfusion_pak::signing;
+use enfusion_search::Searcher;
+use globset::Glob;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use serde_json::json;
+use vfs::VfsPath;
+
+/// Matches returned by `search` when the request doesn't give `max_results`.
+const DEFAULT_MAX_RESULTS: usize = 1000;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The requested file isn't in the build.
+const FILE_NOT_FOUND: i64 = -32001;
+/// The file is in the build, but reading it failed.
+const READ_FAILED: i64 = -32002;
+
+/// A mounted set of archives, addressed by name in requests.
+pub struct Build {
+    pub name: String,
+    pub root: VfsPath,
+    pub files: HashSet<String>,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    id: Option<Value>,
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into() }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchParams {
+    build: String,
+    pattern: String,
+    #[serde(default)]
+    ignore_case: bool,
+    glob: Option<String>,
+    extensions: Option<Vec<String>>,
+    #[serde(default)]
+    context: usize,
+    max_results: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct SearchResult {
+    matches: Vec<Match>,
+    files_scanned: usize,
+    files_matched: usize,
+    /// Whether the search stopped at `max_results`.
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct Match {
+    path: String,
+    line: usize,
+    before: Vec<String>,
+    text: String,
+    after: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReadParams {
+    build: String,
+    path: String,
+    #[serde(default)]
+    offset: usize,
+    length: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct ReadResult {
+    path: String,
+    /// Size of the whole file, after decompiling.
+    size: usize,
+    offset: usize,
+    #[serde(flatten)]
+    contents: Contents,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Contents {
+    Text(String),
+    Hex(String),
+}
+
+pub struct Server {
+    builds: Vec<Build>,
+}
+
+impl Server {
+    pub fn new(builds: Vec<Build>) -> Self {
+        Server { builds }
+    }
+
+    /// Answers requests from `input` until it ends.
+    pub fn run(&self, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                serde_json::to_writer(&mut *output, &response)?;
+                output.write_all(b"\n")?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The response to one line of input, or `None` for a notification.
+    fn handle(&self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, e.to_string());
+                return Some(error_response(Value::Null, error));
+            }
+        };
+        let request: Request = match serde_json::from_value(request) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = RpcError::new(INVALID_REQUEST, e.to_string());
+                return Some(error_response(Value::Null, error));
+            }
+        };
+        if request.jsonrpc != "2.0" {
+            let error = RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\"");
+            return Some(error_response(request.id.unwrap_or_default(), error));
+        }
+
+        let result = self.call(&request.method, request.params);
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "builds" => Ok(json!(self.builds.iter().map(|b| &b.name).collect::<Vec<_>>())),
+            "search" => self.search(parse_params(params)?).map(|result| json!(result)),
+            "read" => self.read(parse_params(params)?).map(|result| json!(result)),
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method {method:?}"))),
+        }
+    }
+
+    fn build(&self, name: &str) -> Result<&Build, RpcError> {
+        self.builds
+            .iter()
+            .find(|build| build.name == name)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("no build named {name:?}")))
+    }
+
+    fn search(&self, params: SearchParams) -> Result<SearchResult, RpcError> {
+        let build = self.build(&params.build)?;
+        let regex = regex::bytes::RegexBuilder::new(&params.pattern)
+            .case_insensitive(params.ignore_case)
+            .build()
+            .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid regex: {e}")))?;
+        let glob = params
+            .glob
+            .as_deref()
+            .map(|glob| Glob::new(glob).map(|glob| glob.compile_matcher()))
+            .transpose()
+            .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid glob: {e}")))?;
+        let max_results = params.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+
+        let files =
+            crate::grep_candidates(&build.root, &build.files, glob.as_ref(), params.extensions);
+        let mut matches = Vec::new();
+        let mut truncated = false;
+        let stats =
+            Searcher::new(regex).context(params.context).search_files(files, |file, found| {
+                for found in found {
+                    if matches.len() == max_results {
+                        truncated = true;
+                        return ControlFlow::Break(());
+                    }
+                    matches.push(Match {
+                        path: file.as_str().to_string(),
+                        line: found.line,
+                        before: found.before,
+                        text: found.text,
+                        after: found.after,
+                    });
+                }
+                ControlFlow::Continue(())
+            });
+
+        Ok(SearchResult {
+            matches,
+            files_scanned: stats.files_scanned,
+            files_matched: stats.files_matched,
+            truncated,
+        })
+    }
+
+    fn read(&self, params: ReadParams) -> Result<ReadResult, RpcError> {
+        let build = self.build(&params.build)?;
+        let path = format!("/{}", params.path.trim_start_matches('/'));
+        if !build.files.contains(&path) {
+            return Err(RpcError::new(FILE_NOT_FOUND, format!("no file at {path}")));
+        }
+
+        let mut data = Vec::new();
+        crate::cat_file(&build.root, &path, &mut data)
+            .map_err(|e| RpcError::new(READ_FAILED, e.to_string()))?;
+
+        let size = data.len();
+        let start = params.offset.min(size);
+        let end = params.length.map_or(size, |length| start.saturating_add(length).min(size));
+        let bytes = &data[start..end];
+        // A range can split a character, so this is decided per request rather than per file
+        let contents = match std::str::from_utf8(bytes) {
+            Ok(text) => Contents::Text(text.to_string()),
+            Err(_) => Contents::Hex(signing::to_hex(bytes)),
+        };
+
+        Ok(ReadResult { path, size, offset: start, contents })
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": error.code, "message": error.message },
+        "id": id,
+    })
+}
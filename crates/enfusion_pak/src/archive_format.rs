@@ -0,0 +1,107 @@
+//! Telling which kind of archive a file is, so callers can pick the backend which opens it.
+//!
+//! The leading bytes are checked first so renamed archives are still recognized, falling
+//! back to the file extension for formats without a reliable magic number.
+
+/// Number of leading bytes [`ArchiveFormat::detect`] looks at.
+pub const HEADER_LEN: usize = 12;
+
+/// An archive format with a backend that can mount it as a VFS. PBOs are mounted by the
+/// `dayz_pbo` crate rather than this one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// Enfusion `.pak` (Arma Reforger, Arma 4)
+    Pak,
+    /// Real Virtuality `.pbo` (DayZ, Arma 3), including compressed entries
+    Pbo,
+    /// Loose mod data distributed as a `.zip`
+    #[cfg(feature = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub const ALL: &[ArchiveFormat] = &[
+        ArchiveFormat::Pak,
+        ArchiveFormat::Pbo,
+        #[cfg(feature = "zip")]
+        ArchiveFormat::Zip,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ArchiveFormat::Pak => "PAK",
+            ArchiveFormat::Pbo => "PBO",
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => "ZIP",
+        }
+    }
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ArchiveFormat::Pak => &["pak"],
+            ArchiveFormat::Pbo => &["pbo"],
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => &["zip"],
+        }
+    }
+
+    /// `(offset, bytes)` pairs which identify the format when present in the header.
+    fn magic(self) -> &'static [(usize, &'static [u8])] {
+        match self {
+            ArchiveFormat::Pak => &[(0, b"FORM"), (8, b"PAC1")],
+            // An empty file name followed by the "Vers" packing method. PBOs without a
+            // header extension entry start with a file name and can't be recognized.
+            ArchiveFormat::Pbo => &[(0, b"\0sreV")],
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => &[(0, b"PK\x03\x04")],
+        }
+    }
+
+    /// Every extension with a backend, for file dialogs.
+    pub fn all_extensions() -> impl Iterator<Item = &'static str> {
+        Self::ALL.iter().flat_map(|format| format.extensions().iter().copied())
+    }
+
+    /// Guesses the format from a file name's extension.
+    pub fn from_extension(name: &str) -> Option<Self> {
+        let (_, ext) = name.rsplit_once('.')?;
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.extensions().iter().any(|known| known.eq_ignore_ascii_case(ext)))
+    }
+
+    /// Recognizes a format from an archive's first [`HEADER_LEN`] bytes.
+    pub fn sniff(header: &[u8]) -> Option<Self> {
+        Self::ALL.iter().copied().find(|format| {
+            format.magic().iter().all(|(offset, bytes)| {
+                header.get(*offset..offset + bytes.len()).is_some_and(|found| found == *bytes)
+            })
+        })
+    }
+
+    /// Detects an archive's format from its header, falling back to its name.
+    pub fn detect(name: &str, header: &[u8]) -> Option<Self> {
+        Self::sniff(header).or_else(|| Self::from_extension(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_wins_over_extension() {
+        let pak = b"FORM\0\0\0\x10PAC1";
+        assert_eq!(ArchiveFormat::detect("data.pbo", pak), Some(ArchiveFormat::Pak));
+
+        let pbo = b"\0sreV\0\0\0\0\0\0\0";
+        assert_eq!(ArchiveFormat::detect("data.bin", pbo), Some(ArchiveFormat::Pbo));
+
+        assert_eq!(ArchiveFormat::detect("data.PBO", b"config.cpp\0"), Some(ArchiveFormat::Pbo));
+        #[cfg(feature = "zip")]
+        assert_eq!(ArchiveFormat::detect("mod.pak", b"PK\x03\x04"), Some(ArchiveFormat::Zip));
+        #[cfg(not(feature = "zip"))]
+        assert_eq!(ArchiveFormat::detect("data.zip", b"PK\x03\x04"), None);
+    }
+}
@@ -1,6 +1,12 @@
 use async_trait::async_trait;
 use std::fmt::Debug;
+use std::future::Future;
+use std::io::SeekFrom;
 use std::ops::Range;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::task::ready;
 use vfs::VfsError;
 use vfs::VfsMetadata;
 use vfs::VfsResult;
@@ -9,10 +15,13 @@ use vfs::async_vfs::SeekAndRead;
 use vfs::error::VfsErrorKind;
 
 use crate::PakFile;
+use crate::pak_vfs::PRIME_CHUNK_LEN;
+use crate::pak_vfs::PakFileMeta;
 use crate::pak_vfs::PakVfs;
 
+use futures::io::AsyncRead;
+use futures::io::AsyncSeek;
 use futures::io::AsyncWrite;
-use futures::io::Cursor;
 use futures::stream::Stream;
 
 /// Trait which allows for requesting a file be asynchronously read into memory.
@@ -28,12 +37,237 @@ pub trait AsyncReadAt {
     async fn read_at(&self, file_range: Range<usize>) -> Result<impl AsRef<[u8]>, VfsError>;
 }
 
+type PrimeFuture = Pin<Box<dyn Future<Output = std::io::Result<Vec<u8>>> + Send>>;
+
+/// Reads one file out of a pak asynchronously without holding all of it in memory.
+///
+/// The async counterpart of [`PakFileReader`](crate::pak_vfs::PakFileReader): stored bytes
+/// are primed a chunk at a time and compressed files are inflated as they're read. Seeking
+/// backwards in a compressed file starts inflating again from the beginning.
+pub struct AsyncPakFileReader<T> {
+    source: T,
+    range: Range<usize>,
+    /// Start of the next stored chunk to prime.
+    stored_pos: usize,
+    /// The last primed chunk, read up to `input_pos`.
+    input: Vec<u8>,
+    input_pos: usize,
+    priming: Option<PrimeFuture>,
+    /// `None` for files which are stored uncompressed.
+    inflater: Option<flate2::Decompress>,
+    inflated_all: bool,
+    /// Position in the decompressed file.
+    pos: u64,
+    len: u64,
+    /// Where a seek which hasn't finished skipping through a compressed file is going.
+    seeking: Option<u64>,
+}
+
+impl<T> AsyncPakFileReader<T>
+where
+    T: std::ops::Deref + Clone + Send + Sync + 'static,
+    T::Target: AsyncPrime + Sync,
+{
+    /// Creates a reader for the file `meta` describes. `source` is the pak it's in.
+    pub fn new(source: T, meta: &PakFileMeta) -> Self {
+        let start = meta.offset as usize;
+        let range = start..start + meta.compressed_len as usize;
+        Self {
+            source,
+            stored_pos: range.start,
+            range,
+            input: Vec::new(),
+            input_pos: 0,
+            priming: None,
+            inflater: (meta.compressed != 0).then(|| flate2::Decompress::new(true)),
+            inflated_all: false,
+            pos: 0,
+            len: meta.decompressed_len as u64,
+            seeking: None,
+        }
+    }
+
+    /// Makes sure there are primed bytes left to read. Returns `false` at the end of the
+    /// stored range.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<bool>> {
+        if self.input_pos < self.input.len() {
+            return Poll::Ready(Ok(true));
+        }
+        if self.stored_pos >= self.range.end {
+            return Poll::Ready(Ok(false));
+        }
+
+        if self.priming.is_none() {
+            let source = self.source.clone();
+            let range = self.stored_pos..self.range.end.min(self.stored_pos + PRIME_CHUNK_LEN);
+            self.priming = Some(Box::pin(async move {
+                let data = AsyncPrime::prime_file(&*source, range)
+                    .await
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
+                Ok(data.as_ref().to_vec())
+            }));
+        }
+        let primed = ready!(self.priming.as_mut().expect("priming").as_mut().poll(cx));
+        self.priming = None;
+
+        let primed = primed?;
+        if primed.is_empty() {
+            return Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("pak ended at {:#X}, before the end of the file", self.stored_pos),
+            )));
+        }
+        self.stored_pos += primed.len();
+        self.input = primed;
+        self.input_pos = 0;
+
+        Poll::Ready(Ok(true))
+    }
+
+    fn poll_read_inner(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        if buf.is_empty() || self.inflated_all {
+            return Poll::Ready(Ok(0));
+        }
+
+        loop {
+            let more = ready!(self.poll_fill(cx))?;
+            let input = &self.input[self.input_pos..];
+            let Some(inflater) = &mut self.inflater else {
+                let read = input.len().min(buf.len());
+                buf[..read].copy_from_slice(&input[..read]);
+                self.input_pos += read;
+                self.pos += read as u64;
+                return Poll::Ready(Ok(read));
+            };
+
+            let (in_before, out_before) = (inflater.total_in(), inflater.total_out());
+            let flush =
+                if more { flate2::FlushDecompress::None } else { flate2::FlushDecompress::Finish };
+            let status = inflater.decompress(input, buf, flush).map_err(std::io::Error::other)?;
+            let consumed = (inflater.total_in() - in_before) as usize;
+            let read = (inflater.total_out() - out_before) as usize;
+            self.input_pos += consumed;
+            self.pos += read as u64;
+
+            if status == flate2::Status::StreamEnd {
+                self.inflated_all = true;
+                return Poll::Ready(Ok(read));
+            }
+            if read > 0 {
+                return Poll::Ready(Ok(read));
+            }
+            if !more || consumed == 0 {
+                return Poll::Ready(Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "compressed file ends early",
+                )));
+            }
+        }
+    }
+
+    /// Goes back to the start of a compressed file.
+    fn rewind_inflater(&mut self) {
+        if let Some(inflater) = &mut self.inflater {
+            inflater.reset(true);
+        }
+        self.stored_pos = self.range.start;
+        self.input.clear();
+        self.input_pos = 0;
+        self.priming = None;
+        self.inflated_all = false;
+        self.pos = 0;
+    }
+}
+
+impl<T> AsyncRead for AsyncPakFileReader<T>
+where
+    T: std::ops::Deref + Clone + Send + Sync + Unpin + 'static,
+    T::Target: AsyncPrime + Sync,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.get_mut().poll_read_inner(cx, buf)
+    }
+}
+
+impl<T> AsyncSeek for AsyncPakFileReader<T>
+where
+    T: std::ops::Deref + Clone + Send + Sync + Unpin + 'static,
+    T::Target: AsyncPrime + Sync,
+{
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        from: SeekFrom,
+    ) -> Poll<std::io::Result<u64>> {
+        let this = self.get_mut();
+        let target = match this.seeking {
+            Some(target) => target,
+            None => {
+                let target = match from {
+                    SeekFrom::Start(offset) => Some(offset),
+                    SeekFrom::End(offset) => this.len.checked_add_signed(offset),
+                    SeekFrom::Current(offset) => this.pos.checked_add_signed(offset),
+                };
+                let Some(target) = target else {
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "invalid seek to a negative or overflowing position",
+                    )));
+                };
+
+                if this.inflater.is_none() {
+                    this.stored_pos =
+                        this.range.start.saturating_add(target.min(this.len) as usize);
+                    this.input.clear();
+                    this.input_pos = 0;
+                    this.priming = None;
+                    this.pos = target;
+                    return Poll::Ready(Ok(target));
+                }
+
+                if target < this.pos {
+                    this.rewind_inflater();
+                }
+                this.seeking = Some(target);
+                target
+            }
+        };
+
+        // Inflate and discard everything up to the target
+        let mut skipped = [0; 8 * 1024];
+        while this.pos < target {
+            let want = ((target - this.pos) as usize).min(skipped.len());
+            match ready!(this.poll_read_inner(cx, &mut skipped[..want])) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    this.seeking = None;
+                    return Poll::Ready(Err(e));
+                }
+            }
+        }
+        // Seeking past the end is allowed, reads there just return nothing
+        this.seeking = None;
+        this.pos = target;
+
+        Poll::Ready(Ok(target))
+    }
+}
+
 /// Asynchronous VFS implementation for reading a `.pak` file.
 #[async_trait]
 impl<T> AsyncFileSystem for PakVfs<T>
 where
-    T: std::ops::Deref + Sync + Send + Debug + 'static,
-    T::Target: AsRef<PakFile> + AsyncPrime,
+    T: std::ops::Deref + Clone + Sync + Send + Unpin + Debug + 'static,
+    T::Target: AsRef<PakFile> + AsyncPrime + Sync,
 {
     async fn read_dir(
         &self,
@@ -52,27 +286,7 @@ where
             return Err(VfsError::from(VfsErrorKind::Other("not a file".into())));
         };
 
-        let mut data = Vec::with_capacity(meta.decompressed_len as usize);
-        let data_start = meta.offset as usize;
-        let data_end = data_start + meta.compressed_len as usize;
-
-        let primed_file = self.source.prime_file(data_start..data_end).await?;
-        let source_slice: &[u8] = primed_file.as_ref();
-        let mut source_range = source_slice;
-        if meta.compressed != 0 {
-            let mut decoder = flate2::read::ZlibDecoder::new(source_range);
-            std::io::copy(&mut decoder, &mut data).map_err(|err| {
-                println!("error occurred during decompression: {err:#?}");
-                println!("offset: {:#X?}", meta.offset);
-                VfsError::from(VfsErrorKind::IoError(err))
-            })?;
-
-            Ok(Box::new(Cursor::new(data)))
-        } else {
-            let _ = std::io::copy(&mut source_range, &mut data);
-
-            Ok(Box::new(Cursor::new(data)))
-        }
+        Ok(Box::new(AsyncPakFileReader::new(self.source.clone(), meta)))
     }
 
     async fn create_file(&self, _path: &str) -> VfsResult<Box<dyn AsyncWrite + Send + Unpin>> {
@@ -135,3 +349,120 @@ where
         Err(VfsErrorKind::NotSupported.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use futures::AsyncReadExt;
+    use futures::AsyncSeekExt;
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::wrappers::async_reader::parse_pak_file;
+    use crate::wrappers::faulty_source::FaultySource;
+    use crate::wrappers::faulty_source::sample_files;
+    use crate::wrappers::faulty_source::sample_pak;
+
+    struct Bytes(Vec<u8>);
+
+    #[async_trait]
+    impl AsyncPrime for Bytes {
+        async fn prime_file(&self, file_range: Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
+            Ok(&self.0[file_range])
+        }
+    }
+
+    /// A file long enough to need several primed chunks.
+    fn contents() -> Vec<u8> {
+        (0..PRIME_CHUNK_LEN * 3).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn reader(stored: &[u8], compressed: bool) -> AsyncPakFileReader<Arc<Bytes>> {
+        let mut pak = b"PAK!".to_vec();
+        pak.extend_from_slice(stored);
+        let meta = PakFileMeta {
+            offset: 4,
+            compressed_len: stored.len() as u32,
+            decompressed_len: contents().len() as u32,
+            compressed: compressed as u8,
+            timestamp: 0,
+        };
+        AsyncPakFileReader::new(Arc::new(Bytes(pak)), &meta)
+    }
+
+    async fn check_reads(mut reader: AsyncPakFileReader<Arc<Bytes>>) {
+        let expected = contents();
+
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).await.unwrap();
+        assert!(all == expected, "contents differ");
+
+        let mut buf = [0; 16];
+        reader.seek(SeekFrom::Start(PRIME_CHUNK_LEN as u64 + 10)).await.unwrap();
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected[PRIME_CHUNK_LEN + 10..][..16]);
+
+        reader.seek(SeekFrom::Current(-100)).await.unwrap();
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected[PRIME_CHUNK_LEN + 10 + 16 - 100..][..16]);
+
+        assert_eq!(reader.seek(SeekFrom::End(-16)).await.unwrap(), expected.len() as u64 - 16);
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected[expected.len() - 16..]);
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+
+        reader.seek(SeekFrom::End(10)).await.unwrap();
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-(expected.len() as i64) - 20)).await.is_err());
+    }
+
+    #[test]
+    fn streams_stored_files() {
+        block_on(check_reads(reader(&contents(), false)));
+    }
+
+    #[test]
+    fn streams_compressed_files() {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&contents()).unwrap();
+        block_on(check_reads(reader(&encoder.finish().unwrap(), true)));
+    }
+
+    #[test]
+    fn truncated_compressed_files_fail_to_read() {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&contents()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut reader = reader(&compressed[..compressed.len() / 2], true);
+        let mut all = Vec::new();
+        assert!(block_on(reader.read_to_end(&mut all)).is_err());
+    }
+
+    #[test]
+    fn opens_files_without_reading_all_of_them() {
+        let source = FaultySource::new(sample_pak()).max_read_len(4096);
+        let wrapper = block_on(parse_pak_file(PathBuf::from("test.pak"), source.clone())).unwrap();
+        let vfs = PakVfs::new(Arc::new(wrapper)).unwrap();
+
+        // Only the first chunk of the large file is read to get at its start
+        let parse_reads = source.reads();
+        let mut reader = block_on(vfs.open_file("/worlds/large.bin")).unwrap();
+        let mut buf = [0; 16];
+        block_on(reader.read_exact(&mut buf)).unwrap();
+        assert_eq!(buf[..], sample_files()[2].1[..16]);
+        assert_eq!(source.reads() - parse_reads, PRIME_CHUNK_LEN / 4096);
+
+        for (path, expected) in sample_files() {
+            let mut read = Vec::new();
+            let mut reader = block_on(vfs.open_file(&format!("/{path}"))).unwrap();
+            block_on(reader.read_to_end(&mut read)).unwrap();
+            assert_eq!(read, expected, "{path}");
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Finding the files added, removed and changed between two builds.
+//!
+//! Builds are compared by listings of what they record about each file ([`FileSummary`]),
+//! so nothing has to be read to find the candidates. Nothing here does I/O: sync callers
+//! can summarize files with [`FileSummary::read_from`], and async callers feed a
+//! [`ContentHasher`] from their own readers.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+use xxhash_rust::xxh3::Xxh3;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// What a build records about a file. Two files with equal summaries are taken to be the
+/// same, so a summary holding only `len` can't tell apart edits which keep the size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileSummary {
+    /// Length of the contents, decompressed.
+    pub len: u64,
+    /// Only known for files in paks.
+    pub compressed_len: Option<u32>,
+    /// The packed modification time. Only known for files in paks.
+    pub timestamp: Option<u32>,
+    /// [`content_hash`] of the contents, if they've been read.
+    pub hash: Option<u64>,
+}
+
+impl FileSummary {
+    /// A summary of a file known only by its length.
+    pub fn with_len(len: u64) -> Self {
+        FileSummary { len, compressed_len: None, timestamp: None, hash: None }
+    }
+
+    /// The length and content hash of everything `reader` yields.
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let mut hasher = ContentHasher::new();
+        let mut buf = [0u8; 8192];
+        let mut len = 0;
+        loop {
+            let read = reader.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+            len += read as u64;
+        }
+        Ok(FileSummary { hash: Some(hasher.finish()), ..FileSummary::with_len(len) })
+    }
+}
+
+#[cfg(feature = "vfs")]
+impl From<&crate::pak_vfs::PakFileMeta> for FileSummary {
+    fn from(meta: &crate::pak_vfs::PakFileMeta) -> Self {
+        FileSummary {
+            len: u64::from(meta.decompressed_len),
+            compressed_len: Some(meta.compressed_len),
+            timestamp: Some(meta.timestamp),
+            hash: None,
+        }
+    }
+}
+
+/// How a file differs between the base and modified builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Only in the modified build.
+    Added,
+    /// Only in the base build.
+    Removed,
+    /// In both builds, with different summaries.
+    Changed,
+}
+
+impl ChangeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        }
+    }
+}
+
+/// A file which differs between two builds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// The file's summary in the base build, unless it was added.
+    pub base: Option<FileSummary>,
+    /// The file's summary in the modified build, unless it was removed.
+    pub modified: Option<FileSummary>,
+}
+
+impl FileChange {
+    /// Whether a changed file's contents could still be the same, because its summaries
+    /// differ only in metadata. Comparing the [`content_hash`] of each copy settles it.
+    pub fn may_be_metadata_only(&self) -> bool {
+        match (self.base, self.modified) {
+            (Some(base), Some(modified)) => {
+                base.len == modified.len
+                    && !matches!((base.hash, modified.hash), (Some(a), Some(b)) if a != b)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Compares the listings of two builds, each a path and summary per file, returning every
+/// file which was added, removed or has a different summary, in path order.
+///
+/// # Examples
+///
+/// ```
+/// use enfusion_pak::diff::ChangeKind;
+/// use enfusion_pak::diff::FileSummary;
+/// use enfusion_pak::diff::diff_listings;
+///
+/// let len = FileSummary::with_len;
+/// let base = [("/a.c", len(1)), ("/b.c", len(2))];
+/// let modified = [("/b.c", len(3)), ("/c.c", len(4))];
+///
+/// let changes: Vec<(String, ChangeKind)> = diff_listings(base, modified)
+///     .into_iter()
+///     .map(|change| (change.path, change.kind))
+///     .collect();
+/// assert_eq!(
+///     changes,
+///     [
+///         ("/a.c".to_string(), ChangeKind::Removed),
+///         ("/b.c".to_string(), ChangeKind::Changed),
+///         ("/c.c".to_string(), ChangeKind::Added),
+///     ]
+/// );
+/// ```
+pub fn diff_listings<P: Into<String>>(
+    base: impl IntoIterator<Item = (P, FileSummary)>,
+    modified: impl IntoIterator<Item = (P, FileSummary)>,
+) -> Vec<FileChange> {
+    let mut files: BTreeMap<String, (Option<FileSummary>, Option<FileSummary>)> = BTreeMap::new();
+    for (path, summary) in base {
+        files.entry(path.into()).or_default().0 = Some(summary);
+    }
+    for (path, summary) in modified {
+        files.entry(path.into()).or_default().1 = Some(summary);
+    }
+
+    files
+        .into_iter()
+        .filter_map(|(path, (base, modified))| {
+            let kind = match (base, modified) {
+                (Some(base), Some(modified)) if base == modified => return None,
+                (Some(_), Some(_)) => ChangeKind::Changed,
+                (Some(_), None) => ChangeKind::Removed,
+                (None, _) => ChangeKind::Added,
+            };
+            Some(FileChange { path, kind, base, modified })
+        })
+        .collect()
+}
+
+/// Writes a listing as a manifest: a `path<TAB>len<TAB>hash` line per file, sorted by path,
+/// with the [`content_hash`] as 16 hex digits or `-` if it isn't known.
+///
+/// The format is fixed so the manifests of two builds can be compared with `diff` or
+/// `git diff`.
+///
+/// # Examples
+///
+/// ```
+/// use enfusion_pak::diff::FileSummary;
+/// use enfusion_pak::diff::content_hash;
+/// use enfusion_pak::diff::write_manifest;
+///
+/// let listing = [
+///     ("/b.c", FileSummary::with_len(7)),
+///     ("/a.c", FileSummary { hash: Some(content_hash(b"abc")), ..FileSummary::with_len(3) }),
+/// ];
+/// let mut manifest = Vec::new();
+/// write_manifest(listing, &mut manifest).unwrap();
+/// assert_eq!(
+///     String::from_utf8(manifest).unwrap(),
+///     "/a.c\t3\t78af5f94892f3950\n/b.c\t7\t-\n"
+/// );
+/// ```
+pub fn write_manifest<P: AsRef<str>>(
+    listing: impl IntoIterator<Item = (P, FileSummary)>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut listing: Vec<(P, FileSummary)> = listing.into_iter().collect();
+    listing.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+    for (path, summary) in listing {
+        write!(out, "{}\t{}\t", path.as_ref(), summary.len)?;
+        match summary.hash {
+            Some(hash) => writeln!(out, "{hash:016x}")?,
+            None => writeln!(out, "-")?,
+        }
+    }
+    Ok(())
+}
+
+/// XXH3-64 of a file's contents, fed a chunk at a time.
+#[derive(Clone)]
+pub struct ContentHasher(Xxh3);
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentHasher {
+    pub fn new() -> Self {
+        ContentHasher(Xxh3::new())
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0.digest()
+    }
+}
+
+/// XXH3-64 of `data`, the same as feeding it all to a [`ContentHasher`].
+pub fn content_hash(data: &[u8]) -> u64 {
+    xxh3_64(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_changes_by_summary() {
+        let summary = |data: &[u8]| FileSummary::read_from(data).unwrap();
+        let base = [("a.c", summary(b"one")), ("b.c", summary(b"two")), ("c.c", summary(b"x"))];
+        let modified = [("a.c", summary(b"one")), ("b.c", summary(b"owt"))];
+
+        let changes = diff_listings(base, modified);
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].path.as_str(), changes[0].kind), ("b.c", ChangeKind::Changed));
+        assert!(!changes[0].may_be_metadata_only());
+        assert_eq!((changes[1].path.as_str(), changes[1].kind), ("c.c", ChangeKind::Removed));
+        assert_eq!(changes[1].base.unwrap().hash, Some(content_hash(b"x")));
+
+        let stamped = |timestamp| FileSummary {
+            compressed_len: Some(3),
+            timestamp: Some(timestamp),
+            ..FileSummary::with_len(3)
+        };
+        let touched = diff_listings([("a.c", stamped(1))], [("a.c", stamped(2))]);
+        assert_eq!(touched[0].kind, ChangeKind::Changed);
+        assert!(touched[0].may_be_metadata_only());
+    }
+}
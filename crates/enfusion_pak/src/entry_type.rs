@@ -0,0 +1,234 @@
+//! Guessing what kind of content an archive entry holds.
+//!
+//! Detection is table driven: each [`EntryType`] has a list of extensions and optional
+//! magic numbers. Magic numbers win when the entry's leading bytes are available since
+//! extensions in game data aren't always reliable (e.g. `config.bin` may or may not be
+//! rapified).
+
+/// Number of leading bytes [`detect_type`] needs to recognize every known magic number.
+pub const HEADER_LEN: usize = 12;
+
+/// Broad category of an archive entry's contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EntryType {
+    /// Enforce Script source (`.c`)
+    Script,
+    /// Text config (`.conf`, `config.cpp`, ...)
+    Config,
+    /// Binarized ("rapified") config
+    RapifiedConfig,
+    /// UI layout (`.layout`)
+    Layout,
+    /// Entity template / prefab (`.et`)
+    Prefab,
+    /// World / scene data (`.ent`, `.smap`)
+    World,
+    /// Resource metadata (`.meta`)
+    Meta,
+    /// Material (`.emat`)
+    Material,
+    /// Texture or image (`.edds`, `.dds`, `.paa`, `.png`, ...)
+    Texture,
+    /// Audio clip or project (`.wav`, `.ogg`, `.acp`, ...)
+    Audio,
+    /// Mesh (`.xob`, `.p3d`)
+    Model,
+    /// Binary animation data (`.anm`, `.rtm`)
+    Animation,
+    /// Text animation graphs and sets (`.agr`, `.ast`, `.asi`, ...)
+    AnimationGraph,
+    /// Localization string table (`.st`)
+    StringTable,
+    Json,
+    Xml,
+    /// Other plain text
+    Text,
+    /// A nested archive (`.pak`, `.pbo`, `.zip`)
+    Archive,
+    Unknown,
+}
+
+struct Rule {
+    entry_type: EntryType,
+    extensions: &'static [&'static str],
+    /// `(offset, bytes)` pairs which identify this type when present in the header.
+    magic: &'static [(usize, &'static [u8])],
+}
+
+/// Magic numbers are checked in table order, so more specific rules come first.
+const RULES: &[Rule] = &[
+    Rule { entry_type: EntryType::RapifiedConfig, extensions: &[], magic: &[(0, b"\0raP")] },
+    Rule { entry_type: EntryType::Script, extensions: &["c", "enf"], magic: &[] },
+    Rule {
+        entry_type: EntryType::Config,
+        extensions: &["conf", "cfg", "cpp", "hpp", "bin"],
+        magic: &[],
+    },
+    Rule { entry_type: EntryType::Layout, extensions: &["layout", "styles"], magic: &[] },
+    Rule { entry_type: EntryType::Prefab, extensions: &["et"], magic: &[] },
+    Rule { entry_type: EntryType::World, extensions: &["ent", "smap", "layer"], magic: &[] },
+    Rule { entry_type: EntryType::Meta, extensions: &["meta"], magic: &[] },
+    Rule { entry_type: EntryType::Material, extensions: &["emat", "gamemat", "rvmat"], magic: &[] },
+    Rule {
+        entry_type: EntryType::Texture,
+        extensions: &["edds", "dds", "paa", "png", "tga", "jpg", "jpeg"],
+        magic: &[(0, b"DDS "), (0, b"\x89PNG"), (0, b"\xFF\xD8\xFF")],
+    },
+    Rule {
+        entry_type: EntryType::Audio,
+        extensions: &["wav", "ogg", "acp", "wss", "sig"],
+        magic: &[(0, b"OggS"), (8, b"WAVE")],
+    },
+    Rule {
+        entry_type: EntryType::Model,
+        extensions: &["xob", "p3d"],
+        magic: &[(8, b"XOB"), (0, b"MLOD"), (0, b"ODOL")],
+    },
+    Rule { entry_type: EntryType::Animation, extensions: &["anm", "rtm"], magic: &[] },
+    Rule {
+        entry_type: EntryType::AnimationGraph,
+        extensions: &["agr", "agf", "asi", "ast", "asy", "aw"],
+        magic: &[],
+    },
+    Rule { entry_type: EntryType::StringTable, extensions: &["st"], magic: &[] },
+    Rule { entry_type: EntryType::Json, extensions: &["json"], magic: &[] },
+    Rule { entry_type: EntryType::Xml, extensions: &["xml"], magic: &[(0, b"<?xml")] },
+    Rule { entry_type: EntryType::Text, extensions: &["txt", "md", "csv", "sqf"], magic: &[] },
+    Rule {
+        entry_type: EntryType::Archive,
+        extensions: &["pak", "pbo", "zip"],
+        magic: &[(8, b"PAC1"), (0, b"PK\x03\x04")],
+    },
+];
+
+impl EntryType {
+    /// Every known type, in display order.
+    pub const ALL: &[EntryType] = &[
+        EntryType::Script,
+        EntryType::Config,
+        EntryType::RapifiedConfig,
+        EntryType::Layout,
+        EntryType::Prefab,
+        EntryType::World,
+        EntryType::Meta,
+        EntryType::Material,
+        EntryType::Texture,
+        EntryType::Audio,
+        EntryType::Model,
+        EntryType::Animation,
+        EntryType::AnimationGraph,
+        EntryType::StringTable,
+        EntryType::Json,
+        EntryType::Xml,
+        EntryType::Text,
+        EntryType::Archive,
+        EntryType::Unknown,
+    ];
+
+    /// Short lowercase name, suitable for command-line arguments.
+    pub fn name(self) -> &'static str {
+        match self {
+            EntryType::Script => "script",
+            EntryType::Config => "config",
+            EntryType::RapifiedConfig => "rapified-config",
+            EntryType::Layout => "layout",
+            EntryType::Prefab => "prefab",
+            EntryType::World => "world",
+            EntryType::Meta => "meta",
+            EntryType::Material => "material",
+            EntryType::Texture => "texture",
+            EntryType::Audio => "audio",
+            EntryType::Model => "model",
+            EntryType::Animation => "animation",
+            EntryType::AnimationGraph => "animation-graph",
+            EntryType::StringTable => "string-table",
+            EntryType::Json => "json",
+            EntryType::Xml => "xml",
+            EntryType::Text => "text",
+            EntryType::Archive => "archive",
+            EntryType::Unknown => "unknown",
+        }
+    }
+
+    /// Looks up a type by its [`EntryType::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|ty| ty.name().eq_ignore_ascii_case(name))
+    }
+
+    /// File extensions associated with this type.
+    pub fn extensions(self) -> &'static [&'static str] {
+        RULES.iter().find(|rule| rule.entry_type == self).map(|rule| rule.extensions).unwrap_or(&[])
+    }
+
+    /// Whether entries of this type are human-readable text.
+    pub fn is_text(self) -> bool {
+        matches!(
+            self,
+            EntryType::Script
+                | EntryType::Config
+                | EntryType::Layout
+                | EntryType::Prefab
+                | EntryType::World
+                | EntryType::Meta
+                | EntryType::Material
+                | EntryType::AnimationGraph
+                | EntryType::StringTable
+                | EntryType::Json
+                | EntryType::Xml
+                | EntryType::Text
+        )
+    }
+}
+
+/// Extensions of every type which [`EntryType::is_text`].
+pub fn text_extensions() -> impl Iterator<Item = &'static str> {
+    EntryType::ALL.iter().filter(|ty| ty.is_text()).flat_map(|ty| ty.extensions().iter().copied())
+}
+
+/// Detects an entry's type from its leading bytes (see [`HEADER_LEN`]) and falls back to
+/// its path's extension. `header` may be empty if the contents aren't available.
+pub fn detect_type(path: &str, header: &[u8]) -> EntryType {
+    let by_magic = RULES.iter().find(|rule| {
+        rule.magic.iter().any(|(offset, magic)| {
+            header.get(*offset..*offset + magic.len()).is_some_and(|bytes| bytes == *magic)
+        })
+    });
+    if let Some(rule) = by_magic {
+        return rule.entry_type;
+    }
+
+    let file_name = path.rsplit_once('/').map(|(_, name)| name).unwrap_or(path);
+    let Some((_, ext)) = file_name.rsplit_once('.') else {
+        return EntryType::Unknown;
+    };
+
+    RULES
+        .iter()
+        .find(|rule| rule.extensions.iter().any(|candidate| candidate.eq_ignore_ascii_case(ext)))
+        .map(|rule| rule.entry_type)
+        .unwrap_or(EntryType::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_by_extension_and_magic() {
+        assert_eq!(detect_type("/scripts/Game/GameMode.c", &[]), EntryType::Script);
+        assert_eq!(detect_type("/UI/layouts/Menu.LAYOUT", &[]), EntryType::Layout);
+        assert_eq!(detect_type("/DZ/data/config.bin", &[]), EntryType::Config);
+        assert_eq!(detect_type("/DZ/data/config.bin", b"\0raP\0\0\0\0"), EntryType::RapifiedConfig);
+        assert_eq!(detect_type("/textures/unknown", b"DDS \x7c\0\0\0"), EntryType::Texture);
+        assert_eq!(detect_type("/anims/Player.agr", &[]), EntryType::AnimationGraph);
+        assert_eq!(detect_type("/no_extension", &[]), EntryType::Unknown);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for ty in EntryType::ALL {
+            assert_eq!(EntryType::from_name(ty.name()), Some(*ty));
+        }
+    }
+}
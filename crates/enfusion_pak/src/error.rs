@@ -1,5 +1,10 @@
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::PathBuf;
+
 use thiserror::Error;
 use winnow::error::ContextError;
+use winnow::error::ErrMode;
 use winnow::error::StrContext;
 
 #[derive(Debug, Error)]
@@ -7,6 +12,290 @@ pub enum PakError {
     #[error("I/O error occurred")]
     IoError(#[from] std::io::Error),
 
-    #[error("Parser error")]
-    ParserError(ContextError<StrContext>),
+    #[error("Parser error: {0}")]
+    ParserError(ParserDiagnostic),
+
+    #[error(transparent)]
+    UnsupportedVersion(#[from] UnsupportedVersion),
+
+    #[error("invalid zip archive: {0}")]
+    InvalidZip(&'static str),
+
+    #[error("cannot write pak: {0}")]
+    Unwritable(&'static str),
+
+    #[error("pak has no FILE chunk")]
+    MissingFileChunk,
+
+    #[error("refusing to extract {0:?} outside of the destination folder")]
+    UnsafePath(String),
+
+    #[error("{0:?} is not a file")]
+    NotAFile(String),
+}
+
+/// An edit to a pak's entry tree which couldn't be made.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EditError {
+    #[error("no entry at {0:?}")]
+    NotFound(String),
+
+    #[error("{0:?} is not a folder")]
+    NotAFolder(String),
+
+    #[error("{0:?} already exists")]
+    AlreadyExists(String),
+
+    #[error("{0:?} is not a valid entry name")]
+    InvalidName(String),
+}
+
+/// A pak's embedded manifest is missing, or doesn't match its files or signature.
+#[derive(Debug, Error)]
+pub enum SignatureError {
+    #[error("pak has no manifest")]
+    NoManifest,
+
+    #[error("pak's manifest is not signed")]
+    NoSignature,
+
+    #[error("malformed manifest: {0}")]
+    MalformedManifest(String),
+
+    #[error("signature does not match the manifest or key")]
+    BadSignature,
+
+    #[error("{} file(s) don't match the manifest", .0.len())]
+    Mismatch(Vec<String>),
+
+    #[error(transparent)]
+    Pak(#[from] PakError),
+}
+
+/// One of several paks being loaded together couldn't be opened or parsed.
+#[derive(Debug, Error)]
+#[error("failed to load {}: {error}", path.display())]
+pub struct ParseManyError {
+    pub path: PathBuf,
+    pub error: PakError,
+}
+
+/// Why a file's contents couldn't be read back intact.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum VerifyError {
+    #[error("stored at {range:?}, outside the DATA chunk at {data:?}")]
+    OutOfBounds { range: Range<usize>, data: Range<usize> },
+    #[error("could not be read: {0}")]
+    Unreadable(String),
+    #[error("could not be decompressed: {0}")]
+    Inflate(String),
+    #[error("decompressed to {actual} bytes, expected {expected}")]
+    WrongLength { expected: u32, actual: u64 },
+}
+
+/// The HEAD chunk declares a format version this crate doesn't know how to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("unsupported pak version {0:#X}")]
+pub struct UnsupportedVersion(pub u32);
+
+/// Number of bytes shown before the failing offset in a diagnostic hexdump.
+const DUMP_BYTES_BEFORE: usize = 32;
+/// Number of bytes shown from the failing offset onwards in a diagnostic hexdump.
+const DUMP_BYTES_AFTER: usize = 48;
+
+/// Describes why and where parsing a pak failed.
+///
+/// When the parser knows where it was, this includes the absolute offset into the pak,
+/// the section being read (e.g. a chunk header or a FILE entry), and a copy of the bytes
+/// surrounding the failure so bug reports about unparseable paks are actionable.
+#[derive(Debug)]
+pub struct ParserDiagnostic {
+    error: ContextError<StrContext>,
+    truncated: bool,
+    offset: Option<usize>,
+    surrounding_start: usize,
+    surrounding: Vec<u8>,
+}
+
+impl ParserDiagnostic {
+    /// Creates a diagnostic without any location information.
+    pub fn new(error: ErrMode<ContextError<StrContext>>) -> Self {
+        let (error, truncated) = match error {
+            ErrMode::Backtrack(e) | ErrMode::Cut(e) => (e, false),
+            ErrMode::Incomplete(_) => (ContextError::new(), true),
+        };
+
+        Self { error, truncated, offset: None, surrounding_start: 0, surrounding: Vec::new() }
+    }
+
+    /// Records that the failure happened at `data[pos]`, where `data[0]` lives at absolute
+    /// offset `data_offset` in the pak.
+    pub fn with_location(mut self, data: &[u8], data_offset: usize, pos: usize) -> Self {
+        let pos = pos.min(data.len());
+        let start = pos.saturating_sub(DUMP_BYTES_BEFORE);
+        let end = (pos + DUMP_BYTES_AFTER).min(data.len());
+
+        self.offset = Some(data_offset + pos);
+        self.surrounding_start = data_offset + start;
+        self.surrounding = data[start..end].to_vec();
+        self
+    }
+
+    /// The underlying parser error.
+    pub fn error(&self) -> &ContextError<StrContext> {
+        &self.error
+    }
+
+    /// Whether parsing failed because the data ended early.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Absolute offset into the pak at which parsing failed, if known.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// The part of the pak that was being read when parsing failed (e.g. `"DATA chunk"` or
+    /// `"FILE entry name"`), if known.
+    pub fn section(&self) -> Option<&'static str> {
+        self.error.context().find_map(|context| match context {
+            StrContext::Label(label) => Some(*label),
+            _ => None,
+        })
+    }
+
+    /// The HEAD version that caused parsing to stop, if that's why it failed.
+    pub fn unsupported_version(&self) -> Option<UnsupportedVersion> {
+        self.error.cause()?.downcast_ref::<UnsupportedVersion>().copied()
+    }
+
+    /// Bytes surrounding the failure along with the absolute offset of the first byte.
+    pub fn surrounding_bytes(&self) -> (usize, &[u8]) {
+        (self.surrounding_start, &self.surrounding)
+    }
+
+    /// Renders the surrounding bytes as a classic offset/hex/ASCII dump.
+    pub fn hexdump(&self) -> String {
+        let mut out = String::new();
+        for (line_idx, line) in self.surrounding.chunks(16).enumerate() {
+            let line_offset = self.surrounding_start + line_idx * 16;
+            let _ = write!(out, "{line_offset:08X}  ");
+            for i in 0..16 {
+                match line.get(i) {
+                    Some(b) => {
+                        let marker = if Some(line_offset + i) == self.offset { '>' } else { ' ' };
+                        let _ = write!(out, "{marker}{b:02X}");
+                    }
+                    None => out.push_str("   "),
+                }
+            }
+            out.push_str("  |");
+            out.extend(
+                line.iter()
+                    .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' }),
+            );
+            out.push_str("|\n");
+        }
+
+        out
+    }
+}
+
+impl From<ContextError<StrContext>> for ParserDiagnostic {
+    fn from(error: ContextError<StrContext>) -> Self {
+        Self::new(ErrMode::Cut(error))
+    }
+}
+
+impl std::fmt::Display for ParserDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.truncated {
+            write!(f, "unexpected end of data")?;
+        } else {
+            write!(f, "{}", self.error)?;
+        }
+
+        if let Some(section) = self.section() {
+            write!(f, " while reading {section}")?;
+        }
+
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset:#X}")?;
+        }
+
+        if !self.surrounding.is_empty() {
+            write!(f, "\n{}", self.hexdump())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PakFile;
+
+    use super::*;
+    use crate::test_pak::pak_bytes;
+
+    #[test]
+    fn diagnostic_reports_offset_of_bad_chunk() {
+        for (tag, section) in [(b"JUNK", "unknown chunk"), (b"DATA", "DATA chunk")] {
+            let mut data = Vec::new();
+            data.extend_from_slice(b"FORM");
+            data.extend_from_slice(&0x20u32.to_be_bytes());
+            data.extend_from_slice(b"PAC1");
+            data.extend_from_slice(tag);
+            // Runs well past the end of the pak
+            data.extend_from_slice(&0x100u32.to_be_bytes());
+            data.resize(0x28, 0);
+
+            let Err(PakError::ParserError(diagnostic)) = PakFile::parse(&data) else {
+                panic!("expected a parser error");
+            };
+
+            assert_eq!(diagnostic.offset(), Some(0xC));
+            assert_eq!(diagnostic.section(), Some(section));
+            assert_eq!(diagnostic.surrounding_bytes(), (0, &data[..]));
+            assert!(diagnostic.hexdump().contains(&format!(">{:02X}", tag[0])));
+        }
+    }
+
+    #[test]
+    fn malformed_file_entries_are_diagnosed() {
+        let pak = pak_bytes(&[("scripts/a.c", "class A {}")]);
+        let name = pak.windows(4).position(|bytes| bytes == b"\x03a.c").unwrap() + 1;
+
+        // The entry's kind comes before its name length, and its compression flag and level
+        // after 18 bytes of offsets, lengths and unknown fields
+        for (index, value, section) in [
+            (name - 2, 7, "FILE entry kind"),
+            (name, 0xFF, "FILE entry name"),
+            (name + 3 + 18, 2, "FILE entry compression flag"),
+            (name + 3 + 19, 9, "FILE entry compression level"),
+        ] {
+            let mut data = pak.clone();
+            data[index] = value;
+            let Err(PakError::ParserError(diagnostic)) = PakFile::parse(&data) else {
+                panic!("parsed a pak with a bad {section}");
+            };
+            assert_eq!(diagnostic.section(), Some(section));
+        }
+    }
+
+    #[test]
+    fn unknown_head_version_is_rejected() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"FORM");
+        data.extend_from_slice(&0x30u32.to_be_bytes());
+        data.extend_from_slice(b"PAC1");
+        data.extend_from_slice(b"HEAD");
+        data.extend_from_slice(&0x1Cu32.to_be_bytes());
+        data.extend_from_slice(&0x20001u32.to_le_bytes());
+        data.resize(0x38, 0);
+
+        let err = PakFile::parse(&data).expect_err("parsed a pak with an unknown version");
+        assert!(matches!(err, PakError::UnsupportedVersion(UnsupportedVersion(0x20001))));
+    }
 }
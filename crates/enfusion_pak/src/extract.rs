@@ -0,0 +1,270 @@
+//! Writing a pak's files to a folder on disk.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use jiff::tz::TimeZone;
+use vfs::VfsError;
+
+use crate::Chunk;
+use crate::FileEntry;
+use crate::FileEntryMeta;
+use crate::PakFile;
+use crate::RcFileEntry;
+use crate::error::PakError;
+use crate::lazy::LazyFileTable;
+use crate::pak_vfs::Prime;
+
+impl PakFile {
+    /// Extracts every file in this pak into `dest`. See [`PakFile::extract_matching`].
+    pub fn extract_all(&self, prime: &impl Prime, dest: &Path) -> Result<usize, PakError> {
+        self.extract_matching(prime, dest, |_| true)
+    }
+
+    /// Extracts the files `filter` accepts into `dest`, returning how many were written.
+    /// `filter` is given each file's path inside the pak without a leading `/`, e.g.
+    /// `scripts/Game/foo.c`.
+    ///
+    /// `prime` reads the pak's bytes. Folders are created as needed, compressed files are
+    /// decompressed as they're written, and each file's modification time is set from the
+    /// pak (read as UTC).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use enfusion_pak::PakFile;
+    /// use enfusion_pak::glob::glob_matches;
+    /// use enfusion_pak::wrappers::bytes::BytesPakFileWrapper;
+    ///
+    /// let path = std::path::PathBuf::from("example.pak");
+    /// let data = std::fs::read(&path).unwrap();
+    /// let pak = PakFile::parse(&data).unwrap();
+    /// let wrapper = BytesPakFileWrapper::new(path, data, pak);
+    /// wrapper
+    ///     .pak_file()
+    ///     .extract_matching(&wrapper, "out".as_ref(), |path| {
+    ///         glob_matches("scripts/**/*.c", path)
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn extract_matching(
+        &self,
+        prime: &impl Prime,
+        dest: &Path,
+        mut filter: impl FnMut(&str) -> bool,
+    ) -> Result<usize, PakError> {
+        let root = self.root_entry(prime)?;
+        let dest = long_path(dest);
+        let mut written = 0;
+        for (path, entry) in root.walk() {
+            if matches!(entry.meta(), FileEntryMeta::File { .. }) && filter(&path) {
+                extract_file(prime, entry, &dest.join(relative_path(&path)?))?;
+                written += 1;
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// The root of the FILE chunk, parsing it first if this pak was parsed lazily.
+    pub(crate) fn root_entry(
+        &self,
+        prime: &(impl Prime + ?Sized),
+    ) -> Result<RcFileEntry, PakError> {
+        if let Some(Chunk::File { fs }) = self.file_chunk() {
+            return Ok(RcFileEntry::clone(fs));
+        }
+
+        let range = self.lazy_file_range().ok_or(PakError::MissingFileChunk)?;
+        let entries = prime.prime_file(range).map_err(vfs_error)?;
+        let table =
+            LazyFileTable::with_version(entries.as_ref(), self.version().unwrap_or_default())?;
+
+        Ok(RcFileEntry::new(table.root().materialize()?))
+    }
+}
+
+fn extract_file(prime: &impl Prime, entry: &FileEntry, dest: &Path) -> Result<(), PakError> {
+    let FileEntryMeta::File { offset, compressed_len, compressed, .. } = entry.meta() else {
+        unreachable!("only files are extracted");
+    };
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut out = BufWriter::new(File::create(dest)?);
+    if let Some(contents) = entry.staged_contents() {
+        out.write_all(contents)?;
+    } else {
+        let start = *offset as usize;
+        let data = prime.prime_file(start..start + *compressed_len as usize).map_err(vfs_error)?;
+        if *compressed != 0 {
+            std::io::copy(&mut flate2::read::ZlibDecoder::new(data.as_ref()), &mut out)?;
+        } else {
+            out.write_all(data.as_ref())?;
+        }
+    }
+
+    let file = out.into_inner().map_err(|e| e.into_error())?;
+    if let Some(modified) = modified_time(entry) {
+        file.set_modified(modified)?;
+    }
+
+    Ok(())
+}
+
+/// Where the file at `path` inside a pak goes under `dest`. Names which could escape `dest`
+/// are rejected, see [`relative_path`].
+///
+/// On Windows this is an extended-length (`\\?\`) path, which isn't limited to 260
+/// characters.
+pub fn dest_path(dest: &Path, path: &str) -> Result<PathBuf, PakError> {
+    Ok(long_path(dest).join(relative_path(path)?))
+}
+
+/// The file at `path` inside a pak as a relative path using the platform's separators.
+/// `path` is `/`-separated and may start with a `/`. Parent folders (`..`) and components
+/// which name a root or a drive on Windows are rejected.
+pub fn relative_path(path: &str) -> Result<PathBuf, PakError> {
+    let mut relative = PathBuf::new();
+    for component in path.split('/').filter(|component| !matches!(*component, "" | ".")) {
+        if component == ".." || component.contains(['\\', ':']) {
+            return Err(PakError::UnsafePath(path.to_string()));
+        }
+        relative.push(component);
+    }
+
+    Ok(relative)
+}
+
+/// `path` as an extended-length (`\\?\`) path on Windows, which isn't limited to 260
+/// characters. Extended-length paths skip Windows' normalization, so pak paths must be
+/// joined with [`relative_path`] rather than appended as strings. Other platforms get
+/// `path` back unchanged.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    use std::ffi::OsString;
+    use std::path::Component;
+    use std::path::Prefix;
+
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+    let mut components = absolute.components();
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(_) => {
+                let mut verbatim = OsString::from(r"\\?\");
+                verbatim.push(prefix.as_os_str());
+                verbatim
+            }
+            Prefix::UNC(server, share) => {
+                let mut verbatim = OsString::from(r"\\?\UNC\");
+                verbatim.push(server);
+                verbatim.push(r"\");
+                verbatim.push(share);
+                verbatim
+            }
+            // Already verbatim, or a device path
+            _ => return absolute,
+        },
+        _ => return absolute,
+    };
+
+    let mut long = PathBuf::from(prefix);
+    long.push(components.as_path());
+    long
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+fn modified_time(entry: &FileEntry) -> Option<SystemTime> {
+    let modified = entry.meta().parsed_timestamp()?.to_zoned(TimeZone::UTC).ok()?;
+    Some(SystemTime::from(modified.timestamp()))
+}
+
+fn vfs_error(e: VfsError) -> PakError {
+    PakError::IoError(std::io::Error::other(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glob::glob_matches;
+    use crate::test_pak::modified;
+    use crate::test_pak::pak_bytes;
+    use crate::wrappers::bytes::BytesPakFileWrapper;
+
+    fn build_pak() -> BytesPakFileWrapper<Vec<u8>> {
+        let written = pak_bytes(&[("scripts/Game/a.c", "class A {}"), ("b.conf", "b")]);
+        let pak = PakFile::parse(&written).unwrap();
+        BytesPakFileWrapper::new("test.pak".into(), written, pak)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("enfusion_pak_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn extracts_files_with_their_timestamps() {
+        let pak = build_pak();
+        let dest = temp_dir("extract_all");
+
+        let written = pak.pak_file().extract_all(&pak, &dest).unwrap();
+        assert_eq!(written, 2);
+
+        let a = dest.join("scripts").join("Game").join("a.c");
+        assert_eq!(std::fs::read(&a).unwrap(), b"class A {}");
+        assert_eq!(std::fs::read(dest.join("b.conf")).unwrap(), b"b");
+
+        let modified = std::fs::metadata(&a).unwrap().modified().unwrap();
+        let expected = modified().to_zoned(TimeZone::UTC).unwrap();
+        assert_eq!(modified, SystemTime::from(expected.timestamp()));
+
+        std::fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn extracts_only_matching_files() {
+        let pak = build_pak();
+        let dest = temp_dir("extract_matching");
+
+        let written = pak
+            .pak_file()
+            .extract_matching(&pak, &dest, |path| glob_matches("**/*.c", path))
+            .unwrap();
+        assert_eq!(written, 1);
+        assert!(dest.join("scripts").join("Game").join("a.c").exists());
+        assert!(!dest.join("b.conf").exists());
+
+        std::fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn rejects_paths_outside_of_dest() {
+        assert_eq!(relative_path("scripts/a.c").unwrap(), Path::new("scripts").join("a.c"));
+        assert_eq!(relative_path("/scripts//./a.c").unwrap(), Path::new("scripts").join("a.c"));
+        for path in ["../a.c", "scripts/../../a.c", "C:/a.c", "scripts\\..\\a.c"] {
+            assert!(matches!(relative_path(path), Err(PakError::UnsafePath(_))), "{path}");
+            assert!(matches!(dest_path(Path::new("out"), path), Err(PakError::UnsafePath(_))));
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn dest_paths_are_extended_length() {
+        let dest = dest_path(Path::new("out"), "scripts/a.c").unwrap();
+        assert!(dest.as_os_str().to_string_lossy().starts_with(r"\\?\"));
+        assert!(dest.ends_with(Path::new("out").join("scripts").join("a.c")));
+    }
+}
@@ -0,0 +1,184 @@
+//! Glob patterns for `/`-separated paths inside an archive, such as `scripts/**/*.c`.
+//!
+//! `*` and `?` match within one path component, `**/` matches any number of folders, a
+//! trailing `**` matches everything below, `[a-z]`/`[!a-z]` match a class of characters,
+//! and `\` escapes the next character. Paths are matched without a leading `/`.
+
+use crate::Chunk;
+use crate::FileEntryMeta;
+use crate::PakFile;
+use crate::RcFileEntry;
+
+/// A file found by [`PakFile::find_entries`].
+#[derive(Debug, Clone)]
+pub struct GlobMatch {
+    /// Path inside the pak without a leading `/`.
+    pub path: String,
+    pub entry: RcFileEntry,
+}
+
+impl PakFile {
+    /// Every file whose path matches the glob `pattern`, sorted by path. Folders which can't
+    /// hold a match, going by the pattern's leading folders, aren't searched.
+    ///
+    /// Lazily parsed paks have no entries to search, and return nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use enfusion_pak::PakFile;
+    ///
+    /// let data = std::fs::read("example.pak").unwrap();
+    /// let pak = PakFile::parse(&data).unwrap();
+    /// for found in pak.find_entries("Prefabs/**/*.et") {
+    ///     println!("{}", found.path);
+    /// }
+    /// ```
+    pub fn find_entries(&self, pattern: &str) -> Vec<GlobMatch> {
+        let Some(Chunk::File { fs }) = self.file_chunk() else {
+            return Vec::new();
+        };
+
+        let literal_dirs = literal_dirs(pattern);
+        let mut matches = Vec::new();
+        let mut walk = fs.walk();
+        while let Some((path, entry)) = walk.next() {
+            match entry.meta() {
+                FileEntryMeta::Folder { .. } => {
+                    if !could_contain(literal_dirs, &path) {
+                        walk.skip_children();
+                    }
+                }
+                FileEntryMeta::File { .. } => {
+                    if glob_matches(pattern, &path) {
+                        matches.push(GlobMatch { path, entry: RcFileEntry::clone(entry) });
+                    }
+                }
+            }
+        }
+
+        matches.sort_by(|a, b| a.path.cmp(&b.path));
+        matches
+    }
+}
+
+/// The folders at the start of `pattern` which contain no special characters, e.g.
+/// `scripts/Game` for `scripts/Game/**/*.c`.
+fn literal_dirs(pattern: &str) -> &str {
+    let special = pattern.find(['*', '?', '[', '\\']).unwrap_or(pattern.len());
+    match pattern[..special].rfind('/') {
+        Some(end) => &pattern[..end],
+        None => "",
+    }
+}
+
+/// Whether the folder at `dir` could hold a path starting with the folders `literal_dirs`.
+fn could_contain(literal_dirs: &str, dir: &str) -> bool {
+    let is_within = |outer: &str, inner: &str| {
+        inner.strip_prefix(outer).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    };
+    literal_dirs.is_empty() || is_within(dir, literal_dirs) || is_within(literal_dirs, dir)
+}
+
+/// Whether `path` matches `pattern`.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    matches_from(pattern.as_bytes(), path.as_bytes())
+}
+
+fn matches_from(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*'] => true,
+        [b'*', b'*', b'/', rest @ ..] => {
+            matches_from(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .any(|(idx, c)| *c == b'/' && matches_from(rest, &path[idx + 1..]))
+        }
+        [b'*', rest @ ..] => {
+            matches_from(rest, path)
+                || (path.first().is_some_and(|c| *c != b'/') && matches_from(pattern, &path[1..]))
+        }
+        [b'?', rest @ ..] => {
+            path.first().is_some_and(|c| *c != b'/') && matches_from(rest, &path[1..])
+        }
+        [b'[', class @ ..] => match class.iter().skip(1).position(|c| *c == b']') {
+            Some(end) => {
+                let (class, rest) = (&class[..end + 1], &class[end + 2..]);
+                path.first().is_some_and(|c| *c != b'/' && class_matches(class, *c))
+                    && matches_from(rest, &path[1..])
+            }
+            None => path.first() == Some(&b'[') && matches_from(class, &path[1..]),
+        },
+        [b'\\', escaped, rest @ ..] => {
+            path.first() == Some(escaped) && matches_from(rest, &path[1..])
+        }
+        [literal, rest @ ..] => path.first() == Some(literal) && matches_from(rest, &path[1..]),
+    }
+}
+
+/// Whether `c` is in a `[...]` class, given what's between the brackets.
+fn class_matches(class: &[u8], c: u8) -> bool {
+    let (negated, mut class) = match class {
+        [b'!' | b'^', rest @ ..] => (true, rest),
+        _ => (false, class),
+    };
+
+    let mut found = false;
+    while let Some(&start) = class.first() {
+        if let [_, b'-', end, rest @ ..] = class {
+            found |= (start..=*end).contains(&c);
+            class = rest;
+        } else {
+            found |= start == c;
+            class = &class[1..];
+        }
+    }
+
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_pak::pak_with;
+
+    #[test]
+    fn matches_glob_syntax() {
+        assert!(glob_matches("**/*.c", "a.c"));
+        assert!(glob_matches("**/*.c", "x/y/a.c"));
+        assert!(!glob_matches("*.c", "x/a.c"));
+        assert!(glob_matches("x/**", "x/y/z"));
+        assert!(glob_matches("a?c", "abc"));
+        assert!(!glob_matches("a?c", "a/c"));
+        assert!(glob_matches("[!a]b", "cb"));
+        assert!(!glob_matches("[!a]b", "ab"));
+        assert!(glob_matches("\\*", "*"));
+        assert!(!glob_matches("\\*", "a"));
+        assert!(glob_matches("[ab", "[ab"));
+    }
+
+    #[test]
+    fn finds_matching_entries() {
+        let pak = pak_with(&[
+            ("scripts/Game/a.c", ""),
+            ("scripts/Game/UI/b.c", ""),
+            ("scripts/GameCode/c.c", ""),
+            ("Prefabs/d.et", ""),
+        ]);
+
+        let paths = |pattern| -> Vec<String> {
+            pak.find_entries(pattern).into_iter().map(|found| found.path).collect()
+        };
+        assert_eq!(paths("scripts/Game/**/*.c"), ["scripts/Game/UI/b.c", "scripts/Game/a.c"]);
+        assert_eq!(paths("**/*.et"), ["Prefabs/d.et"]);
+        assert_eq!(paths("scripts/Game*/*.c"), ["scripts/Game/a.c", "scripts/GameCode/c.c"]);
+        assert!(paths("missing/**").is_empty());
+
+        assert_eq!(literal_dirs("scripts/Game/**/*.c"), "scripts/Game");
+        assert_eq!(literal_dirs("scripts/Game*/*.c"), "scripts");
+        assert_eq!(literal_dirs("*.c"), "");
+        assert!(!could_contain("scripts/Game", "scripts/GameCode"));
+    }
+}
@@ -0,0 +1,334 @@
+//! Hiding files in a loose folder with a gitignore-style ignore file.
+//!
+//! An unpacked mod folder usually contains build artifacts, editor swap files and the like
+//! which aren't part of the mod. Listing them in an [`IGNORE_FILE_NAME`] file at the root
+//! of the folder and wrapping the folder's filesystem in an [`IgnoringVfs`] keeps them out
+//! of anything which walks the VFS.
+//!
+//! Patterns follow `.gitignore`: `#` starts a comment, `!` re-includes what an earlier
+//! pattern excluded, a trailing `/` only matches folders, and a pattern containing a `/`
+//! is relative to the folder root while one without matches at any depth. The glob syntax
+//! is described in [`crate::glob`]. Only the ignore file at the root of the folder is read.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use vfs::VfsError;
+use vfs::VfsFileType;
+use vfs::VfsMetadata;
+use vfs::error::VfsErrorKind;
+
+use crate::glob::glob_matches;
+
+/// Name of the ignore file read from the root of a loose folder.
+pub const IGNORE_FILE_NAME: &str = ".enfignore";
+
+/// The patterns from an ignore file.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreRules {
+    /// Parses the contents of an ignore file.
+    pub fn parse(contents: &str) -> Self {
+        let rules = contents.lines().filter_map(Rule::parse).collect();
+        Self { rules }
+    }
+
+    /// Reads the ignore file at the root of `dir`. A folder without one ignores nothing.
+    pub fn load(dir: &Path) -> std::io::Result<Self> {
+        match std::fs::read_to_string(dir.join(IGNORE_FILE_NAME)) {
+            Ok(contents) => Ok(Self::parse(&contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether `path`, relative to the folder root, is ignored. Everything inside an
+    /// ignored folder is ignored too, and can't be re-included.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let path = path.trim_matches('/');
+        if path.is_empty() {
+            return false;
+        }
+
+        let in_ignored_folder =
+            path.match_indices('/').any(|(idx, _)| self.matches(&path[..idx], true));
+
+        in_ignored_folder || self.matches(path, is_dir)
+    }
+
+    /// Whether the last pattern matching `path` excludes it.
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && glob_matches(&rule.pattern, path))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if line.is_empty() {
+            return None;
+        }
+
+        let pattern = if line.contains('/') {
+            line.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{line}")
+        };
+
+        Some(Rule { pattern, negated, dir_only })
+    }
+}
+
+/// A filesystem which hides everything its [`IgnoreRules`] ignore. Ignored files and
+/// folders aren't listed by `read_dir` and can't be opened.
+#[derive(Debug, Clone)]
+pub struct IgnoringVfs<F> {
+    inner: F,
+    rules: Arc<IgnoreRules>,
+}
+
+impl<F> IgnoringVfs<F> {
+    pub fn new(inner: F, rules: Arc<IgnoreRules>) -> Self {
+        Self { inner, rules }
+    }
+}
+
+fn child_path(dir: &str, name: &str) -> String {
+    format!("{}/{name}", dir.trim_end_matches('/'))
+}
+
+fn not_found() -> VfsError {
+    VfsErrorKind::FileNotFound.into()
+}
+
+fn is_dir(metadata: &VfsMetadata) -> bool {
+    metadata.file_type == VfsFileType::Directory
+}
+
+impl<F> IgnoringVfs<F>
+where
+    F: vfs::FileSystem,
+{
+    fn is_hidden(&self, path: &str) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+
+        let is_dir = self.inner.metadata(path).is_ok_and(|metadata| is_dir(&metadata));
+        self.rules.is_ignored(path, is_dir)
+    }
+}
+
+impl<F> vfs::FileSystem for IgnoringVfs<F>
+where
+    F: vfs::FileSystem,
+{
+    fn read_dir(&self, path: &str) -> vfs::VfsResult<Box<dyn Iterator<Item = String> + Send>> {
+        if self.is_hidden(path) {
+            return Err(not_found());
+        }
+
+        let entries = self.inner.read_dir(path)?;
+        if self.rules.is_empty() {
+            return Ok(entries);
+        }
+
+        let visible: Vec<String> =
+            entries.filter(|name| !self.is_hidden(&child_path(path, name))).collect();
+        Ok(Box::new(visible.into_iter()))
+    }
+
+    fn open_file(&self, path: &str) -> vfs::VfsResult<Box<dyn vfs::SeekAndRead + Send>> {
+        if self.is_hidden(path) {
+            return Err(not_found());
+        }
+        self.inner.open_file(path)
+    }
+
+    fn metadata(&self, path: &str) -> vfs::VfsResult<VfsMetadata> {
+        if self.is_hidden(path) {
+            return Err(not_found());
+        }
+        self.inner.metadata(path)
+    }
+
+    fn exists(&self, path: &str) -> vfs::VfsResult<bool> {
+        Ok(!self.is_hidden(path) && self.inner.exists(path)?)
+    }
+
+    fskit::read_only_fs_stubs!();
+}
+
+#[cfg(feature = "async_vfs")]
+mod async_impl {
+    use async_trait::async_trait;
+    use futures::StreamExt;
+    use futures::io::AsyncWrite;
+    use futures::stream::Stream;
+    use vfs::VfsResult;
+    use vfs::async_vfs::AsyncFileSystem;
+    use vfs::async_vfs::SeekAndRead;
+
+    use super::*;
+
+    impl<F> IgnoringVfs<F>
+    where
+        F: AsyncFileSystem,
+    {
+        async fn is_hidden_async(&self, path: &str) -> bool {
+            if self.rules.is_empty() {
+                return false;
+            }
+
+            let is_dir = self.inner.metadata(path).await.is_ok_and(|metadata| is_dir(&metadata));
+            self.rules.is_ignored(path, is_dir)
+        }
+    }
+
+    #[async_trait]
+    impl<F> AsyncFileSystem for IgnoringVfs<F>
+    where
+        F: AsyncFileSystem,
+    {
+        async fn read_dir(
+            &self,
+            path: &str,
+        ) -> VfsResult<Box<dyn Unpin + Stream<Item = String> + Send>> {
+            if self.is_hidden_async(path).await {
+                return Err(not_found());
+            }
+
+            let entries = self.inner.read_dir(path).await?;
+            if self.rules.is_empty() {
+                return Ok(entries);
+            }
+
+            let mut visible = Vec::new();
+            for name in entries.collect::<Vec<_>>().await {
+                if !self.is_hidden_async(&child_path(path, &name)).await {
+                    visible.push(name);
+                }
+            }
+            Ok(Box::new(futures::stream::iter(visible)))
+        }
+
+        async fn create_dir(&self, _path: &str) -> VfsResult<()> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+
+        async fn open_file(&self, path: &str) -> VfsResult<Box<dyn SeekAndRead + Send + Unpin>> {
+            if self.is_hidden_async(path).await {
+                return Err(not_found());
+            }
+            self.inner.open_file(path).await
+        }
+
+        async fn create_file(&self, _path: &str) -> VfsResult<Box<dyn AsyncWrite + Send + Unpin>> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+
+        async fn append_file(&self, _path: &str) -> VfsResult<Box<dyn AsyncWrite + Send + Unpin>> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+
+        async fn metadata(&self, path: &str) -> VfsResult<VfsMetadata> {
+            if self.is_hidden_async(path).await {
+                return Err(not_found());
+            }
+            self.inner.metadata(path).await
+        }
+
+        async fn exists(&self, path: &str) -> VfsResult<bool> {
+            Ok(!self.is_hidden_async(path).await && self.inner.exists(path).await?)
+        }
+
+        async fn remove_file(&self, _path: &str) -> VfsResult<()> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+
+        async fn remove_dir(&self, _path: &str) -> VfsResult<()> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> IgnoreRules {
+        IgnoreRules::parse(
+            "# build output\n\
+             /build/\n\
+             *.swp\n\
+             *~\n\
+             logs/\n\
+             !logs/keep.log\n\
+             scripts/**/generated_*.c\n\
+             *.bak\n\
+             !important.bak\n\
+             tmp[0-9]\n",
+        )
+    }
+
+    #[test]
+    fn matches_like_gitignore() {
+        let rules = rules();
+
+        assert!(rules.is_ignored("/build", true));
+        assert!(rules.is_ignored("/build/out.pak", false));
+        assert!(!rules.is_ignored("/scripts/build", true));
+        assert!(!rules.is_ignored("/build", false));
+
+        assert!(rules.is_ignored("/scripts/Game/a.c.swp", false));
+        assert!(rules.is_ignored("/a.c~", false));
+        assert!(rules.is_ignored("/scripts/generated_a.c", false));
+        assert!(rules.is_ignored("/scripts/Game/AI/generated_b.c", false));
+        assert!(!rules.is_ignored("/Game/generated_b.c", false));
+
+        assert!(rules.is_ignored("/old.bak", false));
+        assert!(!rules.is_ignored("/important.bak", false));
+        assert!(rules.is_ignored("/tmp3", false));
+        assert!(!rules.is_ignored("/tmpa", false));
+        assert!(!rules.is_ignored("/scripts/Game/a.c", false));
+    }
+
+    #[test]
+    fn files_in_ignored_folders_stay_ignored() {
+        let rules = rules();
+
+        assert!(rules.is_ignored("/logs", true));
+        assert!(rules.is_ignored("/logs/keep.log", false));
+        assert!(rules.is_ignored("/mod/logs/other.log", false));
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Deduplicates strings so that repeated entry names and path segments share a single
+/// allocation.
+///
+/// Large workspaces contain hundreds of thousands of entries whose names repeat heavily
+/// (`Prefabs`, `Data`, `config.conf`, ...). Interning them keeps one `Arc<str>` per
+/// distinct value instead of one heap `String` per entry.
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared copy of `s`, allocating it on first use.
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(existing) = self.strings.get(s) {
+            return Arc::clone(existing);
+        }
+
+        let interned: Arc<str> = Arc::from(s);
+        self.strings.insert(Arc::clone(&interned));
+        interned
+    }
+
+    /// Number of distinct strings held by this interner.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interned_strings_share_allocation() {
+        let mut interner = Interner::new();
+        let a = interner.intern("Prefabs");
+        let b = interner.intern("Prefabs");
+        let c = interner.intern("Configs");
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(interner.len(), 2);
+    }
+}
@@ -0,0 +1,304 @@
+//! On-demand browsing of a pak's FILE chunk.
+//!
+//! The FILE chunk is a depth-first listing of every entry in the archive. Fully
+//! materializing it as a [`FileEntry`] tree is the right choice for most tools, but for
+//! enormous archives where only a handful of folders are ever opened it costs a lot of
+//! memory up front. A [`LazyFileTable`] instead keeps the raw entry bytes and only
+//! parses a folder's direct children the first time they're requested.
+
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use winnow::ModalResult as WResult;
+use winnow::Parser;
+use winnow::binary::le_u32;
+use winnow::binary::u8;
+use winnow::error::ContextError;
+use winnow::error::ErrMode;
+use winnow::stream::Offset;
+use winnow::stream::Stream as _;
+use winnow::token::take;
+
+use crate::Chunk;
+use crate::FileEntry;
+use crate::FileEntryKind;
+use crate::FileEntryMeta;
+use crate::PakFile;
+use crate::Stream;
+use crate::error::PakError;
+use crate::error::ParserDiagnostic;
+use crate::intern::Interner;
+use crate::parse_file_entry;
+use crate::version::PakVersion;
+
+/// A FILE chunk's entry table whose folders are parsed on demand.
+#[derive(Debug, Clone)]
+pub struct LazyFileTable {
+    root: LazyFolder,
+}
+
+impl LazyFileTable {
+    /// Creates a table from the raw bytes of a FILE chunk's entries (i.e. the range
+    /// recorded in [`Chunk::LazyFile`]). Only the root folder's header is parsed.
+    pub fn new(entries: impl Into<Arc<[u8]>>) -> Result<Self, PakError> {
+        Self::with_version(entries, PakVersion::LATEST)
+    }
+
+    /// Like [`LazyFileTable::new`], but for entries laid out as described by `version`.
+    pub fn with_version(
+        entries: impl Into<Arc<[u8]>>,
+        version: PakVersion,
+    ) -> Result<Self, PakError> {
+        let data: Arc<[u8]> = entries.into();
+        let mut input = Stream::new(&data);
+        let start = input.checkpoint();
+
+        let (entry, child_count) =
+            parse_file_entry(&mut input, &mut Interner::new(), version).map_err(into_pak_error)?;
+        if entry.kind() != FileEntryKind::Folder {
+            return Err(PakError::ParserError(ContextError::new().into()));
+        }
+
+        let body_start = input.checkpoint().offset_from(&start);
+        let root = LazyFolder {
+            name: entry.name,
+            child_count,
+            body_start,
+            data: Arc::clone(&data),
+            version,
+            children: Default::default(),
+        };
+
+        Ok(Self { root })
+    }
+
+    /// The archive's root folder.
+    pub fn root(&self) -> &LazyFolder {
+        &self.root
+    }
+}
+
+/// A folder whose children are parsed the first time they're accessed. Clones share the
+/// parsed children.
+#[derive(Debug, Clone)]
+pub struct LazyFolder {
+    name: Arc<str>,
+    child_count: usize,
+    /// Offset of this folder's first child within `data`.
+    body_start: usize,
+    data: Arc<[u8]>,
+    version: PakVersion,
+    children: Arc<OnceLock<Vec<LazyEntry>>>,
+}
+
+impl LazyFolder {
+    /// Folder's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of direct children, available without parsing them.
+    pub fn child_count(&self) -> usize {
+        self.child_count
+    }
+
+    /// Whether this folder's children have been parsed yet.
+    pub fn is_loaded(&self) -> bool {
+        self.children.get().is_some()
+    }
+
+    /// Returns this folder's direct children, parsing them on first access. Subfolders
+    /// are returned unparsed.
+    pub fn children(&self) -> Result<&[LazyEntry], PakError> {
+        if let Some(children) = self.children.get() {
+            return Ok(children);
+        }
+
+        let children = self.parse_children()?;
+        // Another thread may have beaten us to it, in which case its result is
+        // identical to ours.
+        let _ = self.children.set(children);
+
+        Ok(self.children.get().expect("children were just set"))
+    }
+
+    /// Fully parses this folder and all of its descendants into a regular [`FileEntry`].
+    pub fn materialize(&self) -> Result<FileEntry, PakError> {
+        let mut meta = FileEntryMeta::Folder { children: Vec::with_capacity(self.child_count) };
+        for child in self.children()? {
+            match child {
+                LazyEntry::Folder(folder) => meta.push_child(folder.materialize()?),
+                LazyEntry::File(file) => meta.push_child(file.clone()),
+            }
+        }
+
+        Ok(FileEntry { name: Arc::clone(&self.name), meta, contents: None })
+    }
+
+    fn parse_children(&self) -> Result<Vec<LazyEntry>, PakError> {
+        let mut names = Interner::new();
+        let mut input = Stream::new(&self.data[self.body_start..]);
+        let start = input.checkpoint();
+
+        let mut children = Vec::with_capacity(self.child_count);
+        for _ in 0..self.child_count {
+            let (entry, child_count) =
+                parse_file_entry(&mut input, &mut names, self.version).map_err(into_pak_error)?;
+
+            match entry.kind() {
+                FileEntryKind::Folder => {
+                    let body_start = self.body_start + input.checkpoint().offset_from(&start);
+                    skip_entries(&mut input, child_count, self.version.file_meta_len())
+                        .map_err(into_pak_error)?;
+
+                    children.push(LazyEntry::Folder(LazyFolder {
+                        name: entry.name,
+                        child_count,
+                        body_start,
+                        data: Arc::clone(&self.data),
+                        version: self.version,
+                        children: Default::default(),
+                    }));
+                }
+                FileEntryKind::File => children.push(LazyEntry::File(entry)),
+            }
+        }
+
+        Ok(children)
+    }
+}
+
+/// A child of a [`LazyFolder`].
+#[derive(Debug, Clone)]
+pub enum LazyEntry {
+    Folder(LazyFolder),
+    File(FileEntry),
+}
+
+impl LazyEntry {
+    /// Entry's name
+    pub fn name(&self) -> &str {
+        match self {
+            LazyEntry::Folder(folder) => folder.name(),
+            LazyEntry::File(file) => file.name(),
+        }
+    }
+
+    /// What kind of entry this is
+    pub fn kind(&self) -> FileEntryKind {
+        match self {
+            LazyEntry::Folder(_) => FileEntryKind::Folder,
+            LazyEntry::File(_) => FileEntryKind::File,
+        }
+    }
+}
+
+impl PakFile {
+    /// Returns the byte range of the deferred FILE entries if this pak was parsed with
+    /// [`PakFile::parse_lazy`].
+    pub fn lazy_file_range(&self) -> Option<Range<usize>> {
+        self.chunks().iter().find_map(|chunk| match chunk {
+            Chunk::LazyFile { entries } => Some(entries.clone()),
+            _ => None,
+        })
+    }
+
+    /// Builds a [`LazyFileTable`] from `data`, which must be the same bytes this pak was
+    /// parsed from. Returns `None` if this pak has no deferred FILE chunk.
+    pub fn lazy_file_table(&self, data: &[u8]) -> Option<Result<LazyFileTable, PakError>> {
+        let range = self.lazy_file_range()?;
+        let Some(entries) = data.get(range) else {
+            return Some(Err(PakError::ParserError(ContextError::new().into())));
+        };
+
+        Some(LazyFileTable::with_version(entries, self.version().unwrap_or_default()))
+    }
+}
+
+/// Walks past `count` entries (and all of their descendants) without allocating.
+fn skip_entries(input: &mut Stream, count: usize, file_meta_len: usize) -> WResult<()> {
+    for _ in 0..count {
+        let kind = u8(input)?;
+        let name_len = u8(input)?;
+        take(name_len).void().parse_next(input)?;
+
+        if kind == 0 {
+            let children = le_u32(input)?;
+            skip_entries(input, children as usize, file_meta_len)?;
+        } else {
+            take(file_meta_len).void().parse_next(input)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn into_pak_error(err: ErrMode<ContextError>) -> PakError {
+    // The whole table is in memory, so running out of input means it's truncated.
+    PakError::ParserError(ParserDiagnostic::new(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_pak::push_raw_file;
+    use crate::test_pak::push_raw_folder;
+
+    /// root/{scripts/{Game/{a.c}, b.c}, c.conf}
+    fn build_table() -> Vec<u8> {
+        let mut buf = Vec::new();
+        push_raw_folder(&mut buf, "", 2);
+        push_raw_folder(&mut buf, "scripts", 2);
+        push_raw_folder(&mut buf, "Game", 1);
+        push_raw_file(&mut buf, "a.c", 0x10, 4);
+        push_raw_file(&mut buf, "b.c", 0x20, 4);
+        push_raw_file(&mut buf, "c.conf", 0x30, 4);
+        buf
+    }
+
+    #[test]
+    fn children_are_parsed_on_demand() {
+        let table = LazyFileTable::new(build_table()).expect("failed to parse table");
+        let root = table.root();
+        assert_eq!(root.child_count(), 2);
+        assert!(!root.is_loaded());
+
+        let children = root.children().expect("failed to parse root children");
+        assert!(root.is_loaded());
+        let names: Vec<&str> = children.iter().map(LazyEntry::name).collect();
+        assert_eq!(names, ["scripts", "c.conf"]);
+
+        let LazyEntry::Folder(scripts) = &children[0] else { panic!("scripts is not a folder") };
+        assert!(!scripts.is_loaded());
+        let names: Vec<&str> = scripts.children().unwrap().iter().map(LazyEntry::name).collect();
+        assert_eq!(names, ["Game", "b.c"]);
+
+        let LazyEntry::File(conf) = &children[1] else { panic!("c.conf is not a file") };
+        assert!(matches!(conf.meta(), FileEntryMeta::File { offset: 0x30, .. }));
+    }
+
+    #[test]
+    fn materialize_matches_eager_tree() {
+        let table = LazyFileTable::new(build_table()).unwrap();
+        let root = table.root().materialize().unwrap();
+
+        let FileEntryMeta::Folder { children } = root.meta() else { panic!("root is a file") };
+        let FileEntryMeta::Folder { children: scripts } = children[0].meta() else {
+            panic!("scripts is a file")
+        };
+        let FileEntryMeta::Folder { children: game } = scripts[0].meta() else {
+            panic!("Game is a file")
+        };
+        assert_eq!(game[0].name(), "a.c");
+    }
+
+    #[test]
+    fn truncated_table_is_an_error() {
+        let mut data = build_table();
+        data.truncate(data.len() - 4);
+
+        let table = LazyFileTable::new(data).unwrap();
+        assert!(table.root().children().is_err());
+    }
+}
@@ -2,16 +2,53 @@
 
 pub use parser::*;
 
+pub mod archive_format;
 /// Async VFS support
 #[cfg(feature = "async_vfs")]
 pub mod async_pak_vfs;
+/// Finding the files which differ between two builds
+#[cfg(feature = "diff")]
+pub mod diff;
+pub mod entry_type;
 pub mod error;
+#[cfg(feature = "vfs")]
+pub mod extract;
+pub mod glob;
+/// Hiding loose files listed in an ignore file
+#[cfg(feature = "vfs")]
+pub mod ignore_vfs;
+pub mod intern;
+pub mod lazy;
+pub mod manifest;
+/// Merging several paks into one VFS
+#[cfg(feature = "vfs")]
+pub mod pak_set;
 /// VFS support
 #[cfg(feature = "vfs")]
 pub mod pak_vfs;
+/// Parsing many paks concurrently
+#[cfg(feature = "parallel")]
+pub mod parallel;
 mod parser;
+pub mod references;
+/// Signed manifests of file hashes
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod string_table;
+#[cfg(test)]
+mod test_pak;
+/// Checking that every file in a pak reads back intact
+#[cfg(feature = "verify")]
+pub mod verify;
+pub mod version;
 #[cfg(any(feature = "vfs", feature = "async_vfs"))]
 pub use vfs;
+pub mod walk;
 pub use winnow;
 #[cfg(feature = "vfs")]
 pub mod wrappers;
+/// Writing paks
+pub mod writer;
+/// Zip archive support
+#[cfg(feature = "zip")]
+pub mod zip_vfs;
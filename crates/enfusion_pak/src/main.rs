@@ -1,187 +1,287 @@
-use std::collections::VecDeque;
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use clap::Parser;
+use clap::Subcommand;
+use color_eyre::eyre::bail;
 use enfusion_pak::Chunk;
 use enfusion_pak::FileEntry;
 use enfusion_pak::FileEntryMeta;
 use enfusion_pak::PakFile;
 use enfusion_pak::RcFileEntry;
+use enfusion_pak::glob::glob_matches;
+use enfusion_pak::pak_set::PakSet;
+use enfusion_pak::pak_vfs::Prime;
+use enfusion_pak::vfs::VfsPath;
 use enfusion_pak::wrappers::bytes::BytesPakFileWrapper;
 use humansize::BINARY;
 use humansize::format_size;
+use memmap2::Mmap;
+
+type Pak = BytesPakFileWrapper<Mmap>;
 
 /// Parser for Enfusion game engine `.pak` files
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Print long file information
-    #[arg(long, short)]
-    long: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the files in the paks.
+    List {
+        /// Print each file's offset, sizes, compression and timestamp.
+        #[arg(long, short)]
+        long: bool,
+
+        /// Virtually merge contents of files together so that duplicate directories
+        /// across multiple `.pak` files are treated as a single filesystem entry.
+        #[arg(long, short)]
+        merged: bool,
+
+        /// Path to either a single file or a directory containing `.pak` files.
+        file: PathBuf,
+
+        /// Only list files matching this glob, e.g. `scripts/**/*.c`.
+        glob: Option<String>,
+    },
+
+    /// Write files out of the paks, keeping their paths.
+    Extract {
+        /// Path to either a single file or a directory containing `.pak` files.
+        file: PathBuf,
 
-    /// Virtually merge contents of files together so that duplicate directories
-    /// across multiple `.pak` files are treated as a single filesystem entry.
-    #[arg(long, short)]
-    merged: bool,
+        /// File or folder to extract, e.g. `scripts/Game`, or a glob such as `**/*.conf`.
+        /// Extracts everything if omitted.
+        path: Option<String>,
 
-    /// Path to either a single file or a directory containing `.pak` files.
-    file: PathBuf,
+        /// Directory to write files to.
+        #[arg(long, short, default_value = ".")]
+        output: PathBuf,
+    },
+
+    /// Print a summary of each pak's chunks.
+    Info {
+        /// Path to either a single file or a directory containing `.pak` files.
+        file: PathBuf,
+    },
+
+    /// Check that every file in the paks can be read and decompresses to its recorded size.
+    Verify {
+        /// Path to either a single file or a directory containing `.pak` files.
+        file: PathBuf,
+    },
+
+    /// Write a file's contents to stdout. Paks earlier in the directory take precedence.
+    Cat {
+        /// Path to either a single file or a directory containing `.pak` files.
+        file: PathBuf,
+
+        /// Path of the file inside the paks, e.g. `scripts/Game/game.c`.
+        path: String,
+    },
+
+    /// Print each pak's files with their sizes, compression and timestamps as JSON, keyed
+    /// by pak file name, for diffing builds with other tools.
+    Manifest {
+        /// Path to either a single file or a directory containing `.pak` files.
+        file: PathBuf,
+    },
 }
 
-pub fn add_num(integers: &mut Vec<u32>) {
-    integers.push(0);
+/// The `.pak` files at `path`, which is either a pak or a directory of them.
+fn pak_paths(path: &Path) -> color_eyre::Result<Vec<PathBuf>> {
+    if !path.exists() {
+        bail!("{} does not exist", path.display());
+    }
+
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut pak_files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        if let Some("pak") = path.extension().and_then(OsStr::to_str) {
+            pak_files.push(path);
+        }
+    }
+    pak_files.sort();
+
+    Ok(pak_files)
 }
 
-fn parse_pak_files<P: AsRef<Path>>(files: &[P], args: &Args) -> color_eyre::Result<()> {
+/// Parses the paks at `path`. Paks which fail to parse are reported and skipped.
+fn load_paks(path: &Path) -> color_eyre::Result<Vec<Arc<Pak>>> {
     let mut parsed_files = Vec::new();
-
-    for file_path in files {
-        let file_path: &Path = file_path.as_ref();
-        let file = std::fs::File::open(file_path)?;
+    for file_path in pak_paths(path)? {
+        let file = std::fs::File::open(&file_path)?;
         let mmap = unsafe { memmap2::Mmap::map(&file)? };
 
         match PakFile::parse(&mmap) {
             Ok(pak_file) => {
-                parsed_files.push(BytesPakFileWrapper::new(
-                    file_path.to_path_buf(),
-                    mmap,
-                    pak_file,
-                ));
+                parsed_files.push(Arc::new(BytesPakFileWrapper::new(file_path, mmap, pak_file)));
             }
             Err(e) => {
-                eprintln!("Error parsing {file_path:?}: {e:?}");
+                eprintln!("Error parsing {}: {e}", file_path.display());
             }
         }
     }
 
-    if args.merged {
-        let mut merged_file = if let Some(i) =
-            parsed_files.iter().rev().position(|file| file.pak_file().file_chunk().is_some())
-        {
-            parsed_files.remove(i)
-        } else {
-            eprintln!("No data files contained a FILE chunk");
-            return Ok(());
-        };
+    Ok(parsed_files)
+}
 
-        let Chunk::File { fs: merged_fs } =
-            merged_file.pak_file_mut().file_chunk_mut().expect("??")
-        else {
-            unreachable!()
-        };
+/// The paks' combined contents, with earlier paks taking precedence.
+fn mount(paks: Vec<Arc<Pak>>) -> color_eyre::Result<VfsPath> {
+    Ok(VfsPath::new(PakSet::new(paks)?))
+}
 
-        for other in parsed_files {
-            let Some(Chunk::File { fs: other_fs }) = other.pak_file().file_chunk() else {
-                continue;
-            };
+fn root_entry(pak: &PakFile) -> Option<&RcFileEntry> {
+    match pak.file_chunk()? {
+        Chunk::File { fs } => Some(fs),
+        _ => None,
+    }
+}
+
+/// Calls `f` with the path (without a leading `/`) and entry of every file under `root`.
+fn for_each_file(root: &FileEntry, mut f: impl FnMut(&str, &FileEntry)) {
+    for (path, entry) in root.walk() {
+        if matches!(entry.meta(), FileEntryMeta::File { .. }) {
+            f(&path, entry);
+        }
+    }
+}
 
-            RcFileEntry::get_mut(merged_fs)
-                .expect("could not get merged_fs as mut")
-                .merge_ref(RcFileEntry::clone(other_fs));
+fn cmd_list(file: &Path, long: bool, merged: bool, glob: Option<&str>) -> color_eyre::Result<()> {
+    let paks = load_paks(file)?;
+    let print_entries = |root: &FileEntry, shown: &dyn Fn(&str) -> bool| {
+        for_each_file(root, |path, entry| {
+            if !shown(path) || glob.is_some_and(|glob| !glob_matches(glob, path)) {
+                return;
+            }
+            if long {
+                print_long_entry(path, entry);
+            } else {
+                println!("{path}");
+            }
+        })
+    };
+
+    if merged {
+        if paks.iter().all(|pak| root_entry(pak.pak_file()).is_none()) {
+            bail!("No data files contained a FILE chunk");
         }
 
-        print_pak_file_chunk_details(merged_fs, args);
+        let set = PakSet::new(paks)?;
+        for (idx, pak) in set.paks().iter().enumerate() {
+            if let Some(root) = root_entry(pak.pak_file()) {
+                // Skip files shadowed by an earlier pak
+                print_entries(root, &|path| {
+                    set.file_meta(&format!("/{path}")).is_some_and(|meta| meta.pak == idx)
+                });
+            }
+        }
     } else {
-        for (idx, pak) in parsed_files.iter().enumerate() {
-            println!(
-                "File: {}",
-                files
-                    .get(idx)
-                    .expect("failed to get file path?")
-                    .as_ref()
-                    .to_str()
-                    .expect("failed to convert pak file path to str")
-            );
-
-            print_pak_file(pak.pak_file(), args)?;
+        for pak in &paks {
+            println!("File: {}", pak.path().display());
+            if let Some(root) = root_entry(pak.pak_file()) {
+                print_entries(root, &|_| true);
+            }
+            println!();
         }
     }
 
     Ok(())
 }
 
-fn print_pak_file_chunk_details(fs: &FileEntry, args: &Args) {
-    let mut fs_queue = VecDeque::new();
-    fs_queue.push_front((PathBuf::from("Root"), fs));
+fn print_long_entry(path: &str, entry: &FileEntry) {
+    let meta = entry.meta();
+    let FileEntryMeta::File { offset, compressed_len, decompressed_len, compressed, .. } = meta
+    else {
+        return;
+    };
 
-    while let Some((parent_path, next)) = fs_queue.pop_front() {
-        let this_path = parent_path.join(next.name());
-        let meta = next.meta();
-        match meta {
-            FileEntryMeta::Folder { children } => {
-                if children.is_empty() {
-                    println!("\t{}", this_path.to_str().expect("failed to convert path to str"));
-                }
+    let timestamp = match meta.parsed_timestamp() {
+        Some(timestamp) => timestamp.to_string(),
+        None => "-".to_string(),
+    };
+    println!(
+        "{offset:#010X} {:>10} {:>10} {:<4} {timestamp:<19} {path}",
+        format_size(*compressed_len, BINARY),
+        format_size(*decompressed_len, BINARY),
+        if *compressed != 0 { "zlib" } else { "-" },
+    );
+}
 
-                children.iter().for_each(|child| fs_queue.push_back((this_path.clone(), child)));
-            }
-            FileEntryMeta::File {
-                offset,
-                compressed_len,
-                decompressed_len,
-                unk,
-                unk2,
-                compressed,
-                compression_level,
-                timestamp,
-            } => {
-                println!("\t{}", this_path.to_str().expect("failed to convert path to str"));
-
-                if args.long {
-                    println!("\t\tOffset: {:#X}", *offset);
-                    println!(
-                        "\t\tCompressed Size: {} ({} bytes)",
-                        format_size(*compressed_len, BINARY),
-                        *compressed_len
-                    );
-                    println!(
-                        "\t\tDecompressed Size: {} ({} bytes)",
-                        format_size(*decompressed_len, BINARY),
-                        *decompressed_len
-                    );
-                    println!("\t\tUnknown #1: {:#X}", *unk);
-                    println!("\t\tUnknown #2: {:#X}", *unk2);
-                    println!(
-                        "\t\tCompression Flags: {:#X}",
-                        ((*compressed as u16) << 8) | (*compression_level as u16)
-                    );
-                    println!(
-                        "\t\tTimestamp: {:?} ({})",
-                        meta.parsed_timestamp().expect("file has invalid timestamp"),
-                        *timestamp
-                    )
-                }
-            }
-            _ => {
-                // we don't care about other chunk kinds
-            }
-        }
+fn cmd_extract(file: &Path, path: Option<&str>, output: &Path) -> color_eyre::Result<()> {
+    let target = path.map(|path| path.trim_matches('/'));
+    let matches = |path: &str| {
+        let Some(target) = target else {
+            return true;
+        };
+
+        glob_matches(target, path)
+            || path.strip_prefix(target).is_some_and(|rest| rest.starts_with('/'))
+    };
+
+    let mut file_count = 0;
+    for pak in load_paks(file)? {
+        file_count += pak.pak_file().extract_matching(&*pak, output, matches)?;
+    }
+
+    if file_count == 0 {
+        bail!("No files matched");
     }
+    println!("Wrote {file_count} files to {}", output.display());
+
+    Ok(())
 }
 
-fn print_pak_file(pak_file: &PakFile, args: &Args) -> color_eyre::Result<()> {
-    for chunk in pak_file.chunks() {
-        println!("Chunk {:?}", chunk.kind());
-        match chunk {
-            Chunk::Form { file_size, pak_file_type } => {
-                println!("\tSize: {} ({} bytes)", format_size(*file_size, BINARY), *file_size);
-                println!("\tVersion: {:?}", *pak_file_type);
-            }
-            Chunk::Head { version, unknown_data } => {
-                println!("\tVersion: {:#X}", *version);
-                println!("\tUnknown Data Len: {} bytes", (unknown_data.end - unknown_data.start));
-            }
-            Chunk::Data { data } => {
-                println!("\tSize: {} ({} bytes)", format_size(data.len(), BINARY), data.len());
-            }
-            Chunk::File { fs } => {
-                print_pak_file_chunk_details(fs, args);
-            }
-            Chunk::Unknown(_) => todo!(),
-            _ => {
-                eprintln!("Unhandled chunk kind: {:?}", chunk.kind())
+fn cmd_info(file: &Path) -> color_eyre::Result<()> {
+    for pak in load_paks(file)? {
+        println!("File: {}", pak.path().display());
+        for chunk in pak.pak_file().chunks() {
+            println!("Chunk {:?}", chunk.kind());
+            match chunk {
+                Chunk::Form { file_size, pak_file_type } => {
+                    println!("\tSize: {} ({} bytes)", format_size(*file_size, BINARY), *file_size);
+                    println!("\tType: {:?}", *pak_file_type);
+                }
+                Chunk::Head { version, unknown_data } => {
+                    println!("\tVersion: {:#X}", *version);
+                    println!("\tUnknown Data Len: {} bytes", unknown_data.len());
+                }
+                Chunk::Data { data } => {
+                    println!("\tSize: {} ({} bytes)", format_size(data.len(), BINARY), data.len());
+                }
+                Chunk::File { fs } => {
+                    let (mut files, mut compressed, mut decompressed) = (0, 0u64, 0u64);
+                    for_each_file(fs, |_, entry| {
+                        if let FileEntryMeta::File { compressed_len, decompressed_len, .. } =
+                            entry.meta()
+                        {
+                            files += 1;
+                            compressed += u64::from(*compressed_len);
+                            decompressed += u64::from(*decompressed_len);
+                        }
+                    });
+                    println!("\tFiles: {files}");
+                    println!("\tCompressed Size: {}", format_size(compressed, BINARY));
+                    println!("\tDecompressed Size: {}", format_size(decompressed, BINARY));
+                }
+                Chunk::Unknown { tag, data } => {
+                    println!("\tTag: {}", tag.escape_ascii());
+                    println!("\tSize: {} ({} bytes)", format_size(data.len(), BINARY), data.len());
+                }
+                _ => {
+                    // we don't care about other chunk kinds
+                }
             }
         }
         println!();
@@ -190,26 +290,98 @@ fn print_pak_file(pak_file: &PakFile, args: &Args) -> color_eyre::Result<()> {
     Ok(())
 }
 
-fn main() -> color_eyre::Result<()> {
-    let args = Args::parse();
+fn cmd_manifest(file: &Path) -> color_eyre::Result<()> {
+    let mut manifests = BTreeMap::new();
+    for pak in load_paks(file)? {
+        let name = pak.path().file_name().unwrap_or_default().to_string_lossy().into_owned();
+        manifests.insert(name, pak.pak_file().manifest());
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, &manifests)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+fn cmd_verify(file: &Path) -> color_eyre::Result<()> {
+    let mut checked = 0;
+    let mut failed = 0;
+    for pak in load_paks(file)? {
+        let Some(root) = root_entry(pak.pak_file()) else {
+            eprintln!("{}: no FILE chunk", pak.path().display());
+            failed += 1;
+            continue;
+        };
+
+        for_each_file(root, |path, entry| {
+            checked += 1;
+            if let Err(e) = verify_file(&pak, entry) {
+                eprintln!("{}: {path}: {e}", pak.path().display());
+                failed += 1;
+            }
+        });
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {checked} files failed verification");
+    }
+    println!("{checked} files OK");
+
+    Ok(())
+}
 
-    if !args.file.exists() {
-        println!("File does not exist");
+/// Reads a file's data and checks it decompresses to the size recorded for it.
+fn verify_file(pak: &Pak, entry: &FileEntry) -> color_eyre::Result<()> {
+    let FileEntryMeta::File { decompressed_len, compressed, .. } = entry.meta() else {
+        return Ok(());
+    };
+    let Some(range) = pak.pak_file().file_data_range(entry) else {
         return Ok(());
+    };
+
+    if range.end > pak.source().len() {
+        bail!("data at {:#X}..{:#X} lies past the end of the pak", range.start, range.end);
     }
 
-    let mut pak_files = Vec::new();
-    if args.file.is_dir() {
-        for entry in std::fs::read_dir(&args.file)? {
-            let entry = entry?;
-            let path = entry.path();
-            if let Some("pak") = path.extension().and_then(OsStr::to_str) {
-                pak_files.push(path);
-            }
-        }
+    let data = pak.prime_file(range)?;
+    let len = if *compressed != 0 {
+        let mut decoder = flate2::read::ZlibDecoder::new(data.as_ref());
+        std::io::copy(&mut decoder, &mut std::io::sink())?
     } else {
-        pak_files.push(args.file.clone());
+        data.as_ref().len() as u64
+    };
+
+    if len != u64::from(*decompressed_len) {
+        bail!("read {len} bytes, expected {decompressed_len}");
+    }
+
+    Ok(())
+}
+
+fn cmd_cat(file: &Path, path: &str) -> color_eyre::Result<()> {
+    let paks = load_paks(file)?;
+    let entry = mount(paks)?.join(path.trim_start_matches('/'))?;
+    if !entry.is_file()? {
+        bail!("{path} is not a file in the paks");
     }
 
-    parse_pak_files(pak_files.as_ref(), &args)
+    std::io::copy(&mut entry.open_file()?, &mut std::io::stdout().lock())?;
+
+    Ok(())
+}
+
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+    let args = Args::parse();
+
+    match args.command {
+        Command::List { long, merged, file, glob } => {
+            cmd_list(&file, long, merged, glob.as_deref())
+        }
+        Command::Extract { file, path, output } => cmd_extract(&file, path.as_deref(), &output),
+        Command::Info { file } => cmd_info(&file),
+        Command::Verify { file } => cmd_verify(&file),
+        Command::Cat { file, path } => cmd_cat(&file, &path),
+        Command::Manifest { file } => cmd_manifest(&file),
+    }
 }
@@ -0,0 +1,118 @@
+//! A listing of every file in a pak and how it's stored, for comparing builds with other
+//! tools. With the `serde` feature, [`PakManifest`] serializes to JSON or any other format
+//! serde supports.
+
+use jiff::civil::DateTime;
+
+use crate::Chunk;
+use crate::FileEntryMeta;
+use crate::PakFile;
+
+/// Every file in a pak, as returned by [`PakFile::manifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PakManifest {
+    /// Format version from the HEAD chunk, as stored.
+    pub version: Option<u32>,
+    /// Sorted by path.
+    pub files: Vec<ManifestFile>,
+}
+
+/// One file in a [`PakManifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManifestFile {
+    /// Path inside the pak without a leading `/`.
+    pub path: String,
+    pub compressed_len: u32,
+    pub decompressed_len: u32,
+    /// Whether the data is zlib-compressed.
+    pub compressed: bool,
+    pub compression_level: u8,
+    /// When the file was last modified, in whatever time zone the pak was built in. `None`
+    /// if the stored timestamp isn't a valid date.
+    pub modified: Option<DateTime>,
+}
+
+impl PakFile {
+    /// Lists every file in the pak with its sizes, compression and timestamp, sorted by
+    /// path.
+    ///
+    /// Lazily parsed paks have no entries to list, and give a manifest with no files.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use enfusion_pak::PakFile;
+    ///
+    /// let data = std::fs::read("example.pak").unwrap();
+    /// let manifest = PakFile::parse(&data).unwrap().manifest();
+    /// for file in &manifest.files {
+    ///     println!("{} {}", file.decompressed_len, file.path);
+    /// }
+    /// ```
+    pub fn manifest(&self) -> PakManifest {
+        let mut files = Vec::new();
+        if let Some(Chunk::File { fs }) = self.file_chunk() {
+            for (path, entry) in fs.walk() {
+                let meta = entry.meta();
+                let FileEntryMeta::File {
+                    compressed_len,
+                    decompressed_len,
+                    compressed,
+                    compression_level,
+                    ..
+                } = meta
+                else {
+                    continue;
+                };
+                files.push(ManifestFile {
+                    path,
+                    compressed_len: *compressed_len,
+                    decompressed_len: *decompressed_len,
+                    compressed: *compressed != 0,
+                    compression_level: *compression_level,
+                    modified: meta.parsed_timestamp(),
+                });
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        PakManifest { version: self.version().map(|version| version.raw()), files }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_pak::modified;
+    use crate::test_pak::pak_with;
+
+    #[test]
+    fn lists_files_by_path() {
+        let pak = pak_with(&[("scripts/b.c", "class B {}"), ("a.conf", "")]);
+
+        let manifest = pak.manifest();
+        assert_eq!(manifest.version, Some(crate::version::PakVersion::LATEST.raw()));
+        let paths: Vec<&str> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["a.conf", "scripts/b.c"]);
+        assert_eq!(
+            manifest.files[1],
+            ManifestFile {
+                path: "scripts/b.c".to_string(),
+                compressed_len: 10,
+                decompressed_len: 10,
+                compressed: false,
+                compression_level: 0,
+                modified: Some(modified()),
+            }
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&manifest).unwrap();
+            assert!(json.contains(r#""path":"scripts/b.c","compressed_len":10"#));
+            assert_eq!(serde_json::from_str::<PakManifest>(&json).unwrap(), manifest);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Several paks mounted as one filesystem.
+//!
+//! Addons and game data ship as many paks whose FILE chunks describe parts of the same
+//! tree. A [`PakSet`] merges them up front, remembering which pak backs each file, so
+//! reads go straight to that pak instead of probing every layer of an overlay.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use fskit::Metadata;
+use fskit::VfsTree;
+use fskit::VfsTreeBuilder;
+use vfs::VfsError;
+use vfs::VfsMetadata;
+use vfs::error::VfsErrorKind;
+
+use crate::PakFile;
+use crate::error::PakError;
+use crate::pak_vfs::PakFileMeta;
+use crate::pak_vfs::PakFileReader;
+use crate::pak_vfs::Prime;
+use crate::pak_vfs::STREAMING_THRESHOLD;
+use crate::pak_vfs::open_pak_data;
+use crate::pak_vfs::tree_entries;
+
+/// A file in a [`PakSet`] and the pak holding it.
+#[derive(Debug, Clone)]
+pub struct PakSetFileMeta {
+    /// Index of the backing pak in [`PakSet::paks`].
+    pub pak: usize,
+    pub file: PakFileMeta,
+}
+
+impl Metadata for PakSetFileMeta {
+    fn len(&self) -> u64 {
+        self.file.len()
+    }
+}
+
+/// Read-only VFS over the merged contents of several paks.
+///
+/// When more than one pak holds the same path, the earliest pak's file is used, as with an
+/// overlay of [`PakVfs`](crate::pak_vfs::PakVfs)s. Paks without a FILE chunk contribute
+/// nothing.
+///
+/// Clones share the paks and the merged tree, so they're cheap, and a set of `Arc`-wrapped
+/// paks is `Send + Sync`: one loaded set can serve every thread without parsing anything
+/// again.
+#[derive(Debug, Clone)]
+pub struct PakSet<T> {
+    paks: Arc<[T]>,
+    tree: Arc<VfsTree<PakSetFileMeta>>,
+}
+
+impl<T> PakSet<T>
+where
+    T: std::ops::Deref,
+    T::Target: AsRef<PakFile>,
+{
+    /// Merges the filesystems of `paks`, earliest first. Lazily parsed paks have their FILE
+    /// chunks parsed here.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a lazily parsed pak's FILE chunk can't be read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use enfusion_pak::PakFile;
+    /// use enfusion_pak::pak_set::PakSet;
+    /// use enfusion_pak::vfs::VfsPath;
+    /// use enfusion_pak::wrappers::bytes::BytesPakFileWrapper;
+    ///
+    /// let mut paks = Vec::new();
+    /// for name in ["data.pak", "data001.pak"] {
+    ///     let path = std::path::PathBuf::from(name);
+    ///     let data = std::fs::read(&path).unwrap();
+    ///     let parsed_file = PakFile::parse(&data).unwrap();
+    ///     paks.push(Arc::new(BytesPakFileWrapper::new(path, data, parsed_file)));
+    /// }
+    ///
+    /// let root = VfsPath::new(PakSet::new(paks).unwrap());
+    /// let config = root.join("Configs/Game.conf").unwrap().read_to_string().unwrap();
+    /// ```
+    pub fn new(paks: Vec<T>) -> Result<Self, PakError>
+    where
+        T::Target: Prime,
+    {
+        let mut builder = VfsTreeBuilder::new();
+        let mut seen = HashSet::new();
+        for (idx, pak) in paks.iter().enumerate() {
+            let root = match (**pak).as_ref().root_entry(&**pak) {
+                Ok(root) => root,
+                Err(PakError::MissingFileChunk) => continue,
+                Err(e) => return Err(e),
+            };
+            for (path, file) in tree_entries(&root) {
+                // Folders are shared between paks, files belong to the first pak with them
+                if !seen.insert(path.clone()) {
+                    continue;
+                }
+                builder = match file {
+                    Some(file) => builder.insert(&path, PakSetFileMeta { pak: idx, file }),
+                    None => builder.insert_dir(&path, None),
+                };
+            }
+        }
+
+        Ok(Self { paks: paks.into(), tree: Arc::new(builder.build()) })
+    }
+
+    /// The merged paks, in the order they were given.
+    pub fn paks(&self) -> &[T] {
+        &self.paks
+    }
+
+    pub fn tree(&self) -> &VfsTree<PakSetFileMeta> {
+        &self.tree
+    }
+
+    /// The metadata of the file at `path`, including which pak it's read from.
+    pub fn file_meta(&self, path: &str) -> Option<&PakSetFileMeta> {
+        match self.tree.vfs_lookup(path) {
+            Ok(fskit::VfsEntry::File(meta)) => Some(meta),
+            _ => None,
+        }
+    }
+
+    /// The pak which backs the file at `path`.
+    pub fn pak_for(&self, path: &str) -> Option<&T> {
+        self.file_meta(path).map(|meta| &self.paks[meta.pak])
+    }
+}
+
+impl<T> vfs::FileSystem for PakSet<T>
+where
+    T: std::ops::Deref + Clone + Sync + Send + Debug + 'static,
+    T::Target: AsRef<PakFile> + Prime,
+{
+    fn read_dir(&self, path: &str) -> vfs::VfsResult<Box<dyn Iterator<Item = String> + Send>> {
+        self.tree.vfs_read_dir(path)
+    }
+
+    fn open_file(&self, path: &str) -> vfs::VfsResult<Box<dyn vfs::SeekAndRead + Send>> {
+        let entry = self.tree.vfs_lookup(path)?;
+        let fskit::VfsEntry::File(meta) = entry else {
+            return Err(VfsError::from(VfsErrorKind::Other("not a file".into())));
+        };
+        let pak = &self.paks[meta.pak];
+        if meta.len() >= STREAMING_THRESHOLD {
+            return Ok(Box::new(PakFileReader::new(pak.clone(), &meta.file)));
+        }
+        open_pak_data(pak, &meta.file)
+    }
+
+    fn metadata(&self, path: &str) -> vfs::VfsResult<VfsMetadata> {
+        self.tree.vfs_metadata(path)
+    }
+
+    fn exists(&self, path: &str) -> vfs::VfsResult<bool> {
+        self.tree.vfs_exists(path)
+    }
+
+    fskit::read_only_fs_stubs!();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    use vfs::FileSystem;
+
+    use super::*;
+    use crate::test_pak::pak_bytes;
+    use crate::wrappers::bytes::BytesPakFileWrapper;
+
+    /// A parsed pak holding `files`, given as `(path, contents)`.
+    fn pak(files: &[(&str, &str)]) -> Arc<BytesPakFileWrapper<Vec<u8>>> {
+        let data = pak_bytes(files);
+        let pak = PakFile::parse(&data).unwrap();
+        Arc::new(BytesPakFileWrapper::new(PathBuf::new(), data, pak))
+    }
+
+    fn read(set: &PakSet<Arc<BytesPakFileWrapper<Vec<u8>>>>, path: &str) -> String {
+        let mut contents = String::new();
+        set.open_file(path).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn earlier_paks_take_precedence() {
+        let first = pak(&[("scripts/a.c", "first"), ("scripts/b.c", "only first")]);
+        let second = pak(&[("scripts/a.c", "second"), ("configs/c.conf", "only second")]);
+        let set = PakSet::new(vec![first, second]).unwrap();
+
+        assert_eq!(read(&set, "/scripts/a.c"), "first");
+        assert_eq!(read(&set, "/scripts/b.c"), "only first");
+        assert_eq!(read(&set, "/configs/c.conf"), "only second");
+        assert_eq!(set.file_meta("/scripts/a.c").unwrap().pak, 0);
+        assert_eq!(set.file_meta("/configs/c.conf").unwrap().pak, 1);
+        assert!(Arc::ptr_eq(set.pak_for("/configs/c.conf").unwrap(), &set.paks()[1]));
+        assert!(set.pak_for("/scripts").is_none());
+
+        let mut scripts: Vec<String> = set.read_dir("/scripts").unwrap().collect();
+        scripts.sort();
+        assert_eq!(scripts, ["a.c", "b.c"]);
+    }
+
+    #[test]
+    fn merges_lazily_parsed_paks() {
+        let data = pak_bytes(&[("scripts/a.c", "lazy")]);
+        let lazy = PakFile::parse_lazy(&data).unwrap();
+        let lazy = Arc::new(BytesPakFileWrapper::new(PathBuf::new(), data, lazy));
+        let eager = pak(&[("scripts/a.c", "eager"), ("scripts/b.c", "only eager")]);
+        let set = PakSet::new(vec![lazy, eager]).unwrap();
+
+        assert_eq!(read(&set, "/scripts/a.c"), "lazy");
+        assert_eq!(read(&set, "/scripts/b.c"), "only eager");
+    }
+
+    #[test]
+    fn clones_are_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let set = PakSet::new(vec![pak(&[("scripts/a.c", "shared")])]).unwrap();
+        assert_send_sync(&set);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let clone = set.clone();
+                assert!(std::ptr::eq(clone.paks(), set.paks()));
+                assert!(std::ptr::eq(clone.tree(), set.tree()));
+                scope.spawn(move || assert_eq!(read(&clone, "/scripts/a.c"), "shared"));
+            }
+        });
+    }
+}
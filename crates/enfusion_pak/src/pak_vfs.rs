@@ -1,6 +1,10 @@
 use std::fmt::Debug;
 use std::io::Cursor;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
 use std::ops::Range;
+use std::sync::Arc;
 
 use fskit::Metadata;
 use fskit::VfsTree;
@@ -9,10 +13,11 @@ use vfs::VfsError;
 use vfs::VfsMetadata;
 use vfs::error::VfsErrorKind;
 
-use crate::Chunk;
+use crate::FileEntry;
 use crate::FileEntryMeta;
 use crate::PakFile;
-use crate::RcFileEntry;
+use crate::error::PakError;
+use crate::glob::GlobMatch;
 
 /// Trait which allows for requesting a file be read into memory.
 pub trait Prime {
@@ -24,6 +29,61 @@ pub trait ReadAt {
     fn read_at(&self, file_range: std::ops::Range<usize>) -> Result<impl AsRef<[u8]>, VfsError>;
 }
 
+impl ReadAt for [u8] {
+    fn read_at(&self, file_range: std::ops::Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
+        self.get(file_range).ok_or_else(|| VfsErrorKind::Other("read past the end".into()).into())
+    }
+}
+
+impl PakFile {
+    /// Reads and decompresses `entry`'s contents. `source` reads from the pak this one was
+    /// parsed from; a byte slice of the whole pak works.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use enfusion_pak::PakFile;
+    ///
+    /// let data = std::fs::read("example.pak").unwrap();
+    /// let pak = PakFile::parse(&data).unwrap();
+    /// let Some(enfusion_pak::Chunk::File { fs }) = pak.file_chunk() else { return };
+    /// let entry = fs.get("scripts/Game/game.c").unwrap();
+    /// let contents = pak.read_file(entry, &data[..]).unwrap();
+    /// ```
+    pub fn read_file(
+        &self,
+        entry: &FileEntry,
+        source: &(impl ReadAt + ?Sized),
+    ) -> Result<Vec<u8>, PakError> {
+        if let Some(contents) = entry.staged_contents() {
+            return Ok(contents.to_vec());
+        }
+
+        let (Some(range), FileEntryMeta::File { decompressed_len, compressed, .. }) =
+            (self.file_data_range(entry), entry.meta())
+        else {
+            return Err(PakError::NotAFile(entry.name().to_string()));
+        };
+
+        let stored = source.read_at(range).map_err(|e| std::io::Error::other(e.to_string()))?;
+        let stored = stored.as_ref();
+        if *compressed == 0 {
+            return Ok(stored.to_vec());
+        }
+
+        let mut contents = Vec::with_capacity(*decompressed_len as usize);
+        flate2::read::ZlibDecoder::new(stored).read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+}
+
+/// Files at least this large are streamed by `open_file` rather than decompressed into
+/// memory up front. See [`PakFileReader`].
+pub const STREAMING_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+/// Most stored bytes [`PakFileReader`] primes at once.
+pub(crate) const PRIME_CHUNK_LEN: usize = 64 * 1024;
+
 /// File metadata stored in the VFS tree for each PAK entry.
 #[derive(Debug, Clone)]
 pub struct PakFileMeta {
@@ -31,6 +91,8 @@ pub struct PakFileMeta {
     pub compressed_len: u32,
     pub decompressed_len: u32,
     pub compressed: u8,
+    /// Packed modification time, see [`FileEntryMeta::parsed_timestamp`].
+    pub timestamp: u32,
 }
 
 impl Metadata for PakFileMeta {
@@ -39,52 +101,62 @@ impl Metadata for PakFileMeta {
     }
 }
 
-/// Build a [`VfsTree`] from a parsed PAK file.
-fn build_tree(pak: &PakFile) -> VfsTree<PakFileMeta> {
-    let file_chunk = pak.file_chunk().unwrap();
-    let Chunk::File { fs } = file_chunk else { panic!("file chunk is not a file?") };
+/// Build a [`VfsTree`] from a parsed PAK file, parsing its FILE chunk first if it was
+/// parsed lazily.
+fn build_tree(
+    pak: &PakFile,
+    prime: &(impl Prime + ?Sized),
+) -> Result<VfsTree<PakFileMeta>, PakError> {
+    let root = pak.root_entry(prime)?;
 
     let mut builder = VfsTreeBuilder::new();
-
-    let mut queue = vec![("".to_string(), RcFileEntry::clone(fs))];
-    while let Some((path, current)) = queue.pop() {
-        let this_path = if path == "/" {
-            format!("{}{}", path, current.name())
-        } else {
-            format!("{}/{}", path, current.name())
+    for (path, file) in tree_entries(&root) {
+        builder = match file {
+            Some(meta) => builder.insert(&path, meta),
+            None => builder.insert_dir(&path, None),
         };
+    }
+
+    Ok(builder.build())
+}
 
-        match current.meta() {
-            FileEntryMeta::Folder { children } => {
-                builder = builder.insert_dir(&this_path, None);
-                for child in children {
-                    queue.push((this_path.clone(), RcFileEntry::clone(child)));
-                }
-            }
+/// The VFS path of `root` and every folder and file under it, parents before their
+/// children. Folders have no metadata.
+pub(crate) fn tree_entries(
+    root: &FileEntry,
+) -> impl Iterator<Item = (String, Option<PakFileMeta>)> + '_ {
+    let entries = root.walk().map(|(path, entry)| {
+        let meta = match entry.meta() {
+            FileEntryMeta::Folder { .. } => None,
             FileEntryMeta::File {
-                offset, compressed_len, decompressed_len, compressed, ..
-            } => {
-                builder = builder.insert(
-                    &this_path,
-                    PakFileMeta {
-                        offset: *offset,
-                        compressed_len: *compressed_len,
-                        decompressed_len: *decompressed_len,
-                        compressed: *compressed,
-                    },
-                );
-            }
-        }
-    }
+                offset,
+                compressed_len,
+                decompressed_len,
+                compressed,
+                timestamp,
+                ..
+            } => Some(PakFileMeta {
+                offset: *offset,
+                compressed_len: *compressed_len,
+                decompressed_len: *decompressed_len,
+                compressed: *compressed,
+                timestamp: *timestamp,
+            }),
+        };
+        (format!("/{path}"), meta)
+    });
 
-    builder.build()
+    std::iter::once(("/".to_string(), None)).chain(entries)
 }
 
 /// Synchronous VFS implementation for reading a `.pak` file.
+///
+/// The path tree is shared between clones, so handing the same `PakVfs` to both a
+/// sync and an async overlay is cheap (Arc refcount bump).
 #[derive(Debug, Clone)]
 pub struct PakVfs<T> {
     pub(crate) source: T,
-    tree: VfsTree<PakFileMeta>,
+    tree: Arc<VfsTree<PakFileMeta>>,
 }
 
 impl<T> PakVfs<T>
@@ -92,7 +164,12 @@ where
     T: std::ops::Deref,
     T::Target: AsRef<PakFile>,
 {
-    /// Construct a new `PakVfs` from the provided `source`.
+    /// Construct a new `PakVfs` from the provided `source`. A lazily parsed pak has its FILE
+    /// chunk parsed here.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the pak has no FILE chunk, or its lazily parsed one can't be read.
     ///
     /// # Examples
     ///
@@ -107,20 +184,53 @@ where
     /// let mmap = unsafe { memmap2::Mmap::map(&file).unwrap() };
     /// let parsed_file = PakFile::parse(&mmap).unwrap();
     /// let wrapper = CachingPakFileWrapper::new(path, file, parsed_file);
-    /// let vfs = PakVfs::new(Arc::new(wrapper));
+    /// let vfs = PakVfs::new(Arc::new(wrapper)).unwrap();
     /// ```
-    pub fn new(source: T) -> Self {
-        let pak: &PakFile = source.as_ref();
-        let tree = build_tree(pak);
-        Self { source, tree }
+    pub fn new(source: T) -> Result<Self, PakError>
+    where
+        T::Target: Prime,
+    {
+        let pak: &PakFile = (*source).as_ref();
+        let tree = Arc::new(build_tree(pak, &*source)?);
+        Ok(Self { source, tree })
     }
 
     pub fn tree(&self) -> &VfsTree<PakFileMeta> {
         &self.tree
     }
+
+    /// Every file in the pak whose path matches the glob `pattern`. See
+    /// [`PakFile::find_entries`].
+    pub fn glob(&self, pattern: &str) -> Vec<GlobMatch> {
+        (*self.source).as_ref().find_entries(pattern)
+    }
+
+    /// A handle to every file's metadata which outlives this `PakVfs`'s concrete type.
+    pub fn meta_index(&self) -> PakMetaIndex {
+        PakMetaIndex { tree: Arc::clone(&self.tree) }
+    }
 }
 
-fn open_pak_data<T>(
+/// The FILE chunk metadata of a [`PakVfs`]'s files, shared with it.
+///
+/// Looking a file up never reads its data, so comparing two builds through their indexes
+/// is as quick as walking their paths.
+#[derive(Debug, Clone)]
+pub struct PakMetaIndex {
+    tree: Arc<VfsTree<PakFileMeta>>,
+}
+
+impl PakMetaIndex {
+    /// The metadata of the file at `path`, or `None` if the pak has no such file.
+    pub fn get(&self, path: &str) -> Option<&PakFileMeta> {
+        match self.tree.vfs_lookup(path) {
+            Ok(fskit::VfsEntry::File(meta)) => Some(meta),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn open_pak_data<T>(
     source: &T,
     meta: &PakFileMeta,
 ) -> vfs::VfsResult<Box<dyn vfs::SeekAndRead + Send>>
@@ -149,10 +259,161 @@ where
     Ok(Box::new(Cursor::new(data)))
 }
 
-#[cfg(feature = "arc")]
+/// Reads one file out of a pak without holding all of it in memory.
+///
+/// Stored bytes are primed a chunk at a time, and compressed files are inflated as they're
+/// read. Seeking forwards inflates and discards everything skipped over, and seeking
+/// backwards in a compressed file starts inflating again from the beginning, so prefer
+/// reading front to back.
+pub struct PakFileReader<T> {
+    data: ReaderData<T>,
+    /// Position in the decompressed file.
+    pos: u64,
+    len: u64,
+}
+
+enum ReaderData<T> {
+    Stored(StoredBytes<T>),
+    Compressed(flate2::read::ZlibDecoder<StoredBytes<T>>),
+    /// Only seen while a compressed reader is rewound.
+    Rewinding,
+}
+
+/// A [`Read`] over a range of the pak, as it's stored.
+struct StoredBytes<T> {
+    source: T,
+    range: Range<usize>,
+    pos: usize,
+}
+
+impl<T> Read for StoredBytes<T>
+where
+    T: std::ops::Deref,
+    T::Target: Prime,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let end = self.range.end.min(self.pos.saturating_add(buf.len().min(PRIME_CHUNK_LEN)));
+        if self.pos >= end {
+            return Ok(0);
+        }
+
+        let data = self
+            .source
+            .prime_file(self.pos..end)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let data = data.as_ref();
+        let read = data.len().min(buf.len());
+        buf[..read].copy_from_slice(&data[..read]);
+        self.pos += read;
+
+        Ok(read)
+    }
+}
+
+impl<T> PakFileReader<T>
+where
+    T: std::ops::Deref,
+    T::Target: Prime,
+{
+    /// Creates a reader for the file `meta` describes. `source` is the pak it's in.
+    pub fn new(source: T, meta: &PakFileMeta) -> Self {
+        let start = meta.offset as usize;
+        let range = start..start + meta.compressed_len as usize;
+        let stored = StoredBytes { source, pos: range.start, range };
+        let data = if meta.compressed != 0 {
+            ReaderData::Compressed(flate2::read::ZlibDecoder::new(stored))
+        } else {
+            ReaderData::Stored(stored)
+        };
+
+        Self { data, pos: 0, len: meta.decompressed_len as u64 }
+    }
+
+    /// Goes back to the start of a compressed file.
+    fn rewind_decoder(&mut self) {
+        let data = std::mem::replace(&mut self.data, ReaderData::Rewinding);
+        let ReaderData::Compressed(decoder) = data else {
+            unreachable!("only compressed readers are rewound");
+        };
+
+        let mut stored = decoder.into_inner();
+        stored.pos = stored.range.start;
+        self.data = ReaderData::Compressed(flate2::read::ZlibDecoder::new(stored));
+        self.pos = 0;
+    }
+}
+
+impl<T> Read for PakFileReader<T>
+where
+    T: std::ops::Deref,
+    T::Target: Prime,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = match &mut self.data {
+            ReaderData::Stored(stored) => stored.read(buf)?,
+            ReaderData::Compressed(decoder) => decoder.read(buf)?,
+            ReaderData::Rewinding => unreachable!("reader is left rewinding"),
+        };
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<T> Seek for PakFileReader<T>
+where
+    T: std::ops::Deref,
+    T::Target: Prime,
+{
+    fn seek(&mut self, from: SeekFrom) -> std::io::Result<u64> {
+        let target = match from {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        let Some(target) = target else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ));
+        };
+
+        if let ReaderData::Stored(stored) = &mut self.data {
+            stored.pos = stored.range.start.saturating_add(target.min(self.len) as usize);
+            self.pos = target;
+            return Ok(target);
+        }
+
+        if target < self.pos {
+            self.rewind_decoder();
+        }
+        let skip = target - self.pos;
+        std::io::copy(&mut self.by_ref().take(skip), &mut std::io::sink())?;
+        // Seeking past the end is allowed, reads there just return nothing
+        self.pos = target;
+
+        Ok(target)
+    }
+}
+
+impl<T> PakVfs<T>
+where
+    T: std::ops::Deref + Clone,
+    T::Target: AsRef<PakFile> + Prime,
+{
+    /// Opens the file at `path` as a [`PakFileReader`], however large it is.
+    pub fn open_streaming(&self, path: &str) -> vfs::VfsResult<PakFileReader<T>> {
+        let entry = self.tree.vfs_lookup(path)?;
+        let fskit::VfsEntry::File(meta) = entry else {
+            return Err(VfsError::from(VfsErrorKind::Other("not a file".into())));
+        };
+        Ok(PakFileReader::new(self.source.clone(), meta))
+    }
+}
+
 impl<T> vfs::FileSystem for PakVfs<T>
 where
-    T: std::ops::Deref + Sync + Send + Debug + 'static,
+    T: std::ops::Deref + Clone + Sync + Send + Debug + 'static,
     T::Target: AsRef<PakFile> + Prime,
 {
     fn read_dir(&self, path: &str) -> vfs::VfsResult<Box<dyn Iterator<Item = String> + Send>> {
@@ -164,6 +425,9 @@ where
         let fskit::VfsEntry::File(meta) = entry else {
             return Err(VfsError::from(VfsErrorKind::Other("not a file".into())));
         };
+        if meta.len() >= STREAMING_THRESHOLD {
+            return Ok(Box::new(PakFileReader::new(self.source.clone(), meta)));
+        }
         open_pak_data(&self.source, meta)
     }
 
@@ -177,3 +441,126 @@ where
 
     fskit::read_only_fs_stubs!();
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::Chunk;
+    use crate::test_pak::pak_bytes;
+    use crate::wrappers::bytes::BytesPakFileWrapper;
+
+    struct Bytes(Vec<u8>);
+
+    impl Prime for Bytes {
+        fn prime_file(&self, file_range: Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
+            Ok(&self.0[file_range])
+        }
+    }
+
+    /// A file long enough to need several primed chunks.
+    fn contents() -> Vec<u8> {
+        (0..PRIME_CHUNK_LEN * 3).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn reader(source: &Bytes, compressed: bool) -> PakFileReader<&Bytes> {
+        let meta = PakFileMeta {
+            offset: 4,
+            compressed_len: (source.0.len() - 4) as u32,
+            decompressed_len: contents().len() as u32,
+            compressed: compressed as u8,
+            timestamp: 0,
+        };
+        PakFileReader::new(source, &meta)
+    }
+
+    fn pak_with(stored: &[u8]) -> Bytes {
+        let mut pak = b"PAK!".to_vec();
+        pak.extend_from_slice(stored);
+        Bytes(pak)
+    }
+
+    fn check_reads(reader: &mut PakFileReader<&Bytes>) {
+        let expected = contents();
+
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert!(all == expected, "contents differ");
+
+        let mut buf = [0; 16];
+        reader.seek(SeekFrom::Start(PRIME_CHUNK_LEN as u64 + 10)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, expected[PRIME_CHUNK_LEN + 10..][..16]);
+
+        reader.seek(SeekFrom::Current(-100)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, expected[PRIME_CHUNK_LEN + 10 + 16 - 100..][..16]);
+
+        assert_eq!(reader.seek(SeekFrom::End(-16)).unwrap(), expected.len() as u64 - 16);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, expected[expected.len() - 16..]);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        reader.seek(SeekFrom::End(10)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-(expected.len() as i64) - 20)).is_err());
+    }
+
+    #[test]
+    fn reads_files_by_entry() {
+        let data = pak_bytes(&[("scripts/a.c", "class A {}")]);
+        let pak = PakFile::parse(&data).unwrap();
+        let Some(Chunk::File { fs }) = pak.file_chunk() else { panic!("no FILE chunk") };
+
+        let entry = fs.get("scripts/a.c").unwrap();
+        let range = pak.file_data_range(entry).unwrap();
+        assert!(pak.data_range().unwrap().contains(&range.start));
+        assert_eq!(&data[range], b"class A {}");
+        assert_eq!(pak.read_file(entry, &data[..]).unwrap(), b"class A {}");
+
+        let folder = fs.get("scripts").unwrap();
+        assert_eq!(pak.file_data_range(folder), None);
+        assert!(matches!(pak.read_file(folder, &data[..]), Err(PakError::NotAFile(_))));
+        assert!(pak.read_file(entry, &data[..8]).is_err());
+    }
+
+    #[test]
+    fn mounts_lazily_parsed_paks() {
+        let data = pak_bytes(&[("scripts/a.c", "class A {}"), ("configs/b.conf", "b")]);
+        let pak = PakFile::parse_lazy(&data).unwrap();
+        assert!(pak.file_chunk().is_none());
+
+        let vfs =
+            PakVfs::new(Arc::new(BytesPakFileWrapper::new("lazy.pak".into(), data, pak))).unwrap();
+        let root = vfs::VfsPath::new(vfs);
+        assert_eq!(root.join("scripts/a.c").unwrap().read_to_string().unwrap(), "class A {}");
+        assert_eq!(root.join("configs/b.conf").unwrap().read_to_string().unwrap(), "b");
+        assert!(root.join("configs").unwrap().is_dir().unwrap());
+    }
+
+    #[test]
+    fn refuses_paks_without_a_file_chunk() {
+        let data = pak_bytes(&[("scripts/a.c", "class A {}")]);
+        let mut pak = PakFile::parse(&data).unwrap();
+        pak.chunks_mut().retain(|chunk| !matches!(chunk, Chunk::File { .. }));
+
+        let wrapper = BytesPakFileWrapper::new("empty.pak".into(), data, pak);
+        assert!(matches!(PakVfs::new(Arc::new(wrapper)), Err(PakError::MissingFileChunk)));
+    }
+
+    #[test]
+    fn streams_stored_files() {
+        let source = pak_with(&contents());
+        check_reads(&mut reader(&source, false));
+    }
+
+    #[test]
+    fn streams_compressed_files() {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&contents()).unwrap();
+        let source = pak_with(&encoder.finish().unwrap());
+        check_reads(&mut reader(&source, true));
+    }
+}
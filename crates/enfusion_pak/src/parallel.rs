@@ -0,0 +1,142 @@
+//! Parsing many paks at once.
+//!
+//! A full game data directory holds dozens of paks totalling several gigabytes. Each pak is
+//! parsed independently, so they're mapped and parsed on rayon's thread pool and then merged
+//! into a [`PakSet`] in the order they were given.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use memmap2::Mmap;
+use rayon::prelude::*;
+
+use crate::PakFile;
+use crate::error::PakError;
+use crate::error::ParseManyError;
+use crate::pak_set::PakSet;
+use crate::wrappers::bytes::BytesPakFileWrapper;
+
+/// A pak parsed from a memory-mapped file.
+pub type MappedPak = BytesPakFileWrapper<Mmap>;
+
+/// Reported once for every pak parsed by [`PakFile::parse_many_parallel_with_progress`].
+#[derive(Debug, Clone, Copy)]
+pub struct ParseProgress<'a> {
+    /// The pak which was just parsed.
+    pub path: &'a Path,
+    /// Paks parsed so far, including this one.
+    pub parsed: usize,
+    pub total: usize,
+}
+
+impl PakFile {
+    /// Maps and parses the paks at `paths` concurrently, merging them into a [`PakSet`].
+    /// Earlier paths take precedence when paks hold the same file.
+    ///
+    /// Fails with the first pak which can't be opened or parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use enfusion_pak::PakFile;
+    /// use enfusion_pak::vfs::VfsPath;
+    ///
+    /// let mut paths: Vec<_> = std::fs::read_dir("addons/data")
+    ///     .unwrap()
+    ///     .map(|entry| entry.unwrap().path())
+    ///     .filter(|path| path.extension().is_some_and(|ext| ext == "pak"))
+    ///     .collect();
+    /// paths.sort();
+    ///
+    /// let root = VfsPath::new(PakFile::parse_many_parallel(&paths).unwrap());
+    /// ```
+    pub fn parse_many_parallel(
+        paths: &[PathBuf],
+    ) -> Result<PakSet<Arc<MappedPak>>, ParseManyError> {
+        Self::parse_many_parallel_with_progress(paths, |_| {})
+    }
+
+    /// Like [`PakFile::parse_many_parallel`], calling `on_progress` as each pak finishes.
+    /// Paks finish in no particular order, and `on_progress` may be called from several
+    /// threads at once.
+    pub fn parse_many_parallel_with_progress(
+        paths: &[PathBuf],
+        on_progress: impl Fn(ParseProgress<'_>) + Sync,
+    ) -> Result<PakSet<Arc<MappedPak>>, ParseManyError> {
+        let parsed = AtomicUsize::new(0);
+        let paks = paths
+            .par_iter()
+            .map(|path| -> Result<_, ParseManyError> {
+                let pak = map_and_parse(path)
+                    .map_err(|error| ParseManyError { path: path.clone(), error })?;
+                let parsed = parsed.fetch_add(1, Ordering::Relaxed) + 1;
+                on_progress(ParseProgress { path, parsed, total: paths.len() });
+
+                Ok(Arc::new(pak))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Eagerly parsed paks have their FILE chunk already, so merging them can't fail
+        Ok(PakSet::new(paks).expect("eagerly parsed paks have nothing left to parse"))
+    }
+}
+
+fn map_and_parse(path: &Path) -> Result<MappedPak, PakError> {
+    let file = std::fs::File::open(path)?;
+    // SAFETY: paks are only read, and are expected not to change while they're loaded
+    let mmap = unsafe { Mmap::map(&file)? };
+    let pak_file = PakFile::parse(&mmap)?;
+
+    Ok(BytesPakFileWrapper::new(path.to_path_buf(), mmap, pak_file))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::test_pak::pak_bytes;
+
+    /// Writes a pak holding `scripts/{name}` with `contents` into `dir`.
+    fn write_pak(dir: &Path, pak_name: &str, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(pak_name);
+        let file_path = format!("scripts/{name}");
+        std::fs::write(&path, pak_bytes(&[(file_path.as_str(), contents)])).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_paks_in_order() {
+        let dir =
+            std::env::temp_dir().join(format!("enfusion_pak_parallel_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = vec![
+            write_pak(&dir, "a.pak", "shared.c", "first"),
+            write_pak(&dir, "b.pak", "shared.c", "second"),
+            write_pak(&dir, "c.pak", "only_c.c", "third"),
+        ];
+
+        let reported = Mutex::new(Vec::new());
+        let set = PakFile::parse_many_parallel_with_progress(&paths, |progress| {
+            assert_eq!(progress.total, 3);
+            reported.lock().unwrap().push(progress.parsed);
+        })
+        .unwrap();
+
+        let mut reported = reported.into_inner().unwrap();
+        reported.sort();
+        assert_eq!(reported, [1, 2, 3]);
+        assert_eq!(set.paks()[1].path(), paths[1]);
+        assert_eq!(set.file_meta("/scripts/shared.c").unwrap().pak, 0);
+        assert_eq!(set.file_meta("/scripts/only_c.c").unwrap().pak, 2);
+
+        let missing = dir.join("missing.pak");
+        let err = PakFile::parse_many_parallel(&[paths[0].clone(), missing.clone()]).unwrap_err();
+        assert_eq!(err.path, missing);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,6 +1,11 @@
 use std::ops::Range;
+use std::sync::Arc;
 
+use crate::error::EditError;
 use crate::error::PakError;
+use crate::error::ParserDiagnostic;
+use crate::intern::Interner;
+use crate::version::PakVersion;
 use jiff::civil::DateTime;
 use kinded::Kinded;
 use log::debug;
@@ -14,29 +19,78 @@ use winnow::binary::le_u16;
 use winnow::binary::le_u32;
 use winnow::binary::u8;
 use winnow::combinator::alt;
+use winnow::combinator::cut_err;
+use winnow::error::AddContext;
+use winnow::error::ContextError;
 use winnow::error::ErrMode;
+use winnow::error::Needed;
 use winnow::error::StrContext;
 use winnow::stream::Offset;
 use winnow::stream::Stream as _;
 use winnow::token::take;
 
+/// Emits a structured `tracing` event describing a parser state transition. Compiles to
+/// nothing unless the `trace-parser` feature is enabled.
+macro_rules! trace_parser {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "trace-parser")]
+        tracing::trace!(target: "enfusion_pak::parser", $($arg)+);
+    };
+}
+
 /// Represents some type of a file or directory
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FileEntry {
-    name: String,
-    meta: FileEntryMeta,
+    pub(crate) name: Arc<str>,
+    pub(crate) meta: FileEntryMeta,
+    /// Contents of a file created with [`FileEntry::new_file`], which aren't in any pak yet.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) contents: Option<Arc<[u8]>>,
 }
 
-#[cfg(feature = "arc")]
+/// A shared [`FileEntry`]. It's always an `Arc`, so parsed paks can be sent between threads
+/// whichever features are enabled; the name is kept from when the `arc` feature chose
+/// between `Rc` and `Arc`.
 pub type RcFileEntry = std::sync::Arc<FileEntry>;
 
-#[cfg(not(feature = "arc"))]
-pub type RcFileEntry = std::rc::Rc<FileEntry>;
+/// Longest name a FILE entry can store.
+const MAX_NAME_LEN: usize = u8::MAX as usize;
 
 impl FileEntry {
+    /// Creates an empty folder.
+    pub fn new_folder(name: &str) -> Self {
+        FileEntry {
+            name: name.into(),
+            meta: FileEntryMeta::Folder { children: Vec::new() },
+            contents: None,
+        }
+    }
+
+    /// Creates a file holding `contents`, stored uncompressed. The contents are only kept in
+    /// memory until the pak is written with [`PakFile::write_to`].
+    pub fn new_file(name: &str, contents: impl Into<Arc<[u8]>>, modified: DateTime) -> Self {
+        let contents = contents.into();
+        let len = u32::try_from(contents.len()).unwrap_or(u32::MAX);
+        FileEntry {
+            name: name.into(),
+            meta: FileEntryMeta::File {
+                offset: 0,
+                compressed_len: len,
+                decompressed_len: len,
+                unk: 0,
+                unk2: 0,
+                compressed: 0,
+                compression_level: 0,
+                timestamp: pack_timestamp(modified),
+            },
+            contents: Some(contents),
+        }
+    }
+
     /// Entry's name
     pub fn name(&self) -> &str {
-        self.name.as_str()
+        &self.name
     }
 
     /// What kind of entry this is
@@ -50,6 +104,102 @@ impl FileEntry {
         &self.meta
     }
 
+    /// Contents given to [`FileEntry::new_file`]. `None` for folders and for files read
+    /// from a pak, whose data lives at their offset in the pak.
+    pub fn staged_contents(&self) -> Option<&[u8]> {
+        self.contents.as_deref()
+    }
+
+    /// Looks up the entry at `path`, relative to this folder.
+    pub fn get(&self, path: &str) -> Option<&FileEntry> {
+        let mut current = self;
+        for component in path_components(path) {
+            let FileEntryMeta::Folder { children } = &current.meta else {
+                return None;
+            };
+            current = children.iter().find(|child| child.name() == component)?;
+        }
+
+        Some(current)
+    }
+
+    /// Inserts `entry` into the folder at `dir` (relative to this folder), creating any
+    /// missing folders along the way. An existing entry of the same kind and name is
+    /// replaced and returned.
+    pub fn insert(
+        &mut self,
+        dir: &str,
+        entry: FileEntry,
+    ) -> Result<Option<RcFileEntry>, EditError> {
+        check_name(entry.name())?;
+
+        let folder = self.folder_mut(dir, true)?;
+        if let Some(existing) = folder.meta.child(entry.name())
+            && existing.kind() != entry.kind()
+        {
+            return Err(EditError::AlreadyExists(join_path(dir, entry.name())));
+        }
+
+        Ok(folder.meta.insert_child(entry))
+    }
+
+    /// Removes and returns the entry at `path`, relative to this folder.
+    pub fn remove(&mut self, path: &str) -> Result<RcFileEntry, EditError> {
+        let (dir, name) = split_parent(path);
+        self.folder_mut(dir, false)?
+            .meta
+            .remove_child(name)
+            .ok_or_else(|| EditError::NotFound(path.to_string()))
+    }
+
+    /// Renames the entry at `path`, relative to this folder, keeping it in the same folder.
+    pub fn rename(&mut self, path: &str, new_name: &str) -> Result<(), EditError> {
+        check_name(new_name)?;
+
+        let (dir, name) = split_parent(path);
+        let folder = self.folder_mut(dir, false)?;
+        if name != new_name && folder.meta.child(new_name).is_some() {
+            return Err(EditError::AlreadyExists(join_path(dir, new_name)));
+        }
+
+        let FileEntryMeta::Folder { children } = &mut folder.meta else {
+            unreachable!("folder_mut only returns folders");
+        };
+        let child = children
+            .iter_mut()
+            .find(|child| child.name() == name)
+            .ok_or_else(|| EditError::NotFound(path.to_string()))?;
+        RcFileEntry::make_mut(child).name = new_name.into();
+
+        Ok(())
+    }
+
+    /// Walks to the folder at `dir`, unsharing every entry on the way so it can be changed.
+    fn folder_mut(&mut self, dir: &str, create: bool) -> Result<&mut FileEntry, EditError> {
+        let mut current = self;
+        for component in path_components(dir) {
+            let FileEntryMeta::Folder { children } = &mut current.meta else {
+                return Err(EditError::NotAFolder(dir.to_string()));
+            };
+
+            let idx = match children.iter().position(|child| child.name() == component) {
+                Some(idx) => idx,
+                None if create => {
+                    children.push(RcFileEntry::new(FileEntry::new_folder(component)));
+                    children.len() - 1
+                }
+                None => return Err(EditError::NotFound(dir.to_string())),
+            };
+            current = RcFileEntry::make_mut(&mut children[idx]);
+        }
+
+        if current.kind() != FileEntryKind::Folder {
+            return Err(EditError::NotAFolder(dir.to_string()));
+        }
+
+        Ok(current)
+    }
+
     /// Merges `other` into this node.
     pub fn merge(&mut self, other: Self) {
         let FileEntryMeta::Folder { children: self_children } = &mut self.meta else {
@@ -117,6 +267,7 @@ impl FileEntry {
 
 /// An entry's metadata containing either its children or file metadata
 #[derive(Debug, Clone, Kinded, Variantly)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[kinded(kind = FileEntryKind)]
 #[non_exhaustive]
 pub enum FileEntryMeta {
@@ -143,6 +294,43 @@ impl FileEntryMeta {
         }
     }
 
+    /// Returns the direct child named `name`. Always `None` for files.
+    pub fn child(&self, name: &str) -> Option<&RcFileEntry> {
+        match self {
+            FileEntryMeta::Folder { children } => {
+                children.iter().find(|child| child.name() == name)
+            }
+            FileEntryMeta::File { .. } => None,
+        }
+    }
+
+    /// Adds a child, replacing and returning any existing child with the same name. No-op
+    /// if this is a file.
+    pub fn insert_child(&mut self, child: FileEntry) -> Option<RcFileEntry> {
+        let FileEntryMeta::Folder { children } = self else {
+            return None;
+        };
+
+        let child = RcFileEntry::new(child);
+        match children.iter_mut().find(|existing| existing.name == child.name) {
+            Some(existing) => Some(std::mem::replace(existing, child)),
+            None => {
+                children.push(child);
+                None
+            }
+        }
+    }
+
+    /// Removes and returns the direct child named `name`.
+    pub fn remove_child(&mut self, name: &str) -> Option<RcFileEntry> {
+        let FileEntryMeta::Folder { children } = self else {
+            return None;
+        };
+
+        let idx = children.iter().position(|child| child.name() == name)?;
+        Some(children.remove(idx))
+    }
+
     /// Returns this file's timestamp. For directories there is no timestamp information
     /// and this will return `None`. For Files, this returns the date/time at which the file
     /// was modified(?). Note: there is no time zone information recorded.
@@ -172,6 +360,42 @@ impl FileEntryMeta {
     }
 }
 
+/// Packs `modified` the way [`FileEntryMeta::parsed_timestamp`] unpacks it. Years outside of
+/// what fits (2000 to 2063) are clamped.
+fn pack_timestamp(modified: DateTime) -> u32 {
+    let year = (modified.year().clamp(2000, 2063) - 2000) as u32;
+
+    (year << 26)
+        | ((modified.month() as u32) << 22)
+        | ((modified.day() as u32) << 17)
+        | ((modified.hour() as u32) << 12)
+        | ((modified.minute() as u32) << 6)
+        | (modified.second() as u32)
+}
+
+fn path_components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|component| !component.is_empty())
+}
+
+/// Splits `path` into its parent folder and final component.
+fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    let dir = dir.trim_end_matches('/');
+    if dir.is_empty() { name.to_string() } else { format!("{dir}/{name}") }
+}
+
+fn check_name(name: &str) -> Result<(), EditError> {
+    if matches!(name, "" | "." | "..") || name.len() > MAX_NAME_LEN || name.contains(['/', '\\']) {
+        return Err(EditError::InvalidName(name.to_string()));
+    }
+
+    Ok(())
+}
+
 impl TryFrom<u8> for FileEntryKind {
     type Error = ();
 
@@ -179,9 +403,7 @@ impl TryFrom<u8> for FileEntryKind {
         let result = match value {
             0 => Self::Folder,
             1 => Self::File,
-            _ => {
-                panic!("unknown file entry kind: {value:#X}");
-            }
+            _ => return Err(()),
         };
 
         Ok(result)
@@ -189,11 +411,24 @@ impl TryFrom<u8> for FileEntryKind {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PakFile {
     chunks: Vec<Chunk>,
 }
 
 impl PakFile {
+    /// Creates a pak with no files, at the latest supported version. Add files to its
+    /// FILE chunk with [`FileEntry::insert`], then write it with [`PakFile::write_to`] and an
+    /// empty `source`.
+    pub fn new() -> Self {
+        PakFile {
+            chunks: vec![
+                Chunk::Head { version: PakVersion::LATEST.raw(), unknown_data: 0..0 },
+                Chunk::File { fs: RcFileEntry::new(FileEntry::new_folder("")) },
+            ],
+        }
+    }
+
     /// Returns an immutable slice of the [`Chunk`]s contained in this `PakFile`.
     pub fn chunks(&self) -> &[Chunk] {
         &self.chunks
@@ -215,15 +450,57 @@ impl PakFile {
     pub fn file_chunk_mut(&mut self) -> Option<&mut Chunk> {
         self.chunks.iter_mut().find(|chunk| chunk.is_file())
     }
+
+    /// The format version declared by this pak's HEAD chunk, or `None` if it has none.
+    pub fn version(&self) -> Option<PakVersion> {
+        self.chunks.iter().find_map(|chunk| match chunk {
+            Chunk::Head { version, .. } => PakVersion::from_raw(*version).ok(),
+            _ => None,
+        })
+    }
+
+    /// Where the DATA chunk's contents are in the pak, or `None` if it has none.
+    pub fn data_range(&self) -> Option<Range<usize>> {
+        self.chunks.iter().find_map(|chunk| match chunk {
+            Chunk::Data { data } => Some(data.clone()),
+            _ => None,
+        })
+    }
+
+    /// Where `entry`'s data is stored in the pak, compressed or not. `None` for folders and
+    /// for files added with [`FileEntry::new_file`], which aren't in the pak yet.
+    pub fn file_data_range(&self, entry: &FileEntry) -> Option<Range<usize>> {
+        if entry.staged_contents().is_some() {
+            return None;
+        }
+
+        match entry.meta() {
+            FileEntryMeta::File { offset, compressed_len, .. } => {
+                let start = *offset as usize;
+                Some(start..start + *compressed_len as usize)
+            }
+            FileEntryMeta::Folder { .. } => None,
+        }
+    }
+}
+
+impl Default for PakFile {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum PakType {
     PAC1,
 }
 
+/// A chunk of a pak. Chunks hold byte ranges into the pak rather than its data, so with the
+/// `serde` feature they serialize to a description of the pak's layout.
 #[derive(Debug, Kinded, Variantly)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum Chunk {
     Form {
@@ -234,20 +511,41 @@ pub enum Chunk {
         version: u32,
         unknown_data: Range<usize>,
     },
-    /// Contains the
+    /// Holds every file's stored data. `data` is the absolute byte range of the chunk's
+    /// contents within the pak.
     Data {
         data: Range<usize>,
     },
     File {
         fs: RcFileEntry,
     },
-    Unknown(u32),
+    /// A FILE chunk whose entries were not parsed. `entries` is the absolute byte range
+    /// of the entry table within the pak, which can be handed to
+    /// [`LazyFileTable`](crate::lazy::LazyFileTable) to browse folders on demand.
+    LazyFile {
+        entries: Range<usize>,
+    },
+    /// A chunk this parser doesn't understand, e.g. from a newer game version. `data` is the
+    /// absolute byte range of its contents within the pak, which are skipped.
+    Unknown {
+        tag: [u8; 4],
+        data: Range<usize>,
+    },
 }
 
 impl PakFile {
     pub fn parse(data: &[u8]) -> Result<PakFile, PakError> {
-        let mut parser = PakParser::new();
+        Self::parse_with(data, PakParser::new())
+    }
 
+    /// Parses `data` without materializing the FILE chunk's entry tree. The resulting
+    /// `PakFile` contains a [`Chunk::LazyFile`] instead of a [`Chunk::File`]; use
+    /// [`PakFile::lazy_file_table`] to browse it.
+    pub fn parse_lazy(data: &[u8]) -> Result<PakFile, PakError> {
+        Self::parse_with(data, PakParser::new().with_lazy_file_chunk())
+    }
+
+    fn parse_with(data: &[u8], mut parser: PakParser) -> Result<PakFile, PakError> {
         let mut curr_data = data;
         loop {
             let mut input = Stream::new(curr_data);
@@ -268,14 +566,25 @@ impl PakFile {
                     curr_data = &curr_data[count..];
                     parser = next_parser;
                 }
+                Ok(ParserStateMachine::Continue(_)) => {
+                    // All of the data is available, so needing more means the pak is
+                    // truncated.
+                    let pos = data.len() - curr_data.len() + input.checkpoint().offset_from(&start);
+                    let diagnostic = ParserDiagnostic::new(ErrMode::Incomplete(Needed::Unknown))
+                        .with_location(data, 0, pos);
+                    return Err(PakError::ParserError(diagnostic));
+                }
                 Ok(state) => {
                     panic!("Unexpected state: {:?}", state.kind());
                 }
-                Err(winnow::error::ErrMode::Cut(e)) => {
-                    return Err(PakError::ParserError(e));
-                }
                 Err(e) => {
-                    panic!("Unknown error occurred: {e:?}");
+                    let pos = data.len() - curr_data.len() + input.checkpoint().offset_from(&start);
+                    let diagnostic = ParserDiagnostic::new(e).with_location(data, 0, pos);
+                    if let Some(unsupported) = diagnostic.unsupported_version() {
+                        return Err(unsupported.into());
+                    }
+
+                    return Err(PakError::ParserError(diagnostic));
                 }
             }
         }
@@ -287,6 +596,9 @@ pub struct PakParser {
     chunks: Vec<Chunk>,
     pak_len: Option<usize>,
     bytes_parsed: usize,
+    names: Interner,
+    lazy_file_chunk: bool,
+    version: PakVersion,
 }
 
 pub type Stream<'i> = Partial<&'i [u8]>;
@@ -298,9 +610,19 @@ impl PakParser {
             chunks: Vec::with_capacity(4),
             pak_len: None,
             bytes_parsed: 0,
+            names: Interner::new(),
+            lazy_file_chunk: false,
+            version: PakVersion::LATEST,
         }
     }
 
+    /// Skip over the FILE chunk's entries instead of building the entry tree, emitting a
+    /// [`Chunk::LazyFile`] that records where they live.
+    pub fn with_lazy_file_chunk(mut self) -> Self {
+        self.lazy_file_chunk = true;
+        self
+    }
+
     pub fn bytes_parsed(&self) -> usize {
         self.bytes_parsed
     }
@@ -323,10 +645,12 @@ impl PakParser {
                 } => {
                     if bytes_processed + bytes_consumed == chunk_len {
                         assert_eq!(parents.len(), 1);
+                        trace_parser!(end = self.bytes_parsed, chunk_len, "FILE chunk end");
                         self.chunks.push(Chunk::File {
                             fs: RcFileEntry::new(parents.pop().unwrap().entry),
                         });
-                        PakParserState::Done
+                        // Usually the last chunk, but newer paks may have more after it
+                        PakParserState::ParsingChunk
                     } else {
                         PakParserState::ParsingFileChunk {
                             parsed_root,
@@ -369,11 +693,29 @@ impl PakParser {
                     }
                 };
                 debug!("Read complete! Result: {:#?}", parsed.kind());
+                trace_parser!(
+                    offset = self.bytes_parsed,
+                    kind = ?parsed.kind(),
+                    header_len = input.checkpoint().offset_from(&start),
+                    "chunk start"
+                );
 
                 // Parse a single chunk
                 let (skip, chunk, state) = match parsed {
                     Parsed::Chunk(chunk) => (0, Some(chunk), None),
                     Parsed::ChunkAndSkip(skip, chunk) => (skip, Some(chunk), None),
+                    Parsed::FileChunkHeader { chunk_len } if self.lazy_file_chunk => {
+                        let entries_start =
+                            self.bytes_parsed + input.checkpoint().offset_from(&start);
+                        debug!(
+                            "Deferring {:#X} bytes of file entries starting at {:#X}",
+                            chunk_len, entries_start
+                        );
+                        let chunk =
+                            Chunk::LazyFile { entries: entries_start..(entries_start + chunk_len) };
+
+                        (chunk_len, Some(chunk), None)
+                    }
                     Parsed::FileChunkHeader { chunk_len } => {
                         debug!(
                             "We have {:#X} bytes to read starting at {:#X}",
@@ -395,15 +737,39 @@ impl PakParser {
 
                 let skip_from = self.bytes_parsed - skip;
 
-                if let Some(chunk) = chunk
-                    && let Chunk::Form { file_size, .. } = &chunk
-                {
-                    // TODO: we shouldn't read the PAC1 data here
-                    self.pak_len = Some((*file_size as usize) + (bytes_consumed - 4));
+                if self.pak_len.is_some_and(|pak_len| self.bytes_parsed > pak_len) {
+                    // The chunk claims to run past the end of the pak
+                    input.reset(&start);
+                    let label = chunk.as_ref().map_or("FILE chunk", chunk_label);
+                    return Err(ErrMode::Cut(ContextError::new()).add_context(
+                        input,
+                        &start,
+                        StrContext::Label(label),
+                    ));
+                }
+
+                if let Some(mut chunk) = chunk {
+                    if let Chunk::Data { data } | Chunk::Unknown { data, .. } = &mut chunk {
+                        // The chunk's contents are what's being skipped
+                        *data = skip_from..skip_from + data.len();
+                    }
+                    if let Chunk::Form { file_size, .. } = &chunk {
+                        // TODO: we shouldn't read the PAC1 data here
+                        self.pak_len = Some((*file_size as usize) + (bytes_consumed - 4));
+                    }
+                    if let Chunk::Head { version, .. } = &chunk {
+                        // parse_head_chunk has already rejected versions we don't know
+                        self.version = PakVersion::from_raw(*version)
+                            .expect("HEAD version was validated while parsing");
+                    }
+                    trace_parser!(kind = ?chunk.kind(), end = self.bytes_parsed, "chunk end");
                     self.chunks.push(chunk);
                 }
 
-                if skip > 0 {
+                // There's nothing left to read if the skip runs to the end of the file, so
+                // don't ask the caller to seek past EOF.
+                if skip > 0 && !matches!(self.state, PakParserState::Done) {
+                    trace_parser!(from = skip_from, count = skip, "skip");
                     return Ok(ParserStateMachine::Skip {
                         from: skip_from,
                         count: skip,
@@ -425,7 +791,7 @@ impl PakParser {
                     }
                     Err(e) => {
                         debug!("hard error while reading file entry");
-                        return Err(e);
+                        return Err(e.add_context(input, &start, StrContext::Label("FILE entry")));
                     }
                 }
                 self.next_state(input.checkpoint().offset_from(&start), None);
@@ -461,7 +827,15 @@ impl PakParser {
             panic!("Ended up in parse_file_entry in the wrong state")
         };
 
-        let (entry, children) = parse_file_entry(input)?;
+        let entry_start = input.checkpoint();
+        let (entry, children) = parse_file_entry(input, &mut self.names, self.version)?;
+        trace_parser!(
+            offset = self.bytes_parsed,
+            name = entry.name(),
+            kind = ?entry.kind(),
+            children,
+            "entry parsed"
+        );
 
         match entry.meta.kind() {
             FileEntryKind::Folder => {
@@ -474,11 +848,12 @@ impl PakParser {
                 *parsed_root = true;
             }
             FileEntryKind::File => {
-                let parent = parents.last_mut().expect("bug: no parent for this file");
-                parent.children_remaining = parent
-                    .children_remaining
-                    .checked_sub(1)
-                    .expect("encountered more children than expected for a folder");
+                let Some(parent) = parents.last_mut().filter(|dir| dir.children_remaining > 0)
+                else {
+                    input.reset(&entry_start);
+                    return Err(misplaced_entry());
+                };
+                parent.children_remaining -= 1;
 
                 parent.entry.meta.push_child(entry);
             }
@@ -488,13 +863,11 @@ impl PakParser {
         while let Some(dir) =
             parents.pop_if(|parent| parent.children_remaining == 0 && !parent.is_root)
         {
-            let parent =
-                parents.last_mut().expect("expected a folder to have a parent, but there is none");
-
-            parent.children_remaining = parent
-                .children_remaining
-                .checked_sub(1)
-                .expect("encountered more children than expected for a folder");
+            let Some(parent) = parents.last_mut().filter(|dir| dir.children_remaining > 0) else {
+                input.reset(&entry_start);
+                return Err(misplaced_entry());
+            };
+            parent.children_remaining -= 1;
 
             parent.entry.meta.push_child(dir.entry);
         }
@@ -503,6 +876,7 @@ impl PakParser {
     }
 
     pub fn complete(self) -> PakFile {
+        trace_parser!(bytes_parsed = self.bytes_parsed, chunks = self.chunks.len(), "done");
         PakFile { chunks: self.chunks }
     }
 }
@@ -549,84 +923,101 @@ pub enum ParserStateMachine {
     Done(PakFile),
 }
 
-fn parse_file_entry(input: &mut Stream) -> WResult<(FileEntry, usize)> {
-    let entry_kind: FileEntryKind = u8(input)?.try_into().expect("???");
+/// A FILE entry with no folder left to hold it, which a well-formed pak never has.
+fn misplaced_entry() -> ErrMode<ContextError<StrContext>> {
+    let mut error = ContextError::new();
+    error.push(StrContext::Label("FILE entry's folder"));
+    ErrMode::Cut(error)
+}
+
+pub(crate) fn parse_file_entry(
+    input: &mut Stream,
+    names: &mut Interner,
+    version: PakVersion,
+) -> WResult<(FileEntry, usize)> {
+    let entry_kind = cut_err(u8.verify_map(|kind| FileEntryKind::try_from(kind).ok()))
+        .context(StrContext::Label("FILE entry kind"))
+        .parse_next(input)?;
     let name_len = u8(input)?;
-    let name = take(name_len).parse_next(input)?;
-    let name =
-        String::from_utf8(name.to_vec()).expect("name does not contain valid UTF8 characters");
+    let name = cut_err(take(name_len).try_map(std::str::from_utf8))
+        .context(StrContext::Label("FILE entry name"))
+        .parse_next(input)?;
+    let name = names.intern(name);
 
     let (meta, children) = match entry_kind {
         FileEntryKind::Folder => {
             let children_count = le_u32(input)?;
             (FileEntryMeta::Folder { children: Default::default() }, children_count as usize)
         }
-        FileEntryKind::File => {
+        FileEntryKind::File => (parse_file_meta(input, version)?, 0),
+    };
+
+    Ok((FileEntry { name, meta, contents: None }, children))
+}
+
+fn parse_file_meta(input: &mut Stream, version: PakVersion) -> WResult<FileEntryMeta> {
+    match version {
+        PakVersion::V1_3 => {
             let offset = le_u32(input)?;
             let compressed_len = le_u32(input)?;
             let decompressed_len = le_u32(input)?;
-            let unknown = le_u32(input)?;
-            let unk2 = le_u16(input)?;
-            let compressed = u8(input)?;
-            let compression_level = u8(input)?;
+            let (unknown, unk2) =
+                cut_err((le_u32, le_u16).verify(|unknown: &(u32, u16)| *unknown == (0, 0)))
+                    .context(StrContext::Label("FILE entry unknown fields"))
+                    .parse_next(input)?;
+            let compressed = cut_err(u8.verify(|compressed: &u8| matches!(compressed, 0 | 1)))
+                .context(StrContext::Label("FILE entry compression flag"))
+                .parse_next(input)?;
+            let compression_level = cut_err(u8.verify(|level: &u8| matches!(level, 0 | 6)))
+                .context(StrContext::Label("FILE entry compression level"))
+                .parse_next(input)?;
             let timestamp = le_u32(input)?;
 
-            assert_eq!(unknown, 0);
-            assert_eq!(unk2, 0);
-            assert!(matches!(compressed, 0 | 1));
-            assert!(matches!(compression_level, 0 | 6));
-
-            (
-                FileEntryMeta::File {
-                    offset,
-                    compressed_len,
-                    decompressed_len,
-                    unk: unknown,
-                    unk2,
-                    compressed,
-                    compression_level,
-                    timestamp,
-                },
-                0,
-            )
+            Ok(FileEntryMeta::File {
+                offset,
+                compressed_len,
+                decompressed_len,
+                unk: unknown,
+                unk2,
+                compressed,
+                compression_level,
+                timestamp,
+            })
         }
-    };
-
-    Ok((FileEntry { name, meta }, children))
+    }
 }
 
 fn parse_form_chunk(input: &mut Stream) -> WResult<Parsed> {
     let file_size = be_u32(input)?;
-    let pak_type_bytes: [u8; 4] = take(4usize)
-        .parse_next(input)?
-        .try_into()
-        .expect("winnow should have returned a 4-byte buffer");
-    let pak_file_type = match &pak_type_bytes {
-        b"PAC1" => PakType::PAC1,
-        unk => {
-            panic!("unknown pak type: {unk:?}");
-        }
-    };
+    let pak_file_type = cut_err(take(4usize).verify_map(|pak_type: &[u8]| match pak_type {
+        b"PAC1" => Some(PakType::PAC1),
+        _ => None,
+    }))
+    .context(StrContext::Label("FORM pak type"))
+    .parse_next(input)?;
 
     Ok(Parsed::Chunk(Chunk::Form { file_size, pak_file_type }))
 }
 
 fn parse_head_chunk(input: &mut Stream) -> WResult<Parsed> {
     let head_start = input.checkpoint();
-    let header_len = be_u32.parse_next(input)? as usize;
-    assert_eq!(header_len, 0x1c);
+    let header_len = cut_err(be_u32.verify(|len: &u32| *len == 0x1c))
+        .context(StrContext::Label("HEAD length"))
+        .parse_next(input)? as usize;
 
     let mut skip_bytes = 0;
 
     let header_data_start = input.checkpoint();
-    let version = le_u32.parse_next(input)?;
+    let version = cut_err(le_u32.try_map(PakVersion::from_raw))
+        .context(StrContext::Label("HEAD version"))
+        .parse_next(input)?;
     let unknown_data_start = input.checkpoint();
     skip_bytes += unknown_data_start.offset_from(&header_data_start);
 
     let unknown_data_offset = unknown_data_start.offset_from(&head_start);
 
     let chunk = Chunk::Head {
-        version,
+        version: version.raw(),
         unknown_data: unknown_data_offset..(unknown_data_offset + skip_bytes),
     };
 
@@ -651,21 +1042,75 @@ fn parse_file_chunk(input: &mut Stream) -> WResult<Parsed> {
     Ok(Parsed::FileChunkHeader { chunk_len })
 }
 
+/// Reads the tag and length of a chunk none of the other parsers matched, so that its
+/// contents can be skipped.
+fn parse_unknown_chunk(input: &mut Stream) -> WResult<Parsed> {
+    let tag: [u8; 4] = take(4usize)
+        .parse_next(input)?
+        .try_into()
+        .expect("winnow should have returned a 4-byte buffer");
+    let data_len = be_u32.parse_next(input)? as usize;
+    debug!("Skipping unknown chunk {:?} of {data_len:#X} bytes", tag.escape_ascii().to_string());
+
+    Ok(Parsed::ChunkAndSkip(data_len, Chunk::Unknown { tag, data: 0..data_len }))
+}
+
+/// The section diagnostics name for a chunk which failed to parse.
+fn chunk_label(chunk: &Chunk) -> &'static str {
+    match chunk {
+        Chunk::Form { .. } => "FORM chunk",
+        Chunk::Head { .. } => "HEAD chunk",
+        Chunk::Data { .. } => "DATA chunk",
+        Chunk::File { .. } | Chunk::LazyFile { .. } => "FILE chunk",
+        Chunk::Unknown { .. } => "unknown chunk",
+    }
+}
+
 fn parse_chunk(input: &mut Stream) -> WResult<Parsed> {
-    alt((
+    let known = alt((
         (b"FORM", parse_form_chunk)
-            .context(StrContext::Label("chunk"))
+            .context(StrContext::Label("FORM chunk"))
             .context(StrContext::Expected(winnow::error::StrContextValue::Description("FORM"))),
         (b"HEAD", parse_head_chunk)
-            .context(StrContext::Label("chunk"))
+            .context(StrContext::Label("HEAD chunk"))
             .context(StrContext::Expected(winnow::error::StrContextValue::Description("HEAD"))),
         (b"DATA", parse_data_chunk)
-            .context(StrContext::Label("chunk"))
+            .context(StrContext::Label("DATA chunk"))
             .context(StrContext::Expected(winnow::error::StrContextValue::Description("DATA"))),
         (b"FILE", parse_file_chunk)
-            .context(StrContext::Label("chunk"))
+            .context(StrContext::Label("FILE chunk"))
             .context(StrContext::Expected(winnow::error::StrContextValue::Description("FILE"))),
     ))
-    .parse_next(input)
-    .map(|(_, parsed)| parsed)
+    .map(|(_, parsed)| parsed);
+
+    alt((known, parse_unknown_chunk.context(StrContext::Label("unknown chunk")))).parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_pak::pak_bytes;
+
+    #[test]
+    fn unknown_chunks_are_skipped() {
+        let mut data = pak_bytes(&[("scripts/a.c", "class A {}")]);
+        let unknown_start = data.len() + 8;
+        data.extend_from_slice(b"NEW1");
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        let form_len = (data.len() - 8) as u32;
+        data[4..8].copy_from_slice(&form_len.to_be_bytes());
+
+        let pak = PakFile::parse(&data).unwrap();
+        let Some(Chunk::Unknown { tag, data: range }) = pak.chunks().last() else {
+            panic!("unknown chunk wasn't kept");
+        };
+        assert_eq!(tag, b"NEW1");
+        assert_eq!(*range, unknown_start..unknown_start + 4);
+        assert_eq!(&data[range.clone()], [1, 2, 3, 4]);
+
+        let Some(Chunk::File { fs }) = pak.file_chunk() else { panic!("no FILE chunk") };
+        let entry = fs.get("scripts/a.c").unwrap();
+        assert_eq!(&data[pak.file_data_range(entry).unwrap()], b"class A {}");
+    }
 }
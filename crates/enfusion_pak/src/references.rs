@@ -0,0 +1,227 @@
+//! Cross-referencing resource paths found in text entries against an archive's contents.
+//!
+//! Enfusion resources refer to each other with quoted resource names such as
+//! `"{0123456789ABCDEF}Prefabs/Weapons/Rifle.et"`: a GUID followed by the resource's path.
+//! The GUID is what the engine resolves, and each resource's `.meta` file records which
+//! GUID belongs to it, so a reference still resolves after the file moves. Plain quoted
+//! paths (as used by DayZ configs) are also treated as references when they have a known
+//! file extension.
+//!
+//! Build a [`ReferenceIndex`] by adding every entry and the contents of every text entry,
+//! then call [`ReferenceIndex::report`].
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::entry_type::EntryType;
+use crate::entry_type::detect_type;
+
+/// A resource name found in a text entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceRef<'a> {
+    /// The 16 hex digit GUID, without braces.
+    pub guid: Option<&'a str>,
+    /// Path following the GUID. May be empty for GUID-only references.
+    pub path: &'a str,
+}
+
+/// Finds every quoted resource name in `text`.
+pub fn find_references(text: &str) -> Vec<ResourceRef<'_>> {
+    let mut references = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('"') {
+        let after_quote = &rest[start + 1..];
+        let Some(end) = after_quote.find(['"', '\n']) else {
+            break;
+        };
+
+        let quoted = &after_quote[..end];
+        if let Some(reference) = parse_reference(quoted) {
+            references.push(reference);
+        }
+
+        // An unterminated string ends at the line break; resume scanning from there
+        rest = if after_quote.as_bytes()[end] == b'"' {
+            &after_quote[end + 1..]
+        } else {
+            &after_quote[end..]
+        };
+    }
+
+    references
+}
+
+fn parse_reference(quoted: &str) -> Option<ResourceRef<'_>> {
+    if let Some(guid) = quoted.strip_prefix('{').and_then(|s| s.get(..16))
+        && guid.bytes().all(|b| b.is_ascii_hexdigit())
+        && let Some(path) = quoted[17..].strip_prefix('}')
+    {
+        return Some(ResourceRef { guid: Some(guid), path });
+    }
+
+    let looks_like_path = quoted.contains(['/', '\\']) && !quoted.contains(char::is_whitespace);
+    if looks_like_path && detect_type(&normalize(quoted), &[]) != EntryType::Unknown {
+        return Some(ResourceRef { guid: None, path: quoted });
+    }
+
+    None
+}
+
+/// Normalizes a path for comparison: forward slashes, no leading separator, lowercase.
+fn normalize(path: &str) -> String {
+    path.trim_start_matches(['/', '\\']).replace('\\', "/").to_lowercase()
+}
+
+/// A reference to a resource which isn't in the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingReference {
+    /// Entry containing the reference.
+    pub source: String,
+    /// The reference as written.
+    pub reference: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ReferenceReport {
+    /// Entries which nothing refers to, sorted by path.
+    pub unreferenced: Vec<String>,
+    /// References which don't resolve to any entry, sorted by source.
+    pub missing: Vec<MissingReference>,
+}
+
+#[derive(Debug)]
+struct Reference {
+    source: String,
+    guid: Option<String>,
+    path: String,
+}
+
+/// Collects entries and the references between them.
+#[derive(Debug, Default)]
+pub struct ReferenceIndex {
+    /// Normalized path to the path as first added.
+    entries: HashMap<String, String>,
+    /// GUID (uppercase) to the normalized path of the resource it belongs to.
+    guids: HashMap<String, String>,
+    references: Vec<Reference>,
+}
+
+impl ReferenceIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that an entry exists.
+    pub fn add_entry(&mut self, path: &str) {
+        self.entries.entry(normalize(path)).or_insert_with(|| path.to_string());
+    }
+
+    /// Records the references in a text entry's contents. A `.meta` file's reference to
+    /// its own resource registers that resource's GUID rather than counting as a use.
+    pub fn add_source(&mut self, path: &str, text: &str) {
+        let described = path.strip_suffix(".meta").map(normalize);
+        for reference in find_references(text) {
+            let normalized = normalize(reference.path);
+            if let Some(guid) = reference.guid
+                && described.as_ref() == Some(&normalized)
+            {
+                self.guids.insert(guid.to_ascii_uppercase(), normalized);
+                continue;
+            }
+
+            self.references.push(Reference {
+                source: path.to_string(),
+                guid: reference.guid.map(str::to_ascii_uppercase),
+                path: reference.path.to_string(),
+            });
+        }
+    }
+
+    /// Resolves every reference. Scripts and `.meta` files are never reported as
+    /// unreferenced since the engine loads them without being referred to.
+    pub fn report(&self) -> ReferenceReport {
+        let mut referenced = HashSet::new();
+        let mut missing = Vec::new();
+        for reference in &self.references {
+            let by_path = Some(normalize(&reference.path)).filter(|path| !path.is_empty());
+            let by_guid = reference.guid.as_ref().and_then(|guid| self.guids.get(guid)).cloned();
+            let resolved =
+                by_path.into_iter().chain(by_guid).find(|path| self.entries.contains_key(path));
+
+            match resolved {
+                Some(path) => {
+                    referenced.insert(path);
+                }
+                None => missing.push(MissingReference {
+                    source: reference.source.clone(),
+                    reference: match &reference.guid {
+                        Some(guid) => format!("{{{guid}}}{}", reference.path),
+                        None => reference.path.clone(),
+                    },
+                }),
+            }
+        }
+
+        let mut unreferenced: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(normalized, _)| !referenced.contains(*normalized))
+            .filter(|(normalized, _)| {
+                !matches!(detect_type(normalized, &[]), EntryType::Script | EntryType::Meta)
+            })
+            .map(|(_, path)| path.clone())
+            .collect();
+        unreferenced.sort();
+        missing.sort_by(|a, b| a.source.cmp(&b.source).then_with(|| a.reference.cmp(&b.reference)));
+
+        ReferenceReport { unreferenced, missing }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_by_path_and_guid() {
+        let mut index = ReferenceIndex::new();
+        for entry in [
+            "/Prefabs/Rifle.et",
+            "/Prefabs/Rifle.et.meta",
+            "/Prefabs/Moved.et",
+            "/Prefabs/Moved.et.meta",
+            "/Textures/Unused.edds",
+            "/Scripts/Game.c",
+        ] {
+            index.add_entry(entry);
+        }
+
+        index.add_source("/Prefabs/Rifle.et.meta", r#"Name "{1111111111111111}Prefabs/Rifle.et""#);
+        index.add_source("/Prefabs/Moved.et.meta", r#"Name "{2222222222222222}Prefabs/Moved.et""#);
+        index.add_source(
+            "/Scripts/Game.c",
+            r#"
+            ResourceName m_Rifle = "{1111111111111111}Prefabs/Rifle.et";
+            ResourceName m_Moved = "{2222222222222222}Prefabs/OldName.et";
+            ResourceName m_Gone = "{3333333333333333}Prefabs/Gone.et";
+            string m_Message = "not a/path";
+            "#,
+        );
+
+        let report = index.report();
+        assert_eq!(report.unreferenced, ["/Textures/Unused.edds"]);
+        assert_eq!(
+            report.missing,
+            [MissingReference {
+                source: "/Scripts/Game.c".to_string(),
+                reference: "{3333333333333333}Prefabs/Gone.et".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn plain_paths_are_references() {
+        let refs = find_references(r#"model = "\dz\weapons\rifle.p3d"; displayName = "Rifle";"#);
+        assert_eq!(refs, [ResourceRef { guid: None, path: r"\dz\weapons\rifle.p3d" }]);
+    }
+}
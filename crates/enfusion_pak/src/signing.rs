@@ -0,0 +1,352 @@
+//! Manifests of file hashes embedded in a pak, optionally signed with ed25519.
+//!
+//! [`PakFile::embed_manifest`] stores a [`Manifest`] listing the SHA-256 and length of every
+//! file in the pak at [`MANIFEST_PATH`]. Given a key, it also stores a signature over the
+//! manifest's bytes at [`SIGNATURE_PATH`]. [`PakFile::verify_manifest`] checks both, so a
+//! server can refuse a mod whose files were changed after it was signed.
+//!
+//! The manifest is plain text: a header line, then one `<sha256> <len> <path>` line per
+//! file in path order, with paths relative to the pak root.
+
+use std::fmt;
+use std::fmt::Write as _;
+use std::io::Read;
+
+pub use ed25519_dalek::Signature;
+use ed25519_dalek::Signer;
+pub use ed25519_dalek::SigningKey;
+pub use ed25519_dalek::VerifyingKey;
+use jiff::tz::TimeZone;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::Chunk;
+use crate::FileEntry;
+use crate::FileEntryMeta;
+use crate::PakFile;
+use crate::RcFileEntry;
+use crate::error::PakError;
+use crate::error::SignatureError;
+
+/// Folder holding the manifest and its signature. Its contents aren't listed in the
+/// manifest.
+pub const SIGNATURE_DIR: &str = ".signature";
+/// Where the manifest is stored in a pak.
+pub const MANIFEST_PATH: &str = ".signature/manifest.sha256";
+/// Where the manifest's signature is stored in a pak, as 64 raw bytes.
+pub const SIGNATURE_PATH: &str = ".signature/manifest.sig";
+
+const MANIFEST_HEADER: &str = "enfusion-manifest 1";
+
+/// The hash and length of every file in a pak.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Path inside the pak without a leading `/`.
+    pub path: String,
+    /// Decompressed length.
+    pub len: u64,
+    /// SHA-256 of the decompressed contents.
+    pub sha256: [u8; 32],
+}
+
+impl Manifest {
+    /// Hashes every file under `root`, skipping [`SIGNATURE_DIR`]. `source` is the pak
+    /// the entries were parsed from.
+    pub fn build(root: &FileEntry, source: &[u8]) -> Result<Self, PakError> {
+        let mut files = Vec::new();
+        collect_files(root, "", source, &mut files)?;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Manifest { files })
+    }
+
+    /// Parses a manifest in the format its `Display` impl writes.
+    pub fn parse(text: &str) -> Result<Self, SignatureError> {
+        let malformed = |line: &str| SignatureError::MalformedManifest(line.to_string());
+
+        let mut lines = text.lines();
+        if lines.next() != Some(MANIFEST_HEADER) {
+            return Err(malformed("missing header"));
+        }
+
+        let mut files = Vec::new();
+        for line in lines {
+            let mut parts = line.splitn(3, ' ');
+            let (Some(sha256), Some(len), Some(path)) = (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(malformed(line));
+            };
+
+            files.push(ManifestEntry {
+                path: path.to_string(),
+                len: len.parse().map_err(|_| malformed(line))?,
+                sha256: from_hex(sha256).ok_or_else(|| malformed(line))?,
+            });
+        }
+
+        Ok(Manifest { files })
+    }
+
+    /// The listed files, in path order.
+    pub fn files(&self) -> &[ManifestEntry] {
+        &self.files
+    }
+
+    /// Paths of the files which differ between this manifest and `actual`, including those
+    /// only in one of them.
+    pub fn mismatches(&self, actual: &Manifest) -> Vec<String> {
+        let mut mismatched: Vec<String> = self
+            .files
+            .iter()
+            .filter(|expected| !actual.files.contains(expected))
+            .chain(
+                actual.files.iter().filter(|file| !self.files.iter().any(|e| e.path == file.path)),
+            )
+            .map(|file| file.path.clone())
+            .collect();
+        mismatched.sort();
+        mismatched.dedup();
+        mismatched
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{MANIFEST_HEADER}")?;
+        for file in &self.files {
+            writeln!(f, "{} {} {}", to_hex(&file.sha256), file.len, file.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl PakFile {
+    /// Hashes every file into a [`Manifest`] and adds it to this pak at [`MANIFEST_PATH`],
+    /// along with a signature made with `key` if one is given. Any manifest already in the
+    /// pak is replaced. Write the pak with [`PakFile::write_to`] afterwards.
+    ///
+    /// `source` is the pak this one was parsed from, or empty for a pak made with
+    /// [`PakFile::new`]. The FILE chunk must have been parsed, not deferred.
+    pub fn embed_manifest(
+        &mut self,
+        source: &[u8],
+        key: Option<&SigningKey>,
+    ) -> Result<Manifest, PakError> {
+        let Some(Chunk::File { fs }) = self.file_chunk_mut() else {
+            return Err(PakError::MissingFileChunk);
+        };
+        let root = RcFileEntry::make_mut(fs);
+        let _ = root.remove(SIGNATURE_DIR);
+
+        let manifest = Manifest::build(root, source)?;
+        let text = manifest.to_string();
+        let now = jiff::Timestamp::now().to_zoned(TimeZone::UTC).datetime();
+        let (dir, manifest_name) = MANIFEST_PATH.rsplit_once('/').unwrap();
+        let (_, signature_name) = SIGNATURE_PATH.rsplit_once('/').unwrap();
+
+        if let Some(key) = key {
+            let signature = key.sign(text.as_bytes()).to_bytes();
+            let entry = FileEntry::new_file(signature_name, &signature[..], now);
+            root.insert(dir, entry).map_err(|_| PakError::Unwritable("cannot add signature"))?;
+        }
+        let entry = FileEntry::new_file(manifest_name, text.into_bytes(), now);
+        root.insert(dir, entry).map_err(|_| PakError::Unwritable("cannot add manifest"))?;
+
+        Ok(manifest)
+    }
+
+    /// Checks this pak's files against its embedded manifest, and the manifest against its
+    /// signature if `key` is given. Returns the manifest if everything matches.
+    ///
+    /// `source` is the pak this one was parsed from.
+    pub fn verify_manifest(
+        &self,
+        source: &[u8],
+        key: Option<&VerifyingKey>,
+    ) -> Result<Manifest, SignatureError> {
+        let materialized;
+        let root: &FileEntry = match self.file_chunk() {
+            Some(Chunk::File { fs }) => fs,
+            _ => match self.lazy_file_table(source) {
+                Some(table) => {
+                    materialized = table?.root().materialize()?;
+                    &materialized
+                }
+                None => return Err(PakError::MissingFileChunk.into()),
+            },
+        };
+
+        let manifest_entry = root.get(MANIFEST_PATH).ok_or(SignatureError::NoManifest)?;
+        let text = read_file(manifest_entry, source)?;
+
+        if let Some(key) = key {
+            let signature = root.get(SIGNATURE_PATH).ok_or(SignatureError::NoSignature)?;
+            let signature = read_file(signature, source)?;
+            let signature =
+                Signature::from_slice(&signature).map_err(|_| SignatureError::BadSignature)?;
+            key.verify_strict(&text, &signature).map_err(|_| SignatureError::BadSignature)?;
+        }
+
+        let text = String::from_utf8(text)
+            .map_err(|_| SignatureError::MalformedManifest("not UTF-8".to_string()))?;
+        let manifest = Manifest::parse(&text)?;
+        let mismatched = manifest.mismatches(&Manifest::build(root, source)?);
+        if !mismatched.is_empty() {
+            return Err(SignatureError::Mismatch(mismatched));
+        }
+
+        Ok(manifest)
+    }
+}
+
+fn collect_files(
+    folder: &FileEntry,
+    dir: &str,
+    source: &[u8],
+    files: &mut Vec<ManifestEntry>,
+) -> Result<(), PakError> {
+    let FileEntryMeta::Folder { children } = folder.meta() else {
+        return Ok(());
+    };
+
+    for child in children {
+        let path = if dir.is_empty() {
+            child.name().to_string()
+        } else {
+            format!("{dir}/{}", child.name())
+        };
+        match child.meta() {
+            FileEntryMeta::Folder { .. } if path == SIGNATURE_DIR => {}
+            FileEntryMeta::Folder { .. } => collect_files(child, &path, source, files)?,
+            FileEntryMeta::File { .. } => {
+                let contents = read_file(child, source)?;
+                files.push(ManifestEntry {
+                    path,
+                    len: contents.len() as u64,
+                    sha256: Sha256::digest(&contents).into(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads and decompresses a file's contents.
+fn read_file(entry: &FileEntry, source: &[u8]) -> Result<Vec<u8>, PakError> {
+    if let Some(contents) = entry.staged_contents() {
+        return Ok(contents.to_vec());
+    }
+
+    let FileEntryMeta::File { offset, compressed_len, decompressed_len, compressed, .. } =
+        entry.meta()
+    else {
+        return Ok(Vec::new());
+    };
+
+    let start = *offset as usize;
+    let data = source.get(start..start + *compressed_len as usize).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "file data lies past the end")
+    })?;
+
+    let mut contents = Vec::with_capacity(*decompressed_len as usize);
+    if *compressed != 0 {
+        flate2::read::ZlibDecoder::new(data).read_to_end(&mut contents)?;
+    } else {
+        contents.extend_from_slice(data);
+    }
+
+    Ok(contents)
+}
+
+/// Lowercase hex, as used for hashes and keys.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+/// Parses exactly `N` bytes of hex. Surrounding whitespace is ignored.
+pub fn from_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    let hex = hex.trim();
+    if hex.len() != N * 2 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0; N];
+    for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_pak::pak_with;
+
+    fn signed_pak(key: &SigningKey) -> Vec<u8> {
+        let mut pak = pak_with(&[("scripts/a.c", "class A {}"), ("b.conf", "b")]);
+
+        let manifest = pak.embed_manifest(&[], Some(key)).unwrap();
+        assert_eq!(manifest.files().len(), 2);
+
+        let mut written = Vec::new();
+        pak.write_to(&[], &mut written).unwrap();
+        written
+    }
+
+    #[test]
+    fn signed_manifest_verifies() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let data = signed_pak(&key);
+        let pak = PakFile::parse(&data).unwrap();
+
+        let manifest = pak.verify_manifest(&data, Some(&key.verifying_key())).unwrap();
+        assert_eq!(manifest.files()[0].path, "b.conf");
+        assert_eq!(manifest.files()[1].path, "scripts/a.c");
+        assert_eq!(manifest.files()[1].sha256, <[u8; 32]>::from(Sha256::digest(b"class A {}")));
+        assert_eq!(Manifest::parse(&manifest.to_string()).unwrap(), manifest);
+
+        let other_key = SigningKey::from_bytes(&[8; 32]).verifying_key();
+        let err = pak.verify_manifest(&data, Some(&other_key)).unwrap_err();
+        assert!(matches!(err, SignatureError::BadSignature));
+    }
+
+    #[test]
+    fn changed_files_are_reported() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let mut data = signed_pak(&key);
+        let pak = PakFile::parse(&data).unwrap();
+
+        let FileEntryMeta::File { offset, .. } = pak_root(&pak).get("b.conf").unwrap().meta()
+        else {
+            panic!("b.conf is not a file");
+        };
+        data[*offset as usize] = b'c';
+
+        let err = pak.verify_manifest(&data, Some(&key.verifying_key())).unwrap_err();
+        let SignatureError::Mismatch(paths) = err else { panic!("expected a mismatch: {err}") };
+        assert_eq!(paths, ["b.conf"]);
+    }
+
+    fn pak_root(pak: &PakFile) -> &FileEntry {
+        let Some(Chunk::File { fs }) = pak.file_chunk() else { panic!("no FILE chunk") };
+        fs
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        assert_eq!(to_hex(&[0x00, 0xAB, 0x7F]), "00ab7f");
+        assert_eq!(from_hex::<3>(" 00AB7f\n"), Some([0x00, 0xAB, 0x7F]));
+        assert_eq!(from_hex::<3>("00ab"), None);
+        assert_eq!(from_hex::<2>("zzzz"), None);
+    }
+}
@@ -0,0 +1,390 @@
+//! Localization string tables and how completely they're translated.
+//!
+//! Enfusion `.st` files are text configs with one class per string:
+//!
+//! ```text
+//! StringTableSource {
+//!  Items {
+//!   StringTableItem {
+//!    Id "AR-Example_Name"
+//!    Target_en_us "Example"
+//!    Target_de_de "Beispiel"
+//!   }
+//!  }
+//! }
+//! ```
+//!
+//! Any class with an `Id` and at least one `Target_<language>` property is read as a
+//! string; other properties are ignored. DayZ's `stringtable.csv` (a key column followed by
+//! one column per language) is also supported.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StringTableError {
+    #[error("unterminated string starting at line {0}")]
+    UnterminatedString(usize),
+    #[error("unbalanced braces at line {0}")]
+    UnbalancedBraces(usize),
+    #[error("CSV header is missing")]
+    MissingHeader,
+}
+
+/// One localized string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringTableEntry {
+    pub id: String,
+    /// Language to translated text. Languages without a translation are absent.
+    pub translations: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StringTable {
+    pub entries: Vec<StringTableEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Word(&'a str),
+    String(String),
+    Open,
+    Close,
+}
+
+/// Splits an Enfusion text config into tokens along with the line each starts on.
+fn tokenize(text: &str) -> Result<Vec<(usize, Token<'_>)>, StringTableError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '{' => tokens.push((line, Token::Open)),
+            '}' => tokens.push((line, Token::Close)),
+            '/' if chars.peek().is_some_and(|(_, next)| *next == '/') => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            }
+            '"' => {
+                let start_line = line;
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => string.push('\n'),
+                            Some((_, 't')) => string.push('\t'),
+                            Some((_, c)) => string.push(c),
+                            None => return Err(StringTableError::UnterminatedString(start_line)),
+                        },
+                        Some((_, c)) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            string.push(c);
+                        }
+                        None => return Err(StringTableError::UnterminatedString(start_line)),
+                    }
+                }
+                tokens.push((start_line, Token::String(string)));
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((idx, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !matches!(c, '{' | '}' | '"'))
+                {
+                    end = idx + c.len_utf8();
+                }
+                tokens.push((line, Token::Word(&text[start..end])));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+impl StringTable {
+    /// Parses an Enfusion `.st` string table.
+    pub fn parse(text: &str) -> Result<Self, StringTableError> {
+        let tokens = tokenize(text)?;
+
+        // Properties of each class currently open, innermost last
+        let mut open: Vec<(Option<String>, BTreeMap<String, String>)> = Vec::new();
+        let mut entries = Vec::new();
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some((line, token)) = tokens.next() {
+            match token {
+                Token::Open => open.push(Default::default()),
+                Token::Close => {
+                    let (id, translations) =
+                        open.pop().ok_or(StringTableError::UnbalancedBraces(line))?;
+                    if let Some(id) = id
+                        && !translations.is_empty()
+                    {
+                        entries.push(StringTableEntry { id, translations });
+                    }
+                }
+                Token::Word(name) => {
+                    let Some((_, Token::String(_))) = tokens.peek() else {
+                        continue;
+                    };
+                    let Some((_, Token::String(value))) = tokens.next() else {
+                        unreachable!("peeked a string");
+                    };
+                    let Some((id, translations)) = open.last_mut() else {
+                        continue;
+                    };
+
+                    if name == "Id" {
+                        *id = Some(value);
+                    } else if let Some(language) = name.strip_prefix("Target_")
+                        && !value.is_empty()
+                    {
+                        translations.insert(language.to_string(), value);
+                    }
+                }
+                Token::String(_) => {}
+            }
+        }
+
+        if !open.is_empty() {
+            return Err(StringTableError::UnbalancedBraces(text.lines().count()));
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Parses a DayZ `stringtable.csv`, whose header row names the languages.
+    pub fn parse_csv(text: &str) -> Result<Self, StringTableError> {
+        let mut rows = csv_rows(text).into_iter();
+        let header = rows.next().ok_or(StringTableError::MissingHeader)?;
+        let languages = header.get(1..).ok_or(StringTableError::MissingHeader)?;
+
+        let entries = rows
+            .filter_map(|row| {
+                let (id, texts) = row.split_first()?;
+                let translations = languages
+                    .iter()
+                    .zip(texts)
+                    .filter(|(_, text)| !text.is_empty())
+                    .map(|(language, text)| (language.clone(), text.clone()))
+                    .collect();
+
+                Some(StringTableEntry { id: id.clone(), translations })
+            })
+            .collect();
+
+        Ok(Self { entries })
+    }
+}
+
+/// Splits CSV text into rows of fields, unquoting quoted fields. Quoted fields may span
+/// several lines, so rows only end at line breaks outside of quotes. Blank lines are skipped.
+fn csv_rows(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                push_csv_row(&mut rows, std::mem::take(&mut fields));
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    push_csv_row(&mut rows, fields);
+
+    rows
+}
+
+fn push_csv_row(rows: &mut Vec<Vec<String>>, fields: Vec<String>) {
+    if let [field] = fields.as_slice()
+        && field.trim().is_empty()
+    {
+        return;
+    }
+
+    rows.push(fields);
+}
+
+/// Which languages each string is translated into, across one or more string tables.
+#[derive(Debug, Default, Clone)]
+pub struct CoverageReport {
+    /// Every language any string is translated into, sorted.
+    pub languages: Vec<String>,
+    pub rows: Vec<CoverageRow>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoverageRow {
+    pub id: String,
+    /// Table the string was first found in.
+    pub source: String,
+    /// Text for each of [`CoverageReport::languages`], `None` where it's missing.
+    pub translations: Vec<Option<String>>,
+}
+
+impl CoverageReport {
+    /// Merges `tables` (named by their source path) into one report, sorted by string ID.
+    /// When several tables define the same ID, their translations are combined.
+    pub fn new<'a>(tables: impl IntoIterator<Item = (&'a str, &'a StringTable)>) -> Self {
+        let mut merged: BTreeMap<&str, (&str, BTreeMap<&str, &str>)> = BTreeMap::new();
+        let mut languages = BTreeSet::new();
+        for (source, table) in tables {
+            for entry in &table.entries {
+                let (_, translations) =
+                    merged.entry(&entry.id).or_insert((source, BTreeMap::new()));
+                for (language, text) in &entry.translations {
+                    languages.insert(language.as_str());
+                    translations.entry(language).or_insert(text);
+                }
+            }
+        }
+
+        let rows = merged
+            .into_iter()
+            .map(|(id, (source, translations))| CoverageRow {
+                id: id.to_string(),
+                source: source.to_string(),
+                translations: languages
+                    .iter()
+                    .map(|language| translations.get(language).map(|text| text.to_string()))
+                    .collect(),
+            })
+            .collect();
+
+        Self { languages: languages.into_iter().map(str::to_string).collect(), rows }
+    }
+
+    /// IDs of the strings missing a translation into `languages[language]`.
+    pub fn missing(&self, language: usize) -> impl Iterator<Item = &str> {
+        self.rows
+            .iter()
+            .filter(move |row| row.translations[language].is_none())
+            .map(|row| row.id.as_str())
+    }
+
+    /// Writes one row per string with its source table and text in every language. Missing
+    /// translations are left empty.
+    pub fn write_csv(&self, mut out: impl io::Write) -> io::Result<()> {
+        let header = ["id", "source"].into_iter().chain(self.languages.iter().map(String::as_str));
+        write_csv_row(&mut out, header)?;
+
+        for row in &self.rows {
+            let texts = row.translations.iter().map(|text| text.as_deref().unwrap_or_default());
+            write_csv_row(
+                &mut out,
+                [row.id.as_str(), row.source.as_str()].into_iter().chain(texts),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_csv_row<'a>(
+    out: &mut impl io::Write,
+    fields: impl Iterator<Item = &'a str>,
+) -> io::Result<()> {
+    for (idx, field) in fields.enumerate() {
+        if idx > 0 {
+            out.write_all(b",")?;
+        }
+
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(out, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            out.write_all(field.as_bytes())?;
+        }
+    }
+
+    out.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_enfusion_string_tables() {
+        let table = StringTable::parse(
+            r#"
+StringTableSource {
+ Items {
+  StringTableItem {
+   Id "AR-Rifle"
+   Target_en_us "Rifle"
+   Target_de_de "Gewehr"
+   Modified 1700000000
+  }
+  StringTableItem {
+   Id "AR-Quote"
+   // Not translated yet
+   Target_en_us "Say \"hi\""
+   Target_de_de ""
+  }
+ }
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(table.entries.len(), 2);
+        assert_eq!(table.entries[0].translations["de_de"], "Gewehr");
+        assert_eq!(table.entries[1].translations["en_us"], r#"Say "hi""#);
+        assert!(!table.entries[1].translations.contains_key("de_de"));
+    }
+
+    #[test]
+    fn reports_missing_translations() {
+        let pak = StringTable::parse_csv(
+            "\"Language\",\"english\",\"czech\"\n\"STR_A\",\"A\",\"\"\n\"STR_B\",\"B, b\",\"Bé\"\n",
+        )
+        .unwrap();
+
+        let report = CoverageReport::new([("stringtable.csv", &pak)]);
+        assert_eq!(report.languages, ["czech", "english"]);
+        assert_eq!(report.missing(0).collect::<Vec<_>>(), ["STR_A"]);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,source,czech,english\r\nSTR_A,stringtable.csv,,A\r\n\
+             STR_B,stringtable.csv,Bé,\"B, b\"\r\n"
+        );
+    }
+
+    #[test]
+    fn csv_fields_may_span_lines() {
+        let table = StringTable::parse_csv(
+            "Language,english,czech\r\n\
+             STR_A,\"First line\nsecond, \"\"quoted\"\" line\",A\r\n\
+             \r\n\
+             STR_B,B,\"\"\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(table.entries.len(), 2);
+        assert_eq!(table.entries[0].id, "STR_A");
+        assert_eq!(table.entries[0].translations["english"], "First line\nsecond, \"quoted\" line");
+        assert_eq!(table.entries[0].translations["czech"], "A");
+        assert_eq!(table.entries[1].id, "STR_B");
+        assert!(!table.entries[1].translations.contains_key("czech"));
+    }
+}
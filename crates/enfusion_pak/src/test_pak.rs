@@ -0,0 +1,59 @@
+//! Paks built in memory for tests.
+
+use jiff::civil::DateTime;
+use jiff::civil::date;
+
+use crate::Chunk;
+use crate::FileEntry;
+use crate::PakFile;
+use crate::RcFileEntry;
+
+/// When every file in a test pak was last modified.
+pub(crate) fn modified() -> DateTime {
+    date(2024, 5, 6).at(7, 8, 9, 0)
+}
+
+/// A new pak holding `files`, given as their paths and contents. Folders are created as
+/// they're needed.
+pub(crate) fn pak_with(files: &[(&str, impl AsRef<[u8]>)]) -> PakFile {
+    let mut pak = PakFile::new();
+    let Some(Chunk::File { fs }) = pak.file_chunk_mut() else { panic!("no FILE chunk") };
+    let root = RcFileEntry::make_mut(fs);
+    for (path, contents) in files {
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", *path));
+        root.insert(parent, FileEntry::new_file(name, contents.as_ref(), modified())).unwrap();
+    }
+
+    pak
+}
+
+/// The bytes of a pak holding `files`. See [`pak_with`].
+pub(crate) fn pak_bytes(files: &[(&str, impl AsRef<[u8]>)]) -> Vec<u8> {
+    let mut written = Vec::new();
+    pak_with(files).write_to(&[], &mut written).unwrap();
+    written
+}
+
+/// Appends a folder with `children` entries to a raw FILE chunk `table`. Its children are
+/// the entries appended after it.
+pub(crate) fn push_raw_folder(table: &mut Vec<u8>, name: &str, children: u32) {
+    table.push(0);
+    table.push(name.len() as u8);
+    table.extend_from_slice(name.as_bytes());
+    table.extend_from_slice(&children.to_le_bytes());
+}
+
+/// Appends an uncompressed file of `len` bytes at `offset` to a raw FILE chunk `table`.
+pub(crate) fn push_raw_file(table: &mut Vec<u8>, name: &str, offset: u32, len: u32) {
+    table.push(1);
+    table.push(name.len() as u8);
+    table.extend_from_slice(name.as_bytes());
+    table.extend_from_slice(&offset.to_le_bytes()); // offset
+    table.extend_from_slice(&len.to_le_bytes()); // compressed_len
+    table.extend_from_slice(&len.to_le_bytes()); // decompressed_len
+    table.extend_from_slice(&0u32.to_le_bytes()); // unk
+    table.extend_from_slice(&0u16.to_le_bytes()); // unk2
+    table.push(0); // compressed
+    table.push(0); // compression_level
+    table.extend_from_slice(&0u32.to_le_bytes()); // timestamp
+}
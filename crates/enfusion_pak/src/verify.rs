@@ -0,0 +1,190 @@
+//! Checking that every file in a pak can be read back intact.
+//!
+//! [`PakFile::verify`] reads and decompresses each file, checking it against the lengths the
+//! FILE chunk records, and hashes the contents with XXH3. The per-file checksums are rolled
+//! up into a digest of the whole archive, so two copies of a pak can be compared by a
+//! single number and a truncated download or bad repack shows up as either a damaged file
+//! or a different digest.
+
+use std::io::Read;
+use std::ops::Range;
+
+use xxhash_rust::xxh3::Xxh3;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::FileEntry;
+use crate::FileEntryMeta;
+use crate::PakFile;
+use crate::error::PakError;
+use crate::error::VerifyError;
+use crate::pak_vfs::Prime;
+
+/// The result of checking one file.
+#[derive(Debug)]
+pub struct FileCheck {
+    /// Path inside the pak without a leading `/`.
+    pub path: String,
+    /// XXH3-64 of the decompressed contents, or why they couldn't be read.
+    pub checksum: Result<u64, VerifyError>,
+}
+
+/// The files [`PakFile::verify`] checked, in path order.
+#[derive(Debug)]
+pub struct VerifyReport {
+    pub files: Vec<FileCheck>,
+    /// XXH3-64 over every file's path and checksum, in path order. Damaged files only
+    /// contribute their path.
+    pub digest: u64,
+}
+
+impl VerifyReport {
+    /// Whether every file was read back intact.
+    pub fn is_ok(&self) -> bool {
+        self.files.iter().all(|file| file.checksum.is_ok())
+    }
+
+    /// The files which couldn't be read back, and why.
+    pub fn damaged(&self) -> impl Iterator<Item = (&str, &VerifyError)> {
+        self.files
+            .iter()
+            .filter_map(|file| file.checksum.as_ref().err().map(|e| (file.path.as_str(), e)))
+    }
+}
+
+impl PakFile {
+    /// Reads and checksums every file in this pak. `prime` reads the pak's bytes.
+    ///
+    /// Only fails if the FILE chunk itself can't be read; problems with individual files
+    /// are recorded in the report.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use enfusion_pak::PakFile;
+    /// use enfusion_pak::wrappers::bytes::BytesPakFileWrapper;
+    ///
+    /// let path = std::path::PathBuf::from("example.pak");
+    /// let data = std::fs::read(&path).unwrap();
+    /// let pak = PakFile::parse(&data).unwrap();
+    /// let wrapper = BytesPakFileWrapper::new(path, data, pak);
+    ///
+    /// let report = wrapper.pak_file().verify(&wrapper).unwrap();
+    /// for (path, e) in report.damaged() {
+    ///     eprintln!("{path}: {e}");
+    /// }
+    /// println!("digest: {:016x}", report.digest);
+    /// ```
+    pub fn verify(&self, prime: &impl Prime) -> Result<VerifyReport, PakError> {
+        let root = self.root_entry(prime)?;
+        let data = self.data_range();
+
+        let mut files = Vec::new();
+        for (path, entry) in root.walk() {
+            if matches!(entry.meta(), FileEntryMeta::File { .. }) {
+                let checksum = self.file_checksum(prime, entry, data.as_ref());
+                files.push(FileCheck { path, checksum });
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut digest = Xxh3::new();
+        for file in &files {
+            digest.update(file.path.as_bytes());
+            // Paths can't contain NUL, so this keeps a path from running into the checksum
+            digest.update(&[0]);
+            if let Ok(checksum) = &file.checksum {
+                digest.update(&checksum.to_le_bytes());
+            }
+        }
+
+        Ok(VerifyReport { files, digest: digest.digest() })
+    }
+
+    fn file_checksum(
+        &self,
+        prime: &impl Prime,
+        entry: &FileEntry,
+        data: Option<&Range<usize>>,
+    ) -> Result<u64, VerifyError> {
+        if let Some(contents) = entry.staged_contents() {
+            return Ok(xxh3_64(contents));
+        }
+
+        let FileEntryMeta::File { decompressed_len, compressed, .. } = entry.meta() else {
+            unreachable!("only files are checked");
+        };
+        let Some(range) = self.file_data_range(entry) else {
+            unreachable!("only files are checked");
+        };
+        if let Some(data) = data
+            && (range.start < data.start || range.end > data.end)
+        {
+            return Err(VerifyError::OutOfBounds { range, data: data.clone() });
+        }
+
+        let stored = prime.prime_file(range).map_err(|e| VerifyError::Unreadable(e.to_string()))?;
+        let stored = stored.as_ref();
+        let contents = if *compressed != 0 {
+            let mut contents = Vec::with_capacity(*decompressed_len as usize);
+            flate2::read::ZlibDecoder::new(stored)
+                .read_to_end(&mut contents)
+                .map_err(|e| VerifyError::Inflate(e.to_string()))?;
+            std::borrow::Cow::Owned(contents)
+        } else {
+            std::borrow::Cow::Borrowed(stored)
+        };
+
+        if contents.len() as u64 != u64::from(*decompressed_len) {
+            return Err(VerifyError::WrongLength {
+                expected: *decompressed_len,
+                actual: contents.len() as u64,
+            });
+        }
+
+        Ok(xxh3_64(&contents))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::Chunk;
+    use crate::test_pak::pak_bytes;
+    use crate::wrappers::bytes::BytesPakFileWrapper;
+
+    fn verify(data: Vec<u8>, source: impl FnOnce(&[u8]) -> Vec<u8>) -> VerifyReport {
+        let pak = PakFile::parse(&data).unwrap();
+        let wrapper = BytesPakFileWrapper::new(PathBuf::new(), source(&data), pak);
+        wrapper.pak_file().verify(&wrapper).unwrap()
+    }
+
+    #[test]
+    fn detects_changed_and_missing_data() {
+        let data = pak_bytes(&[("scripts/a.c", "first file"), ("scripts/b.c", "second file")]);
+        let intact = verify(data.clone(), <[u8]>::to_vec);
+        assert!(intact.is_ok());
+        assert_eq!(intact.files[0].path, "scripts/a.c");
+        assert_eq!(*intact.files[1].checksum.as_ref().unwrap(), xxh3_64(b"second file"));
+        assert_eq!(verify(data.clone(), <[u8]>::to_vec).digest, intact.digest);
+
+        // Flip a byte of the first file's stored contents
+        let pak = PakFile::parse(&data).unwrap();
+        let Some(Chunk::File { fs }) = pak.file_chunk() else { panic!("no FILE chunk") };
+        let a_start = pak.file_data_range(fs.get("scripts/a.c").unwrap()).unwrap().start;
+        let b_range = pak.file_data_range(fs.get("scripts/b.c").unwrap()).unwrap();
+        let changed = verify(data.clone(), |data| {
+            let mut data = data.to_vec();
+            data[a_start] ^= 0xFF;
+            data
+        });
+        assert!(changed.is_ok());
+        assert_ne!(changed.digest, intact.digest);
+
+        // Cut the source off partway through the second file
+        let truncated = verify(data, |data| data[..b_range.start + 1].to_vec());
+        let damaged: Vec<_> = truncated.damaged().map(|(path, _)| path).collect();
+        assert_eq!(damaged, ["scripts/b.c"]);
+    }
+}
@@ -0,0 +1,56 @@
+//! Differences between versions of the PAK format.
+//!
+//! The HEAD chunk declares which version of the format the rest of the archive uses. The
+//! parser looks the version up here before reading any entries so that a pak from a newer
+//! (or older) engine build fails loudly instead of being silently misparsed.
+
+use crate::error::UnsupportedVersion;
+
+/// A PAK format version this crate knows how to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PakVersion {
+    /// `0x10003`, used by Arma Reforger.
+    V1_3,
+}
+
+impl PakVersion {
+    /// Version assumed before a HEAD chunk has been seen.
+    pub const LATEST: PakVersion = PakVersion::V1_3;
+
+    /// Looks up the version declared by a HEAD chunk.
+    pub fn from_raw(raw: u32) -> Result<Self, UnsupportedVersion> {
+        match raw {
+            0x10003 => Ok(PakVersion::V1_3),
+            other => Err(UnsupportedVersion(other)),
+        }
+    }
+
+    /// The value stored in the HEAD chunk for this version.
+    pub fn raw(self) -> u32 {
+        match self {
+            PakVersion::V1_3 => 0x10003,
+        }
+    }
+
+    /// Size of a file entry's metadata following its name in the FILE chunk.
+    pub(crate) fn file_meta_len(self) -> usize {
+        match self {
+            PakVersion::V1_3 => 24,
+        }
+    }
+}
+
+impl Default for PakVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+impl TryFrom<u32> for PakVersion {
+    type Error = UnsupportedVersion;
+
+    fn try_from(raw: u32) -> Result<Self, Self::Error> {
+        Self::from_raw(raw)
+    }
+}
@@ -0,0 +1,128 @@
+//! Iterating over every entry below a folder in the FILE tree.
+
+use std::collections::VecDeque;
+
+use crate::FileEntry;
+use crate::FileEntryMeta;
+use crate::RcFileEntry;
+
+/// The order [`FileEntry::walk_with`] visits entries in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalkOrder {
+    /// Each folder's contents right after the folder, before its next sibling.
+    #[default]
+    DepthFirst,
+    /// Everything at one depth before anything deeper.
+    BreadthFirst,
+}
+
+impl FileEntry {
+    /// Every file and folder below this entry, depth first, with its path relative to this
+    /// entry. See [`FileEntry::walk_with`].
+    pub fn walk(&self) -> Walk<'_> {
+        self.walk_with(WalkOrder::DepthFirst)
+    }
+
+    /// Every file and folder below this entry, visited in `order`. Paths are `/`-separated
+    /// without a leading `/`, so walking a FILE chunk's root gives paths like
+    /// `scripts/Game/foo.c`. A folder's children are visited in the order they're stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use enfusion_pak::FileEntry;
+    /// use enfusion_pak::walk::WalkOrder;
+    ///
+    /// let modified = jiff::civil::date(2024, 1, 1).at(0, 0, 0, 0);
+    /// let mut root = FileEntry::new_folder("");
+    /// root.insert("scripts/Game", FileEntry::new_file("a.c", &b""[..], modified)).unwrap();
+    /// root.insert("", FileEntry::new_file("b.c", &b""[..], modified)).unwrap();
+    ///
+    /// let paths: Vec<String> = root.walk().map(|(path, _)| path).collect();
+    /// assert_eq!(paths, ["scripts", "scripts/Game", "scripts/Game/a.c", "b.c"]);
+    ///
+    /// let paths: Vec<String> =
+    ///     root.walk_with(WalkOrder::BreadthFirst).map(|(path, _)| path).collect();
+    /// assert_eq!(paths, ["scripts", "b.c", "scripts/Game", "scripts/Game/a.c"]);
+    /// ```
+    pub fn walk_with(&self, order: WalkOrder) -> Walk<'_> {
+        Walk { order, queue: VecDeque::new(), expand: Some((String::new(), self)) }
+    }
+}
+
+/// Iterator returned by [`FileEntry::walk`].
+#[derive(Debug, Clone)]
+pub struct Walk<'a> {
+    order: WalkOrder,
+    queue: VecDeque<(String, &'a RcFileEntry)>,
+    /// The folder returned last, whose children are queued on the next call so that
+    /// [`Walk::skip_children`] can leave them out.
+    expand: Option<(String, &'a FileEntry)>,
+}
+
+impl Walk<'_> {
+    /// Leaves out the contents of the folder returned last.
+    pub fn skip_children(&mut self) {
+        self.expand = None;
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    /// The entry's path, and the entry.
+    type Item = (String, &'a RcFileEntry);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((dir, folder)) = self.expand.take()
+            && let FileEntryMeta::Folder { children } = folder.meta()
+        {
+            let children = children.iter().map(|child| {
+                let path = if dir.is_empty() {
+                    child.name().to_string()
+                } else {
+                    format!("{dir}/{}", child.name())
+                };
+                (path, child)
+            });
+            match self.order {
+                // Pushed in reverse so the first child comes out first
+                WalkOrder::DepthFirst => {
+                    children.rev().for_each(|child| self.queue.push_front(child))
+                }
+                WalkOrder::BreadthFirst => self.queue.extend(children),
+            }
+        }
+
+        let (path, entry) = self.queue.pop_front()?;
+        if matches!(entry.meta(), FileEntryMeta::Folder { .. }) {
+            self.expand = Some((path.clone(), entry));
+        }
+        Some((path, entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_children_of_pruned_folders() {
+        let modified = jiff::civil::date(2024, 1, 1).at(0, 0, 0, 0);
+        let mut root = FileEntry::new_folder("");
+        for (dir, name) in [("a/b", "x.c"), ("a", "y.c"), ("c", "z.c")] {
+            root.insert(dir, FileEntry::new_file(name, &b""[..], modified)).unwrap();
+        }
+
+        for order in [WalkOrder::DepthFirst, WalkOrder::BreadthFirst] {
+            let mut walk = root.walk_with(order);
+            let mut paths = Vec::new();
+            while let Some((path, _)) = walk.next() {
+                if path == "a/b" {
+                    walk.skip_children();
+                }
+                paths.push(path);
+            }
+            paths.sort();
+            assert_eq!(paths, ["a", "a/b", "a/y.c", "c", "c/z.c"]);
+        }
+    }
+}
@@ -10,12 +10,19 @@ use crate::ParserStateMachine;
 use crate::Stream;
 use crate::async_pak_vfs::AsyncPrime;
 use crate::async_pak_vfs::AsyncReadAt;
+use crate::error::ParserDiagnostic;
 use crate::pak_vfs::Prime;
+use crate::winnow::error::ErrMode;
+use crate::winnow::error::Needed;
 use crate::winnow::stream::Offset;
 use crate::winnow::stream::Stream as _;
 use async_trait::async_trait;
 use log::debug;
 use vfs::VfsError;
+use vfs::VfsErrorKind;
+
+/// How much of the pak [`parse_pak_file`] asks for at a time.
+const READ_LEN: usize = 1024 * 64;
 
 /// An async wrapper around a PakFile and its data source which caches reads
 #[allow(unused)]
@@ -89,11 +96,10 @@ where
             }
         }
 
-        let data = self.handle.read_at(file_range.clone()).await?;
+        let data = read_exact_at(&self.handle, file_range.clone()).await?;
 
-        let file_size = file_range.end - file_range.start;
-        let mut buffer = oval::Buffer::with_capacity(file_size);
-        let mut data: &[u8] = data.as_ref();
+        let mut buffer = oval::Buffer::with_capacity(data.len());
+        let mut data: &[u8] = &data;
         let mut buffer_slice = buffer.space();
         let read = std::io::copy(&mut data, &mut buffer_slice).expect("failed to copy to buffer");
         buffer.fill(read as usize);
@@ -125,6 +131,30 @@ where
     }
 }
 
+/// Reads all of `range` from `source`, asking again for the rest whenever a read returns
+/// less than was asked for. Fails if the source ends before the range does.
+async fn read_exact_at<T>(source: &T, range: std::ops::Range<usize>) -> Result<Vec<u8>, VfsError>
+where
+    T: AsyncReadAt + Sync,
+{
+    let mut data = Vec::with_capacity(range.len());
+    while data.len() < range.len() {
+        let read_start = range.start + data.len();
+        let read = source.read_at(read_start..range.end).await?;
+        let read: &[u8] = read.as_ref();
+        if read.is_empty() {
+            return Err(VfsErrorKind::IoError(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("source ended at {read_start:#X}, before the end of {range:#X?}"),
+            ))
+            .into());
+        }
+        data.extend_from_slice(&read[..read.len().min(range.end - read_start)]);
+    }
+
+    Ok(data)
+}
+
 pub async fn parse_pak_file<T>(
     path: PathBuf,
     file_handle: T,
@@ -134,29 +164,32 @@ where
 {
     let mut parser = PakParser::new();
 
-    // 64k buffer size
-    let mut buffer = oval::Buffer::with_capacity(1024 * 64);
+    // The pak from `parser.bytes_parsed()` on, as far as it's been read. Sources can return
+    // less than was asked for, so whatever the parser couldn't use yet is kept and added to
+    // rather than read again.
+    let mut buffer: Vec<u8> = Vec::with_capacity(READ_LEN);
 
     loop {
-        // Populate the buffer with the first 16k
-        //
-        // TODO: fix this so we only load the minimum amount of data
-        let read_range = parser.bytes_parsed()..(parser.bytes_parsed() + buffer.capacity());
-        let read_handle = file_handle.clone();
-        let data = read_handle.read_at(read_range).await?;
-
-        buffer.reset();
-
-        let mut data_slice: &[u8] = data.as_ref();
-        let mut buffer_slice = buffer.space();
-
-        let read = std::io::copy(&mut data_slice, &mut buffer_slice)
-            .expect("failed to copy from vec to oval buffer");
-        buffer.fill(read as usize);
+        let buffer_start = parser.bytes_parsed();
+        let read_start = buffer_start + buffer.len();
+        let read_len = {
+            let data = file_handle.read_at(read_start..(read_start + READ_LEN)).await?;
+            let data: &[u8] = data.as_ref();
+            buffer.extend_from_slice(&data[..data.len().min(READ_LEN)]);
+            data.len()
+        };
+        if read_len == 0 {
+            // The parser still wants more, so the pak is truncated
+            let diagnostic = ParserDiagnostic::new(ErrMode::Incomplete(Needed::Unknown))
+                .with_location(&buffer, buffer_start, buffer.len());
+            return Err(VfsError::from(VfsErrorKind::Other(format!(
+                "error reading pak file {}: {diagnostic}",
+                path.display()
+            ))));
+        }
 
-        let mut input = Stream::new(buffer.data());
+        let mut input = Stream::new(&buffer);
         let start = input.checkpoint();
-        // For mmap the parser should never raise an error or require state transitions
         match parser.parse(&mut input) {
             Ok(ParserStateMachine::Done(pak_file)) => {
                 debug!("Parser is done");
@@ -170,21 +203,106 @@ where
             Ok(ParserStateMachine::Skip { from: _, count, parser: next_parser }) => {
                 assert!(next_parser.bytes_parsed() > 0);
 
-                buffer.consume(
-                    (input.checkpoint().offset_from(&start) + count).min(buffer.available_space()),
-                );
+                // The skipped bytes can run past what's been read
+                let consumed = input.checkpoint().offset_from(&start) + count;
+                buffer.drain(..consumed.min(buffer.len()));
                 parser = next_parser;
             }
             Ok(ParserStateMachine::Continue(next_parser)) => {
-                buffer.consume(input.checkpoint().offset_from(&start));
+                let consumed = input.checkpoint().offset_from(&start);
+                buffer.drain(..consumed);
                 parser = next_parser;
             }
             Ok(ParserStateMachine::Loop(_)) => {
                 unreachable!("This should never occur");
             }
             Err(e) => {
-                panic!("error reading pak file: {e:?}")
+                let pos = input.checkpoint().offset_from(&start);
+                let diagnostic = ParserDiagnostic::new(e).with_location(&buffer, buffer_start, pos);
+                return Err(VfsError::from(VfsErrorKind::Other(format!(
+                    "error reading pak file {}: {diagnostic}",
+                    path.display()
+                ))));
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::Chunk;
+    use crate::wrappers::faulty_source::FaultySource;
+    use crate::wrappers::faulty_source::sample_files;
+    use crate::wrappers::faulty_source::sample_pak;
+
+    fn parse(source: &FaultySource) -> Result<CachingAsyncPakFileWrapper<FaultySource>, VfsError> {
+        block_on(parse_pak_file(PathBuf::from("test.pak"), source.clone()))
+    }
+
+    fn data_range(wrapper: &CachingAsyncPakFileWrapper<FaultySource>, path: &str) -> Range<usize> {
+        let pak = wrapper.as_ref();
+        let Some(Chunk::File { fs }) = pak.file_chunk() else { panic!("no FILE chunk") };
+        let entry = fs.get(path).unwrap_or_else(|| panic!("{path} is missing"));
+        pak.file_data_range(entry).unwrap()
+    }
+
+    /// Reads the file at `path` through the wrapper's cache.
+    async fn read(
+        wrapper: &CachingAsyncPakFileWrapper<FaultySource>,
+        path: &str,
+    ) -> Result<Vec<u8>, VfsError> {
+        let primed = AsyncPrime::prime_file(wrapper, data_range(wrapper, path)).await?;
+        Ok(primed.as_ref().to_vec())
+    }
+
+    #[test]
+    fn parses_and_reads_through_short_slow_reads() {
+        for max_read_len in [1, 7, 4096, READ_LEN * 4] {
+            let source = FaultySource::new(sample_pak()).latency(2).max_read_len(max_read_len);
+            let wrapper = parse(&source).expect("failed to parse pak");
+
+            // Reads of different files interleave, and all of them are put together
+            let files = sample_files();
+            let read_files = block_on(futures::future::join_all(
+                files.iter().map(|(path, _)| read(&wrapper, path)),
+            ));
+            for ((path, contents), read) in files.iter().zip(read_files) {
+                let read = read.unwrap_or_else(|e| panic!("failed to read {path}: {e}"));
+                assert_eq!(&read, contents, "{path} read {max_read_len} bytes at a time");
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_paks_fail_to_parse() {
+        let pak = sample_pak();
+        for len in [0, 10, pak.len() / 2, pak.len() - 1] {
+            let source = FaultySource::new(pak[..len].to_vec()).max_read_len(4096);
+            assert!(parse(&source).is_err(), "parsed a pak cut to {len} bytes");
+        }
+    }
+
+    #[test]
+    fn read_errors_are_returned_and_not_cached() {
+        let pak = sample_pak();
+        let parse_reads = parse(&FaultySource::new(pak.clone())).unwrap().handle.reads();
+        for read in 0..parse_reads {
+            let source = FaultySource::new(pak.clone()).failing_reads([read]);
+            assert!(parse(&source).is_err(), "read {read} failing didn't fail parsing");
+        }
+
+        // The first read after parsing fails, and the one retrying it is cached
+        let source = FaultySource::new(pak).failing_reads([parse_reads]);
+        let wrapper = parse(&source).unwrap();
+        assert!(block_on(read(&wrapper, "scripts/Game/a.c")).is_err());
+        for _ in 0..2 {
+            assert_eq!(block_on(read(&wrapper, "scripts/Game/a.c")).unwrap(), b"class A {}");
+        }
+        assert_eq!(source.reads(), parse_reads + 2);
+    }
+}
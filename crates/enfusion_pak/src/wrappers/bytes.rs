@@ -5,6 +5,7 @@ use std::path::PathBuf;
 #[cfg(feature = "async_vfs")]
 use async_trait::async_trait;
 use vfs::VfsError;
+use vfs::error::VfsErrorKind;
 
 use crate::PakFile;
 #[cfg(feature = "async_vfs")]
@@ -74,7 +75,9 @@ where
     T: AsRef<[u8]>,
 {
     fn prime_file(&self, file_range: std::ops::Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
-        Ok(&self.source_bytes()[file_range])
+        self.source_bytes()
+            .get(file_range)
+            .ok_or_else(|| VfsErrorKind::Other("read past the end".into()).into())
     }
 }
 
@@ -88,6 +91,8 @@ where
         &self,
         file_range: std::ops::Range<usize>,
     ) -> Result<impl AsRef<[u8]>, VfsError> {
-        Ok(&self.source_bytes()[file_range])
+        self.source_bytes()
+            .get(file_range)
+            .ok_or_else(|| VfsErrorKind::Other("read past the end".into()).into())
     }
 }
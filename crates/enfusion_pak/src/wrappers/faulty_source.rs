@@ -0,0 +1,166 @@
+//! An [`AsyncReadAt`] source which misbehaves on request, for testing readers against slow
+//! or unreliable backends before there's a real one (e.g. HTTP range requests) to test with.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use vfs::VfsError;
+use vfs::VfsErrorKind;
+
+use crate::async_pak_vfs::AsyncReadAt;
+use crate::test_pak::pak_bytes;
+use crate::wrappers::retry::Timer;
+
+/// In-memory data served through [`AsyncReadAt`] with configurable faults. Clones share the
+/// data, the faults and the read count.
+///
+/// Like a browser `Blob` or an HTTP range request, reads running past the end of the data
+/// return what there is, and reads starting at or past the end return nothing.
+#[derive(Debug, Clone)]
+pub(crate) struct FaultySource {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    data: Vec<u8>,
+    latency: usize,
+    max_read_len: Option<usize>,
+    failing_reads: HashSet<usize>,
+    stalled_reads: HashSet<usize>,
+    reads: AtomicUsize,
+}
+
+impl FaultySource {
+    /// A source which serves `data` without faults until some are configured.
+    pub(crate) fn new(data: Vec<u8>) -> Self {
+        FaultySource { inner: Arc::new(Inner { data, ..Default::default() }) }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("faults are configured before the source is cloned")
+    }
+
+    /// Leaves every read pending for `polls` polls before it completes, so concurrent reads
+    /// interleave.
+    pub(crate) fn latency(mut self, polls: usize) -> Self {
+        self.inner_mut().latency = polls;
+        self
+    }
+
+    /// Returns at most `len` bytes per read, however many were asked for.
+    pub(crate) fn max_read_len(mut self, len: usize) -> Self {
+        assert!(len > 0, "an empty read means the end of the data");
+        self.inner_mut().max_read_len = Some(len);
+        self
+    }
+
+    /// Fails the reads with these indices, counting from 0 in the order reads are made.
+    pub(crate) fn failing_reads(mut self, reads: impl IntoIterator<Item = usize>) -> Self {
+        self.inner_mut().failing_reads.extend(reads);
+        self
+    }
+
+    /// Never completes the reads with these indices, like a connection which has hung.
+    pub(crate) fn stalled_reads(mut self, reads: impl IntoIterator<Item = usize>) -> Self {
+        self.inner_mut().stalled_reads.extend(reads);
+        self
+    }
+
+    /// How many reads have been made, including ones which failed.
+    pub(crate) fn reads(&self) -> usize {
+        self.inner.reads.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl AsyncReadAt for FaultySource {
+    async fn read_at(&self, file_range: Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
+        let inner = &self.inner;
+        let read = inner.reads.fetch_add(1, Ordering::SeqCst);
+        Pending(inner.latency).await;
+        if inner.stalled_reads.contains(&read) {
+            futures::future::pending::<()>().await;
+        }
+
+        if inner.failing_reads.contains(&read) {
+            return Err(VfsErrorKind::IoError(std::io::Error::other(format!(
+                "injected failure of read {read} ({file_range:#X?})"
+            )))
+            .into());
+        }
+
+        let start = file_range.start.min(inner.data.len());
+        let mut end = file_range.end.clamp(start, inner.data.len());
+        if let Some(max_read_len) = inner.max_read_len {
+            end = end.min(start + max_read_len);
+        }
+        Ok(inner.data[start..end].to_vec())
+    }
+}
+
+/// A future which is pending for the given number of polls, waking itself each time.
+struct Pending(usize);
+
+impl Future for Pending {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 == 0 {
+            return Poll::Ready(());
+        }
+
+        self.0 -= 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// A [`Timer`] which sleeps for a poll per millisecond, so timeouts can be tested against
+/// [`FaultySource::latency`] without waiting. Clones share the record of sleeps.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PollTimer {
+    sleeps: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl PollTimer {
+    /// Every duration slept for, in order, including sleeps cut short.
+    pub(crate) fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Timer for PollTimer {
+    async fn sleep(&self, duration: Duration) {
+        self.sleeps.lock().unwrap().push(duration);
+        Pending(duration.as_millis() as usize).await;
+    }
+}
+
+/// Paths and contents of the files in [`sample_pak`]. The large file is longer than
+/// [`parse_pak_file`](crate::wrappers::async_reader::parse_pak_file) reads at once, so the
+/// parser skips past the end of what it has buffered.
+pub(crate) fn sample_files() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("scripts/Game/a.c", b"class A {}".to_vec()),
+        ("scripts/Game/b.c", b"class B : A {}".to_vec()),
+        ("worlds/large.bin", (0..128 * 1024).map(|i| (i % 251) as u8).collect()),
+        ("readme.txt", b"hello".to_vec()),
+    ]
+}
+
+/// A pak holding [`sample_files`].
+pub(crate) fn sample_pak() -> Vec<u8> {
+    pak_bytes(&sample_files())
+}
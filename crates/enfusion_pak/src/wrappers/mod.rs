@@ -2,4 +2,8 @@
 pub mod async_reader;
 
 pub mod bytes;
+#[cfg(all(test, feature = "async_vfs"))]
+mod faulty_source;
+#[cfg(feature = "async_vfs")]
+pub mod retry;
 pub mod sync_reader;
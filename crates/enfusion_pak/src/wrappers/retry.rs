@@ -0,0 +1,241 @@
+//! Retrying failed and stalled reads from an [`AsyncReadAt`] source.
+//!
+//! Sources backed by the network (or a browser's file APIs) can fail or hang for a moment
+//! without the data being gone. [`RetryingSource`] wraps one so that a single hiccup doesn't
+//! fail a pak load, or a file read halfway through a long search or extraction.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::Either;
+use futures::future::select;
+use log::debug;
+use vfs::VfsError;
+use vfs::VfsErrorKind;
+
+use crate::async_pak_vfs::AsyncReadAt;
+
+/// How [`RetryingSource`] retries reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts made at each read, including the first. 1 never retries.
+    pub attempts: u32,
+    /// How long to wait before the first retry. Each retry after it waits twice as long as
+    /// the one before, up to `max_backoff`.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How long an attempt can take before it's abandoned and counted as failed. `None`
+    /// waits as long as the source takes.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 4,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+            timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy which makes one attempt at each read and never times it out.
+    pub fn never() -> Self {
+        RetryPolicy { attempts: 1, timeout: None, ..Self::default() }
+    }
+
+    /// How long to wait before retry number `retry`, counting from 1.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use enfusion_pak::wrappers::retry::RetryPolicy;
+    ///
+    /// let policy = RetryPolicy {
+    ///     initial_backoff: Duration::from_secs(1),
+    ///     max_backoff: Duration::from_secs(3),
+    ///     ..RetryPolicy::default()
+    /// };
+    /// let backoffs: Vec<u64> = (1..=4).map(|retry| policy.backoff(retry).as_secs()).collect();
+    /// assert_eq!(backoffs, [1, 2, 3, 3]);
+    /// ```
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// Waits for a while. Async runtimes each have their own timers, so [`RetryingSource`] is
+/// given one rather than picking it.
+#[async_trait]
+pub trait Timer {
+    async fn sleep(&self, duration: Duration);
+}
+
+/// Why a read through a [`RetryingSource`] failed, once it's given up.
+///
+/// It's returned as the source of a [`VfsErrorKind::IoError`]; use [`ReadError::find`] to
+/// get it back from a [`VfsError`].
+#[derive(Debug, thiserror::Error)]
+#[error("reading {range:#X?} failed after {attempts} attempt(s): {last_failure}")]
+pub struct ReadError {
+    pub range: Range<usize>,
+    pub attempts: u32,
+    /// Why the final attempt failed.
+    pub last_failure: ReadFailure,
+}
+
+/// Why one attempt at a read failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ReadFailure {
+    #[error("timed out after {0:?}")]
+    TimedOut(Duration),
+    #[error(transparent)]
+    Source(VfsError),
+}
+
+impl ReadError {
+    /// The `ReadError` a read through a [`RetryingSource`] failed with, if `error` is one.
+    pub fn find(error: &VfsError) -> Option<&ReadError> {
+        match error.kind() {
+            VfsErrorKind::IoError(error) => error.get_ref()?.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReadError> for VfsError {
+    fn from(error: ReadError) -> Self {
+        let kind = match error.last_failure {
+            ReadFailure::TimedOut(_) => std::io::ErrorKind::TimedOut,
+            ReadFailure::Source(_) => std::io::ErrorKind::Other,
+        };
+        VfsErrorKind::IoError(std::io::Error::new(kind, error)).into()
+    }
+}
+
+/// An [`AsyncReadAt`] source which retries failed reads, and ones which take too long, as
+/// its [`RetryPolicy`] says. Every failure is retried, since sources can't say which ones
+/// are permanent.
+#[derive(Debug, Clone)]
+pub struct RetryingSource<T, S> {
+    source: T,
+    policy: RetryPolicy,
+    timer: S,
+}
+
+impl<T, S> RetryingSource<T, S> {
+    pub fn new(source: T, policy: RetryPolicy, timer: S) -> Self {
+        RetryingSource { source, policy, timer }
+    }
+
+    pub fn source(&self) -> &T {
+        &self.source
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+}
+
+#[async_trait]
+impl<T, S> AsyncReadAt for RetryingSource<T, S>
+where
+    T: AsyncReadAt + Send + Sync,
+    S: Timer + Send + Sync,
+{
+    async fn read_at(&self, file_range: Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
+        let attempts = self.policy.attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            // Scoped so the data type, which needn't be `Send`, isn't held across the backoff
+            let failure = {
+                let read = self.source.read_at(file_range.clone());
+                let result = match self.policy.timeout {
+                    Some(timeout) => match select(read, self.timer.sleep(timeout)).await {
+                        Either::Left((result, _)) => result.map_err(ReadFailure::Source),
+                        Either::Right(_) => Err(ReadFailure::TimedOut(timeout)),
+                    },
+                    None => read.await.map_err(ReadFailure::Source),
+                };
+                match result {
+                    Ok(data) => return Ok(data),
+                    Err(failure) => failure,
+                }
+            };
+
+            if attempt == attempts {
+                return Err(ReadError { range: file_range, attempts, last_failure: failure }.into());
+            }
+            debug!("read of {file_range:#X?} failed ({failure}), retrying");
+            self.timer.sleep(self.policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::wrappers::faulty_source::FaultySource;
+    use crate::wrappers::faulty_source::PollTimer;
+    use crate::wrappers::faulty_source::sample_pak;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(15),
+            timeout: Some(Duration::from_millis(50)),
+        }
+    }
+
+    fn read(source: FaultySource, timer: &PollTimer) -> Result<Vec<u8>, VfsError> {
+        let retrying = RetryingSource::new(source, policy(), timer.clone());
+        block_on(async { Ok(retrying.read_at(1..4).await?.as_ref().to_vec()) })
+    }
+
+    #[test]
+    fn retries_failed_and_stalled_reads() {
+        let data = b"abcdef".to_vec();
+        let timer = PollTimer::default();
+        let source =
+            FaultySource::new(data.clone()).latency(5).failing_reads([0]).stalled_reads([1]);
+        assert_eq!(read(source.clone(), &timer).unwrap(), b"bcd");
+        assert_eq!(source.reads(), 3);
+        let ms = Duration::from_millis;
+        // Each attempt is raced against the timeout, and the retries back off
+        assert_eq!(timer.sleeps(), [ms(50), ms(10), ms(50), ms(15), ms(50)]);
+    }
+
+    #[test]
+    fn reports_the_last_failure() {
+        let data = b"abcdef".to_vec();
+        let stalled = FaultySource::new(data.clone()).stalled_reads(0..3);
+        let error = read(stalled.clone(), &PollTimer::default()).unwrap_err();
+        let read_error = ReadError::find(&error).expect("not a ReadError");
+        assert_eq!(read_error.range, 1..4);
+        assert_eq!(read_error.attempts, 3);
+        assert!(matches!(read_error.last_failure, ReadFailure::TimedOut(_)));
+        assert_eq!(stalled.reads(), 3);
+
+        let failing = FaultySource::new(data).failing_reads(0..3);
+        let error = read(failing, &PollTimer::default()).unwrap_err();
+        let read_error = ReadError::find(&error).expect("not a ReadError");
+        assert!(matches!(read_error.last_failure, ReadFailure::Source(_)));
+    }
+
+    #[test]
+    fn pak_loads_survive_failed_reads() {
+        let source = FaultySource::new(sample_pak()).max_read_len(100).failing_reads([0, 2, 3]);
+        let retrying = RetryingSource::new(source, policy(), PollTimer::default());
+        let wrapper =
+            block_on(crate::wrappers::async_reader::parse_pak_file("test.pak".into(), retrying))
+                .expect("retries didn't recover the failed reads");
+        assert!(wrapper.as_ref().file_chunk().is_some());
+    }
+}
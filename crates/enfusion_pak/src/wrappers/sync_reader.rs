@@ -8,12 +8,14 @@ use crate::PakFile;
 use crate::PakParser;
 use crate::ParserStateMachine;
 use crate::Stream;
+use crate::error::ParserDiagnostic;
 use crate::pak_vfs::Prime;
 use crate::pak_vfs::ReadAt;
 use crate::winnow::stream::Offset;
 use crate::winnow::stream::Stream as _;
 use log::debug;
 use vfs::VfsError;
+use vfs::VfsErrorKind;
 
 /// A wrapper around a PakFile and its data source which caches reads
 #[allow(unused)]
@@ -126,7 +128,8 @@ where
         // Populate the buffer with the first 16k
         //
         // TODO: fix this so we only load the minimum amount of data
-        let read_range = parser.bytes_parsed()..(parser.bytes_parsed() + buffer.capacity());
+        let read_start = parser.bytes_parsed();
+        let read_range = read_start..(read_start + buffer.capacity());
         let read_handle = file_handle.clone();
         let data = read_handle.read_at(read_range)?;
 
@@ -168,7 +171,13 @@ where
                 unreachable!("This should never occur");
             }
             Err(e) => {
-                panic!("error reading pak file: {e:?}")
+                let pos = input.checkpoint().offset_from(&start);
+                let diagnostic =
+                    ParserDiagnostic::new(e).with_location(buffer.data(), read_start, pos);
+                return Err(VfsError::from(VfsErrorKind::Other(format!(
+                    "error reading pak file {}: {diagnostic}",
+                    path.display()
+                ))));
             }
         }
     }
@@ -0,0 +1,579 @@
+//! Writing a parsed (and possibly edited) [`PakFile`] back out.
+//!
+//! The DATA chunk is laid out from scratch in the order files appear in the FILE chunk, so
+//! every file's offset, the chunk lengths and the FORM size are recomputed from the tree
+//! rather than trusted from the original pak. [`WriteOptions`] can start each file's data
+//! on an aligned offset, which lets readers map large files without copying them.
+
+use std::borrow::Cow;
+use std::io::Write;
+
+use crate::Chunk;
+use crate::FileEntry;
+use crate::FileEntryMeta;
+use crate::PakFile;
+use crate::error::PakError;
+use crate::version::PakVersion;
+
+/// "FORM", the FORM size, and "PAC1".
+const FORM_HEADER_LEN: usize = 12;
+/// A chunk's tag and big-endian length.
+const CHUNK_HEADER_LEN: usize = 8;
+/// Length of the HEAD chunk's body.
+const HEAD_LEN: usize = 0x1c;
+/// The compression level paks record for zlib-compressed files.
+const ZLIB_LEVEL: u8 = 6;
+
+/// How [`PakFile::write_with`] lays out file data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Each aligned file's data starts at an offset in the pak which is a multiple of this.
+    /// Must be a power of two. 1 writes files back to back.
+    pub alignment: u32,
+    /// Only files storing at least this many bytes are aligned. Smaller files are written
+    /// straight after the previous one, so they don't each cost up to `alignment` bytes.
+    pub min_aligned_len: u32,
+    /// The byte the gaps before aligned files are filled with.
+    pub padding: u8,
+    /// Zlib-compress files added with [`FileEntry::new_file`], keeping the compressed data
+    /// only when it's smaller. Files read from the source pak are written as they were stored.
+    pub compress: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions { alignment: 1, min_aligned_len: 0, padding: 0, compress: false }
+    }
+}
+
+impl PakFile {
+    /// Writes this pak to `out` with files back to back. See [`PakFile::write_with`].
+    pub fn write_to<W: Write>(&self, source: &[u8], out: W) -> Result<(), PakError> {
+        self.write_with(source, out, &WriteOptions::default())
+    }
+
+    /// Writes this pak to `out`, laying out file data as `options` says.
+    ///
+    /// `source` must be the bytes this pak was parsed from (or empty for a pak made with
+    /// [`PakFile::new`]): files read from it are copied over as they were stored
+    /// (compressed or not), as are the HEAD chunk and any chunks the parser doesn't
+    /// understand, which keep their place between the others. Files added with
+    /// [`FileEntry::new_file`] are written uncompressed unless [`WriteOptions::compress`] is
+    /// set. Offsets in this `PakFile` aren't updated, so parse the output to keep working
+    /// with the written pak.
+    pub fn write_with<W: Write>(
+        &self,
+        source: &[u8],
+        mut out: W,
+        options: &WriteOptions,
+    ) -> Result<(), PakError> {
+        if !options.alignment.is_power_of_two() {
+            return Err(PakError::Unwritable("alignment must be a power of two"));
+        }
+
+        let materialized;
+        let root: &FileEntry = match self.file_chunk() {
+            Some(Chunk::File { fs }) => fs,
+            _ => match self.lazy_file_table(source) {
+                Some(table) => {
+                    materialized = table?.root().materialize()?;
+                    &materialized
+                }
+                None => return Err(PakError::MissingFileChunk),
+            },
+        };
+
+        let version = self.version().unwrap_or_default();
+        let head = match find_chunk(source, b"HEAD") {
+            Some(head) => head.to_vec(),
+            None => {
+                let mut head = version.raw().to_le_bytes().to_vec();
+                head.resize(HEAD_LEN, 0);
+                head
+            }
+        };
+
+        let order = chunk_order(self, source)?;
+        // chunk_order puts DATA before FILE, so only HEAD and unknown chunks come before it
+        let data_start = FORM_HEADER_LEN
+            + order
+                .iter()
+                .take_while(|chunk| **chunk != OutputChunk::Data)
+                .map(|chunk| match chunk {
+                    OutputChunk::Unknown(_, body) => CHUNK_HEADER_LEN + body.len(),
+                    _ => CHUNK_HEADER_LEN + head.len(),
+                })
+                .sum::<usize>()
+            + CHUNK_HEADER_LEN;
+        let mut layout = Layout {
+            source,
+            version,
+            options,
+            next_offset: data_start,
+            segments: Vec::new(),
+            table: Vec::new(),
+        };
+        layout.push_entry(root)?;
+
+        let data_len = layout.next_offset - data_start;
+        let body_len = |chunk: &OutputChunk<'_>| match chunk {
+            OutputChunk::Head => head.len(),
+            OutputChunk::Data => data_len,
+            OutputChunk::File => layout.table.len(),
+            OutputChunk::Unknown(_, body) => body.len(),
+        };
+        let total_len = FORM_HEADER_LEN
+            + order.iter().map(|chunk| CHUNK_HEADER_LEN + body_len(chunk)).sum::<usize>();
+        let form_size = to_u32(total_len - CHUNK_HEADER_LEN)?;
+
+        out.write_all(b"FORM")?;
+        out.write_all(&form_size.to_be_bytes())?;
+        out.write_all(b"PAC1")?;
+        for chunk in &order {
+            let tag = match chunk {
+                OutputChunk::Head => b"HEAD",
+                OutputChunk::Data => b"DATA",
+                OutputChunk::File => b"FILE",
+                OutputChunk::Unknown(tag, _) => *tag,
+            };
+            write_chunk_header(&mut out, tag, body_len(chunk))?;
+            match chunk {
+                OutputChunk::Head => out.write_all(&head)?,
+                OutputChunk::Data => {
+                    for segment in &layout.segments {
+                        match segment {
+                            Segment::Data(data) => out.write_all(data)?,
+                            Segment::Padding(len) => out.write_all(&vec![options.padding; *len])?,
+                        }
+                    }
+                }
+                OutputChunk::File => out.write_all(&layout.table)?,
+                OutputChunk::Unknown(_, body) => out.write_all(body)?,
+            }
+        }
+        out.flush()?;
+
+        Ok(())
+    }
+}
+
+/// A chunk [`PakFile::write_with`] writes, in the order they're written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputChunk<'a> {
+    Head,
+    Data,
+    File,
+    /// A chunk the parser didn't understand, copied over as it was.
+    Unknown(&'a [u8; 4], &'a [u8]),
+}
+
+/// The chunks to write for `pak`, in the order it has them. Unknown chunks keep their place
+/// between the others, and a HEAD or DATA chunk the pak is missing (as paks made with
+/// [`PakFile::new`] are) is added where it usually goes.
+fn chunk_order<'a>(pak: &'a PakFile, source: &'a [u8]) -> Result<Vec<OutputChunk<'a>>, PakError> {
+    let mut order = Vec::with_capacity(pak.chunks().len());
+    for chunk in pak.chunks() {
+        let chunk = match chunk {
+            Chunk::Form { .. } => continue,
+            Chunk::Head { .. } => OutputChunk::Head,
+            Chunk::Data { .. } => OutputChunk::Data,
+            Chunk::File { .. } | Chunk::LazyFile { .. } => OutputChunk::File,
+            Chunk::Unknown { tag, data } => {
+                let body = source
+                    .get(data.clone())
+                    .ok_or(PakError::Unwritable("unknown chunk lies outside of the source pak"))?;
+                OutputChunk::Unknown(tag, body)
+            }
+        };
+        order.push(chunk);
+    }
+
+    if !order.contains(&OutputChunk::Head) {
+        order.insert(0, OutputChunk::Head);
+    }
+    let file = order.iter().position(|chunk| *chunk == OutputChunk::File).unwrap_or(order.len());
+    match order.iter().position(|chunk| *chunk == OutputChunk::Data) {
+        Some(data) if data > file => {
+            return Err(PakError::Unwritable("FILE chunk comes before the DATA chunk"));
+        }
+        Some(_) => {}
+        None => order.insert(file, OutputChunk::Data),
+    }
+
+    Ok(order)
+}
+
+/// The DATA and FILE chunks being built for [`PakFile::write_with`].
+struct Layout<'a> {
+    source: &'a [u8],
+    version: PakVersion,
+    options: &'a WriteOptions,
+    /// Absolute offset in the output of the next file's data.
+    next_offset: usize,
+    /// The DATA chunk's body, in order.
+    segments: Vec<Segment<'a>>,
+    /// The FILE chunk's body.
+    table: Vec<u8>,
+}
+
+enum Segment<'a> {
+    Data(Cow<'a, [u8]>),
+    /// This many padding bytes.
+    Padding(usize),
+}
+
+impl<'a> Layout<'a> {
+    /// Appends `entry` and everything under it to the FILE chunk, depth first.
+    fn push_entry(&mut self, entry: &'a FileEntry) -> Result<(), PakError> {
+        let name = entry.name().as_bytes();
+        let name_len =
+            u8::try_from(name.len()).map_err(|_| PakError::Unwritable("entry name is too long"))?;
+
+        match entry.meta() {
+            FileEntryMeta::Folder { children } => {
+                self.table.push(0);
+                self.table.push(name_len);
+                self.table.extend_from_slice(name);
+                self.table.extend_from_slice(&to_u32(children.len())?.to_le_bytes());
+
+                for child in children {
+                    self.push_entry(child)?;
+                }
+            }
+            FileEntryMeta::File {
+                offset,
+                compressed_len,
+                decompressed_len,
+                unk,
+                unk2,
+                compressed,
+                compression_level,
+                timestamp,
+            } => {
+                let staged = entry.staged_contents();
+                let (data, decompressed_len, compressed, compression_level) = match staged {
+                    Some(contents) => {
+                        let len = to_u32(contents.len())?;
+                        match self.compress(contents)? {
+                            Some(packed) => (Cow::Owned(packed), len, 1, ZLIB_LEVEL),
+                            None => (Cow::Borrowed(contents), len, *compressed, *compression_level),
+                        }
+                    }
+                    None => {
+                        let start = *offset as usize;
+                        let end = start + *compressed_len as usize;
+                        let data = self.source.get(start..end).ok_or(PakError::Unwritable(
+                            "file data lies outside of the source pak",
+                        ))?;
+                        (Cow::Borrowed(data), *decompressed_len, *compressed, *compression_level)
+                    }
+                };
+
+                let alignment = self.options.alignment as usize;
+                if data.len() >= self.options.min_aligned_len as usize {
+                    let padding = self.next_offset.next_multiple_of(alignment) - self.next_offset;
+                    if padding > 0 {
+                        self.segments.push(Segment::Padding(padding));
+                        self.next_offset += padding;
+                    }
+                }
+
+                self.table.push(1);
+                self.table.push(name_len);
+                self.table.extend_from_slice(name);
+                match self.version {
+                    PakVersion::V1_3 => {
+                        self.table.extend_from_slice(&to_u32(self.next_offset)?.to_le_bytes());
+                        self.table.extend_from_slice(&to_u32(data.len())?.to_le_bytes());
+                        self.table.extend_from_slice(&decompressed_len.to_le_bytes());
+                        self.table.extend_from_slice(&unk.to_le_bytes());
+                        self.table.extend_from_slice(&unk2.to_le_bytes());
+                        self.table.push(compressed);
+                        self.table.push(compression_level);
+                        self.table.extend_from_slice(&timestamp.to_le_bytes());
+                    }
+                }
+
+                self.next_offset += data.len();
+                self.segments.push(Segment::Data(data));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `contents` zlib-compressed, if the options ask for that and it makes them smaller.
+    fn compress(&self, contents: &[u8]) -> Result<Option<Vec<u8>>, PakError> {
+        if !self.options.compress {
+            return Ok(None);
+        }
+
+        let mut encoder = flate2::write::ZlibEncoder::new(
+            Vec::with_capacity(contents.len() / 2),
+            flate2::Compression::new(ZLIB_LEVEL.into()),
+        );
+        encoder.write_all(contents)?;
+        let packed = encoder.finish()?;
+        Ok((packed.len() < contents.len()).then_some(packed))
+    }
+}
+
+/// Returns the body of the first top-level chunk tagged `tag` in a pak.
+fn find_chunk<'a>(source: &'a [u8], tag: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut rest = source.get(FORM_HEADER_LEN..)?;
+    while rest.len() >= CHUNK_HEADER_LEN {
+        let (header, body) = rest.split_at(CHUNK_HEADER_LEN);
+        let len = u32::from_be_bytes(header[4..].try_into().unwrap()) as usize;
+        let body = body.get(..len)?;
+        if &header[..4] == tag {
+            return Some(body);
+        }
+        rest = &rest[CHUNK_HEADER_LEN + len..];
+    }
+
+    None
+}
+
+fn write_chunk_header(out: &mut impl Write, tag: &[u8; 4], len: usize) -> Result<(), PakError> {
+    out.write_all(tag)?;
+    out.write_all(&to_u32(len)?.to_be_bytes())?;
+    Ok(())
+}
+
+/// Paks address everything with 32-bit offsets and lengths.
+fn to_u32(value: usize) -> Result<u32, PakError> {
+    u32::try_from(value).map_err(|_| PakError::Unwritable("pak would be larger than 4 GiB"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use jiff::civil::date;
+
+    use super::*;
+    use crate::error::EditError;
+    use crate::test_pak::modified;
+    use crate::test_pak::pak_bytes;
+    use crate::test_pak::pak_with;
+    use crate::test_pak::push_raw_file;
+    use crate::test_pak::push_raw_folder;
+
+    /// root/{scripts/{a.c}, b.c}
+    fn build_pak() -> Vec<u8> {
+        build_pak_with(&[], &[])
+    }
+
+    /// [`build_pak`] with the raw chunks in `before_data` between the HEAD and DATA chunks,
+    /// and `after_file` after the FILE chunk.
+    fn build_pak_with(before_data: &[u8], after_file: &[u8]) -> Vec<u8> {
+        let data_start =
+            (FORM_HEADER_LEN + CHUNK_HEADER_LEN + HEAD_LEN + before_data.len() + CHUNK_HEADER_LEN)
+                as u32;
+        let data = b"aaaabbbbbb";
+
+        let mut table = Vec::new();
+        push_raw_folder(&mut table, "", 2);
+        push_raw_folder(&mut table, "scripts", 1);
+        push_raw_file(&mut table, "a.c", data_start, 4);
+        push_raw_file(&mut table, "b.c", data_start + 4, 6);
+
+        let mut pak = Vec::new();
+        pak.extend_from_slice(b"FORM");
+        pak.extend_from_slice(&0u32.to_be_bytes());
+        pak.extend_from_slice(b"PAC1");
+        pak.extend_from_slice(b"HEAD");
+        pak.extend_from_slice(&0x1Cu32.to_be_bytes());
+        pak.extend_from_slice(&0x10003u32.to_le_bytes());
+        pak.resize(pak.len() + 0x18, 0xAB);
+        pak.extend_from_slice(before_data);
+        pak.extend_from_slice(b"DATA");
+        pak.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        assert_eq!(pak.len(), data_start as usize);
+        pak.extend_from_slice(data);
+        pak.extend_from_slice(b"FILE");
+        pak.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        pak.extend_from_slice(&table);
+        pak.extend_from_slice(after_file);
+
+        let form_size = (pak.len() - 8) as u32;
+        pak[4..8].copy_from_slice(&form_size.to_be_bytes());
+        pak
+    }
+
+    fn root(pak: &PakFile) -> &FileEntry {
+        let Some(Chunk::File { fs }) = pak.file_chunk() else { panic!("no FILE chunk") };
+        fs
+    }
+
+    fn file_data<'a>(pak_data: &'a [u8], pak: &PakFile, path: &str) -> &'a [u8] {
+        let entry = root(pak).get(path).unwrap_or_else(|| panic!("{path} is missing"));
+        let FileEntryMeta::File { offset, compressed_len, .. } = entry.meta() else {
+            panic!("{path} is not a file");
+        };
+        &pak_data[*offset as usize..][..*compressed_len as usize]
+    }
+
+    #[test]
+    fn unchanged_pak_is_written_byte_for_byte() {
+        let source = build_pak();
+        let pak = PakFile::parse(&source).expect("failed to parse pak");
+
+        let mut written = Vec::new();
+        pak.write_to(&source, &mut written).expect("failed to write pak");
+        assert_eq!(written, source);
+
+        let lazy = PakFile::parse_lazy(&source).unwrap();
+        let mut written = Vec::new();
+        lazy.write_to(&source, &mut written).unwrap();
+        assert_eq!(written, source);
+    }
+
+    #[test]
+    fn edits_are_written_with_new_offsets() {
+        let source = build_pak();
+        let mut pak = PakFile::parse(&source).unwrap();
+        let Some(Chunk::File { fs }) = pak.file_chunk_mut() else { panic!("no FILE chunk") };
+        let tree = crate::RcFileEntry::make_mut(fs);
+
+        tree.remove("scripts/a.c").unwrap();
+        tree.insert("scripts/Game", FileEntry::new_file("new.c", &b"new file"[..], modified()))
+            .unwrap();
+        tree.insert("", FileEntry::new_file("b.c", &b"replaced"[..], modified())).unwrap();
+        tree.rename("scripts/Game", "Core").unwrap();
+
+        let mut written = Vec::new();
+        pak.write_to(&source, &mut written).unwrap();
+        let reparsed = PakFile::parse(&written).expect("failed to parse written pak");
+
+        assert_eq!(&written[4..8], &((written.len() - 8) as u32).to_be_bytes());
+        assert!(root(&reparsed).get("scripts/a.c").is_none());
+        assert_eq!(file_data(&written, &reparsed, "scripts/Core/new.c"), b"new file");
+        assert_eq!(file_data(&written, &reparsed, "b.c"), b"replaced");
+
+        let new_file = root(&reparsed).get("scripts/Core/new.c").unwrap();
+        assert_eq!(new_file.meta().parsed_timestamp(), Some(modified()));
+        // The HEAD chunk's unknown bytes are carried over
+        assert_eq!(find_chunk(&written, b"HEAD"), find_chunk(&source, b"HEAD"));
+    }
+
+    #[test]
+    fn new_pak_is_written_from_scratch() {
+        let written = pak_bytes(&[("scripts/a.c", "class A {}")]);
+        let reparsed = PakFile::parse(&written).expect("failed to parse written pak");
+
+        assert_eq!(reparsed.version(), Some(PakVersion::LATEST));
+        assert_eq!(file_data(&written, &reparsed, "scripts/a.c"), b"class A {}");
+    }
+
+    #[test]
+    fn large_files_are_aligned() {
+        let source = build_pak();
+        let pak = PakFile::parse(&source).unwrap();
+        let options = WriteOptions {
+            alignment: 0x10,
+            min_aligned_len: 5,
+            padding: 0xCC,
+            ..WriteOptions::default()
+        };
+
+        let mut written = Vec::new();
+        pak.write_with(&source, &mut written, &options).unwrap();
+        let reparsed = PakFile::parse(&written).expect("failed to parse aligned pak");
+
+        // a.c is too small to be aligned, so it follows the DATA header directly
+        let offset = |path| match root(&reparsed).get(path).unwrap().meta() {
+            FileEntryMeta::File { offset, .. } => *offset as usize,
+            _ => panic!("{path} is not a file"),
+        };
+        let data_start = FORM_HEADER_LEN + CHUNK_HEADER_LEN + HEAD_LEN + CHUNK_HEADER_LEN;
+        assert_eq!(offset("scripts/a.c"), data_start);
+        assert_eq!(offset("b.c"), (data_start + 4).next_multiple_of(0x10));
+        assert!(written[data_start + 4..offset("b.c")].iter().all(|b| *b == 0xCC));
+        assert_eq!(file_data(&written, &reparsed, "scripts/a.c"), b"aaaa");
+        assert_eq!(file_data(&written, &reparsed, "b.c"), b"bbbbbb");
+
+        let options = WriteOptions { alignment: 3, ..WriteOptions::default() };
+        assert!(pak.write_with(&source, &mut Vec::new(), &options).is_err());
+    }
+
+    #[test]
+    fn new_files_are_compressed_when_asked() {
+        let contents = "class A {}\n".repeat(100);
+        let pak = pak_with(&[("scripts/a.c", contents.as_str()), ("b.c", "tiny")]);
+        let options = WriteOptions { compress: true, ..WriteOptions::default() };
+
+        let mut written = Vec::new();
+        pak.write_with(&[], &mut written, &options).unwrap();
+        let reparsed = PakFile::parse(&written).expect("failed to parse compressed pak");
+
+        let meta = |path| match root(&reparsed).get(path).unwrap().meta() {
+            FileEntryMeta::File { compressed, compressed_len, decompressed_len, .. } => {
+                (*compressed, *compressed_len as usize, *decompressed_len as usize)
+            }
+            _ => panic!("{path} is not a file"),
+        };
+        let (compressed, compressed_len, decompressed_len) = meta("scripts/a.c");
+        assert_eq!((compressed, decompressed_len), (1, contents.len()));
+        assert!(compressed_len < contents.len());
+        let mut decompressed = String::new();
+        flate2::read::ZlibDecoder::new(file_data(&written, &reparsed, "scripts/a.c"))
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, contents);
+
+        // Compressing wouldn't make it any smaller
+        assert_eq!(meta("b.c"), (0, 4, 4));
+    }
+
+    #[test]
+    fn unknown_chunks_keep_their_place() {
+        let chunk = |tag: &[u8; 4], body: &[u8]| {
+            [tag.as_slice(), &(body.len() as u32).to_be_bytes(), body].concat()
+        };
+        let source = build_pak_with(&chunk(b"JUNK", b"before"), &chunk(b"TAIL", b"after"));
+        let pak = PakFile::parse(&source).unwrap();
+
+        let mut written = Vec::new();
+        pak.write_to(&source, &mut written).unwrap();
+        assert_eq!(written, source);
+
+        let options = WriteOptions { alignment: 0x10, ..WriteOptions::default() };
+        let mut aligned = Vec::new();
+        pak.write_with(&source, &mut aligned, &options).unwrap();
+        let reparsed = PakFile::parse(&aligned).expect("failed to parse aligned pak");
+
+        let tags: Vec<&[u8; 4]> = reparsed
+            .chunks()
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Form { .. } => b"FORM",
+                Chunk::Head { .. } => b"HEAD",
+                Chunk::Data { .. } => b"DATA",
+                Chunk::File { .. } | Chunk::LazyFile { .. } => b"FILE",
+                Chunk::Unknown { tag, .. } => tag,
+            })
+            .collect();
+        assert_eq!(tags, [b"FORM", b"HEAD", b"JUNK", b"DATA", b"FILE", b"TAIL"]);
+        assert_eq!(find_chunk(&aligned, b"JUNK"), Some(&b"before"[..]));
+        assert_eq!(find_chunk(&aligned, b"TAIL"), Some(&b"after"[..]));
+        assert_eq!(file_data(&aligned, &reparsed, "scripts/a.c"), b"aaaa");
+        assert_eq!(file_data(&aligned, &reparsed, "b.c"), b"bbbbbb");
+    }
+
+    #[test]
+    fn invalid_edits_are_rejected() {
+        let source = build_pak();
+        let pak = PakFile::parse(&source).unwrap();
+        let mut tree = root(&pak).clone();
+
+        let err = tree.insert("b.c", FileEntry::new_folder("x")).unwrap_err();
+        assert_eq!(err, EditError::NotAFolder("b.c".to_string()));
+        let err =
+            tree.insert("", FileEntry::new_file("scripts", &b""[..], modified())).unwrap_err();
+        assert_eq!(err, EditError::AlreadyExists("scripts".to_string()));
+        assert_eq!(tree.rename("b.c", "scripts"), Err(EditError::AlreadyExists("scripts".into())));
+        assert_eq!(tree.rename("b.c", "a/b.c"), Err(EditError::InvalidName("a/b.c".into())));
+        assert!(matches!(tree.remove("missing.c"), Err(EditError::NotFound(_))));
+    }
+}
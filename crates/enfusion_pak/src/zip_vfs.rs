@@ -0,0 +1,494 @@
+//! Read-only VFS over `.zip` archives so loose mod data can be layered alongside paks.
+//!
+//! Only the central directory is parsed up front. Entry data is read through the same
+//! [`ReadAt`]/[`AsyncReadAt`] handles used for paks when a file is opened. Stored and
+//! deflated entries are supported; ZIP64, encrypted and multi-disk archives are not.
+
+use std::fmt::Debug;
+use std::io::Cursor;
+use std::ops::Range;
+use std::sync::Arc;
+
+use fskit::Metadata;
+use fskit::VfsTree;
+use fskit::VfsTreeBuilder;
+use vfs::VfsError;
+use vfs::VfsMetadata;
+use vfs::error::VfsErrorKind;
+use winnow::ModalResult as WResult;
+use winnow::Parser;
+use winnow::binary::le_u16;
+use winnow::binary::le_u32;
+use winnow::error::ContextError;
+use winnow::error::ErrMode;
+use winnow::token::literal;
+use winnow::token::take;
+
+use crate::error::PakError;
+use crate::pak_vfs::ReadAt;
+
+const LOCAL_HEADER_SIGNATURE: &[u8] = b"PK\x03\x04";
+const CENTRAL_HEADER_SIGNATURE: &[u8] = b"PK\x01\x02";
+const END_OF_DIRECTORY_SIGNATURE: &[u8] = b"PK\x05\x06";
+
+const LOCAL_HEADER_LEN: usize = 30;
+const END_OF_DIRECTORY_LEN: usize = 22;
+
+/// How many trailing bytes may hold the end of central directory record: the record
+/// itself plus a maximum length comment.
+pub const TAIL_LEN: usize = END_OF_DIRECTORY_LEN + u16::MAX as usize;
+
+/// How an entry's data is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZipMethod {
+    Stored,
+    Deflated,
+    /// Any other method, which can be listed but not read.
+    Unsupported(u16),
+}
+
+/// File metadata stored in the VFS tree for each zip entry.
+#[derive(Debug, Clone)]
+pub struct ZipFileMeta {
+    pub local_header_offset: u32,
+    pub compressed_len: u32,
+    pub decompressed_len: u32,
+    pub method: ZipMethod,
+    pub crc32: u32,
+    pub encrypted: bool,
+}
+
+impl Metadata for ZipFileMeta {
+    fn len(&self) -> u64 {
+        self.decompressed_len as u64
+    }
+}
+
+/// An entry in the central directory.
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    /// `/`-separated path. Directories end with a `/`.
+    pub name: String,
+    pub meta: ZipFileMeta,
+}
+
+/// Finds the central directory from the last bytes of an archive, where `tail` starts at
+/// offset `tail_offset`. Returns the directory's byte range and entry count.
+pub fn find_central_directory(
+    tail: &[u8],
+    tail_offset: usize,
+) -> Result<(Range<usize>, usize), PakError> {
+    let record_start = tail
+        .windows(END_OF_DIRECTORY_SIGNATURE.len())
+        .rposition(|window| window == END_OF_DIRECTORY_SIGNATURE)
+        .ok_or(PakError::InvalidZip("end of central directory not found"))?;
+
+    let mut input = &tail[record_start + END_OF_DIRECTORY_SIGNATURE.len()..];
+    let (disk, directory_disk, _disk_entries, entries, len, offset) =
+        (le_u16, le_u16, le_u16, le_u16, le_u32, le_u32).parse_next(&mut input).map_err(
+            |_: ErrMode<ContextError>| PakError::InvalidZip("truncated end of central directory"),
+        )?;
+
+    if disk != 0 || directory_disk != 0 {
+        return Err(PakError::InvalidZip("multi-disk archives aren't supported"));
+    }
+    if entries == u16::MAX || len == u32::MAX || offset == u32::MAX {
+        return Err(PakError::InvalidZip("ZIP64 archives aren't supported"));
+    }
+
+    let range = offset as usize..offset as usize + len as usize;
+    if range.end > tail_offset + record_start {
+        return Err(PakError::InvalidZip("central directory overlaps its end record"));
+    }
+
+    Ok((range, entries as usize))
+}
+
+/// Parses `count` central directory entries.
+pub fn parse_central_directory(data: &[u8], count: usize) -> Result<Vec<ZipEntry>, PakError> {
+    let mut input = data;
+    (0..count)
+        .map(|_| parse_central_entry(&mut input))
+        .collect::<WResult<Vec<_>>>()
+        .map_err(|_| PakError::InvalidZip("malformed central directory entry"))
+}
+
+fn parse_central_entry(input: &mut &[u8]) -> WResult<ZipEntry> {
+    literal(CENTRAL_HEADER_SIGNATURE).parse_next(input)?;
+    let (_version_made_by, _version_needed, flags, method) =
+        (le_u16, le_u16, le_u16, le_u16).parse_next(input)?;
+    let (_time, _date, crc32, compressed_len, decompressed_len) =
+        (le_u16, le_u16, le_u32, le_u32, le_u32).parse_next(input)?;
+    let (name_len, extra_len, comment_len) = (le_u16, le_u16, le_u16).parse_next(input)?;
+    let (_disk, _internal_attributes, _external_attributes, local_header_offset) =
+        (le_u16, le_u16, le_u32, le_u32).parse_next(input)?;
+    let name = take(name_len).parse_next(input)?;
+    take(extra_len as usize + comment_len as usize).parse_next(input)?;
+
+    let method = match method {
+        0 => ZipMethod::Stored,
+        8 => ZipMethod::Deflated,
+        other => ZipMethod::Unsupported(other),
+    };
+
+    Ok(ZipEntry {
+        // Names are UTF-8 when flag bit 11 is set and CP437 otherwise, which agrees with
+        // UTF-8 for the ASCII names seen in practice
+        name: String::from_utf8_lossy(name).replace('\\', "/"),
+        meta: ZipFileMeta {
+            local_header_offset,
+            compressed_len,
+            decompressed_len,
+            method,
+            crc32,
+            encrypted: flags & 1 != 0,
+        },
+    })
+}
+
+/// Build a [`VfsTree`] from the central directory.
+fn build_tree(entries: &[ZipEntry]) -> VfsTree<ZipFileMeta> {
+    let mut builder = VfsTreeBuilder::new();
+
+    for entry in entries {
+        let path = entry.name.trim_start_matches('/');
+        if path.split('/').any(|component| component == "..") {
+            log::warn!("skipping zip entry with a relative path: {path}");
+            continue;
+        }
+
+        if let Some(dir) = path.strip_suffix('/') {
+            if !dir.is_empty() {
+                builder = builder.insert_dir(dir, None);
+            }
+        } else {
+            builder = builder.insert(path, entry.meta.clone());
+        }
+    }
+
+    builder.build()
+}
+
+/// Range of the local header, whose variable length fields must be read to find where
+/// an entry's data starts.
+fn local_header_range(meta: &ZipFileMeta) -> Range<usize> {
+    let start = meta.local_header_offset as usize;
+    start..start + LOCAL_HEADER_LEN
+}
+
+/// Range of an entry's data given its local header.
+fn data_range(meta: &ZipFileMeta, local_header: &[u8]) -> Result<Range<usize>, VfsError> {
+    let mut input = local_header;
+    let (_, _, name_len, extra_len) = (
+        literal(LOCAL_HEADER_SIGNATURE),
+        take(LOCAL_HEADER_LEN - LOCAL_HEADER_SIGNATURE.len() - 4),
+        le_u16,
+        le_u16,
+    )
+        .parse_next(&mut input)
+        .map_err(|_: ErrMode<ContextError>| {
+            VfsError::from(VfsErrorKind::Other("malformed zip local header".into()))
+        })?;
+
+    let start = meta.local_header_offset as usize
+        + LOCAL_HEADER_LEN
+        + name_len as usize
+        + extra_len as usize;
+    Ok(start..start + meta.compressed_len as usize)
+}
+
+/// Decompresses an entry's data and verifies its checksum.
+fn decode(meta: &ZipFileMeta, compressed: &[u8]) -> vfs::VfsResult<Vec<u8>> {
+    let error = |reason: &str| VfsError::from(VfsErrorKind::Other(reason.to_string()));
+    if meta.encrypted {
+        return Err(error("encrypted zip entries aren't supported"));
+    }
+
+    let data = match meta.method {
+        ZipMethod::Stored => compressed.to_vec(),
+        ZipMethod::Deflated => {
+            let mut data = Vec::with_capacity(meta.decompressed_len as usize);
+            let mut decoder = flate2::read::DeflateDecoder::new(compressed);
+            std::io::copy(&mut decoder, &mut data)
+                .map_err(|err| VfsError::from(VfsErrorKind::IoError(err)))?;
+            data
+        }
+        ZipMethod::Unsupported(method) => {
+            return Err(error(&format!("zip compression method {method} isn't supported")));
+        }
+    };
+
+    let mut crc = flate2::Crc::new();
+    crc.update(&data);
+    if crc.sum() != meta.crc32 {
+        return Err(error("zip entry checksum mismatch"));
+    }
+
+    Ok(data)
+}
+
+/// VFS implementation for reading a `.zip` archive.
+///
+/// Cloning is cheap (Arc refcount bump).
+#[derive(Debug, Clone)]
+pub struct ZipVfs<T> {
+    source: T,
+    tree: Arc<VfsTree<ZipFileMeta>>,
+}
+
+impl<T> ZipVfs<T> {
+    /// Builds a VFS from an already parsed central directory.
+    pub fn from_entries(source: T, entries: &[ZipEntry]) -> Self {
+        Self { source, tree: Arc::new(build_tree(entries)) }
+    }
+
+    pub fn tree(&self) -> &VfsTree<ZipFileMeta> {
+        &self.tree
+    }
+}
+
+impl<T> ZipVfs<T>
+where
+    T: ReadAt,
+{
+    /// Reads the central directory of the `len` byte archive in `source`.
+    pub fn new(source: T, len: usize) -> Result<Self, PakError> {
+        let io_error = |e: VfsError| PakError::IoError(std::io::Error::other(e.to_string()));
+
+        let tail_offset = len.saturating_sub(TAIL_LEN);
+        let (directory_range, count) = {
+            let tail = source.read_at(tail_offset..len).map_err(io_error)?;
+            find_central_directory(tail.as_ref(), tail_offset)?
+        };
+        let entries = {
+            let directory = source.read_at(directory_range).map_err(io_error)?;
+            parse_central_directory(directory.as_ref(), count)?
+        };
+
+        Ok(Self::from_entries(source, &entries))
+    }
+}
+
+impl<T> vfs::FileSystem for ZipVfs<T>
+where
+    T: ReadAt + Sync + Send + Debug + 'static,
+{
+    fn read_dir(&self, path: &str) -> vfs::VfsResult<Box<dyn Iterator<Item = String> + Send>> {
+        self.tree.vfs_read_dir(path)
+    }
+
+    fn open_file(&self, path: &str) -> vfs::VfsResult<Box<dyn vfs::SeekAndRead + Send>> {
+        let entry = self.tree.vfs_lookup(path)?;
+        let fskit::VfsEntry::File(meta) = entry else {
+            return Err(VfsError::from(VfsErrorKind::Other("not a file".into())));
+        };
+
+        let range = {
+            let local_header = self.source.read_at(local_header_range(meta))?;
+            data_range(meta, local_header.as_ref())?
+        };
+        let compressed = self.source.read_at(range)?;
+
+        Ok(Box::new(Cursor::new(decode(meta, compressed.as_ref())?)))
+    }
+
+    fn metadata(&self, path: &str) -> vfs::VfsResult<VfsMetadata> {
+        self.tree.vfs_metadata(path)
+    }
+
+    fn exists(&self, path: &str) -> vfs::VfsResult<bool> {
+        self.tree.vfs_exists(path)
+    }
+
+    fskit::read_only_fs_stubs!();
+}
+
+#[cfg(feature = "async_vfs")]
+mod async_impl {
+    use async_trait::async_trait;
+    use futures::io::AsyncWrite;
+    use futures::io::Cursor;
+    use futures::stream::Stream;
+    use vfs::VfsResult;
+    use vfs::async_vfs::AsyncFileSystem;
+    use vfs::async_vfs::SeekAndRead;
+
+    use super::*;
+    use crate::async_pak_vfs::AsyncReadAt;
+
+    impl<T> ZipVfs<T>
+    where
+        T: AsyncReadAt,
+    {
+        /// Reads the central directory of the `len` byte archive in `source`.
+        pub async fn new_async(source: T, len: usize) -> Result<Self, PakError> {
+            let io_error = |e: VfsError| PakError::IoError(std::io::Error::other(e.to_string()));
+
+            let tail_offset = len.saturating_sub(TAIL_LEN);
+            let (directory_range, count) = {
+                let tail = source.read_at(tail_offset..len).await.map_err(io_error)?;
+                find_central_directory(tail.as_ref(), tail_offset)?
+            };
+            let entries = {
+                let directory = source.read_at(directory_range).await.map_err(io_error)?;
+                parse_central_directory(directory.as_ref(), count)?
+            };
+
+            Ok(Self::from_entries(source, &entries))
+        }
+    }
+
+    #[async_trait]
+    impl<T> AsyncFileSystem for ZipVfs<T>
+    where
+        T: AsyncReadAt + Sync + Send + Debug + 'static,
+    {
+        async fn read_dir(
+            &self,
+            path: &str,
+        ) -> VfsResult<Box<dyn Unpin + Stream<Item = String> + Send>> {
+            self.tree().async_vfs_read_dir(path)
+        }
+
+        async fn create_dir(&self, _path: &str) -> VfsResult<()> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+
+        async fn open_file(&self, path: &str) -> VfsResult<Box<dyn SeekAndRead + Send + Unpin>> {
+            let entry = self.tree().vfs_lookup(path)?;
+            let fskit::VfsEntry::File(meta) = entry else {
+                return Err(VfsError::from(VfsErrorKind::Other("not a file".into())));
+            };
+
+            let range = {
+                let local_header = self.source.read_at(local_header_range(meta)).await?;
+                data_range(meta, local_header.as_ref())?
+            };
+            let compressed = self.source.read_at(range).await?;
+
+            Ok(Box::new(Cursor::new(decode(meta, compressed.as_ref())?)))
+        }
+
+        async fn create_file(&self, _path: &str) -> VfsResult<Box<dyn AsyncWrite + Send + Unpin>> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+
+        async fn append_file(&self, _path: &str) -> VfsResult<Box<dyn AsyncWrite + Send + Unpin>> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+
+        async fn metadata(&self, path: &str) -> VfsResult<VfsMetadata> {
+            self.tree().vfs_metadata(path)
+        }
+
+        async fn exists(&self, path: &str) -> VfsResult<bool> {
+            self.tree().vfs_exists(path)
+        }
+
+        async fn remove_file(&self, _path: &str) -> VfsResult<()> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+
+        async fn remove_dir(&self, _path: &str) -> VfsResult<()> {
+            Err(VfsErrorKind::NotSupported.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::io::Write;
+
+    use vfs::FileSystem;
+
+    use super::*;
+
+    struct Bytes(Vec<u8>);
+
+    impl Debug for Bytes {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Bytes({})", self.0.len())
+        }
+    }
+
+    impl ReadAt for Bytes {
+        fn read_at(&self, file_range: Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
+            Ok(&self.0[file_range])
+        }
+    }
+
+    /// Writes a zip with one stored and one deflated entry.
+    fn build_zip() -> Vec<u8> {
+        let files: [(&str, &[u8], u16); 2] =
+            [("README.txt", b"hello", 0), ("scripts/Game/a.c", b"class A {}\nclass B {}\n", 8)];
+
+        let mut out = Vec::new();
+        let mut directory = Vec::new();
+        for (name, contents, method) in files {
+            let data = if method == 8 {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(contents).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                contents.to_vec()
+            };
+            let mut crc = flate2::Crc::new();
+            crc.update(contents);
+
+            let offset = out.len() as u32;
+            let mut fields = Vec::new();
+            fields.extend(method.to_le_bytes());
+            fields.extend([0; 4]); // time, date
+            fields.extend(crc.sum().to_le_bytes());
+            fields.extend((data.len() as u32).to_le_bytes());
+            fields.extend((contents.len() as u32).to_le_bytes());
+            fields.extend((name.len() as u16).to_le_bytes());
+            fields.extend([0; 2]); // extra
+
+            out.extend(LOCAL_HEADER_SIGNATURE);
+            out.extend([20, 0, 0, 0]); // version, flags
+            out.extend(&fields);
+            out.extend(name.as_bytes());
+            out.extend(&data);
+
+            directory.extend(CENTRAL_HEADER_SIGNATURE);
+            directory.extend([20, 0, 20, 0, 0, 0]); // versions, flags
+            directory.extend(&fields);
+            directory.extend([0; 10]); // comment, disk, attributes
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+
+        let directory_offset = out.len() as u32;
+        out.extend(&directory);
+        out.extend(END_OF_DIRECTORY_SIGNATURE);
+        out.extend([0; 4]); // disks
+        out.extend((files.len() as u16).to_le_bytes());
+        out.extend((files.len() as u16).to_le_bytes());
+        out.extend((directory.len() as u32).to_le_bytes());
+        out.extend(directory_offset.to_le_bytes());
+        out.extend([0; 2]); // comment
+        out
+    }
+
+    #[test]
+    fn reads_stored_and_deflated_entries() {
+        let zip = build_zip();
+        let len = zip.len();
+        let vfs = ZipVfs::new(Bytes(zip), len).unwrap();
+
+        let mut scripts: Vec<String> = vfs.read_dir("/scripts/Game").unwrap().collect();
+        scripts.sort();
+        assert_eq!(scripts, ["a.c"]);
+
+        let mut contents = String::new();
+        vfs.open_file("/scripts/Game/a.c").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "class A {}\nclass B {}\n");
+
+        contents.clear();
+        vfs.open_file("/README.txt").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+    }
+}
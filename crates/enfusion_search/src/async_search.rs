@@ -0,0 +1,278 @@
+//! Searching `AsyncVfsPath` workspaces, either with a callback or as a [`Stream`].
+
+use std::collections::VecDeque;
+use std::io;
+use std::ops::ControlFlow;
+
+use enfusion_pak::vfs::async_vfs::AsyncVfsPath;
+use futures::AsyncRead;
+use futures::AsyncReadExt;
+use futures::Stream;
+use futures::StreamExt;
+
+use crate::CHUNK_SIZE;
+use crate::CancellationToken;
+use crate::FileFeeder;
+use crate::FileSelection;
+use crate::LineMatch;
+use crate::SearchStats;
+use crate::Searcher;
+use crate::bytes::ByteScanner;
+use crate::bytes::ByteSearcher;
+
+/// Which files a search looks at.
+#[derive(Debug, Clone)]
+pub enum SearchScope {
+    /// Every searchable file beneath this directory.
+    Directory(AsyncVfsPath),
+    /// Only these files, e.g. the ones visible in a filtered tree.
+    Files(Vec<AsyncVfsPath>),
+}
+
+/// A file with at least one match.
+#[derive(Debug, Clone)]
+pub struct FileMatches {
+    pub file: AsyncVfsPath,
+    pub matches: Vec<LineMatch>,
+}
+
+impl Searcher {
+    /// Searches one file's contents. `cancelled` is checked between chunks, and a cancelled
+    /// search returns the matches found so far.
+    pub async fn search_async_reader(
+        &self,
+        reader: &mut (impl AsyncRead + Unpin),
+        cancelled: impl Fn() -> bool,
+    ) -> io::Result<Vec<LineMatch>> {
+        Ok(self.scan_async_reader(reader, &cancelled).await?.0)
+    }
+
+    /// Searches the files in `scope` chosen by [`Searcher::files`], breadth first, calling
+    /// `on_file` with the matches of each file that has any. `on_scanned` is given the
+    /// running totals after each file is read, matching or not, e.g. to report progress.
+    /// The search stops once `cancelled` returns true or `on_file` returns
+    /// [`ControlFlow::Break`]. Files which can't be read are logged and skipped.
+    pub async fn search_scope(
+        &self,
+        scope: SearchScope,
+        cancelled: impl Fn() -> bool,
+        mut on_scanned: impl FnMut(&SearchStats),
+        mut on_file: impl FnMut(AsyncVfsPath, Vec<LineMatch>) -> ControlFlow<()>,
+    ) -> SearchStats {
+        let mut walker = ScopeWalker::new(self.clone(), scope, cancelled);
+        while let Some(found) = walker.next_match(&mut on_scanned).await {
+            if on_file(found.file, found.matches).is_break() {
+                break;
+            }
+        }
+
+        walker.stats
+    }
+
+    /// Like [`Searcher::search_scope`], but yields each file with matches from a stream.
+    ///
+    /// The search only runs while the stream is polled, stopping after each matching file
+    /// until the consumer asks for the next one, so a slow consumer never has results
+    /// queued up for it. Cancelling `token` ends the stream, including partway through a
+    /// file. To search ahead of the consumer, forward the stream into a bounded channel
+    /// from another task.
+    pub fn search_stream(
+        &self,
+        scope: SearchScope,
+        token: CancellationToken,
+    ) -> impl Stream<Item = FileMatches> + Send + 'static {
+        let walker = ScopeWalker::new(self.clone(), scope, move || token.is_cancelled());
+        futures::stream::unfold(walker, |mut walker| async move {
+            let found = walker.next_match(&mut |_| {}).await?;
+            Some((found, walker))
+        })
+    }
+
+    async fn scan_async_reader(
+        &self,
+        reader: &mut (impl AsyncRead + Unpin),
+        cancelled: &impl Fn() -> bool,
+    ) -> io::Result<(Vec<LineMatch>, u64)> {
+        let mut feeder = FileFeeder::new(self);
+        feed_async_reader(reader, |chunk| feeder.feed(chunk) && !cancelled()).await?;
+        let bytes_read = feeder.bytes_read;
+        Ok((feeder.finish(), bytes_read))
+    }
+}
+
+impl ByteSearcher {
+    /// Searches every file in `scope`, whatever its type, calling `on_file` with the
+    /// offsets of the needle in each file that has it. Reports progress and stops like
+    /// [`Searcher::search_scope`].
+    pub async fn search_scope(
+        &self,
+        scope: SearchScope,
+        cancelled: impl Fn() -> bool,
+        mut on_scanned: impl FnMut(&SearchStats),
+        mut on_file: impl FnMut(AsyncVfsPath, Vec<u64>) -> ControlFlow<()>,
+    ) -> SearchStats {
+        let mut files = ScopeFiles::new(scope, FileSelection::All);
+        let mut stats = SearchStats::default();
+        while let Some(file) = files.next_file(&cancelled).await {
+            let mut reader = match file.open_file().await {
+                Ok(reader) => reader,
+                Err(e) => {
+                    log::warn!("failed to open {}: {e}", file.as_str());
+                    continue;
+                }
+            };
+            let mut scanner = ByteScanner::new(self);
+            let mut bytes_read = 0;
+            let scanned = feed_async_reader(&mut reader, |chunk| {
+                bytes_read += chunk.len() as u64;
+                scanner.feed(chunk) && !cancelled()
+            })
+            .await;
+            if let Err(e) = scanned {
+                log::warn!("failed to read {}: {e}", file.as_str());
+                continue;
+            }
+
+            stats.files_scanned += 1;
+            stats.bytes_scanned += bytes_read;
+            on_scanned(&stats);
+            if cancelled() {
+                break;
+            }
+            let offsets = scanner.finish();
+            if offsets.is_empty() {
+                continue;
+            }
+            stats.files_matched += 1;
+            if on_file(file, offsets).is_break() {
+                break;
+            }
+        }
+
+        stats
+    }
+}
+
+/// Passes `reader`'s contents to `feed` a chunk at a time, until it runs out or `feed`
+/// returns false.
+async fn feed_async_reader(
+    reader: &mut (impl AsyncRead + Unpin),
+    mut feed: impl FnMut(&[u8]) -> bool,
+) -> io::Result<()> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let read = match reader.read(&mut chunk).await {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if !feed(&chunk[..read]) {
+            return Ok(());
+        }
+    }
+}
+
+/// The selected files in a scope, listed breadth first as they're asked for.
+struct ScopeFiles {
+    queue: VecDeque<AsyncVfsPath>,
+    selection: FileSelection,
+}
+
+impl ScopeFiles {
+    fn new(scope: SearchScope, selection: FileSelection) -> Self {
+        let mut queue = VecDeque::new();
+        match scope {
+            SearchScope::Directory(start_path) => queue.push_back(start_path),
+            SearchScope::Files(files) => {
+                queue.extend(files.into_iter().filter(|file| selection.includes(file.as_str())))
+            }
+        }
+
+        ScopeFiles { queue, selection }
+    }
+
+    /// Returns `None` once the scope is exhausted or `cancelled` returns true.
+    async fn next_file(&mut self, cancelled: &impl Fn() -> bool) -> Option<AsyncVfsPath> {
+        while let Some(next) = self.queue.pop_front() {
+            if cancelled() {
+                return None;
+            }
+
+            if !next.is_dir().await.ok().unwrap_or_default() {
+                return Some(next);
+            }
+
+            let mut children = match next.read_dir().await {
+                Ok(children) => children,
+                Err(e) => {
+                    log::warn!("failed to list {}: {e}", next.as_str());
+                    continue;
+                }
+            };
+            while let Some(child) = children.next().await {
+                if !child.is_file().await.ok().unwrap_or_default()
+                    || self.selection.includes(child.as_str())
+                {
+                    self.queue.push_back(child);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Walks a scope breadth first, searching one file at a time.
+struct ScopeWalker<C> {
+    searcher: Searcher,
+    files: ScopeFiles,
+    cancelled: C,
+    stats: SearchStats,
+}
+
+impl<C: Fn() -> bool> ScopeWalker<C> {
+    fn new(searcher: Searcher, scope: SearchScope, cancelled: C) -> Self {
+        let files = ScopeFiles::new(scope, searcher.files.clone());
+        ScopeWalker { searcher, files, cancelled, stats: SearchStats::default() }
+    }
+
+    /// Searches up to the next file with matches. Returns `None` once the scope is
+    /// exhausted or the search is cancelled.
+    async fn next_match(
+        &mut self,
+        on_scanned: &mut impl FnMut(&SearchStats),
+    ) -> Option<FileMatches> {
+        while let Some(next) = self.files.next_file(&self.cancelled).await {
+            let mut reader = match next.open_file().await {
+                Ok(reader) => reader,
+                Err(e) => {
+                    log::warn!("failed to open {}: {e}", next.as_str());
+                    continue;
+                }
+            };
+            let scanned = self.searcher.scan_async_reader(&mut reader, &self.cancelled).await;
+            let (matches, bytes_read) = match scanned {
+                Ok(scanned) => scanned,
+                Err(e) => {
+                    log::warn!("failed to read {}: {e}", next.as_str());
+                    continue;
+                }
+            };
+
+            self.stats.files_scanned += 1;
+            self.stats.bytes_scanned += bytes_read;
+            on_scanned(&self.stats);
+            // A file cut short by cancellation isn't reported
+            if (self.cancelled)() {
+                return None;
+            }
+            if !matches.is_empty() {
+                self.stats.files_matched += 1;
+                return Some(FileMatches { file: next, matches });
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,168 @@
+//! Finding a byte sequence in files of any type.
+//!
+//! Unlike line search, files are matched as they're stored: nothing is decompiled or
+//! decoded, binary files aren't skipped, and matches are reported as byte offsets.
+
+use std::io;
+
+use regex::bytes::Regex;
+
+/// Matches reported per file before the rest of it is skipped, so a common needle such as
+/// `00 00` can't produce millions of results.
+pub const MAX_MATCHES_PER_FILE: usize = 1000;
+
+/// A byte sequence to look for.
+#[derive(Debug, Clone)]
+pub struct ByteSearcher {
+    needle: Vec<u8>,
+    regex: Regex,
+}
+
+impl ByteSearcher {
+    /// Searches for `needle`, or returns `None` if it's empty.
+    pub fn new(needle: &[u8]) -> Option<Self> {
+        if needle.is_empty() {
+            return None;
+        }
+
+        let pattern: String = needle.iter().map(|byte| format!("\\x{byte:02X}")).collect();
+        let regex =
+            Regex::new(&format!("(?s-u){pattern}")).expect("escaped bytes are a valid regex");
+        Some(ByteSearcher { needle: needle.to_vec(), regex })
+    }
+
+    /// Reads `query` as hex if it's only pairs of hex digits, which may be separated by
+    /// whitespace (`DE AD BE EF`), and as ASCII otherwise. A query in double quotes is always
+    /// ASCII, for text such as `"cafe"` which is also valid hex.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use enfusion_search::bytes::ByteSearcher;
+    ///
+    /// let needle = |query| ByteSearcher::parse(query).unwrap().needle().to_vec();
+    /// assert_eq!(needle("DE AD be ef"), [0xDE, 0xAD, 0xBE, 0xEF]);
+    /// assert_eq!(needle("PAC1"), b"PAC1");
+    /// assert_eq!(needle("\"cafe\""), b"cafe");
+    /// assert!(ByteSearcher::parse("  ").is_none());
+    /// ```
+    pub fn parse(query: &str) -> Option<Self> {
+        let query = query.trim();
+        if let Some(ascii) = query.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+            return Self::new(ascii.as_bytes());
+        }
+
+        match parse_hex(query) {
+            Some(bytes) => Self::new(&bytes),
+            None => Self::new(query.as_bytes()),
+        }
+    }
+
+    pub fn needle(&self) -> &[u8] {
+        &self.needle
+    }
+
+    /// Offsets of the needle in one file's contents.
+    pub fn search_reader(&self, reader: &mut impl io::Read) -> io::Result<Vec<u64>> {
+        let mut scanner = ByteScanner::new(self);
+        crate::feed_reader(reader, |chunk| scanner.feed(chunk))?;
+        Ok(scanner.finish())
+    }
+}
+
+/// Bytes written as hex digit pairs, or `None` if `text` isn't that.
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text.bytes().filter(|byte| !byte.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// Finds a needle in a file fed a chunk at a time. Matches don't overlap.
+pub(crate) struct ByteScanner<'s> {
+    searcher: &'s ByteSearcher,
+    /// The end of the last chunk, which could hold the start of a match.
+    carry: Vec<u8>,
+    /// File offset of `carry`.
+    carry_offset: u64,
+    /// File offset the last match ended at.
+    matched_to: u64,
+    matches: Vec<u64>,
+}
+
+impl<'s> ByteScanner<'s> {
+    pub(crate) fn new(searcher: &'s ByteSearcher) -> Self {
+        ByteScanner {
+            searcher,
+            carry: Vec::new(),
+            carry_offset: 0,
+            matched_to: 0,
+            matches: Vec::new(),
+        }
+    }
+
+    /// Scans the next chunk of the file. Returns whether the rest of the file is wanted.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> bool {
+        let mut data = std::mem::take(&mut self.carry);
+        data.extend_from_slice(chunk);
+
+        // The carry can start inside the last chunk's final match
+        let mut start = self.matched_to.saturating_sub(self.carry_offset) as usize;
+        while let Some(found) = self.searcher.regex.find_at(&data, start) {
+            self.matches.push(self.carry_offset + found.start() as u64);
+            self.matched_to = self.carry_offset + found.end() as u64;
+            if self.matches.len() == MAX_MATCHES_PER_FILE {
+                return false;
+            }
+            start = found.end();
+        }
+
+        let keep = (self.searcher.needle.len() - 1).min(data.len());
+        self.carry_offset += (data.len() - keep) as u64;
+        data.drain(..data.len() - keep);
+        self.carry = data;
+        true
+    }
+
+    pub(crate) fn finish(self) -> Vec<u64> {
+        self.matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_needles_across_chunks() {
+        let cases: [(&str, &[u8], &[u64]); 2] = [
+            ("00 FF 00", &[1, 0, 0xFF, 0, 0xFF, 0, 2, 0, 0xFF, 0], &[1, 7]),
+            ("aba", b"abababa", &[0, 4]),
+        ];
+        for (query, data, expected) in cases {
+            let searcher = ByteSearcher::parse(query).unwrap();
+            assert_eq!(searcher.search_reader(&mut &data[..]).unwrap(), expected);
+
+            // Every split of the data finds the same matches
+            for split in 0..data.len() {
+                let mut scanner = ByteScanner::new(&searcher);
+                scanner.feed(&data[..split]);
+                scanner.feed(&data[split..]);
+                assert_eq!(scanner.finish(), expected, "{query} split at {split}");
+            }
+        }
+    }
+
+    #[test]
+    fn stops_at_match_limit() {
+        let searcher = ByteSearcher::new(&[0]).unwrap();
+        let data = vec![0; MAX_MATCHES_PER_FILE * 2];
+        let matches = searcher.search_reader(&mut data.as_slice()).unwrap();
+        assert_eq!(matches.len(), MAX_MATCHES_PER_FILE);
+    }
+}
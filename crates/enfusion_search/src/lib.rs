@@ -0,0 +1,272 @@
+//! Searching file contents across a workspace of mounted archives.
+//!
+//! A [`Searcher`] holds the query. Files are read a chunk at a time and matched line by
+//! line with [`scan::LineScanner`]. Formats that aren't plain text are handled first:
+//! rapified configs are decompiled, UTF-16 files are decoded, and binary files are skipped.
+//!
+//! [`bytes::ByteSearcher`] instead finds a byte sequence in files as they're stored, binary
+//! or not.
+//!
+//! The same implementation backs the CLI's `grep` (synchronous [`VfsPath`]s) and the UI's
+//! workspace search (`AsyncVfsPath`s, with the `async` feature).
+
+use std::io;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use enfusion_pak::entry_type::detect_type;
+use enfusion_pak::vfs::VfsPath;
+use regex::bytes::Regex;
+
+#[cfg(feature = "async")]
+mod async_search;
+pub mod bytes;
+pub mod scan;
+pub mod text;
+
+pub use scan::LineMatch;
+
+use crate::scan::LineScanner;
+
+/// How much of a file is read and scanned at a time.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Whether the type registry considers the file at `path` text, and therefore worth
+/// searching.
+pub fn is_searchable(path: &str) -> bool {
+    detect_type(path, &[]).is_text()
+}
+
+/// Which of the files in a scope a search reads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FileSelection {
+    /// Files the type registry considers text. See [`is_searchable`].
+    #[default]
+    Text,
+    /// Files with one of these extensions, given without the dot and compared
+    /// case-insensitively.
+    Extensions(Vec<String>),
+    /// Every file. Line searches still skip files which look binary once read.
+    All,
+}
+
+impl FileSelection {
+    /// Whether the file at `path` is selected.
+    ///
+    /// ```
+    /// use enfusion_search::FileSelection;
+    ///
+    /// let scripts = FileSelection::Extensions(vec!["c".to_string()]);
+    /// assert!(scripts.includes("/scripts/Game/Foo.C"));
+    /// assert!(!scripts.includes("/scripts.c/readme"));
+    /// ```
+    pub fn includes(&self, path: &str) -> bool {
+        match self {
+            FileSelection::Text => is_searchable(path),
+            FileSelection::Extensions(extensions) => {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                name.rsplit_once('.').is_some_and(|(_, ext)| {
+                    extensions.iter().any(|candidate| candidate.eq_ignore_ascii_case(ext))
+                })
+            }
+            FileSelection::All => true,
+        }
+    }
+}
+
+/// Totals for a finished (or stopped) search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub files_scanned: usize,
+    pub bytes_scanned: u64,
+    pub files_matched: usize,
+}
+
+/// Stops a search from another task or thread. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Whether `other` is this token or one of its clones.
+    pub fn same_as(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A content search: what to match, and how much of the surrounding text to report.
+#[derive(Debug, Clone)]
+pub struct Searcher {
+    regex: Regex,
+    context: usize,
+    files: FileSelection,
+}
+
+impl Searcher {
+    /// Searches for `regex`, reporting one line of context on either side of each match.
+    pub fn new(regex: Regex) -> Self {
+        Searcher { regex, context: 1, files: FileSelection::default() }
+    }
+
+    /// Sets how many lines before and after each match are reported.
+    pub fn context(mut self, lines: usize) -> Self {
+        self.context = lines;
+        self
+    }
+
+    /// Sets which files in a scope are searched. Only used when searching a scope, as
+    /// other searches are handed the files to read.
+    pub fn files(mut self, files: FileSelection) -> Self {
+        self.files = files;
+        self
+    }
+
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    /// Searches one file's contents.
+    pub fn search_reader(&self, reader: &mut impl io::Read) -> io::Result<Vec<LineMatch>> {
+        Ok(self.scan_reader(reader)?.0)
+    }
+
+    /// Searches `files` in order, calling `on_file` with the matches of each file that has
+    /// any. Returning [`ControlFlow::Break`] stops the search. Files which can't be read are
+    /// logged and skipped.
+    pub fn search_files(
+        &self,
+        files: impl IntoIterator<Item = VfsPath>,
+        mut on_file: impl FnMut(&VfsPath, Vec<LineMatch>) -> ControlFlow<()>,
+    ) -> SearchStats {
+        let mut stats = SearchStats::default();
+        for file in files {
+            let scanned = file
+                .open_file()
+                .map_err(io::Error::other)
+                .and_then(|mut reader| self.scan_reader(&mut reader));
+            let (matches, bytes_read) = match scanned {
+                Ok(scanned) => scanned,
+                Err(e) => {
+                    log::warn!("failed to search {}: {e}", file.as_str());
+                    continue;
+                }
+            };
+
+            stats.files_scanned += 1;
+            stats.bytes_scanned += bytes_read;
+            if matches.is_empty() {
+                continue;
+            }
+            stats.files_matched += 1;
+            if on_file(&file, matches).is_break() {
+                break;
+            }
+        }
+
+        stats
+    }
+
+    /// Returns a file's matches and how many bytes were read from it.
+    fn scan_reader(&self, reader: &mut impl io::Read) -> io::Result<(Vec<LineMatch>, u64)> {
+        let mut feeder = FileFeeder::new(self);
+        feed_reader(reader, |chunk| feeder.feed(chunk))?;
+        let bytes_read = feeder.bytes_read;
+        Ok((feeder.finish(), bytes_read))
+    }
+}
+
+/// Passes `reader`'s contents to `feed` a chunk at a time, until it runs out or `feed`
+/// returns false.
+fn feed_reader(reader: &mut impl io::Read, mut feed: impl FnMut(&[u8]) -> bool) -> io::Result<()> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if !feed(&chunk[..read]) {
+            return Ok(());
+        }
+    }
+}
+
+/// Feeds one file to a [`LineScanner`], deciding from its first chunk how the file has to
+/// be read.
+struct FileFeeder<'r> {
+    scanner: LineScanner<'r>,
+    state: FeedState,
+    bytes_read: u64,
+}
+
+enum FeedState {
+    Start,
+    Streaming,
+    /// Rapified configs and UTF-16 text can only be decoded as a whole.
+    Buffering(Vec<u8>),
+    Binary,
+}
+
+impl<'r> FileFeeder<'r> {
+    fn new(searcher: &'r Searcher) -> Self {
+        FileFeeder {
+            scanner: LineScanner::new(&searcher.regex, searcher.context),
+            state: FeedState::Start,
+            bytes_read: 0,
+        }
+    }
+
+    /// Scans the next chunk of the file. Returns whether the rest of the file is wanted.
+    fn feed(&mut self, data: &[u8]) -> bool {
+        self.bytes_read += data.len() as u64;
+        if let FeedState::Start = self.state {
+            self.state = if cfg_parser::is_rapified(data) || text::has_utf16_bom(data) {
+                FeedState::Buffering(Vec::new())
+            } else if text::is_probably_binary(data) {
+                FeedState::Binary
+            } else {
+                FeedState::Streaming
+            };
+        }
+
+        match &mut self.state {
+            FeedState::Streaming => self.scanner.feed(data),
+            FeedState::Buffering(buffer) => buffer.extend_from_slice(data),
+            FeedState::Start | FeedState::Binary => return false,
+        }
+        true
+    }
+
+    fn finish(mut self) -> Vec<LineMatch> {
+        if let FeedState::Buffering(data) = std::mem::replace(&mut self.state, FeedState::Binary) {
+            if cfg_parser::is_rapified(&data) {
+                if let Ok(rap) = cfg_parser::RapFile::parse(&data) {
+                    self.scanner.feed(cfg_parser::decompile(&rap).as_bytes());
+                }
+            } else if let Some(text) = text::decode_text(&data) {
+                self.scanner.feed(text.as_bytes());
+            }
+        }
+
+        self.scanner.finish()
+    }
+}
+
+#[cfg(feature = "async")]
+pub use async_search::FileMatches;
+#[cfg(feature = "async")]
+pub use async_search::SearchScope;
@@ -0,0 +1,220 @@
+//! Incremental line-based regex scanning.
+//!
+//! Files are fed in as arbitrary chunks. Complete lines are matched as they arrive and any
+//! trailing partial line is carried over to the next chunk, so memory use is bounded by
+//! the chunk size plus the longest line rather than the size of the file.
+
+use std::collections::VecDeque;
+
+use regex::bytes::Regex;
+
+/// Lines longer than this are split so a file without line breaks can't grow the carry
+/// buffer without bound.
+const MAX_LINE_LEN: usize = 1024 * 1024;
+
+/// A matching line and the lines around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// 1-based number of the matching line.
+    pub line: usize,
+    /// Lines before the match, in file order.
+    pub before: Vec<String>,
+    /// The matching line, without its line ending.
+    pub text: String,
+    /// Lines after the match, in file order.
+    pub after: Vec<String>,
+}
+
+impl LineMatch {
+    /// Number of the first line of context.
+    pub fn first_line(&self) -> usize {
+        self.line - self.before.len()
+    }
+
+    /// Every line, context included, with its line number.
+    pub fn lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.before
+            .iter()
+            .chain(std::iter::once(&self.text))
+            .chain(&self.after)
+            .map(String::as_str)
+            .enumerate()
+            .map(|(idx, line)| (self.first_line() + idx, line))
+    }
+}
+
+/// Finds regex matches line by line, reporting each matching line along with up to
+/// `context` lines before and after it.
+///
+/// Matches can't span line breaks. A match within the lines after another one is reported
+/// as part of it, and no line is reported twice.
+pub struct LineScanner<'r> {
+    regex: &'r Regex,
+    context: usize,
+    carry: Vec<u8>,
+    /// Line number of the line currently in `carry`.
+    line_number: usize,
+    prev_lines: VecDeque<String>,
+    /// The match still waiting for lines after it, and how many more it wants.
+    pending: Option<(LineMatch, usize)>,
+    /// Number of the last line reported as part of a match.
+    last_reported: usize,
+    matches: Vec<LineMatch>,
+}
+
+impl<'r> LineScanner<'r> {
+    pub fn new(regex: &'r Regex, context: usize) -> Self {
+        Self {
+            regex,
+            context,
+            carry: Vec::new(),
+            line_number: 1,
+            prev_lines: VecDeque::new(),
+            pending: None,
+            last_reported: 0,
+            matches: Vec::new(),
+        }
+    }
+
+    /// Scans the next chunk of the file.
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        while let Some(newline) = chunk.iter().position(|b| *b == b'\n') {
+            let (line, rest) = chunk.split_at(newline + 1);
+            chunk = rest;
+
+            if self.carry.is_empty() {
+                self.process_line(line);
+            } else {
+                self.carry.extend_from_slice(line);
+                let line = std::mem::take(&mut self.carry);
+                self.process_line(&line);
+            }
+        }
+
+        self.carry.extend_from_slice(chunk);
+        if self.carry.len() >= MAX_LINE_LEN {
+            let line = std::mem::take(&mut self.carry);
+            self.process_line(&line);
+        }
+    }
+
+    /// Scans any remaining partial line and returns all matches found.
+    pub fn finish(mut self) -> Vec<LineMatch> {
+        if !self.carry.is_empty() {
+            let line = std::mem::take(&mut self.carry);
+            self.process_line(&line);
+        }
+
+        if let Some((found, _)) = self.pending.take() {
+            self.report(found);
+        }
+        self.matches
+    }
+
+    fn process_line(&mut self, line: &[u8]) {
+        let line = trim_line_ending(line);
+        let text = String::from_utf8_lossy(line).into_owned();
+
+        let is_match = self.regex.is_match(line);
+        if let Some((pending, wanted)) = &mut self.pending {
+            // A match among the lines after the pending one extends it
+            pending.after.push(text.clone());
+            *wanted = if is_match { self.context } else { *wanted - 1 };
+            if *wanted == 0 {
+                let (found, _) = self.pending.take().expect("pending match");
+                self.report(found);
+            }
+        } else if is_match {
+            // Lines already shown after the previous match aren't repeated before this one
+            let unreported = self.line_number - 1 - self.last_reported;
+            let skip = self.prev_lines.len().saturating_sub(unreported);
+            let found = LineMatch {
+                line: self.line_number,
+                before: self.prev_lines.iter().skip(skip).cloned().collect(),
+                text: text.clone(),
+                after: Vec::new(),
+            };
+            if self.context == 0 {
+                self.report(found);
+            } else {
+                self.pending = Some((found, self.context));
+            }
+        }
+
+        if self.context > 0 {
+            if self.prev_lines.len() == self.context {
+                self.prev_lines.pop_front();
+            }
+            self.prev_lines.push_back(text);
+        }
+        self.line_number += 1;
+    }
+
+    fn report(&mut self, found: LineMatch) {
+        self.last_reported = found.line + found.after.len();
+        self.matches.push(found);
+    }
+}
+
+fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(chunks: &[&str], pattern: &str, context: usize) -> Vec<(usize, String)> {
+        let regex = Regex::new(pattern).unwrap();
+        let mut scanner = LineScanner::new(&regex, context);
+        for chunk in chunks {
+            scanner.feed(chunk.as_bytes());
+        }
+
+        scanner
+            .finish()
+            .into_iter()
+            .map(|found| {
+                let lines: Vec<&str> = found.lines().map(|(_, line)| line).collect();
+                (found.first_line(), lines.join("\n"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_matches_with_surrounding_lines() {
+        let matches = scan(&["one\ntwo\nthree\nfour\n"], "three", 1);
+        assert_eq!(matches, [(2, "two\nthree\nfour".to_string())]);
+
+        let matches = scan(&["first\nsecond"], "first|second", 1);
+        assert_eq!(matches, [(1, "first\nsecond".to_string())]);
+
+        let matches = scan(&["a\nb\nc\nd\ne\n"], "c", 2);
+        assert_eq!(matches, [(1, "a\nb\nc\nd\ne".to_string())]);
+
+        let matches = scan(&["a\nb\nc\n"], "b", 0);
+        assert_eq!(matches, [(2, "b".to_string())]);
+    }
+
+    #[test]
+    fn nearby_matches_share_lines() {
+        let matches = scan(&["a\nX\nX\nb\nX\nc\nd\n"], "X", 1);
+        assert_eq!(matches, [(1, "a\nX\nX\nb".to_string()), (5, "X\nc".to_string())]);
+
+        let matches = scan(&["a\nX\nb\nc\nX\nd\n"], "X", 1);
+        assert_eq!(matches, [(1, "a\nX\nb".to_string()), (4, "c\nX\nd".to_string())]);
+
+        let matches = scan(&["X\nb\nX\nc\n"], "X", 1);
+        assert_eq!(matches, [(1, "X\nb".to_string()), (3, "X\nc".to_string())]);
+
+        let matches = scan(&["X\nX\nb\n"], "X", 0);
+        assert_eq!(matches, [(1, "X".to_string()), (2, "X".to_string())]);
+    }
+
+    #[test]
+    fn lines_split_across_chunks_are_carried() {
+        let matches = scan(&["alpha\nbe", "ta gam", "ma\ndelta"], "beta gamma", 1);
+        assert_eq!(matches, [(1, "alpha\nbeta gamma\ndelta".to_string())]);
+    }
+}
@@ -0,0 +1,78 @@
+//! Telling binary files apart from text, and decoding the text encodings game files use.
+
+/// How much of a file is inspected when guessing whether it's binary.
+pub const SNIFF_LEN: usize = 8 * 1024;
+
+/// Files are considered binary if more than 1 in this many sniffed bytes are control
+/// characters.
+const CONTROL_CHAR_RATIO: usize = 10;
+
+/// Guesses whether `data` is binary from its first [`SNIFF_LEN`] bytes. Any NUL byte, or a
+/// high proportion of control characters other than common whitespace, means binary.
+pub fn is_probably_binary(data: &[u8]) -> bool {
+    let sniffed = &data[..data.len().min(SNIFF_LEN)];
+    if sniffed.contains(&0) {
+        return true;
+    }
+
+    let control_chars = sniffed
+        .iter()
+        .filter(|b| matches!(b, 0x01..=0x08 | 0x0E..=0x1A | 0x1C..=0x1F | 0x7F))
+        .count();
+
+    control_chars * CONTROL_CHAR_RATIO > sniffed.len()
+}
+
+/// Whether `data` starts with a UTF-16 byte order mark.
+pub fn has_utf16_bom(data: &[u8]) -> bool {
+    data.starts_with(b"\xFF\xFE") || data.starts_with(b"\xFE\xFF")
+}
+
+/// Decodes `data` as text, or returns `None` if it looks binary.
+///
+/// UTF-16 files are recognized by their byte order mark, a UTF-8 byte order mark is
+/// dropped, and anything else that isn't valid UTF-8 is read as Latin-1, which is close
+/// enough for the Windows-1252 text older game files use.
+pub fn decode_text(data: &[u8]) -> Option<String> {
+    let utf16 = |data: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units = data.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]]));
+        char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+    };
+
+    if has_utf16_bom(data) {
+        let from_bytes = if data[0] == 0xFF { u16::from_le_bytes } else { u16::from_be_bytes };
+        return Some(utf16(&data[2..], from_bytes));
+    }
+
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    if is_probably_binary(data) {
+        return None;
+    }
+
+    match std::str::from_utf8(data) {
+        Ok(text) => Some(text.to_string()),
+        Err(_) => Some(data.iter().map(|b| *b as char).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_binary_content() {
+        assert!(!is_probably_binary(b"class Foo\r\n{\n\tint x;\n}\n"));
+        assert!(!is_probably_binary("Zvědavost".as_bytes()));
+        assert!(is_probably_binary(b"DDS \x7c\x00\x00\x00"));
+        assert!(is_probably_binary(&[0x01, 0x02, 0x03, b'a', b'b']));
+    }
+
+    #[test]
+    fn decodes_text_encodings() {
+        assert_eq!(decode_text(b"\xEF\xBB\xBFclass A {}").as_deref(), Some("class A {}"));
+        assert_eq!(decode_text(b"\xFF\xFEh\0i\0").as_deref(), Some("hi"));
+        assert_eq!(decode_text(b"\xFE\xFF\0h\0i").as_deref(), Some("hi"));
+        assert_eq!(decode_text(b"caf\xE9").as_deref(), Some("café"));
+        assert_eq!(decode_text(b"DDS \x7c\x00\x00\x00"), None);
+    }
+}
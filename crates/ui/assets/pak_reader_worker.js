@@ -0,0 +1,126 @@
+// Serves byte ranges of archive files to the web app so file reads, and the block cache
+// backing them, stay off the UI thread. Results are transferred rather than copied.
+//
+// Paks are parsed here too, by loading the app's own wasm module and calling its
+// `parsePakInWorker` export, which reads through `readRange` below.
+//
+// Messages:
+//   { kind: "register", fileId, file }   remember a File under an id
+//   { kind: "read", id, fileId, start, end }
+//   { kind: "parse", id, fileId, name, glueUrl, module }
+// Replies:
+//   { id, result } or { id, error }, or { id, unsupported } if the app couldn't be loaded
+
+var BLOCK_SIZE = 1024 * 1024;
+// Maximum number of cached blocks
+var CACHE_LIMIT = 64;
+
+var files = new Map();
+// Map iteration follows insertion order, so re-inserting on access makes this an LRU.
+var cache = new Map();
+var reader = new FileReaderSync();
+// Promise of the app's wasm-bindgen glue, once it's been asked for
+var app = null;
+
+function readBlock(fileId, file, block) {
+  var key = fileId + ":" + block;
+  var data = cache.get(key);
+  if (data !== undefined) {
+    cache.delete(key);
+    cache.set(key, data);
+    return data;
+  }
+
+  var start = block * BLOCK_SIZE;
+  data = new Uint8Array(
+    reader.readAsArrayBuffer(file.slice(start, Math.min(start + BLOCK_SIZE, file.size))),
+  );
+  cache.set(key, data);
+  if (cache.size > CACHE_LIMIT) {
+    cache.delete(cache.keys().next().value);
+  }
+
+  return data;
+}
+
+function readRange(fileId, start, end) {
+  var file = files.get(fileId);
+  if (file === undefined) {
+    throw new Error("unknown file id " + fileId);
+  }
+
+  end = Math.min(end, file.size);
+  // Large reads are whole file contents which won't be read again soon, so bypass the cache
+  if (end - start >= BLOCK_SIZE) {
+    return new Uint8Array(reader.readAsArrayBuffer(file.slice(start, end)));
+  }
+
+  var out = new Uint8Array(Math.max(end - start, 0));
+  var pos = start;
+  while (pos < end) {
+    var block = Math.floor(pos / BLOCK_SIZE);
+    var data = readBlock(fileId, file, block);
+    var offset = pos - block * BLOCK_SIZE;
+    var len = Math.min(data.length - offset, end - pos);
+    if (len <= 0) {
+      break;
+    }
+
+    out.set(data.subarray(offset, offset + len), pos - start);
+    pos += len;
+  }
+
+  return out;
+}
+
+function loadApp(glueUrl, module) {
+  if (app === null) {
+    app = import(glueUrl).then(function (glue) {
+      return glue.default({ module_or_path: module }).then(function () {
+        return glue;
+      });
+    });
+  }
+
+  return app;
+}
+
+function parsePak(message) {
+  loadApp(message.glueUrl, message.module).then(
+    function (glue) {
+      glue.parsePakInWorker(message.fileId, message.name).then(
+        function (json) {
+          self.postMessage({ id: message.id, result: json });
+        },
+        function (err) {
+          self.postMessage({ id: message.id, error: String(err) });
+        },
+      );
+    },
+    function (err) {
+      self.postMessage({ id: message.id, unsupported: String(err) });
+    },
+  );
+}
+
+self.onmessage = function (e) {
+  var message = e.data;
+  if (message.kind === "register") {
+    files.set(message.fileId, message.file);
+    return;
+  }
+
+  if (message.kind === "read") {
+    try {
+      var data = readRange(message.fileId, message.start, message.end);
+      self.postMessage({ id: message.id, result: data.buffer }, [data.buffer]);
+    } catch (err) {
+      self.postMessage({ id: message.id, error: String(err) });
+    }
+    return;
+  }
+
+  if (message.kind === "parse") {
+    parsePak(message);
+  }
+};
@@ -3,7 +3,8 @@ var filesToCache = [
   "./", 
   "./index.html",
   "./ui.js",
-  "./ui_bg.wasm"
+  "./ui_bg.wasm",
+  "./pak_reader_worker.js"
 ];
 
 /* Start the service worker and cache all of the app's content */
@@ -0,0 +1,512 @@
+//! Analyses included with the app.
+
+use std::collections::HashMap;
+use std::hash::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+use async_trait::async_trait;
+use enfusion_pak::entry_type::EntryType;
+use enfusion_pak::entry_type::detect_type;
+use enfusion_pak::references::ReferenceIndex;
+use enfusion_pak::string_table::CoverageReport;
+use enfusion_pak::string_table::StringTable;
+use enfusion_search::LineMatch;
+use enfusion_search::Searcher;
+use enfusion_search::text::is_probably_binary;
+use tracing::warn;
+
+use super::Analysis;
+use super::AnalysisError;
+use super::Export;
+use super::InputFile;
+use super::InputPrompt;
+use super::Item;
+use super::Report;
+use super::Section;
+use super::Workspace;
+use crate::deprecations;
+
+/// Scans scripts for calls to deprecated engine APIs listed in a TOML rules file.
+pub struct DeprecatedApis;
+
+#[async_trait]
+impl Analysis for DeprecatedApis {
+    fn name(&self) -> &str {
+        "Deprecated APIs"
+    }
+
+    fn description(&self) -> &str {
+        "Find calls to deprecated or renamed engine APIs in scripts, using a TOML rules file"
+    }
+
+    fn input(&self) -> Option<InputFile> {
+        Some(InputFile {
+            title: "Open Deprecation Rules",
+            filter_name: "Deprecation rules",
+            extensions: &["toml"],
+        })
+    }
+
+    async fn run(
+        &self,
+        workspace: &Workspace,
+        input: Option<&[u8]>,
+    ) -> Result<Report, AnalysisError> {
+        let rules = deprecations::parse_rules(&String::from_utf8_lossy(input.unwrap_or_default()))?;
+
+        let mut sections = Vec::new();
+        for file in workspace.files().await {
+            if file.extension().is_none_or(|ext| !ext.eq_ignore_ascii_case("c")) {
+                continue;
+            }
+
+            let Some(data) = workspace.read(&file).await else {
+                continue;
+            };
+            let findings = deprecations::scan_script(&rules, &String::from_utf8_lossy(&data));
+            if findings.is_empty() {
+                continue;
+            }
+
+            let items = findings
+                .into_iter()
+                .map(|finding| {
+                    let rule = &rules[finding.rule];
+                    let mut text = rule.api.clone();
+                    if let Some(replacement) = &rule.replacement {
+                        text.push_str(&format!(" → {replacement}"));
+                    }
+                    if let Some(note) = &rule.note {
+                        text.push_str(&format!(" ({note})"));
+                    }
+
+                    Item {
+                        file: Some(file.as_str().to_string()),
+                        line: Some(finding.line.0),
+                        text,
+                        detail: Some(finding.text),
+                    }
+                })
+                .collect();
+            sections.push(Section { heading: file.as_str().to_string(), items });
+        }
+        sections.sort_by(|a, b| a.heading.cmp(&b.heading));
+
+        let uses: usize = sections.iter().map(|section| section.items.len()).sum();
+        Ok(Report {
+            summary: format!(
+                "{uses} use(s) of {} deprecated API(s) in {} script(s)",
+                rules.len(),
+                sections.len()
+            ),
+            sections,
+            exports: Vec::new(),
+        })
+    }
+}
+
+/// Cross-references resource names in text files against the files in the workspace.
+pub struct UnreferencedFiles;
+
+#[async_trait]
+impl Analysis for UnreferencedFiles {
+    fn name(&self) -> &str {
+        "Unreferenced Files"
+    }
+
+    fn description(&self) -> &str {
+        "Find files nothing refers to, and references to files which don't exist. Files the \
+         engine loads directly, such as project files, are also listed."
+    }
+
+    async fn run(&self, workspace: &Workspace, _: Option<&[u8]>) -> Result<Report, AnalysisError> {
+        let mut index = ReferenceIndex::new();
+        for file in workspace.files().await {
+            index.add_entry(file.as_str());
+            if !detect_type(file.as_str(), &[]).is_text() {
+                continue;
+            }
+
+            let Some(data) = workspace.read(&file).await else {
+                continue;
+            };
+            if !is_probably_binary(&data) {
+                index.add_source(file.as_str(), &String::from_utf8_lossy(&data));
+            }
+        }
+
+        let report = index.report();
+        Ok(Report {
+            summary: format!(
+                "{} unreferenced file(s), {} missing reference(s)",
+                report.unreferenced.len(),
+                report.missing.len()
+            ),
+            sections: vec![
+                Section {
+                    heading: "Unreferenced files".to_string(),
+                    items: report
+                        .unreferenced
+                        .into_iter()
+                        .map(|file| Item {
+                            text: file.clone(),
+                            file: Some(file),
+                            ..Item::default()
+                        })
+                        .collect(),
+                },
+                Section {
+                    heading: "Missing references".to_string(),
+                    items: report
+                        .missing
+                        .into_iter()
+                        .map(|missing| Item {
+                            file: Some(missing.source),
+                            text: missing.reference,
+                            ..Item::default()
+                        })
+                        .collect(),
+                },
+            ],
+            exports: Vec::new(),
+        })
+    }
+}
+
+/// Compares the languages every localized string is translated into.
+pub struct LocalizationCoverage;
+
+#[async_trait]
+impl Analysis for LocalizationCoverage {
+    fn name(&self) -> &str {
+        "Localization Coverage"
+    }
+
+    fn description(&self) -> &str {
+        "List the strings missing a translation in each language, from .st string tables or \
+         DayZ's stringtable.csv"
+    }
+
+    async fn run(&self, workspace: &Workspace, _: Option<&[u8]>) -> Result<Report, AnalysisError> {
+        let mut tables = Vec::new();
+        for file in workspace.files().await {
+            let parse = if detect_type(file.as_str(), &[]) == EntryType::StringTable {
+                StringTable::parse
+            } else if file.filename().eq_ignore_ascii_case("stringtable.csv") {
+                StringTable::parse_csv
+            } else {
+                continue;
+            };
+
+            let Some(data) = workspace.read(&file).await else {
+                continue;
+            };
+            match parse(&String::from_utf8_lossy(&data)) {
+                Ok(table) => tables.push((file.as_str().to_string(), table)),
+                Err(e) => warn!(file = file.as_str(), %e, "failed to parse string table"),
+            }
+        }
+
+        let coverage =
+            CoverageReport::new(tables.iter().map(|(source, table)| (source.as_str(), table)));
+
+        let mut languages = Section { heading: "Languages".to_string(), items: Vec::new() };
+        let mut sections = Vec::new();
+        for (idx, language) in coverage.languages.iter().enumerate() {
+            let missing: Vec<Item> = coverage
+                .rows
+                .iter()
+                .filter(|row| row.translations[idx].is_none())
+                .map(|row| Item {
+                    file: Some(row.source.clone()),
+                    text: row.id.clone(),
+                    ..Item::default()
+                })
+                .collect();
+
+            languages.items.push(Item {
+                text: format!(
+                    "{language}: {} translated, {} missing",
+                    coverage.rows.len() - missing.len(),
+                    missing.len()
+                ),
+                ..Item::default()
+            });
+            if !missing.is_empty() {
+                sections.push(Section {
+                    heading: format!("Missing {language} translations"),
+                    items: missing,
+                });
+            }
+        }
+        sections.insert(0, languages);
+
+        let mut csv = Vec::new();
+        coverage.write_csv(&mut csv)?;
+
+        Ok(Report {
+            summary: format!(
+                "{} string(s) in {} language(s) from {} table(s)",
+                coverage.rows.len(),
+                coverage.languages.len(),
+                tables.len()
+            ),
+            sections,
+            exports: vec![Export {
+                label: "Export CSV...".to_string(),
+                file_name: "localization_coverage.csv".to_string(),
+                data: csv,
+            }],
+        })
+    }
+}
+
+/// Lists files provided by more than one archive, where the lower priority copies are
+/// shadowed.
+pub struct ArchiveConflicts;
+
+#[async_trait]
+impl Analysis for ArchiveConflicts {
+    fn name(&self) -> &str {
+        "Archive Conflicts"
+    }
+
+    fn description(&self) -> &str {
+        "List files which more than one loaded archive provides, and which archive's copy is used"
+    }
+
+    async fn run(&self, workspace: &Workspace, _: Option<&[u8]>) -> Result<Report, AnalysisError> {
+        let mut providers: HashMap<String, Vec<&str>> = HashMap::new();
+        for layer in &workspace.layers {
+            let Ok(files) = layer.root.walk_dir() else {
+                continue;
+            };
+
+            for file in files.flatten() {
+                if file.is_file().unwrap_or_default() {
+                    providers.entry(file.as_str().to_string()).or_default().push(&layer.name);
+                }
+            }
+        }
+
+        let mut items: Vec<Item> = providers
+            .into_iter()
+            .filter(|(_, archives)| archives.len() > 1)
+            .map(|(file, archives)| Item {
+                text: format!("used from {}, shadows {}", archives[0], archives[1..].join(", ")),
+                file: Some(file),
+                ..Item::default()
+            })
+            .collect();
+        items.sort_by(|a, b| a.file.cmp(&b.file));
+
+        Ok(Report {
+            summary: format!("{} file(s) are provided by more than one archive", items.len()),
+            sections: vec![Section { heading: "Conflicting files".to_string(), items }],
+            exports: Vec::new(),
+        })
+    }
+}
+
+/// Finds files whose contents are identical.
+pub struct DuplicateFiles;
+
+#[async_trait]
+impl Analysis for DuplicateFiles {
+    fn name(&self) -> &str {
+        "Duplicate Files"
+    }
+
+    fn description(&self) -> &str {
+        "Find files with identical contents at different paths"
+    }
+
+    async fn run(&self, workspace: &Workspace, _: Option<&[u8]>) -> Result<Report, AnalysisError> {
+        // Only files which share a size can be duplicates, so only those are read
+        let mut by_size: HashMap<u64, Vec<_>> = HashMap::new();
+        for file in workspace.files().await {
+            if let Ok(metadata) = file.metadata().await
+                && metadata.len > 0
+            {
+                by_size.entry(metadata.len).or_default().push(file);
+            }
+        }
+
+        let mut groups = Vec::new();
+        for (size, files) in by_size.into_iter().filter(|(_, files)| files.len() > 1) {
+            let mut by_hash: HashMap<u64, Vec<String>> = HashMap::new();
+            for file in files {
+                let Some(data) = workspace.read(&file).await else {
+                    continue;
+                };
+
+                let mut hasher = DefaultHasher::new();
+                data.hash(&mut hasher);
+                by_hash.entry(hasher.finish()).or_default().push(file.as_str().to_string());
+            }
+
+            groups.extend(
+                by_hash.into_values().filter(|paths| paths.len() > 1).map(|paths| (size, paths)),
+            );
+        }
+
+        // Largest savings first
+        groups.sort_by_key(|(size, paths)| std::cmp::Reverse(size * (paths.len() as u64 - 1)));
+        let wasted: u64 = groups.iter().map(|(size, paths)| size * (paths.len() as u64 - 1)).sum();
+
+        Ok(Report {
+            summary: format!(
+                "{} group(s) of identical files, {} in redundant copies",
+                groups.len(),
+                humansize::format_size(wasted, humansize::BINARY)
+            ),
+            sections: groups
+                .into_iter()
+                .map(|(size, mut paths)| {
+                    paths.sort();
+                    Section {
+                        heading: format!(
+                            "{} copies of {}",
+                            paths.len(),
+                            humansize::format_size(size, humansize::BINARY)
+                        ),
+                        items: paths
+                            .into_iter()
+                            .map(|path| Item {
+                                text: path.clone(),
+                                file: Some(path),
+                                ..Item::default()
+                            })
+                            .collect(),
+                    }
+                })
+                .collect(),
+            exports: Vec::new(),
+        })
+    }
+}
+
+/// Finds the references to a class or method name, showing which files and archives renaming
+/// it would touch.
+pub struct RenameImpact;
+
+#[async_trait]
+impl Analysis for RenameImpact {
+    fn name(&self) -> &str {
+        "Rename Impact"
+    }
+
+    fn description(&self) -> &str {
+        "Find every reference to a class or method name in scripts, configs, prefabs and \
+         layouts, grouped by the archive each file comes from"
+    }
+
+    fn prompt(&self) -> Option<InputPrompt> {
+        Some(InputPrompt {
+            title: "Rename Impact",
+            label: "Class or method name",
+            hint: "SCR_BaseGameMode",
+        })
+    }
+
+    async fn run(
+        &self,
+        workspace: &Workspace,
+        input: Option<&[u8]>,
+    ) -> Result<Report, AnalysisError> {
+        let name = String::from_utf8_lossy(input.unwrap_or_default()).trim().to_string();
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("{name:?} is not a class or method name").into());
+        }
+
+        // Matches are textual, so a name is only found as a whole word, never as part of a
+        // longer identifier
+        let name_pattern = regex::escape(&name);
+        let searcher =
+            Searcher::new(regex::bytes::Regex::new(&format!(r"\b{name_pattern}\b"))?).context(0);
+        let declaration = regex::Regex::new(&format!(r"\bclass\s+{name_pattern}\b"))?;
+
+        // The referencing files each archive provides, in the archives' priority order
+        let mut by_layer: Vec<Vec<(String, Vec<LineMatch>)>> =
+            vec![Vec::new(); workspace.layers.len()];
+        for file in workspace.files().await {
+            if !matches!(
+                detect_type(file.as_str(), &[]),
+                EntryType::Script | EntryType::Config | EntryType::Prefab | EntryType::Layout
+            ) {
+                continue;
+            }
+
+            let Some(data) = workspace.read(&file).await else {
+                continue;
+            };
+            let found = searcher.search_reader(&mut data.as_slice())?;
+            if found.is_empty() {
+                continue;
+            }
+
+            let provider =
+                workspace.layers.iter().position(|layer| layer.file_size(file.as_str()).is_some());
+            match provider {
+                Some(layer) => by_layer[layer].push((file.as_str().to_string(), found)),
+                None => warn!(file = file.as_str(), "no archive provides file"),
+            }
+        }
+
+        let mut declarations = Section { heading: "Declarations".to_string(), items: Vec::new() };
+        let mut sections = Vec::new();
+        let mut affected_files = Vec::new();
+        for (layer, mut files) in workspace.layers.iter().zip(by_layer) {
+            if files.is_empty() {
+                continue;
+            }
+
+            files.sort_by(|a, b| a.0.cmp(&b.0));
+            let mut items = Vec::new();
+            for (file, found) in &files {
+                for found in found {
+                    let item = Item {
+                        file: Some(file.clone()),
+                        line: Some(found.line),
+                        text: found.text.trim().to_string(),
+                        detail: None,
+                    };
+                    if declaration.is_match(&found.text) {
+                        declarations.items.push(item.clone());
+                    }
+                    items.push(item);
+                }
+            }
+            sections.push(Section {
+                heading: format!("{} ({} file(s))", layer.name, files.len()),
+                items,
+            });
+            affected_files.extend(files.into_iter().map(|(file, _)| file));
+        }
+
+        let references: usize = sections.iter().map(|section| section.items.len()).sum();
+        let summary = format!(
+            "{references} reference(s) to {name} in {} file(s) across {} archive(s)",
+            affected_files.len(),
+            sections.len()
+        );
+        if !declarations.items.is_empty() {
+            sections.insert(0, declarations);
+        }
+
+        let mut exports = Vec::new();
+        if !affected_files.is_empty() {
+            affected_files.sort();
+            let mut file_list = affected_files.join("\n");
+            file_list.push('\n');
+            exports.push(Export {
+                label: "Export File List...".to_string(),
+                file_name: format!("{name}_references.txt"),
+                data: file_list.into_bytes(),
+            });
+        }
+
+        Ok(Report { summary, sections, exports })
+    }
+}
@@ -0,0 +1,159 @@
+//! Analyses which inspect the whole workspace and produce a report.
+//!
+//! Each analysis implements [`Analysis`] and is listed in the Analyses menu once added to
+//! the app's [`AnalysisRegistry`]. The built-in analyses live in [`builtin`]; others can be
+//! added with [`crate::EnfusionToolsApp::register_analysis`].
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use enfusion_pak::vfs::async_vfs::AsyncVfsPath;
+
+pub mod builtin;
+
+use crate::shutdown;
+pub use crate::task::ArchiveLayer;
+use crate::task::all_files;
+use crate::task::read_file_data;
+
+pub type AnalysisError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The loaded files an analysis runs against.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Every loaded archive merged into one filesystem.
+    pub root: AsyncVfsPath,
+    /// The individual archives, highest priority first.
+    pub layers: Vec<ArchiveLayer>,
+}
+
+impl Workspace {
+    /// Every file in the workspace.
+    pub async fn files(&self) -> Vec<AsyncVfsPath> {
+        all_files(self.root.clone()).await
+    }
+
+    /// Reads a file's contents, or returns `None` if it can't be read. Always returns
+    /// `None` once the app starts closing so analyses wind down quickly.
+    pub async fn read(&self, file: &AsyncVfsPath) -> Option<Vec<u8>> {
+        if shutdown::requested() {
+            return None;
+        }
+
+        read_file_data(file.clone()).await
+    }
+}
+
+/// A file the user picks before an analysis runs, such as a rules file.
+#[derive(Debug, Clone, Copy)]
+pub struct InputFile {
+    /// File dialog title.
+    pub title: &'static str,
+    pub filter_name: &'static str,
+    pub extensions: &'static [&'static str],
+}
+
+/// Text the user types before an analysis runs, such as a name to look for.
+#[derive(Debug, Clone, Copy)]
+pub struct InputPrompt {
+    /// Window title.
+    pub title: &'static str,
+    /// Shown above the text box.
+    pub label: &'static str,
+    /// Placeholder shown while the text box is empty.
+    pub hint: &'static str,
+}
+
+#[async_trait]
+pub trait Analysis: Send + Sync {
+    /// Name shown in the Analyses menu and on the report tab.
+    fn name(&self) -> &str;
+
+    /// Shown when hovering the analysis in the menu.
+    fn description(&self) -> &str;
+
+    /// A file to ask the user for before running. Its contents are passed to
+    /// [`Analysis::run`].
+    fn input(&self) -> Option<InputFile> {
+        None
+    }
+
+    /// Text to ask the user for before running, in place of [`Analysis::input`]. It's passed
+    /// to [`Analysis::run`] as UTF-8.
+    fn prompt(&self) -> Option<InputPrompt> {
+        None
+    }
+
+    async fn run(
+        &self,
+        workspace: &Workspace,
+        input: Option<&[u8]>,
+    ) -> Result<Report, AnalysisError>;
+}
+
+/// The results of an analysis.
+#[derive(Debug, Default, Clone)]
+pub struct Report {
+    /// One line overview shown above the sections.
+    pub summary: String,
+    pub sections: Vec<Section>,
+    /// Forms the report can be saved in, e.g. a CSV of the full results.
+    pub exports: Vec<Export>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub heading: String,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Item {
+    /// Workspace file the item is about, which can be opened from the report.
+    pub file: Option<String>,
+    /// 1-based line within `file`.
+    pub line: Option<usize>,
+    pub text: String,
+    /// Extra context shown in a monospace font, e.g. the offending source line.
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// Button label.
+    pub label: String,
+    /// Suggested name for the saved file.
+    pub file_name: String,
+    pub data: Vec<u8>,
+}
+
+/// The analyses available in the Analyses menu, in menu order.
+#[derive(Clone)]
+pub struct AnalysisRegistry {
+    analyses: Vec<Arc<dyn Analysis>>,
+}
+
+impl Default for AnalysisRegistry {
+    fn default() -> Self {
+        Self {
+            analyses: vec![
+                Arc::new(builtin::DeprecatedApis),
+                Arc::new(builtin::UnreferencedFiles),
+                Arc::new(builtin::LocalizationCoverage),
+                Arc::new(builtin::ArchiveConflicts),
+                Arc::new(builtin::DuplicateFiles),
+                Arc::new(builtin::RenameImpact),
+            ],
+        }
+    }
+}
+
+impl AnalysisRegistry {
+    pub fn register(&mut self, analysis: impl Analysis + 'static) {
+        self.analyses.push(Arc::new(analysis));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Analysis>> {
+        self.analyses.iter()
+    }
+}
@@ -1,19 +1,85 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::mpsc;
 
+use cfg_parser::units::ValueRules;
+use egui::Key;
+use egui::KeyboardShortcut;
+use egui::Modifiers;
 use egui_dock::DockArea;
 use egui_dock::DockState;
 use egui_dock::Style;
 use egui_ltreeview::TreeViewState;
+use enfusion_pak::entry_type::EntryType;
 use enfusion_pak::vfs::VfsPath;
 use enfusion_pak::vfs::async_vfs::AsyncVfsPath;
+use enfusion_search::CancellationToken;
+use enfusion_search::SearchScope;
+use enfusion_search::text::decode_text;
 use tracing::debug;
 use tracing::error;
+use tracing::info;
+use tracing::warn;
 
+use crate::analysis::Analysis;
+use crate::analysis::AnalysisRegistry;
+use crate::analysis::Workspace;
+use crate::audio;
+use crate::audio::AudioUpdate;
+use crate::audio::Player;
+use crate::binary::hexdump;
+use crate::crash;
+use crate::dialogs;
+use crate::dialogs::DialogKind;
+use crate::diff;
+use crate::events::EventBus;
+use crate::events::Subscriber;
+use crate::events::WorkspaceEvent;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::game_install;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::game_install::Game;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::game_install::GameInstall;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::game_install::WorkshopMod;
+#[cfg(target_arch = "wasm32")]
+use crate::pak_wrapper::fs_access;
+#[cfg(target_arch = "wasm32")]
+use crate::pak_wrapper::remote;
+use crate::palette;
+use crate::palette::Palette;
+use crate::palette::PaletteColor;
+use crate::palette::PaletteKind;
+use crate::palette::PaletteSettings;
+use crate::permalink;
+use crate::permalink::PakSetHash;
+use crate::permalink::Permalink;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::plugin;
+use crate::preview::Previewer;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::profile;
+use crate::search::PastSearch;
+use crate::search::SearchHistory;
+use crate::search::SearchMode;
+use crate::search::SearchOptions;
+use crate::search::SearchQuery;
+use crate::search::SearchedFiles;
+use crate::settings;
+#[cfg(target_arch = "wasm32")]
+use crate::share;
+#[cfg(target_arch = "wasm32")]
+use crate::share::ShareLink;
+use crate::shutdown;
+use crate::syntax;
+use crate::task::ArchiveLayer;
 use crate::task::BackgroundTask;
 use crate::task::BackgroundTaskMessage;
+use crate::task::ComparisonBuild;
+use crate::task::FileLoad;
 use crate::task::FileName;
 use crate::task::FileReference;
 use crate::task::FullPath;
@@ -21,11 +87,35 @@ use crate::task::SearchId;
 use crate::task::execute;
 use crate::task::process_background_requests;
 use crate::task::start_background_thread;
+use crate::texture;
+use crate::ui::help::Tour;
+use crate::ui::tab::AnalysisData;
+use crate::ui::tab::AudioPreviewData;
+use crate::ui::tab::DetachedTab;
 use crate::ui::tab::DiffData;
+use crate::ui::tab::EditorContents;
 use crate::ui::tab::EditorData;
+use crate::ui::tab::HexViewData;
+use crate::ui::tab::HistoryData;
+use crate::ui::tab::ImagePreviewData;
+use crate::ui::tab::Provider;
+use crate::ui::tab::ProvidersData;
 use crate::ui::tab::SearchData;
 use crate::ui::tab::TabKind;
 use crate::ui::tab::ToolsTabViewer;
+use crate::ui::tab::save_export;
+use crate::ui::tree::QuickFilter;
+use crate::ui::view_state::SavedViews;
+use crate::undo::Command;
+use crate::undo::FileFilter;
+use crate::undo::UndoStack;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::update;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::update::Release;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::update::UpdateStatus;
+use crate::vfs_ext::normalize_path;
 
 #[derive(Debug)]
 pub struct TreeNode {
@@ -34,6 +124,13 @@ pub struct TreeNode {
     pub title: String,
     pub close_count: usize,
     pub vfs_path: VfsPath,
+    /// For directories, the number of files beneath this node (recursively).
+    pub descendant_files: usize,
+    /// For directories, the total decompressed size of all files beneath this node.
+    /// For files, the file's own decompressed size.
+    pub total_size: u64,
+    /// For files, the kind of content guessed from the file's extension.
+    pub entry_type: Option<EntryType>,
 }
 
 pub(crate) type KnownPaths = HashMap<(FullPath, FileName), VfsPath>;
@@ -47,17 +144,129 @@ pub(crate) struct AppInternalData {
     pub(crate) overlay_fs: Option<VfsPath>,
     pub(crate) async_overlay_fs: Option<AsyncVfsPath>,
     pub(crate) known_file_paths: Arc<KnownPaths>,
-    pub(crate) file_path_set: Arc<HashSet<String>>,
+    pub(crate) file_path_set: Arc<HashSet<Arc<str>>>,
+    /// Archives mounted in the overlay, highest priority first.
+    pub(crate) layers: Vec<ArchiveLayer>,
+    /// Other builds loaded for editor tabs to compare their files against.
+    pub(crate) comparison_builds: Vec<ComparisonBuild>,
+    pub(crate) analyses: AnalysisRegistry,
+    /// Consulted in order before showing a file as text or a hexdump.
+    pub(crate) previewers: Vec<Arc<dyn Previewer>>,
+    /// Which units structured config views show values in.
+    pub(crate) value_rules: ValueRules,
 
     pub(crate) opened_file_text: String,
     pub(crate) file_filter: String,
+    pub(crate) quick_filter: Option<QuickFilter>,
+    /// The filter the tree was last filtered with, which undoing a filter change returns to.
+    pub(crate) applied_filter: FileFilter,
+    pub(crate) undo_stack: UndoStack<Command>,
+
+    /// Workspace changes waiting to be delivered to tabs and other panels.
+    pub(crate) events: EventBus,
 
     pub(crate) next_search_query_id: SearchId,
+    /// File and line from the last opened permalink, applied to the file's editor tab once
+    /// it loads.
+    pending_link_line: Option<(String, usize)>,
+    /// Tabs popped out of the dock into their own windows.
+    pub(crate) detached_tabs: Vec<DetachedTab>,
+    /// Detached tab window to bring to the front on the next frame.
+    focus_viewport: Option<egui::ViewportId>,
+    next_detached_tab_id: usize,
     pub(crate) tree_view_state: TreeViewState<usize>,
     pub(crate) tree: Vec<TreeNode>,
     pub(crate) filtered_tree: Option<Vec<TreeNode>>,
     pub(crate) open_nodes: Vec<bool>,
     pub(crate) dir_count: usize,
+
+    /// Game installs found on this machine.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) game_installs: Vec<GameInstall>,
+    /// Downloaded workshop mods found on this machine.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) workshop_mods: Vec<WorkshopMod>,
+
+    /// Names of the files that can be reopened from the previous session.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) last_workspace: Vec<String>,
+    /// URLs the loaded archives were fetched from, which share links point to. Empty if
+    /// they were opened from disk.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) archive_urls: Vec<String>,
+    /// Share link whose archives are loading, and whose file is opened once they have.
+    #[cfg(target_arch = "wasm32")]
+    pending_share_link: Option<ShareLink>,
+    /// Contents of the window for opening archives from URLs, while it's shown.
+    #[cfg(target_arch = "wasm32")]
+    archive_urls_input: Option<String>,
+
+    /// Why the last query couldn't be searched for, shown beside the search box.
+    search_error: Option<String>,
+
+    /// Analysis waiting on its [`Analysis::prompt`], and the text typed so far.
+    analysis_prompt: Option<(Arc<dyn Analysis>, String)>,
+
+    /// The user asked to close the window, which is waiting for file writes to finish.
+    #[cfg(not(target_arch = "wasm32"))]
+    closing: bool,
+
+    /// A crash report waiting to be saved or dismissed.
+    crash_report: Option<String>,
+
+    /// The audio preview playing, or paused. Only one plays at a time.
+    pub(crate) audio_player: Option<Player>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    update: UpdateStatus,
+}
+
+impl AppInternalData {
+    /// Stops anything `tab` was doing, then records it being closed so it can be reopened.
+    pub(crate) fn close_tab(&mut self, tab: &mut TabKind) {
+        if let TabKind::AudioPreview(audio) = tab {
+            if let Some(decoding) = audio.decoding.take() {
+                decoding.cancel();
+                audio.error = Some("Decoding stopped when the tab was closed".to_string());
+            }
+            let path = audio.opened_file.as_str();
+            if self.audio_player.as_ref().is_some_and(|player| player.path == path) {
+                self.audio_player = None;
+            }
+        }
+        self.undo_stack.record(Command::CloseTab(tab.clone()));
+    }
+
+    /// The loaded archives, for crash reports.
+    fn workspace_summary(&self) -> String {
+        let mut summary =
+            format!("{} archives, {} files\n", self.layers.len(), self.file_path_set.len());
+        for layer in &self.layers {
+            summary.push_str(&layer.name);
+            summary.push('\n');
+        }
+        summary
+    }
+
+    /// Identifies the loaded archives in permalinks.
+    pub(crate) fn pak_set(&self) -> PakSetHash {
+        PakSetHash::new(&self.layers, self.known_file_paths.len())
+    }
+
+    /// A link to `path` (and optionally a line in it) in the loaded archives.
+    pub(crate) fn permalink(&self, path: &str, line: Option<usize>) -> Permalink {
+        Permalink { pak_set: self.pak_set(), path: path.to_string(), line }
+    }
+
+    /// A share link opening `path` (and optionally a line in it) from the same hosted
+    /// archives, or `None` if the archives weren't fetched from URLs.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn share_link(&self, path: &str, line: Option<usize>) -> Option<ShareLink> {
+        if self.archive_urls.is_empty() {
+            return None;
+        }
+        Some(ShareLink { archives: self.archive_urls.clone(), file: Some(path.to_string()), line })
+    }
 }
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
@@ -76,6 +285,46 @@ pub struct EnfusionToolsApp {
     pub(crate) opened_file_path: Option<String>,
 
     pub(crate) search_query: String,
+
+    /// Restrict workspace search to the files visible in the filtered tree.
+    pub(crate) search_filtered_only: bool,
+
+    /// How the search query is matched.
+    pub(crate) search_options: SearchOptions,
+
+    /// Recently run searches, which can be run again from the search box's history menu.
+    pub(crate) search_history: SearchHistory,
+
+    /// Filters, scroll positions and expanded groups of recent search and diff tabs.
+    pub(crate) saved_views: SavedViews,
+
+    /// Directory each kind of file dialog was last used in.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) dialog_directories: HashMap<DialogKind, PathBuf>,
+
+    /// Directories of the workshop mods to load on top of game data, in load order.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) selected_mods: Vec<PathBuf>,
+
+    /// Reformat minified text configs when opening or diffing them.
+    pub(crate) pretty_print_configs: bool,
+
+    /// Colors of diffs and search matches.
+    pub(crate) palette: PaletteSettings,
+
+    /// The value rules file loaded from the Edit menu, used instead of the default rules.
+    pub(crate) value_rules: Option<String>,
+
+    /// Record a local performance profile which can be saved from the File menu.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) profiling_enabled: bool,
+
+    /// Ask GitHub for the latest release when the app starts. Off unless the user turns it on.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) check_for_updates: bool,
+
+    /// Whether the getting-started tour is still to be shown, and where it's up to.
+    pub(crate) tour: Tour,
 }
 
 impl Default for EnfusionToolsApp {
@@ -95,17 +344,66 @@ impl Default for EnfusionToolsApp {
                 async_overlay_fs: None,
                 opened_file_text: "".to_string(),
                 file_filter: "".to_string(),
+                quick_filter: None,
+                applied_filter: FileFilter::default(),
+                undo_stack: UndoStack::default(),
                 known_file_paths: Default::default(),
                 file_path_set: Default::default(),
+                layers: Vec::new(),
+                comparison_builds: Vec::new(),
+                analyses: AnalysisRegistry::default(),
+                previewers: Vec::new(),
+                value_rules: ValueRules::default(),
+                events: EventBus::default(),
                 next_search_query_id: SearchId(0),
+                pending_link_line: None,
+                detached_tabs: Vec::new(),
+                focus_viewport: None,
+                next_detached_tab_id: 0,
                 tree_view_state: TreeViewState::default(),
                 tree: Default::default(),
                 dir_count: 0,
                 filtered_tree: None,
                 open_nodes: vec![],
+                #[cfg(not(target_arch = "wasm32"))]
+                game_installs: Vec::new(),
+                #[cfg(not(target_arch = "wasm32"))]
+                workshop_mods: Vec::new(),
+                #[cfg(target_arch = "wasm32")]
+                last_workspace: Vec::new(),
+                #[cfg(target_arch = "wasm32")]
+                archive_urls: Vec::new(),
+                #[cfg(target_arch = "wasm32")]
+                pending_share_link: None,
+                #[cfg(target_arch = "wasm32")]
+                archive_urls_input: None,
+                search_error: None,
+                analysis_prompt: None,
+                #[cfg(not(target_arch = "wasm32"))]
+                closing: false,
+                crash_report: None,
+                audio_player: None,
+                #[cfg(not(target_arch = "wasm32"))]
+                update: UpdateStatus::default(),
             },
             opened_file_path: None,
             search_query: "".to_string(),
+            search_filtered_only: false,
+            search_options: SearchOptions::default(),
+            search_history: SearchHistory::default(),
+            saved_views: SavedViews::default(),
+            pretty_print_configs: true,
+            palette: PaletteSettings::default(),
+            value_rules: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            dialog_directories: HashMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            selected_mods: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            profiling_enabled: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            check_for_updates: false,
+            tour: Tour::default(),
         }
     }
 }
@@ -127,14 +425,40 @@ impl EnfusionToolsApp {
         let (task_queue, maybe_task_queue_receiver) =
             start_background_thread(app.internal.inbox.sender());
 
+        settings::set_pretty_print_configs(app.pretty_print_configs);
+        settings::set_palette(app.palette.palette());
+        app.internal.crash_report = crash::saved_report();
+        if let Some(rules) = &app.value_rules {
+            match ValueRules::parse(rules) {
+                Ok(rules) => app.internal.value_rules = rules,
+                Err(e) => error!(%e, "saved value rules are invalid, using the defaults"),
+            }
+        }
+
         #[cfg(not(target_arch = "wasm32"))]
         {
+            profile::set_enabled(app.profiling_enabled);
+            if app.check_for_updates {
+                app.start_update_check();
+            }
+            app.internal.game_installs = game_install::find_installs();
+            app.internal.workshop_mods = game_install::find_workshop_mods();
+
+            if let Some(dir) = plugin::plugin_dir() {
+                for native_plugin in plugin::load_plugins(&dir) {
+                    if let Some(analysis) = native_plugin.analysis() {
+                        app.register_analysis(analysis);
+                    }
+                    app.internal.previewers.push(native_plugin);
+                }
+            }
+
             if !app.file_paths.is_empty() {
                 let mut pak_file_paths = Vec::new();
                 for file in &app.file_paths {
                     let path = std::path::PathBuf::from(file);
                     let file_ref = FileReference(path);
-                    if file_ref.0.exists() && file_ref.has_supported_extension() {
+                    if file_ref.0.exists() && file_ref.is_loadable() {
                         pak_file_paths.push(file_ref);
                     }
                 }
@@ -145,9 +469,23 @@ impl EnfusionToolsApp {
             }
         }
 
+        #[cfg(target_arch = "wasm32")]
+        if fs_access::is_supported() {
+            let inbox = app.internal.inbox.sender();
+            execute(async move {
+                let names = fs_access::last_workspace_names().await;
+                let _ = inbox.send(BackgroundTaskMessage::LastWorkspaceFound(names));
+            });
+        }
+
         app.internal.task_queue = Some(task_queue);
         app.internal.task_queue_rx = maybe_task_queue_receiver;
 
+        #[cfg(target_arch = "wasm32")]
+        if let Some(link) = share::from_page() {
+            app.open_share_link(link);
+        }
+
         app
     }
 
@@ -182,6 +520,8 @@ impl EnfusionToolsApp {
                         let old_async_overlay =
                             self.internal.async_overlay_fs.replace(loaded_files.async_overlay_fs);
                         let old_tree = std::mem::take(&mut self.internal.tree);
+                        let old_layers =
+                            std::mem::replace(&mut self.internal.layers, loaded_files.layers);
 
                         std::thread::spawn(move || {
                             drop(old_known);
@@ -189,6 +529,7 @@ impl EnfusionToolsApp {
                             drop(old_overlay);
                             drop(old_async_overlay);
                             drop(old_tree);
+                            drop(old_layers);
                         });
                     }
                     #[cfg(target_arch = "wasm32")]
@@ -205,6 +546,8 @@ impl EnfusionToolsApp {
                         let old_async_overlay =
                             self.internal.async_overlay_fs.replace(loaded_files.async_overlay_fs);
                         let old_tree = std::mem::take(&mut self.internal.tree);
+                        let old_layers =
+                            std::mem::replace(&mut self.internal.layers, loaded_files.layers);
 
                         wasm_bindgen_futures::spawn_local(async move {
                             drop(old_known);
@@ -212,6 +555,7 @@ impl EnfusionToolsApp {
                             drop(old_overlay);
                             drop(old_async_overlay);
                             drop(old_tree);
+                            drop(old_layers);
                         });
                     }
 
@@ -223,49 +567,98 @@ impl EnfusionToolsApp {
                         .fold(0, |accum, node| if node.is_dir { accum + 1 } else { accum });
                     self.internal.open_nodes.clear();
                     self.internal.open_nodes.push(true);
+
+                    let pak_set = self.internal.pak_set();
+                    self.internal.events.publish(WorkspaceEvent::WorkspaceLoaded { pak_set });
+                    crash::set_workspace_summary(self.internal.workspace_summary());
+
+                    #[cfg(target_arch = "wasm32")]
+                    {
+                        let link = self.internal.pending_share_link.take();
+                        self.internal.archive_urls =
+                            link.as_ref().map(|link| link.archives.clone()).unwrap_or_default();
+                        if let Some(ShareLink { file: Some(file), line, .. }) = link
+                            && !self.open_linked_file(file.clone(), line)
+                        {
+                            error!(%file, "share link points to a file its archives don't have");
+                        }
+                    }
                 }
                 Err(e) => {
                     error!(?e, "failed to load files");
                 }
             },
             BackgroundTaskMessage::SearchResult(search_id, search_result) => {
-                for tab in self.dock_state.iter_all_tabs_mut() {
-                    let TabKind::SearchResults(data) = tab.1 else {
-                        continue;
-                    };
-
-                    if data.id == search_id {
-                        data.results.push(search_result);
-                        break;
-                    }
+                self.internal
+                    .events
+                    .publish(WorkspaceEvent::SearchResult(search_id, search_result));
+            }
+            BackgroundTaskMessage::SearchProgress(search_id, progress) => {
+                self.internal.events.publish(WorkspaceEvent::SearchProgress(search_id, progress));
+            }
+            BackgroundTaskMessage::SearchComplete(search_id, progress, end) => {
+                self.internal
+                    .events
+                    .publish(WorkspaceEvent::SearchComplete(search_id, progress, end));
+            }
+            BackgroundTaskMessage::AnalysisFinished(name, result) => match result {
+                Ok(report) => {
+                    self.dock_state.main_surface_mut().push_to_first_leaf(TabKind::Analysis(
+                        AnalysisData { title: name, report },
+                    ));
                 }
+                Err(e) => {
+                    error!(analysis = name, %e, "analysis failed");
+                }
+            },
+            BackgroundTaskMessage::FileDataLoaded(file, items, FileLoad::Reload) => {
+                let contents = editor_text(&self.internal.previewers, &file, &items);
+                self.internal.events.publish(WorkspaceEvent::ContentsReloaded {
+                    path: file.as_str().to_string(),
+                    pak_set: self.internal.pak_set(),
+                    contents,
+                });
             }
-            BackgroundTaskMessage::FileDataLoaded(file, items) => {
-                // Try decompiling rapified config.bin files
-                if cfg_parser::is_rapified(&items)
-                    && let Ok(rap) = cfg_parser::RapFile::parse(&items)
-                {
-                    let decompiled = cfg_parser::decompile(&rap);
-                    let surface = self.dock_state.main_surface_mut();
-                    surface.push_to_first_leaf(TabKind::Editor(EditorData {
-                        title: format!("{} - Decompiled", file.filename()),
-                        opened_file: file,
-                        contents: decompiled,
-                    }));
+            BackgroundTaskMessage::FileDataLoaded(
+                file,
+                items,
+                load @ (FileLoad::Open | FileLoad::OpenNewTab),
+            ) => {
+                let linked_line = match self.internal.pending_link_line.take() {
+                    Some((path, line)) if path == file.as_str() => Some(line),
+                    pending => {
+                        self.internal.pending_link_line = pending;
+                        None
+                    }
+                };
+
+                if load == FileLoad::Open && self.focus_open_editor(&file, &items, linked_line) {
                     return;
                 }
 
-                // Try reading as text
-                let Ok(str_data) = String::from_utf8(items) else {
-                    return;
+                let path = file.as_str().to_string();
+                let pak_set = self.internal.pak_set();
+                let tab = match editor_contents(&self.internal.previewers, &file, &items) {
+                    Some((title, contents)) => TabKind::Editor(EditorData {
+                        title,
+                        opened_file: file,
+                        contents: EditorContents::Loaded(contents),
+                        last_viewed: None,
+                        pop_out_requested: false,
+                        cursor_line: None,
+                        linked_line,
+                        pak_set,
+                        outdated: false,
+                        comparison: None,
+                        structured: None,
+                    }),
+                    None => binary_tab(file, items, pak_set),
                 };
-
-                let surface = self.dock_state.main_surface_mut();
-                surface.push_to_first_leaf(TabKind::Editor(EditorData {
-                    title: file.filename(),
-                    opened_file: file,
-                    contents: str_data,
-                }));
+                self.dock_state.main_surface_mut().push_to_first_leaf(tab);
+                self.internal.events.publish(WorkspaceEvent::FileOpened { path });
+            }
+            BackgroundTaskMessage::AudioDecoded(file, decoding, update) => {
+                self.update_audio_preview(file, decoding, update);
             }
             BackgroundTaskMessage::FilesFiltered(filtered_tree) => {
                 self.internal.filtered_tree = Some(filtered_tree);
@@ -273,196 +666,1713 @@ impl EnfusionToolsApp {
             BackgroundTaskMessage::RequestOpenFile(vfs_path) => {
                 self.open_file(vfs_path);
             }
+            #[cfg(not(target_arch = "wasm32"))]
+            BackgroundTaskMessage::DialogDirectoryUsed(kind, directory) => {
+                self.dialog_directories.insert(kind, directory);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            BackgroundTaskMessage::UpdateChecked(result) => {
+                self.internal.update = match result {
+                    Ok(Some(release)) => {
+                        info!(version = %release.version, "a newer release is available");
+                        UpdateStatus::Available(release)
+                    }
+                    Ok(None) => UpdateStatus::UpToDate,
+                    Err(e) => {
+                        error!(%e, "failed to check for updates");
+                        UpdateStatus::Failed(e)
+                    }
+                };
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            BackgroundTaskMessage::UpdateStaged(release, result) => {
+                self.internal.update = match result {
+                    Ok(path) => UpdateStatus::Staged(release, path),
+                    Err(e) => {
+                        error!(%e, "failed to download update");
+                        UpdateStatus::Failed(e)
+                    }
+                };
+            }
+            #[cfg(target_arch = "wasm32")]
+            BackgroundTaskMessage::LastWorkspaceFound(names) => {
+                self.internal.last_workspace = names;
+            }
+            #[cfg(target_arch = "wasm32")]
+            BackgroundTaskMessage::SharedArchivesFetched(link, files) => {
+                if let Some(background_task_sender) = &self.internal.task_queue {
+                    self.internal.pending_share_link = Some(link);
+                    let _ = background_task_sender.send(BackgroundTask::LoadPakFiles(files));
+                }
+            }
             BackgroundTaskMessage::FilesDiffed(diff_results) => match diff_results {
-                Ok(results) => {
-                    let surface = self.dock_state.main_surface_mut();
-                    surface.push_to_first_leaf(TabKind::Diff(DiffData {
-                        modified: results,
-                        modified_filtered: Default::default(),
-                        path_filter: Default::default(),
-                    }));
+                Ok((pak_set, results)) => {
+                    let mut tab = TabKind::Diff(DiffData::new(results, pak_set));
+                    tab.restore_view(&self.saved_views);
+                    self.dock_state.main_surface_mut().push_to_first_leaf(tab);
                 }
                 Err(e) => {
                     error!(?e, "failed to load files");
                 }
             },
+            BackgroundTaskMessage::ComparisonBuildLoaded(build) => match build {
+                Ok(build) => {
+                    info!(name = %build.name, "loaded build to compare");
+                    self.internal.comparison_builds.push(build);
+                }
+                Err(e) => {
+                    error!(?e, "failed to load build to compare");
+                }
+            },
+            BackgroundTaskMessage::ValueTracked(history) => {
+                let title = format!("History: {}", history.property());
+                self.dock_state
+                    .main_surface_mut()
+                    .push_to_first_leaf(TabKind::History(HistoryData { title, history }));
+            }
+            BackgroundTaskMessage::ValueRulesLoaded(text) => match ValueRules::parse(&text) {
+                Ok(rules) => {
+                    self.internal.value_rules = rules;
+                    self.value_rules = Some(text);
+                }
+                Err(e) => {
+                    error!(%e, "failed to read value rules");
+                }
+            },
         }
     }
 
-    pub(crate) fn open_file(&self, file: VfsPath) {
-        if !file.is_file().unwrap_or_default() {
+    /// Opens, or fills in, the preview tab of an audio file being decoded.
+    fn update_audio_preview(
+        &mut self,
+        file: VfsPath,
+        decoding: CancellationToken,
+        update: AudioUpdate,
+    ) {
+        let docked = self.dock_state.iter_all_tabs_mut().map(|(_, tab)| tab);
+        let detached = self.internal.detached_tabs.iter_mut().map(|detached| &mut detached.tab);
+        let decoded_by = |tab: &&mut TabKind| {
+            matches!(tab, TabKind::AudioPreview(audio)
+                if audio.decoding.as_ref().is_some_and(|token| token.same_as(&decoding)))
+        };
+        if let Some(TabKind::AudioPreview(audio)) = docked.chain(detached).find(decoded_by) {
+            apply_audio_update(audio, update);
             return;
         }
 
-        if let Some(task_queue) = self.internal.task_queue.as_ref() {
-            debug!("sending task");
-            // Get the async version of this file
-            let _ = task_queue.send(crate::task::BackgroundTask::LoadFileData(
-                file,
-                self.internal.async_overlay_fs.clone().expect("no async overlay FS?"),
-            ));
+        // Updates for a tab which was closed, or whose decode was stopped, are dropped
+        let format = match update {
+            AudioUpdate::Started(format) => Some(format),
+            AudioUpdate::Failed(_) => None,
+            AudioUpdate::Progress(..) | AudioUpdate::Finished { .. } => return,
+        };
+        let path = normalize_path(file.as_str());
+        let pak_set = self.internal.pak_set();
+        let shows_file = |tab: &TabKind| {
+            matches!(tab, TabKind::AudioPreview(audio)
+                if audio.pak_set == pak_set && normalize_path(audio.opened_file.as_str()) == path)
+        };
+        // Opening a file which is already open brings its tab forward instead
+        if let Some(found) = self.dock_state.find_tab_from(shows_file) {
+            decoding.cancel();
+            self.dock_state.set_active_tab(found);
+            return;
+        }
+        if let Some(detached) =
+            self.internal.detached_tabs.iter().find(|detached| shows_file(&detached.tab))
+        {
+            decoding.cancel();
+            self.internal.focus_viewport = Some(detached.viewport_id);
+            return;
         }
-    }
-}
 
-impl eframe::App for EnfusionToolsApp {
-    /// Called by the frame work to save state before shutdown.
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        // Files which fail before decoding starts still get a tab, to show why
+        let mut audio = AudioPreviewData {
+            title: format!("{} - Audio", file.filename()),
+            opened_file: file,
+            format,
+            peaks: Vec::new(),
+            progress: 0.0,
+            clip: None,
+            truncated: false,
+            error: None,
+            decoding: format.map(|_| decoding),
+            pak_set,
+        };
+        if let AudioUpdate::Failed(error) = update {
+            audio.error = Some(error);
+        }
+        let path = audio.opened_file.as_str().to_string();
+        self.dock_state.main_surface_mut().push_to_first_leaf(TabKind::AudioPreview(audio));
+        self.internal.events.publish(WorkspaceEvent::FileOpened { path });
     }
 
-    /// Called each time the UI needs repainting, which may be many times per second.
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.internal.inbox.set_ctx(ctx);
+    /// Brings forward an editor tab already showing `file` from the loaded archives,
+    /// pointing it at `linked_line` if there is one. `data` is the file's contents, which
+    /// the tab takes if it had dropped its own. Returns whether there was such a tab.
+    fn focus_open_editor(
+        &mut self,
+        file: &VfsPath,
+        data: &[u8],
+        linked_line: Option<usize>,
+    ) -> bool {
+        let path = normalize_path(file.as_str());
+        let pak_set = self.internal.pak_set();
+        let shows_file = |tab: &TabKind| match tab {
+            TabKind::Editor(editor) => {
+                !editor.outdated && normalize_path(editor.opened_file.as_str()) == path
+            }
+            TabKind::HexView(hex) => {
+                hex.pak_set == pak_set && normalize_path(hex.opened_file.as_str()) == path
+            }
+            TabKind::ImagePreview(preview) => {
+                preview.pak_set == pak_set && normalize_path(preview.opened_file.as_str()) == path
+            }
+            _ => false,
+        };
 
-        // Process any background messages
-        if let Some(task_queue_rx) = self.internal.task_queue_rx.as_ref() {
-            process_background_requests(self.internal.inbox.sender(), task_queue_rx);
+        let docked = self.dock_state.iter_all_tabs_mut().map(|(_, tab)| tab);
+        let detached = self.internal.detached_tabs.iter_mut().map(|detached| &mut detached.tab);
+        let Some(tab) = docked.chain(detached).find(|tab| shows_file(tab)) else {
+            return false;
+        };
+        if let TabKind::Editor(editor) = tab {
+            if linked_line.is_some() {
+                editor.linked_line = linked_line;
+            }
+            if matches!(editor.contents, EditorContents::Evicted | EditorContents::Reloading) {
+                let contents = editor_text(&self.internal.previewers, file, data);
+                editor.contents = EditorContents::Loaded(contents);
+            }
         }
 
-        while let Some(message) = self.internal.inbox.read_without_ctx().next() {
-            self.process_message_from_background(message);
+        if let Some(found) = self.dock_state.find_tab_from(shows_file) {
+            self.dock_state.set_active_tab(found);
+        } else if let Some(detached) =
+            self.internal.detached_tabs.iter().find(|detached| shows_file(&detached.tab))
+        {
+            self.internal.focus_viewport = Some(detached.viewport_id);
         }
+        true
+    }
 
-        // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
-        // For inspiration and more examples, go to https://emilk.github.io/egui
+    /// Hands the events published while processing this frame's messages to every tab and
+    /// subscriber.
+    fn dispatch_events(&mut self) {
+        let Self { dock_state, internal, opened_file_path, .. } = self;
+        internal.events.dispatch(|event| {
+            if let WorkspaceEvent::FileOpened { path } = event {
+                *opened_file_path = Some(path.clone());
+            }
 
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            // The top panel is often a good place for a menu bar:
+            for (_, tab) in dock_state.iter_all_tabs_mut() {
+                tab.on_event(event);
+            }
+            for detached in &mut internal.detached_tabs {
+                detached.tab.on_event(event);
+            }
+        });
+    }
 
-            egui::MenuBar::new().ui(ui, |ui| {
-                // NOTE: no File->Quit on web pages!
-                let is_web = cfg!(target_arch = "wasm32");
-                if !is_web {
-                    ui.menu_button("File", |ui| {
-                        if ui.button("Quit").clicked() {
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                        }
+    /// Asks GitHub for the latest release on another thread.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_update_check(&mut self) {
+        self.internal.update = UpdateStatus::Checking;
+        let inbox = self.internal.inbox.sender();
+        std::thread::spawn(move || {
+            let result = update::check().map_err(|e| e.to_string());
+            let _ = inbox.send(BackgroundTaskMessage::UpdateChecked(result));
+        });
+    }
+
+    /// Downloads `release`'s binary next to this one on another thread.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_update_download(&mut self, release: Release) {
+        let Some(asset) = release.binary.clone() else {
+            return;
+        };
+        self.internal.update = UpdateStatus::Downloading(release.clone());
+        let inbox = self.internal.inbox.sender();
+        std::thread::spawn(move || {
+            let result = update::stage(&release, &asset).map_err(|e| e.to_string());
+            let _ = inbox.send(BackgroundTaskMessage::UpdateStaged(release, result));
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_update_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.check_for_updates, "Check for updates on startup")
+            .on_hover_text("Asks GitHub for the latest release each time the app starts");
+
+        let busy =
+            matches!(self.internal.update, UpdateStatus::Checking | UpdateStatus::Downloading(_));
+        if ui.add_enabled(!busy, egui::Button::new("Check for Updates Now")).clicked() {
+            self.start_update_check();
+        }
+        match &self.internal.update {
+            UpdateStatus::Checking => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Checking...");
+                });
+            }
+            UpdateStatus::UpToDate => {
+                ui.weak(format!("Version {} is the latest", update::Version::current()));
+            }
+            UpdateStatus::Failed(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Update failed: {e}"));
+            }
+            _ => {}
+        }
+        ui.separator();
+    }
+
+    /// A menu bar entry shown while a newer release is available, to read about and
+    /// download it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_update_notice(&mut self, ui: &mut egui::Ui) {
+        let release = match &self.internal.update {
+            UpdateStatus::Available(release)
+            | UpdateStatus::Downloading(release)
+            | UpdateStatus::Staged(release, _) => release.clone(),
+            _ => return,
+        };
+
+        let mut download = false;
+        let mut dismiss = false;
+        ui.menu_button(format!("Update to {}", release.version), |ui| {
+            ui.label(format!(
+                "Version {} is available. This is version {}.",
+                release.version,
+                update::Version::current()
+            ));
+            ui.hyperlink_to("Release Notes", &release.page_url);
+            match (&self.internal.update, &release.binary) {
+                (UpdateStatus::Staged(_, path), _) => {
+                    ui.label(format!(
+                        "Downloaded to {}. Close the app and run the new version, or rename \
+                         it over this one.",
+                        path.display()
+                    ));
+                }
+                (UpdateStatus::Downloading(_), _) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Downloading...");
                     });
-                    ui.add_space(16.0);
                 }
+                (_, Some(asset)) if asset.sha256().is_none() => {
+                    ui.weak(
+                        "The release doesn't list a SHA-256 for this platform's build, so it \
+                         can't be checked. Download it from the release notes instead.",
+                    );
+                }
+                (_, Some(asset)) => {
+                    let size = humansize::format_size(asset.size, humansize::BINARY);
+                    download = ui
+                        .button(format!("Download ({size})"))
+                        .on_hover_text("Saves the new version next to this one")
+                        .clicked();
+                }
+                (_, None) => {
+                    ui.weak("The release has no build for this platform.");
+                }
+            }
+            dismiss = ui.button("Dismiss").clicked();
+        });
 
-                egui::widgets::global_theme_preference_buttons(ui);
+        if download {
+            self.start_update_download(release);
+        } else if dismiss {
+            self.internal.update = UpdateStatus::NotChecked;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_profile_menu(&mut self, ui: &mut egui::Ui) {
+        if ui.checkbox(&mut self.profiling_enabled, "Record performance profile").changed() {
+            profile::set_enabled(self.profiling_enabled);
+        }
+
+        if ui
+            .add_enabled(self.profiling_enabled, egui::Button::new("Save performance profile..."))
+            .on_hover_text("Writes load, read and search timings to a local JSON file")
+            .clicked()
+        {
+            let task = rfd::AsyncFileDialog::new()
+                .set_title("Save Performance Profile")
+                .set_file_name("enfusion_tools_profile.json")
+                .add_filter("JSON", &["json"])
+                .save_file();
+            execute(async move {
+                let Some(handle) = task.await else {
+                    return;
+                };
+
+                let _write = shutdown::track_write();
+                if let Err(e) = profile::write_to(handle.path()) {
+                    error!(?e, "failed to write performance profile");
+                }
             });
-        });
+        }
 
-        self.show_file_tree(ctx);
+        ui.separator();
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.vertical(|ui| {
-                ui.horizontal(|ui| {
-                    if ui.button("Open Files").clicked() {
-                        let task = rfd::AsyncFileDialog::new()
-                            .add_filter("Supported archives", &["pak", "pbo"])
-                            .add_filter("PAK files", &["pak"])
-                            .add_filter("PBO files", &["pbo"])
-                            .pick_files();
-                        if let Some(background_task_sender) = self.internal.task_queue.clone() {
-                            execute(async move {
-                                let file = task.await;
-                                if let Some(mut files) = file {
-                                    #[cfg(target_arch = "wasm32")]
-                                    let _ =
-                                        background_task_sender.send(BackgroundTask::LoadPakFiles(
-                                            files
-                                                .drain(..)
-                                                .map(FileReference)
-                                                .filter(|f| f.has_supported_extension())
-                                                .collect(),
-                                        ));
-
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    let _ =
-                                        background_task_sender.send(BackgroundTask::LoadPakFiles(
-                                            files
-                                                .drain(..)
-                                                .map(|handle| {
-                                                    FileReference(handle.path().to_owned())
-                                                })
-                                                .filter(|f| f.has_supported_extension())
-                                                .collect(),
-                                        ));
-                                }
-                            });
-                        }
+    /// Asks the user for archives to load.
+    fn pick_files_to_open(&self) {
+        #[cfg(target_arch = "wasm32")]
+        if fs_access::is_supported() {
+            if let Some(background_task_sender) = self.internal.task_queue.clone() {
+                let inbox = self.internal.inbox.sender();
+                execute(async move {
+                    if let Some(files) = fs_access::pick_and_remember().await {
+                        let names = files.iter().map(FileReference::file_name).collect();
+                        let _ = inbox.send(BackgroundTaskMessage::LastWorkspaceFound(names));
+                        let _ = background_task_sender.send(BackgroundTask::LoadPakFiles(
+                            files.into_iter().filter(|f| f.has_supported_extension()).collect(),
+                        ));
                     }
-                    if ui.button("Diff Builds").clicked()
-                        && let Some(background_task_sender) = self.internal.task_queue.clone()
-                    {
-                        execute(async move {
-                            let base_files = rfd::AsyncFileDialog::new()
-                                .set_title("Choose Base Files")
-                                .pick_files()
-                                .await;
-                            if let Some(mut base_files) = base_files {
-                                let modified_files = rfd::AsyncFileDialog::new()
-                                    .set_title("Choose Changed Files")
-                                    .pick_files()
-                                    .await;
-                                if let Some(mut modified_files) = modified_files {
-                                    #[cfg(target_arch = "wasm32")]
-                                    let _ =
-                                        background_task_sender.send(BackgroundTask::DiffBuilds {
-                                            base: base_files.drain(..).map(FileReference).collect(),
-                                            modified: modified_files
-                                                .drain(..)
-                                                .map(FileReference)
-                                                .collect(),
-                                        });
-
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    let _ =
-                                        background_task_sender.send(BackgroundTask::DiffBuilds {
-                                            base: base_files
-                                                .drain(..)
-                                                .map(|handle| {
-                                                    FileReference(handle.path().to_owned())
-                                                })
-                                                .collect(),
-                                            modified: modified_files
-                                                .drain(..)
-                                                .map(|handle| {
-                                                    FileReference(handle.path().to_owned())
-                                                })
-                                                .collect(),
-                                        });
-                                }
-                            }
-                        });
-                    }
-                    ui.label("Search");
-                    let response = ui.text_edit_singleline(&mut self.search_query);
+                });
+            }
+            return;
+        }
+
+        let task = dialogs::archive_dialog(
+            DialogKind::Open,
+            self.dialog_directory(DialogKind::Open).as_deref(),
+        )
+        .pick_files();
+        if let Some(background_task_sender) = self.internal.task_queue.clone() {
+            #[cfg(not(target_arch = "wasm32"))]
+            let inbox = self.internal.inbox.sender();
+            execute(async move {
+                let file = task.await;
+                if let Some(mut files) = file {
+                    #[cfg(target_arch = "wasm32")]
+                    let _ = background_task_sender.send(BackgroundTask::LoadPakFiles(
+                        files
+                            .drain(..)
+                            .map(FileReference::from)
+                            .filter(|f| f.has_supported_extension())
+                            .collect(),
+                    ));
 
-                    if response.lost_focus()
-                        && response.ctx.input(|input| input.key_pressed(egui::Key::Enter))
+                    #[cfg(not(target_arch = "wasm32"))]
                     {
-                        debug!("Search requested");
-                        if let Some(task_queue) = &self.internal.task_queue
-                            && let Some(vfs_root) = self.internal.async_overlay_fs.clone()
-                        {
-                            debug!("Sending earch task");
-                            self.internal.opened_file_text.clear();
-                            let search_id = self.internal.next_search_query_id;
-                            self.internal.next_search_query_id.0 += 1;
-
-                            let _ = task_queue.send(BackgroundTask::PerformSearch(
-                                search_id,
-                                vfs_root,
-                                self.search_query.clone(),
+                        if let Some(directory) = dialogs::picked_directory(&files) {
+                            let _ = inbox.send(BackgroundTaskMessage::DialogDirectoryUsed(
+                                DialogKind::Open,
+                                directory,
                             ));
+                        }
+                        let _ = background_task_sender.send(BackgroundTask::LoadPakFiles(
+                            files
+                                .drain(..)
+                                .map(|handle| FileReference(handle.path().to_owned()))
+                                .filter(|f| f.has_supported_extension())
+                                .collect(),
+                        ));
+                    }
+                }
+            });
+        }
+    }
 
-                            let query = self.search_query.clone();
-                            self.dock_state.main_surface_mut().push_to_first_leaf(
-                                TabKind::SearchResults(SearchData {
-                                    tab_title: format!("{query} - Search Results"),
-                                    query: self.search_query.clone(),
-                                    id: search_id,
-                                    results: Default::default(),
-                                }),
-                            );
+    /// Offers to load the archives of each detected game install.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_load_game_data_button(&mut self, ui: &mut egui::Ui) {
+        let mut load = None;
+        let hover_text = |install: &GameInstall| {
+            let mods = self
+                .internal
+                .workshop_mods
+                .iter()
+                .filter(|workshop_mod| {
+                    workshop_mod.game == install.game
+                        && self.selected_mods.contains(&workshop_mod.path)
+                })
+                .count();
+            match mods {
+                0 => install.path.display().to_string(),
+                mods => format!("{} with {mods} mod(s)", install.path.display()),
+            }
+        };
+        match self.internal.game_installs.as_slice() {
+            [] => {}
+            [install] => {
+                if ui.button("Load Game Data").on_hover_text(hover_text(install)).clicked() {
+                    load = Some(install.clone());
+                }
+            }
+            installs => {
+                ui.menu_button("Load Game Data", |ui| {
+                    for install in installs {
+                        if ui
+                            .button(install.game.name())
+                            .on_hover_text(hover_text(install))
+                            .clicked()
+                        {
+                            load = Some(install.clone());
                         }
                     }
                 });
+            }
+        }
+
+        self.show_workshop_mods_menu(ui);
+
+        if let Some(install) = load
+            && let Some(task_queue) = self.internal.task_queue.as_ref()
+        {
+            // Earlier archives take precedence in the overlay, so mods go first
+            let mod_archives = self
+                .internal
+                .workshop_mods
+                .iter()
+                .filter(|workshop_mod| {
+                    workshop_mod.game == install.game
+                        && self.selected_mods.contains(&workshop_mod.path)
+                })
+                .flat_map(|workshop_mod| workshop_mod.archives.iter().cloned());
+            let archives = mod_archives.chain(install.archives()).map(FileReference).collect();
+            let _ = task_queue.send(BackgroundTask::LoadPakFiles(archives));
+        }
+    }
+
+    /// Lists workshop mods with their archives so they can be selected for overlaying on
+    /// top of the game's data.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_workshop_mods_menu(&mut self, ui: &mut egui::Ui) {
+        if self.internal.workshop_mods.is_empty() {
+            return;
+        }
+
+        let title = format!("Mods ({} selected)", self.selected_mods.len());
+        ui.menu_button(title, |ui| {
+            ui.label("Selected mods are loaded on top of the game data");
+            ui.separator();
+
+            for workshop_mod in &self.internal.workshop_mods {
+                ui.horizontal(|ui| {
+                    let mut selected = self.selected_mods.contains(&workshop_mod.path);
+                    if ui.checkbox(&mut selected, "").changed() {
+                        let before = self.selected_mods.clone();
+                        if selected {
+                            self.selected_mods.push(workshop_mod.path.clone());
+                        } else {
+                            self.selected_mods.retain(|path| path != &workshop_mod.path);
+                        }
+                        let after = self.selected_mods.clone();
+                        self.internal.undo_stack.record(Command::SelectMods { before, after });
+                    }
+
+                    egui::CollapsingHeader::new(format!(
+                        "{} ({})",
+                        workshop_mod.name,
+                        workshop_mod.game.name()
+                    ))
+                    .id_salt(&workshop_mod.path)
+                    .show(ui, |ui| {
+                        for archive in &workshop_mod.archives {
+                            let name = archive.strip_prefix(&workshop_mod.path).unwrap_or(archive);
+                            ui.label(name.display().to_string());
+                        }
+                    });
+                });
+            }
+        });
+    }
+
+    /// Asks the user for a base build and a changed build, then diffs them.
+    fn pick_builds_to_diff(&self) {
+        let Some(background_task_sender) = self.internal.task_queue.clone() else {
+            return;
+        };
+
+        let base_dir = self.dialog_directory(DialogKind::DiffBase);
+        let modified_dir = self.dialog_directory(DialogKind::DiffModified);
+        #[cfg(not(target_arch = "wasm32"))]
+        let inbox = self.internal.inbox.sender();
+        execute(async move {
+            let base_files = dialogs::archive_dialog(DialogKind::DiffBase, base_dir.as_deref())
+                .pick_files()
+                .await;
+            let Some(mut base_files) = base_files else {
+                return;
+            };
+
+            let modified_files =
+                dialogs::archive_dialog(DialogKind::DiffModified, modified_dir.as_deref())
+                    .pick_files()
+                    .await;
+            let Some(mut modified_files) = modified_files else {
+                return;
+            };
+
+            #[cfg(target_arch = "wasm32")]
+            let _ = background_task_sender.send(BackgroundTask::DiffBuilds {
+                base: base_files.drain(..).map(FileReference::from).collect(),
+                modified: modified_files.drain(..).map(FileReference::from).collect(),
+            });
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                for (kind, files) in [
+                    (DialogKind::DiffBase, &base_files),
+                    (DialogKind::DiffModified, &modified_files),
+                ] {
+                    if let Some(directory) = dialogs::picked_directory(files) {
+                        let _ =
+                            inbox.send(BackgroundTaskMessage::DialogDirectoryUsed(kind, directory));
+                    }
+                }
+
+                let _ = background_task_sender.send(BackgroundTask::DiffBuilds {
+                    base: base_files
+                        .drain(..)
+                        .map(|handle| FileReference(handle.path().to_owned()))
+                        .collect(),
+                    modified: modified_files
+                        .drain(..)
+                        .map(|handle| FileReference(handle.path().to_owned()))
+                        .collect(),
+                });
+            }
+        });
+    }
+
+    /// Hashes every file in the workspace, then asks where to save the manifest.
+    fn dump_manifest(&self) {
+        let Some(root) = self.internal.async_overlay_fs.clone() else {
+            return;
+        };
+
+        let layers = self.internal.layers.clone();
+        let file_paths = Arc::clone(&self.internal.file_path_set);
+        execute(async move {
+            info!(files = file_paths.len(), "hashing files for manifest");
+            if let Some(manifest) = diff::workspace_manifest(root, layers, file_paths).await {
+                save_export("Save Manifest".to_string(), "manifest.txt".to_string(), manifest)
+                    .await;
+            }
+        });
+    }
+
+    /// Asks for another build's archives and loads them next to the workspace.
+    fn pick_build_to_compare(&self) {
+        let Some(background_task_sender) = self.internal.task_queue.clone() else {
+            return;
+        };
+
+        let directory = self.dialog_directory(DialogKind::Compare);
+        #[cfg(not(target_arch = "wasm32"))]
+        let inbox = self.internal.inbox.sender();
+        execute(async move {
+            let files = dialogs::archive_dialog(DialogKind::Compare, directory.as_deref())
+                .pick_files()
+                .await;
+            let Some(mut files) = files else {
+                return;
+            };
+
+            #[cfg(target_arch = "wasm32")]
+            let _ = background_task_sender.send(BackgroundTask::LoadComparisonBuild(
+                files.drain(..).map(FileReference::from).collect(),
+            ));
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                if let Some(directory) = dialogs::picked_directory(&files) {
+                    let _ = inbox.send(BackgroundTaskMessage::DialogDirectoryUsed(
+                        DialogKind::Compare,
+                        directory,
+                    ));
+                }
+
+                let _ = background_task_sender.send(BackgroundTask::LoadComparisonBuild(
+                    files.drain(..).map(|handle| FileReference(handle.path().to_owned())).collect(),
+                ));
+            }
+        });
+    }
+
+    /// Asks for an unpacked mod folder and overlays it on the loaded archives. With `diff`,
+    /// the workspace is diffed against the overlaid result instead of being replaced by it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pick_folder_to_overlay(&self, diff: bool) {
+        let Some(background_task_sender) = self.internal.task_queue.clone() else {
+            return;
+        };
+
+        let directory = self.dialog_directory(DialogKind::Folder);
+        let workspace: Vec<FileReference> =
+            self.file_paths.iter().map(|path| FileReference(PathBuf::from(path))).collect();
+        let inbox = self.internal.inbox.sender();
+        execute(async move {
+            let Some(folder) = dialogs::folder_dialog(directory.as_deref()).pick_folder().await
+            else {
+                return;
+            };
+            let folder = folder.path().to_owned();
+            if let Some(parent) = folder.parent() {
+                let _ = inbox.send(BackgroundTaskMessage::DialogDirectoryUsed(
+                    DialogKind::Folder,
+                    parent.to_owned(),
+                ));
+            }
+
+            // Earlier layers take precedence, so the folder goes on top
+            let overlaid = std::iter::once(FileReference(folder.clone()))
+                .chain(workspace.iter().filter(|layer| layer.0 != folder).cloned())
+                .collect();
+            let task = if diff {
+                BackgroundTask::DiffBuilds { base: workspace, modified: overlaid }
+            } else {
+                BackgroundTask::LoadPakFiles(overlaid)
+            };
+            let _ = background_task_sender.send(task);
+        });
+    }
+
+    /// Stops background work when the window is asked to close, holding the window open
+    /// until pending file writes (exports, saved profiles) finish.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) {
+            shutdown::begin();
+            if shutdown::pending_writes() > 0 {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                self.internal.closing = true;
+            }
+        }
+
+        if !self.internal.closing {
+            return;
+        }
+
+        let pending = shutdown::pending_writes();
+        if pending == 0 {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return;
+        }
+
+        egui::Modal::new(egui::Id::new("closing")).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Finishing {pending} file write(s) before closing..."));
+            });
+        });
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+    }
+
+    /// Drops the contents of editor tabs which haven't been shown for a while. They're read
+    /// again when the tab is next shown.
+    fn evict_idle_editors(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|input| input.time);
+        let pak_set = self.internal.pak_set();
+        let docked = self.dock_state.iter_all_tabs_mut().map(|(_, tab)| tab);
+        let detached = self.internal.detached_tabs.iter_mut().map(|detached| &mut detached.tab);
+        for tab in docked.chain(detached) {
+            if let TabKind::Editor(editor) = tab {
+                editor.evict_if_idle(now, pak_set);
+            }
+        }
+    }
+
+    /// Moves tabs which asked to be popped out of the dock into their own windows.
+    fn pop_out_requested_tabs(&mut self) {
+        let mut popped_out = Vec::new();
+        self.dock_state.retain_tabs(|tab| {
+            if tab.take_pop_out_request() {
+                popped_out.push(tab.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        for tab in popped_out {
+            let viewport_id = egui::ViewportId::from_hash_of((
+                "detached_tab",
+                self.internal.next_detached_tab_id,
+            ));
+            self.internal.next_detached_tab_id += 1;
+            self.internal.detached_tabs.push(DetachedTab { viewport_id, tab });
+        }
+    }
+
+    /// Shows each popped out tab in its own native window, or in a floating window where
+    /// the platform only has one viewport (e.g. the web).
+    fn show_detached_tabs(&mut self, ctx: &egui::Context) {
+        if let Some(viewport_id) = self.internal.focus_viewport.take() {
+            ctx.send_viewport_cmd_to(viewport_id, egui::ViewportCommand::Focus);
+        }
+
+        let mut detached_tabs = std::mem::take(&mut self.internal.detached_tabs);
+        detached_tabs.retain_mut(|detached| {
+            let mut open = true;
+            let mut dock = false;
+            let title = detached.tab.title().to_string();
+            let mut viewer = ToolsTabViewer {
+                app_internal_data: &mut self.internal,
+                saved_views: &mut self.saved_views,
+            };
+            let mut contents = |ui: &mut egui::Ui| {
+                if ui
+                    .small_button("Dock")
+                    .on_hover_text("Move this tab back into the dock")
+                    .clicked()
+                {
+                    dock = true;
+                }
+                viewer.show_tab_contents(ui, &mut detached.tab);
+            };
+
+            ctx.show_viewport_immediate(
+                detached.viewport_id,
+                egui::ViewportBuilder::default().with_title(&title).with_inner_size([900.0, 700.0]),
+                |ctx, class| {
+                    if class == egui::ViewportClass::Embedded {
+                        egui::Window::new(&title)
+                            .id(egui::Id::new(detached.viewport_id))
+                            .open(&mut open)
+                            .default_size([900.0, 700.0])
+                            .show(ctx, &mut contents);
+                    } else {
+                        egui::CentralPanel::default().show(ctx, &mut contents);
+                        if ctx.input(|i| i.viewport().close_requested()) {
+                            open = false;
+                        }
+                    }
+                },
+            );
+
+            if dock {
+                self.dock_state.main_surface_mut().push_to_first_leaf(detached.tab.clone());
+            } else if !open {
+                self.internal.close_tab(&mut detached.tab);
+            }
+            open && !dock
+        });
+
+        self.internal.detached_tabs = detached_tabs;
+    }
+
+    /// Reverts the most recent undoable action.
+    fn undo(&mut self) {
+        if let Some(command) = self.internal.undo_stack.undo().cloned() {
+            self.apply_command(command, true);
+        }
+    }
+
+    /// Applies the most recently undone action again.
+    fn redo(&mut self) {
+        if let Some(command) = self.internal.undo_stack.redo().cloned() {
+            self.apply_command(command, false);
+        }
+    }
+
+    /// Reverts `command` if `undo` is set, otherwise applies it again.
+    fn apply_command(&mut self, command: Command, undo: bool) {
+        match command {
+            Command::CloseTab(tab) if undo => {
+                self.dock_state.main_surface_mut().push_to_first_leaf(tab);
+            }
+            Command::CloseTab(tab) => {
+                // Tabs have no identity beyond their title. Tabs with the same title show the
+                // same thing, so it doesn't matter which of them is closed.
+                let title = tab.title();
+                let mut closed = false;
+                self.dock_state.retain_tabs(|open| {
+                    let keep = closed || open.title() != title;
+                    closed |= !keep;
+                    keep
+                });
+            }
+            Command::Filter { before, after } => {
+                let filter = if undo { before } else { after };
+                self.internal.file_filter = filter.query.clone();
+                self.internal.quick_filter = filter.quick_filter;
+                self.internal.applied_filter = filter;
+                self.rebuild_filtered_tree();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Command::SelectMods { before, after } => {
+                self.selected_mods = if undo { before } else { after };
+            }
+        }
+    }
+
+    /// Picks the palette of diffs and search matches, and edits the custom one's colors.
+    fn show_palette_menu(&mut self, ui: &mut egui::Ui) {
+        let colors = &mut self.palette;
+        let mut changed = false;
+        for kind in PaletteKind::ALL {
+            changed |= ui.radio_value(&mut colors.kind, kind, kind.name()).changed();
+        }
+
+        ui.separator();
+        ui.add_enabled_ui(colors.kind == PaletteKind::Custom, |ui| {
+            egui::Grid::new("custom_palette").num_columns(3).show(ui, |ui| {
+                for color in PaletteColor::ALL {
+                    let hex = colors.custom_mut(color);
+                    let parsed = palette::parse_hex(hex);
+                    ui.label(color.name());
+                    let swatch = parsed.unwrap_or(Palette::DEFAULT.get(color));
+                    egui::color_picker::show_color(ui, swatch, egui::vec2(16.0, 16.0));
+                    let mut edit = egui::TextEdit::singleline(hex).desired_width(72.0);
+                    if parsed.is_none() {
+                        edit = edit.text_color(ui.visuals().error_fg_color);
+                    }
+                    changed |= ui
+                        .add(edit)
+                        .on_hover_text("A hex color such as #648fff, used once it's valid")
+                        .changed();
+                    ui.end_row();
+                }
+            });
+            if ui.button("Reset Custom Colors").clicked() {
+                colors.reset_custom();
+                changed = true;
+            }
+        });
+        ui.weak("Diffs which are already shown keep their colors until they're reopened.");
+
+        if changed {
+            settings::set_palette(self.palette.palette());
+        }
+    }
+
+    /// Undo and Redo entries for the Edit menu.
+    fn show_edit_menu(&mut self, ui: &mut egui::Ui) {
+        let undo = self.internal.undo_stack.next_undo().map(Command::describe);
+        let undo_label = match &undo {
+            Some(action) => format!("Undo {action}"),
+            None => "Undo".to_string(),
+        };
+        if ui
+            .add_enabled(undo.is_some(), egui::Button::new(undo_label).shortcut_text("Ctrl+Z"))
+            .clicked()
+        {
+            self.undo();
+            ui.close();
+        }
+
+        let redo = self.internal.undo_stack.next_redo().map(Command::describe);
+        let redo_label = match &redo {
+            Some(action) => format!("Redo {action}"),
+            None => "Redo".to_string(),
+        };
+        if ui
+            .add_enabled(redo.is_some(), egui::Button::new(redo_label).shortcut_text("Ctrl+Y"))
+            .clicked()
+        {
+            self.redo();
+            ui.close();
+        }
+
+        ui.separator();
+        if ui
+            .checkbox(&mut self.pretty_print_configs, "Pretty-print minified configs")
+            .on_hover_text(
+                "Show configs written on a single line with one entry per line, when opening \
+                 and diffing them",
+            )
+            .changed()
+        {
+            settings::set_pretty_print_configs(self.pretty_print_configs);
+        }
+        ui.menu_button("Colors", |ui| self.show_palette_menu(ui));
+
+        ui.menu_button("Searched Files", |ui| {
+            let options = &mut self.search_options;
+            ui.radio_value(&mut options.files, SearchedFiles::Text, "Text files");
+            ui.radio_value(&mut options.files, SearchedFiles::Extensions, "These extensions:");
+            ui.add_enabled(
+                options.files == SearchedFiles::Extensions,
+                egui::TextEdit::singleline(&mut options.extensions).hint_text("c, conf, layout"),
+            )
+            .on_hover_text("Separated by commas or spaces");
+            ui.radio_value(&mut options.files, SearchedFiles::All, "All files")
+                .on_hover_text("Every file which doesn't look binary, whatever its extension");
+            ui.label("Binary searches always read every file.");
+        });
+
+        if ui
+            .button("Load Value Rules...")
+            .on_hover_text(
+                "Choose a file of property name patterns and units for structured config views",
+            )
+            .clicked()
+        {
+            self.pick_value_rules();
+            ui.close();
+        }
+        if ui
+            .add_enabled(self.value_rules.is_some(), egui::Button::new("Reset Value Rules"))
+            .clicked()
+        {
+            self.value_rules = None;
+            self.internal.value_rules = ValueRules::default();
+            ui.close();
+        }
+    }
+
+    /// Offers to save a crash report, from this run or one which crashed, so it can be
+    /// attached to an issue.
+    fn show_crash_report_window(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.internal.crash_report else {
+            return;
+        };
+
+        let mut open = true;
+        let mut handled = false;
+        egui::Window::new("Crash Report").open(&mut open).collapsible(false).show(ctx, |ui| {
+            ui.label(
+                "Enfusion Tools hit an error. Saving this report and attaching it to an issue \
+                 helps get it fixed. It holds the error, recent log messages and the names of \
+                 the loaded archives, and isn't sent anywhere.",
+            );
+            ui.collapsing("Show Report", |ui| {
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    ui.monospace(report);
+                });
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Save Report...").clicked() {
+                    let data = report.clone().into_bytes();
+                    let file_name = "enfusion_tools_crash_report.txt".to_string();
+                    execute(save_export("Save Crash Report".to_string(), file_name, data));
+                    handled = true;
+                }
+                if ui.button("Copy").clicked() {
+                    ui.ctx().copy_text(report.clone());
+                }
+                if ui.button("Dismiss").clicked() {
+                    handled = true;
+                }
+            });
+        });
+
+        if handled || !open {
+            crash::discard_saved_report();
+            self.internal.crash_report = None;
+        }
+    }
+
+    /// Asks for a value rules file, which replaces the current rules if it's valid.
+    fn pick_value_rules(&self) {
+        let inbox = self.internal.inbox.sender();
+        execute(async move {
+            let Some(handle) = rfd::AsyncFileDialog::new()
+                .set_title("Choose Value Rules")
+                .add_filter("Text files", &["txt"])
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+
+            let text = String::from_utf8_lossy(&handle.read().await).into_owned();
+            let _ = inbox.send(BackgroundTaskMessage::ValueRulesLoaded(text));
+        });
+    }
+
+    /// Undo and redo shortcuts, which are left to text fields while one has focus.
+    fn handle_undo_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.memory(|memory| memory.focused().is_some()) {
+            return;
+        }
+
+        // Checked before Ctrl+Z, which would otherwise also match Ctrl+Shift+Z
+        let redo_shift_z = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+        let redo_y = KeyboardShortcut::new(Modifiers::COMMAND, Key::Y);
+        let undo_z = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+
+        let (undo, redo) = ctx.input_mut(|input| {
+            let redo = input.consume_shortcut(&redo_shift_z) || input.consume_shortcut(&redo_y);
+            (input.consume_shortcut(&undo_z), redo)
+        });
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+    }
+
+    /// Where a file dialog should start: the directory last used for this kind of dialog,
+    /// or the game's data directory if one was found.
+    fn dialog_directory(&self, kind: DialogKind) -> Option<PathBuf> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.dialog_directories.get(&kind).cloned().or_else(|| {
+                self.internal
+                    .game_installs
+                    .iter()
+                    .find(|install| install.game == Game::Reforger)
+                    .map(GameInstall::data_dir)
+            })
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = kind;
+            None
+        }
+    }
+
+    /// Fetches the archives `link` names, then loads them in place of the workspace.
+    #[cfg(target_arch = "wasm32")]
+    fn open_share_link(&self, link: ShareLink) {
+        let inbox = self.internal.inbox.sender();
+        execute(async move {
+            match remote::fetch_archives(&link.archives).await {
+                Ok(files) => {
+                    let _ = inbox.send(BackgroundTaskMessage::SharedArchivesFetched(link, files));
+                }
+                Err(e) => error!(%e, "failed to fetch shared archives"),
+            }
+        });
+    }
+
+    /// Asks for the URLs of archives to load, one per line.
+    #[cfg(target_arch = "wasm32")]
+    fn show_archive_urls_window(&mut self, ctx: &egui::Context) {
+        let Some(input) = self.internal.archive_urls_input.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut submitted = false;
+        egui::Window::new("Open Archives from URLs").open(&mut open).collapsible(false).show(
+            ctx,
+            |ui| {
+                ui.label("One URL per line, in load order");
+                ui.add(
+                    egui::TextEdit::multiline(input)
+                        .hint_text("https://example.com/data.pak")
+                        .desired_width(f32::INFINITY),
+                );
+                submitted = ui.button("Open").clicked();
+            },
+        );
+
+        if submitted {
+            let archives: Vec<String> = input
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+            if !archives.is_empty() {
+                self.open_share_link(ShareLink { archives, file: None, line: None });
+            }
+            open = false;
+        }
+        if !open {
+            self.internal.archive_urls_input = None;
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn reopen_last_workspace(&self) {
+        if let Some(background_task_sender) = self.internal.task_queue.clone() {
+            execute(async move {
+                let files = fs_access::reopen_last_workspace().await;
+                if !files.is_empty() {
+                    let _ = background_task_sender.send(BackgroundTask::LoadPakFiles(files));
+                }
+            });
+        }
+    }
+
+    /// Runs `analysis`, or asks for its prompt's text first if it has one.
+    fn start_analysis(&mut self, analysis: Arc<dyn Analysis>) {
+        if analysis.prompt().is_some() {
+            self.internal.analysis_prompt = Some((analysis, String::new()));
+        } else {
+            self.run_analysis(analysis, None);
+        }
+    }
+
+    /// Asks for the text of the waiting analysis' prompt, then runs it.
+    fn show_analysis_prompt_window(&mut self, ctx: &egui::Context) {
+        let Some((analysis, text)) = self.internal.analysis_prompt.as_mut() else {
+            return;
+        };
+        let Some(prompt) = analysis.prompt() else {
+            self.internal.analysis_prompt = None;
+            return;
+        };
+
+        let mut open = true;
+        let mut submitted = false;
+        egui::Window::new(prompt.title).open(&mut open).collapsible(false).show(ctx, |ui| {
+            ui.label(prompt.label);
+            let response = ui.add(egui::TextEdit::singleline(text).hint_text(prompt.hint));
+            let entered =
+                response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            submitted = ui.button("Run").clicked() || entered;
+        });
+
+        if submitted && !text.trim().is_empty() {
+            let (analysis, text) = self.internal.analysis_prompt.take().expect("prompt is shown");
+            self.run_analysis(analysis, Some(text));
+        } else if !open {
+            self.internal.analysis_prompt = None;
+        }
+    }
+
+    /// Runs `analysis` against the loaded files in the background, passing it the text
+    /// typed for its prompt, or first asking for its input file if it needs one.
+    fn run_analysis(&self, analysis: Arc<dyn Analysis>, text: Option<String>) {
+        let (Some(background_task_sender), Some(root)) =
+            (self.internal.task_queue.clone(), self.internal.async_overlay_fs.clone())
+        else {
+            return;
+        };
+
+        let workspace = Workspace { root, layers: self.internal.layers.clone() };
+        execute(async move {
+            let input = match (text, analysis.input()) {
+                (Some(text), _) => Some(text.into_bytes()),
+                (None, Some(input_file)) => {
+                    let Some(handle) = rfd::AsyncFileDialog::new()
+                        .set_title(input_file.title)
+                        .add_filter(input_file.filter_name, input_file.extensions)
+                        .pick_file()
+                        .await
+                    else {
+                        return;
+                    };
+
+                    Some(handle.read().await)
+                }
+                (None, None) => None,
+            };
+
+            let _ = background_task_sender.send(BackgroundTask::RunAnalysis {
+                analysis,
+                workspace,
+                input,
+            });
+        });
+    }
+
+    /// Adds an analysis to the Analyses menu.
+    pub fn register_analysis(&mut self, analysis: impl Analysis + 'static) {
+        self.internal.analyses.register(analysis);
+    }
+
+    /// Adds a previewer, which takes priority over the default text and hex views for the
+    /// files it handles.
+    pub fn register_previewer(&mut self, previewer: impl Previewer + 'static) {
+        self.internal.previewers.push(Arc::new(previewer));
+    }
+
+    /// Adds a subscriber which is told whenever the workspace changes, alongside the open
+    /// tabs.
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'static) {
+        self.internal.events.subscribe(subscriber);
+    }
+
+    /// Opens a tab listing every loaded archive which contains `file`, in priority order.
+    pub(crate) fn show_providers(&mut self, file: &VfsPath) {
+        let providers = self
+            .internal
+            .layers
+            .iter()
+            .filter_map(|layer| {
+                let size = layer.file_size(file.as_str())?;
+                Some(Provider { archive: layer.name.clone(), size })
+            })
+            .collect();
+
+        self.dock_state.main_surface_mut().push_to_first_leaf(TabKind::Providers(ProvidersData {
+            title: format!("{} - Providers", file.filename()),
+            path: file.as_str().to_string(),
+            providers,
+        }));
+    }
+
+    /// Searches the loaded archives for the search box's query, or only the files in the
+    /// filtered tree if "Only filtered files" is checked.
+    fn search_workspace(&mut self) {
+        let Some(vfs_root) = self.internal.async_overlay_fs.clone() else { return };
+        let (scope, title_suffix) = match &self.internal.filtered_tree {
+            Some(filtered_tree) if self.search_filtered_only => (
+                SearchScope::Files(
+                    filtered_tree
+                        .iter()
+                        .filter(|node| !node.is_dir)
+                        .filter_map(|node| vfs_root.join(node.vfs_path.as_str()).ok())
+                        .collect(),
+                ),
+                " (filtered)",
+            ),
+            _ => (SearchScope::Directory(vfs_root), ""),
+        };
+        self.start_search(scope, title_suffix);
+    }
+
+    /// Searches `scope` for the search box's query, showing the results in a new tab whose
+    /// title ends with `title_suffix`.
+    pub(crate) fn start_search(&mut self, scope: SearchScope, title_suffix: &str) {
+        let query = self.search_options.compile(&self.search_query);
+        self.internal.search_error = query.as_ref().err().map(|e| e.to_string());
+        let (Ok(query), Some(task_queue)) = (query, &self.internal.task_queue) else {
+            return;
+        };
+
+        debug!("Sending search task");
+        let matcher = match &query {
+            SearchQuery::Lines(searcher) => Some(searcher.regex().clone()),
+            SearchQuery::Bytes(_) => None,
+        };
+        self.search_history.record(PastSearch {
+            query: self.search_query.clone(),
+            options: self.search_options.clone(),
+        });
+        self.internal.opened_file_text.clear();
+        let search_id = self.internal.next_search_query_id;
+        self.internal.next_search_query_id.0 += 1;
+        let _ = task_queue.send(BackgroundTask::PerformSearch {
+            id: search_id,
+            scope,
+            query,
+            max_results: self.search_options.max_results,
+        });
+
+        let query = self.search_query.clone();
+        let mut tab = TabKind::SearchResults(SearchData {
+            tab_title: format!("{query} - Search Results{title_suffix}"),
+            query,
+            id: search_id,
+            results: Default::default(),
+            matcher,
+            progress: Default::default(),
+            end: None,
+            view: Default::default(),
+        });
+        tab.restore_view(&self.saved_views);
+        self.dock_state.main_surface_mut().push_to_first_leaf(tab);
+    }
+
+    /// Searches the folder at `path` for the search box's query: in the overlay, or if
+    /// `layer` is given, only in that archive's copy of the folder.
+    pub(crate) fn search_in_folder(&mut self, path: &str, layer: Option<usize>) {
+        let shown_path = if path.is_empty() { "/" } else { path };
+        let (root, title_suffix) = match layer {
+            Some(layer) => {
+                let Some(layer) = self.internal.layers.get(layer) else { return };
+                (layer.async_root.clone(), format!(" in {shown_path} ({})", layer.name))
+            }
+            None => {
+                let Some(root) = self.internal.async_overlay_fs.clone() else { return };
+                (root, format!(" in {shown_path}"))
+            }
+        };
+        match root.join(path) {
+            Ok(folder) => self.start_search(SearchScope::Directory(folder), &title_suffix),
+            Err(e) => warn!(%e, path, "can't search folder"),
+        }
+    }
+
+    /// Opens the file an `enfpak://` link points to, warning if it was made for different
+    /// archives than the ones loaded.
+    pub(crate) fn open_permalink(&mut self, link: &str) {
+        let link: Permalink = match link.parse() {
+            Ok(link) => link,
+            Err(e) => {
+                warn!(%e, link, "invalid permalink");
+                return;
+            }
+        };
+        if self.internal.overlay_fs.is_none() {
+            warn!(%link, "no archives are loaded to open the permalink in");
+            return;
+        }
+
+        let pak_set = self.internal.pak_set();
+        if link.pak_set != pak_set {
+            warn!(%link, loaded = %pak_set, "permalink was made for a different set of archives");
+        }
+
+        if !self.open_linked_file(link.path.clone(), link.line) {
+            error!(%link, "permalink points to a file which isn't loaded");
+        }
+    }
+
+    /// Opens the file at `path` in the overlay, pointing its editor tab at `line` once it
+    /// loads. Returns `false` if there's no such file.
+    fn open_linked_file(&mut self, path: String, line: Option<usize>) -> bool {
+        let Some(overlay_fs) = self.internal.overlay_fs.as_ref() else {
+            return false;
+        };
+        match overlay_fs.join(&path) {
+            Ok(file) if file.is_file().unwrap_or_default() => {
+                self.internal.pending_link_line = line.map(|line| (path, line));
+                self.open_file(file);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn open_file(&self, file: VfsPath) {
+        self.load_file(file, FileLoad::Open);
+    }
+
+    /// Reads `file` to show it in an editor tab, as described by `load`.
+    pub(crate) fn load_file(&self, file: VfsPath, load: FileLoad) {
+        if !file.is_file().unwrap_or_default() {
+            return;
+        }
+
+        if let Some(task_queue) = self.internal.task_queue.as_ref() {
+            debug!("sending task");
+            // Get the async version of this file
+            let overlay_fs = self.internal.async_overlay_fs.clone().expect("no async overlay FS?");
+            let task = if load == FileLoad::Open && audio::is_audio_path(file.as_str()) {
+                BackgroundTask::LoadAudio(file, overlay_fs)
+            } else {
+                BackgroundTask::LoadFileData(file, overlay_fs, load)
+            };
+            let _ = task_queue.send(task);
+        }
+    }
+}
+
+/// The title and text of an editor tab showing `file`, whose contents are `data`, or `None`
+/// if it's binary and belongs in a hex view. `previewers` are tried before text.
+fn editor_contents(
+    previewers: &[Arc<dyn Previewer>],
+    file: &VfsPath,
+    data: &[u8],
+) -> Option<(String, String)> {
+    // Try decompiling rapified config.bin files
+    if cfg_parser::is_rapified(data)
+        && let Ok(rap) = cfg_parser::RapFile::parse(data)
+    {
+        return Some((format!("{} - Decompiled", file.filename()), cfg_parser::decompile(&rap)));
+    }
+
+    let path = file.as_str();
+    let previewer = previewers.iter().find(|previewer| previewer.handles(path));
+    if let Some(previewer) = previewer {
+        match previewer.preview(path, data) {
+            Ok(contents) => {
+                return Some((format!("{} - {}", file.filename(), previewer.name()), contents));
+            }
+            Err(e) => {
+                warn!(previewer = previewer.name(), %e, "failed to preview file");
+            }
+        }
+    }
+
+    let text = decode_text(data)?;
+    if syntax::wants_pretty_print(file.as_str(), &text) {
+        let title = format!("{} - Formatted", file.filename());
+        return Some((title, cfg_parser::pretty::pretty_print(&text)));
+    }
+    Some((file.filename(), text))
+}
+
+/// The tab for a binary file: a preview if it's a texture which can be decoded, or else a
+/// hex view.
+fn binary_tab(file: VfsPath, data: Vec<u8>, pak_set: PakSetHash) -> TabKind {
+    if texture::is_texture(&data) {
+        match texture::decode(&data, texture::MAX_PREVIEW_SIDE) {
+            Ok(decoded) => {
+                let size = [decoded.width, decoded.height];
+                let image = egui::ColorImage::from_rgba_unmultiplied(size, &decoded.rgba);
+                return TabKind::ImagePreview(ImagePreviewData {
+                    title: format!("{} - Preview", file.filename()),
+                    opened_file: file,
+                    description: decoded.description,
+                    image: Some(Arc::new(image)),
+                    texture: None,
+                    fit: true,
+                    pak_set,
+                });
+            }
+            Err(e) => warn!(%e, path = file.as_str(), "failed to decode texture"),
+        }
+    }
+
+    TabKind::HexView(HexViewData {
+        title: format!("{} - Hex", file.filename()),
+        opened_file: file,
+        data: data.into(),
+        pak_set,
+    })
+}
+
+/// The text an editor tab which is already open shows for `file`. Editors only show
+/// binaries if a file has become binary since it was opened, so they get a hexdump.
+fn editor_text(previewers: &[Arc<dyn Previewer>], file: &VfsPath, data: &[u8]) -> String {
+    editor_contents(previewers, file, data).map_or_else(|| hexdump(data), |(_, text)| text)
+}
+
+impl eframe::App for EnfusionToolsApp {
+    /// Called by the frame work to save state before shutdown.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+
+    /// Called after the final [`Self::save`]. Background tasks are told to stop, and any
+    /// write still running (e.g. the window was closed by the OS) gets a moment to finish.
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        shutdown::begin();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if !shutdown::wait_for_writes(std::time::Duration::from_secs(5)) {
+            warn!(pending = shutdown::pending_writes(), "exiting with file writes in progress");
+        }
+    }
+
+    /// Called each time the UI needs repainting, which may be many times per second.
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.internal.inbox.set_ctx(ctx);
+
+        // Process any background messages
+        if let Some(task_queue_rx) = self.internal.task_queue_rx.as_ref() {
+            process_background_requests(self.internal.inbox.sender(), task_queue_rx);
+        }
+
+        while let Some(message) = self.internal.inbox.read_without_ctx().next() {
+            self.process_message_from_background(message);
+        }
+        self.dispatch_events();
+        if let Some(report) = crash::take_pending_report() {
+            self.internal.crash_report = Some(report);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.handle_close_request(ctx);
+        self.handle_undo_shortcuts(ctx);
+
+        // Links pasted while no text field has focus are opened
+        if ctx.memory(|memory| memory.focused().is_none()) {
+            let pasted_link = ctx.input(|input| {
+                input.events.iter().find_map(|event| match event {
+                    egui::Event::Paste(text) if text.trim().starts_with(permalink::SCHEME) => {
+                        Some(text.clone())
+                    }
+                    _ => None,
+                })
+            });
+            if let Some(link) = pasted_link {
+                self.open_permalink(&link);
+            }
+        }
+
+        // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
+        // For inspiration and more examples, go to https://emilk.github.io/egui
+
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            // The top panel is often a good place for a menu bar:
+
+            egui::MenuBar::new().ui(ui, |ui| {
+                // NOTE: no File->Quit on web pages!
+                let is_web = cfg!(target_arch = "wasm32");
+                if !is_web {
+                    ui.menu_button("File", |ui| {
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            self.show_update_menu(ui);
+                            self.show_profile_menu(ui);
+                        }
+
+                        if ui.button("Quit").clicked() {
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        }
+                    });
+                    ui.add_space(16.0);
+                }
+
+                ui.menu_button("Edit", |ui| self.show_edit_menu(ui));
+                ui.add_space(16.0);
+
+                ui.menu_button("Analyses", |ui| {
+                    let loaded = self.internal.async_overlay_fs.is_some();
+                    let mut clicked = None;
+                    for analysis in self.internal.analyses.iter() {
+                        let label = if analysis.input().is_some() || analysis.prompt().is_some() {
+                            format!("{}...", analysis.name())
+                        } else {
+                            analysis.name().to_string()
+                        };
+                        if ui
+                            .add_enabled(loaded, egui::Button::new(label))
+                            .on_hover_text(analysis.description())
+                            .clicked()
+                        {
+                            clicked = Some(Arc::clone(analysis));
+                            ui.close();
+                        }
+                    }
+                    if let Some(analysis) = clicked {
+                        self.start_analysis(analysis);
+                    }
+                });
+                ui.add_space(16.0);
+
+                ui.menu_button("Help", |ui| self.show_help_menu(ui));
+                ui.add_space(16.0);
+
+                #[cfg(not(target_arch = "wasm32"))]
+                self.show_update_notice(ui);
+
+                egui::widgets::global_theme_preference_buttons(ui);
+            });
+        });
+
+        self.show_file_tree(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Open Files").clicked() {
+                        self.pick_files_to_open();
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    if !self.internal.last_workspace.is_empty() {
+                        let names = self.internal.last_workspace.join("\n");
+                        if ui
+                            .button("Reopen Last Workspace")
+                            .on_hover_text(format!("Reopens:\n{names}"))
+                            .clicked()
+                        {
+                            self.reopen_last_workspace();
+                        }
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    if ui
+                        .button("Open URLs")
+                        .on_hover_text("Fetch archives hosted over HTTP, which can then be shared")
+                        .clicked()
+                    {
+                        self.internal.archive_urls_input.get_or_insert_default();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.show_load_game_data_button(ui);
+                    if ui.button("Diff Builds").clicked() {
+                        self.pick_builds_to_diff();
+                    }
+                    if ui
+                        .button("Load Build to Compare")
+                        .on_hover_text(
+                            "Load another build without replacing the workspace, so open files \
+                             can be compared against it",
+                        )
+                        .clicked()
+                    {
+                        self.pick_build_to_compare();
+                    }
+                    if ui
+                        .add_enabled(
+                            self.internal.async_overlay_fs.is_some(),
+                            egui::Button::new("Dump Manifest"),
+                        )
+                        .on_hover_text(
+                            "Save a sorted list of every file's path, size and hash, to compare \
+                             builds with diff or git diff",
+                        )
+                        .clicked()
+                    {
+                        self.dump_manifest();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        if ui
+                            .button("Overlay Folder")
+                            .on_hover_text("Layer an unpacked mod folder over the loaded archives")
+                            .clicked()
+                        {
+                            self.pick_folder_to_overlay(false);
+                        }
+                        if ui
+                            .button("Diff Folder")
+                            .on_hover_text(
+                                "Compare the loaded archives with and without an unpacked mod \
+                                 folder layered over them",
+                            )
+                            .clicked()
+                        {
+                            self.pick_folder_to_overlay(true);
+                        }
+                    }
+                    ui.label("Search");
+                    let response = ui.text_edit_singleline(&mut self.search_query);
+                    let mut rerun = None;
+                    ui.add_enabled_ui(!self.search_history.is_empty(), |ui| {
+                        ui.menu_button("History", |ui| {
+                            for past in self.search_history.iter() {
+                                if ui
+                                    .button(&past.query)
+                                    .on_hover_text(past.describe_options())
+                                    .clicked()
+                                {
+                                    rerun = Some(past.clone());
+                                    ui.close();
+                                }
+                            }
+                            ui.separator();
+                            if ui.button("Clear History").clicked() {
+                                self.search_history.clear();
+                                ui.close();
+                            }
+                        })
+                        .response
+                        .on_hover_text("Run a recent search again");
+                    });
+                    let options = &mut self.search_options;
+                    egui::ComboBox::from_id_salt("search_mode")
+                        .selected_text(options.mode.name())
+                        .show_ui(ui, |ui| {
+                            for mode in SearchMode::ALL {
+                                ui.selectable_value(&mut options.mode, mode, mode.name());
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Binary searches every file's raw bytes for hex (DE AD BE EF) or \
+                             ASCII, which can be put in double quotes to keep it from being \
+                             read as hex",
+                        );
+                    ui.add_enabled_ui(options.mode != SearchMode::Binary, |ui| {
+                        ui.checkbox(&mut options.case_sensitive, "Match case");
+                        ui.checkbox(&mut options.whole_word, "Whole word");
+                    });
+                    ui.add(
+                        egui::DragValue::new(&mut options.max_results)
+                            .range(1..=100_000)
+                            .prefix("Max results: "),
+                    )
+                    .on_hover_text("The search stops once it has found this many matches");
+                    if self.internal.filtered_tree.is_some() {
+                        ui.checkbox(&mut self.search_filtered_only, "Only filtered files");
+                    }
+                    if let Some(e) = &self.internal.search_error {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+
+                    let enter_pressed = response.lost_focus()
+                        && response.ctx.input(|input| input.key_pressed(egui::Key::Enter));
+                    if enter_pressed && self.search_query.trim().starts_with(permalink::SCHEME) {
+                        let link = std::mem::take(&mut self.search_query);
+                        self.open_permalink(&link);
+                    } else if enter_pressed {
+                        debug!("Search requested");
+                        self.search_workspace();
+                    } else if let Some(PastSearch { query, options }) = rerun {
+                        self.search_query = query;
+                        self.search_options = options;
+                        self.search_workspace();
+                    }
+                });
 
                 DockArea::new(&mut self.dock_state)
                     .style(Style::from_egui(ui.style().as_ref()))
@@ -470,12 +2380,27 @@ impl eframe::App for EnfusionToolsApp {
                     .show_leaf_collapse_buttons(false)
                     .show_leaf_close_all_buttons(false)
                     .show_close_buttons(true)
-                    .show_inside(ui, &mut ToolsTabViewer { app_internal_data: &mut self.internal });
+                    .show_inside(
+                        ui,
+                        &mut ToolsTabViewer {
+                            app_internal_data: &mut self.internal,
+                            saved_views: &mut self.saved_views,
+                        },
+                    );
             });
 
             // ui.add_sized(ui.available_size(), widget)
             // ui.text_edit_multiline(&mut self.internal.opened_file_text);
         });
+
+        self.pop_out_requested_tabs();
+        self.show_detached_tabs(ctx);
+        #[cfg(target_arch = "wasm32")]
+        self.show_archive_urls_window(ctx);
+        self.show_analysis_prompt_window(ctx);
+        self.show_tour_window(ctx);
+        self.show_crash_report_window(ctx);
+        self.evict_idle_editors(ctx);
     }
 }
 
@@ -489,3 +2414,24 @@ impl eframe::App for EnfusionToolsApp {
 //         ui.label(".");
 //     });
 // }
+
+fn apply_audio_update(audio: &mut AudioPreviewData, update: AudioUpdate) {
+    match update {
+        AudioUpdate::Started(format) => audio.format = Some(format),
+        AudioUpdate::Progress(peaks, progress) => {
+            audio.peaks.extend(peaks);
+            audio.progress = progress;
+        }
+        AudioUpdate::Finished { clip, peaks, truncated } => {
+            audio.peaks.extend(peaks);
+            audio.progress = 1.0;
+            audio.clip = Some(clip);
+            audio.truncated = truncated;
+            audio.decoding = None;
+        }
+        AudioUpdate::Failed(error) => {
+            audio.error = Some(error);
+            audio.decoding = None;
+        }
+    }
+}
@@ -0,0 +1,650 @@
+//! Decoding and playing WAV and Ogg Vorbis files for audio preview tabs.
+//!
+//! Files are decoded by a [`StreamDecoder`] a chunk at a time as they're read, so the
+//! compressed file never has to be held in memory. Decoded audio is kept as interleaved
+//! 16-bit samples, which [`Player`] plays through the default output device.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use lewton::audio::PreviousWindowRight;
+use lewton::audio::read_audio_packet_generic;
+use lewton::header::IdentHeader;
+use lewton::header::SetupHeader;
+use lewton::samples::InterleavedSamples;
+
+/// Decoding stops after this many samples, about six minutes of 44.1 kHz stereo audio.
+pub const MAX_SAMPLES: usize = 32 * 1024 * 1024;
+
+/// Frames summarized by each [`Peak`] of a waveform.
+const PEAK_FRAMES: usize = 256;
+
+/// Whether the file at `path` is audio which can be previewed.
+pub fn is_audio_path(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case("wav") || ext.eq_ignore_ascii_case("ogg"))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AudioError {
+    #[error("not a WAV or Ogg file")]
+    Unrecognized,
+    #[error("invalid WAV file: {0}")]
+    InvalidWav(&'static str),
+    #[error("{0} WAV files can't be played")]
+    UnsupportedWav(String),
+    #[error("invalid Ogg file: {0}")]
+    InvalidOgg(&'static str),
+    #[error("invalid Vorbis audio: {0}")]
+    Vorbis(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl std::fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.channels {
+            1 => write!(f, "{} Hz, mono", self.sample_rate),
+            2 => write!(f, "{} Hz, stereo", self.sample_rate),
+            channels => write!(f, "{} Hz, {channels} channels", self.sample_rate),
+        }
+    }
+}
+
+/// A decoded sound, ready to play.
+#[derive(Debug, Clone)]
+pub struct AudioClip {
+    pub format: AudioFormat,
+    /// Interleaved samples.
+    pub samples: Arc<[i16]>,
+}
+
+impl AudioClip {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.format.channels.max(1) as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        frame_time(self.frames(), self.format)
+    }
+}
+
+/// How long `frames` frames of audio in `format` play for.
+pub fn frame_time(frames: usize, format: AudioFormat) -> Duration {
+    Duration::from_secs_f64(frames as f64 / format.sample_rate.max(1) as f64)
+}
+
+/// Formats a position in a clip as minutes and seconds, e.g. `1:05.3`.
+pub fn format_time(time: Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+/// Progress decoding an audio file, sent from the background thread to its preview tab.
+#[derive(Debug)]
+pub enum AudioUpdate {
+    /// The file's format was read and decoding has begun.
+    Started(AudioFormat),
+    /// More of the waveform, and how much of the file has been read, from 0 to 1.
+    Progress(Vec<Peak>, f32),
+    /// The end of the waveform and the whole clip. `truncated` if decoding stopped at
+    /// [`MAX_SAMPLES`].
+    Finished {
+        clip: AudioClip,
+        peaks: Vec<Peak>,
+        truncated: bool,
+    },
+    Failed(String),
+}
+
+/// The lowest and highest sample, over every channel, of a stretch of a waveform.
+pub type Peak = [i16; 2];
+
+/// Summarizes samples into [`Peak`]s of [`PEAK_FRAMES`] frames each.
+#[derive(Debug)]
+pub struct WaveformBuilder {
+    samples_per_peak: usize,
+    current: Peak,
+    count: usize,
+}
+
+impl WaveformBuilder {
+    pub fn new(format: AudioFormat) -> Self {
+        WaveformBuilder {
+            samples_per_peak: PEAK_FRAMES * format.channels.max(1) as usize,
+            current: [i16::MAX, i16::MIN],
+            count: 0,
+        }
+    }
+
+    /// Adds interleaved samples, pushing a peak onto `peaks` for each stretch completed.
+    pub fn push(&mut self, samples: &[i16], peaks: &mut Vec<Peak>) {
+        for &sample in samples {
+            self.current = [self.current[0].min(sample), self.current[1].max(sample)];
+            self.count += 1;
+            if self.count == self.samples_per_peak {
+                self.finish(peaks);
+            }
+        }
+    }
+
+    /// Pushes the peak of the stretch in progress, if it's begun.
+    pub fn finish(&mut self, peaks: &mut Vec<Peak>) {
+        if self.count > 0 {
+            peaks.push(self.current);
+            self.current = [i16::MAX, i16::MIN];
+            self.count = 0;
+        }
+    }
+}
+
+/// Decodes a WAV or Ogg Vorbis file from chunks of its bytes, as they're read.
+#[derive(Default)]
+pub struct StreamDecoder {
+    kind: Option<DecoderKind>,
+    /// The start of the file, until there's enough to tell what it is.
+    sniffed: Vec<u8>,
+}
+
+enum DecoderKind {
+    Wav(WavDecoder),
+    Ogg(Box<OggDecoder>),
+}
+
+impl StreamDecoder {
+    /// The audio's format, once enough of the file has been read to know it.
+    pub fn format(&self) -> Option<AudioFormat> {
+        match self.kind.as_ref()? {
+            DecoderKind::Wav(wav) => wav.format,
+            DecoderKind::Ogg(ogg) => ogg.ident.as_ref().map(|ident| AudioFormat {
+                sample_rate: ident.audio_sample_rate,
+                channels: ident.audio_channels as u16,
+            }),
+        }
+    }
+
+    /// Decodes the next chunk of the file, appending the samples it completes to `out`.
+    pub fn push(&mut self, data: &[u8], out: &mut Vec<i16>) -> Result<(), AudioError> {
+        let Some(kind) = &mut self.kind else {
+            self.sniffed.extend_from_slice(data);
+            if self.sniffed.len() < 4 {
+                return Ok(());
+            }
+            self.kind = Some(match &self.sniffed[..4] {
+                b"RIFF" => DecoderKind::Wav(WavDecoder::default()),
+                b"OggS" => DecoderKind::Ogg(Box::default()),
+                _ => return Err(AudioError::Unrecognized),
+            });
+            let sniffed = std::mem::take(&mut self.sniffed);
+            return self.push(&sniffed, out);
+        };
+        match kind {
+            DecoderKind::Wav(wav) => wav.push(data, out),
+            DecoderKind::Ogg(ogg) => ogg.push(data, out),
+        }
+    }
+
+    /// Checks the file ended at a sensible place once every chunk has been pushed.
+    pub fn finish(&self) -> Result<(), AudioError> {
+        match &self.kind {
+            None => Err(AudioError::Unrecognized),
+            Some(DecoderKind::Wav(wav)) if wav.format.is_none() => {
+                Err(AudioError::InvalidWav("no audio data"))
+            }
+            Some(DecoderKind::Ogg(ogg)) if ogg.setup.is_none() => {
+                Err(AudioError::InvalidOgg("the Vorbis headers are incomplete"))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().expect("slice is four bytes"))
+}
+
+/// How a WAV file's samples are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WavEncoding {
+    /// Integers of this many bytes. 8-bit samples are unsigned.
+    Int(usize),
+    Float,
+}
+
+impl WavEncoding {
+    fn sample_len(self) -> usize {
+        match self {
+            WavEncoding::Int(bytes) => bytes,
+            WavEncoding::Float => 4,
+        }
+    }
+
+    fn decode(self, sample: &[u8]) -> i16 {
+        match (self, sample) {
+            (WavEncoding::Int(1), [byte]) => (*byte as i16 - 128) << 8,
+            // Wider samples keep their most significant 16 bits
+            (WavEncoding::Int(_), [.., low, high]) => i16::from_le_bytes([*low, *high]),
+            (WavEncoding::Float, _) => {
+                let value = f32::from_le_bytes(sample.try_into().expect("sample is four bytes"));
+                (value.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+            }
+            _ => unreachable!("samples are at least one byte"),
+        }
+    }
+}
+
+/// Decodes PCM and floating point WAV files.
+#[derive(Default)]
+struct WavDecoder {
+    pending: Vec<u8>,
+    /// Set once the RIFF header has been read.
+    riff_read: bool,
+    format: Option<AudioFormat>,
+    encoding: Option<WavEncoding>,
+    /// Bytes of sample data left to read, once the `data` chunk has been reached. `None`
+    /// until then; files being written as they're streamed give no length.
+    data_left: Option<usize>,
+}
+
+impl WavDecoder {
+    fn push(&mut self, data: &[u8], out: &mut Vec<i16>) -> Result<(), AudioError> {
+        self.pending.extend_from_slice(data);
+        if self.data_left.is_none() && !self.read_header()? {
+            return Ok(());
+        }
+
+        let (Some(encoding), Some(data_left)) = (self.encoding, &mut self.data_left) else {
+            return Ok(());
+        };
+        let sample_len = encoding.sample_len();
+        let available = self.pending.len().min(*data_left);
+        let usable = available - available % sample_len;
+        out.extend(self.pending[..usable].chunks_exact(sample_len).map(|s| encoding.decode(s)));
+        *data_left -= usable;
+        if *data_left < sample_len {
+            // Anything after the samples, like trailing chunks, is ignored
+            *data_left = 0;
+            self.pending.clear();
+        } else {
+            self.pending.drain(..usable);
+        }
+        Ok(())
+    }
+
+    /// Reads chunks until the start of the samples. Returns `false` if more of the file is
+    /// needed first.
+    fn read_header(&mut self) -> Result<bool, AudioError> {
+        if !self.riff_read {
+            if self.pending.len() < 12 {
+                return Ok(false);
+            }
+            if &self.pending[8..12] != b"WAVE" {
+                return Err(AudioError::InvalidWav("missing WAVE header"));
+            }
+            self.pending.drain(..12);
+            self.riff_read = true;
+        }
+
+        loop {
+            if self.pending.len() < 8 {
+                return Ok(false);
+            }
+            let id: [u8; 4] = self.pending[..4].try_into().expect("slice is four bytes");
+            let len = u32_at(&self.pending, 4) as usize;
+
+            if &id == b"data" {
+                if self.encoding.is_none() {
+                    return Err(AudioError::InvalidWav("samples come before their format"));
+                }
+                self.pending.drain(..8);
+                self.data_left =
+                    Some(if len == 0 || len == u32::MAX as usize { usize::MAX } else { len });
+                return Ok(true);
+            }
+
+            // Chunks are padded to an even length
+            let chunk_end = 8 + len + len % 2;
+            if self.pending.len() < chunk_end {
+                return Ok(false);
+            }
+            if &id == b"fmt " {
+                let (format, encoding) = read_wav_format(&self.pending[8..8 + len])?;
+                self.format = Some(format);
+                self.encoding = Some(encoding);
+            }
+            self.pending.drain(..chunk_end);
+        }
+    }
+}
+
+fn read_wav_format(chunk: &[u8]) -> Result<(AudioFormat, WavEncoding), AudioError> {
+    if chunk.len() < 16 {
+        return Err(AudioError::InvalidWav("format chunk is too short"));
+    }
+    let mut tag = u16_at(chunk, 0);
+    let channels = u16_at(chunk, 2);
+    let sample_rate = u32_at(chunk, 4);
+    let bits = u16_at(chunk, 14);
+    // WAVE_FORMAT_EXTENSIBLE keeps the real format at the start of a GUID
+    if tag == 0xFFFE && chunk.len() >= 26 {
+        tag = u16_at(chunk, 24);
+    }
+
+    let encoding = match (tag, bits) {
+        (1, 8 | 16 | 24 | 32) => WavEncoding::Int(bits as usize / 8),
+        (3, 32) => WavEncoding::Float,
+        (1 | 3, _) => return Err(AudioError::UnsupportedWav(format!("{bits}-bit"))),
+        (tag, _) => return Err(AudioError::UnsupportedWav(format!("Format {tag:#06X}"))),
+    };
+    if channels == 0 || sample_rate == 0 {
+        return Err(AudioError::InvalidWav("no channels or sample rate"));
+    }
+    Ok((AudioFormat { sample_rate, channels }, encoding))
+}
+
+/// Decodes the first Vorbis stream in an Ogg file.
+struct OggDecoder {
+    pending: Vec<u8>,
+    /// The stream being decoded, from its first page.
+    serial: Option<u32>,
+    /// A packet which continues on the next page.
+    packet: Vec<u8>,
+    ident: Option<IdentHeader>,
+    comment_read: bool,
+    setup: Option<SetupHeader>,
+    window: PreviousWindowRight,
+}
+
+impl Default for OggDecoder {
+    fn default() -> Self {
+        OggDecoder {
+            pending: Vec::new(),
+            serial: None,
+            packet: Vec::new(),
+            ident: None,
+            comment_read: false,
+            setup: None,
+            window: PreviousWindowRight::new(),
+        }
+    }
+}
+
+impl OggDecoder {
+    fn push(&mut self, data: &[u8], out: &mut Vec<i16>) -> Result<(), AudioError> {
+        self.pending.extend_from_slice(data);
+        let pending = std::mem::take(&mut self.pending);
+        let mut offset = 0;
+        let result = loop {
+            match self.read_page(&pending[offset..], out) {
+                Ok(Some(page_len)) => offset += page_len,
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        self.pending = pending[offset..].to_vec();
+        result
+    }
+
+    /// Decodes the packets completed by the page at the start of `data`, returning the
+    /// page's length, or `None` if `data` doesn't hold all of it yet.
+    fn read_page(&mut self, data: &[u8], out: &mut Vec<i16>) -> Result<Option<usize>, AudioError> {
+        if data.len() < 27 {
+            return Ok(None);
+        }
+        if &data[..4] != b"OggS" {
+            return Err(AudioError::InvalidOgg("missing page header"));
+        }
+        let segments = data[26] as usize;
+        let Some(lacing) = data.get(27..27 + segments) else {
+            return Ok(None);
+        };
+        let body_start = 27 + segments;
+        let page_len = body_start + lacing.iter().map(|&len| len as usize).sum::<usize>();
+        if data.len() < page_len {
+            return Ok(None);
+        }
+
+        let serial = u32_at(data, 14);
+        if *self.serial.get_or_insert(serial) != serial {
+            return Ok(Some(page_len));
+        }
+        let mut segment_start = body_start;
+        for &len in lacing {
+            let segment_end = segment_start + len as usize;
+            self.packet.extend_from_slice(&data[segment_start..segment_end]);
+            segment_start = segment_end;
+            // A segment shorter than 255 bytes ends its packet
+            if len < 255 {
+                let packet = std::mem::take(&mut self.packet);
+                self.read_packet(&packet, out)?;
+            }
+        }
+        Ok(Some(page_len))
+    }
+
+    fn read_packet(&mut self, packet: &[u8], out: &mut Vec<i16>) -> Result<(), AudioError> {
+        let vorbis_error = |e: &dyn std::fmt::Display| AudioError::Vorbis(e.to_string());
+        let Some(ident) = &self.ident else {
+            self.ident =
+                Some(lewton::header::read_header_ident(packet).map_err(|e| vorbis_error(&e))?);
+            return Ok(());
+        };
+        if !self.comment_read {
+            lewton::header::read_header_comment(packet).map_err(|e| vorbis_error(&e))?;
+            self.comment_read = true;
+            return Ok(());
+        }
+        let Some(setup) = &self.setup else {
+            let blocksizes = (ident.blocksize_0, ident.blocksize_1);
+            let setup = lewton::header::read_header_setup(packet, ident.audio_channels, blocksizes)
+                .map_err(|e| vorbis_error(&e))?;
+            self.setup = Some(setup);
+            return Ok(());
+        };
+
+        let decoded: InterleavedSamples<i16> =
+            read_audio_packet_generic(ident, setup, packet, &mut self.window)
+                .map_err(|e| vorbis_error(&e))?;
+        out.extend(decoded.samples);
+        Ok(())
+    }
+}
+
+/// Plays an [`AudioClip`] from a frame onwards.
+struct ClipSource {
+    samples: Arc<[i16]>,
+    next: usize,
+    format: AudioFormat,
+}
+
+impl Iterator for ClipSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.samples.get(self.next).copied();
+        self.next += 1;
+        sample
+    }
+}
+
+impl rodio::Source for ClipSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.format.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.format.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// A clip playing, or paused, on the default output device. Dropping it stops playback.
+pub struct Player {
+    /// The path of the file being played, to find its tab.
+    pub path: String,
+    clip: AudioClip,
+    /// The frame playback started from.
+    start: usize,
+    sink: rodio::Sink,
+    // Kept alive for as long as the sink plays through it
+    _stream: rodio::OutputStream,
+}
+
+impl Player {
+    /// Starts playing `clip` from frame `start`.
+    pub fn play(path: String, clip: &AudioClip, start: usize) -> Result<Self, String> {
+        let (stream, handle) = rodio::OutputStream::try_default().map_err(|e| e.to_string())?;
+        let sink = rodio::Sink::try_new(&handle).map_err(|e| e.to_string())?;
+        let start = start.min(clip.frames());
+        sink.append(ClipSource {
+            samples: Arc::clone(&clip.samples),
+            next: start * clip.format.channels as usize,
+            format: clip.format,
+        });
+        Ok(Player { path, clip: clip.clone(), start, sink, _stream: stream })
+    }
+
+    /// The frame being played.
+    pub fn position(&self) -> usize {
+        let played = self.sink.get_pos().as_secs_f64() * self.clip.format.sample_rate as f64;
+        (self.start + played as usize).min(self.clip.frames())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    pub fn toggle_pause(&self) {
+        if self.sink.is_paused() {
+            self.sink.play();
+        } else {
+            self.sink.pause();
+        }
+    }
+
+    /// Whether the whole clip has been played.
+    pub fn finished(&self) -> bool {
+        self.sink.empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(format_tag: u16, bits: u16, samples: &[u8]) -> Vec<u8> {
+        let mut data = b"RIFF\0\0\0\0WAVE".to_vec();
+        data.extend(b"LIST\x03\0\0\0abc\0");
+        data.extend(b"fmt \x10\0\0\0");
+        data.extend(format_tag.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        data.extend(22050u32.to_le_bytes());
+        data.extend([0; 6]);
+        data.extend(bits.to_le_bytes());
+        data.extend(b"data");
+        data.extend((samples.len() as u32).to_le_bytes());
+        data.extend(samples);
+        data.extend(b"junk");
+        data
+    }
+
+    /// Decodes `data` pushed `chunk_len` bytes at a time.
+    fn decode(
+        data: &[u8],
+        chunk_len: usize,
+    ) -> Result<(Option<AudioFormat>, Vec<i16>), AudioError> {
+        let mut decoder = StreamDecoder::default();
+        let mut out = Vec::new();
+        for chunk in data.chunks(chunk_len) {
+            decoder.push(chunk, &mut out)?;
+        }
+        decoder.finish()?;
+        Ok((decoder.format(), out))
+    }
+
+    #[test]
+    fn decodes_wav_in_any_size_of_chunk() {
+        let samples = [0x00, 0x80, 0xFF, 0x7F, 0x34, 0x12, 0, 0];
+        let expected = [i16::MIN, i16::MAX, 0x1234, 0];
+        for chunk_len in [1, 3, 7, 1000] {
+            let (format, out) = decode(&wav(1, 16, &samples), chunk_len).unwrap();
+            assert_eq!(format, Some(AudioFormat { sample_rate: 22050, channels: 2 }));
+            assert_eq!(out, expected, "chunks of {chunk_len}");
+        }
+
+        let (_, out) = decode(&wav(1, 8, &[0, 128, 255, 128]), 5).unwrap();
+        assert_eq!(out, [i16::MIN, 0, 0x7F00, 0]);
+        let (_, out) = decode(&wav(1, 24, &[0xFF, 0x34, 0x12, 0, 0, 0x80]), 5).unwrap();
+        assert_eq!(out, [0x1234, i16::MIN]);
+        let float: Vec<u8> = [1.0f32, -0.5].iter().flat_map(|f| f.to_le_bytes()).collect();
+        let (_, out) = decode(&wav(3, 32, &float), 5).unwrap();
+        assert_eq!(out, [i16::MAX, -16383]);
+    }
+
+    #[test]
+    fn rejects_unsupported_audio() {
+        assert!(matches!(decode(&wav(2, 4, &[0; 4]), 64), Err(AudioError::UnsupportedWav(_))));
+        assert!(matches!(decode(b"ID3\x04 mp3", 64), Err(AudioError::Unrecognized)));
+        assert!(matches!(decode(b"RIFF\0\0\0\0WAVE", 64), Err(AudioError::InvalidWav(_))));
+    }
+
+    #[test]
+    fn reads_packets_across_ogg_pages() {
+        // A page with a packet continued on a later page, then one from another stream
+        let page = |serial: u32, lacing: &[u8], body: &[u8]| {
+            let mut page = b"OggS\0\0".to_vec();
+            page.extend([0; 8]);
+            page.extend(serial.to_le_bytes());
+            page.extend([0; 8]);
+            page.push(lacing.len() as u8);
+            page.extend(lacing);
+            page.extend(body);
+            page
+        };
+        let mut data = page(1, &[255], &[7; 255]);
+        data.extend(page(2, &[1], &[9]));
+        data.extend(page(1, &[0], &[]));
+
+        let mut decoder = OggDecoder::default();
+        // The joined packet isn't a Vorbis header, so the decoder reports it
+        let error = decoder.push(&data, &mut Vec::new()).unwrap_err();
+        assert!(matches!(error, AudioError::Vorbis(_)));
+        assert_eq!(decoder.serial, Some(1));
+    }
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(format_time(Duration::from_millis(65_349)), "1:05.3");
+        assert_eq!(format_time(Duration::ZERO), "0:00.0");
+    }
+
+    #[test]
+    fn summarizes_waveforms() {
+        let format = AudioFormat { sample_rate: 8000, channels: 2 };
+        let mut builder = WaveformBuilder::new(format);
+        let mut peaks = Vec::new();
+        let mut samples = vec![0i16; PEAK_FRAMES * 2];
+        samples[3] = -5;
+        samples[10] = 7;
+        builder.push(&samples[..100], &mut peaks);
+        builder.push(&samples[100..], &mut peaks);
+        builder.push(&[1, 2], &mut peaks);
+        builder.finish(&mut peaks);
+        assert_eq!(peaks, [[-5, 7], [1, 2]]);
+    }
+}
@@ -0,0 +1,58 @@
+//! Showing binary files, which can't be opened as text.
+
+use std::fmt::Write as _;
+
+/// At most this many bytes of a binary file are rendered by [`hexdump`].
+const HEXDUMP_LIMIT: usize = 1024 * 1024;
+
+/// Bytes shown on each line of a hexdump.
+pub const HEX_ROW_LEN: usize = 16;
+
+/// Renders `data` as offset/hex/ASCII lines for display in a text view.
+pub fn hexdump(data: &[u8]) -> String {
+    let shown = &data[..data.len().min(HEXDUMP_LIMIT)];
+
+    let mut out = String::with_capacity(shown.len() * 4 + 64);
+    for row in 0..shown.len().div_ceil(HEX_ROW_LEN) {
+        write_hex_row(&mut out, shown, row);
+        out.push('\n');
+    }
+
+    if shown.len() < data.len() {
+        let _ = writeln!(out, "[... {} more bytes not shown]", data.len() - shown.len());
+    }
+
+    out
+}
+
+/// Line `row` of `data`'s hexdump, for views which only render the rows on screen.
+pub fn hex_row(data: &[u8], row: usize) -> String {
+    let mut out = String::with_capacity(80);
+    write_hex_row(&mut out, data, row);
+    out
+}
+
+fn write_hex_row(out: &mut String, data: &[u8], row: usize) {
+    let start = (row * HEX_ROW_LEN).min(data.len());
+    let line = &data[start..(start + HEX_ROW_LEN).min(data.len())];
+    let _ = write!(out, "{start:08X}  ");
+    for i in 0..HEX_ROW_LEN {
+        match line.get(i) {
+            Some(b) => {
+                let _ = write!(out, "{b:02X} ");
+            }
+            None => out.push_str("   "),
+        }
+        if i == 7 {
+            out.push(' ');
+        }
+    }
+    out.push_str(" |");
+    out.extend(line.iter().copied().map(printable));
+    out.push('|');
+}
+
+/// How a byte is shown in a hexdump's ASCII column.
+pub fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }
+}
@@ -0,0 +1,259 @@
+//! Diagnostic bundles for crashes.
+//!
+//! [`install_panic_hook`] turns any panic into a plain text report holding the panic
+//! message and location, the most recent log lines (see [`RecentLogs`]) and a summary of
+//! the loaded workspace. The report is kept somewhere that survives the app dying, and the
+//! app offers to save it, either straight away if it kept running (e.g. a background thread
+//! panicked) or the next time it starts. Nothing is ever sent anywhere.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::panic::PanicHookInfo;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+/// Only the most recent log lines are kept for reports.
+const MAX_LOG_LINES: usize = 200;
+
+static RECENT_LOGS: Mutex<LogLines> = Mutex::new(LogLines::new(MAX_LOG_LINES));
+static WORKSPACE_SUMMARY: Mutex<String> = Mutex::new(String::new());
+/// A report from a panic this run which the app hasn't picked up yet.
+static PENDING_REPORT: Mutex<Option<String>> = Mutex::new(None);
+
+/// The last lines written to a log, oldest first.
+struct LogLines {
+    lines: VecDeque<String>,
+    max_lines: usize,
+}
+
+impl LogLines {
+    const fn new(max_lines: usize) -> Self {
+        LogLines { lines: VecDeque::new(), max_lines }
+    }
+
+    fn push(&mut self, text: &str) {
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            if self.lines.len() == self.max_lines {
+                self.lines.pop_front();
+            }
+            self.lines.push_back(line.to_string());
+        }
+    }
+}
+
+/// A `tracing_subscriber` writer which keeps the most recent log lines for crash reports.
+/// Use it without ANSI colors, since the lines end up in a text file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecentLogs;
+
+impl std::io::Write for RecentLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        RECENT_LOGS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for RecentLogs {
+    type Writer = RecentLogs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        *self
+    }
+}
+
+/// Sets the workspace description included in crash reports, e.g. when archives load.
+pub(crate) fn set_workspace_summary(summary: String) {
+    *WORKSPACE_SUMMARY.lock().unwrap_or_else(PoisonError::into_inner) = summary;
+}
+
+/// Writes a crash report for every panic, then runs the hook which was installed before.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = build_report(info);
+        saved::write(&report);
+        if let Ok(mut pending) = PENDING_REPORT.try_lock() {
+            *pending = Some(report);
+        }
+        previous(info);
+    }));
+}
+
+/// A report from a panic since the last call, if there was one.
+pub(crate) fn take_pending_report() -> Option<String> {
+    PENDING_REPORT.try_lock().ok()?.take()
+}
+
+/// The report saved by a crash before the app last exited, if there was one.
+pub(crate) fn saved_report() -> Option<String> {
+    saved::read()
+}
+
+/// Forgets the saved report once the user has saved or dismissed it.
+pub(crate) fn discard_saved_report() {
+    saved::remove();
+}
+
+fn build_report(info: &PanicHookInfo<'_>) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "(no message)".to_string());
+    let location = info.location().map(|location| location.to_string());
+    let thread = std::thread::current().name().map(str::to_string);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let backtrace = Some(std::backtrace::Backtrace::force_capture().to_string());
+    #[cfg(target_arch = "wasm32")]
+    let backtrace = None;
+
+    // Locks are only tried: the panic may have happened while one was held
+    let workspace = WORKSPACE_SUMMARY.try_lock().map(|summary| summary.clone()).ok();
+    let logs = RECENT_LOGS.try_lock().map(|logs| Vec::from(logs.lines.clone())).ok();
+
+    format_report(&Crash { message, location, thread, backtrace, workspace, logs })
+}
+
+/// Everything a report says about one panic.
+struct Crash {
+    message: String,
+    location: Option<String>,
+    thread: Option<String>,
+    backtrace: Option<String>,
+    /// `None` if it couldn't be read when the panic happened.
+    workspace: Option<String>,
+    logs: Option<Vec<String>>,
+}
+
+fn format_report(crash: &Crash) -> String {
+    let unknown = "(unknown)";
+    let unavailable = "(unavailable)";
+    let mut report = String::new();
+    let _ = writeln!(report, "Enfusion Tools crash report");
+    let _ = writeln!(report, "Version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "Platform: {}-{}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(report, "Thread: {}", crash.thread.as_deref().unwrap_or(unknown));
+    let _ = writeln!(report, "Location: {}", crash.location.as_deref().unwrap_or(unknown));
+    let _ = writeln!(report, "Panic: {}", crash.message);
+
+    if let Some(backtrace) = &crash.backtrace {
+        let _ = writeln!(report, "\nBacktrace:\n{}", backtrace.trim_end());
+    }
+
+    let workspace = match crash.workspace.as_deref() {
+        Some("") => "(nothing loaded)",
+        Some(summary) => summary,
+        None => unavailable,
+    };
+    let _ = writeln!(report, "\nWorkspace:\n{}", workspace.trim_end());
+
+    let _ = writeln!(report, "\nRecent log:");
+    match &crash.logs {
+        Some(logs) => {
+            for line in logs {
+                let _ = writeln!(report, "{line}");
+            }
+        }
+        None => {
+            let _ = writeln!(report, "{unavailable}");
+        }
+    }
+    report
+}
+
+/// Where a report waits for the next start: a file in the temp directory.
+#[cfg(not(target_arch = "wasm32"))]
+mod saved {
+    use std::path::PathBuf;
+
+    fn path() -> PathBuf {
+        std::env::temp_dir().join("enfusion_tools_crash_report.txt")
+    }
+
+    pub(super) fn write(report: &str) {
+        let path = path();
+        match std::fs::write(&path, report) {
+            Ok(()) => eprintln!(
+                "A crash report was saved to {}. The app will offer it the next time it starts.",
+                path.display()
+            ),
+            Err(e) => eprintln!("Failed to save a crash report to {}: {e}", path.display()),
+        }
+    }
+
+    pub(super) fn read() -> Option<String> {
+        std::fs::read_to_string(path()).ok()
+    }
+
+    pub(super) fn remove() {
+        let _ = std::fs::remove_file(path());
+    }
+}
+
+/// Where a report waits for the next start: the page's local storage, since a panic stops
+/// the app until the page is reloaded.
+#[cfg(target_arch = "wasm32")]
+mod saved {
+    const KEY: &str = "enfusion_tools_crash_report";
+
+    fn storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub(super) fn write(report: &str) {
+        if let Some(storage) = storage() {
+            let _ = storage.set_item(KEY, report);
+        }
+    }
+
+    pub(super) fn read() -> Option<String> {
+        storage()?.get_item(KEY).ok()?
+    }
+
+    pub(super) fn remove() {
+        if let Some(storage) = storage() {
+            let _ = storage.remove_item(KEY);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recent_log_lines() {
+        let mut logs = LogLines::new(3);
+        logs.push("one\ntwo\n");
+        logs.push("three\n\n");
+        logs.push("four\n");
+        assert_eq!(logs.lines, ["two", "three", "four"]);
+    }
+
+    #[test]
+    fn reports_say_what_was_unavailable() {
+        let report = format_report(&Crash {
+            message: "index out of bounds".to_string(),
+            location: Some("src/app.rs:10:5".to_string()),
+            thread: None,
+            backtrace: None,
+            workspace: Some(String::new()),
+            logs: None,
+        });
+        assert!(report.contains("Panic: index out of bounds\n"));
+        assert!(report.contains("Location: src/app.rs:10:5\n"));
+        assert!(report.contains("Thread: (unknown)\n"));
+        assert!(report.contains("Workspace:\n(nothing loaded)\n"));
+        assert!(report.ends_with("Recent log:\n(unavailable)\n"));
+        assert!(!report.contains("Backtrace"));
+    }
+}
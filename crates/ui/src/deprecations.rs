@@ -0,0 +1,149 @@
+//! Finding uses of deprecated or renamed engine APIs in Enforce Script sources.
+//!
+//! Rules are loaded from a TOML file with one `[[deprecated]]` table per API:
+//!
+//! ```toml
+//! [[deprecated]]
+//! api = "SCR_Example.OldMethod"
+//! replacement = "SCR_Example.NewMethod"
+//! note = "Renamed in 1.2"
+//!
+//! [[deprecated]]
+//! # Treat `api` as a regular expression instead of a call to match literally
+//! api = 'GetGame\(\)\.GetOld\w+'
+//! regex = true
+//! ```
+//!
+//! Literal APIs only match when called, i.e. when followed by `(`. Line comments are
+//! ignored.
+
+use regex::Regex;
+
+use crate::task::LineNumber;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RulesError {
+    #[error("invalid rules file: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid pattern for {api:?}: {source}")]
+    Pattern { api: String, source: regex::Error },
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    deprecated: Vec<RuleDefinition>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RuleDefinition {
+    api: String,
+    replacement: Option<String>,
+    note: Option<String>,
+    #[serde(default)]
+    regex: bool,
+}
+
+/// A deprecated API and how to find calls to it.
+#[derive(Debug, Clone)]
+pub struct DeprecationRule {
+    pub api: String,
+    pub replacement: Option<String>,
+    pub note: Option<String>,
+    pattern: Regex,
+}
+
+/// Parses and compiles the rules in a TOML rules file.
+pub fn parse_rules(toml: &str) -> Result<Vec<DeprecationRule>, RulesError> {
+    let file: RulesFile = toml::from_str(toml)?;
+
+    file.deprecated
+        .into_iter()
+        .map(|definition| {
+            let pattern = if definition.regex {
+                definition.api.clone()
+            } else {
+                let boundary =
+                    if definition.api.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                        r"\b"
+                    } else {
+                        ""
+                    };
+                format!(r"{boundary}{}\s*\(", regex::escape(&definition.api))
+            };
+
+            let pattern = Regex::new(&pattern)
+                .map_err(|source| RulesError::Pattern { api: definition.api.clone(), source })?;
+
+            Ok(DeprecationRule {
+                api: definition.api,
+                replacement: definition.replacement,
+                note: definition.note,
+                pattern,
+            })
+        })
+        .collect()
+}
+
+/// One use of a deprecated API.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub line: LineNumber,
+    /// Index of the matching rule.
+    pub rule: usize,
+    pub text: String,
+}
+
+/// Finds every line of `script` which uses a deprecated API.
+pub fn scan_script(rules: &[DeprecationRule], script: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (idx, line) in script.lines().enumerate() {
+        let code = line.split_once("//").map(|(code, _comment)| code).unwrap_or(line);
+        for (rule_idx, rule) in rules.iter().enumerate() {
+            if rule.pattern.is_match(code) {
+                findings.push(Finding {
+                    line: LineNumber(idx + 1),
+                    rule: rule_idx,
+                    text: line.trim().to_string(),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_calls_outside_comments() {
+        let rules = parse_rules(
+            r#"
+[[deprecated]]
+api = "OldMethod"
+replacement = "NewMethod"
+
+[[deprecated]]
+api = 'GetGame\(\)\.GetOld\w+'
+regex = true
+"#,
+        )
+        .unwrap();
+
+        let script = "\
+void Foo()
+{
+    OldMethod(1);
+    MyOldMethod(1);
+    int OldMethod;
+    // OldMethod(2);
+    GetGame().GetOldManager();
+}";
+
+        let found: Vec<_> =
+            scan_script(&rules, script).into_iter().map(|f| (f.line.0, f.rule)).collect();
+        assert_eq!(found, [(3, 0), (7, 1)]);
+    }
+}
@@ -0,0 +1,66 @@
+//! File dialogs for choosing archives.
+
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use enfusion_pak::archive_format::ArchiveFormat;
+
+/// Which dialog a remembered directory belongs to, so that e.g. picking the base build
+/// of a diff doesn't move where "Open Files" starts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum DialogKind {
+    Open,
+    DiffBase,
+    DiffModified,
+    /// Another build whose files editor tabs can be compared against.
+    Compare,
+    /// An unpacked mod folder to overlay on the workspace.
+    Folder,
+}
+
+impl DialogKind {
+    fn title(self) -> &'static str {
+        match self {
+            DialogKind::Open => "Open Archives",
+            DialogKind::DiffBase => "Choose Base Files",
+            DialogKind::DiffModified => "Choose Changed Files",
+            DialogKind::Compare => "Choose Build to Compare",
+            DialogKind::Folder => "Choose Mod Folder",
+        }
+    }
+}
+
+/// An archive picker for `kind`, starting in `directory` if given.
+pub fn archive_dialog(kind: DialogKind, directory: Option<&Path>) -> rfd::AsyncFileDialog {
+    let supported: Vec<&str> = ArchiveFormat::all_extensions().collect();
+    let mut dialog = rfd::AsyncFileDialog::new()
+        .set_title(kind.title())
+        .add_filter("Supported archives", &supported);
+    for format in ArchiveFormat::ALL {
+        dialog = dialog.add_filter(format!("{} files", format.name()), format.extensions());
+    }
+
+    if let Some(directory) = directory {
+        dialog = dialog.set_directory(directory);
+    }
+
+    dialog
+}
+
+/// A folder picker for unpacked mod data, starting in `directory` if given.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn folder_dialog(directory: Option<&Path>) -> rfd::AsyncFileDialog {
+    let mut dialog = rfd::AsyncFileDialog::new().set_title(DialogKind::Folder.title());
+    if let Some(directory) = directory {
+        dialog = dialog.set_directory(directory);
+    }
+
+    dialog
+}
+
+/// The directory the user picked `files` from.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn picked_directory(files: &[rfd::FileHandle]) -> Option<PathBuf> {
+    files.first()?.path().parent().map(Path::to_path_buf)
+}
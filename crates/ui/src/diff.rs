@@ -1,141 +1,468 @@
+use cfg_parser::diff::ConfigChange;
+use cfg_parser::diff::diff_entries;
+use cfg_parser::pretty::pretty_print;
 use egui::Color32;
 use egui::FontId;
 use egui::TextFormat;
+use enfusion_pak::diff::ChangeKind;
+use enfusion_pak::diff::ContentHasher;
+use enfusion_pak::diff::FileSummary;
+use enfusion_pak::diff::content_hash;
+use enfusion_pak::diff::diff_listings;
+use enfusion_pak::diff::write_manifest;
 use enfusion_pak::vfs::async_vfs::AsyncVfsPath;
+use enfusion_search::text::decode_text;
 use futures::io::AsyncRead;
 use futures::io::AsyncReadExt;
-use futures::io::AsyncSeek;
-use futures::io::AsyncSeekExt;
-use futures::io::SeekFrom;
+use similar::Algorithm;
 use similar::ChangeTag;
+use similar::DiffOp;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::Mutex;
+use tracing::error;
 
 use egui::text::LayoutJob;
 use enfusion_pak::vfs::VfsPath;
 
+use crate::binary::hexdump;
+use crate::binary::printable;
+use crate::palette::Palette;
+use crate::settings;
+use crate::shutdown;
+use crate::syntax;
 use crate::task;
+use crate::task::ArchiveLayer;
 use crate::task::LoadedFiles;
 
+/// Which differences a line diff leaves out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Compare lines with their whitespace removed, and skip blank lines.
+    pub ignore_whitespace: bool,
+    /// Compare scripts with their comments removed, and skip lines holding only a comment.
+    pub ignore_comments: bool,
+}
+
+/// A result's rendered contents or diff, and the options it was rendered with.
+pub type RenderedDiff = Arc<Mutex<Option<(DiffOptions, Arc<LayoutJob>)>>>;
+
 #[derive(Debug, Clone)]
 pub enum DiffResult {
     Added {
         path: VfsPath,
         overlay: AsyncVfsPath,
-        data: Arc<Mutex<Option<Arc<LayoutJob>>>>,
+        data: RenderedDiff,
+    },
+    /// Only in the base build, whose overlay `overlay` is.
+    Removed {
+        path: VfsPath,
+        overlay: AsyncVfsPath,
+        data: RenderedDiff,
     },
     Changed {
         base_path: VfsPath,
         base_overlay: AsyncVfsPath,
         modified_path: VfsPath,
         modified_overlay: AsyncVfsPath,
-        data: Arc<Mutex<Option<Arc<LayoutJob>>>>,
+        data: RenderedDiff,
+        /// Changes are found from metadata, so the contents may turn out to be the same.
+        contents: Arc<Mutex<ContentCheck>>,
+        /// The changed keys of a text config, filled in once they've been compared.
+        structure: Arc<Mutex<Option<Arc<LayoutJob>>>>,
     },
 }
 
+/// Whether a change found from the files' metadata has been confirmed by reading them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContentCheck {
+    #[default]
+    Unchecked,
+    Checking,
+    /// Only the metadata differs.
+    Identical,
+    Different,
+}
+
+/// Summarizes `path` as found in the highest priority layer which has it, without reading
+/// its data. Paks are looked up in their FILE chunk, other layers through their filesystem.
+pub fn summarize(layers: &[ArchiveLayer], path: &str) -> Option<FileSummary> {
+    layers.iter().find_map(|layer| match &layer.pak_meta {
+        Some(index) => index.get(path).map(FileSummary::from),
+        None => layer.file_size(path).map(FileSummary::with_len),
+    })
+}
+
 impl DiffResult {
     pub fn comparison_path(&self) -> &str {
         match self {
-            DiffResult::Added { path, .. } => path.as_str(),
+            DiffResult::Added { path, .. } | DiffResult::Removed { path, .. } => path.as_str(),
             DiffResult::Changed { base_path, .. } => base_path.as_str(),
         }
     }
-}
 
-pub async fn diff_builds(base: LoadedFiles, mut modified: LoadedFiles) -> Vec<DiffResult> {
-    let mut changes = Vec::new();
+    /// The color a result's path is shown in: the palette's color for added, removed or
+    /// changed files, and gray for changes which turned out to be metadata only.
+    pub fn color(&self) -> Color32 {
+        let palette = settings::palette();
+        match self {
+            DiffResult::Added { .. } => palette.added,
+            DiffResult::Removed { .. } => palette.removed,
+            DiffResult::Changed { contents, .. } => match *contents.lock().unwrap() {
+                ContentCheck::Identical => Color32::GRAY,
+                _ => palette.changed,
+            },
+        }
+    }
+
+    /// How far a changed file's contents have been compared. `None` for added and removed
+    /// files.
+    pub fn content_check(&self) -> Option<ContentCheck> {
+        match self {
+            DiffResult::Added { .. } | DiffResult::Removed { .. } => None,
+            DiffResult::Changed { contents, .. } => Some(*contents.lock().unwrap()),
+        }
+    }
 
-    for (key, base_vfs_path) in base.known_paths.iter() {
-        let Some(modified_vfs_path) = modified.known_paths.remove(key) else { continue };
+    /// Compares a changed file's contents in both builds, unless that's been done already.
+    pub async fn check_contents(&self) {
+        let DiffResult::Changed {
+            base_path,
+            base_overlay,
+            modified_path,
+            modified_overlay,
+            contents,
+            ..
+        } = self
+        else {
+            return;
+        };
 
-        if base_vfs_path.is_dir().unwrap()
-            || (!base_vfs_path.as_str().starts_with("/scripts")
-                && !base_vfs_path.as_str().starts_with("/Configs"))
         {
-            continue;
+            let mut check = contents.lock().unwrap();
+            if *check != ContentCheck::Unchecked {
+                return;
+            }
+            *check = ContentCheck::Checking;
         }
 
-        // Check if the contents of these files are different.
+        let base = base_overlay.join(base_path.as_str()).unwrap();
+        let modified = modified_overlay.join(modified_path.as_str()).unwrap();
+        let result = match (base.open_file().await, modified.open_file().await) {
+            (Ok(base), Ok(modified)) => {
+                match (hash_stream(base).await, hash_stream(modified).await) {
+                    (Ok(base), Ok(modified)) => Ok(base == modified),
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => Err(std::io::Error::other(e)),
+        };
 
-        // Fast path for different file sizes
-        if base_vfs_path.metadata().unwrap().len != modified_vfs_path.metadata().unwrap().len {
-            changes.push(DiffResult::Changed {
-                base_path: base_vfs_path.clone(),
-                base_overlay: base.async_overlay_fs.clone(),
-                modified_path: modified_vfs_path,
-                modified_overlay: modified.async_overlay_fs.clone(),
-                data: Default::default(),
-            });
-            continue;
+        *contents.lock().unwrap() = match result {
+            Ok(true) => ContentCheck::Identical,
+            Ok(false) => ContentCheck::Different,
+            Err(e) => {
+                error!(%e, path = base_path.as_str(), "failed to compare file contents");
+                ContentCheck::Unchecked
+            }
+        };
+    }
+
+    /// The rendered file contents or diff, once it has been built with `options`. The
+    /// contents of added and removed files don't depend on them.
+    pub fn rendered(&self, options: DiffOptions) -> Option<Arc<LayoutJob>> {
+        let (DiffResult::Added { data, .. }
+        | DiffResult::Removed { data, .. }
+        | DiffResult::Changed { data, .. }) = self;
+        match (self, &*data.lock().unwrap()) {
+            (DiffResult::Added { .. } | DiffResult::Removed { .. }, Some((_, job))) => {
+                Some(Arc::clone(job))
+            }
+            (_, Some((rendered_with, job))) if *rendered_with == options => Some(Arc::clone(job)),
+            _ => None,
         }
+    }
 
-        // Sizes are the same, let's compare contents
-        // let base_avfs_path = base.async_overlay_fs.join(&key.0.0).unwrap();
-        // let modified_avfs_path = modified.async_overlay_fs.join(&key.0.0).unwrap();
+    /// The rendered structural diff of a changed text config, if the file is one. Empty
+    /// until [`DiffResult::render_structure`] has built it.
+    pub fn structure(&self) -> Option<&Arc<Mutex<Option<Arc<LayoutJob>>>>> {
+        match self {
+            DiffResult::Changed { modified_path, structure, .. }
+                if syntax::is_text_config(modified_path.as_str()) =>
+            {
+                Some(structure)
+            }
+            _ => None,
+        }
+    }
 
-        // let base_reader = base_avfs_path.open_file().await.unwrap();
-        // let modified_reader = modified_avfs_path.open_file().await.unwrap();
+    /// Compares the entries of a changed text config in both builds, if that hasn't been
+    /// done already.
+    pub async fn render_structure(&self) {
+        let DiffResult::Changed {
+            base_path,
+            base_overlay,
+            modified_path,
+            modified_overlay,
+            structure,
+            ..
+        } = self
+        else {
+            return;
+        };
+        if structure.lock().unwrap().is_some() {
+            return;
+        }
 
-        // if !streams_equal(base_reader, modified_reader).await.expect("failed to compare streams") {
-        //     changes.push(DiffResult::Changed {
-        //         base_path: base_vfs_path.clone(),
-        //         base_overlay: base.async_overlay_fs.clone(),
-        //         modified_path: modified_vfs_path,
-        //         modified_overlay: modified.async_overlay_fs.clone(),
-        //         data: Default::default(),
-        //     });
-        // }
+        let base = base_overlay.join(base_path.as_str()).unwrap();
+        let modified = modified_overlay.join(modified_path.as_str()).unwrap();
+        let base = task::read_file_data(base).await.and_then(|data| decode_text(&data));
+        let modified = task::read_file_data(modified).await.and_then(|data| decode_text(&data));
+        let job = match (base, modified) {
+            (Some(base), Some(modified)) => structural_diff_job(&base, &modified),
+            _ => message_job("Couldn't read the file as text in both builds"),
+        };
+
+        *structure.lock().unwrap() = Some(job.into());
     }
 
-    for (_, file) in modified.known_paths {
-        if !file.as_str().starts_with("/scripts") && !file.as_str().starts_with("/Configs") {
-            continue;
+    /// Builds the rendered file contents or diff if it hasn't been already with `options`
+    /// and returns it.
+    pub async fn render(&self, options: DiffOptions) -> Arc<LayoutJob> {
+        if let Some(job) = self.rendered(options) {
+            return job;
+        }
+
+        match self {
+            DiffResult::Added { path, overlay, data }
+            | DiffResult::Removed { path, overlay, data } => {
+                let file = overlay.join(path.as_str()).unwrap();
+                let mut job = LayoutJob::default();
+                if let Some(data) = task::read_file_data(file).await {
+                    match decode_text(&data) {
+                        Some(mut text) => {
+                            if syntax::wants_pretty_print(path.as_str(), &text) {
+                                text = pretty_print(&text);
+                            }
+                            job.append(text.as_str(), 0.0, Default::default());
+                        }
+                        None => job.append(
+                            &format!("{}\n{}", binary_summary(&data), hexdump(&data)),
+                            0.0,
+                            TextFormat { font_id: FontId::monospace(12.0), ..Default::default() },
+                        ),
+                    }
+                }
+                *data.lock().unwrap() = Some((options, job.into()));
+            }
+            DiffResult::Changed {
+                base_path,
+                base_overlay,
+                modified_path,
+                modified_overlay,
+                data,
+                ..
+            } => {
+                let base = base_overlay.join(base_path.as_str()).unwrap();
+                let modified = modified_overlay.join(modified_path.as_str()).unwrap();
+                build_file_diff(base, modified, options, Arc::clone(data)).await;
+            }
         }
-        changes.push(DiffResult::Added {
-            path: file,
-            overlay: modified.async_overlay_fs.clone(),
-            data: Default::default(),
-        });
+
+        self.rendered(options).unwrap_or_default()
     }
+}
 
-    changes.sort_by(|a, b| a.comparison_path().cmp(b.comparison_path()));
+/// How many results a [`DiffTree`] folder holds, including those in its subfolders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
 
-    changes
+/// Diff results grouped by directory, so that large diffs can be reviewed a folder at a
+/// time.
+#[derive(Debug, Clone, Default)]
+pub struct DiffTree {
+    /// The folder's name, empty for the root.
+    pub name: String,
+    /// The folder's full path, used to remember whether it's expanded.
+    pub path: String,
+    pub folders: BTreeMap<String, DiffTree>,
+    /// Results for files directly in this folder, in path order.
+    pub files: Vec<DiffResult>,
+    pub counts: DiffCounts,
 }
 
-#[allow(unused)]
-async fn streams_equal<R1: AsyncRead + AsyncSeek + Unpin, R2: AsyncRead + AsyncSeek + Unpin>(
-    mut a: R1,
-    mut b: R2,
-) -> std::io::Result<bool> {
-    a.seek(SeekFrom::Start(0)).await?;
-    b.seek(SeekFrom::Start(0)).await?;
+impl DiffTree {
+    pub fn new(results: impl IntoIterator<Item = DiffResult>) -> Self {
+        let mut root = DiffTree::default();
+        for result in results {
+            let path = result.comparison_path().trim_start_matches('/');
+            let dirs = path.rsplit_once('/').map_or("", |(dirs, _)| dirs);
+
+            let mut folder = &mut root;
+            folder.count(&result);
+            for name in dirs.split('/').filter(|name| !name.is_empty()) {
+                let path = format!("{}/{name}", folder.path);
+                folder = folder.folders.entry(name.to_string()).or_insert_with(|| DiffTree {
+                    name: name.to_string(),
+                    path,
+                    ..Default::default()
+                });
+                folder.count(&result);
+            }
+            folder.files.push(result);
+        }
+
+        root.sort_files();
+        root
+    }
 
-    let mut buf1 = [0u8; 8192];
-    let mut buf2 = [0u8; 8192];
+    fn count(&mut self, result: &DiffResult) {
+        match result {
+            DiffResult::Added { .. } => self.counts.added += 1,
+            DiffResult::Removed { .. } => self.counts.removed += 1,
+            DiffResult::Changed { .. } => self.counts.changed += 1,
+        }
+    }
 
-    loop {
-        let n1 = a.read(&mut buf1).await?;
-        let n2 = b.read(&mut buf2).await?;
+    fn sort_files(&mut self) {
+        self.files.sort_by(|a, b| a.comparison_path().cmp(b.comparison_path()));
+        for folder in self.folders.values_mut() {
+            folder.sort_files();
+        }
+    }
+
+    /// Every result in the tree, a folder at a time.
+    pub fn results(&self) -> Vec<DiffResult> {
+        let DiffCounts { added, removed, changed } = self.counts;
+        let mut results = Vec::with_capacity(added + removed + changed);
+        self.collect_results(&mut results);
+        results
+    }
 
-        if n1 != n2 {
-            return Ok(false);
+    fn collect_results(&self, out: &mut Vec<DiffResult>) {
+        for folder in self.folders.values() {
+            folder.collect_results(out);
         }
-        if n1 == 0 {
-            return Ok(true);
+        out.extend(self.files.iter().cloned());
+    }
+}
+
+/// Finds the files added, removed or changed in `modified` by comparing what the builds
+/// record about them, without reading any file data. A change found this way may be metadata only,
+/// which [`DiffResult::check_contents`] confirms per file.
+pub async fn diff_builds(base: LoadedFiles, modified: LoadedFiles) -> Vec<DiffResult> {
+    let listing = |build: &LoadedFiles| {
+        build
+            .file_path_set
+            .iter()
+            .filter_map(|path| Some((path.to_string(), summarize(&build.layers, path)?)))
+            .collect::<Vec<_>>()
+    };
+
+    let mut results = Vec::new();
+    for change in diff_listings(listing(&base), listing(&modified)) {
+        let path = change.path.as_str();
+        let (Ok(base_path), Ok(modified_path)) =
+            (base.overlay_fs.join(path), modified.overlay_fs.join(path))
+        else {
+            continue;
+        };
+
+        match change.kind {
+            ChangeKind::Added => results.push(DiffResult::Added {
+                path: modified_path,
+                overlay: modified.async_overlay_fs.clone(),
+                data: Default::default(),
+            }),
+            ChangeKind::Changed => {
+                // Files of different sizes can't have the same contents
+                let contents = if change.may_be_metadata_only() {
+                    ContentCheck::Unchecked
+                } else {
+                    ContentCheck::Different
+                };
+                results.push(DiffResult::Changed {
+                    base_path,
+                    base_overlay: base.async_overlay_fs.clone(),
+                    modified_path,
+                    modified_overlay: modified.async_overlay_fs.clone(),
+                    data: Default::default(),
+                    contents: Arc::new(Mutex::new(contents)),
+                    structure: Default::default(),
+                })
+            }
+            ChangeKind::Removed => results.push(DiffResult::Removed {
+                path: base_path,
+                overlay: base.async_overlay_fs.clone(),
+                data: Default::default(),
+            }),
+        }
+    }
+
+    results
+}
+
+/// A manifest of every file in the workspace, as [`write_manifest`] formats it, for diffing
+/// builds with external tools. Files are hashed as stored, before any decompiling. A file
+/// which can't be read is listed without a hash. Returns `None` if the app started closing.
+pub async fn workspace_manifest(
+    root: AsyncVfsPath,
+    layers: Vec<ArchiveLayer>,
+    file_paths: Arc<HashSet<Arc<str>>>,
+) -> Option<Vec<u8>> {
+    let mut listing = Vec::with_capacity(file_paths.len());
+    for path in file_paths.iter() {
+        if shutdown::requested() {
+            return None;
         }
-        if buf1[..n1] != buf2[..n1] {
-            return Ok(false);
+        // Directories have no summary
+        let Some(summary) = summarize(&layers, path) else {
+            continue;
+        };
+
+        let hash = match root.join(&**path) {
+            Ok(file) => match file.open_file().await {
+                Ok(reader) => hash_stream(reader).await,
+                Err(e) => Err(std::io::Error::other(e)),
+            },
+            Err(e) => Err(std::io::Error::other(e)),
+        };
+        let hash = hash.map_err(|e| error!(?e, path = &**path, "failed to hash file")).ok();
+        listing.push((path, FileSummary { hash, ..FileSummary::with_len(summary.len) }));
+    }
+
+    let mut manifest = Vec::new();
+    write_manifest(listing, &mut manifest).expect("writing to a Vec can't fail");
+    Some(manifest)
+}
+
+/// Hashes everything `reader` yields, the same way the library hashes files it reads.
+async fn hash_stream(mut reader: impl AsyncRead + Unpin) -> std::io::Result<u64> {
+    let mut hasher = ContentHasher::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(hasher.finish());
         }
+        hasher.update(&buf[..read]);
     }
 }
 
 pub async fn build_file_diff(
     base: AsyncVfsPath,
     modified: AsyncVfsPath,
-    output: Arc<Mutex<Option<Arc<LayoutJob>>>>,
+    options: DiffOptions,
+    output: RenderedDiff,
 ) {
+    let path = modified.as_str().to_string();
     let Some(base_contents) = task::read_file_data(base).await else {
         return;
     };
@@ -143,32 +470,218 @@ pub async fn build_file_diff(
         return;
     };
 
-    let Ok(base_contents_str) = String::from_utf8(base_contents) else {
-        *output.lock().unwrap() = Some(LayoutJob::default().into());
+    let (Some(base_contents_str), Some(modified_contents_str)) =
+        (decode_text(&base_contents), decode_text(&modified_contents))
+    else {
+        let job = binary_diff_job(&base_contents, &modified_contents);
+        *output.lock().unwrap() = Some((options, job.into()));
         return;
     };
 
-    let Ok(modified_contents_str) = String::from_utf8(modified_contents) else {
-        *output.lock().unwrap() = Some(LayoutJob::default().into());
-        return;
+    let job = text_diff_job(&path, &base_contents_str, &modified_contents_str, options);
+    *output.lock().unwrap() = Some((options, job.into()));
+}
+
+/// Diffs `path` in `build` against `contents`, the version of it pinned in an editor tab.
+pub async fn diff_with_build(
+    build: task::ComparisonBuild,
+    path: String,
+    contents: String,
+    output: Arc<Mutex<Option<Arc<LayoutJob>>>>,
+) {
+    let other = match build.async_overlay_fs.join(&path) {
+        Ok(file) => task::read_file_data(file).await,
+        Err(_) => None,
+    };
+
+    let job = match other.as_deref().and_then(decode_text) {
+        Some(other) => text_diff_job(&path, &other, &contents, DiffOptions::default()),
+        None => message_job(&format!("{path} isn't a text file in {}", build.name)),
     };
+    *output.lock().unwrap() = Some(job.into());
+}
+
+/// The entries added, removed and changed going from `base` to `modified`, two versions of a
+/// text config.
+fn structural_diff_job(base: &str, modified: &str) -> LayoutJob {
+    let parsed = (cfg_parser::text::parse(base), cfg_parser::text::parse(modified));
+    let (base, modified) = match parsed {
+        (Ok(base), Ok(modified)) => (base, modified),
+        (Err(e), _) => return message_job(&format!("Couldn't parse the base version: {e}")),
+        (_, Err(e)) => return message_job(&format!("Couldn't parse the changed version: {e}")),
+    };
+
+    let changes = diff_entries(&base, &modified);
+    if changes.is_empty() {
+        return message_job("No entries changed, only formatting or comments");
+    }
 
-    let diff = similar::TextDiff::from_lines(&base_contents_str, &modified_contents_str);
+    let mut job = LayoutJob::default();
+    let font_id = FontId::monospace(12.0);
+    let palette = settings::palette();
+    for change in changes {
+        let color = match change {
+            ConfigChange::Added { .. } => palette.added,
+            ConfigChange::Removed { .. } => palette.removed,
+            ConfigChange::Changed { .. } => palette.changed,
+            ConfigChange::ClassChanged { .. } => Color32::LIGHT_BLUE,
+        };
+        job.append(
+            &format!("{change}\n"),
+            0.0,
+            TextFormat { color, font_id: font_id.clone(), ..Default::default() },
+        );
+    }
+
+    job
+}
+
+/// A binary file's size and hash, which identify it when its bytes can't be read as text.
+fn binary_summary(data: &[u8]) -> String {
+    format!("{} bytes, XXH3 {:016x}", data.len(), content_hash(data))
+}
+
+/// The rows of 16 bytes which differ between `base` and `modified` at the same offsets, as
+/// hexdump lines with the changed bytes highlighted, after each version's size and hash.
+fn binary_diff_job(base: &[u8], modified: &[u8]) -> LayoutJob {
+    let font_id = FontId::monospace(12.0);
+    let plain = TextFormat { font_id: font_id.clone(), ..Default::default() };
+    let palette = settings::palette();
+    let removed = TextFormat { color: palette.removed, ..plain.clone() };
+    let added = TextFormat { color: palette.added, ..plain.clone() };
+    let Palette { removed_highlight, added_highlight, .. } = palette;
+    let mut job = LayoutJob::default();
+    job.append(
+        &format!("Binary files differ\n-{}\n+{}\n", binary_summary(base), binary_summary(modified)),
+        0.0,
+        plain.clone(),
+    );
+
+    let row_bytes = |data: &[u8], row: usize| row * 16..(row * 16 + 16).min(data.len());
+    let mut differing = 0;
+    let mut last_shown = None;
+    for row in 0..base.len().max(modified.len()).div_ceil(16) {
+        let old = base.get(row_bytes(base, row)).unwrap_or_default();
+        let new = modified.get(row_bytes(modified, row)).unwrap_or_default();
+        if old == new {
+            continue;
+        }
+
+        differing += 1;
+        if differing > BINARY_DIFF_ROW_LIMIT {
+            continue;
+        }
+        if last_shown.is_some_and(|last| row > last + 1) {
+            job.append("[...]\n", 0.0, plain.clone());
+        }
+        last_shown = Some(row);
+
+        if !old.is_empty() {
+            append_hex_row(&mut job, "-", row * 16, old, new, removed.clone(), removed_highlight);
+        }
+        if !new.is_empty() {
+            append_hex_row(&mut job, "+", row * 16, new, old, added.clone(), added_highlight);
+        }
+    }
+
+    if differing == 0 {
+        job.append("Contents are identical\n", 0.0, plain);
+    } else if differing > BINARY_DIFF_ROW_LIMIT {
+        let hidden = differing - BINARY_DIFF_ROW_LIMIT;
+        job.append(&format!("[... {hidden} more rows differ]\n"), 0.0, plain);
+    }
+    job
+}
+
+/// Appends a hexdump row of `bytes`, found at `offset`, with the bytes which differ from
+/// `other`, the row at the same offset in the other version, on a `highlight` background.
+fn append_hex_row(
+    job: &mut LayoutJob,
+    sign: &str,
+    offset: usize,
+    bytes: &[u8],
+    other: &[u8],
+    format: TextFormat,
+    highlight: Color32,
+) {
+    let highlighted = TextFormat { background: highlight, ..format.clone() };
+    let format_at = |idx: usize| {
+        if other.get(idx) == bytes.get(idx) { format.clone() } else { highlighted.clone() }
+    };
+
+    job.append(&format!("{sign}{offset:08X}  "), 0.0, format.clone());
+    for idx in 0..16 {
+        match bytes.get(idx) {
+            Some(byte) => {
+                job.append(&format!("{byte:02X}"), 0.0, format_at(idx));
+                job.append(" ", 0.0, format.clone());
+            }
+            None => job.append("   ", 0.0, format.clone()),
+        }
+        if idx == 7 {
+            job.append(" ", 0.0, format.clone());
+        }
+    }
+    job.append(" |", 0.0, format.clone());
+    for (idx, byte) in bytes.iter().enumerate() {
+        job.append(&printable(*byte).to_string(), 0.0, format_at(idx));
+    }
+    job.append("|\n", 0.0, format);
+}
+
+/// A single line of monospace text, for diffs which couldn't be built.
+fn message_job(message: &str) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    job.append(
+        &format!("{message}\n"),
+        0.0,
+        TextFormat { font_id: FontId::monospace(12.0), ..Default::default() },
+    );
+    job
+}
+
+/// Lines added and removed going from `base` to `modified`, two versions of `path`, with a
+/// few lines of context around each change. Lines are compared as `options` says, but
+/// shown as they're written.
+fn text_diff_job(path: &str, base: &str, modified: &str, options: DiffOptions) -> LayoutJob {
+    // If either side is minified, both are reformatted so that their lines correspond
+    let pretty;
+    let (base, modified) =
+        if syntax::wants_pretty_print(path, base) || syntax::wants_pretty_print(path, modified) {
+            pretty = (pretty_print(base), pretty_print(modified));
+            (pretty.0.as_str(), pretty.1.as_str())
+        } else {
+            (base, modified)
+        };
+
+    let ignore_comments = options.ignore_comments && syntax::is_script(path);
+    let base = ComparedLines::new(base, options.ignore_whitespace, ignore_comments);
+    let modified = ComparedLines::new(modified, options.ignore_whitespace, ignore_comments);
+    let ops = similar::capture_diff_slices(Algorithm::Myers, &base.keys, &modified.keys);
     let mut job = LayoutJob::default();
 
     let mut distance_from_change = 0;
     const CONTEXT_DISTANCE: usize = 5;
     let mut previous_lines: VecDeque<String> = VecDeque::with_capacity(CONTEXT_DISTANCE);
     let font_id = FontId::monospace(12.0);
-    for change in diff.iter_all_changes() {
+    let palette = settings::palette();
+    let changes = ops
+        .iter()
+        .flat_map(|op| op.iter_changes(&base.keys, &modified.keys).map(move |change| (op, change)));
+    for (op, change) in changes {
+        let (text, replaced) = match (change.old_index(), change.new_index()) {
+            (Some(idx), None) => (base.lines[idx], replaced_line(op, ChangeTag::Delete, idx)),
+            (_, Some(idx)) => (modified.lines[idx], replaced_line(op, change.tag(), idx)),
+            (None, None) => unreachable!("every change has a side"),
+        };
         let (sign, color) = match change.tag() {
             ChangeTag::Delete => {
                 distance_from_change = 0;
-                ("-", Some(Color32::LIGHT_RED))
+                ("-", Some(palette.removed))
             }
             ChangeTag::Insert => {
                 distance_from_change = 0;
-                ("+", Some(Color32::LIGHT_GREEN))
+                ("+", Some(palette.added))
             }
             ChangeTag::Equal => {
                 distance_from_change += 1;
@@ -185,15 +698,20 @@ pub async fn build_file_diff(
                 );
             }
 
-            job.append(
-                &format!("{sign}{change}\n"),
-                0.0,
-                if let Some(color) = color {
-                    TextFormat { color, font_id: font_id.clone(), ..Default::default() }
-                } else {
-                    Default::default()
-                },
-            );
+            let format = if let Some(color) = color {
+                TextFormat { color, font_id: font_id.clone(), ..Default::default() }
+            } else {
+                Default::default()
+            };
+            match replaced {
+                Some(ReplacedLine::Old(idx)) => {
+                    append_word_diff(&mut job, sign, text, base.lines[idx], format)
+                }
+                Some(ReplacedLine::New(idx)) => {
+                    append_word_diff(&mut job, sign, text, modified.lines[idx], format)
+                }
+                None => job.append(&format!("{sign}{text}\n"), 0.0, format),
+            }
         } else if distance_from_change == CONTEXT_DISTANCE + 1 {
             job.append(
                 "[...]\n",
@@ -205,9 +723,236 @@ pub async fn build_file_diff(
                 let _ = previous_lines.pop_front();
             }
 
-            previous_lines.push_back(format!("{sign}{change}\n"));
+            previous_lines.push_back(format!("{sign}{text}\n"));
         }
     }
 
-    *output.lock().unwrap() = Some(job.into());
+    job
+}
+
+/// The line on the other side of a block of replaced lines which a changed line is paired
+/// with, by their position in the block.
+enum ReplacedLine {
+    /// A line of the base version, which an inserted line replaced.
+    Old(usize),
+    /// A line of the modified version, which replaced a deleted line.
+    New(usize),
+}
+
+/// The line paired with the line at `idx`, changed as `tag` says, if `op` replaced lines.
+fn replaced_line(op: &DiffOp, tag: ChangeTag, idx: usize) -> Option<ReplacedLine> {
+    let DiffOp::Replace { old_index, old_len, new_index, new_len } = *op else {
+        return None;
+    };
+    let paired = old_len.min(new_len);
+    match tag {
+        ChangeTag::Delete if idx - old_index < paired => {
+            Some(ReplacedLine::New(new_index + idx - old_index))
+        }
+        ChangeTag::Insert if idx - new_index < paired => {
+            Some(ReplacedLine::Old(old_index + idx - new_index))
+        }
+        _ => None,
+    }
+}
+
+/// Rows of a binary diff which are shown before the rest are only counted.
+const BINARY_DIFF_ROW_LIMIT: usize = 128;
+
+/// Appends `text`, a changed line shown after `sign`, with the words which aren't in
+/// `other`, the line it replaced or was replaced by, highlighted so that a small edit to a
+/// long line stands out.
+fn append_word_diff(job: &mut LayoutJob, sign: &str, text: &str, other: &str, format: TextFormat) {
+    let deleted = sign == "-";
+    let (old, new) =
+        if deleted { (tokens(text), tokens(other)) } else { (tokens(other), tokens(text)) };
+    let palette = settings::palette();
+    let highlighted = TextFormat {
+        background: if deleted { palette.removed_highlight } else { palette.added_highlight },
+        ..format.clone()
+    };
+
+    job.append(sign, 0.0, format.clone());
+    let ops = similar::capture_diff_slices(Algorithm::Myers, &old, &new);
+    for change in ops.iter().flat_map(|op| op.iter_changes(&old, &new)) {
+        let word = change.value();
+        let highlight = match change.tag() {
+            ChangeTag::Equal => false,
+            ChangeTag::Delete if deleted => true,
+            ChangeTag::Insert if !deleted => true,
+            // Only in the line this one is paired with
+            ChangeTag::Delete | ChangeTag::Insert => continue,
+        };
+        // Highlighted spaces would mostly show where words moved
+        let format = if highlight && !word.trim().is_empty() { &highlighted } else { &format };
+        job.append(word, 0.0, format.clone());
+    }
+    job.append("\n", 0.0, format);
+}
+
+/// Splits a line into runs of word characters, runs of whitespace, and single punctuation
+/// characters, so that changing a number doesn't highlight the `;` after it.
+fn tokens(line: &str) -> Vec<&str> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Some(true)
+        } else if c.is_whitespace() {
+            Some(false)
+        } else {
+            None
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        if let Some(kind) = class(c) {
+            while let Some((idx, c)) = chars.next_if(|&(_, c)| class(c) == Some(kind)) {
+                end = idx + c.len_utf8();
+            }
+        }
+        tokens.push(&line[start..end]);
+    }
+    tokens
+}
+
+/// The lines of one side of a text diff, as they're shown and as they're compared.
+struct ComparedLines<'a> {
+    lines: Vec<&'a str>,
+    /// What each of `lines` is compared by.
+    keys: Vec<String>,
+}
+
+impl<'a> ComparedLines<'a> {
+    /// Splits `text` into lines, leaving out those which are blank once whitespace or
+    /// comments are ignored so that adding or removing them isn't a change.
+    fn new(text: &'a str, ignore_whitespace: bool, ignore_comments: bool) -> Self {
+        let code = if ignore_comments { Cow::Owned(strip_comments(text)) } else { text.into() };
+        let mut compared = ComparedLines { lines: Vec::new(), keys: Vec::new() };
+        // `code` has the same line breaks as `text`, but `split` keeps a trailing empty line
+        // which `lines` drops
+        for (line, code) in text.lines().zip(code.split('\n')) {
+            let key = if ignore_whitespace {
+                code.split_whitespace().collect()
+            } else if ignore_comments {
+                code.trim_end().to_string()
+            } else {
+                line.to_string()
+            };
+            if key.trim().is_empty() && (ignore_whitespace || !line.trim().is_empty()) {
+                continue;
+            }
+            compared.lines.push(line);
+            compared.keys.push(key);
+        }
+        compared
+    }
+}
+
+/// `text` without its `//` and `/* */` comments, keeping the line breaks inside block
+/// comments so that its lines match up with the original's.
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => out.extend(chars.next()),
+                        '"' | '\n' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                    } else if c == '/' && previous == Some('*') {
+                        break;
+                    }
+                    previous = Some(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed_lines(base: &str, modified: &str, options: DiffOptions) -> Vec<String> {
+        let job = text_diff_job("scripts/Game/a.c", base, modified, options);
+        job.text.lines().filter(|line| line.starts_with(['+', '-'])).map(String::from).collect()
+    }
+
+    #[test]
+    fn ignores_whitespace_and_comments() {
+        let base = "void F()\n{\n\tint a = 1; // one\n\tint b = 2;\n}\n";
+        let modified = "void F()\n{\n    int a = 1;\n\n    /* b */\n\tint b = 3;\n}\n";
+
+        assert_eq!(changed_lines(base, modified, DiffOptions::default()).len(), 6);
+        let whitespace = DiffOptions { ignore_whitespace: true, ignore_comments: false };
+        assert_eq!(
+            changed_lines(base, modified, whitespace),
+            [
+                "-\tint a = 1; // one",
+                "-\tint b = 2;",
+                "+    int a = 1;",
+                "+    /* b */",
+                "+\tint b = 3;"
+            ]
+        );
+        let both = DiffOptions { ignore_whitespace: true, ignore_comments: true };
+        assert_eq!(changed_lines(base, modified, both), ["-\tint b = 2;", "+\tint b = 3;"]);
+    }
+
+    #[test]
+    fn highlights_changed_words() {
+        let job = text_diff_job(
+            "scripts/Game/a.c",
+            "int damage = 10; int range = 5;\n",
+            "int damage = 12; int range = 5;\n",
+            DiffOptions::default(),
+        );
+        let highlighted: Vec<&str> = job
+            .sections
+            .iter()
+            .filter(|section| section.format.background != Color32::TRANSPARENT)
+            .map(|section| &job.text[section.byte_range.clone()])
+            .collect();
+        assert_eq!(highlighted, ["10", "12"]);
+    }
+
+    #[test]
+    fn highlights_changed_bytes() {
+        let job = binary_diff_job(b"0123456789abcdef0123", b"0123456789abcdef0X23!");
+        assert!(job.text.contains("-00000010  30 31 32 33"));
+        assert!(!job.text.contains("00000000  "));
+        let highlighted: Vec<&str> = job
+            .sections
+            .iter()
+            .filter(|section| section.format.background != Color32::TRANSPARENT)
+            .map(|section| &job.text[section.byte_range.clone()])
+            .collect();
+        assert_eq!(highlighted, ["31", "1", "58", "21", "X", "!"]);
+    }
+
+    #[test]
+    fn strips_comments_outside_strings() {
+        assert_eq!(
+            strip_comments("Print(\"http://a\"); /* x\ny */ F(); // z\n\"\\\"/*\""),
+            "Print(\"http://a\"); \n F(); \n\"\\\"/*\""
+        );
+    }
 }
@@ -0,0 +1,102 @@
+//! Workspace-wide events which keep panels in sync.
+//!
+//! Anything that more than one panel may care about (a new set of archives, a file being
+//! opened, search results arriving) is published on the [`EventBus`] instead of being
+//! routed to a specific tab. Once per frame the app delivers pending events to every open
+//! tab, docked or detached, and to any other registered [`Subscriber`]. A new panel only has
+//! to implement [`Subscriber`] to follow the workspace.
+
+use crate::permalink::PakSetHash;
+use crate::task::SearchEnd;
+use crate::task::SearchId;
+use crate::task::SearchProgress;
+use crate::task::SearchResult;
+
+#[derive(Clone)]
+pub enum WorkspaceEvent {
+    /// A new set of archives replaced the loaded ones.
+    WorkspaceLoaded { pak_set: PakSetHash },
+    /// A file was opened in an editor tab.
+    FileOpened { path: String },
+    /// A file's contents were read again for editor tabs which had dropped them.
+    ContentsReloaded { path: String, pak_set: PakSetHash, contents: String },
+    /// Matches in one more file were found by a running search.
+    SearchResult(SearchId, SearchResult),
+    /// A running search's totals so far.
+    SearchProgress(SearchId, SearchProgress),
+    /// A search stopped, with its final totals.
+    SearchComplete(SearchId, SearchProgress, SearchEnd),
+}
+
+/// Something which reacts to workspace events.
+pub trait Subscriber {
+    fn on_event(&mut self, event: &WorkspaceEvent);
+}
+
+/// Events published since the last frame, and the subscribers which aren't tabs.
+#[derive(Default)]
+pub struct EventBus {
+    pending: Vec<WorkspaceEvent>,
+    subscribers: Vec<Box<dyn Subscriber>>,
+}
+
+impl EventBus {
+    /// Queues `event` for delivery at the end of the current frame's message processing.
+    pub fn publish(&mut self, event: WorkspaceEvent) {
+        self.pending.push(event);
+    }
+
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    /// Delivers pending events in the order they were published, first to the registered
+    /// subscribers and then to `deliver`, which hands them to whatever the bus can't own
+    /// (e.g. tabs in the dock).
+    pub fn dispatch(&mut self, mut deliver: impl FnMut(&WorkspaceEvent)) {
+        for event in std::mem::take(&mut self.pending) {
+            for subscriber in &mut self.subscribers {
+                subscriber.on_event(&event);
+            }
+            deliver(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl Subscriber for Recorder {
+        fn on_event(&mut self, event: &WorkspaceEvent) {
+            if let WorkspaceEvent::FileOpened { path } = event {
+                self.0.borrow_mut().push(path.clone());
+            }
+        }
+    }
+
+    fn opened(path: &str) -> WorkspaceEvent {
+        WorkspaceEvent::FileOpened { path: path.to_string() }
+    }
+
+    #[test]
+    fn delivers_events_in_order_once() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = EventBus::default();
+        bus.subscribe(Recorder(Rc::clone(&seen)));
+
+        bus.publish(opened("/a.c"));
+        bus.publish(opened("/b.c"));
+        let mut delivered = 0;
+        bus.dispatch(|_| delivered += 1);
+        bus.dispatch(|_| delivered += 1);
+
+        assert_eq!(*seen.borrow(), ["/a.c", "/b.c"]);
+        assert_eq!(delivered, 2);
+    }
+}
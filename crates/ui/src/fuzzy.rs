@@ -0,0 +1,122 @@
+//! Ranked fuzzy matching for path filters.
+//!
+//! The algorithm follows fzf's "v1" matcher: find the first left-to-right occurrence of
+//! every query character, walk backwards from the end of that match to find the tightest
+//! window, then score the window. Matches at the start of path segments or words and runs
+//! of consecutive characters score higher, gaps score lower.
+
+const SCORE_MATCH: i64 = 16;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+const BONUS_SEGMENT_START: i64 = 10;
+const BONUS_WORD_START: i64 = 8;
+const BONUS_CAMEL_CASE: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_LAST_SEGMENT: i64 = 12;
+
+/// Scores how well `needle` matches `haystack`, ignoring ASCII case. Returns `None` if
+/// the characters of `needle` don't all appear in `haystack` in order. Higher is better.
+///
+/// An empty needle matches everything with a score of 0.
+pub fn fuzzy_score(needle: &str, haystack: &str) -> Option<i64> {
+    let needle = needle.as_bytes();
+    let haystack_bytes = haystack.as_bytes();
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    // Forward pass: find where the leftmost complete match ends.
+    let mut needle_idx = 0;
+    let mut end = None;
+    for (idx, c) in haystack_bytes.iter().enumerate() {
+        if c.eq_ignore_ascii_case(&needle[needle_idx]) {
+            needle_idx += 1;
+            if needle_idx == needle.len() {
+                end = Some(idx);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // Backward pass: find the latest start that still matches, giving the tightest window.
+    let mut needle_idx = needle.len();
+    let mut start = end;
+    for idx in (0..=end).rev() {
+        if haystack_bytes[idx].eq_ignore_ascii_case(&needle[needle_idx - 1]) {
+            needle_idx -= 1;
+            if needle_idx == 0 {
+                start = idx;
+                break;
+            }
+        }
+    }
+
+    let last_segment_start = haystack.rfind('/').map(|idx| idx + 1).unwrap_or(0);
+
+    let mut score = 0;
+    let mut needle_idx = 0;
+    let mut prev_matched = false;
+    let mut in_gap = false;
+    for idx in start..=end {
+        if needle_idx < needle.len()
+            && haystack_bytes[idx].eq_ignore_ascii_case(&needle[needle_idx])
+        {
+            score += SCORE_MATCH + boundary_bonus(haystack_bytes, idx);
+            if prev_matched {
+                score += BONUS_CONSECUTIVE;
+            }
+            if idx >= last_segment_start {
+                score += BONUS_LAST_SEGMENT;
+            }
+
+            needle_idx += 1;
+            prev_matched = true;
+            in_gap = false;
+        } else {
+            score -= if in_gap { PENALTY_GAP_EXTENSION } else { PENALTY_GAP_START };
+            prev_matched = false;
+            in_gap = true;
+        }
+    }
+
+    Some(score)
+}
+
+fn boundary_bonus(haystack: &[u8], idx: usize) -> i64 {
+    let Some(prev) = idx.checked_sub(1).map(|prev| haystack[prev]) else {
+        return BONUS_SEGMENT_START;
+    };
+
+    let current = haystack[idx];
+    match prev {
+        b'/' => BONUS_SEGMENT_START,
+        b'_' | b'-' | b'.' | b' ' => BONUS_WORD_START,
+        _ if prev.is_ascii_lowercase() && current.is_ascii_uppercase() => BONUS_CAMEL_CASE,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requires_all_characters_in_order() {
+        assert!(fuzzy_score("scr", "/scripts/Game").is_some());
+        assert!(fuzzy_score("SCR", "/scripts/Game").is_some());
+        assert!(fuzzy_score("rcs", "/scripts/Game").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn ranks_tight_and_boundary_matches_higher() {
+        let exact = fuzzy_score("gamemode", "/scripts/Game/GameMode.c").unwrap();
+        let scattered = fuzzy_score("gamemode", "/scripts/Game/GenericAudioManagerModel.c");
+        assert!(scattered.is_none_or(|scattered| exact > scattered));
+
+        let file_name = fuzzy_score("chimera", "/Prefabs/Characters/Chimera.et").unwrap();
+        let directory = fuzzy_score("chimera", "/Chimera/Prefabs/Character.et").unwrap();
+        assert!(file_name > directory);
+    }
+}
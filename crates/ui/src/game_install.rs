@@ -0,0 +1,323 @@
+//! Locating Enfusion game installs on disk.
+//!
+//! Steam installs are found by reading every Steam library listed in
+//! `steamapps/libraryfolders.vdf` under the usual Steam locations for this OS. Workshop
+//! mods live either in a Steam library's `steamapps/workshop` (DayZ) or in the game's
+//! profile directory (Arma Reforger).
+
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Steam's default install locations, relative to the home directory unless absolute.
+#[cfg(windows)]
+const STEAM_DIRS: &[&str] = &[r"C:\Program Files (x86)\Steam", r"C:\Program Files\Steam"];
+#[cfg(target_os = "macos")]
+const STEAM_DIRS: &[&str] = &["Library/Application Support/Steam"];
+#[cfg(not(any(windows, target_os = "macos")))]
+const STEAM_DIRS: &[&str] =
+    &[".steam/steam", ".local/share/Steam", ".var/app/com.valvesoftware.Steam/.local/share/Steam"];
+
+/// Where Arma Reforger downloads workshop addons, relative to the home directory.
+const REFORGER_ADDONS_DIR: &str = "Documents/My Games/ArmaReforger/addons";
+
+/// Steam app ID whose `steamapps/workshop/content` directory holds DayZ mods.
+const DAYZ_APP_ID: &str = "221100";
+
+/// Games whose data can be loaded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Game {
+    Reforger,
+    ReforgerTools,
+    DayZ,
+}
+
+impl Game {
+    const ALL: [Game; 3] = [Game::Reforger, Game::ReforgerTools, Game::DayZ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Game::Reforger => "Arma Reforger",
+            Game::ReforgerTools => "Arma Reforger Tools",
+            Game::DayZ => "DayZ",
+        }
+    }
+
+    /// The game's directory inside a Steam library's `steamapps/common`.
+    fn steam_dir(self) -> &'static str {
+        match self {
+            Game::Reforger => "Arma Reforger",
+            Game::ReforgerTools => "Arma Reforger Tools",
+            Game::DayZ => "DayZ",
+        }
+    }
+
+    /// Directory beneath the install holding the game's archives.
+    fn data_dir(self) -> &'static str {
+        match self {
+            Game::Reforger | Game::ReforgerTools => "addons",
+            Game::DayZ => "Addons",
+        }
+    }
+
+    fn archive_extension(self) -> &'static str {
+        match self {
+            Game::Reforger | Game::ReforgerTools => "pak",
+            Game::DayZ => "pbo",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameInstall {
+    pub game: Game,
+    pub path: PathBuf,
+}
+
+impl GameInstall {
+    /// Directory holding the game's archives.
+    pub fn data_dir(&self) -> PathBuf {
+        self.path.join(self.game.data_dir())
+    }
+
+    /// Every archive in the game's data directory and its subdirectories, sorted by path.
+    pub fn archives(&self) -> Vec<PathBuf> {
+        find_archives(&self.data_dir(), self.game.archive_extension())
+    }
+}
+
+/// A downloaded workshop mod.
+#[derive(Debug, Clone)]
+pub struct WorkshopMod {
+    pub game: Game,
+    pub name: String,
+    pub path: PathBuf,
+    /// The mod's archives, sorted by path.
+    pub archives: Vec<PathBuf>,
+}
+
+/// Finds downloaded workshop mods which contain at least one archive, sorted by name.
+pub fn find_workshop_mods() -> Vec<WorkshopMod> {
+    let mut mod_dirs = Vec::new();
+    mod_dirs.extend(subdirectories(&home_dir().join(REFORGER_ADDONS_DIR)).map(|path| {
+        let name = reforger_mod_name(&path);
+        (Game::Reforger, name, path)
+    }));
+    for library in steam_libraries() {
+        let workshop = library.join("steamapps").join("workshop").join("content").join(DAYZ_APP_ID);
+        mod_dirs.extend(subdirectories(&workshop).map(|path| {
+            let name = dayz_mod_name(&path);
+            (Game::DayZ, name, path)
+        }));
+    }
+
+    let mut mods: Vec<WorkshopMod> = mod_dirs
+        .into_iter()
+        .map(|(game, name, path)| {
+            let archives = find_archives(&path, game.archive_extension());
+            WorkshopMod { game, name, path, archives }
+        })
+        .filter(|workshop_mod| !workshop_mod.archives.is_empty())
+        .collect();
+    mods.sort_by_cached_key(|workshop_mod| workshop_mod.name.to_lowercase());
+
+    mods
+}
+
+/// Reforger addon directories are named `<name>_<GUID>` and describe themselves in
+/// `ServerData.json`.
+fn reforger_mod_name(path: &Path) -> String {
+    let from_manifest = std::fs::read(path.join("ServerData.json"))
+        .ok()
+        .and_then(|data| serde_json::from_slice::<serde_json::Value>(&data).ok())
+        .and_then(|manifest| manifest.get("name")?.as_str().map(str::to_string));
+    if let Some(name) = from_manifest {
+        return name;
+    }
+
+    let dir_name = dir_name(path);
+    match dir_name.rsplit_once('_') {
+        Some((name, guid)) if guid.len() == 16 && guid.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            name.to_string()
+        }
+        _ => dir_name,
+    }
+}
+
+/// DayZ workshop directories are named by workshop ID, with the display name in
+/// `meta.cpp` as `name = "...";`.
+fn dayz_mod_name(path: &Path) -> String {
+    std::fs::read_to_string(path.join("meta.cpp"))
+        .ok()
+        .and_then(|meta| {
+            meta.lines().find_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                if !key.trim().eq_ignore_ascii_case("name") {
+                    return None;
+                }
+
+                Some(value.trim().trim_end_matches(';').trim_matches('"').to_string())
+            })
+        })
+        .unwrap_or_else(|| dir_name(path))
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn subdirectories(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+}
+
+/// Every file with the given extension in `dir` and its subdirectories, sorted by path.
+fn find_archives(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut archives = Vec::new();
+    let mut queue = vec![dir.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                queue.push(path);
+            } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension)) {
+                archives.push(path);
+            }
+        }
+    }
+
+    archives.sort();
+    archives
+}
+
+/// Finds every known game installed in any Steam library.
+pub fn find_installs() -> Vec<GameInstall> {
+    let mut installs = Vec::new();
+    for library in steam_libraries() {
+        let common = library.join("steamapps").join("common");
+        for game in Game::ALL {
+            let path = common.join(game.steam_dir());
+            let already_found = installs.iter().any(|install: &GameInstall| install.path == path);
+            if !already_found && path.join(game.data_dir()).is_dir() {
+                installs.push(GameInstall { game, path });
+            }
+        }
+    }
+
+    installs
+}
+
+/// Steam root directories plus every library folder they list.
+fn steam_libraries() -> Vec<PathBuf> {
+    let home = home_dir();
+
+    let mut libraries = Vec::new();
+    for steam_dir in STEAM_DIRS {
+        let steam_dir = home.join(steam_dir);
+        if !steam_dir.is_dir() {
+            continue;
+        }
+
+        let vdf_path = steam_dir.join("steamapps").join("libraryfolders.vdf");
+        let listed = std::fs::read_to_string(vdf_path)
+            .map(|vdf| library_folder_paths(&vdf))
+            .unwrap_or_default();
+
+        for library in std::iter::once(steam_dir).chain(listed) {
+            if !libraries.iter().any(|known: &PathBuf| same_dir(known, &library)) {
+                libraries.push(library);
+            }
+        }
+    }
+
+    libraries
+}
+
+/// Extracts the `"path"` values from a `libraryfolders.vdf` file.
+///
+/// VDF is a tree of quoted keys and values, so rather than parsing the structure this
+/// pulls out every quoted string and takes the one following each `"path"` key.
+fn library_folder_paths(vdf: &str) -> Vec<PathBuf> {
+    let mut strings = Vec::new();
+    let mut chars = vdf.chars();
+    while let Some(c) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+
+        let mut string = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => string.extend(chars.next()),
+                c => string.push(c),
+            }
+        }
+        strings.push(string);
+    }
+
+    strings
+        .windows(2)
+        .filter(|pair| pair[0].eq_ignore_ascii_case("path"))
+        .map(|pair| PathBuf::from(&pair[1]))
+        .collect()
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+        .map(PathBuf::from)
+        .unwrap_or_default()
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_library_folders() {
+        let vdf = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"apps"
+		{
+			"1874880"		"12345"
+		}
+	}
+	"1"
+	{
+		"path"		"D:\\SteamLibrary"
+	}
+}
+"#;
+
+        assert_eq!(
+            library_folder_paths(vdf),
+            [PathBuf::from(r"C:\Program Files (x86)\Steam"), PathBuf::from(r"D:\SteamLibrary")]
+        );
+    }
+
+    #[test]
+    fn mod_names_fall_back_to_directory_names() {
+        let missing = Path::new("/nonexistent/addons");
+        assert_eq!(reforger_mod_name(&missing.join("Status_Quo_595F2BF2F44836FB")), "Status_Quo");
+        assert_eq!(reforger_mod_name(&missing.join("Status_Quo")), "Status_Quo");
+        assert_eq!(dayz_mod_name(&missing.join("1559212036")), "1559212036");
+    }
+}
@@ -0,0 +1,147 @@
+//! Following one text config value through every loaded build.
+
+use cfg_parser::text::find_entry;
+use egui::Align2;
+use egui::Color32;
+use egui::FontId;
+use egui::Pos2;
+use egui::Sense;
+use egui::Shape;
+use egui::Stroke;
+use egui::Ui;
+use egui::pos2;
+use egui::vec2;
+use enfusion_search::text::decode_text;
+
+use crate::task;
+use crate::task::BackgroundTaskMessage;
+use crate::task::ComparisonBuild;
+
+const CHART_HEIGHT: f32 = 220.0;
+
+/// A config value as it's set in each build.
+#[derive(Clone)]
+pub struct ValueHistory {
+    pub file: String,
+    /// Keys from the top level down to the tracked property.
+    pub entry_path: Vec<String>,
+    /// The open file's value first, then each build's in the order they were loaded.
+    pub points: Vec<HistoryPoint>,
+}
+
+#[derive(Clone)]
+pub struct HistoryPoint {
+    pub build: String,
+    /// `None` if the build doesn't have the file, or the file doesn't set the value.
+    pub value: Option<String>,
+}
+
+impl HistoryPoint {
+    /// The value as a number, if it's a single number.
+    pub fn number(&self) -> Option<f64> {
+        self.value.as_deref()?.trim_matches('"').parse().ok()
+    }
+}
+
+impl ValueHistory {
+    /// Name of the tracked property.
+    pub fn property(&self) -> &str {
+        self.entry_path.last().map_or("", String::as_str)
+    }
+}
+
+/// Looks up the property at `entry_path` in `contents`, an editor's copy of `file`, and in
+/// the same file in each of `builds`, sending the result to the UI.
+pub async fn track_value(
+    file: String,
+    entry_path: Vec<String>,
+    contents: String,
+    builds: Vec<ComparisonBuild>,
+    inbox: egui_inbox::UiInboxSender<BackgroundTaskMessage>,
+) {
+    let mut points = vec![HistoryPoint {
+        build: "Open file".to_string(),
+        value: value_at(&contents, &entry_path),
+    }];
+    for build in builds {
+        let data = match build.async_overlay_fs.join(&file) {
+            Ok(path) if build.contains(&file) => task::read_file_data(path).await,
+            _ => None,
+        };
+        let value =
+            data.as_deref().and_then(decode_text).and_then(|text| value_at(&text, &entry_path));
+        points.push(HistoryPoint { build: build.name, value });
+    }
+
+    let _ =
+        inbox.send(BackgroundTaskMessage::ValueTracked(ValueHistory { file, entry_path, points }));
+}
+
+fn value_at(text: &str, entry_path: &[String]) -> Option<String> {
+    let entries = cfg_parser::text::parse(text).ok()?;
+    let entry = find_entry(&entries, entry_path)?;
+    if entry.children.is_some() {
+        return None;
+    }
+    Some(entry.values.join(" "))
+}
+
+/// Plots the numeric values in `history` from left to right in build order, skipping builds
+/// whose value isn't a number.
+pub fn show_chart(ui: &mut Ui, history: &ValueHistory) {
+    let numbers: Vec<(usize, f64)> = history
+        .points
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, point)| Some((idx, point.number()?)))
+        .collect();
+    if numbers.len() < 2 {
+        ui.weak("Fewer than two builds set this to a number, so there's nothing to chart");
+        return;
+    }
+
+    let (rect, _) =
+        ui.allocate_exact_size(vec2(ui.available_width(), CHART_HEIGHT), Sense::hover());
+    let painter = ui.painter_at(rect);
+    let plot = rect.shrink2(vec2(48.0, 24.0));
+    let (mut min, mut max) = numbers
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &(_, n)| (min.min(n), max.max(n)));
+    if min == max {
+        // Keeps an unchanging value in the middle of the chart
+        min -= 1.0;
+        max += 1.0;
+    }
+
+    let x_step = plot.width() / (history.points.len() - 1) as f32;
+    let to_screen = |idx: usize, n: f64| {
+        let y = ((n - min) / (max - min)) as f32;
+        pos2(plot.left() + idx as f32 * x_step, plot.bottom() - y * plot.height())
+    };
+
+    let text_color = ui.visuals().text_color();
+    let positions: Vec<Pos2> = numbers.iter().map(|&(idx, n)| to_screen(idx, n)).collect();
+    painter.add(Shape::line(positions.clone(), Stroke::new(2.0, Color32::LIGHT_BLUE)));
+    for (&(idx, _), pos) in numbers.iter().zip(positions) {
+        painter.circle_filled(pos, 4.0, Color32::LIGHT_BLUE);
+        let value = history.points[idx].value.as_deref().unwrap_or_default();
+        painter.text(
+            pos - vec2(0.0, 6.0),
+            Align2::CENTER_BOTTOM,
+            value,
+            FontId::proportional(12.0),
+            text_color,
+        );
+    }
+
+    let weak_color = ui.visuals().weak_text_color();
+    for (idx, point) in history.points.iter().enumerate() {
+        painter.text(
+            pos2(plot.left() + idx as f32 * x_step, rect.bottom()),
+            Align2::CENTER_BOTTOM,
+            &point.build,
+            FontId::proportional(11.0),
+            weak_color,
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Rendering tab contents to standalone HTML pages for sharing outside the app.
+//!
+//! Pages keep the editor's dark background and colors, including when printed, so a
+//! browser's "Print to PDF" gives the same result.
+
+use std::fmt::Write as _;
+
+use egui::Color32;
+use egui::TextFormat;
+use egui::text::LayoutJob;
+
+use crate::diff::DiffOptions;
+use crate::diff::DiffResult;
+
+/// Foreground used for text without a color of its own.
+const TEXT_COLOR: &str = "#ebdbb2";
+/// Page background, matching the editor's Gruvbox theme.
+const BACKGROUND_COLOR: &str = "#282828";
+
+/// A page titled `title` showing `job` as preformatted text.
+pub fn text_document(title: &str, job: &LayoutJob) -> String {
+    let mut body = String::from("<pre>");
+    push_layout_job(&mut body, job);
+    body.push_str("</pre>\n");
+    document(title, &body)
+}
+
+/// A page with a section per file in `results`, each showing the file's diff (or contents,
+/// for added and removed files) as `options` says to build it. Diffs which haven't been
+/// viewed yet are built first.
+pub async fn diff_document(title: &str, results: &[DiffResult], options: DiffOptions) -> String {
+    let mut body = String::new();
+    for result in results {
+        let _ = writeln!(
+            body,
+            "<h2 style=\"color: {}\">{}</h2>",
+            css_color(result.color()),
+            escape(result.comparison_path())
+        );
+
+        body.push_str("<pre>");
+        push_layout_job(&mut body, &result.render(options).await);
+        body.push_str("</pre>\n");
+    }
+
+    document(title, &body)
+}
+
+fn document(title: &str, body: &str) -> String {
+    let title = escape(title);
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n\
+         <style>\n\
+         body {{ background: {BACKGROUND_COLOR}; color: {TEXT_COLOR}; font-family: sans-serif; \
+         print-color-adjust: exact; -webkit-print-color-adjust: exact; }}\n\
+         h2 {{ font-family: monospace; font-size: 1em; margin-bottom: 0.25em; }}\n\
+         pre {{ white-space: pre-wrap; margin-top: 0; }}\n\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>{title}</h1>\n\
+         {body}\
+         </body>\n\
+         </html>\n"
+    )
+}
+
+/// Appends `job`'s text to `out`, wrapping sections with their own color or a background in
+/// a `<span>`.
+fn push_layout_job(out: &mut String, job: &LayoutJob) {
+    let default_color = TextFormat::default().color;
+    for section in &job.sections {
+        let text = escape(&job.text[section.byte_range.clone()]);
+        let TextFormat { color, background, .. } = section.format;
+        let mut style = Vec::new();
+        if color != default_color && color != Color32::PLACEHOLDER {
+            style.push(format!("color: {}", css_color(color)));
+        }
+        if background != Color32::TRANSPARENT {
+            style.push(format!("background: {}", css_color(background)));
+        }
+
+        if style.is_empty() {
+            out.push_str(&text);
+        } else {
+            let _ = write!(out, "<span style=\"{}\">{text}</span>", style.join("; "));
+        }
+    }
+}
+
+fn css_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_sections_and_escapes_text() {
+        let mut job = LayoutJob::default();
+        job.append("a < b\n", 0.0, Default::default());
+        job.append(
+            "+x & y\n",
+            0.0,
+            TextFormat { color: Color32::from_rgb(0x90, 0xee, 0x90), ..Default::default() },
+        );
+
+        let mut html = String::new();
+        push_layout_job(&mut html, &job);
+        assert_eq!(html, "a &lt; b\n<span style=\"color: #90ee90\">+x &amp; y\n</span>");
+
+        let mut job = LayoutJob::default();
+        let format =
+            TextFormat { background: Color32::from_rgb(0x28, 0x5a, 0x28), ..Default::default() };
+        job.append("12", 0.0, format);
+        let mut html = String::new();
+        push_layout_job(&mut html, &job);
+        assert_eq!(html, "<span style=\"background: #285a28\">12</span>");
+    }
+}
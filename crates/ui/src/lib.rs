@@ -1,10 +1,37 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+pub mod analysis;
 mod app;
+mod audio;
+mod binary;
+pub mod crash;
+mod deprecations;
+mod dialogs;
 mod diff;
+pub mod events;
+mod fuzzy;
+#[cfg(not(target_arch = "wasm32"))]
+mod game_install;
+mod history;
+mod html;
 mod pak_wrapper;
+mod palette;
+mod permalink;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod plugin;
+pub mod preview;
+mod profile;
+mod search;
 mod settings;
+#[cfg(any(target_arch = "wasm32", test))]
+mod share;
+mod shutdown;
+mod syntax;
 mod task;
+mod texture;
 mod ui;
+mod undo;
+#[cfg(not(target_arch = "wasm32"))]
+mod update;
 mod vfs_ext;
 pub use app::EnfusionToolsApp;
@@ -4,11 +4,17 @@
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(
-            |_| tracing_subscriber::EnvFilter::new("ui=debug,dayz_pbo=debug,enfusion_pak=debug"),
-        ))
+    use tracing_subscriber::layer::SubscriberExt as _;
+    use tracing_subscriber::util::SubscriberInitExt as _;
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+            tracing_subscriber::EnvFilter::new("ui=debug,dayz_pbo=debug,enfusion_pak=debug")
+        }))
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_ansi(false).with_writer(ui::crash::RecentLogs))
         .init();
+    ui::crash::install_panic_hook();
 
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -35,6 +41,11 @@ fn main() {
     use tracing_subscriber::layer::SubscriberExt as _;
     use tracing_subscriber::util::SubscriberInitExt as _;
 
+    // The archive reader worker loads this module to parse paks, and has no page to run on
+    if web_sys::window().is_none() {
+        return;
+    }
+
     tracing_subscriber::registry()
         .with(tracing::level_filters::LevelFilter::DEBUG)
         .with(
@@ -43,7 +54,15 @@ fn main() {
                 .without_time()
                 .with_writer(tracing_web::MakeConsoleWriter),
         )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .without_time()
+                .with_writer(ui::crash::RecentLogs),
+        )
         .init();
+    // Before eframe's own hook, which calls this one
+    ui::crash::install_panic_hook();
 
     let web_options = eframe::WebOptions::default();
 
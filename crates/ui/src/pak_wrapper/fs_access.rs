@@ -0,0 +1,158 @@
+//! Remembering opened archives across sessions in the browser.
+//!
+//! Files picked with the File System Access API come with handles that can be stored in
+//! IndexedDB. On the next visit the handles are loaded back and, once the user grants
+//! read permission again, reopened without going through a file picker. Browsers without
+//! the API fall back to the regular `<input type="file">` picker and nothing is
+//! remembered.
+
+use tracing::warn;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::wasm_bindgen;
+use web_sys::js_sys;
+
+use crate::pak_wrapper::FileReference;
+
+#[wasm_bindgen(inline_js = r#"
+const DB_NAME = "enfusion_tools";
+const STORE = "file_handles";
+const LAST_WORKSPACE = "last_workspace";
+
+function openDb() {
+    return new Promise((resolve, reject) => {
+        const request = indexedDB.open(DB_NAME, 1);
+        request.onupgradeneeded = () => request.result.createObjectStore(STORE);
+        request.onsuccess = () => resolve(request.result);
+        request.onerror = () => reject(request.error);
+    });
+}
+
+export function fsAccessSupported() {
+    return typeof window.showOpenFilePicker === "function" && typeof indexedDB !== "undefined";
+}
+
+export async function pickFileHandles() {
+    return await window.showOpenFilePicker({
+        multiple: true,
+        types: [{
+            description: "Supported archives",
+            accept: { "application/octet-stream": [".pak", ".pbo"] },
+        }],
+    });
+}
+
+export async function storeLastWorkspace(handles) {
+    const db = await openDb();
+    await new Promise((resolve, reject) => {
+        const tx = db.transaction(STORE, "readwrite");
+        tx.objectStore(STORE).put(handles, LAST_WORKSPACE);
+        tx.oncomplete = () => resolve();
+        tx.onerror = () => reject(tx.error);
+    });
+}
+
+export async function loadLastWorkspace() {
+    const db = await openDb();
+    return await new Promise((resolve, reject) => {
+        const request = db.transaction(STORE, "readonly").objectStore(STORE).get(LAST_WORKSPACE);
+        request.onsuccess = () => resolve(request.result ?? []);
+        request.onerror = () => reject(request.error);
+    });
+}
+
+export async function filesFromHandles(handles) {
+    const files = [];
+    for (const handle of handles) {
+        let permission = await handle.queryPermission({ mode: "read" });
+        if (permission !== "granted") {
+            permission = await handle.requestPermission({ mode: "read" });
+        }
+        if (permission === "granted") {
+            files.push(await handle.getFile());
+        }
+    }
+    return files;
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(js_name = fsAccessSupported)]
+    fn fs_access_supported() -> bool;
+
+    #[wasm_bindgen(catch, js_name = pickFileHandles)]
+    async fn pick_file_handles() -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(catch, js_name = storeLastWorkspace)]
+    async fn store_last_workspace(handles: &JsValue) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(catch, js_name = loadLastWorkspace)]
+    async fn load_last_workspace() -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(catch, js_name = filesFromHandles)]
+    async fn files_from_handles(handles: &JsValue) -> Result<JsValue, JsValue>;
+}
+
+/// Whether this browser supports persisting file handles.
+pub fn is_supported() -> bool {
+    fs_access_supported()
+}
+
+/// Shows the File System Access API picker and remembers the chosen files as the last
+/// workspace. Returns `None` if the picker was dismissed.
+pub async fn pick_and_remember() -> Option<Vec<FileReference>> {
+    let handles = pick_file_handles().await.ok()?;
+
+    if let Err(e) = store_last_workspace(&handles).await {
+        warn!(?e, "failed to remember opened files");
+    }
+
+    files(&handles).await
+}
+
+/// Names of the files in the last workspace, or an empty list if there isn't one.
+pub async fn last_workspace_names() -> Vec<String> {
+    let handles = match load_last_workspace().await {
+        Ok(handles) => handles,
+        Err(e) => {
+            warn!(?e, "failed to load remembered files");
+            return Vec::new();
+        }
+    };
+
+    js_sys::Array::from(&handles)
+        .iter()
+        .filter_map(|handle| js_sys::Reflect::get(&handle, &"name".into()).ok()?.as_string())
+        .collect()
+}
+
+/// Reopens the last workspace, asking the user to grant read access again if needed.
+/// Files the user declines access to are skipped.
+pub async fn reopen_last_workspace() -> Vec<FileReference> {
+    let handles = match load_last_workspace().await {
+        Ok(handles) => handles,
+        Err(e) => {
+            warn!(?e, "failed to load remembered files");
+            return Vec::new();
+        }
+    };
+
+    files(&handles).await.unwrap_or_default()
+}
+
+async fn files(handles: &JsValue) -> Option<Vec<FileReference>> {
+    let files = match files_from_handles(handles).await {
+        Ok(files) => files,
+        Err(e) => {
+            warn!(?e, "failed to read files from handles");
+            return None;
+        }
+    };
+
+    Some(
+        js_sys::Array::from(&files)
+            .iter()
+            .filter_map(|file| file.dyn_into::<web_sys::File>().ok())
+            .map(FileReference)
+            .collect(),
+    )
+}
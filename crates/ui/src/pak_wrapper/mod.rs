@@ -1,9 +1,16 @@
-#[cfg(target_family = "wasm")]
+#[cfg(target_arch = "wasm32")]
 #[path = "wasm.rs"]
 mod wrapper;
 
-#[cfg(not(target_family = "wasm"))]
+#[cfg(not(target_arch = "wasm32"))]
 #[path = "native.rs"]
 mod wrapper;
 
+#[cfg(target_arch = "wasm32")]
+pub mod fs_access;
+#[cfg(target_arch = "wasm32")]
+mod reader_worker;
+#[cfg(target_arch = "wasm32")]
+pub mod remote;
+
 pub use wrapper::*;
@@ -2,6 +2,8 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use enfusion_pak::archive_format::ArchiveFormat;
+use enfusion_pak::archive_format::HEADER_LEN;
 use enfusion_pak::async_pak_vfs::AsyncReadAt;
 use enfusion_pak::error::PakError;
 use enfusion_pak::pak_vfs::ReadAt;
@@ -14,8 +16,27 @@ use memmap2::Mmap;
 pub struct FileReference(pub std::path::PathBuf);
 
 impl FileReference {
+    /// How this file is shown to the user.
+    pub fn display_name(&self) -> String {
+        self.0.display().to_string()
+    }
+
+    /// Whether this is an unpacked folder rather than an archive.
+    pub fn is_directory(&self) -> bool {
+        self.0.is_dir()
+    }
+
+    /// Whether this can be mounted in a workspace, either as an archive or a folder.
+    pub fn is_loadable(&self) -> bool {
+        self.is_directory() || self.has_supported_extension()
+    }
+
     pub fn has_supported_extension(&self) -> bool {
-        matches!(self.0.extension().and_then(|e| e.to_str()), Some("pak" | "pbo"))
+        self.0
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(ArchiveFormat::from_extension)
+            .is_some()
     }
 }
 
@@ -55,29 +76,41 @@ impl AsRef<[u8]> for MmapWrapper {
     }
 }
 
-pub fn parse_pak_file(path: PathBuf) -> Result<BytesPakFileWrapper<MmapWrapper>, PakError> {
-    let file = std::fs::File::open(&path)?;
-    let mmap = unsafe { memmap2::Mmap::map(&file)? };
-
-    let parsed_pak = enfusion_pak::PakFile::parse(&mmap)?;
-
-    Ok(BytesPakFileWrapper::new(path, MmapWrapper(Arc::new(mmap)), parsed_pak))
-}
-
-/// Represents a parsed archive file — either a PAK or PBO — that can be
-/// mounted as a VFS.
+/// Represents a parsed archive file — a PAK, PBO, or ZIP — or an unpacked folder that
+/// can be mounted as a VFS.
 pub enum ParsedArchive {
+    /// Loose files on disk, e.g. a mod's working folder.
+    Directory(PathBuf),
     Pak(Arc<BytesPakFileWrapper<MmapWrapper>>),
     Pbo(dayz_pbo::pbo_vfs::PboVfs<MmapWrapper>),
+    #[cfg(feature = "zip")]
+    Zip(enfusion_pak::zip_vfs::ZipVfs<MmapWrapper>),
 }
 
 pub fn parse_archive_file(path: PathBuf) -> Result<ParsedArchive, PakError> {
-    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    if path.is_dir() {
+        return Ok(ParsedArchive::Directory(path));
+    }
+
+    let file = std::fs::File::open(&path)?;
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let header = &mmap[..mmap.len().min(HEADER_LEN)];
+    let Some(format) = ArchiveFormat::detect(&name, header) else {
+        return Err(PakError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "unrecognized archive format",
+        )));
+    };
 
-    match ext.as_str() {
-        "pbo" => {
-            let file = std::fs::File::open(&path)?;
-            let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    match format {
+        ArchiveFormat::Pak => {
+            let parsed_pak = enfusion_pak::PakFile::parse(&mmap)?;
+            let pak = BytesPakFileWrapper::new(path, MmapWrapper(Arc::new(mmap)), parsed_pak);
+            Ok(ParsedArchive::Pak(Arc::new(pak)))
+        }
+        ArchiveFormat::Pbo => {
             let pbo = dayz_pbo::PboFile::parse(&mmap).map_err(|e| {
                 PakError::IoError(std::io::Error::new(
                     std::io::ErrorKind::InvalidData,
@@ -87,9 +120,11 @@ pub fn parse_archive_file(path: PathBuf) -> Result<ParsedArchive, PakError> {
             let vfs = dayz_pbo::pbo_vfs::PboVfs::new(MmapWrapper(Arc::new(mmap)), pbo);
             Ok(ParsedArchive::Pbo(vfs))
         }
-        _ => {
-            let pak = parse_pak_file(path)?;
-            Ok(ParsedArchive::Pak(Arc::new(pak)))
+        #[cfg(feature = "zip")]
+        ArchiveFormat::Zip => {
+            let len = mmap.len();
+            let vfs = enfusion_pak::zip_vfs::ZipVfs::new(MmapWrapper(Arc::new(mmap)), len)?;
+            Ok(ParsedArchive::Zip(vfs))
         }
     }
 }
@@ -0,0 +1,246 @@
+//! Off-main-thread archive reads and pak parsing for the web build.
+//!
+//! Byte ranges are read by a dedicated Web Worker (`assets/pak_reader_worker.js`) which
+//! also keeps a block cache. Data comes back as transferred `ArrayBuffer`s.
+//!
+//! Paks are parsed on the worker too. It loads this app's own wasm module and calls
+//! [`parse_pak_in_worker`], which reads the pak through the worker's cache and sends the
+//! parsed `PakFile` back serialized, so the UI thread only deserializes its listing.
+
+use std::cell::Cell;
+use std::cell::OnceCell;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use enfusion_pak::PakFile;
+use enfusion_pak::async_pak_vfs::AsyncReadAt;
+use enfusion_pak::vfs::VfsError;
+use enfusion_pak::vfs::error::VfsErrorKind;
+use futures::channel::oneshot;
+use tracing::warn;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::prelude::wasm_bindgen;
+use web_sys::js_sys;
+
+/// Path of the worker script, relative to the page. Trunk copies it next to `index.html`.
+const WORKER_URL: &str = "./pak_reader_worker.js";
+
+/// Replies to requests sent to the worker, by request ID.
+type PendingRequests = Rc<RefCell<HashMap<u32, oneshot::Sender<JsValue>>>>;
+
+#[wasm_bindgen]
+extern "C" {
+    /// URL of the app's wasm-bindgen glue, which the worker imports to run the parser.
+    #[wasm_bindgen(thread_local_v2, js_namespace = ["import", "meta"], js_name = url)]
+    static APP_SCRIPT_URL: String;
+
+    /// The worker script's cached read of a registered file. Only exists on the worker.
+    #[wasm_bindgen(catch, js_name = readRange)]
+    fn worker_read_range(file_id: u32, start: f64, end: f64)
+    -> Result<js_sys::Uint8Array, JsValue>;
+}
+
+struct ReaderWorker {
+    worker: web_sys::Worker,
+    /// Files registered with the worker. A file's index is its ID.
+    files: RefCell<Vec<web_sys::File>>,
+    pending: PendingRequests,
+    next_request_id: Cell<u32>,
+    _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+}
+
+thread_local! {
+    static WORKER: OnceCell<Option<Rc<ReaderWorker>>> = const { OnceCell::new() };
+}
+
+impl ReaderWorker {
+    fn spawn() -> Result<Self, JsValue> {
+        let worker = web_sys::Worker::new(WORKER_URL)?;
+        let pending = PendingRequests::default();
+
+        let handler_pending = Rc::clone(&pending);
+        let on_message = Closure::<dyn FnMut(_)>::new(move |event: web_sys::MessageEvent| {
+            let reply = event.data();
+            let Some(id) = get(&reply, "id").as_f64() else {
+                return;
+            };
+            let Some(sender) = handler_pending.borrow_mut().remove(&(id as u32)) else {
+                return;
+            };
+
+            let _ = sender.send(reply);
+        });
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            worker,
+            files: Default::default(),
+            pending,
+            next_request_id: Cell::new(0),
+            _on_message: on_message,
+        })
+    }
+
+    /// Returns the worker's ID for `file`, sending the file over on first use.
+    fn file_id(&self, file: &web_sys::File) -> Result<u32, JsValue> {
+        let mut files = self.files.borrow_mut();
+        if let Some(id) = files.iter().position(|known| js_sys::Object::is(known, file)) {
+            return Ok(id as u32);
+        }
+
+        let id = files.len() as u32;
+        self.worker.post_message(&message(&[
+            ("kind", "register".into()),
+            ("fileId", id.into()),
+            ("file", JsValue::from(file.clone())),
+        ]))?;
+        files.push(file.clone());
+
+        Ok(id)
+    }
+
+    /// Sends the worker a request and waits for its reply. `fields` doesn't include the
+    /// request's ID.
+    async fn request(&self, fields: &[(&str, JsValue)]) -> Result<JsValue, String> {
+        let id = self.next_request_id.get();
+        self.next_request_id.set(id.wrapping_add(1));
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.borrow_mut().insert(id, tx);
+
+        let request = message(fields);
+        let _ = js_sys::Reflect::set(&request, &"id".into(), &id.into());
+        if let Err(e) = self.worker.post_message(&request) {
+            self.pending.borrow_mut().remove(&id);
+            return Err(format!("{e:?}"));
+        }
+
+        rx.await.map_err(|_| "reader worker went away".to_string())
+    }
+
+    async fn read(&self, file: &web_sys::File, range: Range<usize>) -> Result<Vec<u8>, String> {
+        let file_id = self.file_id(file).map_err(|e| format!("{e:?}"))?;
+        let reply = self
+            .request(&[
+                ("kind", "read".into()),
+                ("fileId", file_id.into()),
+                ("start", (range.start as f64).into()),
+                ("end", (range.end as f64).into()),
+            ])
+            .await?;
+
+        reply_result(&reply).map(|buffer| js_sys::Uint8Array::new(&buffer).to_vec())
+    }
+
+    /// Parses the pak in `file` on the worker. `Ok(None)` means the worker couldn't load
+    /// the parser.
+    async fn parse_pak(&self, file: &web_sys::File) -> Result<Option<PakFile>, String> {
+        let file_id = self.file_id(file).map_err(|e| format!("{e:?}"))?;
+        let reply = self
+            .request(&[
+                ("kind", "parse".into()),
+                ("fileId", file_id.into()),
+                ("name", file.name().into()),
+                ("glueUrl", APP_SCRIPT_URL.with(String::clone).into()),
+                ("module", wasm_bindgen::module()),
+            ])
+            .await?;
+        if let Some(e) = get(&reply, "unsupported").as_string() {
+            warn!(e, "reader worker couldn't load the parser, parsing on the main thread");
+            return Ok(None);
+        }
+
+        let json = reply_result(&reply)?.as_string().unwrap_or_default();
+        serde_json::from_str(&json).map(Some).map_err(|e| e.to_string())
+    }
+}
+
+/// The reader worker, started on first use. `None` if it couldn't be started.
+fn worker() -> Option<Rc<ReaderWorker>> {
+    WORKER.with(|worker| {
+        worker
+            .get_or_init(|| match ReaderWorker::spawn() {
+                Ok(worker) => Some(Rc::new(worker)),
+                Err(e) => {
+                    warn!(?e, "failed to start reader worker, reading on the main thread");
+                    None
+                }
+            })
+            .clone()
+    })
+}
+
+/// Reads `range` of `file` on the reader worker. Returns `None` if the worker couldn't be
+/// started, in which case the caller should read on the main thread instead.
+pub async fn read_range(
+    file: &web_sys::File,
+    range: Range<usize>,
+) -> Option<Result<Vec<u8>, String>> {
+    let worker = worker()?;
+
+    Some(worker.read(file, range).await)
+}
+
+/// Parses the pak in `file` on the reader worker. Returns `None` if the worker couldn't be
+/// started or couldn't load the parser, in which case the caller should parse on the main
+/// thread instead.
+pub async fn parse_pak(file: &web_sys::File) -> Option<Result<PakFile, String>> {
+    worker()?.parse_pak(file).await.transpose()
+}
+
+/// Runs on the reader worker: parses the pak registered under `file_id` and returns it as
+/// JSON.
+#[wasm_bindgen(js_name = parsePakInWorker)]
+pub async fn parse_pak_in_worker(file_id: u32, name: String) -> Result<String, JsValue> {
+    let parsed = enfusion_pak::wrappers::async_reader::parse_pak_file(
+        PathBuf::from(name),
+        WorkerFile(file_id),
+    )
+    .await
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let pak: &PakFile = parsed.as_ref();
+
+    serde_json::to_string(pak).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// A file registered with the worker, read from the worker itself.
+#[derive(Debug, Clone, Copy)]
+struct WorkerFile(u32);
+
+#[async_trait]
+impl AsyncReadAt for WorkerFile {
+    async fn read_at(&self, file_range: Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
+        worker_read_range(self.0, file_range.start as f64, file_range.end as f64)
+            .map(|data| data.to_vec())
+            .map_err(|e| VfsErrorKind::Other(format!("{e:?}")).into())
+    }
+}
+
+/// The `result` of a reply, or its `error`.
+fn reply_result(reply: &JsValue) -> Result<JsValue, String> {
+    let result = get(reply, "result");
+    if result.is_undefined() {
+        return Err(get(reply, "error").as_string().unwrap_or_default());
+    }
+
+    Ok(result)
+}
+
+fn message(fields: &[(&str, JsValue)]) -> js_sys::Object {
+    let object = js_sys::Object::new();
+    for (key, value) in fields {
+        let _ = js_sys::Reflect::set(&object, &(*key).into(), value);
+    }
+
+    object
+}
+
+fn get(object: &JsValue, key: &str) -> JsValue {
+    js_sys::Reflect::get(object, &key.into()).unwrap_or(JsValue::UNDEFINED)
+}
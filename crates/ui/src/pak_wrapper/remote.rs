@@ -0,0 +1,39 @@
+//! Fetching archives hosted over HTTP, for share links.
+//!
+//! Each archive is downloaded into a `File` with the name from its URL, so it loads the
+//! same way as one picked from disk.
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::pak_wrapper::FileReference;
+
+#[wasm_bindgen(inline_js = r#"
+export async function fetchArchive(url) {
+    const response = await fetch(url);
+    if (!response.ok) {
+        throw `${url}: HTTP ${response.status} ${response.statusText}`;
+    }
+    const path = new URL(response.url || url, window.location.href).pathname;
+    const name = decodeURIComponent(path.split("/").pop());
+    return new File([await response.blob()], name);
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(catch, js_name = fetchArchive)]
+    async fn fetch_archive(url: &str) -> Result<JsValue, JsValue>;
+}
+
+/// Downloads the archives at `urls`, in order. Fails with the first download's error.
+pub async fn fetch_archives(urls: &[String]) -> Result<Vec<FileReference>, String> {
+    let mut files = Vec::with_capacity(urls.len());
+    for url in urls {
+        let file = fetch_archive(url)
+            .await
+            .map_err(|e| e.as_string().unwrap_or_else(|| format!("{url}: {e:?}")))?;
+        let file = file.dyn_into::<web_sys::File>().map_err(|_| format!("{url}: not a file"))?;
+        files.push(FileReference(file));
+    }
+    Ok(files)
+}
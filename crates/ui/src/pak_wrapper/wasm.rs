@@ -1,22 +1,93 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use eframe::wasm_bindgen::prelude::Closure;
+use enfusion_pak::archive_format::ArchiveFormat;
 use enfusion_pak::async_pak_vfs::AsyncReadAt;
+use enfusion_pak::pak_vfs::ReadAt;
 use enfusion_pak::vfs::VfsError;
+use enfusion_pak::vfs::error::VfsErrorKind;
+use enfusion_pak::wrappers::async_reader::CachingAsyncPakFileWrapper;
+use enfusion_pak::wrappers::async_reader::parse_pak_file;
+use enfusion_pak::wrappers::retry::RetryPolicy;
+use enfusion_pak::wrappers::retry::RetryingSource;
+use enfusion_pak::wrappers::retry::Timer;
 use futures::channel::oneshot;
+use tracing::error;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::wasm_bindgen;
 use web_sys::js_sys;
 
+use crate::pak_wrapper::reader_worker;
 use crate::task::execute;
 
 #[repr(transparent)]
 #[derive(Clone, Debug)]
-pub struct FileReference(pub rfd::FileHandle);
+pub struct FileReference(pub web_sys::File);
 
 impl FileReference {
+    pub fn file_name(&self) -> String {
+        self.0.name()
+    }
+
+    /// How this file is shown to the user.
+    pub fn display_name(&self) -> String {
+        self.file_name()
+    }
+
     pub fn has_supported_extension(&self) -> bool {
-        let name = self.0.file_name();
-        name.ends_with(".pak") || name.ends_with(".pbo")
+        ArchiveFormat::from_extension(&self.file_name()).is_some()
+    }
+
+    /// This file as a source whose failed or stalled reads are retried, for archives which
+    /// are read from for as long as they're loaded.
+    pub fn retrying(&self) -> RetryingSource<FileReference, WebTimer> {
+        RetryingSource::new(self.clone(), RetryPolicy::default(), WebTimer)
+    }
+
+    /// Parses this file as a pak, on the reader worker unless it can't be used there.
+    pub async fn parse_pak(
+        &self,
+    ) -> Result<CachingAsyncPakFileWrapper<RetryingSource<FileReference, WebTimer>>, String> {
+        let (tx, rx) = oneshot::channel();
+        let handle = self.clone();
+
+        // The worker isn't Send either
+        execute(async move {
+            let _ = tx.send(reader_worker::parse_pak(&handle.0).await);
+        });
+
+        match rx.await.ok().flatten() {
+            Some(parsed) => parsed.map(|pak_file| {
+                CachingAsyncPakFileWrapper::new(self.file_name().into(), self.retrying(), pak_file)
+            }),
+            None => parse_pak_file(self.file_name().into(), self.retrying())
+                .await
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    async fn read_range(&self, file_range: std::ops::Range<usize>) -> Result<Vec<u8>, VfsError> {
+        let (tx, rx) = oneshot::channel();
+        let handle = self.clone();
+
+        // Need to execute this task separately as it does not impl Send
+        execute(async move {
+            let _ = tx.send(read_file_slice(handle, file_range).await);
+        });
+
+        match rx.await {
+            Ok(Ok(data)) => Ok(data),
+            // Failed reads were logged by `read_file_slice`
+            _ => Err(VfsErrorKind::Other(format!("failed to read {}", self.file_name())).into()),
+        }
+    }
+}
+
+impl From<rfd::FileHandle> for FileReference {
+    fn from(handle: rfd::FileHandle) -> Self {
+        Self(handle.inner().clone())
     }
 }
 
@@ -29,19 +100,14 @@ impl AsyncReadAt for FileReference {
         &self,
         file_range: std::ops::Range<usize>,
     ) -> Result<impl AsRef<[u8]>, VfsError> {
-        let (tx, rx) = oneshot::channel();
-        let handle = self.clone();
-
-        // Need to execute this task separately as it does not impl Send
-        execute(async move {
-            let data = read_file_slice(handle, file_range).await.expect("failed to read buffer");
-
-            let _ = tx.send(data);
-        });
-
-        let data = rx.await.expect("failed to receive buffer");
+        self.read_range(file_range).await
+    }
+}
 
-        Ok(data)
+/// Files can only be read asynchronously on the web, so sync opens through a VFS fail.
+impl ReadAt for FileReference {
+    fn read_at(&self, _file_range: std::ops::Range<usize>) -> Result<impl AsRef<[u8]>, VfsError> {
+        Err::<Vec<u8>, _>(VfsErrorKind::NotSupported.into())
     }
 }
 
@@ -51,17 +117,34 @@ impl dayz_pbo::async_pbo_vfs::AsyncReadAt for FileReference {
         &self,
         file_range: std::ops::Range<usize>,
     ) -> Result<impl AsRef<[u8]>, VfsError> {
-        let (tx, rx) = oneshot::channel();
-        let handle = self.clone();
+        self.read_range(file_range).await
+    }
+}
 
-        execute(async move {
-            let data = read_file_slice(handle, file_range).await.expect("failed to read buffer");
-            let _ = tx.send(data);
-        });
+#[wasm_bindgen(inline_js = r#"
+export function sleep(ms) {
+    return new Promise((resolve) => setTimeout(resolve, ms));
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(js_name = sleep)]
+    async fn js_sleep(ms: f64) -> JsValue;
+}
 
-        let data = rx.await.expect("failed to receive buffer");
+/// A [`Timer`] backed by `setTimeout`.
+#[derive(Debug, Clone, Copy)]
+pub struct WebTimer;
 
-        Ok(data)
+#[async_trait]
+impl Timer for WebTimer {
+    async fn sleep(&self, duration: Duration) {
+        let (tx, rx) = oneshot::channel();
+        // JS futures aren't Send either
+        execute(async move {
+            js_sleep(duration.as_secs_f64() * 1000.0).await;
+            let _ = tx.send(());
+        });
+        let _ = rx.await;
     }
 }
 
@@ -70,6 +153,10 @@ async fn read_file_slice(
     file_reference: FileReference,
     range: std::ops::Range<usize>,
 ) -> Result<Vec<u8>, ()> {
+    if let Some(result) = reader_worker::read_range(&file_reference.0, range.clone()).await {
+        return result.map_err(|e| error!(file = %file_reference.file_name(), e, "read failed"));
+    }
+
     let range = (range.start as u64)..(range.end as u64);
     let start = range.start;
     let end = range.end;
@@ -80,7 +167,6 @@ async fn read_file_slice(
         // Create a slice of the file using the slice method
         let blob = file
             .0
-            .inner()
             .slice_with_f64_and_f64(start as f64, end as f64)
             .expect("failed to create file blob");
 
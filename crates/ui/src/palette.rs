@@ -0,0 +1,230 @@
+//! Colors of added, removed and changed lines and of search matches, which can be switched
+//! to a set that stays apart under red-green color blindness or to the user's own.
+
+use egui::Color32;
+
+/// The colors diffs and search results are drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub added: Color32,
+    pub removed: Color32,
+    pub changed: Color32,
+    /// Background of the words or bytes an added line doesn't share with the line it
+    /// replaced.
+    pub added_highlight: Color32,
+    pub removed_highlight: Color32,
+    /// Tint over a search query's matches in the results.
+    pub search_match: Color32,
+}
+
+impl Palette {
+    pub const DEFAULT: Palette = Palette {
+        added: Color32::LIGHT_GREEN,
+        removed: Color32::LIGHT_RED,
+        changed: Color32::ORANGE,
+        added_highlight: Color32::from_rgb(40, 90, 40),
+        removed_highlight: Color32::from_rgb(110, 40, 40),
+        search_match: Color32::from_rgb(255, 210, 0),
+    };
+
+    /// Blue for additions and orange for removals, from IBM's color-blind safe palette.
+    pub const DEUTERANOPIA: Palette = Palette {
+        added: Color32::from_rgb(0x64, 0x8f, 0xff),
+        removed: Color32::from_rgb(0xfe, 0x61, 0x00),
+        changed: Color32::from_rgb(0xff, 0xb0, 0x00),
+        added_highlight: Color32::from_rgb(30, 60, 130),
+        removed_highlight: Color32::from_rgb(130, 55, 10),
+        search_match: Color32::from_rgb(0xdc, 0x26, 0x7f),
+    };
+
+    pub fn get(&self, color: PaletteColor) -> Color32 {
+        match color {
+            PaletteColor::Added => self.added,
+            PaletteColor::Removed => self.removed,
+            PaletteColor::Changed => self.changed,
+            PaletteColor::AddedHighlight => self.added_highlight,
+            PaletteColor::RemovedHighlight => self.removed_highlight,
+            PaletteColor::SearchMatch => self.search_match,
+        }
+    }
+
+    fn get_mut(&mut self, color: PaletteColor) -> &mut Color32 {
+        match color {
+            PaletteColor::Added => &mut self.added,
+            PaletteColor::Removed => &mut self.removed,
+            PaletteColor::Changed => &mut self.changed,
+            PaletteColor::AddedHighlight => &mut self.added_highlight,
+            PaletteColor::RemovedHighlight => &mut self.removed_highlight,
+            PaletteColor::SearchMatch => &mut self.search_match,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::DEFAULT
+    }
+}
+
+/// One of a [`Palette`]'s colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteColor {
+    Added,
+    Removed,
+    Changed,
+    AddedHighlight,
+    RemovedHighlight,
+    SearchMatch,
+}
+
+impl PaletteColor {
+    pub const ALL: [PaletteColor; 6] = [
+        PaletteColor::Added,
+        PaletteColor::Removed,
+        PaletteColor::Changed,
+        PaletteColor::AddedHighlight,
+        PaletteColor::RemovedHighlight,
+        PaletteColor::SearchMatch,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaletteColor::Added => "Added",
+            PaletteColor::Removed => "Removed",
+            PaletteColor::Changed => "Changed",
+            PaletteColor::AddedHighlight => "Added words",
+            PaletteColor::RemovedHighlight => "Removed words",
+            PaletteColor::SearchMatch => "Search matches",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum PaletteKind {
+    #[default]
+    Default,
+    Deuteranopia,
+    Custom,
+}
+
+impl PaletteKind {
+    pub const ALL: [PaletteKind; 3] =
+        [PaletteKind::Default, PaletteKind::Deuteranopia, PaletteKind::Custom];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaletteKind::Default => "Default",
+            PaletteKind::Deuteranopia => "Deuteranopia-friendly",
+            PaletteKind::Custom => "Custom",
+        }
+    }
+}
+
+/// The palette picked in the Edit menu, and the hex colors of the custom one.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct PaletteSettings {
+    pub kind: PaletteKind,
+    /// Colors such as `#90ee90`, in the order of [`PaletteColor::ALL`]. Ones which can't be
+    /// parsed are drawn in the default palette's color.
+    pub custom: Vec<String>,
+}
+
+impl Default for PaletteSettings {
+    fn default() -> Self {
+        PaletteSettings { kind: PaletteKind::Default, custom: default_custom_colors() }
+    }
+}
+
+impl PaletteSettings {
+    pub fn palette(&self) -> Palette {
+        match self.kind {
+            PaletteKind::Default => Palette::DEFAULT,
+            PaletteKind::Deuteranopia => Palette::DEUTERANOPIA,
+            PaletteKind::Custom => {
+                let mut palette = Palette::DEFAULT;
+                for (color, hex) in PaletteColor::ALL.into_iter().zip(&self.custom) {
+                    if let Some(parsed) = parse_hex(hex) {
+                        *palette.get_mut(color) = parsed;
+                    }
+                }
+                palette
+            }
+        }
+    }
+
+    /// The hex text of a custom color, for editing.
+    pub fn custom_mut(&mut self, color: PaletteColor) -> &mut String {
+        // Settings saved by a version with fewer colors are missing the newer ones
+        if self.custom.len() < PaletteColor::ALL.len() {
+            let defaults = default_custom_colors();
+            self.custom.extend(defaults.into_iter().skip(self.custom.len()));
+        }
+        let index = PaletteColor::ALL.iter().position(|c| *c == color).expect("listed in ALL");
+        &mut self.custom[index]
+    }
+
+    pub fn reset_custom(&mut self) {
+        self.custom = default_custom_colors();
+    }
+}
+
+fn default_custom_colors() -> Vec<String> {
+    PaletteColor::ALL.into_iter().map(|color| to_hex(Palette::DEFAULT.get(color))).collect()
+}
+
+/// Parses an opaque color written as `#rrggbb` or `#rgb`. The `#` is optional.
+pub fn parse_hex(text: &str) -> Option<Color32> {
+    let digits = text.trim().trim_start_matches('#');
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |range: std::ops::Range<usize>| u8::from_str_radix(&digits[range], 16).ok();
+    match digits.len() {
+        6 => Some(Color32::from_rgb(channel(0..2)?, channel(2..4)?, channel(4..6)?)),
+        // Each digit is repeated, so `#f80` is `#ff8800`
+        3 => Some(Color32::from_rgb(
+            channel(0..1)? * 0x11,
+            channel(1..2)? * 0x11,
+            channel(2..3)? * 0x11,
+        )),
+        _ => None,
+    }
+}
+
+pub fn to_hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_hex("#90ee90"), Some(Color32::from_rgb(0x90, 0xee, 0x90)));
+        assert_eq!(parse_hex(" 648FFF "), Some(Color32::from_rgb(0x64, 0x8f, 0xff)));
+        assert_eq!(parse_hex("#f80"), Some(Color32::from_rgb(0xff, 0x88, 0x00)));
+        assert_eq!(parse_hex("#90ee9"), None);
+        assert_eq!(parse_hex("#90ee9g"), None);
+        assert_eq!(parse_hex("#ü0ee9"), None);
+        assert_eq!(to_hex(Color32::from_rgb(0x28, 0x5a, 0x28)), "#285a28");
+    }
+
+    #[test]
+    fn custom_colors_fall_back_to_the_defaults() {
+        let mut settings = PaletteSettings { kind: PaletteKind::Custom, ..Default::default() };
+        assert_eq!(settings.palette(), Palette::DEFAULT);
+
+        *settings.custom_mut(PaletteColor::Added) = "#648fff".to_string();
+        *settings.custom_mut(PaletteColor::Removed) = "not a color".to_string();
+        let palette = settings.palette();
+        assert_eq!(palette.added, Color32::from_rgb(0x64, 0x8f, 0xff));
+        assert_eq!(palette.removed, Palette::DEFAULT.removed);
+
+        // Saved before search matches had a color
+        settings.custom.truncate(2);
+        assert_eq!(settings.palette().search_match, Palette::DEFAULT.search_match);
+        assert_eq!(settings.custom_mut(PaletteColor::SearchMatch), "#ffd200");
+    }
+}
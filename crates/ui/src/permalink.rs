@@ -0,0 +1,154 @@
+//! `enfpak://` links pointing at a file (and optionally a line) in a set of archives.
+//!
+//! A link looks like `enfpak://3f2a9c04d1e8b756/scripts/Game/foo.c#L120`. The first
+//! segment identifies the loaded archives so a link opened against a different build can
+//! be flagged, and the rest is the file's path inside the overlay.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::task::ArchiveLayer;
+
+pub const SCHEME: &str = "enfpak://";
+
+/// Identifies a set of loaded archives by their file names, load order, and how many files
+/// they contain, so it matches across machines with different install directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PakSetHash(u64);
+
+impl PakSetHash {
+    pub fn new(layers: &[ArchiveLayer], file_count: usize) -> Self {
+        // FNV-1a, which unlike `DefaultHasher` is stable across Rust versions
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        let mut hash = OFFSET_BASIS;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(PRIME);
+            }
+        };
+        for layer in layers {
+            let file_name = layer.name.rsplit(['/', '\\']).next().unwrap_or(&layer.name);
+            write(file_name.as_bytes());
+            write(&[0]);
+        }
+        write(&(file_count as u64).to_le_bytes());
+
+        PakSetHash(hash)
+    }
+}
+
+impl fmt::Display for PakSetHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum PermalinkError {
+    #[error("links must start with {SCHEME}")]
+    MissingScheme,
+    #[error("\"{0}\" is not a pak set hash")]
+    InvalidPakSet(String),
+    #[error("link has no file path")]
+    MissingPath,
+    #[error("\"{0}\" is not a line reference like #L120")]
+    InvalidLine(String),
+}
+
+/// A link to a file, and optionally a line in it, within a set of archives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permalink {
+    pub pak_set: PakSetHash,
+    /// Path inside the overlay, starting with `/`.
+    pub path: String,
+    /// 1-based line number.
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for Permalink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{SCHEME}{}/{}", self.pak_set, self.path.trim_start_matches('/'))?;
+        if let Some(line) = self.line {
+            write!(f, "#L{line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Permalink {
+    type Err = PermalinkError;
+
+    fn from_str(link: &str) -> Result<Self, Self::Err> {
+        let rest = link.trim().strip_prefix(SCHEME).ok_or(PermalinkError::MissingScheme)?;
+        let (pak_set, rest) = rest.split_once('/').ok_or(PermalinkError::MissingPath)?;
+        let pak_set = u64::from_str_radix(pak_set, 16)
+            .map(PakSetHash)
+            .map_err(|_| PermalinkError::InvalidPakSet(pak_set.to_string()))?;
+
+        let (path, line) = match rest.split_once('#') {
+            Some((path, fragment)) => {
+                let line = fragment
+                    .strip_prefix('L')
+                    .and_then(|line| line.parse().ok())
+                    .filter(|line| *line > 0)
+                    .ok_or_else(|| PermalinkError::InvalidLine(fragment.to_string()))?;
+                (path, Some(line))
+            }
+            None => (rest, None),
+        };
+        if path.is_empty() {
+            return Err(PermalinkError::MissingPath);
+        }
+
+        Ok(Permalink { pak_set, path: format!("/{path}"), line })
+    }
+}
+
+/// The 1-based line containing the character at `char_index` in `text`.
+pub fn line_at(text: &str, char_index: usize) -> usize {
+    text.chars().take(char_index).filter(|c| *c == '\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_links() {
+        let link = Permalink {
+            pak_set: PakSetHash(0x3f2a9c04d1e8b756),
+            path: "/scripts/Game/foo.c".to_string(),
+            line: Some(120),
+        };
+        assert_eq!(link.to_string(), "enfpak://3f2a9c04d1e8b756/scripts/Game/foo.c#L120");
+        assert_eq!(link.to_string().parse(), Ok(link));
+
+        let no_line: Permalink = "enfpak://00000000000000ff/Configs/a.conf".parse().unwrap();
+        assert_eq!(no_line.path, "/Configs/a.conf");
+        assert_eq!(no_line.line, None);
+    }
+
+    #[test]
+    fn rejects_malformed_links() {
+        assert_eq!("https://x/y".parse::<Permalink>(), Err(PermalinkError::MissingScheme));
+        assert_eq!("enfpak://ff".parse::<Permalink>(), Err(PermalinkError::MissingPath));
+        assert!(matches!(
+            "enfpak://zz/a.c".parse::<Permalink>(),
+            Err(PermalinkError::InvalidPakSet(_))
+        ));
+        assert!(matches!(
+            "enfpak://ff/a.c#L0".parse::<Permalink>(),
+            Err(PermalinkError::InvalidLine(_))
+        ));
+    }
+
+    #[test]
+    fn finds_cursor_lines() {
+        assert_eq!(line_at("a\nb\nc", 0), 1);
+        assert_eq!(line_at("a\nb\nc", 2), 2);
+        assert_eq!(line_at("a\nb\nc", 5), 3);
+    }
+}
@@ -0,0 +1,314 @@
+//! Native plugins providing previewers and analyses for formats the app doesn't know.
+//!
+//! A plugin is a dynamic library (`.dll`, `.so`, or `.dylib`) in the plugin directory:
+//! `$ENFUSION_TOOLS_PLUGIN_DIR` if set, otherwise `plugins` next to the executable. It
+//! exports one function returning a static [`PluginV1`]:
+//!
+//! ```ignore
+//! #[unsafe(no_mangle)]
+//! pub extern "C" fn enfusion_tools_plugin() -> *const PluginV1 {
+//!     &PLUGIN
+//! }
+//! ```
+//!
+//! Every function a plugin provides may be called from any thread, and must not unwind.
+//! Output is returned in [`PluginBuffer`]s allocated by the plugin and released with its
+//! `free_buffer`.
+//!
+//! Plugins aren't available in the web build.
+
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::ffi::c_char;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::info;
+use tracing::warn;
+
+use crate::analysis::Analysis;
+use crate::analysis::AnalysisError;
+use crate::analysis::Item;
+use crate::analysis::Report;
+use crate::analysis::Section;
+use crate::analysis::Workspace;
+use crate::preview::Previewer;
+
+/// Version of [`PluginV1`]. Plugins reporting any other version aren't loaded.
+pub const ABI_VERSION: u32 = 1;
+
+/// Name of the function every plugin exports.
+const ENTRY_POINT: &[u8] = b"enfusion_tools_plugin";
+
+/// Bytes allocated by a plugin.
+#[repr(C)]
+pub struct PluginBuffer {
+    pub ptr: *mut u8,
+    pub len: usize,
+}
+
+/// Called with a file's path (NUL-terminated UTF-8) and contents. Returns 0 on success,
+/// with the output written to `out`. On failure `out` may hold an error message.
+pub type FileCallback = unsafe extern "C" fn(
+    path: *const c_char,
+    data: *const u8,
+    len: usize,
+    out: *mut PluginBuffer,
+) -> i32;
+
+/// What a plugin provides. Strings are NUL-terminated UTF-8.
+#[repr(C)]
+pub struct PluginV1 {
+    /// Must be [`ABI_VERSION`].
+    pub abi_version: u32,
+    pub name: *const c_char,
+    /// Null-terminated array of the file extensions (without a dot) the plugin handles.
+    pub extensions: *const *const c_char,
+    /// Renders a file as UTF-8 text for an editor tab.
+    pub preview: Option<FileCallback>,
+    /// Name of the analysis added to the Analyses menu, if the plugin has one.
+    pub analysis_name: *const c_char,
+    /// Inspects one file for the analysis. Each output line is a finding, optionally
+    /// starting with `<line number>:`.
+    pub analyze: Option<FileCallback>,
+    pub free_buffer: unsafe extern "C" fn(buffer: PluginBuffer),
+}
+
+pub struct NativePlugin {
+    name: String,
+    extensions: Vec<String>,
+    analysis_name: Option<String>,
+    plugin: *const PluginV1,
+    // Keeps `plugin` and the functions it points to loaded
+    _library: libloading::Library,
+}
+
+// Plugins are required to be callable from any thread, and the vtable is immutable.
+unsafe impl Send for NativePlugin {}
+unsafe impl Sync for NativePlugin {}
+
+impl NativePlugin {
+    /// Loads the plugin at `path`.
+    ///
+    /// # Safety
+    ///
+    /// The library's initialization code runs, and its entry point must return a valid
+    /// [`PluginV1`] that lives as long as the library is loaded.
+    unsafe fn load(path: &Path) -> Result<Self, String> {
+        let library = unsafe { libloading::Library::new(path) }.map_err(|e| e.to_string())?;
+        let entry_point =
+            unsafe { library.get::<unsafe extern "C" fn() -> *const PluginV1>(ENTRY_POINT) }
+                .map_err(|e| e.to_string())?;
+
+        let plugin = unsafe { entry_point() };
+        let Some(vtable) = (unsafe { plugin.as_ref() }) else {
+            return Err("entry point returned null".to_string());
+        };
+        if vtable.abi_version != ABI_VERSION {
+            return Err(format!(
+                "plugin ABI version {} isn't supported (expected {ABI_VERSION})",
+                vtable.abi_version
+            ));
+        }
+
+        let mut extensions = Vec::new();
+        if !vtable.extensions.is_null() {
+            let mut next = vtable.extensions;
+            while let Some(extension) = unsafe { string(*next) } {
+                extensions.push(extension.to_ascii_lowercase());
+                next = unsafe { next.add(1) };
+            }
+        }
+
+        Ok(Self {
+            name: unsafe { string(vtable.name) }.unwrap_or_else(|| path.display().to_string()),
+            extensions,
+            analysis_name: unsafe { string(vtable.analysis_name) },
+            plugin,
+            _library: library,
+        })
+    }
+
+    /// The analysis this plugin adds to the Analyses menu, if it has one.
+    pub fn analysis(self: &Arc<Self>) -> Option<PluginAnalysis> {
+        let name = self.analysis_name.clone()?;
+        self.vtable().analyze?;
+
+        Some(PluginAnalysis { plugin: Arc::clone(self), name })
+    }
+
+    fn vtable(&self) -> &PluginV1 {
+        unsafe { &*self.plugin }
+    }
+
+    fn handles_extension(&self, path: &str) -> bool {
+        path.rsplit_once('.').is_some_and(|(_, ext)| {
+            self.extensions.iter().any(|known| known.eq_ignore_ascii_case(ext))
+        })
+    }
+
+    fn call(&self, callback: FileCallback, path: &str, data: &[u8]) -> Result<String, String> {
+        let path = CString::new(path).map_err(|e| e.to_string())?;
+        let mut out = PluginBuffer { ptr: std::ptr::null_mut(), len: 0 };
+        let status = unsafe { callback(path.as_ptr(), data.as_ptr(), data.len(), &mut out) };
+
+        let output = if out.ptr.is_null() {
+            String::new()
+        } else {
+            let bytes = unsafe { std::slice::from_raw_parts(out.ptr, out.len) };
+            let output = String::from_utf8_lossy(bytes).into_owned();
+            unsafe { (self.vtable().free_buffer)(out) };
+            output
+        };
+
+        match status {
+            0 => Ok(output),
+            _ if !output.is_empty() => Err(output),
+            status => Err(format!("{} failed with status {status}", self.name)),
+        }
+    }
+}
+
+/// Reads a NUL-terminated string, or `None` for a null pointer.
+unsafe fn string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+
+    Some(unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+}
+
+impl Previewer for NativePlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn handles(&self, path: &str) -> bool {
+        self.vtable().preview.is_some() && self.handles_extension(path)
+    }
+
+    fn preview(&self, path: &str, data: &[u8]) -> Result<String, String> {
+        let preview = self.vtable().preview.ok_or("plugin has no previewer")?;
+        self.call(preview, path, data)
+    }
+}
+
+/// Runs a plugin's analysis over every file with one of the plugin's extensions.
+pub struct PluginAnalysis {
+    plugin: Arc<NativePlugin>,
+    name: String,
+}
+
+#[async_trait]
+impl Analysis for PluginAnalysis {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "Provided by a plugin"
+    }
+
+    async fn run(&self, workspace: &Workspace, _: Option<&[u8]>) -> Result<Report, AnalysisError> {
+        let analyze = self.plugin.vtable().analyze.ok_or("plugin has no analysis")?;
+
+        let mut sections = Vec::new();
+        for file in workspace.files().await {
+            if !self.plugin.handles_extension(file.as_str()) {
+                continue;
+            }
+
+            let Some(data) = workspace.read(&file).await else {
+                continue;
+            };
+            let items = match self.plugin.call(analyze, file.as_str(), &data) {
+                Ok(output) => findings(file.as_str(), &output),
+                Err(e) => vec![Item {
+                    file: Some(file.as_str().to_string()),
+                    text: format!("error: {e}"),
+                    ..Item::default()
+                }],
+            };
+            if !items.is_empty() {
+                sections.push(Section { heading: file.as_str().to_string(), items });
+            }
+        }
+        sections.sort_by(|a, b| a.heading.cmp(&b.heading));
+
+        let count: usize = sections.iter().map(|section| section.items.len()).sum();
+        Ok(Report {
+            summary: format!("{count} finding(s) in {} file(s)", sections.len()),
+            sections,
+            exports: Vec::new(),
+        })
+    }
+}
+
+/// Parses an analysis callback's output: one finding per line, each optionally starting
+/// with `<line number>:`.
+fn findings(file: &str, output: &str) -> Vec<Item> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|finding| {
+            let numbered = finding
+                .split_once(':')
+                .and_then(|(line, text)| Some((line.trim().parse().ok()?, text.trim())));
+            let (line, text) = match numbered {
+                Some((line, text)) => (Some(line), text),
+                None => (None, finding.trim()),
+            };
+
+            Item { file: Some(file.to_string()), line, text: text.to_string(), detail: None }
+        })
+        .collect()
+}
+
+/// Where plugins are loaded from.
+pub fn plugin_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("ENFUSION_TOOLS_PLUGIN_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+
+    Some(std::env::current_exe().ok()?.parent()?.join("plugins"))
+}
+
+/// Loads every plugin in `dir`, skipping (and logging) any that fail to load.
+pub fn load_plugins(dir: &Path) -> Vec<Arc<NativePlugin>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut plugins = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let is_library = path.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION);
+        if !is_library {
+            continue;
+        }
+
+        // Loading a library runs its code; anything in the plugin directory is trusted
+        match unsafe { NativePlugin::load(&path) } {
+            Ok(plugin) => {
+                info!(name = plugin.name, path = %path.display(), "loaded plugin");
+                plugins.push(Arc::new(plugin));
+            }
+            Err(e) => warn!(path = %path.display(), %e, "failed to load plugin"),
+        }
+    }
+
+    plugins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_findings() {
+        let items = findings("/a.nav", "12: bad link\n\nno line: here\n");
+        let parsed: Vec<_> = items.iter().map(|item| (item.line, item.text.as_str())).collect();
+        assert_eq!(parsed, [(Some(12), "bad link"), (None, "no line: here")]);
+    }
+}
@@ -0,0 +1,16 @@
+//! Turning file contents into text for an editor tab.
+//!
+//! Files are shown as text or a hexdump unless a registered [`Previewer`] understands
+//! them. Previewers can be added with [`crate::EnfusionToolsApp::register_previewer`] or
+//! loaded from native plugins.
+
+pub trait Previewer: Send + Sync {
+    /// Shown in the title of tabs this previewer renders.
+    fn name(&self) -> &str;
+
+    /// Whether this previewer understands the file at `path`.
+    fn handles(&self, path: &str) -> bool;
+
+    /// Renders a file's contents as text.
+    fn preview(&self, path: &str, data: &[u8]) -> Result<String, String>;
+}
@@ -0,0 +1,159 @@
+//! Opt-in local performance profile.
+//!
+//! While enabled, timings for workspace loads, file reads and searches plus cache hit
+//! counts are collected in memory. Users can save them to a JSON file and attach it to a
+//! report about a slow workspace. Nothing is ever sent anywhere.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+/// Samples of each kind beyond this many are dropped so a long session can't grow the
+/// profile without bound.
+const MAX_SAMPLES: usize = 1000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PROFILE: Mutex<Option<Profile>> = Mutex::new(None);
+
+#[derive(Debug, Default, serde::Serialize)]
+struct Profile {
+    started_unix_secs: u64,
+    loads: Vec<LoadSample>,
+    file_reads: Vec<FileReadSample>,
+    searches: Vec<SearchSample>,
+    caches: BTreeMap<&'static str, CacheStats>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct LoadSample {
+    pub archives: usize,
+    pub files: usize,
+    pub parse_ms: f64,
+    pub crawl_ms: f64,
+    pub tree_ms: f64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct FileReadSample {
+    pub bytes: usize,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SearchSample {
+    pub files_scanned: usize,
+    pub bytes_scanned: u64,
+    pub files_matched: usize,
+    pub duration_ms: f64,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+struct CacheStats {
+    hits: u64,
+    misses: u64,
+    hit_rate: f64,
+}
+
+/// Environment details written alongside the samples.
+#[derive(serde::Serialize)]
+struct Report<'a> {
+    app_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    duration_secs: u64,
+    #[serde(flatten)]
+    profile: &'a Profile,
+}
+
+/// Starts or stops recording. Stopping discards anything recorded so far.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    let mut profile = PROFILE.lock().expect("profile lock poisoned");
+    *profile = enabled.then(|| Profile { started_unix_secs: unix_secs(), ..Default::default() });
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns the current time if recording, for measuring an operation's duration.
+pub fn start() -> Option<Instant> {
+    is_enabled().then(Instant::now)
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+pub fn record_load(sample: LoadSample) {
+    with_profile(|profile| push_sample(&mut profile.loads, sample));
+}
+
+pub fn record_file_read(sample: FileReadSample) {
+    with_profile(|profile| push_sample(&mut profile.file_reads, sample));
+}
+
+pub fn record_search(sample: SearchSample) {
+    with_profile(|profile| push_sample(&mut profile.searches, sample));
+}
+
+/// Adds hit and miss counts to the named cache's running totals.
+pub fn record_cache(name: &'static str, hits: u64, misses: u64) {
+    with_profile(|profile| {
+        let stats = profile.caches.entry(name).or_default();
+        stats.hits += hits;
+        stats.misses += misses;
+
+        let total = stats.hits + stats.misses;
+        stats.hit_rate = if total == 0 { 0.0 } else { stats.hits as f64 / total as f64 };
+    });
+}
+
+/// Writes everything recorded so far to `path` as pretty-printed JSON.
+pub fn write_to(path: &Path) -> std::io::Result<()> {
+    let profile = PROFILE.lock().expect("profile lock poisoned");
+    let Some(profile) = profile.as_ref() else {
+        return Err(std::io::Error::other("profiling is not enabled"));
+    };
+
+    let report = Report {
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        duration_secs: unix_secs().saturating_sub(profile.started_unix_secs),
+        profile,
+    };
+
+    let json = serde_json::to_vec_pretty(&report).map_err(std::io::Error::other)?;
+    std::fs::write(path, json)
+}
+
+fn with_profile(f: impl FnOnce(&mut Profile)) {
+    if !is_enabled() {
+        return;
+    }
+
+    if let Some(profile) = PROFILE.lock().expect("profile lock poisoned").as_mut() {
+        f(profile);
+    }
+}
+
+fn push_sample<T>(samples: &mut Vec<T>, sample: T) {
+    if samples.len() < MAX_SAMPLES {
+        samples.push(sample);
+    }
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or_default()
+}
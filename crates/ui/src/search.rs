@@ -0,0 +1,300 @@
+//! Turning the search box's query and options into a search to run.
+
+use enfusion_search::FileSelection;
+use enfusion_search::Searcher;
+use enfusion_search::bytes::ByteSearcher;
+use regex::bytes::RegexBuilder;
+
+/// Only the most recent searches are kept in the history.
+const MAX_HISTORY: usize = 20;
+
+/// How the search box's query is read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum SearchMode {
+    /// A regular expression, matched against each line of text files.
+    #[default]
+    Regex,
+    /// Text matched as typed against each line of text files.
+    Literal,
+    /// Hex bytes or ASCII, found anywhere in files of any type. See [`ByteSearcher::parse`].
+    Binary,
+}
+
+impl SearchMode {
+    pub const ALL: [SearchMode; 3] = [SearchMode::Regex, SearchMode::Literal, SearchMode::Binary];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchMode::Regex => "Regex",
+            SearchMode::Literal => "Literal",
+            SearchMode::Binary => "Binary",
+        }
+    }
+}
+
+/// Which files text searches read. Binary searches read every file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum SearchedFiles {
+    /// Every type the registry considers text.
+    #[default]
+    Text,
+    /// Files with one of [`SearchOptions::extensions`].
+    Extensions,
+    /// Every file which doesn't look binary, whatever its extension.
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub mode: SearchMode,
+    /// Binary searches always match bytes exactly.
+    pub case_sensitive: bool,
+    /// Only match the query where it isn't part of a longer word. Not used by binary
+    /// searches.
+    pub whole_word: bool,
+    /// Matches to show before the search stops. A match is a line with its context, or one
+    /// offset of a binary search.
+    pub max_results: usize,
+    pub files: SearchedFiles,
+    /// Extensions searched when `files` is [`SearchedFiles::Extensions`], separated by
+    /// commas or spaces, e.g. `c, conf, layout`.
+    pub extensions: String,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            mode: SearchMode::default(),
+            case_sensitive: false,
+            whole_word: false,
+            max_results: 5000,
+            files: SearchedFiles::default(),
+            extensions: "c, et, conf, layout".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchQueryError {
+    #[error("nothing to search for")]
+    Empty,
+    #[error("no file extensions to search are set")]
+    NoExtensions,
+    #[error(transparent)]
+    InvalidRegex(#[from] regex::Error),
+}
+
+/// A query ready to run.
+#[derive(Debug, Clone)]
+pub enum SearchQuery {
+    /// Matches lines of text files, reporting line numbers.
+    Lines(Searcher),
+    /// Matches bytes of any file, reporting offsets.
+    Bytes(ByteSearcher),
+}
+
+impl SearchOptions {
+    pub fn compile(&self, query: &str) -> Result<SearchQuery, SearchQueryError> {
+        if query.trim().is_empty() {
+            return Err(SearchQueryError::Empty);
+        }
+
+        let mut pattern = match self.mode {
+            SearchMode::Binary => {
+                return ByteSearcher::parse(query)
+                    .map(SearchQuery::Bytes)
+                    .ok_or(SearchQueryError::Empty);
+            }
+            SearchMode::Regex => query.to_string(),
+            SearchMode::Literal => regex::escape(query),
+        };
+        if self.whole_word {
+            pattern = format!(r"\b(?:{pattern})\b");
+        }
+
+        let regex = RegexBuilder::new(&pattern).case_insensitive(!self.case_sensitive).build()?;
+        Ok(SearchQuery::Lines(Searcher::new(regex).files(self.file_selection()?)))
+    }
+
+    fn file_selection(&self) -> Result<FileSelection, SearchQueryError> {
+        Ok(match self.files {
+            SearchedFiles::Text => FileSelection::Text,
+            SearchedFiles::Extensions => {
+                let extensions: Vec<String> = self
+                    .extensions
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .map(|ext| ext.trim_start_matches('.'))
+                    .filter(|ext| !ext.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if extensions.is_empty() {
+                    return Err(SearchQueryError::NoExtensions);
+                }
+                FileSelection::Extensions(extensions)
+            }
+            SearchedFiles::All => FileSelection::All,
+        })
+    }
+}
+
+/// A search which was run, kept to run again.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct PastSearch {
+    pub query: String,
+    pub options: SearchOptions,
+}
+
+impl PastSearch {
+    /// The options the search was run with, as a short description such as
+    /// `Literal, match case`.
+    pub fn describe_options(&self) -> String {
+        let options = &self.options;
+        let mut parts = vec![options.mode.name().to_string()];
+        if options.mode != SearchMode::Binary {
+            if options.case_sensitive {
+                parts.push("match case".to_string());
+            }
+            if options.whole_word {
+                parts.push("whole word".to_string());
+            }
+            match options.files {
+                SearchedFiles::Text => {}
+                SearchedFiles::Extensions => parts.push(format!("in {}", options.extensions)),
+                SearchedFiles::All => parts.push("all files".to_string()),
+            }
+        }
+        parts.push(format!("up to {} results", options.max_results));
+        parts.join(", ")
+    }
+}
+
+/// Recently run searches, most recent first.
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SearchHistory {
+    searches: Vec<PastSearch>,
+}
+
+impl SearchHistory {
+    pub fn iter(&self) -> impl Iterator<Item = &PastSearch> {
+        self.searches.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.searches.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.searches.clear();
+    }
+
+    /// Adds `search` as the most recent search. Running the same query with the same
+    /// options again moves it to the top rather than listing it twice.
+    pub fn record(&mut self, search: PastSearch) {
+        self.searches.retain(|past| *past != search);
+        self.searches.insert(0, search);
+        self.searches.truncate(MAX_HISTORY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(options: &SearchOptions, query: &str, line: &str) -> bool {
+        match options.compile(query).unwrap() {
+            SearchQuery::Lines(searcher) => searcher.regex().is_match(line.as_bytes()),
+            SearchQuery::Bytes(_) => panic!("{query} compiled to a byte search"),
+        }
+    }
+
+    #[test]
+    fn applies_options_to_text_queries() {
+        let regex = SearchOptions::default();
+        assert!(matches(&regex, "get.*name", "GetDisplayName()"));
+        let case_sensitive = SearchOptions { case_sensitive: true, ..regex.clone() };
+        assert!(!matches(&case_sensitive, "get", "GetName()"));
+
+        let literal = SearchOptions { mode: SearchMode::Literal, ..regex.clone() };
+        assert!(matches(&literal, "a.b(", "x = a.b(c)"));
+        assert!(!matches(&literal, "a.b(", "x = aXb(c)"));
+
+        let whole_word = SearchOptions { whole_word: true, ..literal };
+        assert!(matches(&whole_word, "Player", "Player p;"));
+        assert!(!matches(&whole_word, "Player", "SCR_PlayerController p;"));
+        assert!(!matches(&SearchOptions { whole_word: true, ..regex }, "a|b", "ab"));
+    }
+
+    #[test]
+    fn parses_searched_extensions() {
+        let options = SearchOptions {
+            files: SearchedFiles::Extensions,
+            extensions: ".c,conf  layout".to_string(),
+            ..SearchOptions::default()
+        };
+        let selection = options.file_selection().unwrap();
+        assert_eq!(
+            selection,
+            FileSelection::Extensions(vec!["c".into(), "conf".into(), "layout".into()])
+        );
+
+        let none = SearchOptions { extensions: " , ".to_string(), ..options };
+        assert!(matches!(none.compile("foo"), Err(SearchQueryError::NoExtensions)));
+    }
+
+    #[test]
+    fn compiles_binary_queries() {
+        let binary = SearchOptions { mode: SearchMode::Binary, ..SearchOptions::default() };
+        let Ok(SearchQuery::Bytes(searcher)) = binary.compile("50 41 43 31") else {
+            panic!("expected a byte search");
+        };
+        assert_eq!(searcher.needle(), b"PAC1");
+
+        assert!(matches!(binary.compile("  "), Err(SearchQueryError::Empty)));
+        assert!(matches!(
+            SearchOptions::default().compile("(unclosed"),
+            Err(SearchQueryError::InvalidRegex(_))
+        ));
+    }
+
+    #[test]
+    fn history_keeps_recent_distinct_searches() {
+        let search = |query: &str, mode| PastSearch {
+            query: query.to_string(),
+            options: SearchOptions { mode, ..SearchOptions::default() },
+        };
+        let mut history = SearchHistory::default();
+        history.record(search("a", SearchMode::Regex));
+        history.record(search("b", SearchMode::Regex));
+        history.record(search("a", SearchMode::Literal));
+        history.record(search("a", SearchMode::Regex));
+        let queries: Vec<_> =
+            history.iter().map(|past| (&*past.query, past.options.mode)).collect();
+        assert_eq!(
+            queries,
+            [("a", SearchMode::Regex), ("a", SearchMode::Literal), ("b", SearchMode::Regex)]
+        );
+
+        for i in 0..MAX_HISTORY {
+            history.record(search(&i.to_string(), SearchMode::Regex));
+        }
+        assert_eq!(history.iter().count(), MAX_HISTORY);
+        assert_eq!(history.iter().next().unwrap().query, (MAX_HISTORY - 1).to_string());
+    }
+
+    #[test]
+    fn describes_past_search_options() {
+        let past = PastSearch {
+            query: "x".to_string(),
+            options: SearchOptions {
+                mode: SearchMode::Literal,
+                case_sensitive: true,
+                files: SearchedFiles::All,
+                max_results: 10,
+                ..SearchOptions::default()
+            },
+        };
+        assert_eq!(past.describe_options(), "Literal, match case, all files, up to 10 results");
+    }
+}
@@ -1 +1,29 @@
+//! Preferences which are read away from the UI thread.
 
+use std::sync::RwLock;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use crate::palette::Palette;
+
+static PRETTY_PRINT_CONFIGS: AtomicBool = AtomicBool::new(true);
+static PALETTE: RwLock<Palette> = RwLock::new(Palette::DEFAULT);
+
+/// Whether minified text configs are reformatted before they're shown or diffed.
+pub fn pretty_print_configs() -> bool {
+    PRETTY_PRINT_CONFIGS.load(Ordering::Relaxed)
+}
+
+pub fn set_pretty_print_configs(enabled: bool) {
+    PRETTY_PRINT_CONFIGS.store(enabled, Ordering::Relaxed);
+}
+
+/// The colors diffs and search results are drawn in, including diffs built in the
+/// background.
+pub fn palette() -> Palette {
+    *PALETTE.read().unwrap()
+}
+
+pub fn set_palette(palette: Palette) {
+    *PALETTE.write().unwrap() = palette;
+}
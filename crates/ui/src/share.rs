@@ -0,0 +1,206 @@
+//! Share links for the browser build, which open the same view of archives hosted over
+//! HTTP.
+//!
+//! A share link is the page's URL with a hash such as
+//! `#archive=https://example.com/data.pak&file=/scripts/Game/foo.c&line=120`. Opening it
+//! fetches each archive in order, loads them as the workspace, then opens the file at the
+//! line. Only the archive URLs travel in the link, so it can't show anything but what's
+//! hosted.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(target_arch = "wasm32")]
+use tracing::warn;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ShareLinkError {
+    #[error("link names no archives to load")]
+    NoArchives,
+    #[error("\"{0}\" is not a line number")]
+    InvalidLine(String),
+    #[error("\"{0}\" is not percent-encoded UTF-8")]
+    InvalidEscape(String),
+}
+
+/// Archives to load from URLs, and optionally a file in them to open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareLink {
+    /// URLs of the archives, in the order they're loaded.
+    pub archives: Vec<String>,
+    /// Path inside the overlay, starting with `/`.
+    pub file: Option<String>,
+    /// 1-based line number in `file`.
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for ShareLink {
+    /// Writes the link as a URL hash, including the leading `#`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = '#';
+        let mut pair = |f: &mut fmt::Formatter<'_>, key: &str, value: &str| {
+            let result = write!(f, "{separator}{key}={}", encode_component(value));
+            separator = '&';
+            result
+        };
+        for archive in &self.archives {
+            pair(f, "archive", archive)?;
+        }
+        if let Some(file) = &self.file {
+            pair(f, "file", file)?;
+            if let Some(line) = self.line {
+                pair(f, "line", &line.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ShareLink {
+    type Err = ShareLinkError;
+
+    /// Reads a URL hash, with or without its leading `#`. Unknown keys are ignored.
+    fn from_str(hash: &str) -> Result<Self, Self::Err> {
+        let mut link = ShareLink { archives: Vec::new(), file: None, line: None };
+        let hash = hash.trim().trim_start_matches('#');
+        for pair in hash.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = decode_component(value)?;
+            match key {
+                "archive" => link.archives.push(value),
+                "file" => link.file = Some(format!("/{}", value.trim_start_matches('/'))),
+                "line" => {
+                    link.line = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|line| *line > 0)
+                            .ok_or(ShareLinkError::InvalidLine(value))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        if link.archives.is_empty() {
+            return Err(ShareLinkError::NoArchives);
+        }
+        Ok(link)
+    }
+}
+
+/// Percent-encodes everything but unreserved characters and the separators of URLs and
+/// paths, so the hash stays readable.
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn decode_component(value: &str) -> Result<String, ShareLinkError> {
+    let invalid = || ShareLinkError::InvalidEscape(value.to_string());
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).ok_or_else(invalid)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(inline_js = r#"
+export function pageHash() {
+    return window.location.hash;
+}
+
+export function pageUrl() {
+    return window.location.href.split("#")[0];
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(js_name = pageHash)]
+    fn page_hash() -> String;
+
+    #[wasm_bindgen(js_name = pageUrl)]
+    fn page_url() -> String;
+}
+
+/// The share link the page was opened with, if any.
+#[cfg(target_arch = "wasm32")]
+pub fn from_page() -> Option<ShareLink> {
+    let hash = page_hash();
+    if hash.is_empty() {
+        return None;
+    }
+    hash.parse().map_err(|e| warn!(%e, hash, "ignoring invalid share link")).ok()
+}
+
+/// This page's URL, opening `link`.
+#[cfg(target_arch = "wasm32")]
+pub fn link_url(link: &ShareLink) -> String {
+    format!("{}{link}", page_url())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_links() {
+        let link = ShareLink {
+            archives: vec![
+                "https://example.com/builds/1.2/data.pak".to_string(),
+                "https://example.com/mods/a&b=c.pak".to_string(),
+            ],
+            file: Some("/scripts/Game/foo bar.c".to_string()),
+            line: Some(120),
+        };
+        let hash = link.to_string();
+        assert_eq!(
+            hash,
+            "#archive=https://example.com/builds/1.2/data.pak\
+             &archive=https://example.com/mods/a%26b%3Dc.pak\
+             &file=/scripts/Game/foo%20bar.c&line=120"
+        );
+        assert_eq!(hash.parse(), Ok(link));
+
+        let no_file: ShareLink = "archive=data.pak&file=Configs/a.conf&zoom=2".parse().unwrap();
+        assert_eq!(no_file.archives, ["data.pak"]);
+        assert_eq!(no_file.file.as_deref(), Some("/Configs/a.conf"));
+        assert_eq!(no_file.line, None);
+    }
+
+    #[test]
+    fn rejects_malformed_links() {
+        assert_eq!("#file=/a.c".parse::<ShareLink>(), Err(ShareLinkError::NoArchives));
+        assert_eq!(
+            "#archive=a.pak&line=0".parse::<ShareLink>(),
+            Err(ShareLinkError::InvalidLine("0".to_string()))
+        );
+        assert!(matches!(
+            "#archive=a%2.pak".parse::<ShareLink>(),
+            Err(ShareLinkError::InvalidEscape(_))
+        ));
+        assert!(matches!(
+            "#archive=%FF.pak".parse::<ShareLink>(),
+            Err(ShareLinkError::InvalidEscape(_))
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+//! Stopping background work cleanly when the app closes.
+//!
+//! Once [`begin`] is called, long-running tasks (archive loads, searches, analyses) see
+//! [`requested`] and stop early. Tasks writing files the user asked for hold a
+//! [`WriteGuard`] instead, and closing the window is held off until they finish.
+
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static PENDING_WRITES: Mutex<usize> = Mutex::new(0);
+static WRITES_FINISHED: Condvar = Condvar::new();
+
+/// Asks background tasks to stop.
+pub fn begin() {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Whether the app is shutting down and background work should stop.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+/// Marks a file write in progress until dropped.
+#[must_use]
+pub struct WriteGuard(());
+
+/// Registers a file write that must finish before the app exits.
+pub fn track_write() -> WriteGuard {
+    *PENDING_WRITES.lock().expect("pending writes lock poisoned") += 1;
+    WriteGuard(())
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        *PENDING_WRITES.lock().expect("pending writes lock poisoned") -= 1;
+        WRITES_FINISHED.notify_all();
+    }
+}
+
+/// Number of file writes still in progress.
+pub fn pending_writes() -> usize {
+    *PENDING_WRITES.lock().expect("pending writes lock poisoned")
+}
+
+/// Blocks until every tracked write finishes or `timeout` passes. Returns whether all
+/// writes finished.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn wait_for_writes(timeout: Duration) -> bool {
+    let pending = PENDING_WRITES.lock().expect("pending writes lock poisoned");
+    let (pending, _) = WRITES_FINISHED
+        .wait_timeout_while(pending, timeout, |pending| *pending > 0)
+        .expect("pending writes lock poisoned");
+    *pending == 0
+}
@@ -0,0 +1,128 @@
+//! Picking how the editor shows a file from its detected type.
+
+use std::collections::BTreeSet;
+
+use egui_code_editor::Syntax;
+use enfusion_pak::entry_type::EntryType;
+use enfusion_pak::entry_type::detect_type;
+
+use crate::settings;
+
+/// Highlighting for the file at `path`, based on the type registry.
+pub fn syntax_for(path: &str) -> Syntax {
+    match detect_type(path, &[]) {
+        EntryType::Script => enforce_script(),
+        // Reforger's `.conf` files are in the same text format as its prefabs and layouts,
+        // not the Arma and DayZ config syntax of the other config types
+        EntryType::Config if has_extension(path, "conf") => enfusion_text(),
+        // Decompiled config.bin files are shown as config.cpp
+        EntryType::Config | EntryType::RapifiedConfig => config(),
+        EntryType::Layout
+        | EntryType::Prefab
+        | EntryType::World
+        | EntryType::Meta
+        | EntryType::Material
+        | EntryType::AnimationGraph => enfusion_text(),
+        _ => Syntax::new("text"),
+    }
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, ext)| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Whether `text`, the contents of the file at `path`, should be shown pretty-printed: it's
+/// a minified text config and pretty-printing is turned on.
+pub fn wants_pretty_print(path: &str, text: &str) -> bool {
+    is_text_config(path)
+        && settings::pretty_print_configs()
+        && cfg_parser::pretty::is_minified(text)
+}
+
+/// Whether the file at `path` is an Enforce script.
+pub fn is_script(path: &str) -> bool {
+    matches!(detect_type(path, &[]), EntryType::Script)
+}
+
+/// Whether the file at `path` is written in Enfusion's text config syntax.
+pub fn is_text_config(path: &str) -> bool {
+    matches!(
+        detect_type(path, &[]),
+        EntryType::Config
+            | EntryType::Layout
+            | EntryType::Prefab
+            | EntryType::World
+            | EntryType::Meta
+            | EntryType::Material
+            | EntryType::AnimationGraph
+    )
+}
+
+const ENFORCE_KEYWORDS: &[&str] = &[
+    "autoptr",
+    "break",
+    "case",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "else",
+    "enum",
+    "event",
+    "extends",
+    "external",
+    "false",
+    "for",
+    "foreach",
+    "if",
+    "inout",
+    "modded",
+    "native",
+    "new",
+    "notnull",
+    "null",
+    "out",
+    "override",
+    "private",
+    "proto",
+    "protected",
+    "ref",
+    "return",
+    "sealed",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "true",
+    "typedef",
+    "volatile",
+    "while",
+];
+
+const ENFORCE_TYPES: &[&str] = &[
+    "array", "bool", "Class", "float", "func", "int", "Managed", "map", "set", "string",
+    "typename", "vector", "void",
+];
+
+fn enforce_script() -> Syntax {
+    Syntax::new("enforce")
+        .with_comment("//")
+        .with_comment_multiline(["/*", "*/"])
+        .with_keywords(ENFORCE_KEYWORDS.iter().copied().collect::<BTreeSet<_>>())
+        .with_types(ENFORCE_TYPES.iter().copied().collect::<BTreeSet<_>>())
+}
+
+fn config() -> Syntax {
+    Syntax::new("config")
+        .with_comment("//")
+        .with_comment_multiline(["/*", "*/"])
+        .with_keywords(["class", "delete", "enum"])
+        .with_special(["#define", "#include", "#ifdef", "#ifndef", "#endif", "#else"])
+}
+
+/// Enfusion's text resource format shared by layouts, prefabs, worlds and most other
+/// editor-authored files.
+fn enfusion_text() -> Syntax {
+    Syntax::new("enfusion").with_comment("//").with_comment_multiline(["/*", "*/"])
+}
@@ -1,24 +1,42 @@
-use std::collections::BTreeMap;
-use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::collections::VecDeque;
-use std::ops::Range;
+use std::ops::ControlFlow;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 use std::sync::mpsc::Receiver;
+use std::time::Duration;
 
 use egui_inbox::UiInboxSender;
+#[cfg(target_arch = "wasm32")]
+use enfusion_pak::archive_format::ArchiveFormat;
+use enfusion_pak::entry_type::detect_type;
 use enfusion_pak::error::PakError;
+#[cfg(not(target_arch = "wasm32"))]
+use enfusion_pak::ignore_vfs::IgnoreRules;
+#[cfg(not(target_arch = "wasm32"))]
+use enfusion_pak::ignore_vfs::IgnoringVfs;
+use enfusion_pak::intern::Interner;
+use enfusion_pak::pak_vfs::PakMetaIndex;
 use enfusion_pak::pak_vfs::PakVfs;
 use enfusion_pak::vfs::MemoryFS;
 use enfusion_pak::vfs::OverlayFS;
+#[cfg(not(target_arch = "wasm32"))]
+use enfusion_pak::vfs::PhysicalFS;
 use enfusion_pak::vfs::VfsPath;
 use enfusion_pak::vfs::async_vfs::AsyncMemoryFS;
 use enfusion_pak::vfs::async_vfs::AsyncOverlayFS;
+#[cfg(not(target_arch = "wasm32"))]
+use enfusion_pak::vfs::async_vfs::AsyncPhysicalFS;
 use enfusion_pak::vfs::async_vfs::AsyncVfsPath;
+use enfusion_search::CancellationToken;
+use enfusion_search::SearchScope;
+use enfusion_search::SearchStats;
+use futures::AsyncReadExt;
 use futures::StreamExt;
 use itertools::Itertools;
 use tracing::debug;
@@ -26,10 +44,31 @@ use tracing::error;
 use tracing::info;
 #[cfg(not(target_arch = "wasm32"))]
 use tracing::warn;
+use web_time::Instant;
 
+use crate::analysis::Analysis;
+use crate::analysis::Report;
+use crate::analysis::Workspace;
 use crate::app::KnownPaths;
 use crate::app::TreeNode;
+use crate::audio::AudioClip;
+use crate::audio::AudioUpdate;
+use crate::audio::MAX_SAMPLES;
+use crate::audio::StreamDecoder;
+use crate::audio::WaveformBuilder;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::dialogs::DialogKind;
 use crate::diff;
+use crate::fuzzy::fuzzy_score;
+use crate::history::ValueHistory;
+use crate::permalink::PakSetHash;
+use crate::profile;
+use crate::search::SearchQuery;
+#[cfg(target_arch = "wasm32")]
+use crate::share::ShareLink;
+use crate::shutdown;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::update::Release;
 // use crate::pak_wrapper::parse_pak_file;
 use crate::vfs_ext::VfsExt;
 
@@ -41,7 +80,55 @@ pub struct LoadedFiles {
     pub overlay_fs: VfsPath,
     pub async_overlay_fs: AsyncVfsPath,
     pub known_paths: KnownPaths,
-    pub file_path_set: HashSet<String>,
+    pub file_path_set: HashSet<Arc<str>>,
+    /// The overlay's layers, highest priority first.
+    pub layers: Vec<ArchiveLayer>,
+}
+
+/// One archive mounted in the overlay filesystem.
+#[derive(Debug, Clone)]
+pub struct ArchiveLayer {
+    pub name: String,
+    pub root: VfsPath,
+    /// The same archive as `root`, read asynchronously, e.g. for searches.
+    pub async_root: AsyncVfsPath,
+    /// The archive's FILE chunk metadata, if it's a pak.
+    pub pak_meta: Option<PakMetaIndex>,
+}
+
+impl ArchiveLayer {
+    /// Size of the file at `path` in this archive, or `None` if the archive doesn't
+    /// contain it.
+    pub fn file_size(&self, path: &str) -> Option<u64> {
+        let file = self.root.join(path).ok()?;
+        if !file.is_file().unwrap_or_default() {
+            return None;
+        }
+
+        Some(file.metadata().map(|meta| meta.len).unwrap_or_default())
+    }
+
+    /// Whether this archive has a folder at `path`.
+    pub fn has_dir(&self, path: &str) -> bool {
+        self.root.join(path).is_ok_and(|dir| dir.is_dir().unwrap_or_default())
+    }
+}
+
+/// Another build loaded next to the workspace, which editor tabs can compare their file
+/// against.
+#[derive(Debug, Clone)]
+pub struct ComparisonBuild {
+    /// Shown in the editor's version picker.
+    pub name: String,
+    pub async_overlay_fs: AsyncVfsPath,
+    pub file_path_set: Arc<HashSet<Arc<str>>>,
+}
+
+impl ComparisonBuild {
+    /// Whether the build has a file at `path`.
+    pub fn contains(&self, path: &str) -> bool {
+        self.file_path_set.contains(path)
+    }
 }
 
 #[repr(transparent)]
@@ -52,217 +139,276 @@ pub struct SearchId(pub usize);
 #[derive(Debug, Copy, Clone)]
 pub struct LineNumber(pub usize);
 
+/// Why a file's data is being read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileLoad {
+    /// To show it in an editor tab, or bring forward a tab already showing it.
+    Open,
+    /// To show it in a new editor tab even if one already shows it.
+    OpenNewTab,
+    /// To restore the contents an editor tab dropped while it wasn't being looked at.
+    Reload,
+}
+
 #[derive(Debug)]
 pub enum BackgroundTaskMessage {
     LoadedPakFiles(Result<(LoadedFiles, Vec<TreeNode>), PakError>),
-    FileDataLoaded(VfsPath, Vec<u8>),
+    FileDataLoaded(VfsPath, Vec<u8>, FileLoad),
+    /// Decoding `VfsPath` made progress. Cancelling the token stops it, and tells decodes
+    /// of the same file apart.
+    AudioDecoded(VfsPath, CancellationToken, AudioUpdate),
     SearchResult(SearchId, SearchResult),
+    /// Totals of a running search, sent every [`SEARCH_PROGRESS_INTERVAL`].
+    SearchProgress(SearchId, SearchProgress),
+    /// A search stopped, with its final totals.
+    SearchComplete(SearchId, SearchProgress, SearchEnd),
+    /// An analysis finished, with its name and report or error message.
+    AnalysisFinished(String, Result<Report, String>),
     FilesFiltered(Vec<TreeNode>),
     RequestOpenFile(VfsPath),
-    FilesDiffed(Result<Vec<diff::DiffResult>, PakError>),
+    /// Differences between two builds, and the modified build's pak set for permalinks.
+    FilesDiffed(Result<(PakSetHash, Vec<diff::DiffResult>), PakError>),
+    ComparisonBuildLoaded(Result<ComparisonBuild, PakError>),
+    /// Contents of a value rules file the user picked.
+    ValueRulesLoaded(String),
+    /// A config value read from every loaded build, to chart in a new tab.
+    ValueTracked(ValueHistory),
+    /// The user picked files from this directory in a file dialog.
+    #[cfg(not(target_arch = "wasm32"))]
+    DialogDirectoryUsed(DialogKind, PathBuf),
+    /// The newest release, if it's newer than this build, or why checking failed.
+    #[cfg(not(target_arch = "wasm32"))]
+    UpdateChecked(Result<Option<Release>, String>),
+    /// Where a release's binary was downloaded to, or why downloading it failed.
+    #[cfg(not(target_arch = "wasm32"))]
+    UpdateStaged(Release, Result<PathBuf, String>),
+    /// Names of the files remembered from the previous session.
+    #[cfg(target_arch = "wasm32")]
+    LastWorkspaceFound(Vec<String>),
+    /// The archives of a share link were downloaded, and are ready to load.
+    #[cfg(target_arch = "wasm32")]
+    SharedArchivesFetched(ShareLink, Vec<FileReference>),
 }
 
 #[repr(transparent)]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
-pub struct FullPath(pub String);
+pub struct FullPath(pub Arc<str>);
 
 #[repr(transparent)]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
-pub struct FileName(pub String);
+pub struct FileName(pub Arc<str>);
 
 pub enum BackgroundTask {
     /// Requests the background thread to begin parsing PAK files.
     LoadPakFiles(Vec<FileReference>),
-    PerformSearch(SearchId, AsyncVfsPath, String),
-    LoadFileData(VfsPath, AsyncVfsPath),
+    PerformSearch {
+        id: SearchId,
+        scope: SearchScope,
+        query: SearchQuery,
+        /// Matches to report before the search stops.
+        max_results: usize,
+    },
+    /// Stops the running search, if there is one.
+    CancelSearch,
+    RunAnalysis {
+        analysis: Arc<dyn Analysis>,
+        workspace: Workspace,
+        /// Contents of the file picked for [`Analysis::input`], if it asks for one.
+        input: Option<Vec<u8>>,
+    },
+    LoadFileData(VfsPath, AsyncVfsPath, FileLoad),
+    /// Decodes an audio file for a preview tab, a chunk at a time.
+    LoadAudio(VfsPath, AsyncVfsPath),
     FilterPaths {
         known_paths: Arc<KnownPaths>,
-        file_path_set: Arc<HashSet<String>>,
+        file_path_set: Arc<HashSet<Arc<str>>>,
         root: VfsPath,
-        query: String,
+        filter: PathFilter,
     },
     DiffBuilds {
         base: Vec<FileReference>,
         modified: Vec<FileReference>,
     },
+    /// Loads archives as a [`ComparisonBuild`] instead of replacing the workspace.
+    LoadComparisonBuild(Vec<FileReference>),
 }
 
-#[derive(Debug, Clone)]
-pub struct SearchResult {
-    pub file: AsyncVfsPath,
-    pub matches: Vec<(LineNumber, String)>,
+/// Criteria used to narrow down the file tree.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    /// Case-insensitive substring. Matched against the full path if it contains a `/`,
+    /// otherwise against the file name.
+    pub query: String,
+    /// If non-empty, only files with one of these extensions are included.
+    pub extensions: &'static [&'static str],
 }
 
-pub async fn perform_search(
-    search_id: SearchId,
-    start_path: AsyncVfsPath,
-    query: String,
-    search_stop: Arc<AtomicBool>,
-    results_sender: egui_inbox::UiInboxSender<BackgroundTaskMessage>,
-) {
-    let mut file_queue = VecDeque::new();
-    let regex = regex::RegexBuilder::new(&query)
-        .case_insensitive(true)
-        .build()
-        .expect("failed to compile regex");
-    file_queue.push_back(start_path);
-    while let Some(next) = file_queue.pop_front() {
-        // Check to see if we should stop searching before doing too much work.
-        // We'll check this at multiple points.
-        if search_stop.load(Ordering::Relaxed) {
-            break;
-        }
+impl PathFilter {
+    /// Returns how well the entry matches this filter, or `None` if it's excluded.
+    fn score(&self, full_path: &str, file_name: &str) -> Option<i64> {
+        if !self.extensions.is_empty() {
+            let (_, ext) = file_name.rsplit_once('.')?;
 
-        if next.is_dir().await.ok().unwrap_or_default() {
-            let mut stream = next.read_dir().await.expect("failed to read dir");
-            while let Some(child) = stream.next().await {
-                if child.is_file().await.ok().unwrap_or_default() {
-                    // If this file doesn't have an extension that we believe to be a text
-                    // file, let's ignore it
-                    if let Some(
-                        "bin" | "c" | "et" | "conf" | "layout" | "agr" | "asi" | "ast" | "asy"
-                        | "aw" | "emat" | "hpp" | "json" | "txt" | "xml",
-                    ) = child.extension().as_deref()
-                    {
-                        file_queue.push_back(child);
-                    }
-                } else {
-                    file_queue.push_back(child);
-                }
+            if !self.extensions.iter().any(|candidate| candidate.eq_ignore_ascii_case(ext)) {
+                return None;
             }
-
-            continue;
         }
 
-        // Handle files
-        let mut data = Vec::with_capacity(next.metadata().await.expect("no metadata").len as usize);
-        if let Err(e) =
-            futures::io::copy(&mut next.open_file().await.expect("could not open"), &mut data).await
-        {
-            error!(file = next.as_str(), ?e, "failed to read file data");
-            continue;
-        }
+        let haystack = if self.query.contains('/') { full_path } else { file_name };
+        fuzzy_score(&self.query, haystack)
+    }
+}
 
-        // For rapified config.bin files, decompile to text before searching
-        let file_data = if cfg_parser::is_rapified(&data) {
-            match cfg_parser::RapFile::parse(&data) {
-                Ok(rap) => cfg_parser::decompile(&rap),
-                Err(_) => continue,
-            }
-        } else {
-            let Some(text) = String::from_utf8(data).ok() else {
-                continue;
-            };
-            text
-        };
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub file: AsyncVfsPath,
+    pub matches: SearchMatches,
+}
 
-        let matches = regex.find_iter(&file_data);
-        let match_locations: Vec<Range<usize>> = matches.map(|m| m.range()).collect();
-        if match_locations.is_empty() {
-            continue;
+#[derive(Debug, Clone)]
+pub enum SearchMatches {
+    /// The first line number of each match, and its lines with their context.
+    Lines(Vec<(LineNumber, String)>),
+    /// Byte offsets of a binary search's matches.
+    Offsets(Vec<u64>),
+}
+
+impl SearchMatches {
+    pub fn len(&self) -> usize {
+        match self {
+            SearchMatches::Lines(lines) => lines.len(),
+            SearchMatches::Offsets(offsets) => offsets.len(),
         }
+    }
 
-        let mut linebreak_locations: BTreeMap<usize, (usize, bool)> = BTreeMap::new();
-        let mut linebreaks_for_match: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
-        let mut match_idx = 0usize;
-        for (idx, c) in file_data.as_bytes().iter().enumerate() {
-            if *c == b'\n' {
-                let line_num = if linebreak_locations.is_empty() {
-                    1usize
-                } else {
-                    linebreak_locations.last_entry().unwrap().get().0
-                };
-                linebreak_locations.insert(idx, (line_num + 1, false));
-
-                // Check if can lock any linebreaks that are AFTER the previous match
-                let prev_match_idx = match_idx.saturating_sub(1);
-                let last_start = match_locations[prev_match_idx].start;
-                if idx > last_start {
-                    for (idx, (_line_num, locked)) in
-                        linebreak_locations.range_mut(last_start..=idx).take(2)
-                    {
-                        *locked = true;
-                        linebreaks_for_match.entry(prev_match_idx).or_default().insert(*idx);
-                    }
-                }
+    fn truncate(&mut self, len: usize) {
+        match self {
+            SearchMatches::Lines(lines) => lines.truncate(len),
+            SearchMatches::Offsets(offsets) => offsets.truncate(len),
+        }
+    }
+}
 
-                if match_idx >= match_locations.len() {
-                    match_idx += 1;
+/// How often a running search reports its progress.
+pub const SEARCH_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
-                    // If `match_idx` is 1 greater than the number of locations, we want to
-                    // stop matching
-                    if match_idx == match_locations.len() + 1 {
-                        break;
-                    }
-                } else if idx > match_locations[match_idx].start {
-                    // Start comparing to the next match. At this point we also
-                    // want to prune the tree of non-locked linebreaks
-
-                    // Lock in the two positions closest to this
-                    for (idx, (_line_num, locked)) in
-                        linebreak_locations.range_mut(..idx).rev().take(2)
-                    {
-                        *locked = true;
-                        linebreaks_for_match.entry(match_idx).or_default().insert(*idx);
-                    }
+/// Running totals of a search.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SearchProgress {
+    pub files_scanned: usize,
+    /// Matches reported so far, as counted by [`SearchMatches::len`].
+    pub matches: usize,
+    pub elapsed: Duration,
+}
 
-                    linebreak_locations.retain(|_k, (_line_num, locked)| {
-                        // Keep any locked linebreaks and discard all others
-                        *locked
-                    });
+/// Why a search stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchEnd {
+    /// Every file in scope was searched.
+    Finished,
+    /// The search reached its maximum number of results.
+    LimitReached,
+    /// The user stopped the search, started a new one, or closed the app.
+    Cancelled,
+}
 
-                    // We will go 1-past the number of matches so that we can get
-                    // the next 2 linebreaks after the final match
-                    match_idx += 1;
-                }
-            }
-        }
+/// Whether a search should stop, either because a newer search replaced it or the app is
+/// closing.
+fn search_cancelled(search_stop: &CancellationToken) -> bool {
+    search_stop.is_cancelled() || shutdown::requested()
+}
 
-        let match_with_context = match_locations
-            .iter()
-            .enumerate()
-            .map(|(idx, m)| {
-                // Grab the linebreaks for this match.
-                // If there are no items, we will grab the whole file context since it
-                // probably implies there are no linebreaks
-                let (context_start, context_end) =
-                    if let Some(linebreak_ranges) = linebreaks_for_match.get(&idx) {
-                        let first = linebreak_ranges
-                            .first()
-                            .expect("BUG: linebreak ranges should always have items");
-                        let last = linebreak_ranges
-                            .last()
-                            .expect("BUG: linebreak ranges should always have items");
-
-                        if *last < m.end { (*first, file_data.len()) } else { (*first, *last) }
-                    } else {
-                        (0, file_data.len())
-                    };
+pub async fn perform_search(
+    search_id: SearchId,
+    scope: SearchScope,
+    query: SearchQuery,
+    max_results: usize,
+    search_stop: CancellationToken,
+    results_sender: egui_inbox::UiInboxSender<BackgroundTaskMessage>,
+) {
+    // Shared by the callbacks. Atomics rather than `Cell`s, as the search future must be `Send`
+    let found = AtomicUsize::new(0);
+    let limit_reached = AtomicBool::new(false);
+    let started = Instant::now();
+    let progress = |stats: &SearchStats| SearchProgress {
+        files_scanned: stats.files_scanned,
+        matches: found.load(Ordering::Relaxed),
+        elapsed: started.elapsed(),
+    };
 
-                // Grab the line number for the first match
-                let context_line_start = if context_start == 0 {
-                    1
-                } else {
-                    linebreak_locations.get(&context_start).unwrap().0
-                };
+    let mut last_progress = started;
+    let on_scanned = |stats: &SearchStats| {
+        if last_progress.elapsed() >= SEARCH_PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            let _ = results_sender
+                .send(BackgroundTaskMessage::SearchProgress(search_id, progress(stats)));
+        }
+    };
+    let send = |file, mut matches: SearchMatches| {
+        let remaining = max_results.max(1) - found.load(Ordering::Relaxed);
+        let limit_reached_now = matches.len() >= remaining;
+        if limit_reached_now {
+            matches.truncate(remaining);
+            limit_reached.store(true, Ordering::Relaxed);
+        }
+        found.fetch_add(matches.len(), Ordering::Relaxed);
 
-                (LineNumber(context_line_start), file_data[context_start..context_end].to_owned())
-            })
-            .collect();
+        let result = SearchResult { file, matches };
+        match results_sender.send(BackgroundTaskMessage::SearchResult(search_id, result)) {
+            Ok(()) if !limit_reached_now => ControlFlow::Continue(()),
+            Ok(()) => ControlFlow::Break(()),
+            // The user probably started a new search
+            Err(_) => ControlFlow::Break(()),
+        }
+    };
 
-        if search_stop.load(Ordering::Relaxed) {
-            break;
+    let search_started = profile::start();
+    let cancelled = || search_cancelled(&search_stop);
+    let stats = match query {
+        SearchQuery::Lines(searcher) => {
+            searcher
+                .search_scope(scope, cancelled, on_scanned, |file, matches| {
+                    let matches = matches
+                        .iter()
+                        .map(|found| {
+                            let context = found.lines().map(|(_, line)| line).join("\n");
+                            (LineNumber(found.first_line()), context)
+                        })
+                        .collect();
+                    send(file, SearchMatches::Lines(matches))
+                })
+                .await
         }
-        if results_sender
-            .send(BackgroundTaskMessage::SearchResult(
-                search_id,
-                SearchResult { file: next, matches: match_with_context },
-            ))
-            .is_err()
-        {
-            // The user probably started a new search
-            break;
+        SearchQuery::Bytes(searcher) => {
+            searcher
+                .search_scope(scope, cancelled, on_scanned, |file, offsets| {
+                    send(file, SearchMatches::Offsets(offsets))
+                })
+                .await
         }
+    };
+
+    let end = if limit_reached.load(Ordering::Relaxed) {
+        SearchEnd::LimitReached
+    } else if search_cancelled(&search_stop) {
+        SearchEnd::Cancelled
+    } else {
+        SearchEnd::Finished
+    };
+    let _ = results_sender.send(BackgroundTaskMessage::SearchComplete(
+        search_id,
+        progress(&stats),
+        end,
+    ));
+
+    if let Some(search_started) = search_started {
+        profile::record_search(profile::SearchSample {
+            files_scanned: stats.files_scanned,
+            bytes_scanned: stats.bytes_scanned,
+            files_matched: stats.files_matched,
+            duration_ms: profile::millis(search_started.elapsed()),
+            cancelled: end == SearchEnd::Cancelled,
+        });
     }
 }
 
@@ -293,7 +439,7 @@ pub fn process_background_requests(
     inbox: UiInboxSender<BackgroundTaskMessage>,
     task_queue: &Receiver<BackgroundTask>,
 ) {
-    let mut search_stop = Arc::new(AtomicBool::new(false));
+    let mut search_stop = CancellationToken::new();
     #[cfg(not(target_arch = "wasm32"))]
     let get_message = || task_queue.recv();
 
@@ -301,6 +447,10 @@ pub fn process_background_requests(
     let get_message = || task_queue.try_recv();
 
     while let Ok(task) = get_message() {
+        if shutdown::requested() {
+            break;
+        }
+
         match task {
             BackgroundTask::LoadPakFiles(handles) => {
                 let inbox = inbox.clone();
@@ -312,12 +462,10 @@ pub fn process_background_requests(
                         .expect("failed to send completion");
                 });
             }
-            BackgroundTask::PerformSearch(search_id, start_path, query) => {
+            BackgroundTask::PerformSearch { id, scope, query, max_results } => {
                 // Notify any pending searches that they should stop
-                search_stop.store(true, std::sync::atomic::Ordering::Relaxed);
-                drop(search_stop);
-
-                search_stop = Arc::new(AtomicBool::new(false));
+                search_stop.cancel();
+                search_stop = CancellationToken::new();
 
                 // Start a new thread for search. This will allow us to easily drop searches when
                 // the user performs a new search
@@ -325,16 +473,29 @@ pub fn process_background_requests(
                 let thread_stopper = search_stop.clone();
                 #[cfg(not(target_arch = "wasm32"))]
                 execute(async move {
-                    perform_search(search_id, start_path, query, thread_stopper, thread_sender)
+                    perform_search(id, scope, query, max_results, thread_stopper, thread_sender)
                         .await;
                 });
                 #[cfg(target_arch = "wasm32")]
                 execute(async move {
-                    perform_search(search_id, start_path, query, thread_stopper, thread_sender)
+                    perform_search(id, scope, query, max_results, thread_stopper, thread_sender)
                         .await;
                 });
             }
-            BackgroundTask::LoadFileData(vfs_path, overlay_fs) => {
+            BackgroundTask::CancelSearch => search_stop.cancel(),
+            BackgroundTask::RunAnalysis { analysis, workspace, input } => {
+                let inbox = inbox.clone();
+                execute(async move {
+                    let result =
+                        analysis.run(&workspace, input.as_deref()).await.map_err(|e| e.to_string());
+                    info!(analysis = analysis.name(), ok = result.is_ok(), "analysis finished");
+                    let _ = inbox.send(BackgroundTaskMessage::AnalysisFinished(
+                        analysis.name().to_string(),
+                        result,
+                    ));
+                });
+            }
+            BackgroundTask::LoadFileData(vfs_path, overlay_fs, load) => {
                 debug!("Got a LoadFileData task");
                 let sender = inbox.clone();
                 execute(async move {
@@ -343,16 +504,36 @@ pub fn process_background_requests(
                         .expect("could not map sync path to async path");
 
                     if let Some(file_data) = read_file_data(async_vfs_path).await {
-                        let _ =
-                            sender.send(BackgroundTaskMessage::FileDataLoaded(vfs_path, file_data));
+                        let _ = sender
+                            .send(BackgroundTaskMessage::FileDataLoaded(vfs_path, file_data, load));
+                    }
+                });
+            }
+            BackgroundTask::LoadAudio(vfs_path, overlay_fs) => {
+                let sender = inbox.clone();
+                execute(async move {
+                    let async_vfs_path = overlay_fs
+                        .join(vfs_path.as_str())
+                        .expect("could not map sync path to async path");
+                    let cancel = CancellationToken::new();
+                    let send = |update| {
+                        let _ = sender.send(BackgroundTaskMessage::AudioDecoded(
+                            vfs_path.clone(),
+                            cancel.clone(),
+                            update,
+                        ));
+                    };
+                    if let Err(e) = decode_audio(async_vfs_path, &cancel, &send).await {
+                        error!(%e, path = vfs_path.as_str(), "failed to decode audio");
+                        send(AudioUpdate::Failed(e));
                     }
                 });
             }
-            BackgroundTask::FilterPaths { known_paths, file_path_set, root, query } => {
+            BackgroundTask::FilterPaths { known_paths, file_path_set, root, filter } => {
                 let inbox = inbox.clone();
                 execute(async move {
                     let new_tree =
-                        build_file_tree(&root, &known_paths, &file_path_set, Some(query));
+                        build_file_tree(&root, &known_paths, &file_path_set, Some(filter));
 
                     let _ = inbox.send(BackgroundTaskMessage::FilesFiltered(new_tree));
                 });
@@ -376,16 +557,123 @@ pub fn process_background_requests(
                         }
                     };
 
+                    let pak_set =
+                        PakSetHash::new(&modified_loaded.layers, modified_loaded.known_paths.len());
                     let modified = diff::diff_builds(base_loaded, modified_loaded).await;
 
-                    let _ = inbox.send(BackgroundTaskMessage::FilesDiffed(Ok(modified)));
+                    let _ = inbox.send(BackgroundTaskMessage::FilesDiffed(Ok((pak_set, modified))));
                 });
             }
+            BackgroundTask::LoadComparisonBuild(handles) => {
+                let inbox = inbox.clone();
+                execute(async move {
+                    let name = match handles.as_slice() {
+                        [] => return,
+                        [handle] => handle.display_name(),
+                        [first, rest @ ..] => {
+                            format!("{} and {} more", first.display_name(), rest.len())
+                        }
+                    };
+                    let build = load_pak_files_from_handles(handles).await.map(|(loaded, _)| {
+                        ComparisonBuild {
+                            name,
+                            async_overlay_fs: loaded.async_overlay_fs,
+                            file_path_set: Arc::new(loaded.file_path_set),
+                        }
+                    });
+
+                    let _ = inbox.send(BackgroundTaskMessage::ComparisonBuildLoaded(build));
+                });
+            }
+        }
+    }
+}
+
+/// Every file beneath `root`.
+pub(crate) async fn all_files(root: AsyncVfsPath) -> Vec<AsyncVfsPath> {
+    let mut files = Vec::new();
+    let mut queue = vec![root];
+    while let Some(next) = queue.pop() {
+        if !next.is_dir().await.unwrap_or_default() {
+            files.push(next);
+            continue;
+        }
+
+        let Ok(mut children) = next.read_dir().await else {
+            continue;
+        };
+        while let Some(child) = children.next().await {
+            queue.push(child);
         }
     }
+
+    files
+}
+
+/// Bytes of an audio file read at a time while it's decoded.
+const AUDIO_READ_LEN: usize = 256 * 1024;
+
+/// Reads and decodes an audio file a chunk at a time, sending its waveform as it goes.
+async fn decode_audio(
+    path: AsyncVfsPath,
+    cancel: &CancellationToken,
+    send: &impl Fn(AudioUpdate),
+) -> Result<(), String> {
+    let file_len = path.metadata().await.map_err(|e| e.to_string())?.len;
+    let mut reader = path.open_file().await.map_err(|e| e.to_string())?;
+    let mut decoder = StreamDecoder::default();
+    let mut waveform = None;
+    let mut samples = Vec::new();
+    let mut buf = vec![0; AUDIO_READ_LEN];
+    let mut read = 0;
+    let mut truncated = false;
+
+    loop {
+        if cancel.is_cancelled() || shutdown::requested() {
+            return Ok(());
+        }
+        let len = reader.read(&mut buf).await.map_err(|e| e.to_string())?;
+        if len == 0 {
+            break;
+        }
+        read += len as u64;
+        let decoded = samples.len();
+        decoder.push(&buf[..len], &mut samples).map_err(|e| e.to_string())?;
+        let Some(format) = decoder.format() else {
+            continue;
+        };
+        let waveform = waveform.get_or_insert_with(|| {
+            send(AudioUpdate::Started(format));
+            WaveformBuilder::new(format)
+        });
+
+        if samples.len() > MAX_SAMPLES {
+            samples.truncate(MAX_SAMPLES - MAX_SAMPLES % format.channels.max(1) as usize);
+            truncated = true;
+        }
+        let mut peaks = Vec::new();
+        waveform.push(&samples[decoded.min(samples.len())..], &mut peaks);
+        send(AudioUpdate::Progress(peaks, read as f32 / file_len.max(1) as f32));
+        if truncated {
+            break;
+        }
+    }
+
+    if !truncated {
+        decoder.finish().map_err(|e| e.to_string())?;
+    }
+    let (Some(format), Some(mut waveform)) = (decoder.format(), waveform) else {
+        return Err("the file has no audio".to_string());
+    };
+    let mut peaks = Vec::new();
+    waveform.finish(&mut peaks);
+    let clip = AudioClip { format, samples: samples.into() };
+    send(AudioUpdate::Finished { clip, peaks, truncated });
+    Ok(())
 }
 
 pub async fn read_file_data(path: AsyncVfsPath) -> Option<Vec<u8>> {
+    let read_started = profile::start();
     let metadata = path.metadata().await.ok()?;
     let mut reader = path.open_file().await.ok()?;
     let mut file_data = Vec::with_capacity(metadata.len as usize);
@@ -393,6 +681,13 @@ pub async fn read_file_data(path: AsyncVfsPath) -> Option<Vec<u8>> {
 
     futures::io::copy(&mut reader, &mut file_data).await.expect("failed to copy data");
 
+    if let Some(read_started) = read_started {
+        profile::record_file_read(profile::FileReadSample {
+            bytes: file_data.len(),
+            duration_ms: profile::millis(read_started.elapsed()),
+        });
+    }
+
     Some(file_data)
 }
 
@@ -400,6 +695,7 @@ async fn load_pak_files_from_handles(
     handles: Vec<FileReference>,
 ) -> Result<(LoadedFiles, Vec<TreeNode>), PakError> {
     info!(count = handles.len(), "loading archive files");
+    let load_started = profile::start();
 
     let mut parsed_paths = Vec::with_capacity(handles.len() + 1);
     parsed_paths.push(VfsPath::new(MemoryFS::new()));
@@ -408,59 +704,99 @@ async fn load_pak_files_from_handles(
     parsed_async_paths.push(AsyncVfsPath::new(AsyncMemoryFS::new()));
 
     let mut parsed_handles = Vec::with_capacity(handles.len());
+    // The FILE chunk metadata of each pak, parallel to `parsed_handles`
+    let mut parsed_metas = Vec::with_capacity(handles.len());
     for handle in handles {
+        if shutdown::requested() {
+            return Err(PakError::IoError(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "the app is shutting down",
+            )));
+        }
+
         #[cfg(target_arch = "wasm32")]
         {
             if !handle.has_supported_extension() {
                 continue;
             }
-            let name = handle.0.file_name();
-            let is_pbo = name.ends_with(".pbo");
-
-            if is_pbo {
-                match dayz_pbo::wrappers::parse_pbo_file(handle.clone()).await {
-                    Ok(vfs) => {
-                        parsed_paths.push(VfsPath::new(vfs.clone()));
-                        parsed_async_paths.push(AsyncVfsPath::new(vfs));
-                    }
-                    Err(e) => {
-                        error!(file = %name, ?e, "failed to parse PBO");
-                        continue;
-                    }
+            let name = handle.file_name();
+            let header = enfusion_pak::async_pak_vfs::AsyncReadAt::read_at(
+                &handle,
+                0..enfusion_pak::archive_format::HEADER_LEN,
+            )
+            .await
+            .map(|header| header.as_ref().to_vec())
+            .unwrap_or_default();
+            let format = ArchiveFormat::detect(&name, &header);
+
+            let parsed = match format {
+                Some(ArchiveFormat::Pbo) => dayz_pbo::wrappers::parse_pbo_file(handle.clone())
+                    .await
+                    .map(|vfs| (VfsPath::new(vfs.clone()), AsyncVfsPath::new(vfs), None))
+                    .map_err(|e| e.to_string()),
+                #[cfg(feature = "zip")]
+                Some(ArchiveFormat::Zip) => {
+                    let len = handle.0.size() as usize;
+                    enfusion_pak::zip_vfs::ZipVfs::new_async(handle.clone(), len)
+                        .await
+                        .map(|vfs| (VfsPath::new(vfs.clone()), AsyncVfsPath::new(vfs), None))
+                        .map_err(|e| e.to_string())
                 }
-            } else {
-                let cloned = handle.clone();
-                match enfusion_pak::wrappers::async_reader::parse_pak_file(
-                    cloned.0.file_name().into(),
-                    cloned,
-                )
-                .await
-                {
-                    Ok(parsed_file) => {
-                        let vfs = PakVfs::new(Arc::new(parsed_file));
-                        parsed_paths.push(VfsPath::new(vfs.clone()));
-                        parsed_async_paths.push(AsyncVfsPath::new(vfs));
-                    }
-                    Err(e) => {
-                        error!(file = %name, ?e, "failed to parse PAK");
-                        continue;
-                    }
+                _ => handle.parse_pak().await.and_then(|parsed_file| {
+                    let vfs = PakVfs::new(Arc::new(parsed_file)).map_err(|e| e.to_string())?;
+                    let meta = vfs.meta_index();
+                    Ok((VfsPath::new(vfs.clone()), AsyncVfsPath::new(vfs), Some(meta)))
+                }),
+            };
+            match parsed {
+                Ok((path, async_path, meta)) => {
+                    parsed_paths.push(path);
+                    parsed_async_paths.push(async_path);
+                    parsed_metas.push(meta);
+                }
+                Err(e) => {
+                    error!(file = %name, %e, "failed to parse archive file");
+                    continue;
                 }
             }
             parsed_handles.push(handle);
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
-            if !handle.has_supported_extension() {
+            if !handle.is_loadable() {
                 warn!(path = ?handle.0, "skipping unsupported file extension");
                 continue;
             }
             info!(path = ?handle.0, "parsing archive file");
             let cloned = handle.clone();
             match crate::pak_wrapper::parse_archive_file(cloned.0) {
+                Ok(crate::pak_wrapper::ParsedArchive::Directory(root)) => {
+                    info!(path = ?handle.0, "mounted folder");
+                    let rules = IgnoreRules::load(&root).unwrap_or_else(|e| {
+                        warn!(path = ?handle.0, %e, "failed to read ignore file");
+                        IgnoreRules::default()
+                    });
+                    let rules = Arc::new(rules);
+                    parsed_paths.push(VfsPath::new(IgnoringVfs::new(
+                        PhysicalFS::new(&root),
+                        Arc::clone(&rules),
+                    )));
+                    parsed_async_paths.push(AsyncVfsPath::new(IgnoringVfs::new(
+                        AsyncPhysicalFS::new(&root),
+                        rules,
+                    )));
+                    parsed_metas.push(None);
+                }
                 Ok(crate::pak_wrapper::ParsedArchive::Pak(pak)) => {
+                    let vfs = match PakVfs::new(pak) {
+                        Ok(vfs) => vfs,
+                        Err(e) => {
+                            error!(path = ?handle.0, %e, "failed to mount PAK");
+                            continue;
+                        }
+                    };
                     info!(path = ?handle.0, "mounted PAK");
-                    let vfs = PakVfs::new(pak);
+                    parsed_metas.push(Some(vfs.meta_index()));
                     parsed_paths.push(VfsPath::new(vfs.clone()));
                     parsed_async_paths.push(AsyncVfsPath::new(vfs));
                 }
@@ -469,6 +805,14 @@ async fn load_pak_files_from_handles(
                     let cloned = pbo_vfs.clone();
                     parsed_paths.push(VfsPath::new(pbo_vfs));
                     parsed_async_paths.push(AsyncVfsPath::new(cloned));
+                    parsed_metas.push(None);
+                }
+                #[cfg(feature = "zip")]
+                Ok(crate::pak_wrapper::ParsedArchive::Zip(zip_vfs)) => {
+                    info!(path = ?handle.0, "mounted ZIP");
+                    parsed_paths.push(VfsPath::new(zip_vfs.clone()));
+                    parsed_async_paths.push(AsyncVfsPath::new(zip_vfs));
+                    parsed_metas.push(None);
                 }
                 Err(e) => {
                     error!(path = ?handle.0, ?e, "failed to parse archive file");
@@ -479,6 +823,7 @@ async fn load_pak_files_from_handles(
         }
     }
 
+    let crawl_started = profile::start();
     info!(vfs_count = parsed_paths.len() - 1, "building overlay filesystem");
     let overlay_fs = VfsPath::new(OverlayFS::new(&parsed_paths));
     let async_overlay_fs = AsyncVfsPath::new(AsyncOverlayFS::new(&parsed_async_paths));
@@ -486,19 +831,27 @@ async fn load_pak_files_from_handles(
     // Crawl each individual VFS layer instead of the overlay.
     // OverlayFS::read_dir is O(layers) per directory — with 100+ layers this
     // dominates load time. Individual layers have O(1) read_dir (HashMap lookup).
+    //
+    // Full paths are shared between `known_paths` and `file_path_set`, and file names
+    // are interned since the same names repeat across directories and layers.
     let mut known_paths = HashMap::new();
     let mut file_path_set = HashSet::new();
+    let mut names = Interner::new();
+    let mut name_lookups = 0;
 
     for layer in &parsed_paths[1..] {
         let mut queue = vec![layer.clone()];
         while let Some(next) = queue.pop() {
-            let full_path = next.as_str().to_string();
-            let name = next.filename();
+            let full_path: Arc<str> = Arc::from(next.as_str());
+            let name = names.intern(next.filename_ref());
+            name_lookups += 1;
 
             // Use the overlay_fs path for the value so file access goes through
             // the overlay (which handles deduplication correctly).
-            if let Ok(overlay_path) = overlay_fs.join(&full_path) {
-                known_paths.entry((FullPath(full_path), FileName(name))).or_insert(overlay_path);
+            if let Ok(overlay_path) = overlay_fs.join(&*full_path) {
+                known_paths
+                    .entry((FullPath(Arc::clone(&full_path)), FileName(name)))
+                    .or_insert(overlay_path);
             }
 
             match next.read_dir() {
@@ -508,16 +861,44 @@ async fn load_pak_files_from_handles(
                     }
                 }
                 Err(_) => {
-                    file_path_set.insert(next.as_str().to_string());
+                    file_path_set.insert(full_path);
                 }
             }
         }
     }
 
     info!(known_paths = known_paths.len(), files = file_path_set.len(), "crawled filesystem");
+    let tree_started = profile::start();
     let file_tree = build_file_tree(&overlay_fs, &known_paths, &file_path_set, None);
     info!(tree_nodes = file_tree.len(), "built file tree");
 
+    if let (Some(load_started), Some(crawl_started), Some(tree_started)) =
+        (load_started, crawl_started, tree_started)
+    {
+        profile::record_load(profile::LoadSample {
+            archives: parsed_handles.len(),
+            files: file_path_set.len(),
+            parse_ms: profile::millis(crawl_started - load_started),
+            crawl_ms: profile::millis(tree_started - crawl_started),
+            tree_ms: profile::millis(tree_started.elapsed()),
+        });
+
+        let misses = names.len() as u64;
+        profile::record_cache("file_names", name_lookups - misses, misses);
+    }
+
+    let layers = parsed_handles
+        .iter()
+        .zip(parsed_paths[1..].iter().zip(&parsed_async_paths[1..]))
+        .zip(parsed_metas)
+        .map(|((handle, (root, async_root)), pak_meta)| ArchiveLayer {
+            name: handle.display_name(),
+            root: root.clone(),
+            async_root: async_root.clone(),
+            pak_meta,
+        })
+        .collect();
+
     Ok((
         LoadedFiles {
             disk_files_parsed: parsed_handles,
@@ -525,38 +906,17 @@ async fn load_pak_files_from_handles(
             async_overlay_fs,
             known_paths,
             file_path_set,
+            layers,
         },
         file_tree,
     ))
 }
 
-pub fn ascii_icontains(needle: &str, haystack: &str) -> bool {
-    if needle.is_empty() {
-        return true;
-    }
-    if haystack.is_empty() {
-        return false;
-    }
-
-    let needle_bytes = needle.as_bytes();
-    haystack.as_bytes().windows(needle_bytes.len()).any(|window| {
-        for i in 0..window.len() {
-            let haystack_c = window[i];
-            let needle_c = needle_bytes[i];
-            if (haystack_c & !0x20) != (needle_c & !0x20) {
-                return false;
-            }
-        }
-
-        true
-    })
-}
-
 fn build_file_tree(
     path: &VfsPath,
     known_files: &HashMap<(FullPath, FileName), VfsPath>,
-    is_file_cache: &HashSet<String>,
-    filter: Option<String>,
+    is_file_cache: &HashSet<Arc<str>>,
+    filter: Option<PathFilter>,
 ) -> Vec<TreeNode> {
     // Build the file tree that will be displayed
     let mut node_id = 0;
@@ -564,42 +924,43 @@ fn build_file_tree(
     let mut file_tree = Vec::new();
 
     // For filtered trees we need to do things slightly differently:
-    // 1. First filter using the known file paths
-    // 2. Begin building the tree
-    // 3. Using the results from #1 in the tree loop, check to see if the path is a parent
-    // or descendent of a filtered tree.
-
-    let filtered_files = {
-        let query_has_path = filter.as_ref().map(|f| f.contains('/')).unwrap_or_default();
-        let mut filtered_files = Vec::new();
-
-        for ((FullPath(full_path), FileName(file_name)), vfs_path) in known_files.iter() {
-            let haystack = if query_has_path { full_path.as_str() } else { file_name.as_str() };
-
-            if let Some(query) = filter.as_ref()
-                && ascii_icontains(query, haystack)
-            {
-                filtered_files.push(vfs_path.clone());
+    // 1. First score the known file paths against the filter
+    // 2. Record the best score of each match and of every ancestor directory
+    // 3. Build the tree from the entries in #2, ordering each directory's children so the
+    // best matches come first.
+
+    let best_scores = filter.as_ref().map(|filter| {
+        let mut best_scores: HashMap<&str, i64> = HashMap::new();
+        let mut record = |path, score| {
+            let best = best_scores.entry(path).or_insert(score);
+            *best = (*best).max(score);
+        };
+
+        for (FullPath(full_path), FileName(file_name)) in known_files.keys() {
+            let Some(score) = filter.score(full_path, file_name) else {
+                continue;
+            };
+
+            if is_file_cache.contains(full_path) {
+                record(&**full_path, score);
+            }
+
+            let mut path = &**full_path;
+            while let Some((parent, _)) = path.rsplit_once('/') {
+                record(parent, score);
+                path = parent;
             }
         }
 
-        if filter.is_some() { Some(filtered_files) } else { None }
-    };
+        best_scores
+    });
 
     while let Some((close_count, child)) = queue.pop() {
-        let is_included_in_filter = |child: &VfsPath| {
-            if let Some(filtered_files) = filtered_files.as_ref() {
-                filtered_files.iter().any(|node| {
-                    if is_file_cache.contains(child.as_str()) {
-                        child == node
-                    } else {
-                        node.parent().as_str().starts_with(child.as_str())
-                    }
-                })
-            } else {
-                true
-            }
+        let filter_score = |child: &VfsPath| match best_scores.as_ref() {
+            Some(best_scores) => best_scores.get(child.as_str()).copied(),
+            None => Some(0),
         };
+        let is_included_in_filter = |child: &VfsPath| filter_score(child).is_some();
 
         if is_included_in_filter(&child) {
             if !is_file_cache.contains(child.as_str()) {
@@ -609,6 +970,9 @@ fn build_file_tree(
                     title: if node_id == 0 { "Root".to_string() } else { child.filename() },
                     close_count: 0,
                     vfs_path: child.clone(),
+                    descendant_files: 0,
+                    total_size: 0,
+                    entry_type: None,
                 });
 
                 let reader = child.read_dir().expect("failed to read dir");
@@ -616,8 +980,12 @@ fn build_file_tree(
                 let mut propagated_close = close_count + 1;
                 let mut has_children = false;
                 for child in reader
-                    .sorted_by(|a, b| a.filename_ref().cmp(b.filename_ref()))
                     .filter(is_included_in_filter)
+                    .sorted_by(|a, b| {
+                        filter_score(b)
+                            .cmp(&filter_score(a))
+                            .then_with(|| a.filename_ref().cmp(b.filename_ref()))
+                    })
                     .rev()
                 {
                     queue.push((propagated_close, child));
@@ -630,12 +998,18 @@ fn build_file_tree(
                     file_tree.last_mut().unwrap().close_count = 1;
                 }
             } else {
+                // Pak metadata lives in memory, so this doesn't touch the file's data
+                let total_size = child.metadata().map(|meta| meta.len).unwrap_or_default();
+                let entry_type = detect_type(child.as_str(), &[]);
                 file_tree.push(TreeNode {
                     id: node_id,
                     is_dir: false,
                     title: child.filename(),
                     close_count,
                     vfs_path: child,
+                    descendant_files: 0,
+                    total_size,
+                    entry_type: Some(entry_type),
                 });
             }
         }
@@ -643,9 +1017,42 @@ fn build_file_tree(
         node_id += 1;
     }
 
+    compute_folder_stats(&mut file_tree);
+
     file_tree
 }
 
+/// Fills in each directory's descendant file count and total size from the file nodes
+/// beneath it.
+fn compute_folder_stats(file_tree: &mut [TreeNode]) {
+    let dir_indices: HashMap<&str, usize> = file_tree
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| node.is_dir)
+        .map(|(idx, node)| (node.vfs_path.as_str(), idx))
+        .collect();
+
+    let mut stats = vec![(0usize, 0u64); file_tree.len()];
+    for node in file_tree.iter().filter(|node| !node.is_dir) {
+        let mut path = node.vfs_path.as_str();
+        while let Some((parent, _)) = path.rsplit_once('/') {
+            if let Some(&idx) = dir_indices.get(parent) {
+                stats[idx].0 += 1;
+                stats[idx].1 += node.total_size;
+            }
+            path = parent;
+        }
+    }
+
+    drop(dir_indices);
+    for (node, (descendant_files, total_size)) in file_tree.iter_mut().zip(stats) {
+        if node.is_dir {
+            node.descendant_files = descendant_files;
+            node.total_size = total_size;
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn execute<F: Future<Output = ()> + Send + 'static>(f: F) {
     // this is stupid... use any executor of your choice instead
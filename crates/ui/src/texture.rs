@@ -0,0 +1,547 @@
+//! Decoding DDS and Enfusion EDDS textures into RGBA pixels for previews.
+//!
+//! The block compressed formats BC1 to BC5 are decoded, along with uncompressed formats
+//! described by channel masks. EDDS files are DDS files whose mips each follow a small table
+//! and may be LZ4 compressed.
+
+use std::borrow::Cow;
+
+/// Textures are previewed at the largest mip whose sides are at most this long, which keeps
+/// the decoded pixels (and the GPU texture made from them) a reasonable size.
+pub const MAX_PREVIEW_SIDE: usize = 4096;
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_LEN: usize = 128;
+const DX10_HEADER_LEN: usize = 20;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TextureError {
+    #[error("not a DDS texture")]
+    NotDds,
+    #[error("the texture ends early")]
+    Truncated,
+    #[error("{0} textures can't be previewed")]
+    Unsupported(String),
+    #[error("no mip of a previewable size")]
+    MissingMip,
+    #[error("invalid LZ4 data in mip")]
+    InvalidLz4,
+}
+
+/// How pixels are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PixelFormat {
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    /// Uncompressed pixels of `bytes` bytes each, read as little endian integers.
+    Masked {
+        bytes: usize,
+        masks: [u32; 4],
+        luminance: bool,
+    },
+}
+
+impl PixelFormat {
+    /// Bytes in one mip of this size.
+    fn mip_len(&self, width: usize, height: usize) -> usize {
+        let blocks = width.div_ceil(4) * height.div_ceil(4);
+        match self {
+            PixelFormat::Bc1 | PixelFormat::Bc4 => blocks * 8,
+            PixelFormat::Bc2 | PixelFormat::Bc3 | PixelFormat::Bc5 => blocks * 16,
+            PixelFormat::Masked { bytes, .. } => width * height * bytes,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            PixelFormat::Bc1 => "BC1".to_string(),
+            PixelFormat::Bc2 => "BC2".to_string(),
+            PixelFormat::Bc3 => "BC3".to_string(),
+            PixelFormat::Bc4 => "BC4".to_string(),
+            PixelFormat::Bc5 => "BC5".to_string(),
+            PixelFormat::Masked { bytes, .. } => format!("{}-bit uncompressed", bytes * 8),
+        }
+    }
+
+    fn from_fourcc(fourcc: &[u8; 4]) -> Result<Self, TextureError> {
+        match fourcc {
+            b"DXT1" => Ok(PixelFormat::Bc1),
+            b"DXT2" | b"DXT3" => Ok(PixelFormat::Bc2),
+            b"DXT4" | b"DXT5" => Ok(PixelFormat::Bc3),
+            b"ATI1" | b"BC4U" => Ok(PixelFormat::Bc4),
+            b"ATI2" | b"BC5U" => Ok(PixelFormat::Bc5),
+            _ => Err(TextureError::Unsupported(String::from_utf8_lossy(fourcc).into_owned())),
+        }
+    }
+
+    fn from_dxgi(format: u32) -> Result<Self, TextureError> {
+        let rgba = [0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000];
+        let bgra = [0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000];
+        let bgrx = [0xFF_0000, 0xFF00, 0xFF, 0];
+        let masked = |bytes, masks| PixelFormat::Masked { bytes, masks, luminance: false };
+        match format {
+            27..=29 => Ok(masked(4, rgba)),
+            61 => Ok(PixelFormat::Masked { bytes: 1, masks: [0xFF, 0, 0, 0], luminance: true }),
+            70..=72 => Ok(PixelFormat::Bc1),
+            73..=75 => Ok(PixelFormat::Bc2),
+            76..=78 => Ok(PixelFormat::Bc3),
+            79 | 80 => Ok(PixelFormat::Bc4),
+            82 | 83 => Ok(PixelFormat::Bc5),
+            87 | 90 | 91 => Ok(masked(4, bgra)),
+            88 | 92 | 93 => Ok(masked(4, bgrx)),
+            81 | 84 => Err(TextureError::Unsupported("signed BC4/BC5".to_string())),
+            94..=96 => Err(TextureError::Unsupported("BC6H".to_string())),
+            97..=99 => Err(TextureError::Unsupported("BC7".to_string())),
+            other => Err(TextureError::Unsupported(format!("DXGI format {other}"))),
+        }
+    }
+}
+
+/// One mip of a texture, as RGBA pixels.
+#[derive(Debug)]
+pub struct DecodedTexture {
+    pub width: usize,
+    pub height: usize,
+    /// Row by row, four bytes per pixel.
+    pub rgba: Vec<u8>,
+    /// The texture's format and full size, e.g. `BC3, 2048x2048, 12 mips, EDDS`.
+    pub description: String,
+}
+
+/// Whether `data` looks like a DDS or EDDS texture.
+pub fn is_texture(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, TextureError> {
+    let bytes = data.get(offset..offset + 4).ok_or(TextureError::Truncated)?;
+    Ok(u32::from_le_bytes(bytes.try_into().expect("slice is four bytes")))
+}
+
+/// Decodes the largest mip of a DDS or EDDS texture with no side longer than `max_side`.
+pub fn decode(data: &[u8], max_side: usize) -> Result<DecodedTexture, TextureError> {
+    if !is_texture(data) {
+        return Err(TextureError::NotDds);
+    }
+    let height = u32_at(data, 12)? as usize;
+    let width = u32_at(data, 16)? as usize;
+    let mip_count = (u32_at(data, 28)? as usize).max(1);
+    let pf_flags = u32_at(data, 80)?;
+    let fourcc = u32_at(data, 84)?.to_le_bytes();
+
+    let mut data_start = HEADER_LEN;
+    let format = if pf_flags & DDPF_FOURCC == 0 {
+        let bits = u32_at(data, 88)? as usize;
+        if !matches!(bits, 8 | 16 | 24 | 32) {
+            return Err(TextureError::Unsupported(format!("{bits}-bit uncompressed")));
+        }
+        let mut masks = [u32_at(data, 92)?, u32_at(data, 96)?, u32_at(data, 100)?, 0];
+        if pf_flags & DDPF_ALPHAPIXELS != 0 {
+            masks[3] = u32_at(data, 104)?;
+        }
+        PixelFormat::Masked { bytes: bits / 8, masks, luminance: pf_flags & DDPF_LUMINANCE != 0 }
+    } else if &fourcc == b"DX10" {
+        data_start += DX10_HEADER_LEN;
+        PixelFormat::from_dxgi(u32_at(data, HEADER_LEN)?)?
+    } else {
+        PixelFormat::from_fourcc(&fourcc)?
+    };
+
+    let mip_size = |level: usize| ((width >> level).max(1), (height >> level).max(1));
+    let level = (0..mip_count)
+        .find(|&level| {
+            let (w, h) = mip_size(level);
+            w.max(h) <= max_side
+        })
+        .ok_or(TextureError::MissingMip)?;
+    let (mip_width, mip_height) = mip_size(level);
+    let mip_len = format.mip_len(mip_width, mip_height);
+
+    let body = data.get(data_start..).ok_or(TextureError::Truncated)?;
+    let (pixels, container) = match edds_mips(body, mip_count) {
+        Some(mips) => {
+            let mip = mips.into_iter().find(|mip| mip.decoded_len() == Some(mip_len));
+            (mip.ok_or(TextureError::MissingMip)?.decode()?, "EDDS")
+        }
+        None => {
+            let offset: usize = (0..level)
+                .map(|level| {
+                    let (w, h) = mip_size(level);
+                    format.mip_len(w, h)
+                })
+                .sum();
+            let mip = body.get(offset..offset + mip_len).ok_or(TextureError::Truncated)?;
+            (Cow::Borrowed(mip), "DDS")
+        }
+    };
+    if pixels.len() < mip_len {
+        return Err(TextureError::Truncated);
+    }
+
+    let rgba = decode_pixels(format, &pixels, mip_width, mip_height);
+    let mut description =
+        format!("{}, {width}x{height}, {mip_count} mips, {container}", format.name());
+    if level > 0 {
+        description.push_str(&format!(", showing {mip_width}x{mip_height}"));
+    }
+    Ok(DecodedTexture { width: mip_width, height: mip_height, rgba, description })
+}
+
+/// A mip's data in an EDDS file.
+enum EddsMip<'a> {
+    Copy(&'a [u8]),
+    Lz4(&'a [u8]),
+}
+
+impl<'a> EddsMip<'a> {
+    /// The mip's length once decompressed.
+    fn decoded_len(&self) -> Option<usize> {
+        match self {
+            EddsMip::Copy(data) => Some(data.len()),
+            EddsMip::Lz4(data) => u32_at(data, 0).ok().map(|len| len as usize),
+        }
+    }
+
+    fn decode(self) -> Result<Cow<'a, [u8]>, TextureError> {
+        match self {
+            EddsMip::Copy(data) => Ok(Cow::Borrowed(data)),
+            EddsMip::Lz4(data) => decompress_lz4_chain(data).map(Cow::Owned),
+        }
+    }
+}
+
+/// Reads the EDDS mip table at the start of `body`: a `COPY` or `LZ4 ` tag and length for
+/// each mip, followed by the mips in the same order. `None` if `body` doesn't start with
+/// one, as in plain DDS files.
+fn edds_mips(body: &[u8], mip_count: usize) -> Option<Vec<EddsMip<'_>>> {
+    let table_len = mip_count.checked_mul(8)?;
+    let table = body.get(..table_len)?;
+    let mut offset = table_len;
+    let mut mips = Vec::with_capacity(mip_count);
+    for entry in table.chunks_exact(8) {
+        let len = u32::from_le_bytes(entry[4..].try_into().expect("slice is four bytes")) as usize;
+        let data = body.get(offset..offset.checked_add(len)?)?;
+        offset += len;
+        mips.push(match &entry[..4] {
+            b"COPY" => EddsMip::Copy(data),
+            b"LZ4 " => EddsMip::Lz4(data),
+            _ => return None,
+        });
+    }
+    Some(mips)
+}
+
+/// Decompresses an EDDS `LZ4 ` mip: its decompressed length, then LZ4 blocks which each
+/// follow a 24-bit length and a flags byte whose top bit marks the last block. Each block
+/// can refer back to the data decompressed before it.
+fn decompress_lz4_chain(data: &[u8]) -> Result<Vec<u8>, TextureError> {
+    let len = u32_at(data, 0)? as usize;
+    let mut out = Vec::with_capacity(len);
+    let mut offset = 4;
+    while offset < data.len() {
+        let header = u32_at(data, offset)?;
+        offset += 4;
+        let block_len = (header & 0xFF_FFFF) as usize;
+        let block = data.get(offset..offset + block_len).ok_or(TextureError::Truncated)?;
+        offset += block_len;
+        decompress_lz4_block(block, &mut out)?;
+        if header & 0x8000_0000 != 0 {
+            break;
+        }
+    }
+    if out.len() != len {
+        return Err(TextureError::InvalidLz4);
+    }
+    Ok(out)
+}
+
+/// Decompresses one raw LZ4 block onto the end of `out`, whose contents matches may copy.
+fn decompress_lz4_block(block: &[u8], out: &mut Vec<u8>) -> Result<(), TextureError> {
+    let mut pos = 0;
+    let mut next = || -> Result<u8, TextureError> {
+        let byte = *block.get(pos).ok_or(TextureError::InvalidLz4)?;
+        pos += 1;
+        Ok(byte)
+    };
+    let read_len = |mut len: usize, next: &mut dyn FnMut() -> Result<u8, TextureError>| {
+        if len == 15 {
+            loop {
+                let byte = next()?;
+                len += byte as usize;
+                if byte != 255 {
+                    break;
+                }
+            }
+        }
+        Ok::<_, TextureError>(len)
+    };
+
+    loop {
+        let token = next()?;
+        let literals = read_len((token >> 4) as usize, &mut next)?;
+        for _ in 0..literals {
+            let byte = next()?;
+            out.push(byte);
+        }
+        // The last sequence is only literals
+        let Ok(low) = next() else {
+            return Ok(());
+        };
+        let distance = u16::from_le_bytes([low, next()?]) as usize;
+        if distance == 0 || distance > out.len() {
+            return Err(TextureError::InvalidLz4);
+        }
+        let match_len = read_len((token & 0xF) as usize, &mut next)? + 4;
+        // Matches can overlap the bytes they produce, so they're copied a byte at a time
+        let start = out.len() - distance;
+        for idx in start..start + match_len {
+            out.push(out[idx]);
+        }
+    }
+}
+
+fn decode_pixels(format: PixelFormat, data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut rgba = vec![0; width * height * 4];
+    let block_len = match format {
+        PixelFormat::Masked { bytes, masks, luminance } => {
+            for (pixel, out) in data.chunks_exact(bytes).zip(rgba.chunks_exact_mut(4)) {
+                let mut value = [0; 4];
+                value[..bytes].copy_from_slice(pixel);
+                let value = u32::from_le_bytes(value);
+                let [r, g, b, a] = masks.map(|mask| channel(value, mask));
+                let a = if masks[3] == 0 { 255 } else { a };
+                out.copy_from_slice(&if luminance { [r, r, r, a] } else { [r, g, b, a] });
+            }
+            return rgba;
+        }
+        PixelFormat::Bc1 | PixelFormat::Bc4 => 8,
+        PixelFormat::Bc2 | PixelFormat::Bc3 | PixelFormat::Bc5 => 16,
+    };
+
+    let blocks_wide = width.div_ceil(4);
+    for (idx, block) in
+        data.chunks_exact(block_len).take(blocks_wide * height.div_ceil(4)).enumerate()
+    {
+        let texels = decode_block(format, block);
+        let (block_x, block_y) = ((idx % blocks_wide) * 4, (idx / blocks_wide) * 4);
+        for (texel, color) in texels.iter().enumerate() {
+            let (x, y) = (block_x + texel % 4, block_y + texel / 4);
+            if x < width && y < height {
+                let at = (y * width + x) * 4;
+                rgba[at..at + 4].copy_from_slice(color);
+            }
+        }
+    }
+    rgba
+}
+
+/// The channel of `value` selected by `mask`, scaled to eight bits.
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let max = mask >> mask.trailing_zeros();
+    let bits = (value & mask) >> mask.trailing_zeros();
+    (bits as u64 * 255 / max as u64) as u8
+}
+
+/// The 16 texels of a 4x4 block, row by row.
+fn decode_block(format: PixelFormat, block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = [[0, 0, 0, 255]; 16];
+    match format {
+        PixelFormat::Bc1 => texels = color_block(block, true),
+        PixelFormat::Bc2 => {
+            texels = color_block(&block[8..], false);
+            let alphas = u64::from_le_bytes(block[..8].try_into().expect("slice is eight bytes"));
+            for (idx, texel) in texels.iter_mut().enumerate() {
+                texel[3] = ((alphas >> (idx * 4)) & 0xF) as u8 * 17;
+            }
+        }
+        PixelFormat::Bc3 => {
+            texels = color_block(&block[8..], false);
+            for (texel, alpha) in texels.iter_mut().zip(alpha_block(&block[..8])) {
+                texel[3] = alpha;
+            }
+        }
+        PixelFormat::Bc4 => {
+            for (texel, value) in texels.iter_mut().zip(alpha_block(block)) {
+                *texel = [value, value, value, 255];
+            }
+        }
+        PixelFormat::Bc5 => {
+            let reds = alpha_block(&block[..8]);
+            let greens = alpha_block(&block[8..]);
+            for (idx, texel) in texels.iter_mut().enumerate() {
+                *texel = [reds[idx], greens[idx], 0, 255];
+            }
+        }
+        PixelFormat::Masked { .. } => unreachable!("uncompressed pixels aren't in blocks"),
+    }
+    texels
+}
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let r = (color >> 11) as u8 & 0x1F;
+    let g = (color >> 5) as u8 & 0x3F;
+    let b = color as u8 & 0x1F;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+/// Decodes a BC1 color block. Blocks of other formats always use four colors, while BC1
+/// blocks whose first color isn't greater than their second have three and transparency.
+fn color_block(block: &[u8], bc1: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let (rgb0, rgb1) = (rgb565(c0), rgb565(c1));
+    let mix = |w0: u16, w1: u16| {
+        let [r, g, b] = std::array::from_fn(|channel| {
+            ((rgb0[channel] as u16 * w0 + rgb1[channel] as u16 * w1) / (w0 + w1)) as u8
+        });
+        [r, g, b, 255]
+    };
+
+    let palette = if c0 > c1 || !bc1 {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(1, 1), [0, 0, 0, 0]]
+    };
+    std::array::from_fn(|idx| palette[((indices >> (idx * 2)) & 0x3) as usize])
+}
+
+/// Decodes a BC3 alpha block, also used for BC4 and BC5 channels, into 16 values.
+fn alpha_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u16, block[1] as u16);
+    let mut palette = [0u8; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+    if a0 > a1 {
+        for idx in 1..7 {
+            palette[idx + 1] = ((a0 * (7 - idx as u16) + a1 * idx as u16) / 7) as u8;
+        }
+    } else {
+        for idx in 1..5 {
+            palette[idx + 1] = ((a0 * (5 - idx as u16) + a1 * idx as u16) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut indices = [0u8; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+    std::array::from_fn(|idx| palette[((indices >> (idx * 3)) & 0x7) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A DDS header for a texture with a FourCC format.
+    fn header(width: u32, height: u32, mips: u32, fourcc: &[u8; 4]) -> Vec<u8> {
+        let mut data = vec![0; HEADER_LEN];
+        data[..4].copy_from_slice(MAGIC);
+        data[4..8].copy_from_slice(&124u32.to_le_bytes());
+        data[12..16].copy_from_slice(&height.to_le_bytes());
+        data[16..20].copy_from_slice(&width.to_le_bytes());
+        data[28..32].copy_from_slice(&mips.to_le_bytes());
+        data[76..80].copy_from_slice(&32u32.to_le_bytes());
+        data[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        data[84..88].copy_from_slice(fourcc);
+        data
+    }
+
+    /// A BC1 block of a single color.
+    fn solid_bc1(color: u16) -> [u8; 8] {
+        let [lo, hi] = color.to_le_bytes();
+        [lo, hi, 0, 0, 0, 0, 0, 0]
+    }
+
+    #[test]
+    fn decodes_bc1_and_picks_a_small_enough_mip() {
+        // An 8x4 red mip, then a 4x2 blue one
+        let mut data = header(8, 4, 2, b"DXT1");
+        data.extend(solid_bc1(0xF800));
+        data.extend(solid_bc1(0xF800));
+        data.extend(solid_bc1(0x001F));
+
+        let full = decode(&data, 8).unwrap();
+        assert_eq!((full.width, full.height), (8, 4));
+        assert!(full.rgba.chunks(4).all(|texel| texel == [255, 0, 0, 255]));
+        assert_eq!(full.description, "BC1, 8x4, 2 mips, DDS");
+
+        let small = decode(&data, 4).unwrap();
+        assert_eq!((small.width, small.height), (4, 2));
+        assert_eq!(small.rgba.len(), 4 * 2 * 4);
+        assert!(small.rgba.chunks(4).all(|texel| texel == [0, 0, 255, 255]));
+        assert!(small.description.ends_with("showing 4x2"));
+    }
+
+    #[test]
+    fn decodes_block_palettes() {
+        // Alpha 0 to 255 over 8 steps, and texel 1 picking the second endpoint
+        let alphas = alpha_block(&[255, 0, 0b0000_1000, 0, 0, 0, 0, 0]);
+        assert_eq!(alphas[0], 255);
+        assert_eq!(alphas[1], 0);
+        let alphas = alpha_block(&[0, 255, 0b0011_0111, 0, 0, 0, 0, 0]);
+        assert_eq!(alphas[..2], [255, 0]);
+
+        // Three color mode, with texel 0 transparent
+        let texels = color_block(&[0, 0, 0xFF, 0xFF, 0b11, 0, 0, 0], true);
+        assert_eq!(texels[0], [0, 0, 0, 0]);
+        assert_eq!(texels[1], [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn decodes_lz4_edds_mips() {
+        let pixels: Vec<u8> = [solid_bc1(0x07E0); 4].concat();
+        // Eight literal bytes, a match repeating them two and a half times, then the rest
+        let mut block = vec![0x8F];
+        block.extend(&pixels[..8]);
+        block.extend([8, 0, 1, 0x40]);
+        block.extend(&pixels[28..]);
+        let mut mip = (pixels.len() as u32).to_le_bytes().to_vec();
+        mip.extend((block.len() as u32 | 0x8000_0000).to_le_bytes());
+        mip.extend(&block);
+        let small = solid_bc1(0);
+
+        let mut data = header(8, 8, 2, b"DXT1");
+        data.extend(b"COPY");
+        data.extend((small.len() as u32).to_le_bytes());
+        data.extend(b"LZ4 ");
+        data.extend((mip.len() as u32).to_le_bytes());
+        data.extend(small);
+        data.extend(&mip);
+
+        let texture = decode(&data, MAX_PREVIEW_SIDE).unwrap();
+        assert_eq!((texture.width, texture.height), (8, 8));
+        assert!(texture.rgba.chunks(4).all(|texel| texel == [0, 255, 0, 255]));
+        assert_eq!(texture.description, "BC1, 8x8, 2 mips, EDDS");
+    }
+
+    #[test]
+    fn decodes_uncompressed_and_rejects_unsupported_formats() {
+        let mut data = header(1, 1, 1, b"\0\0\0\0");
+        data[80..84].copy_from_slice(&0x41u32.to_le_bytes());
+        data[88..92].copy_from_slice(&32u32.to_le_bytes());
+        for (idx, mask) in [0xFF_0000u32, 0xFF00, 0xFF, 0xFF00_0000].iter().enumerate() {
+            data[92 + idx * 4..96 + idx * 4].copy_from_slice(&mask.to_le_bytes());
+        }
+        data.extend([1, 2, 3, 4]);
+        assert_eq!(decode(&data, MAX_PREVIEW_SIDE).unwrap().rgba, [3, 2, 1, 4]);
+
+        let mut bc7 = header(4, 4, 1, b"DX10");
+        bc7.extend(98u32.to_le_bytes());
+        bc7.extend([0; DX10_HEADER_LEN - 4]);
+        bc7.extend([0; 16]);
+        assert!(matches!(decode(&bc7, MAX_PREVIEW_SIDE), Err(TextureError::Unsupported(_))));
+        assert!(matches!(decode(b"PNG", MAX_PREVIEW_SIDE), Err(TextureError::NotDds)));
+    }
+}
@@ -0,0 +1,168 @@
+use cfg_parser::table::RecordTable;
+use cfg_parser::text::ConfigEntry;
+use cfg_parser::text::keyed;
+use cfg_parser::units::RenderedValue;
+use cfg_parser::units::ValueRules;
+use cfg_parser::units::render;
+use egui::Rgba;
+use egui::RichText;
+use egui::Sense;
+use egui::Ui;
+
+use crate::task::execute;
+use crate::ui::tab::save_export;
+
+/// A table of a config's repeated records, and how the user sorted it.
+#[derive(Clone)]
+pub struct ConfigTable {
+    pub table: RecordTable,
+    /// The column the rows are sorted by, and whether they're in descending order.
+    pub sorted_by: Option<(usize, bool)>,
+}
+
+/// How [`show_entries`] shows values, and what the user asked for while it was shown.
+pub(crate) struct EntryView<'a> {
+    pub(crate) rules: &'a ValueRules,
+    /// Show angles and durations in their other unit.
+    pub(crate) convert: bool,
+    /// Whether other builds are loaded to track properties across.
+    pub(crate) can_track: bool,
+    /// Keys down to the property the user asked to track, from [`keyed`].
+    pub(crate) tracked: Option<Vec<String>>,
+}
+
+/// Shows text config entries as a tree, with blocks collapsed under their headers and
+/// properties whose unit the view's rules recognize shown in that unit. `path` holds the
+/// keys of the block `entries` are in.
+pub(crate) fn show_entries(
+    ui: &mut Ui,
+    entries: &[ConfigEntry],
+    view: &mut EntryView<'_>,
+    path: &mut Vec<String>,
+) {
+    for (idx, (key, entry)) in keyed(entries).into_iter().enumerate() {
+        let Some(children) = &entry.children else {
+            ui.horizontal(|ui| {
+                let label =
+                    egui::Label::new(RichText::new(&entry.key).strong()).sense(Sense::click());
+                let response = ui.add(label);
+                if view.can_track {
+                    response.context_menu(|ui| {
+                        if ui.button("Track Across Builds").clicked() {
+                            let mut tracked = path.clone();
+                            tracked.push(key.clone());
+                            view.tracked = Some(tracked);
+                            ui.close();
+                        }
+                    });
+                }
+                show_values(ui, &entry.key, &entry.values, view.rules, view.convert);
+            });
+            continue;
+        };
+
+        let header = format!("{} {}", entry.key, entry.values.join(" "));
+        let header = match header.trim() {
+            "" => format!("[{idx}]"),
+            header => header.to_string(),
+        };
+        egui::CollapsingHeader::new(header).id_salt(&key).show(ui, |ui| {
+            path.push(key.clone());
+            show_entries(ui, children, view, path);
+            path.pop();
+        });
+    }
+}
+
+/// Shows `tables` from the config `file_name` one after another. Clicking a column header
+/// sorts by that column, and clicking it again reverses the order.
+pub(crate) fn show_tables(
+    ui: &mut Ui,
+    file_name: &str,
+    tables: &mut [ConfigTable],
+    rules: &ValueRules,
+    convert: bool,
+) {
+    if tables.is_empty() {
+        ui.weak("No blocks of the same class appear side by side in this config");
+        return;
+    }
+
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    for (idx, ConfigTable { table, sorted_by }) in tables.iter_mut().enumerate() {
+        let title = format!("{} ({} records)", table.path.join(" > "), table.rows.len());
+        egui::CollapsingHeader::new(title).id_salt(idx).default_open(true).show(ui, |ui| {
+            if ui.small_button("Export CSV").clicked() {
+                let class = table.path.last().map_or("records", String::as_str);
+                execute(save_export(
+                    "Export CSV".to_string(),
+                    format!("{stem}_{class}.csv"),
+                    table.to_csv().into_bytes(),
+                ));
+            }
+
+            let mut clicked = None;
+            egui::Grid::new(("records", idx)).striped(true).show(ui, |ui| {
+                for (column, name) in table.columns.iter().enumerate() {
+                    let arrow = match *sorted_by {
+                        Some((sorted, false)) if sorted == column => " ⏶",
+                        Some((sorted, true)) if sorted == column => " ⏷",
+                        _ => "",
+                    };
+                    let header =
+                        egui::Button::new(RichText::new(format!("{name}{arrow}")).strong())
+                            .frame(false);
+                    if ui.add(header).clicked() {
+                        clicked = Some(column);
+                    }
+                }
+                ui.end_row();
+
+                for row in &table.rows {
+                    for (name, cell) in table.columns.iter().zip(row) {
+                        ui.horizontal(|ui| {
+                            show_values(
+                                ui,
+                                column_key(name),
+                                std::slice::from_ref(cell),
+                                rules,
+                                convert,
+                            );
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if let Some(column) = clicked {
+                let descending = *sorted_by == Some((column, false));
+                table.sort_by_column(column, descending);
+                *sorted_by = Some((column, descending));
+            }
+        });
+    }
+}
+
+/// The property a table column shows, without the blocks it's nested in or its number.
+fn column_key(column: &str) -> &str {
+    let property = column.rsplit('.').next().unwrap_or(column);
+    property.split(' ').next().unwrap_or(property)
+}
+
+fn show_values(ui: &mut Ui, key: &str, values: &[String], rules: &ValueRules, convert: bool) {
+    let raw = values.join(" ");
+    let rendered = rules.unit_for(key).and_then(|unit| render(unit, values, convert));
+    match rendered {
+        Some(RenderedValue::Text(text)) => {
+            ui.label(text).on_hover_text(raw);
+        }
+        Some(RenderedValue::Color([r, g, b, a])) => {
+            let color = Rgba::from_rgba_unmultiplied(r, g, b, a);
+            egui::color_picker::show_color(ui, color, egui::vec2(16.0, 16.0));
+            ui.label(raw);
+        }
+        None => {
+            ui.label(raw);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! The Help menu and the getting-started tour shown on first run.
+
+use crate::EnfusionToolsApp;
+
+/// A common task and how to do it, shown as a page of the tour and in the Help menu.
+pub(crate) struct Workflow {
+    pub title: &'static str,
+    pub steps: &'static [&'static str],
+}
+
+pub(crate) const WORKFLOWS: &[Workflow] = &[
+    Workflow {
+        title: "Load Archives",
+        steps: &[
+            "Open Files loads .pak, .pbo and .zip archives into one merged file tree.",
+            "On desktop, Load Game Data finds installed games and their workshop mods, and \
+             Overlay Folder layers an unpacked mod folder over the loaded archives.",
+            "Type in the filter above the tree to narrow it down, and click a file to open it.",
+        ],
+    },
+    Workflow {
+        title: "Search",
+        steps: &[
+            "Type a query in the search box and press Enter. Results open in a new tab.",
+            "The options beside the box switch between regex, literal and binary searches, and \
+             Edit > Searched Files picks which files are read.",
+            "Right-click a folder in the tree to search only that folder.",
+            "History lists recent searches to run again.",
+        ],
+    },
+    Workflow {
+        title: "Diff Builds",
+        steps: &[
+            "Diff Builds compares two sets of archives and lists the files added, removed and \
+             changed between them.",
+            "Load Build to Compare keeps the workspace open and lets open files be compared \
+             with another build.",
+            "On desktop, Diff Folder shows what an unpacked mod folder changes.",
+        ],
+    },
+    Workflow {
+        title: "Extract and Export",
+        steps: &[
+            "Dump Manifest saves every file's path, size and hash.",
+            "Export buttons on tabs save what they show as HTML or CSV.",
+            "To write files out of archives, use the command line tool: \
+             enfusion extract <archives> --output <directory>",
+        ],
+    },
+];
+
+/// Shortcuts listed in the Help menu, as (keys, action).
+const SHORTCUTS: &[(&str, &str)] = &[
+    ("Ctrl+Z", "Undo"),
+    ("Ctrl+Y, Ctrl+Shift+Z", "Redo"),
+    ("Ctrl+V", "Open a pasted enfpak:// link, outside text fields"),
+    ("Enter", "Search, in the search box, or apply the tree's filter"),
+    ("Right-click", "More actions for files and folders in the tree"),
+];
+
+/// Progress through the getting-started tour, which walks through [`WORKFLOWS`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct Tour {
+    /// The page being shown. 0 is the welcome page, and each workflow follows it.
+    page: usize,
+    /// Set once the tour has been finished or closed, so it isn't shown again.
+    dismissed: bool,
+}
+
+impl Tour {
+    const PAGES: usize = WORKFLOWS.len() + 1;
+
+    pub fn is_open(&self) -> bool {
+        !self.dismissed
+    }
+
+    /// The workflow on the current page, or `None` on the welcome page.
+    fn workflow(&self) -> Option<&'static Workflow> {
+        self.page.checked_sub(1).and_then(|idx| WORKFLOWS.get(idx))
+    }
+
+    fn is_last_page(&self) -> bool {
+        self.page + 1 >= Self::PAGES
+    }
+
+    /// Moves to the next page, dismissing the tour after the last one.
+    fn next(&mut self) {
+        if self.is_last_page() {
+            self.dismiss();
+        } else {
+            self.page += 1;
+        }
+    }
+
+    fn back(&mut self) {
+        self.page = self.page.saturating_sub(1);
+    }
+
+    fn dismiss(&mut self) {
+        *self = Tour { page: 0, dismissed: true };
+    }
+
+    /// Shows the tour again from the start.
+    pub fn restart(&mut self) {
+        *self = Tour::default();
+    }
+}
+
+impl EnfusionToolsApp {
+    pub(crate) fn show_help_menu(&mut self, ui: &mut egui::Ui) {
+        if ui.button("Getting Started Tour").clicked() {
+            self.tour.restart();
+            ui.close();
+        }
+        ui.separator();
+
+        for workflow in WORKFLOWS {
+            ui.menu_button(workflow.title, |ui| {
+                ui.set_max_width(360.0);
+                show_steps(ui, workflow);
+            });
+        }
+        ui.menu_button("Keyboard Shortcuts", |ui| {
+            egui::Grid::new("help_shortcuts").num_columns(2).striped(true).show(ui, |ui| {
+                for (keys, action) in SHORTCUTS {
+                    ui.strong(*keys);
+                    ui.label(*action);
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// The getting-started tour, until it's finished or closed.
+    pub(crate) fn show_tour_window(&mut self, ctx: &egui::Context) {
+        if !self.tour.is_open() {
+            return;
+        }
+
+        let tour = &mut self.tour;
+        let mut open = true;
+        egui::Window::new("Getting Started")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                match tour.workflow() {
+                    Some(workflow) => {
+                        ui.heading(workflow.title);
+                        show_steps(ui, workflow);
+                    }
+                    None => {
+                        ui.heading("Welcome to Enfusion Tools");
+                        ui.label(
+                            "Browse, search and compare the files in Enfusion .pak archives, \
+                             and DayZ and Arma .pbo archives. This tour covers the main \
+                             tasks; it can be shown again from the Help menu.",
+                        );
+                    }
+                }
+
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} of {}", tour.page + 1, Tour::PAGES));
+                    if ui.add_enabled(tour.page > 0, egui::Button::new("Back")).clicked() {
+                        tour.back();
+                    }
+                    let next = if tour.is_last_page() { "Done" } else { "Next" };
+                    if ui.button(next).clicked() {
+                        tour.next();
+                    }
+                    if !tour.is_last_page() && ui.button("Skip Tour").clicked() {
+                        tour.dismiss();
+                    }
+                });
+            });
+
+        if !open {
+            self.tour.dismiss();
+        }
+    }
+}
+
+fn show_steps(ui: &mut egui::Ui, workflow: &Workflow) {
+    for step in workflow.steps {
+        ui.label(format!("• {step}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tour_pages_through_every_workflow() {
+        let mut tour = Tour::default();
+        assert!(tour.is_open());
+        assert!(tour.workflow().is_none());
+
+        let mut titles = Vec::new();
+        tour.back();
+        while tour.is_open() {
+            tour.next();
+            if let Some(workflow) = tour.workflow() {
+                titles.push(workflow.title);
+            }
+        }
+        let expected: Vec<_> = WORKFLOWS.iter().map(|workflow| workflow.title).collect();
+        assert_eq!(titles, expected);
+
+        tour.restart();
+        assert_eq!(tour, Tour::default());
+    }
+}
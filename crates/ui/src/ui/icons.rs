@@ -0,0 +1,22 @@
+use enfusion_pak::entry_type::EntryType;
+
+/// Glyph from egui's bundled emoji fonts used to mark entries of the given type.
+pub(crate) fn entry_type_icon(entry_type: EntryType) -> &'static str {
+    match entry_type {
+        EntryType::Script => "📜",
+        EntryType::Config | EntryType::RapifiedConfig => "⚙",
+        EntryType::Layout => "🗔",
+        EntryType::Prefab => "🧩",
+        EntryType::World => "🌍",
+        EntryType::Meta => "🏷",
+        EntryType::Material => "🎨",
+        EntryType::Texture => "🖼",
+        EntryType::Audio => "🔊",
+        EntryType::Model => "🔷",
+        EntryType::Animation | EntryType::AnimationGraph => "🎞",
+        EntryType::StringTable => "🌐",
+        EntryType::Json | EntryType::Xml | EntryType::Text => "📄",
+        EntryType::Archive => "📦",
+        _ => "🗋",
+    }
+}
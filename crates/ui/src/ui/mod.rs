@@ -1,5 +1,9 @@
+pub(crate) mod config_viewer;
 pub(crate) mod diff_viewer;
+pub(crate) mod help;
+pub(crate) mod icons;
 pub(crate) mod search;
 pub(crate) mod tab;
 pub(crate) mod text_viewer;
 pub(crate) mod tree;
+pub(crate) mod view_state;
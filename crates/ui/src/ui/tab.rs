@@ -1,28 +1,95 @@
 use std::sync::Arc;
+use std::sync::Mutex;
 
+use cfg_parser::text::ConfigEntry;
+use cfg_parser::text::TextConfigError;
 use egui::Color32;
 use egui::TextFormat;
 use egui::Ui;
+use egui::text::CCursor;
 use egui::text::LayoutJob;
+use egui::text_edit::TextEditOutput;
 use egui_code_editor::CodeEditor;
 use egui_code_editor::ColorTheme;
-use egui_code_editor::Syntax;
+use egui_code_editor::highlighting::highlight;
+use enfusion_pak::entry_type::detect_type;
 use enfusion_pak::vfs::VfsPath;
+use enfusion_search::CancellationToken;
+use enfusion_search::bytes::MAX_MATCHES_PER_FILE;
+use regex::Regex;
+use tracing::error;
 
+use crate::analysis::Report;
 use crate::app::AppInternalData;
+use crate::audio;
+use crate::audio::AudioClip;
+use crate::audio::AudioFormat;
+use crate::audio::Peak;
+use crate::audio::Player;
+use crate::binary::HEX_ROW_LEN;
+use crate::binary::hex_row;
 use crate::diff;
-use crate::diff::DiffResult;
-use crate::task;
+use crate::events::Subscriber;
+use crate::events::WorkspaceEvent;
+use crate::fuzzy::fuzzy_score;
+use crate::history;
+use crate::history::ValueHistory;
+use crate::html;
+use crate::palette::Palette;
+use crate::permalink;
+use crate::permalink::PakSetHash;
+use crate::permalink::Permalink;
+use crate::settings;
+#[cfg(target_arch = "wasm32")]
+use crate::share;
+use crate::shutdown;
+use crate::syntax::is_text_config;
+use crate::syntax::syntax_for;
+use crate::task::BackgroundTask;
+use crate::task::ComparisonBuild;
+use crate::task::FileLoad;
 use crate::task::LineNumber;
+use crate::task::SearchEnd;
 use crate::task::SearchId;
+use crate::task::SearchMatches;
+use crate::task::SearchProgress;
 use crate::task::SearchResult;
 use crate::task::execute;
+use crate::ui::config_viewer;
+use crate::ui::config_viewer::ConfigTable;
+use crate::ui::config_viewer::EntryView;
+use crate::ui::icons::entry_type_icon;
+use crate::ui::view_state::SavedViews;
+use crate::ui::view_state::TabViewState;
+use crate::undo::Command;
 
 #[derive(Clone)]
 pub enum TabKind {
     Editor(EditorData),
     SearchResults(SearchData),
     Diff(DiffData),
+    Providers(ProvidersData),
+    Analysis(AnalysisData),
+    History(HistoryData),
+    HexView(HexViewData),
+    ImagePreview(ImagePreviewData),
+    AudioPreview(AudioPreviewData),
+}
+
+/// Height of the waveform in audio preview tabs.
+const WAVEFORM_HEIGHT: f32 = 160.0;
+
+/// Editor contents at least this long are dropped once the tab goes unseen for
+/// [`EVICT_AFTER_SECS`].
+const MIN_EVICTED_LEN: usize = 64 * 1024;
+const EVICT_AFTER_SECS: f64 = 5.0 * 60.0;
+
+#[derive(Clone)]
+pub enum EditorContents {
+    Loaded(String),
+    /// Dropped to save memory, and read again from the archives when the tab is shown.
+    Evicted,
+    Reloading,
 }
 
 #[derive(Clone)]
@@ -30,7 +97,42 @@ pub enum TabKind {
 pub struct EditorData {
     pub opened_file: VfsPath,
     pub title: String,
-    pub contents: String,
+    pub contents: EditorContents,
+    /// When the tab was last shown, in seconds of UI time. The editor's scroll position
+    /// is kept by egui, so it survives the contents being dropped.
+    pub last_viewed: Option<f64>,
+    /// Set when the user asks to move this tab into its own window.
+    pub pop_out_requested: bool,
+    /// Line the text cursor was last on, used by "Copy Link".
+    pub cursor_line: Option<usize>,
+    /// Line referenced by the permalink this tab was opened from.
+    pub linked_line: Option<usize>,
+    /// Archives loaded when the file was opened.
+    pub pak_set: PakSetHash,
+    /// Different archives have been loaded since, so `contents` may not match the workspace.
+    pub outdated: bool,
+    /// The build `contents` is being compared against, if any.
+    pub comparison: Option<EditorComparison>,
+    /// Set while a text config is shown as a tree of entries instead of as text.
+    pub structured: Option<StructuredConfig>,
+}
+
+/// An editor's contents parsed as a text config.
+#[derive(Clone)]
+pub struct StructuredConfig {
+    pub entries: Arc<Result<Vec<ConfigEntry>, TextConfigError>>,
+    /// Show angles and durations in their other unit, e.g. degrees as radians.
+    pub convert_units: bool,
+    /// Set while repeated records are shown as tables instead of the tree.
+    pub tables: Option<Vec<ConfigTable>>,
+}
+
+/// The same file in another build, diffed against an editor's contents.
+#[derive(Clone)]
+pub struct EditorComparison {
+    pub build: ComparisonBuild,
+    /// Set once the other build's copy has been read and diffed.
+    pub diff: Arc<Mutex<Option<Arc<LayoutJob>>>>,
 }
 
 #[derive(Clone)]
@@ -40,13 +142,147 @@ pub struct SearchData {
     pub tab_title: String,
     pub id: SearchId,
     pub results: Vec<SearchResult>,
+    /// The query's regex, to highlight its matches in the results. `None` for binary
+    /// searches.
+    pub matcher: Option<Regex>,
+    pub progress: SearchProgress,
+    /// Set once the search stops.
+    pub end: Option<SearchEnd>,
+    pub view: TabViewState,
 }
 
 #[derive(Clone)]
 pub struct DiffData {
     pub modified: Vec<diff::DiffResult>,
-    pub modified_filtered: Option<Vec<diff::DiffResult>>,
-    pub path_filter: String,
+    /// The results matching the view's path filter, grouped by directory.
+    pub tree: diff::DiffTree,
+    /// Includes the path filter `tree` was built from.
+    pub view: TabViewState,
+    /// The modified build's archives, which permalinks to diffed files point into.
+    pub pak_set: PakSetHash,
+    /// Show text configs as the entries which changed instead of as a line diff.
+    pub structural: bool,
+    /// What line diffs in this tab leave out.
+    pub options: diff::DiffOptions,
+    /// Set when the user asks to move this tab into its own window.
+    pub pop_out_requested: bool,
+}
+
+impl EditorData {
+    /// Drops large contents which haven't been looked at recently. Contents opened from
+    /// archives other than the loaded ones (`pak_set`) are kept, since they can't be read
+    /// again.
+    pub fn evict_if_idle(&mut self, now: f64, pak_set: PakSetHash) {
+        let last_viewed = *self.last_viewed.get_or_insert(now);
+        let EditorContents::Loaded(contents) = &self.contents else {
+            return;
+        };
+        if now - last_viewed >= EVICT_AFTER_SECS
+            && contents.len() >= MIN_EVICTED_LEN
+            && self.pak_set == pak_set
+        {
+            self.contents = EditorContents::Evicted;
+        }
+    }
+}
+
+/// Every archive containing a path, highest priority first.
+#[derive(Clone)]
+pub struct ProvidersData {
+    pub title: String,
+    pub path: String,
+    pub providers: Vec<Provider>,
+}
+
+/// A config value tracked across the loaded builds.
+#[derive(Clone)]
+pub struct HistoryData {
+    pub title: String,
+    pub history: ValueHistory,
+}
+
+/// A binary file, shown as offset/hex/ASCII rows.
+#[derive(Clone)]
+pub struct HexViewData {
+    pub title: String,
+    pub opened_file: VfsPath,
+    pub data: Arc<[u8]>,
+    /// Archives loaded when the file was opened.
+    pub pak_set: PakSetHash,
+}
+
+/// A decoded texture.
+#[derive(Clone)]
+pub struct ImagePreviewData {
+    pub title: String,
+    pub opened_file: VfsPath,
+    /// The texture's format and size, from [`crate::texture::DecodedTexture`].
+    pub description: String,
+    /// Decoded pixels, until they're uploaded as `texture` the first time the tab is shown.
+    pub image: Option<Arc<egui::ColorImage>>,
+    pub texture: Option<egui::TextureHandle>,
+    /// Scale the texture to fit the tab, rather than showing it at its own size.
+    pub fit: bool,
+    /// Archives loaded when the file was opened.
+    pub pak_set: PakSetHash,
+}
+
+/// An audio file, decoded in the background while its waveform fills in.
+#[derive(Clone)]
+pub struct AudioPreviewData {
+    pub title: String,
+    pub opened_file: VfsPath,
+    /// Set once decoding has read the file's header.
+    pub format: Option<AudioFormat>,
+    /// The waveform decoded so far.
+    pub peaks: Vec<Peak>,
+    /// How much of the file has been read, from 0 to 1.
+    pub progress: f32,
+    /// The decoded sound, once decoding finishes.
+    pub clip: Option<AudioClip>,
+    /// Decoding stopped at [`crate::audio::MAX_SAMPLES`], before the end of the file.
+    pub truncated: bool,
+    pub error: Option<String>,
+    /// Stops decoding, while the file is still being decoded.
+    pub decoding: Option<CancellationToken>,
+    /// Archives loaded when the file was opened.
+    pub pak_set: PakSetHash,
+}
+
+/// The report produced by an analysis.
+#[derive(Clone)]
+pub struct AnalysisData {
+    pub title: String,
+    pub report: Report,
+}
+
+impl DiffData {
+    pub fn new(modified: Vec<diff::DiffResult>, pak_set: PakSetHash) -> Self {
+        Self {
+            tree: diff::DiffTree::new(modified.iter().cloned()),
+            modified,
+            view: Default::default(),
+            pak_set,
+            structural: false,
+            options: Default::default(),
+            pop_out_requested: false,
+        }
+    }
+
+    /// Rebuilds `tree` from the results matching the view's path filter.
+    pub fn apply_path_filter(&mut self) {
+        let filter = &self.view.path_filter;
+        let matching = self.modified.iter().filter(|diff| {
+            filter.is_empty() || fuzzy_score(filter, diff.comparison_path()).is_some()
+        });
+        self.tree = diff::DiffTree::new(matching.cloned());
+    }
+}
+
+#[derive(Clone)]
+pub struct Provider {
+    pub archive: String,
+    pub size: u64,
 }
 
 impl TabKind {
@@ -55,167 +291,1009 @@ impl TabKind {
             TabKind::Editor(data) => data.title.as_str(),
             TabKind::SearchResults(data) => data.tab_title.as_str(),
             TabKind::Diff(_results) => "Diff",
+            TabKind::Providers(data) => data.title.as_str(),
+            TabKind::Analysis(data) => data.title.as_str(),
+            TabKind::History(data) => data.title.as_str(),
+            TabKind::HexView(data) => data.title.as_str(),
+            TabKind::ImagePreview(data) => data.title.as_str(),
+            TabKind::AudioPreview(data) => data.title.as_str(),
+        }
+    }
+
+    /// Identifies the tab's view state in [`SavedViews`], for tabs that have one. Reopening
+    /// the same search or diffing against the same build gets the same key.
+    pub fn view_key(&self) -> Option<String> {
+        match self {
+            TabKind::SearchResults(data) => Some(format!("search/{}", data.query)),
+            TabKind::Diff(data) => Some(format!("diff/{}", data.pak_set)),
+            TabKind::Editor(_)
+            | TabKind::Providers(_)
+            | TabKind::Analysis(_)
+            | TabKind::History(_)
+            | TabKind::HexView(_)
+            | TabKind::ImagePreview(_)
+            | TabKind::AudioPreview(_) => None,
+        }
+    }
+
+    /// Picks up the view state saved for this tab's key, if there is one.
+    pub fn restore_view(&mut self, saved_views: &SavedViews) {
+        let Some(view) = self.view_key().and_then(|key| saved_views.get(&key).cloned()) else {
+            return;
+        };
+        match self {
+            TabKind::SearchResults(data) => data.view = view,
+            TabKind::Diff(data) => {
+                data.view = view;
+                data.apply_path_filter();
+            }
+            TabKind::Editor(_)
+            | TabKind::Providers(_)
+            | TabKind::Analysis(_)
+            | TabKind::History(_)
+            | TabKind::HexView(_)
+            | TabKind::ImagePreview(_)
+            | TabKind::AudioPreview(_) => {}
+        }
+    }
+
+    /// Whether the tab asked to be moved into its own window, clearing the request.
+    pub fn take_pop_out_request(&mut self) -> bool {
+        match self {
+            TabKind::Editor(data) => std::mem::take(&mut data.pop_out_requested),
+            TabKind::Diff(data) => std::mem::take(&mut data.pop_out_requested),
+            TabKind::SearchResults(_)
+            | TabKind::Providers(_)
+            | TabKind::Analysis(_)
+            | TabKind::History(_)
+            | TabKind::HexView(_)
+            | TabKind::ImagePreview(_)
+            | TabKind::AudioPreview(_) => false,
         }
     }
 }
+
+impl Subscriber for TabKind {
+    fn on_event(&mut self, event: &WorkspaceEvent) {
+        match (self, event) {
+            (TabKind::Editor(data), WorkspaceEvent::WorkspaceLoaded { pak_set }) => {
+                data.outdated = data.pak_set != *pak_set;
+            }
+            (
+                TabKind::Editor(data),
+                WorkspaceEvent::ContentsReloaded { path, pak_set, contents },
+            ) if matches!(data.contents, EditorContents::Reloading)
+                && data.opened_file.as_str() == path =>
+            {
+                data.contents = EditorContents::Loaded(contents.clone());
+                data.pak_set = *pak_set;
+                data.outdated = false;
+            }
+            (TabKind::SearchResults(data), WorkspaceEvent::SearchResult(id, result))
+                if data.id == *id =>
+            {
+                data.results.push(result.clone());
+            }
+            (TabKind::SearchResults(data), WorkspaceEvent::SearchProgress(id, progress))
+                if data.id == *id && data.end.is_none() =>
+            {
+                data.progress = *progress;
+            }
+            (TabKind::SearchResults(data), WorkspaceEvent::SearchComplete(id, progress, end))
+                if data.id == *id =>
+            {
+                data.progress = *progress;
+                data.end = Some(*end);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A tab shown in its own viewport instead of the dock.
+pub struct DetachedTab {
+    pub viewport_id: egui::ViewportId,
+    pub tab: TabKind,
+}
+
 pub struct ToolsTabViewer<'a> {
     pub app_internal_data: &'a mut AppInternalData,
+    pub saved_views: &'a mut SavedViews,
 }
 
 impl ToolsTabViewer<'_> {
-    fn build_editor_tab(&self, editor: &mut EditorData, ui: &mut Ui) {
+    /// Draws a tab's contents without any dock-specific controls.
+    pub fn show_tab_contents(&mut self, ui: &mut Ui, tab: &mut TabKind) {
+        let view_key = tab.view_key();
+        let key = view_key.as_deref().unwrap_or_default();
+        match tab {
+            TabKind::Editor(editor_data) => {
+                self.build_editor_tab(editor_data, ui);
+            }
+            TabKind::SearchResults(search_data) => {
+                self.build_search_results_tab(search_data, key, ui);
+            }
+            TabKind::Diff(diff_data) => {
+                self.build_diff_tab(diff_data, key, ui);
+            }
+            TabKind::Providers(providers_data) => {
+                self.build_providers_tab(providers_data, ui);
+            }
+            TabKind::Analysis(data) => {
+                self.build_analysis_tab(data, ui);
+            }
+            TabKind::History(data) => {
+                self.build_history_tab(data, ui);
+            }
+            TabKind::HexView(data) => {
+                self.build_hex_view_tab(data, ui);
+            }
+            TabKind::ImagePreview(data) => {
+                self.build_image_preview_tab(data, ui);
+            }
+            TabKind::AudioPreview(data) => {
+                self.build_audio_preview_tab(data, ui);
+            }
+        }
+
+        let view = match tab {
+            TabKind::SearchResults(data) => Some(&data.view),
+            TabKind::Diff(data) => Some(&data.view),
+            TabKind::Editor(_)
+            | TabKind::Providers(_)
+            | TabKind::Analysis(_)
+            | TabKind::History(_)
+            | TabKind::HexView(_)
+            | TabKind::ImagePreview(_)
+            | TabKind::AudioPreview(_) => None,
+        };
+        if let (Some(view), Some(key)) = (view, &view_key) {
+            self.saved_views.remember(key, view);
+        }
+    }
+
+    fn code_editor(editor: &EditorData) -> CodeEditor {
         CodeEditor::default()
             .id_source(format!("{}_code_editor", &editor.title))
             .with_rows(12)
             .with_fontsize(14.0)
             .with_theme(ColorTheme::GRUVBOX)
-            .with_syntax(Syntax::rust())
+            .with_syntax(syntax_for(editor.opened_file.as_str()))
             .with_numlines(true)
             .vscroll(true)
             .auto_shrink(false)
-            .show(ui, &mut &*editor.contents);
     }
 
-    fn build_search_results_tab(&self, search_data: &SearchData, ui: &mut Ui) {
-        ui.vertical(|ui| {
-            for file_result in &search_data.results {
-                let id = ui.make_persistent_id(file_result.file.as_str());
-
-                egui::collapsing_header::CollapsingState::load_with_default_open(
-                    ui.ctx(),
-                    id,
-                    true,
-                )
-                .show_header(ui, |ui| {
-                    ui.label(file_result.file.as_str());
-                    if ui.button("Open").clicked()
-                        && let Some(overlay_fs) = self.app_internal_data.overlay_fs.as_ref()
-                    {
-                        let _ = self.app_internal_data.inbox.sender().send(
-                            crate::task::BackgroundTaskMessage::RequestOpenFile(
-                                overlay_fs
-                                    .join(file_result.file.as_str())
-                                    .expect("failed to map async file to sync file"),
-                            ),
-                        );
+    fn build_editor_tab(&self, editor: &mut EditorData, ui: &mut Ui) {
+        editor.last_viewed = Some(ui.input(|input| input.time));
+        let contents = match &editor.contents {
+            EditorContents::Loaded(contents) => contents,
+            EditorContents::Evicted | EditorContents::Reloading => {
+                if matches!(editor.contents, EditorContents::Evicted) {
+                    self.reload_contents(editor);
+                }
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Reloading...");
+                });
+                return;
+            }
+        };
+
+        ui.horizontal(|ui| {
+            if ui.small_button("Export HTML").on_hover_text(EXPORT_HTML_HINT).clicked() {
+                let job = highlight(ui.ctx(), &Self::code_editor(editor), contents);
+                let html = html::text_document(&editor.title, &job);
+                execute(save_export(
+                    "Export HTML".to_string(),
+                    format!("{}.html", editor.opened_file.filename()),
+                    html.into_bytes(),
+                ));
+            }
+
+            let line = editor.cursor_line.or(editor.linked_line);
+            self.copy_link_button(ui, editor.opened_file.as_str(), line);
+            #[cfg(target_arch = "wasm32")]
+            self.copy_share_link_button(ui, editor.opened_file.as_str(), line);
+            if let Some(line) = editor.linked_line {
+                ui.weak(format!("Linked to line {line}"));
+            }
+            if editor.outdated {
+                ui.colored_label(
+                    Color32::YELLOW,
+                    "Opened from archives which are no longer loaded",
+                );
+            }
+            self.comparison_picker(
+                ui,
+                &mut editor.comparison,
+                editor.opened_file.as_str(),
+                contents,
+            );
+            if is_text_config(editor.opened_file.as_str()) {
+                Self::structure_toggle(ui, &mut editor.structured, contents);
+            }
+        });
+
+        if let Some(comparison) = &editor.comparison {
+            let diff = comparison.diff.lock().unwrap().clone();
+            if let Some(diff) = diff {
+                egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| ui.label(diff));
+            } else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Reading {}...", comparison.build.name));
+                });
+            }
+            return;
+        }
+
+        if let Some(structured) = &mut editor.structured {
+            let rules = &self.app_internal_data.value_rules;
+            let convert = structured.convert_units;
+            let scroll_area = egui::ScrollArea::both()
+                .id_salt(("structured", editor.opened_file.as_str()))
+                .auto_shrink(false);
+            match (structured.entries.as_ref(), &mut structured.tables) {
+                (Ok(_), Some(tables)) => {
+                    let file_name = editor.opened_file.filename();
+                    scroll_area.show(ui, |ui| {
+                        config_viewer::show_tables(ui, &file_name, tables, rules, convert);
+                    });
+                }
+                (Ok(entries), None) => {
+                    let builds = &self.app_internal_data.comparison_builds;
+                    let mut view =
+                        EntryView { rules, convert, can_track: !builds.is_empty(), tracked: None };
+                    scroll_area.show(ui, |ui| {
+                        config_viewer::show_entries(ui, entries, &mut view, &mut Vec::new());
+                    });
+                    if let Some(entry_path) = view.tracked {
+                        execute(history::track_value(
+                            editor.opened_file.as_str().to_string(),
+                            entry_path,
+                            contents.clone(),
+                            builds.clone(),
+                            self.app_internal_data.inbox.sender(),
+                        ));
                     }
-                })
-                .body(|ui| {
-                    for (num, (LineNumber(line_num), file_match)) in
-                        file_result.matches.iter().enumerate()
+                }
+                (Err(e), _) => {
+                    ui.colored_label(Color32::RED, format!("Couldn't read the config: {e}"));
+                }
+            }
+            return;
+        }
+
+        let output = Self::code_editor(editor).show(ui, &mut contents.as_str());
+        if let Some(cursor) = output.cursor_range {
+            editor.cursor_line = Some(permalink::line_at(contents, cursor.primary.index));
+        }
+    }
+
+    /// Switches between showing `contents`, a text config, as text and as a tree of entries.
+    fn structure_toggle(ui: &mut Ui, structured: &mut Option<StructuredConfig>, contents: &str) {
+        let mut enabled = structured.is_some();
+        if ui
+            .checkbox(&mut enabled, "Structured")
+            .on_hover_text("Show the config's entries as a tree, with values in their units")
+            .changed()
+        {
+            *structured = enabled.then(|| StructuredConfig {
+                entries: Arc::new(cfg_parser::text::parse(contents)),
+                convert_units: false,
+                tables: None,
+            });
+        }
+
+        let Some(structured) = structured else {
+            return;
+        };
+        ui.checkbox(&mut structured.convert_units, "Convert Units").on_hover_text(
+            "Show degrees as radians, seconds as milliseconds, and the other way around",
+        );
+        if let Ok(entries) = structured.entries.as_ref() {
+            let mut tables = structured.tables.is_some();
+            if ui
+                .checkbox(&mut tables, "Tables")
+                .on_hover_text("Show blocks of the same class side by side as sortable tables")
+                .changed()
+            {
+                structured.tables = tables.then(|| {
+                    cfg_parser::table::find_tables(entries)
+                        .into_iter()
+                        .map(|table| ConfigTable { table, sorted_by: None })
+                        .collect()
+                });
+            }
+        }
+    }
+
+    /// Picks another loaded build to compare `contents`, the editor's copy of `path`,
+    /// against. Builds without the file are listed but can't be picked.
+    fn comparison_picker(
+        &self,
+        ui: &mut Ui,
+        comparison: &mut Option<EditorComparison>,
+        path: &str,
+        contents: &str,
+    ) {
+        let builds = &self.app_internal_data.comparison_builds;
+        if builds.is_empty() {
+            return;
+        }
+
+        let selected = comparison.as_ref().map_or("Nothing", |current| current.build.name.as_str());
+        // `Some(None)` when the comparison is turned off
+        let mut picked = None;
+        ui.label("Compare With");
+        egui::ComboBox::from_id_salt(("compare_with", path)).selected_text(selected).show_ui(
+            ui,
+            |ui| {
+                if ui.selectable_label(comparison.is_none(), "Nothing").clicked() {
+                    picked = Some(None);
+                }
+                for build in builds {
+                    let current =
+                        comparison.as_ref().is_some_and(|current| current.build.name == build.name);
+                    let response = ui
+                        .add_enabled_ui(build.contains(path), |ui| {
+                            ui.selectable_label(current, &build.name)
+                        })
+                        .inner
+                        .on_disabled_hover_text("This build doesn't have the file");
+                    if response.clicked() && !current {
+                        picked = Some(Some(build.clone()));
+                    }
+                }
+            },
+        );
+
+        match picked {
+            Some(Some(build)) => {
+                let diff = Arc::new(Mutex::new(None));
+                execute(diff::diff_with_build(
+                    build.clone(),
+                    path.to_string(),
+                    contents.to_string(),
+                    Arc::clone(&diff),
+                ));
+                *comparison = Some(EditorComparison { build, diff });
+            }
+            Some(None) => *comparison = None,
+            None => {}
+        }
+    }
+
+    /// Asks the background loader to read an editor's evicted contents again.
+    fn reload_contents(&self, editor: &mut EditorData) {
+        let internal = &self.app_internal_data;
+        if let (Some(task_queue), Some(async_overlay_fs)) =
+            (internal.task_queue.as_ref(), internal.async_overlay_fs.clone())
+        {
+            let _ = task_queue.send(BackgroundTask::LoadFileData(
+                editor.opened_file.clone(),
+                async_overlay_fs,
+                FileLoad::Reload,
+            ));
+            editor.contents = EditorContents::Reloading;
+        }
+    }
+
+    /// A button which copies an `enfpak://` link to `path` in the loaded archives.
+    fn copy_link_button(&self, ui: &mut Ui, path: &str, line: Option<usize>) {
+        copy_link_button(ui, || self.app_internal_data.permalink(path, line));
+    }
+
+    /// A button which copies a link to this page that fetches the same archives and opens
+    /// `path`. Nothing is shown unless the archives were fetched from URLs.
+    #[cfg(target_arch = "wasm32")]
+    fn copy_share_link_button(&self, ui: &mut Ui, path: &str, line: Option<usize>) {
+        let Some(link) = self.app_internal_data.share_link(path, line) else {
+            return;
+        };
+        if ui
+            .small_button("Copy Share Link")
+            .on_hover_text("Copy a link which opens this file from the same hosted archives")
+            .clicked()
+        {
+            ui.ctx().copy_text(share::link_url(&link));
+        }
+    }
+
+    fn build_search_results_tab(&self, search_data: &mut SearchData, key: &str, ui: &mut Ui) {
+        let SearchData { id: search_id, results, matcher, progress, end, view, .. } = search_data;
+        ui.horizontal(|ui| {
+            let totals = format!(
+                "{} match(es) in {} file(s), {} file(s) searched in {:.1}s",
+                progress.matches,
+                results.len(),
+                progress.files_scanned,
+                progress.elapsed.as_secs_f32()
+            );
+            match end {
+                None => {
+                    ui.spinner();
+                    ui.label(format!("Searching: {totals}"));
+                    if ui.button("Stop").clicked()
+                        && let Some(task_queue) = &self.app_internal_data.task_queue
                     {
-                        CodeEditor::default()
-                            .id_source(format!("search_{}_result_{}", search_data.id.0, num))
-                            .with_rows(file_match.lines().count())
-                            .with_fontsize(14.0)
-                            .with_theme(ColorTheme::GRUVBOX)
-                            .with_syntax(Syntax::rust())
-                            .with_numlines(true)
-                            .with_numlines_shift(
-                                (line_num - 1).try_into().expect("invalid line num shift"),
-                            )
-                            .vscroll(false)
-                            .auto_shrink(false)
-                            .show(ui, &mut file_match.as_str());
-
-                        ui.separator();
+                        let _ = task_queue.send(BackgroundTask::CancelSearch);
                     }
-                });
+                }
+                Some(SearchEnd::Finished) => {
+                    ui.label(format!("Search finished: {totals}"));
+                }
+                Some(SearchEnd::LimitReached) => {
+                    ui.colored_label(
+                        Color32::ORANGE,
+                        format!("Search stopped at the maximum number of results: {totals}"),
+                    );
+                }
+                Some(SearchEnd::Cancelled) => {
+                    ui.colored_label(Color32::LIGHT_RED, format!("Search cancelled: {totals}"));
+                }
+            }
+        });
+        ui.separator();
+
+        let matcher = matcher.as_ref();
+        view.scroll_area(ui, key, |ui, view| {
+            for file_result in results.iter() {
+                let file = file_result.file.as_str();
+                view.group(ui, key, file, true)
+                    .show_header(ui, |ui| {
+                        ui.label(format!("{} {file}", entry_type_icon(detect_type(file, &[]))));
+                        if ui.button("Open").clicked()
+                            && let Some(overlay_fs) = self.app_internal_data.overlay_fs.as_ref()
+                        {
+                            let _ = self.app_internal_data.inbox.sender().send(
+                                crate::task::BackgroundTaskMessage::RequestOpenFile(
+                                    overlay_fs
+                                        .join(file)
+                                        .expect("failed to map async file to sync file"),
+                                ),
+                            );
+                        }
+                        self.copy_link_button(ui, file, None);
+                    })
+                    .body(|ui| {
+                        let lines = match &file_result.matches {
+                            SearchMatches::Lines(lines) => lines,
+                            SearchMatches::Offsets(offsets) => {
+                                Self::show_match_offsets(ui, offsets);
+                                return;
+                            }
+                        };
+                        for (num, (LineNumber(line_num), file_match)) in lines.iter().enumerate() {
+                            self.copy_link_button(ui, file, Some(*line_num));
+                            let output = CodeEditor::default()
+                                .id_source(format!("search_{}_result_{}", search_id.0, num))
+                                .with_rows(file_match.lines().count())
+                                .with_fontsize(14.0)
+                                .with_theme(ColorTheme::GRUVBOX)
+                                .with_syntax(syntax_for(file))
+                                .with_numlines(true)
+                                .with_numlines_shift(
+                                    (line_num - 1).try_into().expect("invalid line num shift"),
+                                )
+                                .vscroll(false)
+                                .auto_shrink(false)
+                                .show(ui, &mut file_match.as_str());
+                            if let Some(matcher) = matcher {
+                                highlight_matches(ui, &output, file_match, matcher);
+                            }
+
+                            ui.separator();
+                        }
+                    });
+            }
+        });
+    }
+
+    /// Lists a binary search's matches in one file.
+    fn show_match_offsets(ui: &mut Ui, offsets: &[u64]) {
+        if offsets.len() == MAX_MATCHES_PER_FILE {
+            ui.weak(format!("Showing the first {MAX_MATCHES_PER_FILE} matches"));
+        }
+        ui.horizontal_wrapped(|ui| {
+            for offset in offsets {
+                ui.monospace(format!("{offset:#010X}")).on_hover_text(format!("Offset {offset}"));
             }
         });
     }
 
-    fn build_diff_tab(&self, diff_data: &mut DiffData, ui: &mut Ui) {
+    fn build_providers_tab(&self, providers_data: &ProvidersData, ui: &mut Ui) {
+        ui.vertical(|ui| {
+            ui.label(&providers_data.path);
+            ui.label(format!(
+                "{} archive(s) contain this file. The first one listed is used; the rest are \
+                 shadowed by it.",
+                providers_data.providers.len()
+            ));
+            ui.separator();
+
+            egui::Grid::new(format!("{}_providers", providers_data.path)).striped(true).show(
+                ui,
+                |ui| {
+                    ui.strong("Priority");
+                    ui.strong("Archive");
+                    ui.strong("Size");
+                    ui.strong("Status");
+                    ui.end_row();
+
+                    for (priority, provider) in providers_data.providers.iter().enumerate() {
+                        ui.label((priority + 1).to_string());
+                        ui.label(&provider.archive);
+                        ui.label(humansize::format_size(provider.size, humansize::BINARY));
+                        if priority == 0 {
+                            ui.colored_label(Color32::LIGHT_GREEN, "used");
+                        } else {
+                            ui.weak("shadowed");
+                        }
+                        ui.end_row();
+                    }
+                },
+            );
+        });
+    }
+
+    fn build_analysis_tab(&self, data: &AnalysisData, ui: &mut Ui) {
+        let report = &data.report;
         ui.vertical(|ui| {
             ui.horizontal(|ui| {
-                ui.label("Path Filter:");
-                if ui.text_edit_singleline(&mut diff_data.path_filter).changed() {
-                    diff_data.modified_filtered = Some(
-                        diff_data
-                            .modified
-                            .iter()
-                            .filter(|diff| {
-                                task::ascii_icontains(
-                                    &diff_data.path_filter,
-                                    diff.comparison_path(),
-                                )
-                            })
-                            .cloned()
-                            .collect(),
-                    );
+                ui.label(&report.summary);
+                for export in &report.exports {
+                    if ui.button(&export.label).clicked() {
+                        let export = export.clone();
+                        execute(save_export(export.label, export.file_name, export.data));
+                    }
                 }
             });
-            let modified = if let Some(filtered) = &diff_data.modified_filtered {
-                filtered
-            } else {
-                &diff_data.modified
-            };
-            for result in modified {
-                let mut heading = LayoutJob::default();
-                match result {
-                    DiffResult::Added { path, overlay, data } => {
-                        heading.append(
-                            path.as_str(),
-                            0.0,
-                            TextFormat { color: Color32::LIGHT_GREEN, ..Default::default() },
-                        );
-
-                        ui.collapsing(heading, |ui| {
-                            let data_inner = data.lock().unwrap();
-                            if let Some(data_inner) = &*data_inner {
-                                ui.label(Arc::clone(data_inner));
-                            } else {
-                                let added_file = overlay.join(path.as_str()).unwrap();
-                                let output = Arc::clone(data);
-                                execute(async move {
-                                    if let Some(data) = task::read_file_data(added_file)
-                                        .await
-                                        .and_then(|data| String::from_utf8(data).ok())
-                                    {
-                                        let mut job = LayoutJob::default();
-                                        job.append(data.as_str(), 0.0, Default::default());
-                                        *output.lock().unwrap() = Some(job.into());
-                                    } else {
-                                        *output.lock().unwrap() = Some(LayoutJob::default().into());
-                                    }
-                                });
+            ui.separator();
+
+            for (idx, section) in report.sections.iter().enumerate() {
+                egui::CollapsingHeader::new(format!(
+                    "{} ({})",
+                    section.heading,
+                    section.items.len()
+                ))
+                .id_salt((&data.title, idx))
+                .default_open(report.sections.len() <= 5)
+                .show(ui, |ui| {
+                    for item in &section.items {
+                        ui.horizontal(|ui| {
+                            if let Some(file) = &item.file {
+                                self.open_button(ui, file);
                             }
-                        });
-                    }
-                    DiffResult::Changed {
-                        base_path,
-                        base_overlay,
-                        modified_path,
-                        modified_overlay,
-                        data,
-                    } => {
-                        heading.append(
-                            base_path.as_str(),
-                            0.0,
-                            TextFormat { color: Color32::ORANGE, ..Default::default() },
-                        );
-
-                        ui.collapsing(heading, |ui| {
-                            let data_inner = data.lock().unwrap();
-                            if let Some(data_inner) = &*data_inner {
-                                ui.label(Arc::clone(data_inner));
-                            } else {
-                                let base = base_overlay.join(base_path.as_str()).unwrap();
-                                let modified =
-                                    modified_overlay.join(modified_path.as_str()).unwrap();
-                                let output = Arc::clone(data);
-                                execute(async move {
-                                    diff::build_file_diff(base, modified, output).await;
-                                });
+                            if let Some(line) = item.line {
+                                ui.weak(format!("line {line}:"));
                             }
+                            ui.label(&item.text);
                         });
+                        if let Some(detail) = &item.detail {
+                            ui.monospace(detail);
+                        }
                     }
+                });
+            }
+        });
+    }
+
+    fn build_hex_view_tab(&self, data: &HexViewData, ui: &mut Ui) {
+        ui.label(format!("{} ({} bytes)", data.opened_file.as_str(), data.data.len()));
+        ui.separator();
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let rows = data.data.len().div_ceil(HEX_ROW_LEN);
+        egui::ScrollArea::both()
+            .id_salt(("hex_view", data.opened_file.as_str()))
+            .auto_shrink(false)
+            .show_rows(ui, row_height, rows, |ui, rows| {
+                for row in rows {
+                    let text = egui::RichText::new(hex_row(&data.data, row)).monospace();
+                    ui.add(egui::Label::new(text).extend());
+                }
+            });
+    }
+
+    fn build_image_preview_tab(&self, data: &mut ImagePreviewData, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} ({})", data.opened_file.as_str(), data.description));
+            ui.checkbox(&mut data.fit, "Fit to Tab");
+        });
+        ui.separator();
+
+        if let Some(image) = data.image.take() {
+            let name = data.opened_file.as_str();
+            let options = egui::TextureOptions::LINEAR;
+            data.texture = Some(ui.ctx().load_texture(name, image, options));
+        }
+        let Some(texture) = &data.texture else {
+            return;
+        };
+
+        let image = egui::Image::new(egui::load::SizedTexture::from_handle(texture));
+        if data.fit {
+            ui.add(image.max_size(ui.available_size()));
+        } else {
+            egui::ScrollArea::both()
+                .id_salt(("image_preview", data.opened_file.as_str()))
+                .auto_shrink(false)
+                .show(ui, |ui| ui.add(image.fit_to_original_size(1.0)));
+        }
+    }
+
+    fn build_audio_preview_tab(&mut self, data: &mut AudioPreviewData, ui: &mut Ui) {
+        let path = data.opened_file.as_str();
+        ui.horizontal(|ui| {
+            ui.label(path);
+            if let Some(format) = data.format {
+                ui.weak(format.to_string());
+            }
+            if let Some(clip) = &data.clip {
+                ui.weak(audio::format_time(clip.duration()));
+            }
+        });
+        if let Some(error) = &data.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        if data.truncated {
+            ui.weak("Only the start of this file was decoded, as it's too long to keep in memory");
+        }
+        ui.separator();
+
+        let player = &mut self.app_internal_data.audio_player;
+        if player.as_ref().is_some_and(Player::finished) {
+            *player = None;
+        }
+        let playing = player.as_ref().filter(|player| player.path == path);
+        let position = playing.map(Player::position);
+        let paused = playing.is_some_and(Player::is_paused);
+
+        enum Action {
+            Play(usize),
+            TogglePause,
+            Stop,
+        }
+        let mut action = None;
+        ui.horizontal(|ui| {
+            let label = if position.is_none() || paused { "Play" } else { "Pause" };
+            if ui.add_enabled(data.clip.is_some(), egui::Button::new(label)).clicked() {
+                action =
+                    Some(if position.is_some() { Action::TogglePause } else { Action::Play(0) });
+            }
+            if ui.add_enabled(position.is_some(), egui::Button::new("Stop")).clicked() {
+                action = Some(Action::Stop);
+            }
+            if data.decoding.is_some() {
+                let progress = egui::ProgressBar::new(data.progress).desired_width(200.0);
+                ui.add(progress.text("Decoding"));
+            }
+            if let (Some(position), Some(clip)) = (position, &data.clip) {
+                let time = audio::format_time(audio::frame_time(position, clip.format));
+                ui.monospace(format!("{time} / {}", audio::format_time(clip.duration())));
+            }
+        });
+
+        let size = egui::vec2(ui.available_width(), WAVEFORM_HEIGHT);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::click());
+        let rect = response.rect;
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        paint_waveform(&painter, rect, &data.peaks, ui.visuals().selection.bg_fill);
+        if let (Some(position), Some(clip)) = (position, &data.clip) {
+            let x = rect.left() + rect.width() * position as f32 / clip.frames().max(1) as f32;
+            let stroke = egui::Stroke::new(2.0, ui.visuals().warn_fg_color);
+            painter.vline(x, rect.y_range(), stroke);
+        }
+        if let (Some(clip), Some(pointer)) = (&data.clip, response.interact_pointer_pos())
+            && response.clicked()
+        {
+            let fraction = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            action = Some(Action::Play((fraction * clip.frames() as f32) as usize));
+        }
+        if data.clip.is_some() {
+            response.on_hover_text("Click to play from a point");
+        }
+
+        match (action, &data.clip) {
+            (Some(Action::Play(frame)), Some(clip)) => {
+                match Player::play(path.to_string(), clip, frame) {
+                    Ok(new_player) => *player = Some(new_player),
+                    Err(e) => data.error = Some(format!("Couldn't play audio: {e}")),
+                }
+            }
+            (Some(Action::TogglePause), _) => {
+                if let Some(player) = player {
+                    player.toggle_pause();
+                }
+            }
+            (Some(Action::Stop), _) => *player = None,
+            _ => {}
+        }
+        if position.is_some() && !paused {
+            // Keeps the playhead moving
+            ui.ctx().request_repaint_after(std::time::Duration::from_millis(30));
+        }
+    }
+
+    fn build_history_tab(&self, data: &HistoryData, ui: &mut Ui) {
+        let history = &data.history;
+        ui.horizontal(|ui| {
+            ui.label(format!("{} in {}", history.entry_path.join(" > "), history.file));
+            self.open_button(ui, &history.file);
+        });
+        ui.separator();
+
+        history::show_chart(ui, history);
+        ui.separator();
+
+        egui::Grid::new(("history", &data.title)).striped(true).show(ui, |ui| {
+            ui.strong("Build");
+            ui.strong(history.property());
+            ui.end_row();
+
+            for point in &history.points {
+                ui.label(&point.build);
+                match &point.value {
+                    Some(value) => ui.label(value),
+                    None => ui.weak("not set"),
+                };
+                ui.end_row();
+            }
+        });
+    }
+
+    /// A button which opens `file` from the overlay in an editor tab.
+    fn open_button(&self, ui: &mut Ui, file: &str) {
+        if ui.button("Open").clicked()
+            && let Some(overlay_fs) = self.app_internal_data.overlay_fs.as_ref()
+            && let Ok(path) = overlay_fs.join(file)
+        {
+            let _ = self
+                .app_internal_data
+                .inbox
+                .sender()
+                .send(crate::task::BackgroundTaskMessage::RequestOpenFile(path));
+        }
+    }
+
+    fn build_diff_tab(&self, diff_data: &mut DiffData, key: &str, ui: &mut Ui) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                if ui.small_button("Export HTML").on_hover_text(EXPORT_HTML_HINT).clicked() {
+                    let results = diff_data.tree.results();
+                    let options = diff_data.options;
+                    execute(async move {
+                        let html = html::diff_document("Diff", &results, options).await;
+                        save_export(
+                            "Export HTML".to_string(),
+                            "diff.html".to_string(),
+                            html.into_bytes(),
+                        )
+                        .await;
+                    });
+                }
+                ui.label("Path Filter:");
+                if ui.text_edit_singleline(&mut diff_data.view.path_filter).changed() {
+                    diff_data.apply_path_filter();
                 }
+                ui.checkbox(&mut diff_data.structural, "Structural").on_hover_text(
+                    "Show changed configs and prefabs as the entries which were added, removed \
+                     or changed instead of as a line diff",
+                );
+                ui.checkbox(&mut diff_data.options.ignore_whitespace, "Ignore Whitespace")
+                    .on_hover_text("Hide changes to indentation, spacing and blank lines");
+                ui.checkbox(&mut diff_data.options.ignore_comments, "Ignore Comments")
+                    .on_hover_text("Hide changes to scripts which only touch their comments");
+            });
+
+            let DiffData { tree, view, pak_set, structural, options, .. } = diff_data;
+            let options = DiffFolderOptions {
+                pak_set: *pak_set,
+                filtering: !view.path_filter.is_empty(),
+                structural: *structural,
+                diff: *options,
+                palette: settings::palette(),
+            };
+            view.scroll_area(ui, key, |ui, view| {
+                show_diff_folder(ui, view, key, tree, options, 0);
+            });
+        });
+    }
+}
+
+/// Shows a diff folder's subfolders as rollups with their result counts, then its files.
+/// Top level folders start open, as does everything while the results are filtered.
+fn show_diff_folder(
+    ui: &mut Ui,
+    view: &mut TabViewState,
+    key: &str,
+    folder: &diff::DiffTree,
+    options: DiffFolderOptions,
+    depth: usize,
+) {
+    for subfolder in folder.folders.values() {
+        let mut heading = LayoutJob::default();
+        heading.append(&format!("{}/", subfolder.name), 0.0, TextFormat::default());
+        let diff::DiffCounts { added, removed, changed } = subfolder.counts;
+        if added > 0 {
+            let format = TextFormat { color: options.palette.added, ..Default::default() };
+            heading.append(&format!("{added} added"), 8.0, format);
+        }
+        if removed > 0 {
+            let format = TextFormat { color: options.palette.removed, ..Default::default() };
+            heading.append(&format!("{removed} removed"), 8.0, format);
+        }
+        if changed > 0 {
+            let format = TextFormat { color: options.palette.changed, ..Default::default() };
+            heading.append(&format!("{changed} changed"), 8.0, format);
+        }
+
+        view.group(ui, key, &subfolder.path, depth == 0 || options.filtering)
+            .show_header(ui, |ui| ui.label(heading))
+            .body(|ui| show_diff_folder(ui, view, key, subfolder, options, depth + 1));
+    }
+
+    let pak_set = options.pak_set;
+    for result in &folder.files {
+        let path = result.comparison_path();
+        let name = path.rsplit_once('/').map_or(path, |(_, name)| name);
+        let mut heading = LayoutJob::default();
+        heading.append(name, 0.0, TextFormat { color: result.color(), ..Default::default() });
+
+        view.group(ui, key, path, false).show_header(ui, |ui| ui.label(heading)).body(|ui| {
+            // Links open files in the modified build, which removed files aren't in
+            if !matches!(result, diff::DiffResult::Removed { .. }) {
+                copy_link_button(ui, || Permalink { pak_set, path: path.to_string(), line: None });
+            }
+            if let Some(check) = result.content_check() {
+                content_check_row(ui, result, check);
+            }
+
+            if options.structural
+                && let Some(structure) = result.structure()
+            {
+                if let Some(structure) = &*structure.lock().unwrap() {
+                    ui.label(Arc::clone(structure));
+                } else {
+                    let result = result.clone();
+                    execute(async move {
+                        result.render_structure().await;
+                    });
+                }
+                return;
+            }
+
+            if let Some(rendered) = result.rendered(options.diff) {
+                ui.label(rendered);
+            } else {
+                let result = result.clone();
+                let diff_options = options.diff;
+                execute(async move {
+                    result.render(diff_options).await;
+                });
             }
         });
     }
 }
 
+/// How a diff tab's folders are shown, shared by every level of [`show_diff_folder`].
+#[derive(Clone, Copy)]
+struct DiffFolderOptions {
+    /// The modified build's archives, which permalinks point into.
+    pak_set: PakSetHash,
+    /// Whether the results are narrowed by a path filter.
+    filtering: bool,
+    /// Show text configs as their changed entries.
+    structural: bool,
+    /// What line diffs leave out.
+    diff: diff::DiffOptions,
+    palette: Palette,
+}
+
+/// Draws `peaks` as a vertical line per column of `rect`, stretched to fill it.
+fn paint_waveform(painter: &egui::Painter, rect: egui::Rect, peaks: &[Peak], color: Color32) {
+    let columns = rect.width() as usize;
+    if peaks.is_empty() || columns == 0 {
+        return;
+    }
+    let to_y = |sample: i16| rect.center().y - sample as f32 / 32768.0 * rect.height() / 2.0;
+    let stroke = egui::Stroke::new(1.0, color);
+    for column in 0..columns {
+        let start = column * peaks.len() / columns;
+        let end = ((column + 1) * peaks.len() / columns).max(start + 1);
+        let (low, high) = peaks[start..end]
+            .iter()
+            .fold((i16::MAX, i16::MIN), |(low, high), [min, max]| (low.min(*min), high.max(*max)));
+        let x = rect.left() + column as f32 + 0.5;
+        painter.vline(x, egui::Rangef::new(to_y(high), to_y(low)), stroke);
+    }
+}
+
+/// Tints the matches of `matcher` in `text`, a search result shown by the code editor
+/// which returned `output`.
+fn highlight_matches(ui: &Ui, output: &TextEditOutput, text: &str, matcher: &Regex) {
+    let fill = settings::palette().search_match.gamma_multiply(0.4);
+    let offset = output.galley_pos.to_vec2();
+    // The galley's cursors count characters rather than bytes
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        // Searches match a line at a time, so anchors apply to each line
+        for found in matcher.find_iter(line.trim_end_matches(['\r', '\n'])) {
+            let start = line_start + line[..found.start()].chars().count();
+            let end = start + found.as_str().chars().count();
+            let start = output.galley.pos_from_cursor(CCursor::new(start));
+            let end = output.galley.pos_from_cursor(CCursor::new(end));
+            // Only the part of a wrapped match on its first row is tinted
+            let end_x = if end.min.y == start.min.y { end.max.x } else { start.max.x };
+            let rect = egui::Rect::from_min_max(start.min, egui::pos2(end_x, start.max.y));
+            ui.painter().rect_filled(rect.translate(offset), 2.0, fill);
+        }
+        line_start += line.chars().count();
+    }
+}
+
+/// Says whether a changed file's contents have been compared, offering to compare them if
+/// only its metadata is known to differ.
+fn content_check_row(ui: &mut Ui, result: &diff::DiffResult, check: diff::ContentCheck) {
+    match check {
+        diff::ContentCheck::Unchecked => {
+            ui.horizontal(|ui| {
+                ui.label("Metadata differs.");
+                if ui
+                    .small_button("Compare Contents")
+                    .on_hover_text("Read the file from both builds to see whether it changed")
+                    .clicked()
+                {
+                    let result = result.clone();
+                    execute(async move {
+                        result.check_contents().await;
+                    });
+                }
+            });
+        }
+        diff::ContentCheck::Checking => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Comparing contents...");
+            });
+        }
+        diff::ContentCheck::Identical => {
+            ui.label("Contents are identical; only the metadata differs.");
+        }
+        diff::ContentCheck::Different => {}
+    }
+}
+
+const EXPORT_HTML_HINT: &str =
+    "Save as a standalone HTML page, which can be printed to PDF from a browser";
+
+/// A button which copies the link made by `link` to the clipboard.
+fn copy_link_button(ui: &mut Ui, link: impl FnOnce() -> Permalink) {
+    if ui
+        .small_button("Copy Link")
+        .on_hover_text("Copy an enfpak:// link to this location")
+        .clicked()
+    {
+        ui.ctx().copy_text(link().to_string());
+    }
+}
+
+/// Asks where to save `data` and writes it there.
+pub(crate) async fn save_export(title: String, file_name: String, data: Vec<u8>) {
+    let Some(handle) =
+        rfd::AsyncFileDialog::new().set_title(&title).set_file_name(&file_name).save_file().await
+    else {
+        return;
+    };
+
+    let _write = shutdown::track_write();
+    if let Err(e) = handle.write(&data).await {
+        error!(?e, file = file_name, "failed to export file");
+    }
+}
+
 impl egui_dock::TabViewer for ToolsTabViewer<'_> {
     type Tab = TabKind;
 
@@ -223,17 +1301,49 @@ impl egui_dock::TabViewer for ToolsTabViewer<'_> {
         tab.title().into()
     }
 
-    fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
+    /// Search and diff tabs scroll themselves so they can restore their scroll position, hex
+    /// views so they only lay out the rows on screen, and image previews so they can fit the
+    /// tab.
+    fn scroll_bars(&self, tab: &Self::Tab) -> [bool; 2] {
         match tab {
-            TabKind::Editor(editor_data) => {
-                self.build_editor_tab(editor_data, ui);
-            }
-            TabKind::SearchResults(search_data) => {
-                self.build_search_results_tab(search_data, ui);
-            }
-            TabKind::Diff(diff_data) => {
-                self.build_diff_tab(diff_data, ui);
-            }
+            TabKind::SearchResults(_)
+            | TabKind::Diff(_)
+            | TabKind::HexView(_)
+            | TabKind::ImagePreview(_) => [false, false],
+            TabKind::Editor(_)
+            | TabKind::Providers(_)
+            | TabKind::Analysis(_)
+            | TabKind::History(_)
+            | TabKind::AudioPreview(_) => [true, true],
         }
     }
+
+    fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
+        let pop_out_requested = match tab {
+            TabKind::Editor(data) => Some(&mut data.pop_out_requested),
+            TabKind::Diff(data) => Some(&mut data.pop_out_requested),
+            TabKind::SearchResults(_)
+            | TabKind::Providers(_)
+            | TabKind::Analysis(_)
+            | TabKind::History(_)
+            | TabKind::HexView(_)
+            | TabKind::ImagePreview(_)
+            | TabKind::AudioPreview(_) => None,
+        };
+        if let Some(pop_out_requested) = pop_out_requested
+            && ui
+                .small_button("Pop Out")
+                .on_hover_text("Move this tab into its own window")
+                .clicked()
+        {
+            *pop_out_requested = true;
+        }
+
+        self.show_tab_contents(ui, tab);
+    }
+
+    fn on_close(&mut self, tab: &mut Self::Tab) -> egui_dock::OnCloseResponse {
+        self.app_internal_data.close_tab(tab);
+        egui_dock::OnCloseResponse::Close
+    }
 }
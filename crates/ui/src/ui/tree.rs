@@ -1,14 +1,117 @@
+use std::cell::Cell;
 use std::sync::Arc;
 
+use egui::Color32;
+use egui::FontId;
 use egui::ScrollArea;
 use egui::TextEdit;
 use egui::Widget;
+use egui::text::LayoutJob;
+use egui::text::TextFormat;
 use egui_ltreeview::NodeBuilder;
 use egui_ltreeview::TreeView;
+use enfusion_pak::entry_type::EntryType;
 
 use crate::EnfusionToolsApp;
+use crate::app::TreeNode;
+use crate::task::ArchiveLayer;
+use crate::task::FileLoad;
+use crate::task::PathFilter;
+use crate::ui::icons::entry_type_icon;
+use crate::undo::Command;
+use crate::undo::FileFilter;
+
+/// One-click extension filters shown above the file tree.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum QuickFilter {
+    Scripts,
+    Configs,
+    Layouts,
+    Textures,
+    Audio,
+}
+
+impl QuickFilter {
+    pub(crate) const ALL: [QuickFilter; 5] = [
+        QuickFilter::Scripts,
+        QuickFilter::Configs,
+        QuickFilter::Layouts,
+        QuickFilter::Textures,
+        QuickFilter::Audio,
+    ];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            QuickFilter::Scripts => "Scripts",
+            QuickFilter::Configs => "Configs",
+            QuickFilter::Layouts => "Layouts",
+            QuickFilter::Textures => "Textures",
+            QuickFilter::Audio => "Audio",
+        }
+    }
+
+    pub(crate) fn extensions(self) -> &'static [&'static str] {
+        let entry_type = match self {
+            QuickFilter::Scripts => EntryType::Script,
+            QuickFilter::Configs => EntryType::Config,
+            QuickFilter::Layouts => EntryType::Layout,
+            QuickFilter::Textures => EntryType::Texture,
+            QuickFilter::Audio => EntryType::Audio,
+        };
+
+        entry_type.extensions()
+    }
+}
 
 impl EnfusionToolsApp {
+    /// Applies the current filter text and quick filter, recording the change so it can be
+    /// undone.
+    fn apply_file_filter(&mut self) {
+        let after = FileFilter {
+            query: self.internal.file_filter.clone(),
+            quick_filter: self.internal.quick_filter,
+        };
+        if after != self.internal.applied_filter {
+            let before = std::mem::replace(&mut self.internal.applied_filter, after.clone());
+            self.internal.undo_stack.record(Command::Filter { before, after });
+        }
+
+        self.rebuild_filtered_tree();
+    }
+
+    /// Rebuilds the filtered tree from the current filter text and quick filter.
+    pub(crate) fn rebuild_filtered_tree(&mut self) {
+        let query = &self.internal.file_filter;
+        if query.is_empty() && self.internal.quick_filter.is_none() {
+            self.internal.filtered_tree = None;
+            return;
+        }
+
+        // Single characters match nearly everything, so don't bother building a tree unless
+        // a quick filter narrows it down
+        if query.len() == 1 && self.internal.quick_filter.is_none() {
+            return;
+        }
+
+        if let Some(overlay_fs) = self.internal.overlay_fs.clone()
+            && let Some(task_queue) = self.internal.task_queue.as_ref()
+        {
+            let _ = task_queue.send(crate::task::BackgroundTask::FilterPaths {
+                known_paths: Arc::clone(&self.internal.known_file_paths),
+                file_path_set: Arc::clone(&self.internal.file_path_set),
+                root: overlay_fs,
+                filter: PathFilter {
+                    query: query.clone(),
+                    extensions: self
+                        .internal
+                        .quick_filter
+                        .map(QuickFilter::extensions)
+                        .unwrap_or_default(),
+                },
+            });
+        }
+    }
+
     pub(crate) fn show_file_tree(&mut self, ctx: &egui::Context) {
         // static FILE_TREE_WIDTH_KEY: &str = "file_tree_desired_width";
         // static FILE_TREE_FIRST_LOAD_KEY: &str = "file_tree_first_load";
@@ -30,34 +133,44 @@ impl EnfusionToolsApp {
         //     }
         // }
 
+        let mut providers_for = None;
+        let mut search_in = None;
         left_panel.show(ctx, |ui| {
             ui.vertical(|ui| {
+                let mut quick_filter_changed = false;
+                ui.horizontal_wrapped(|ui| {
+                    for quick_filter in QuickFilter::ALL {
+                        let selected = self.internal.quick_filter == Some(quick_filter);
+                        if ui.selectable_label(selected, quick_filter.label()).clicked() {
+                            self.internal.quick_filter =
+                                if selected { None } else { Some(quick_filter) };
+                            quick_filter_changed = true;
+                        }
+                    }
+                });
+
                 let response =
                     TextEdit::singleline(&mut self.internal.file_filter).hint_text("Filter").ui(ui);
 
-                if response.lost_focus()
-                    && response.ctx.input(|input| input.key_pressed(egui::Key::Enter))
+                if quick_filter_changed
+                    || (response.lost_focus()
+                        && response.ctx.input(|input| input.key_pressed(egui::Key::Enter)))
                 {
-                    if self.internal.file_filter.is_empty() {
-                        self.internal.filtered_tree = None;
-                    } else if self.internal.file_filter.len() >= 2
-                        && let Some(overlay_fs) = self.internal.overlay_fs.clone()
-                        && let Some(task_queue) = self.internal.task_queue.as_ref()
-                    {
-                        let _ = task_queue.send(crate::task::BackgroundTask::FilterPaths {
-                            known_paths: Arc::clone(&self.internal.known_file_paths),
-                            file_path_set: Arc::clone(&self.internal.file_path_set),
-                            root: overlay_fs,
-                            query: self.internal.file_filter.clone(),
-                        });
-                    }
+                    self.apply_file_filter();
                 }
                 if self.internal.overlay_fs.is_some() {
                     // let mut open_state_changed = false;
                     ScrollArea::both().show(ui, |ui| {
                         let tree =
                             self.internal.filtered_tree.as_ref().unwrap_or(&self.internal.tree);
+                        let text_color = ui.visuals().text_color();
+                        let weak_text_color = ui.visuals().weak_text_color();
 
+                        let layers = &self.internal.layers;
+                        let providers_requested = Cell::new(None);
+                        let new_tab_requested = Cell::new(None);
+                        // A folder, and the archive to search it in if not the overlay
+                        let search_requested = Cell::new(None);
                         let (_response, actions) =
                             TreeView::new(ui.make_persistent_id("main_fs_tree_view"))
                                 .allow_multi_selection(false)
@@ -75,7 +188,19 @@ impl EnfusionToolsApp {
                                                 let is_open = builder.node(
                                                     NodeBuilder::dir(node.id)
                                                         .default_open(node.id == 0)
-                                                        .label(&node.title),
+                                                        .label(folder_label(
+                                                            node,
+                                                            text_color,
+                                                            weak_text_color,
+                                                        ))
+                                                        .context_menu(|ui| {
+                                                            folder_context_menu(
+                                                                ui,
+                                                                node,
+                                                                layers,
+                                                                &search_requested,
+                                                            )
+                                                        }),
                                                 );
 
                                                 if !is_open {
@@ -84,7 +209,26 @@ impl EnfusionToolsApp {
 
                                                 self.internal.open_nodes.push(is_open);
                                             } else {
-                                                builder.leaf(node.id, &node.title);
+                                                builder.node(
+                                                    NodeBuilder::leaf(node.id)
+                                                        .label(file_label(node))
+                                                        .context_menu(|ui| {
+                                                            if ui
+                                                                .button("Open in New Tab")
+                                                                .clicked()
+                                                            {
+                                                                new_tab_requested
+                                                                    .set(Some(node.id));
+                                                                ui.close();
+                                                            }
+                                                            if ui.button("Show Providers").clicked()
+                                                            {
+                                                                providers_requested
+                                                                    .set(Some(node.id));
+                                                                ui.close();
+                                                            }
+                                                        }),
+                                                );
                                             }
                                         } else if node.is_dir {
                                             self.internal.open_nodes.push(false);
@@ -104,6 +248,16 @@ impl EnfusionToolsApp {
                                     }
                                 });
 
+                        if let Some(node_id) = providers_requested.get() {
+                            providers_for = Some(tree[node_id].vfs_path.clone());
+                        }
+                        if let Some(node_id) = new_tab_requested.get() {
+                            self.load_file(tree[node_id].vfs_path.clone(), FileLoad::OpenNewTab);
+                        }
+                        if let Some((node_id, layer)) = search_requested.get() {
+                            search_in = Some((tree[node_id].vfs_path.as_str().to_string(), layer));
+                        }
+
                         for action in actions {
                             match action {
                                 egui_ltreeview::Action::SetSelected(_items) => {
@@ -135,5 +289,66 @@ impl EnfusionToolsApp {
                 }
             })
         });
+
+        if let Some(path) = providers_for {
+            self.show_providers(&path);
+        }
+        if let Some((path, layer)) = search_in {
+            self.search_in_folder(&path, layer);
+        }
+    }
+}
+
+/// Actions for a folder in the tree. Searches are recorded in `search_requested` as the
+/// folder's node and, to search only one archive's copy of it, the archive's layer index.
+fn folder_context_menu(
+    ui: &mut egui::Ui,
+    node: &TreeNode,
+    layers: &[ArchiveLayer],
+    search_requested: &Cell<Option<(usize, Option<usize>)>>,
+) {
+    if ui
+        .button("Search in This Folder")
+        .on_hover_text("Search this folder's files for the search box's query")
+        .clicked()
+    {
+        search_requested.set(Some((node.id, None)));
+        ui.close();
     }
+    ui.menu_button("Search in Archive", |ui| {
+        let path = node.vfs_path.as_str();
+        for (index, layer) in layers.iter().enumerate().filter(|(_, layer)| layer.has_dir(path)) {
+            if ui.button(&layer.name).clicked() {
+                search_requested.set(Some((node.id, Some(index))));
+                ui.close();
+            }
+        }
+    });
+}
+
+/// A file's name prefixed with an icon for its type.
+fn file_label(node: &TreeNode) -> String {
+    let icon = entry_type_icon(node.entry_type.unwrap_or(EntryType::Unknown));
+    format!("{icon} {}", node.title)
+}
+
+/// A folder's name followed by its file count and total size in subdued text.
+fn folder_label(node: &TreeNode, text_color: Color32, weak_text_color: Color32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    job.append(&node.title, 0.0, TextFormat::simple(FontId::default(), text_color));
+
+    if node.descendant_files > 0 {
+        let files = if node.descendant_files == 1 { "file" } else { "files" };
+        job.append(
+            &format!(
+                "{} {files}, {}",
+                node.descendant_files,
+                humansize::format_size(node.total_size, humansize::BINARY)
+            ),
+            6.0,
+            TextFormat::simple(FontId::proportional(11.0), weak_text_color),
+        );
+    }
+
+    job
 }
@@ -0,0 +1,133 @@
+//! How a user has arranged a tab's view (filter, scroll position, expanded groups), kept
+//! across restarts and across moving the tab between the dock and its own window.
+
+use std::collections::BTreeMap;
+
+use egui::Id;
+use egui::Ui;
+use egui::collapsing_header::CollapsingState;
+use egui::scroll_area::ScrollAreaOutput;
+
+/// Only the most recently changed views are saved.
+const MAX_SAVED_VIEWS: usize = 64;
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TabViewState {
+    pub path_filter: String,
+    /// Vertical scroll position, in points.
+    pub scroll_offset: f32,
+    /// Whether each group the user has seen is open, by the group's path.
+    pub open_groups: BTreeMap<String, bool>,
+    /// The UI the tab's scroll area was last shown in.
+    #[serde(skip)]
+    shown_in: Option<Id>,
+}
+
+/// Where the view is shown doesn't count.
+impl PartialEq for TabViewState {
+    fn eq(&self, other: &Self) -> bool {
+        self.path_filter == other.path_filter
+            && self.scroll_offset == other.scroll_offset
+            && self.open_groups == other.open_groups
+    }
+}
+
+impl TabViewState {
+    /// Shows `contents` in a vertical scroll area which returns to the saved position
+    /// wherever the tab is shown for the first time.
+    pub fn scroll_area<R>(
+        &mut self,
+        ui: &mut Ui,
+        key: &str,
+        contents: impl FnOnce(&mut Ui, &mut Self) -> R,
+    ) -> R {
+        let mut area = egui::ScrollArea::vertical().id_salt(key).auto_shrink(false);
+        // egui only remembers scroll positions per place in the UI, which changes when a
+        // tab moves and is forgotten after a restart
+        if self.shown_in.replace(ui.id()) != Some(ui.id()) {
+            area = area.vertical_scroll_offset(self.scroll_offset);
+        }
+
+        let ScrollAreaOutput { inner, state, .. } = area.show(ui, |ui| contents(ui, self));
+        self.scroll_offset = state.offset.y;
+        inner
+    }
+
+    /// A collapsible group for `path`, using `default_open` until the user toggles it.
+    ///
+    /// The group's ID only depends on the tab and path, so it stays open or closed when the
+    /// tab moves.
+    pub fn group(&mut self, ui: &Ui, key: &str, path: &str, default_open: bool) -> CollapsingState {
+        let default_open = self.open_groups.get(path).copied().unwrap_or(default_open);
+        let state =
+            CollapsingState::load_with_default_open(ui.ctx(), Id::new((key, path)), default_open);
+        if state.is_open() != default_open || self.open_groups.contains_key(path) {
+            self.open_groups.insert(path.to_string(), state.is_open());
+        }
+        state
+    }
+}
+
+/// View states of recently used tabs, keyed by [`crate::ui::tab::TabKind::view_key`].
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SavedViews {
+    /// Least recently changed first.
+    views: Vec<(String, TabViewState)>,
+}
+
+impl SavedViews {
+    pub fn get(&self, key: &str) -> Option<&TabViewState> {
+        self.views.iter().find(|(saved, _)| saved == key).map(|(_, view)| view)
+    }
+
+    /// Saves `view` for `key` if it changed.
+    pub fn remember(&mut self, key: &str, view: &TabViewState) {
+        let existing = self.views.iter().position(|(saved, _)| saved == key);
+        if let Some(idx) = existing {
+            if self.views[idx].1 == *view {
+                return;
+            }
+            self.views.remove(idx);
+        } else if self.views.len() == MAX_SAVED_VIEWS {
+            self.views.remove(0);
+        }
+        self.views.push((key.to_string(), TabViewState { shown_in: None, ..view.clone() }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filtered(filter: &str) -> TabViewState {
+        TabViewState { path_filter: filter.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn keeps_the_latest_view_per_key() {
+        let mut saved = SavedViews::default();
+        saved.remember("diff/1", &filtered("a"));
+        saved.remember("diff/1", &filtered("b"));
+        saved.remember("search/x", &filtered("c"));
+
+        assert_eq!(saved.get("diff/1"), Some(&filtered("b")));
+        assert_eq!(saved.get("search/x"), Some(&filtered("c")));
+        assert_eq!(saved.get("diff/2"), None);
+    }
+
+    #[test]
+    fn forgets_least_recently_changed_views() {
+        let mut saved = SavedViews::default();
+        for idx in 0..MAX_SAVED_VIEWS {
+            saved.remember(&idx.to_string(), &filtered(""));
+        }
+        saved.remember("0", &filtered("changed"));
+        saved.remember("new", &filtered(""));
+
+        assert!(saved.get("0").is_some());
+        assert!(saved.get("1").is_none());
+        assert!(saved.get("new").is_some());
+    }
+}
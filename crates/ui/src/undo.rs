@@ -0,0 +1,134 @@
+//! Undoing and redoing UI actions which change what the workspace shows.
+//!
+//! Actions are recorded as [`Command`]s after they happen. The app reverts or reapplies
+//! them itself, so each command holds whatever state it needs to go both ways.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use crate::ui::tab::TabKind;
+use crate::ui::tree::QuickFilter;
+
+/// How many actions can be undone before the oldest are forgotten.
+const MAX_HISTORY: usize = 100;
+
+#[derive(Clone)]
+pub(crate) enum Command {
+    /// A tab was closed. Undoing reopens it in the main dock area.
+    CloseTab(TabKind),
+    /// A different file tree filter was applied.
+    Filter { before: FileFilter, after: FileFilter },
+    /// Workshop mods were selected or deselected, changing what loads with the game data.
+    #[cfg(not(target_arch = "wasm32"))]
+    SelectMods { before: Vec<PathBuf>, after: Vec<PathBuf> },
+}
+
+impl Command {
+    /// Short description for the Edit menu.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Command::CloseTab(tab) => format!("Close \"{}\"", tab.title()),
+            Command::Filter { .. } => "Filter Files".to_string(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Command::SelectMods { .. } => "Mod Selection".to_string(),
+        }
+    }
+}
+
+/// The file tree's filter text and quick filter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FileFilter {
+    pub(crate) query: String,
+    pub(crate) quick_filter: Option<QuickFilter>,
+}
+
+/// Actions which can be undone, and undone actions which can be redone.
+pub(crate) struct UndoStack<C> {
+    done: Vec<C>,
+    undone: Vec<C>,
+}
+
+impl<C> Default for UndoStack<C> {
+    fn default() -> Self {
+        UndoStack { done: Vec::new(), undone: Vec::new() }
+    }
+}
+
+impl<C> UndoStack<C> {
+    /// Records an action which just happened. Anything undone before it can no longer be
+    /// redone.
+    pub(crate) fn record(&mut self, command: C) {
+        self.undone.clear();
+        if self.done.len() == MAX_HISTORY {
+            self.done.remove(0);
+        }
+        self.done.push(command);
+    }
+
+    /// The most recent action, which the caller must now revert.
+    pub(crate) fn undo(&mut self) -> Option<&C> {
+        let command = self.done.pop()?;
+        self.undone.push(command);
+        self.undone.last()
+    }
+
+    /// The most recently undone action, which the caller must now apply again.
+    pub(crate) fn redo(&mut self) -> Option<&C> {
+        let command = self.undone.pop()?;
+        self.done.push(command);
+        self.done.last()
+    }
+
+    pub(crate) fn next_undo(&self) -> Option<&C> {
+        self.done.last()
+    }
+
+    pub(crate) fn next_redo(&self) -> Option<&C> {
+        self.undone.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut stack = UndoStack::default();
+        stack.record(1);
+        stack.record(2);
+
+        assert_eq!(stack.undo(), Some(&2));
+        assert_eq!(stack.undo(), Some(&1));
+        assert_eq!(stack.undo(), None);
+        assert_eq!(stack.redo(), Some(&1));
+        assert_eq!(stack.next_undo(), Some(&1));
+        assert_eq!(stack.next_redo(), Some(&2));
+    }
+
+    #[test]
+    fn recording_discards_redo_history() {
+        let mut stack = UndoStack::default();
+        stack.record(1);
+        stack.undo();
+        stack.record(2);
+
+        assert_eq!(stack.redo(), None);
+        assert_eq!(stack.undo(), Some(&2));
+    }
+
+    #[test]
+    fn forgets_the_oldest_actions() {
+        let mut stack = UndoStack::default();
+        for action in 0..MAX_HISTORY + 5 {
+            stack.record(action);
+        }
+
+        let mut undone = 0;
+        while let Some(action) = stack.undo() {
+            assert!(*action >= 5);
+            undone += 1;
+        }
+        assert_eq!(undone, MAX_HISTORY);
+    }
+}
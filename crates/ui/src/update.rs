@@ -0,0 +1,264 @@
+//! Opt-in check for newer releases of the native app.
+//!
+//! Only the GitHub releases API is contacted, and only when the user has turned checking
+//! on or asks for a check. A newer release's binary for this platform can be downloaded
+//! next to the running one, to be swapped in by the user.
+
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use sha2::Digest;
+use sha2::Sha256;
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/landaire/enfusion_tools/releases/latest";
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    #[error("request failed: {0}")]
+    Request(#[from] ureq::Error),
+    #[error("unexpected response from GitHub: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+    #[error("couldn't save the download: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the download's SHA-256 is {actual}, not {expected}")]
+    HashMismatch { expected: String, actual: String },
+    #[error("the download is {actual} bytes, not {expected}")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error("the release doesn't list a SHA-256 for the download, so it can't be checked")]
+    Unverified,
+}
+
+/// A version number like `0.2.1`, compared part by part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(u64, u64, u64);
+
+impl Version {
+    /// The version of the running app.
+    pub fn current() -> Self {
+        Version::parse(env!("CARGO_PKG_VERSION")).expect("package version is major.minor.patch")
+    }
+
+    /// Parses a release tag such as `v1.2.3` or `1.2`. Tags which aren't versions, such as
+    /// builds of a branch, give `None`.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.trim().trim_start_matches(['v', 'V']);
+        // Pre-release and build suffixes aren't compared
+        let core = tag.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+        let major = parts.next()??;
+        let minor = parts.next().unwrap_or(Some(0))?;
+        let patch = parts.next().unwrap_or(Some(0))?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Version(major, minor, patch))
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// A release newer than the running app.
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: Version,
+    /// The release's page, with its notes.
+    pub page_url: String,
+    /// The app's binary for this platform, if the release has one.
+    pub binary: Option<Asset>,
+}
+
+/// Where checking for and downloading an update is up to.
+#[derive(Debug, Default)]
+pub enum UpdateStatus {
+    #[default]
+    NotChecked,
+    Checking,
+    UpToDate,
+    Available(Release),
+    Downloading(Release),
+    /// The release's binary was saved to this path.
+    Staged(Release, PathBuf),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+    pub size: u64,
+    /// `sha256:<hex>`, for assets uploaded since GitHub started recording digests.
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+impl Asset {
+    /// The hex SHA-256 GitHub recorded for this asset, if any.
+    pub fn sha256(&self) -> Option<&str> {
+        self.digest.as_deref()?.strip_prefix("sha256:")
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+/// Name of the release asset built for this platform, as the release workflow uploads it.
+fn binary_name() -> Option<String> {
+    let target = match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("macos", "aarch64") => "aarch64-apple-darwin",
+        ("linux", "x86_64") => "x86_64-unknown-linux-gnu",
+        ("windows", "x86_64") => "x86_64-pc-windows-msvc",
+        _ => return None,
+    };
+    Some(format!("enfusion_tools_ui-{target}{}", std::env::consts::EXE_SUFFIX))
+}
+
+/// Asks GitHub for the latest release, returning it if it's newer than the running app.
+/// Blocks until GitHub answers.
+pub fn check() -> Result<Option<Release>, UpdateError> {
+    let body = ureq::get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", concat!("enfusion_tools/", env!("CARGO_PKG_VERSION")))
+        .call()?
+        .body_mut()
+        .read_to_string()?;
+    let release: GithubRelease = serde_json::from_str(&body)?;
+    Ok(newer_release(release, Version::current()))
+}
+
+fn newer_release(release: GithubRelease, current: Version) -> Option<Release> {
+    let version = Version::parse(&release.tag_name).filter(|version| *version > current)?;
+    let binary_name = binary_name();
+    let binary = release.assets.into_iter().find(|asset| Some(&asset.name) == binary_name.as_ref());
+    Some(Release { version, page_url: release.html_url, binary })
+}
+
+/// Downloads `asset` next to the running app, under a name with the release's version,
+/// and returns where it was saved. The running app is left alone: it can't be replaced
+/// while it runs on every platform, so the user swaps in the new binary after closing it.
+///
+/// Assets without a SHA-256 to check the download against aren't downloaded.
+pub fn stage(release: &Release, asset: &Asset) -> Result<PathBuf, UpdateError> {
+    let expected_hash = asset.sha256().ok_or(UpdateError::Unverified)?;
+    let exe = std::env::current_exe()?;
+    let staged_name = format!(
+        "{}-{}{}",
+        exe.file_stem().and_then(|stem| stem.to_str()).unwrap_or("enfusion_tools_ui"),
+        release.version,
+        std::env::consts::EXE_SUFFIX
+    );
+    let dest = exe.with_file_name(&staged_name);
+    // Downloaded under a temporary name so a failed download never looks finished
+    let partial = exe.with_file_name(format!("{staged_name}.partial"));
+
+    let staged = download(asset, expected_hash, &partial).and_then(|()| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            std::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755))?;
+        }
+        std::fs::rename(&partial, &dest)?;
+        Ok(())
+    });
+    if let Err(e) = staged {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+
+    Ok(dest)
+}
+
+/// Downloads `asset` to `path`, checking its size and that its SHA-256 is `expected_hash`.
+fn download(asset: &Asset, expected_hash: &str, path: &Path) -> Result<(), UpdateError> {
+    let mut response = ureq::get(&asset.browser_download_url)
+        .header("User-Agent", concat!("enfusion_tools/", env!("CARGO_PKG_VERSION")))
+        .call()?;
+    let mut body = response.body_mut().as_reader();
+    let mut file = std::fs::File::create(path)?;
+    let mut hasher = Sha256::new();
+    let mut len = 0;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = body.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        file.write_all(&buf[..read])?;
+        len += read as u64;
+    }
+    drop(file);
+
+    if len != asset.size {
+        return Err(UpdateError::SizeMismatch { expected: asset.size, actual: len });
+    }
+    let actual: String = hasher.finalize().iter().map(|b| format!("{b:02x}")).collect();
+    if !actual.eq_ignore_ascii_case(expected_hash) {
+        return Err(UpdateError::HashMismatch { expected: expected_hash.to_string(), actual });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_release_tags() {
+        assert_eq!(Version::parse("v1.2.3"), Some(Version(1, 2, 3)));
+        assert_eq!(Version::parse("0.4"), Some(Version(0, 4, 0)));
+        assert_eq!(Version::parse("v2.0.0-rc.1"), Some(Version(2, 0, 0)));
+        assert_eq!(Version::parse("main"), None);
+        assert_eq!(Version::parse("1.2.3.4"), None);
+        assert!(Version(0, 10, 0) > Version(0, 9, 9));
+    }
+
+    #[test]
+    fn only_newer_releases_are_offered() {
+        let release = |tag: &str| GithubRelease {
+            tag_name: tag.to_string(),
+            html_url: format!("https://github.com/landaire/enfusion_tools/releases/tag/{tag}"),
+            assets: Vec::new(),
+        };
+        let current = Version(0, 2, 0);
+        assert!(newer_release(release("v0.2.0"), current).is_none());
+        assert!(newer_release(release("v0.1.9"), current).is_none());
+        assert!(newer_release(release("main"), current).is_none());
+
+        let newer = newer_release(release("v0.3.0"), current).expect("v0.3.0 is newer");
+        assert_eq!(newer.version, Version(0, 3, 0));
+        assert!(newer.binary.is_none());
+    }
+
+    #[test]
+    fn downloads_need_a_hash() {
+        let mut asset = Asset {
+            name: "enfusion_tools_ui".to_string(),
+            browser_download_url: "https://example.invalid/enfusion_tools_ui".to_string(),
+            size: 1,
+            digest: Some("sha256:AB12".to_string()),
+        };
+        assert_eq!(asset.sha256(), Some("AB12"));
+
+        asset.digest = Some("md5:ab12".to_string());
+        assert_eq!(asset.sha256(), None);
+        let release = Release {
+            version: Version(9, 0, 0),
+            page_url: String::new(),
+            binary: Some(asset.clone()),
+        };
+        assert!(matches!(stage(&release, &asset), Err(UpdateError::Unverified)));
+    }
+}
@@ -4,6 +4,15 @@ pub(crate) trait VfsExt {
     fn filename_ref(&self) -> &str;
 }
 
+/// `path` with `/` separators, without empty or `.` components, and without a leading
+/// `/`, so that every spelling of a path inside the overlay compares equal.
+pub(crate) fn normalize_path(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 impl VfsExt for VfsPath {
     fn filename_ref(&self) -> &str {
         let path = self.as_str();
@@ -11,3 +20,16 @@ impl VfsExt for VfsPath {
         &path[index..]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_path_spellings() {
+        for path in ["/scripts/Game/foo.c", "scripts//Game/./foo.c", "\\scripts\\Game\\foo.c"] {
+            assert_eq!(normalize_path(path), "scripts/Game/foo.c");
+        }
+        assert_eq!(normalize_path("/"), "");
+    }
+}